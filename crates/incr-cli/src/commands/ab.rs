@@ -0,0 +1,274 @@
+//! A/B command - validate new models or settings against earlier results.
+//!
+//! Re-extracts a corpus with a candidate configuration (and optionally
+//! another model directory) and compares every document with the JSON
+//! output of an earlier `batch` run, reporting per-field agreement and the
+//! values the candidate lost or changed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Args;
+use console::style;
+use tracing::warn;
+
+use incr_core::invoice::compare::FieldAgreement;
+use incr_core::invoice::{Comparison, FieldKind, HybridInvoiceParser, InvoiceParser};
+use incr_core::models::capabilities::Capabilities;
+use incr_core::models::config::{IncrConfig, Preset};
+use incr_core::models::invoice::{Invoice, SourceType};
+use incr_core::pdf::{PdfExtractor, PdfProcessor};
+
+use super::engines::shared_engine;
+use super::variant::{get_variant_dir, resolve_variant};
+use super::{file_date, load_config, merge_capabilities, parser_from_config};
+
+/// Regressions listed in the text report.
+const MAX_LISTED: usize = 20;
+
+/// Arguments for the ab command.
+#[derive(Args)]
+pub struct AbArgs {
+    /// Directory with the documents to extract
+    #[arg(required = true)]
+    corpus: PathBuf,
+
+    /// Directory with the baseline results (<name>.json from `batch --format json`)
+    #[arg(long)]
+    baseline: PathBuf,
+
+    /// Config file with the candidate settings (default: the global --config)
+    #[arg(long)]
+    candidate_config: Option<PathBuf>,
+
+    /// Candidate model directory (default: directory of the configured variant)
+    #[arg(long)]
+    model_dir: Option<PathBuf>,
+
+    /// Exit with an error if any field regressed
+    #[arg(long)]
+    fail_on_regression: bool,
+
+    /// Output the comparison as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn run(
+    args: AbArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let candidate_config = args
+        .candidate_config
+        .as_deref()
+        .map(|p| p.to_string_lossy().into_owned());
+    let config = load_config(
+        candidate_config.as_deref().or(config_path),
+        profile,
+        preset,
+        "ab",
+    )?;
+
+    let documents = corpus_files(&args.corpus)?;
+    if documents.is_empty() {
+        anyhow::bail!("No documents found in {}", args.corpus.display());
+    }
+
+    let parser = parser_from_config(&config)?;
+
+    let model_dir = args
+        .model_dir
+        .clone()
+        .unwrap_or_else(|| get_variant_dir(resolve_variant(&config)));
+
+    let mut comparison = Comparison::new();
+    let mut missing = 0;
+
+    for path in &documents {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let Some(baseline) = load_baseline(&args.baseline, path)? else {
+            warn!("No baseline result for {}", path.display());
+            missing += 1;
+            continue;
+        };
+
+        let parser = parser.clone().with_reference_date(file_date(path));
+        match extract(path, &parser, &model_dir, &config) {
+            Ok(candidate) => comparison.add(name, &baseline, &candidate),
+            Err(e) => {
+                warn!("Failed to process {}: {}", path.display(), e);
+                comparison.add_failure(name, &baseline);
+            }
+        }
+    }
+
+    if comparison.documents == 0 {
+        anyhow::bail!(
+            "No baseline results found in {} for the documents in {}",
+            args.baseline.display(),
+            args.corpus.display()
+        );
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+    } else {
+        print_report(&comparison, missing);
+    }
+
+    if args.fail_on_regression && comparison.regressed() > 0 {
+        anyhow::bail!("{} field values regressed", comparison.regressed());
+    }
+
+    Ok(())
+}
+
+/// Documents in the corpus directory, sorted by name.
+fn corpus_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("Cannot read {}", dir.display()))? {
+        let path = entry?.path();
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        if matches!(ext.as_str(), "pdf" | "png" | "jpg" | "jpeg" | "tiff") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// The baseline result for a document, `None` if there is none.
+fn load_baseline(dir: &Path, document: &Path) -> anyhow::Result<Option<Invoice>> {
+    let stem = document.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let path = dir.join(format!("{}.json", stem));
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let data = fs::read(&path)?;
+    let invoice = serde_json::from_slice(&data).with_context(|| {
+        format!(
+            "{} is not an invoice in snake_case JSON (output.field_naming)",
+            path.display()
+        )
+    })?;
+    Ok(Some(invoice))
+}
+
+/// Extract a document as `batch` does: PDFs from their text layer, images
+/// with OCR. The engine is loaded for the first image.
+fn extract(
+    path: &Path,
+    parser: &HybridInvoiceParser,
+    model_dir: &Path,
+    config: &IncrConfig,
+) -> anyhow::Result<Invoice> {
+    let is_pdf = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+
+    if is_pdf {
+        let mut extractor = PdfExtractor::new();
+        extractor.load(&fs::read(path)?)?;
+
+        let text = extractor.extract_text()?;
+        if text.trim().is_empty() {
+            anyhow::bail!("No text extracted from PDF");
+        }
+
+        let mut invoice = parser.parse(&text)?.invoice;
+        invoice.metadata.capabilities = Capabilities::text_layer();
+        return Ok(invoice);
+    }
+
+    let engine = shared_engine(model_dir, config)?;
+    let image = image::open(path)?;
+    let ocr = engine
+        .process(&image)
+        .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+    if ocr.text.trim().is_empty() {
+        anyhow::bail!("No text detected in image");
+    }
+
+    let mut invoice = parser.parse(&ocr.text)?.invoice;
+    invoice.metadata.source_type = SourceType::Image;
+    invoice.metadata.capabilities = merge_capabilities([&ocr.capabilities]);
+    Ok(invoice)
+}
+
+fn print_report(comparison: &Comparison, missing: usize) {
+    println!(
+        "{} Compared {} documents ({} failed, {} without baseline)",
+        style("ℹ").blue(),
+        comparison.documents,
+        comparison.failed,
+        missing
+    );
+    println!();
+    println!(
+        "{:<22} {:>7} {:>7} {:>7} {:>7} {:>9}",
+        "Field", "Agreed", "Changed", "Lost", "Gained", "Agreement"
+    );
+    println!("{}", "-".repeat(64));
+
+    for field in FieldKind::ALL {
+        if let Some(agreement) = comparison.fields.get(field.path()) {
+            print_row(field.path(), agreement);
+        }
+    }
+
+    println!();
+    if comparison.regressions.is_empty() {
+        println!("{} No regressions", style("✓").green());
+        return;
+    }
+
+    println!(
+        "{} {} regressions",
+        style("✗").red(),
+        comparison.regressions.len()
+    );
+    for regression in comparison.regressions.iter().take(MAX_LISTED) {
+        let candidate = regression
+            .candidate
+            .as_ref()
+            .map_or_else(|| "missing".to_string(), |v| v.to_string());
+        println!(
+            "  {} {}: {} -> {}",
+            regression.document, regression.field, regression.baseline, candidate
+        );
+    }
+    if comparison.regressions.len() > MAX_LISTED {
+        println!(
+            "  ... {} more (use --json for the full list)",
+            comparison.regressions.len() - MAX_LISTED
+        );
+    }
+}
+
+fn print_row(field: &str, agreement: &FieldAgreement) {
+    let percent = agreement
+        .agreement()
+        .map_or_else(|| "-".to_string(), |p| format!("{:.1}%", p));
+    let regressed = agreement.changed + agreement.lost;
+    let percent = if regressed > 0 {
+        style(percent).red()
+    } else {
+        style(percent).green()
+    };
+
+    println!(
+        "{:<22} {:>7} {:>7} {:>7} {:>7} {:>9}",
+        field, agreement.agreed, agreement.changed, agreement.lost, agreement.gained, percent
+    );
+}
@@ -0,0 +1,122 @@
+//! Audit logging shared by the commands that extract or correct invoices.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use tracing::debug;
+
+use incr_core::audit::{model_fingerprints, AuditLog, AuditRecord, DocumentFingerprint};
+use incr_core::models::config::IncrConfig;
+use incr_core::models::invoice::Invoice;
+#[cfg(feature = "full")]
+use incr_core::training::Correction;
+
+use super::variant::resolve_variant;
+
+/// Hashes of the model files in `model_dir`, plus the configured variant.
+pub fn model_versions(config: &IncrConfig, model_dir: &Path) -> BTreeMap<String, String> {
+    let models = &config.models;
+    let (recognition_model, dictionary) = models.recognition_files();
+    let mut files = vec![
+        models.detection_model.as_str(),
+        models.classification_model.as_str(),
+        recognition_model.as_str(),
+        dictionary.as_str(),
+    ];
+    if let Some(classifier) = &models.classifier_model {
+        files.push(classifier);
+    }
+
+    let mut fingerprints = model_fingerprints(model_dir, &files);
+    fingerprints.insert("variant".to_string(), resolve_variant(config).to_string());
+    fingerprints
+}
+
+/// Writes audit records when `audit.path` is configured.
+pub struct Auditor {
+    log: AuditLog,
+    config: IncrConfig,
+    models: BTreeMap<String, String>,
+    actor: Option<String>,
+}
+
+impl Auditor {
+    /// Open the configured audit log, or `None` if auditing is disabled.
+    ///
+    /// Model files in `model_dir` are hashed once here so every record
+    /// identifies the exact models in use.
+    pub fn open(config: &IncrConfig, model_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let Some(path) = &config.audit.path else {
+            return Ok(None);
+        };
+
+        let log = AuditLog::open(path)?;
+        debug!("Writing audit records to {}", log.path().display());
+
+        let fingerprints = model_versions(config, model_dir);
+
+        let actor = config
+            .audit
+            .actor
+            .clone()
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok());
+
+        Ok(Some(Self {
+            log,
+            config: config.clone(),
+            models: fingerprints,
+            actor,
+        }))
+    }
+
+    /// Record an extraction from document bytes.
+    pub fn extraction(
+        &self,
+        command: &str,
+        name: Option<String>,
+        data: &[u8],
+        result: Result<&Invoice, String>,
+    ) -> anyhow::Result<()> {
+        let document = DocumentFingerprint::new(name, data);
+        let record = AuditRecord::extraction(command, document, &self.config, result);
+        self.append(record)
+    }
+
+    /// Record an extraction from a file on disk.
+    pub fn file_extraction(
+        &self,
+        command: &str,
+        path: &Path,
+        result: Result<&Invoice, String>,
+    ) -> anyhow::Result<()> {
+        let data = fs::read(path)?;
+        self.extraction(command, Some(path.display().to_string()), &data, result)
+    }
+
+    /// Record corrections applied to a file's extracted data.
+    #[cfg(feature = "full")]
+    pub fn correction(
+        &self,
+        command: &str,
+        path: &Path,
+        corrected: &Invoice,
+        corrections: Vec<Correction>,
+    ) -> anyhow::Result<()> {
+        let data = fs::read(path)?;
+        let document = DocumentFingerprint::new(Some(path.display().to_string()), &data);
+        let record =
+            AuditRecord::correction(command, document, &self.config, corrected, corrections);
+        self.append(record)
+    }
+
+    fn append(&self, record: AuditRecord) -> anyhow::Result<()> {
+        let mut record = record.with_models(self.models.clone());
+        if let Some(actor) = &self.actor {
+            record = record.with_actor(actor.clone());
+        }
+        self.log.append(&record)?;
+        Ok(())
+    }
+}
@@ -1,23 +1,38 @@
 //! Batch processing command for multiple invoice files.
 
+#[cfg(feature = "store")]
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use clap::Args;
 use console::style;
 use glob::glob;
-use image::DynamicImage;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use tracing::{debug, error, warn};
 
-use incr_core::models::config::IncrConfig;
+use incr_core::jpk::JpkFa;
+use incr_core::models::capabilities::Capabilities;
+use incr_core::models::config::{IncrConfig, Preset};
 use incr_core::models::invoice::Invoice;
-use incr_core::invoice::{HybridInvoiceParser, InvoiceParser};
+use incr_core::models::naming::FieldNaming;
+use incr_core::invoice::coverage::COVERAGE_FIELDS;
+use incr_core::invoice::barcodes::apply_barcodes;
+use incr_core::invoice::{BatchStats, HybridInvoiceParser, InvoiceParser, StatsSummary, TextConfidence};
 use incr_core::pdf::{PdfExtractor, PdfProcessor};
-use incr_core::{create_engine_from_dir, create_engine_from_embedded};
-
-use super::models::{get_active_variant, get_variant_dir};
+use incr_core::PureOcrEngine;
+
+use super::audit::Auditor;
+use super::{embedded_invoice, file_date, load_config, merge_capabilities, parser_from_config};
+use super::engines::shared_engine;
+#[cfg(feature = "store")]
+use super::store::ResultStore;
+use super::sink::{open_sink, Encoding, FileSink, JsonLinesSink, OutputSink, SinkRecord};
+use super::progress::{BarProgress, MultiProgress, ProgressBar, ProgressStyle};
+use super::variant::{get_variant_dir, resolve_variant};
+use super::work_queue::{LocalSource, Shard, WorkSource};
 
 /// Arguments for the batch command.
 #[derive(Args)]
@@ -34,11 +49,11 @@ pub struct BatchArgs {
     #[arg(short, long, value_enum, default_value = "json")]
     format: super::process::OutputFormat,
 
-    /// Also generate a summary CSV
+    /// Also generate a summary CSV and stats.json
     #[arg(long)]
     summary: bool,
 
-    /// Number of parallel workers
+    /// Number of files processed in parallel (workers share one OCR engine)
     #[arg(short = 'j', long, default_value = "4")]
     jobs: usize,
 
@@ -53,6 +68,49 @@ pub struct BatchArgs {
     /// Keep [UNK] tokens in OCR output instead of replacing with spaces
     #[arg(long)]
     keep_unk: bool,
+
+    /// JSON field names: snake_case, camel_case or legacy (overrides the config)
+    #[arg(long, value_name = "NAMING")]
+    field_naming: Option<FieldNaming>,
+
+    /// CSV output: one row per line item, with the invoice columns repeated
+    #[arg(long)]
+    include_line_items: bool,
+
+    /// Save extracted raw text as <name>.ocr.txt in the output directory (used by `reparse`)
+    #[arg(long)]
+    save_text: bool,
+
+    /// Only process shard K of N (every Nth file starting at K, e.g. 3/8)
+    #[arg(long, value_name = "K/N", conflicts_with = "queue")]
+    shard: Option<Shard>,
+
+    /// Pull files from a shared Redis work queue (e.g. redis://host:6379)
+    #[arg(long, value_name = "URL")]
+    queue: Option<String>,
+
+    /// Name of the work queue; workers using the same name share jobs
+    #[arg(long, default_value = "incr:batch", requires = "queue")]
+    queue_name: String,
+
+    /// Worker identifier used in the summary file name (defaults to the process id)
+    #[arg(long, requires = "queue")]
+    worker_id: Option<String>,
+
+    /// Record results in this database and skip files already extracted
+    /// into it (overrides `store.path` from the config)
+    #[arg(long, value_name = "PATH")]
+    store: Option<PathBuf>,
+
+    /// Process files even if the results store already has them
+    #[arg(long)]
+    reprocess: bool,
+
+    /// Also send each invoice to DEST as soon as its file is done: stdout
+    /// (JSON lines), a webhook URL, s3://bucket/prefix or a directory
+    /// (repeatable; overrides `output.sinks` from the config)
+    #[arg(long = "sink", value_name = "DEST")]
+    sinks: Vec<String>,
 }
 
 /// Result of processing a single file.
@@ -63,22 +121,59 @@ struct ProcessResult {
     processing_time_ms: u64,
 }
 
-pub async fn run(args: BatchArgs, config_path: Option<&str>) -> anyhow::Result<()> {
+pub async fn run(
+    args: BatchArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
     let start = Instant::now();
 
+    if matches!(args.format, super::process::OutputFormat::TableCsv) {
+        anyhow::bail!("--format table-csv is only supported by the process command");
+    }
+
+    if args.save_text && args.output_dir.is_none() {
+        anyhow::bail!("--save-text requires --output-dir");
+    }
+
+    if matches!(args.format, super::process::OutputFormat::Parquet) {
+        if !cfg!(feature = "parquet") {
+            anyhow::bail!("--format parquet requires incr to be built with the 'parquet' feature");
+        }
+        if args.output_dir.is_none() {
+            anyhow::bail!("--format parquet requires --output-dir");
+        }
+    }
+
+    if matches!(args.format, super::process::OutputFormat::Xlsx) {
+        if !cfg!(feature = "xlsx") {
+            anyhow::bail!("--format xlsx requires incr to be built with the 'xlsx' feature");
+        }
+        if args.output_dir.is_none() {
+            anyhow::bail!("--format xlsx requires --output-dir");
+        }
+    }
+
+    if matches!(args.format, super::process::OutputFormat::JpkFa) && args.output_dir.is_none() {
+        anyhow::bail!("--format jpk-fa requires --output-dir");
+    }
+
     // Load configuration
-    let mut config = if let Some(path) = config_path {
-        IncrConfig::from_file(std::path::Path::new(path))?
-    } else {
-        IncrConfig::default()
-    };
+    let mut config = load_config(config_path, profile, preset, "batch")?;
 
     if args.keep_unk {
         config.ocr.keep_unk = true;
     }
+    if let Some(naming) = args.field_naming {
+        config.output.field_naming = naming;
+    }
+    if args.include_line_items {
+        config.output.include_line_items = true;
+    }
 
     // Expand glob pattern
-    let files: Vec<PathBuf> = glob(&args.input)?
+    let mut files: Vec<PathBuf> = glob(&args.input)?
         .filter_map(|r| r.ok())
         .filter(|p| {
             let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
@@ -90,20 +185,87 @@ pub async fn run(args: BatchArgs, config_path: Option<&str>) -> anyhow::Result<(
         anyhow::bail!("No matching files found for pattern: {}", args.input);
     }
 
-    println!(
+    eprintln!(
         "{} Found {} files to process",
         style("ℹ").blue(),
         files.len()
     );
 
-    // Create output directory if specified
-    if let Some(ref output_dir) = args.output_dir {
-        fs::create_dir_all(output_dir)?;
+    // Sort so every shard sees the same ordering
+    files.sort();
+    if let Some(shard) = args.shard {
+        files = shard.select(files);
+        eprintln!(
+            "{} Shard {}: {} files assigned",
+            style("ℹ").blue(),
+            shard,
+            files.len()
+        );
+    }
+
+    #[cfg(feature = "store")]
+    let store = open_store(&args, &config)?;
+    #[cfg(not(feature = "store"))]
+    if let Some(path) = args.store.as_ref().or(config.store.path.as_ref()) {
+        anyhow::bail!(
+            "results store {} requires incr to be built with the 'store' feature",
+            path.display()
+        );
+    }
+    #[cfg(feature = "store")]
+    let mut hashes = HashMap::new();
+    #[cfg(feature = "store")]
+    if let (Some(store), false) = (&store, args.reprocess) {
+        let total = files.len();
+        (files, hashes) = skip_extracted(store, files)?;
+        if files.len() < total {
+            eprintln!(
+                "{} Skipping {} files already in the results store",
+                style("ℹ").blue(),
+                total - files.len()
+            );
+        }
+    }
+
+    let model_dir = args
+        .model_dir
+        .clone()
+        .unwrap_or_else(|| get_variant_dir(resolve_variant(&config)));
+
+    let source = open_work_source(&args, files)?;
+
+    // Per-file outputs go to --output-dir and the configured sinks
+    let encoding = Encoding {
+        format: args.format,
+        naming: config.output.field_naming,
+        line_items: config.output.include_line_items,
+    };
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+    if let Some(output_dir) = &args.output_dir {
+        sinks.push(Box::new(FileSink::new(output_dir.clone(), encoding, args.save_text)?));
+    }
+    let destinations = if args.sinks.is_empty() {
+        &config.output.sinks
+    } else {
+        &args.sinks
+    };
+    for destination in destinations {
+        sinks.push(open_sink(destination, encoding, false)?);
+    }
+    if let super::process::OutputFormat::Ndjson = args.format {
+        sinks.push(Box::new(match &args.output_dir {
+            Some(output_dir) => {
+                let path = output_dir.join(batch_file_name(&args, "invoices", "ndjson"));
+                eprintln!("{} Writing invoices to {}", style("ℹ").blue(), path.display());
+                JsonLinesSink::create(&path, encoding)?
+            }
+            None => JsonLinesSink::stdout(encoding),
+        }));
     }
 
     // Set up progress bars
     let multi_progress = MultiProgress::new();
-    let overall_pb = multi_progress.add(ProgressBar::new(files.len() as u64));
+    let overall_pb = multi_progress.add(ProgressBar::new(source.pending().unwrap_or(0)));
     overall_pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files")
@@ -111,47 +273,43 @@ pub async fn run(args: BatchArgs, config_path: Option<&str>) -> anyhow::Result<(
             .progress_chars("=>-"),
     );
 
-    // Process files (simplified sequential processing for now)
-    let mut results = Vec::with_capacity(files.len());
-    let parser = HybridInvoiceParser::new()
-        .with_nip_validation(config.extraction.validate_nip)
-        .with_regon_validation(config.extraction.validate_regon)
-        .with_iban_validation(config.extraction.validate_iban);
-
-    for path in files {
-        let file_start = Instant::now();
-        let result = process_single_file(&path, &parser, &args, &config);
+    let parser = parser_from_config(&config)?;
+
+    let auditor = Auditor::open(&config, &model_dir)?;
+
+    let workers = Workers {
+        source: Mutex::new(source),
+        parser: &parser,
+        config: &config,
+        model_dir: &model_dir,
+        auditor: auditor.as_ref(),
+        sinks: &sinks,
+        #[cfg(feature = "store")]
+        store: store.as_ref(),
+        #[cfg(feature = "store")]
+        hashes,
+        progress: &multi_progress,
+        overall: &overall_pb,
+        continue_on_error: args.continue_on_error,
+        stop: AtomicBool::new(false),
+        results: Mutex::new(Vec::new()),
+    };
 
-        let processing_time_ms = file_start.elapsed().as_millis() as u64;
+    debug!("Processing with {} workers", args.jobs.max(1));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..args.jobs.max(1))
+            .map(|_| scope.spawn(|| workers.run()))
+            .collect();
 
-        match result {
-            Ok(invoice) => {
-                results.push(ProcessResult {
-                    path: path.clone(),
-                    invoice: Some(invoice),
-                    error: None,
-                    processing_time_ms,
-                });
-            }
-            Err(e) => {
-                let error_msg = e.to_string();
-                if args.continue_on_error {
-                    warn!("Failed to process {}: {}", path.display(), error_msg);
-                    results.push(ProcessResult {
-                        path: path.clone(),
-                        invoice: None,
-                        error: Some(error_msg),
-                        processing_time_ms,
-                    });
-                } else {
-                    error!("Failed to process {}: {}", path.display(), error_msg);
-                    anyhow::bail!("Processing failed: {}", error_msg);
-                }
-            }
-        }
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("batch worker panicked"))
+            .collect::<anyhow::Result<Vec<()>>>()
+    })?;
 
-        overall_pb.inc(1);
-    }
+    // Workers finish in any order; report files in path order
+    let mut results = workers.results.into_inner().expect("results lock poisoned");
+    results.sort_by(|a, b| a.path.cmp(&b.path));
 
     overall_pb.finish_with_message("Complete");
 
@@ -159,66 +317,109 @@ pub async fn run(args: BatchArgs, config_path: Option<&str>) -> anyhow::Result<(
     let successful: Vec<_> = results.iter().filter(|r| r.invoice.is_some()).collect();
     let failed: Vec<_> = results.iter().filter(|r| r.error.is_some()).collect();
 
-    for result in &successful {
-        if let (Some(invoice), Some(output_dir)) = (&result.invoice, &args.output_dir) {
-            let output_name = result.path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("invoice");
-
-            let extension = match args.format {
-                super::process::OutputFormat::Json => "json",
-                super::process::OutputFormat::Csv => "csv",
-                super::process::OutputFormat::Text => "txt",
-            };
+    #[cfg(feature = "parquet")]
+    if let (super::process::OutputFormat::Parquet, Some(output_dir)) = (args.format, &args.output_dir) {
+        let invoices: Vec<_> = successful
+            .iter()
+            .filter_map(|r| r.invoice.as_ref().map(|invoice| (r.path.as_path(), invoice)))
+            .collect();
+        let invoices_path = output_dir.join(batch_file_name(&args, "invoices", "parquet"));
+        let items_path = output_dir.join(batch_file_name(&args, "line_items", "parquet"));
+
+        super::parquet::write(&invoices, &invoices_path, &items_path)?;
+        eprintln!(
+            "{} Parquet tables written to {} and {}",
+            style("✓").green(),
+            invoices_path.display(),
+            items_path.display()
+        );
+    }
 
-            let output_path = output_dir.join(format!("{}.{}", output_name, extension));
+    #[cfg(feature = "xlsx")]
+    if let (super::process::OutputFormat::Xlsx, Some(output_dir)) = (args.format, &args.output_dir) {
+        let invoices: Vec<_> = successful
+            .iter()
+            .filter_map(|r| r.invoice.as_ref().map(|invoice| (r.path.as_path(), invoice)))
+            .collect();
+        let xlsx_path = output_dir.join(batch_file_name(&args, "invoices", "xlsx"));
+
+        super::xlsx::write(&invoices, &xlsx_path)?;
+        eprintln!(
+            "{} Excel workbook written to {}",
+            style("✓").green(),
+            xlsx_path.display()
+        );
+    }
 
-            let content = match args.format {
-                super::process::OutputFormat::Json => serde_json::to_string(invoice)?,
-                super::process::OutputFormat::Csv => format_invoice_csv(invoice)?,
-                super::process::OutputFormat::Text => format_invoice_text(invoice),
-            };
+    if let (super::process::OutputFormat::JpkFa, Some(output_dir)) = (args.format, &args.output_dir) {
+        let invoices: Vec<_> = successful
+            .iter()
+            .filter_map(|r| r.invoice.clone())
+            .collect();
+        let jpk_path = output_dir.join(batch_file_name(&args, "jpk_fa", "xml"));
+
+        fs::write(&jpk_path, JpkFa::new(config.output.jpk.clone()).write(&invoices)?)?;
+        eprintln!(
+            "{} JPK_FA file with {} invoices written to {}",
+            style("✓").green(),
+            invoices.len(),
+            jpk_path.display()
+        );
+    }
 
-            fs::write(&output_path, content)?;
-            debug!("Wrote output to {}", output_path.display());
+    let mut stats = BatchStats::new(config.extraction.min_field_confidence);
+    for result in &results {
+        match &result.invoice {
+            Some(invoice) => stats.add(invoice, result.processing_time_ms),
+            None => stats.add_failure(result.processing_time_ms),
         }
     }
+    let stats = stats.summary();
 
     // Generate summary if requested
     if args.summary {
         let summary_path = args.output_dir
             .as_ref()
-            .map(|d| d.join("summary.csv"))
-            .unwrap_or_else(|| PathBuf::from("summary.csv"));
+            .map(|d| d.join(batch_file_name(&args, "summary", "csv")))
+            .unwrap_or_else(|| PathBuf::from(batch_file_name(&args, "summary", "csv")));
 
         write_summary(&summary_path, &results)?;
-        println!(
+        eprintln!(
             "{} Summary written to {}",
             style("✓").green(),
             summary_path.display()
         );
+
+        let stats_path = summary_path.with_file_name(batch_file_name(&args, "stats", "json"));
+        fs::write(&stats_path, serde_json::to_vec_pretty(&stats)?)?;
+        eprintln!(
+            "{} Statistics written to {}",
+            style("✓").green(),
+            stats_path.display()
+        );
     }
 
     // Print summary
-    println!();
-    println!(
+    eprintln!();
+    eprintln!(
         "{} Processed {} files in {:?}",
         style("✓").green(),
         results.len(),
         start.elapsed()
     );
-    println!(
+    eprintln!(
         "   {} successful, {} failed",
         style(successful.len()).green(),
         style(failed.len()).red()
     );
 
+    print_stats(&stats, config.extraction.min_field_confidence);
+
     if !failed.is_empty() {
-        println!();
-        println!("{}", style("Failed files:").red());
+        eprintln!();
+        eprintln!("{}", style("Failed files:").red());
         for result in &failed {
-            println!(
+            eprintln!(
                 "  - {}: {}",
                 result.path.display(),
                 result.error.as_deref().unwrap_or("unknown error")
@@ -229,85 +430,345 @@ pub async fn run(args: BatchArgs, config_path: Option<&str>) -> anyhow::Result<(
     Ok(())
 }
 
-fn process_single_file(
-    path: &PathBuf,
-    parser: &HybridInvoiceParser,
-    args: &BatchArgs,
-    config: &IncrConfig,
-) -> anyhow::Result<Invoice> {
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    match extension.as_str() {
-        "pdf" => {
-            let data = fs::read(path)?;
-            let mut extractor = PdfExtractor::new();
-            extractor.load(&data)?;
-
-            let text = extractor.extract_text()?;
-            if text.trim().is_empty() {
-                anyhow::bail!("No text extracted from PDF");
+/// Print the quality section of the run summary.
+fn print_stats(stats: &StatsSummary, min_confidence: f32) {
+    let Some(average) = stats.average_confidence else {
+        return;
+    };
+
+    eprintln!();
+    eprintln!("{}", style("Quality:").bold());
+    eprintln!(
+        "   Average confidence {:.1}%, {} below {:.0}%",
+        average * 100.0,
+        stats.low_confidence,
+        min_confidence * 100.0
+    );
+
+    eprintln!("   Field coverage:");
+    for field in COVERAGE_FIELDS {
+        let percent = stats.field_coverage.get(*field).copied().unwrap_or_default();
+        eprintln!("     {:<20} {:>5.1}%", field, percent);
+    }
+
+    if !stats.warnings.is_empty() {
+        eprintln!("   Most frequent warnings:");
+        for warning in stats.warnings.iter().take(5) {
+            eprintln!("     {:>4}x {}", warning.count, warning.code);
+        }
+    }
+
+    if !stats.top_failing_suppliers.is_empty() {
+        eprintln!("   Suppliers with low-confidence results:");
+        for supplier in &stats.top_failing_suppliers {
+            eprintln!(
+                "     {} {} ({} of {} documents, average {:.1}%)",
+                supplier.supplier,
+                supplier.name,
+                supplier.low_confidence,
+                supplier.documents,
+                supplier.average_confidence * 100.0
+            );
+        }
+    }
+
+    if let Some(times) = stats.processing_time_ms {
+        eprintln!(
+            "   Processing time: p50 {}ms, p90 {}ms, p99 {}ms, max {}ms",
+            times.p50, times.p90, times.p99, times.max
+        );
+    }
+}
+
+/// Open the results store given with `--store` or in `store.path`, if any.
+#[cfg(feature = "store")]
+fn open_store(args: &BatchArgs, config: &IncrConfig) -> anyhow::Result<Option<ResultStore>> {
+    let Some(path) = args.store.as_ref().or(config.store.path.as_ref()) else {
+        return Ok(None);
+    };
+
+    debug!("Recording results in {}", path.display());
+    Ok(Some(ResultStore::open(path)?))
+}
+
+/// The files whose content the store has no successful extraction for,
+/// with the content hashes of those that could be read. Files that can't be
+/// read are kept, for the workers to report.
+#[cfg(feature = "store")]
+fn skip_extracted(
+    store: &ResultStore,
+    files: Vec<PathBuf>,
+) -> anyhow::Result<(Vec<PathBuf>, HashMap<PathBuf, String>)> {
+    let mut pending = Vec::with_capacity(files.len());
+    let mut hashes = HashMap::with_capacity(files.len());
+    for path in files {
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("Can't read {} to check the results store: {}", path.display(), e);
+                pending.push(path);
+                continue;
             }
+        };
+        let sha256 = incr_core::audit::sha256_hex(&data);
+        if !store.is_extracted(&sha256)? {
+            hashes.insert(path.clone(), sha256);
+            pending.push(path);
+        }
+    }
+    Ok((pending, hashes))
+}
+
+/// The content hash of `path`: the one computed when checking the store,
+/// so the result is recorded for the content that was checked, otherwise
+/// read now. `None` if the file can't be read.
+#[cfg(feature = "store")]
+fn content_hash(hashes: &HashMap<PathBuf, String>, path: &Path) -> Option<String> {
+    match hashes.get(path) {
+        Some(sha256) => Some(sha256.clone()),
+        None => fs::read(path).ok().map(|data| incr_core::audit::sha256_hex(&data)),
+    }
+}
 
-            let result = parser.parse(&text)?;
-            Ok(result.invoice)
+/// Choose where files come from: the local (possibly sharded) list or a shared queue.
+fn open_work_source(args: &BatchArgs, files: Vec<PathBuf>) -> anyhow::Result<Box<dyn WorkSource>> {
+    let Some(url) = &args.queue else {
+        return Ok(Box::new(LocalSource::new(files)));
+    };
+
+    #[cfg(feature = "redis-queue")]
+    {
+        let queue = super::work_queue::RedisQueue::connect(url, &args.queue_name, &files)?;
+        eprintln!(
+            "{} Joined work queue '{}' ({} jobs pending)",
+            style("ℹ").blue(),
+            args.queue_name,
+            queue.pending().unwrap_or(0)
+        );
+        Ok(Box::new(queue))
+    }
+
+    #[cfg(not(feature = "redis-queue"))]
+    {
+        let _ = files;
+        anyhow::bail!(
+            "--queue {} requires incr to be built with the 'redis-queue' feature",
+            url
+        )
+    }
+}
+
+/// Name of a file covering the whole run (summary, Parquet tables), suffixed
+/// so parallel shards/workers don't overwrite each other.
+fn batch_file_name(args: &BatchArgs, stem: &str, extension: &str) -> String {
+    if let Some(shard) = args.shard {
+        return format!("{}.shard-{}-of-{}.{}", stem, shard.index, shard.count, extension);
+    }
+
+    if args.queue.is_some() {
+        let worker = args
+            .worker_id
+            .clone()
+            .unwrap_or_else(|| std::process::id().to_string());
+        return format!("{}.worker-{}.{}", stem, worker, extension);
+    }
+
+    format!("{}.{}", stem, extension)
+}
+
+/// State shared by the batch workers.
+///
+/// Workers take files from the work source one at a time, so a slow file
+/// doesn't hold up the others. The OCR engine is loaded once, for the first
+/// image, and shared by all workers.
+struct Workers<'a> {
+    source: Mutex<Box<dyn WorkSource>>,
+    parser: &'a HybridInvoiceParser,
+    config: &'a IncrConfig,
+    model_dir: &'a Path,
+    auditor: Option<&'a Auditor>,
+    sinks: &'a [Box<dyn OutputSink>],
+    #[cfg(feature = "store")]
+    store: Option<&'a ResultStore>,
+    /// Content hashes of the files, where already computed.
+    #[cfg(feature = "store")]
+    hashes: HashMap<PathBuf, String>,
+    progress: &'a MultiProgress,
+    overall: &'a ProgressBar,
+    continue_on_error: bool,
+    /// Set when a worker failed and the others should stop taking files.
+    stop: AtomicBool,
+    results: Mutex<Vec<ProcessResult>>,
+}
+
+impl Workers<'_> {
+    /// Process files until the source is empty or another worker failed.
+    fn run(&self) -> anyhow::Result<()> {
+        let result = self.work();
+        if result.is_err() {
+            self.stop.store(true, Ordering::Relaxed);
         }
-        "png" | "jpg" | "jpeg" | "webp" | "tiff" | "tif" | "bmp" => {
-            // Process image with OCR
-            let image = image::open(path)?;
-            let text = run_ocr_on_image(&image, args, config)?;
+        result
+    }
+
+    fn work(&self) -> anyhow::Result<()> {
+        while !self.stop.load(Ordering::Relaxed) {
+            let Some(path) = self.source.lock().expect("work source lock poisoned").next_job()? else {
+                break;
+            };
 
-            if text.trim().is_empty() {
-                anyhow::bail!("No text detected in image");
+            let file_pb = self.progress.add(ProgressBar::new(100));
+            file_pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("  {prefix:30!} [{bar:20.cyan/blue}] {msg}")
+                    .unwrap()
+                    .progress_chars("=>-"),
+            );
+            file_pb.set_prefix(
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+
+            let file_start = Instant::now();
+            let result = self
+                .process(&path, &file_pb)
+                .and_then(|(invoice, raw_text)| self.deliver(&path, invoice, &raw_text));
+            let processing_time_ms = file_start.elapsed().as_millis() as u64;
+            file_pb.finish_and_clear();
+
+            if let Some(auditor) = self.auditor {
+                let outcome = result.as_ref().map_err(|e| e.to_string());
+                auditor.file_extraction("batch", &path, outcome)?;
+            }
+
+            #[cfg(feature = "store")]
+            if let Some(store) = self.store {
+                let sha256 = content_hash(&self.hashes, &path);
+                let outcome = result.as_ref().map_err(|e| e.to_string());
+                match sha256 {
+                    Some(sha256) => store.record(&sha256, &path, outcome)?,
+                    None => {
+                        warn!("Can't read {}, not recorded in the results store", path.display())
+                    }
+                }
             }
 
-            let result = parser.parse(&text)?;
-            let mut invoice = result.invoice;
-            invoice.metadata.source_type = incr_core::models::invoice::SourceType::Image;
-            Ok(invoice)
+            self.source.lock().expect("work source lock poisoned").complete(&path)?;
+
+            let result = match result {
+                Ok(invoice) => ProcessResult {
+                    path,
+                    invoice: Some(invoice),
+                    error: None,
+                    processing_time_ms,
+                },
+                Err(e) => {
+                    let error_msg = e.to_string();
+                    if !self.continue_on_error {
+                        error!("Failed to process {}: {}", path.display(), error_msg);
+                        anyhow::bail!("Processing failed: {}", error_msg);
+                    }
+
+                    warn!("Failed to process {}: {}", path.display(), error_msg);
+                    ProcessResult {
+                        path,
+                        invoice: None,
+                        error: Some(error_msg),
+                        processing_time_ms,
+                    }
+                }
+            };
+
+            self.results.lock().expect("results lock poisoned").push(result);
+            self.overall.inc(1);
         }
-        _ => {
-            anyhow::bail!("Unsupported file format: {}", extension);
+
+        Ok(())
+    }
+
+    /// Hand an extracted invoice to every sink.
+    fn deliver(&self, path: &Path, invoice: Invoice, raw_text: &str) -> anyhow::Result<Invoice> {
+        let record = SinkRecord {
+            path,
+            invoice: &invoice,
+            raw_text,
+        };
+        for sink in self.sinks {
+            sink.write(&record)?;
         }
+        Ok(invoice)
     }
-}
 
-fn run_ocr_on_image(
-    image: &DynamicImage,
-    args: &BatchArgs,
-    config: &IncrConfig,
-) -> anyhow::Result<String> {
-    // Get model directory
-    let model_dir = args.model_dir.clone().unwrap_or_else(|| {
-        get_variant_dir(get_active_variant())
-    });
-
-    // Try external models first, then embedded
-    let det_model = model_dir.join(&config.models.detection_model);
-    let engine = if det_model.exists() {
-        debug!("Using external models from {}", model_dir.display());
-        create_engine_from_dir(&model_dir, config.ocr.clone())
-            .map_err(|e| anyhow::anyhow!("Failed to load OCR models: {}", e))?
-    } else {
-        debug!("Using embedded mobile models");
-        create_engine_from_embedded(config.ocr.clone())
-            .map_err(|e| anyhow::anyhow!("Failed to load embedded OCR models: {}", e))?
-    };
+    /// The shared OCR engine, loaded on first use.
+    fn engine(&self) -> anyhow::Result<Arc<PureOcrEngine>> {
+        shared_engine(self.model_dir, self.config)
+    }
 
-    let result = engine
-        .process(image)
-        .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+    fn process(&self, path: &Path, pb: &ProgressBar) -> anyhow::Result<(Invoice, String)> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let parser = self.parser.clone().with_reference_date(file_date(path));
+        let progress = BarProgress::new(pb);
+
+        match extension.as_str() {
+            "pdf" => {
+                pb.set_message("Extracting text");
+                let data = fs::read(path)?;
+                let mut extractor = PdfExtractor::new();
+                extractor.load(&data)?;
+
+                if self.config.pdf.prefer_embedded_xml {
+                    if let Some(embedded) = embedded_invoice(&extractor) {
+                        return Ok(embedded);
+                    }
+                }
 
-    debug!(
-        "OCR detected {} text boxes in {}ms",
-        result.boxes.len(),
-        result.processing_time_ms
-    );
+                let text = extractor.extract_text()?;
+                if text.trim().is_empty() {
+                    anyhow::bail!("No text extracted from PDF");
+                }
 
-    Ok(result.text)
+                let mut invoice = parser.parse_with_progress(&text, &progress)?.invoice;
+                invoice.metadata.capabilities = Capabilities::text_layer();
+                Ok((invoice, text))
+            }
+            "png" | "jpg" | "jpeg" | "webp" | "tiff" | "tif" | "bmp" => {
+                // Process image with OCR
+                let image = image::open(path)?;
+                let ocr = self
+                    .engine()?
+                    .process_with_progress(&image, &progress)
+                    .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+                debug!(
+                    "OCR detected {} text boxes in {}ms",
+                    ocr.boxes.len(),
+                    ocr.processing_time_ms
+                );
+
+                let confidence = TextConfidence::from_ocr(&ocr);
+                let text = ocr.text;
+                if text.trim().is_empty() {
+                    anyhow::bail!("No text detected in image");
+                }
+
+                let result = parser.parse_with_text_confidence(&text, &confidence, &progress)?;
+                let mut invoice = result.invoice;
+                apply_barcodes(&mut invoice, None, &ocr.barcodes);
+                invoice.metadata.source_type = incr_core::models::invoice::SourceType::Image;
+                invoice.metadata.capabilities = merge_capabilities([&ocr.capabilities]);
+                Ok((invoice, text))
+            }
+            _ => {
+                anyhow::bail!("Unsupported file format: {}", extension);
+            }
+        }
+    }
 }
 
 fn write_summary(path: &PathBuf, results: &[ProcessResult]) -> anyhow::Result<()> {
@@ -337,7 +798,7 @@ fn write_summary(path: &PathBuf, results: &[ProcessResult]) -> anyhow::Result<()
                 filename,
                 "success",
                 &invoice.header.invoice_number,
-                &invoice.header.issue_date.to_string(),
+                &invoice.header.issue_date.map(|d| d.to_string()).unwrap_or_default(),
                 &invoice.issuer.name,
                 &invoice.issuer.nip.clone().unwrap_or_default(),
                 &invoice.summary.total_gross.to_string(),
@@ -367,7 +828,14 @@ fn write_summary(path: &PathBuf, results: &[ProcessResult]) -> anyhow::Result<()
     Ok(())
 }
 
-fn format_invoice_csv(invoice: &Invoice) -> anyhow::Result<String> {
+pub fn format_invoice_csv(invoice: &Invoice) -> anyhow::Result<String> {
+    let Some(issue_date) = invoice.header.issue_date else {
+        anyhow::bail!(
+            "Invoice {} has no issue date; refusing to export it as CSV",
+            invoice.header.invoice_number
+        );
+    };
+
     let mut wtr = csv::Writer::from_writer(vec![]);
 
     // Write header
@@ -389,7 +857,7 @@ fn format_invoice_csv(invoice: &Invoice) -> anyhow::Result<String> {
     // Write data
     wtr.write_record([
         &invoice.header.invoice_number,
-        &invoice.header.issue_date.to_string(),
+        &issue_date.to_string(),
         &invoice.header.sale_date.map(|d| d.to_string()).unwrap_or_default(),
         &invoice.header.due_date.map(|d| d.to_string()).unwrap_or_default(),
         &invoice.issuer.name,
@@ -406,11 +874,14 @@ fn format_invoice_csv(invoice: &Invoice) -> anyhow::Result<String> {
     Ok(data)
 }
 
-fn format_invoice_text(invoice: &Invoice) -> String {
+pub fn format_invoice_text(invoice: &Invoice) -> String {
     let mut output = String::new();
 
     output.push_str(&format!("Invoice: {}\n", invoice.header.invoice_number));
-    output.push_str(&format!("Date: {}\n", invoice.header.issue_date));
+    match invoice.header.issue_date {
+        Some(date) => output.push_str(&format!("Date: {}\n", date)),
+        None => output.push_str("Date: MISSING\n"),
+    }
     output.push_str("\n");
 
     output.push_str("Issuer:\n");
@@ -432,6 +903,12 @@ fn format_invoice_text(invoice: &Invoice) -> String {
     output.push_str(&format!("  Net:   {} {}\n", invoice.summary.total_net, invoice.header.currency));
     output.push_str(&format!("  VAT:   {} {}\n", invoice.summary.total_vat, invoice.header.currency));
     output.push_str(&format!("  Gross: {} {}\n", invoice.summary.total_gross, invoice.header.currency));
+    if let Some(info) = &invoice.summary.currency_info {
+        output.push_str(&format!(
+            "  Rate:  {} PLN/{} (VAT {} PLN, gross {} PLN)\n",
+            info.exchange_rate, invoice.header.currency, info.total_vat_pln, info.total_gross_pln
+        ));
+    }
 
     if let Some(due_date) = invoice.header.due_date {
         output.push_str(&format!("\nPayment due: {}\n", due_date));
@@ -439,3 +916,42 @@ fn format_invoice_text(invoice: &Invoice) -> String {
 
     output
 }
+
+#[cfg(all(test, feature = "store"))]
+mod tests {
+    use super::*;
+    use incr_core::audit::sha256_hex;
+
+    #[test]
+    fn test_skip_extracted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ResultStore::open(&dir.path().join("results.sqlite")).unwrap();
+        let (done, new, missing) = (
+            dir.path().join("done.pdf"),
+            dir.path().join("new.pdf"),
+            dir.path().join("missing.pdf"),
+        );
+        fs::write(&done, b"done").unwrap();
+        fs::write(&new, b"new").unwrap();
+        store.record(&sha256_hex(b"done"), &done, Ok(&Invoice::new())).unwrap();
+
+        // The unreadable file stays pending, for a worker to report
+        let files = vec![done.clone(), new.clone(), missing.clone()];
+        let (pending, hashes) = skip_extracted(&store, files).unwrap();
+        assert_eq!(pending, [new.clone(), missing.clone()]);
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(content_hash(&hashes, &missing), None);
+
+        // The result is recorded under the hash that was checked, even if
+        // the file changed in the meantime
+        fs::write(&new, b"changed").unwrap();
+        let sha256 = content_hash(&hashes, &new).unwrap();
+        assert_eq!(sha256, sha256_hex(b"new"));
+        assert_eq!(content_hash(&HashMap::new(), &new), Some(sha256_hex(b"changed")));
+        store.record(&sha256, &new, Ok(&Invoice::new())).unwrap();
+
+        fs::write(&new, b"new").unwrap();
+        let (pending, _) = skip_extracted(&store, vec![done, new]).unwrap();
+        assert!(pending.is_empty());
+    }
+}
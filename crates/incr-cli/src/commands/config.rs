@@ -1,12 +1,19 @@
 //! Config command - manage configuration.
+//!
+//! Every subcommand works on the file given with `--config`, or on the
+//! user's configuration file (see [`user_config_path`]). Files ending in
+//! `.json` are read and written as JSON, all others as TOML.
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
 use console::style;
+use serde_json::Value;
 
-use incr_core::models::config::IncrConfig;
+use incr_core::models::config::{ConfigFormat, IncrConfig, Preset};
+
+use super::user_config_path;
 
 /// Arguments for the config command.
 #[derive(Args)]
@@ -18,9 +25,9 @@ pub struct ConfigArgs {
 #[derive(Subcommand)]
 enum ConfigCommand {
     /// Show current configuration
-    Show,
+    Show(ShowArgs),
 
-    /// Initialize a new configuration file
+    /// Initialize a new configuration file with the default settings
     Init(InitArgs),
 
     /// Get a specific configuration value
@@ -31,19 +38,44 @@ enum ConfigCommand {
 
     /// Set a configuration value
     Set {
-        /// Configuration key
+        /// Configuration key (e.g., "commands.batch.ocr.num_threads")
         key: String,
-        /// New value
+        /// New value, parsed as JSON if possible (e.g. 0.5, true, "text")
         value: String,
     },
 
     /// Show configuration file path
     Path,
+
+    /// Check a configuration file and its profiles for errors
+    Validate {
+        /// Configuration file (default: user config file)
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct ShowArgs {
+    /// Show the settings a command runs with: the preset, the command's
+    /// overrides and the `--profile` applied
+    #[arg(long, value_name = "COMMAND")]
+    command: Option<String>,
+
+    /// Output format (default: the format of the file)
+    #[arg(long, value_enum)]
+    format: Option<ShowFormat>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ShowFormat {
+    Toml,
+    Json,
 }
 
 #[derive(Args)]
 struct InitArgs {
-    /// Output path for configuration file
+    /// Output path for configuration file (default: `--config` or the
+    /// user config file); a `.json` path is written as JSON
     #[arg(short, long)]
     output: Option<PathBuf>,
 
@@ -52,43 +84,70 @@ struct InitArgs {
     force: bool,
 }
 
-pub async fn run(args: ConfigArgs) -> anyhow::Result<()> {
+pub async fn run(
+    args: ConfigArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let path = config_path.map_or_else(user_config_path, PathBuf::from);
+
     match args.command {
-        ConfigCommand::Show => show_config(),
-        ConfigCommand::Init(init_args) => init_config(init_args),
-        ConfigCommand::Get { key } => get_config(&key),
-        ConfigCommand::Set { key, value } => set_config(&key, &value),
-        ConfigCommand::Path => show_path(),
+        ConfigCommand::Show(show_args) => show_config(&path, show_args, profile, preset),
+        ConfigCommand::Init(init_args) => init_config(&path, init_args),
+        ConfigCommand::Get { key } => get_config(&path, &key),
+        ConfigCommand::Set { key, value } => set_config(&path, &key, &value),
+        ConfigCommand::Path => show_path(&path),
+        ConfigCommand::Validate { path: file } => validate_config(&file.unwrap_or(path)),
     }
 }
 
-fn default_config_path() -> PathBuf {
-    dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("incr")
-        .join("config.json")
+/// The configuration in `path`, or the defaults if there is no such file.
+fn read_config(path: &Path) -> anyhow::Result<IncrConfig> {
+    if path.exists() {
+        Ok(IncrConfig::from_file(path)?)
+    } else {
+        Ok(IncrConfig::default())
+    }
 }
 
-fn show_config() -> anyhow::Result<()> {
-    let config_path = default_config_path();
-
-    let config = if config_path.exists() {
-        IncrConfig::from_file(&config_path)?
-    } else {
-        println!(
-            "{} No config file found, showing defaults.",
-            style("ℹ").blue()
+fn show_config(
+    path: &Path,
+    args: ShowArgs,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    if !path.exists() {
+        eprintln!(
+            "{} No config file found at {}, showing defaults.",
+            style("ℹ").blue(),
+            path.display()
         );
-        IncrConfig::default()
-    };
+    }
+    let mut config = read_config(path)?;
 
-    println!("{}", serde_json::to_string_pretty(&config)?);
+    if let Some(command) = &args.command {
+        config = config.resolve_with_preset(Some(command), profile, preset)?;
+        // The overlays are applied; listing them again would be misleading
+        config.profiles.clear();
+        config.commands.clear();
+    }
+
+    let format = match args.format {
+        Some(ShowFormat::Toml) => ConfigFormat::Toml,
+        Some(ShowFormat::Json) => ConfigFormat::Json,
+        None => ConfigFormat::of_path(path),
+    };
+    print!("{}", config.to_string_as(format)?);
+    if format == ConfigFormat::Json {
+        println!();
+    }
 
     Ok(())
 }
 
-fn init_config(args: InitArgs) -> anyhow::Result<()> {
-    let output_path = args.output.unwrap_or_else(default_config_path);
+fn init_config(path: &Path, args: InitArgs) -> anyhow::Result<()> {
+    let output_path = args.output.unwrap_or_else(|| path.to_path_buf());
 
     if output_path.exists() && !args.force {
         anyhow::bail!(
@@ -114,14 +173,8 @@ fn init_config(args: InitArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn get_config(key: &str) -> anyhow::Result<()> {
-    let config_path = default_config_path();
-
-    let config = if config_path.exists() {
-        IncrConfig::from_file(&config_path)?
-    } else {
-        IncrConfig::default()
-    };
+fn get_config(path: &Path, key: &str) -> anyhow::Result<()> {
+    let config = read_config(path)?;
 
     // Convert config to JSON for key lookup
     let json = serde_json::to_value(&config)?;
@@ -141,49 +194,46 @@ fn get_config(key: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn set_config(key: &str, value: &str) -> anyhow::Result<()> {
-    let config_path = default_config_path();
-
-    let mut config = if config_path.exists() {
-        IncrConfig::from_file(&config_path)?
-    } else {
-        // Create parent directory if needed
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        IncrConfig::default()
-    };
+fn set_config(path: &Path, key: &str, value: &str) -> anyhow::Result<()> {
+    let config = read_config(path)?;
 
     // Parse the value
-    let parsed_value: serde_json::Value = serde_json::from_str(value)
-        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    let parsed_value: Value = serde_json::from_str(value)
+        .unwrap_or_else(|_| Value::String(value.to_string()));
 
     // Convert config to JSON, modify, and convert back
     let mut json = serde_json::to_value(&config)?;
 
-    // Navigate and set the key
     let parts: Vec<&str> = key.split('.').collect();
-    let mut current = &mut json;
+    let Some((last, parents)) = parts.split_last() else {
+        anyhow::bail!("Empty configuration key");
+    };
 
-    for (i, part) in parts.iter().enumerate() {
-        if i == parts.len() - 1 {
-            // Last part - set the value
-            if let Some(obj) = current.as_object_mut() {
-                obj.insert((*part).to_string(), parsed_value.clone());
-            } else {
-                anyhow::bail!("Cannot set value at non-object path");
-            }
-        } else {
-            // Navigate deeper
-            current = current.get_mut(*part).ok_or_else(|| {
-                anyhow::anyhow!("Configuration path not found: {}", key)
-            })?;
-        }
+    // Navigate, creating the sections of new profiles and command
+    // overrides; the result is checked against the schema below
+    let mut current = &mut json;
+    for part in parents {
+        let object = current
+            .as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("Cannot set value at non-object path: {}", key))?;
+        current = object
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
     }
+    match current.as_object_mut() {
+        Some(object) => object.insert(last.to_string(), parsed_value.clone()),
+        None => anyhow::bail!("Cannot set value at non-object path: {}", key),
+    };
 
     // Convert back to config
-    config = serde_json::from_value(json)?;
-    config.save(&config_path)?;
+    let config: IncrConfig = serde_json::from_value(json)
+        .map_err(|e| anyhow::anyhow!("Cannot set {}: {}", key, e))?;
+    config.check()?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    config.save(path)?;
 
     println!(
         "{} Set {} = {}",
@@ -195,12 +245,10 @@ fn set_config(key: &str, value: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn show_path() -> anyhow::Result<()> {
-    let config_path = default_config_path();
-
-    println!("Configuration file: {}", config_path.display());
+fn show_path(path: &Path) -> anyhow::Result<()> {
+    println!("Configuration file: {}", path.display());
 
-    if config_path.exists() {
+    if path.exists() {
         println!("Status: {}", style("exists").green());
     } else {
         println!("Status: {}", style("not created").yellow());
@@ -210,3 +258,25 @@ fn show_path() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn validate_config(config_path: &Path) -> anyhow::Result<()> {
+    let config = IncrConfig::from_file(config_path)?;
+
+    println!(
+        "{} {} is valid",
+        style("✓").green(),
+        config_path.display()
+    );
+
+    if !config.profiles.is_empty() {
+        let names: Vec<&str> = config.profiles.keys().map(|k| k.as_str()).collect();
+        println!("  Profiles: {}", names.join(", "));
+    }
+
+    if !config.commands.is_empty() {
+        let names: Vec<&str> = config.commands.keys().map(|k| k.as_str()).collect();
+        println!("  Command overrides: {}", names.join(", "));
+    }
+
+    Ok(())
+}
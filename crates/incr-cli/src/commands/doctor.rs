@@ -0,0 +1,153 @@
+//! Doctor command - check the installation and what the pipeline can do.
+//!
+//! Optional models (angle classification, layout, super-resolution) are
+//! used when installed and skipped otherwise. `--capabilities` lists every
+//! pipeline stage with whether it runs and, if not, why.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::Context;
+use clap::Args;
+use console::style;
+
+use incr_core::models::capabilities::{Capabilities, Stage};
+use incr_core::models::config::{IncrConfig, Preset};
+
+use super::load_config;
+use super::process::load_engine;
+use super::variant::{embedded_models, get_variant_dir, resolve_variant};
+
+/// Arguments for the doctor command.
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// List the pipeline stages that run and why others are skipped
+    #[arg(long)]
+    capabilities: bool,
+
+    /// Apply the config overrides of this command
+    #[arg(long, default_value = "process")]
+    command: String,
+
+    /// Model directory (default: directory of the configured variant)
+    #[arg(long)]
+    model_dir: Option<PathBuf>,
+}
+
+/// Whether a stage runs with the current installation and config.
+enum Availability {
+    /// On every image that is OCR'd.
+    Always,
+    /// Only when the document needs it.
+    OnDemand(String),
+    /// Never, for the given reason.
+    Never(String),
+}
+
+pub async fn run(
+    args: DoctorArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let config = load_config(config_path, profile, preset, &args.command)?;
+
+    println!("{}", style("incr doctor").bold());
+    println!(
+        "  Config:  {}{}{}",
+        config_path.unwrap_or("defaults"),
+        profile.map(|p| format!(", profile {}", p)).unwrap_or_default(),
+        config.preset.map(|p| format!(", preset {}", p.name())).unwrap_or_default()
+    );
+
+    let variant = resolve_variant(&config);
+    let model_dir = args.model_dir.clone().unwrap_or_else(|| get_variant_dir(variant));
+    if model_dir.join(&config.models.detection_model).exists() {
+        println!("  Models:  {} ({})", model_dir.display(), variant);
+    } else {
+        println!(
+            "  Models:  embedded {} models ({} not found in {})",
+            embedded_models(variant).0,
+            config.models.detection_model,
+            model_dir.display()
+        );
+    }
+
+    let start = Instant::now();
+    let engine = load_engine(&model_dir, &config).context("OCR engine failed to load")?;
+    println!(
+        "  Engine:  {} loaded in {}ms",
+        style("✓").green(),
+        start.elapsed().as_millis()
+    );
+
+    if !args.capabilities {
+        println!("\nRun 'incr doctor --capabilities' to list the pipeline stages.");
+        return Ok(());
+    }
+
+    let mut capabilities = engine.capabilities();
+    // Line items are parsed from the page text unless layout analysis runs
+    if !capabilities.has_run(Stage::Layout) {
+        capabilities.skipped(Stage::Tables, "layout analysis is not available");
+    }
+
+    println!("\n{}", style("Pipeline stages").bold());
+    for stage in Stage::ALL {
+        let (marker, note) = match availability(stage, &capabilities, &config) {
+            Availability::Always => (style("✓").green(), String::new()),
+            Availability::OnDemand(note) => (style("~").cyan(), note),
+            Availability::Never(reason) => (style("✗").red(), reason),
+        };
+        println!("  {} {:<17} {}", marker, stage.name(), note);
+    }
+
+    Ok(())
+}
+
+fn availability(stage: Stage, capabilities: &Capabilities, config: &IncrConfig) -> Availability {
+    if let Some(status) = capabilities.status(stage) {
+        return match &status.reason {
+            Some(reason) if !status.ran => Availability::Never(reason.clone()),
+            _ => Availability::Always,
+        };
+    }
+
+    // Stages that depend on the document
+    match stage {
+        Stage::TextLayer if !config.pdf.prefer_embedded_text => {
+            Availability::Never("disabled by pdf.prefer_embedded_text".to_string())
+        }
+        Stage::TextLayer => Availability::OnDemand("for PDFs with a text layer".to_string()),
+        Stage::Deskew if !config.ocr.auto_deskew => {
+            Availability::Never("disabled by ocr.auto_deskew".to_string())
+        }
+        Stage::Deskew => Availability::OnDemand("for pages skewed by 0.3-15 degrees".to_string()),
+        Stage::Orientation if !config.ocr.auto_orient => {
+            Availability::Never("disabled by ocr.auto_orient".to_string())
+        }
+        Stage::Orientation => {
+            Availability::OnDemand("for pages scanned sideways or upside down".to_string())
+        }
+        Stage::Upscaling if !config.ocr.auto_upscale => {
+            Availability::Never("disabled by ocr.auto_upscale".to_string())
+        }
+        Stage::Upscaling => Availability::OnDemand(format!(
+            "when text lines are below {}px (ocr.min_text_height)",
+            config.ocr.min_text_height
+        )),
+        Stage::SuperResolution => Availability::OnDemand(format!(
+            "for small text below {} confidence (ocr.super_resolution_threshold)",
+            config.ocr.super_resolution_threshold
+        )),
+        Stage::SecondPass => Availability::OnDemand(format!(
+            "for lines below {} confidence (ocr.second_pass_threshold)",
+            config.ocr.second_pass_threshold
+        )),
+        Stage::Barcodes => {
+            Availability::OnDemand("for pages with QR codes or barcodes".to_string())
+        }
+        Stage::Tables => Availability::OnDemand("when layout analysis finds tables".to_string()),
+        _ => Availability::Always,
+    }
+}
@@ -0,0 +1,111 @@
+//! OCR engines shared within one CLI invocation.
+//!
+//! Loading the models takes seconds and about 100 MB per engine, so
+//! commands take their engines from this cache instead of loading their
+//! own: everything that runs OCR with the same models and OCR settings
+//! during an invocation (batch workers, server requests) uses one
+//! engine, loaded the first time it is needed.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tracing::debug;
+
+use incr_core::models::config::IncrConfig;
+use incr_core::PureOcrEngine;
+
+use super::process::load_engine;
+
+/// An engine once it has loaded. The lock is held while it loads.
+type Slot = Mutex<Option<Arc<PureOcrEngine>>>;
+
+static ENGINES: OnceLock<Mutex<HashMap<EngineKey, Arc<Slot>>>> = OnceLock::new();
+
+/// What decides which engine gets loaded: the model directory, and the
+/// OCR settings and model file names of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EngineKey {
+    model_dir: PathBuf,
+    settings: String,
+}
+
+impl EngineKey {
+    fn new(model_dir: &Path, config: &IncrConfig) -> Self {
+        Self {
+            model_dir: model_dir
+                .canonicalize()
+                .unwrap_or_else(|_| model_dir.to_path_buf()),
+            settings: serde_json::to_string(&(&config.ocr, &config.models)).unwrap_or_default(),
+        }
+    }
+}
+
+/// The engine for the models in `model_dir` with the OCR settings of
+/// `config`, loaded on first use.
+///
+/// Concurrent callers asking for the same engine wait for a single load.
+/// A failed load is not remembered: the next caller tries again, so a
+/// server recovers once the missing models are downloaded.
+pub fn shared_engine(model_dir: &Path, config: &IncrConfig) -> anyhow::Result<Arc<PureOcrEngine>> {
+    let key = EngineKey::new(model_dir, config);
+    let slot = {
+        let mut engines = cache().lock().expect("engine cache lock poisoned");
+        engines.entry(key).or_default().clone()
+    };
+
+    get_or_load(&slot, || {
+        debug!("Loading OCR engine from {}", model_dir.display());
+        load_engine(model_dir, config)
+    })
+}
+
+/// The value in `slot`, loaded with `load` if there is none yet. Errors
+/// leave the slot empty.
+fn get_or_load<T>(
+    slot: &Mutex<Option<Arc<T>>>,
+    load: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<Arc<T>> {
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(value) = slot.as_ref() {
+        return Ok(value.clone());
+    }
+    let value = Arc::new(load()?);
+    *slot = Some(value.clone());
+    Ok(value)
+}
+
+/// Whether the engine for `model_dir` and `config` has been loaded.
+#[cfg(feature = "server")]
+pub fn is_loaded(model_dir: &Path, config: &IncrConfig) -> bool {
+    let key = EngineKey::new(model_dir, config);
+    let engines = cache().lock().expect("engine cache lock poisoned");
+    // A slot that is locked is still loading
+    engines
+        .get(&key)
+        .is_some_and(|slot| slot.try_lock().is_ok_and(|engine| engine.is_some()))
+}
+
+fn cache() -> &'static Mutex<HashMap<EngineKey, Arc<Slot>>> {
+    ENGINES.get_or_init(Default::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_load_is_retried() {
+        let slot = Mutex::new(None);
+
+        let error = get_or_load(&slot, || anyhow::bail!("model not found")).unwrap_err();
+        assert_eq!(error.to_string(), "model not found");
+        assert!(slot.lock().unwrap().is_none());
+
+        let loaded = get_or_load(&slot, || Ok(1)).unwrap();
+        assert_eq!(*loaded, 1);
+        // Loaded once, then shared
+        let again = get_or_load(&slot, || Ok(2)).unwrap();
+        assert!(Arc::ptr_eq(&loaded, &again));
+    }
+}
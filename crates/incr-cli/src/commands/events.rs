@@ -0,0 +1,151 @@
+//! Publishing of completed extractions to a message broker (`events.url`).
+//!
+//! Each extracted invoice is sent as one message, keyed by invoice number,
+//! to a Kafka topic (`kafka` feature) or NATS subject (`nats` feature).
+
+use tracing::info;
+
+use incr_core::models::config::{EventFormat, IncrConfig};
+use incr_core::models::invoice::Invoice;
+use incr_core::models::naming::FieldNaming;
+
+/// A broker connection messages are sent through.
+pub trait EventSink: Send + Sync {
+    /// Send one message. Called from blocking threads, never async code.
+    fn send(&self, topic: &str, key: &str, payload: Vec<u8>) -> anyhow::Result<()>;
+}
+
+/// Publishes extracted invoices when `events.url` is configured.
+pub struct Publisher {
+    sink: Box<dyn EventSink>,
+    topic: String,
+    format: EventFormat,
+    naming: FieldNaming,
+}
+
+impl Publisher {
+    /// Connect to the configured broker, or `None` if publishing is disabled.
+    pub async fn connect(config: &IncrConfig) -> anyhow::Result<Option<Self>> {
+        let Some(url) = &config.events.url else {
+            return Ok(None);
+        };
+
+        let sink = open_sink(url).await?;
+        info!("Publishing extractions to {} ({})", url, config.events.topic);
+
+        Ok(Some(Self {
+            sink,
+            topic: config.events.topic.clone(),
+            format: config.events.format,
+            naming: config.output.field_naming,
+        }))
+    }
+
+    /// Publish an extracted invoice.
+    pub fn publish(&self, invoice: &Invoice) -> anyhow::Result<()> {
+        let payload = match self.format {
+            EventFormat::Json => serde_json::to_vec(&self.naming.apply(invoice))?,
+            EventFormat::Proto => incr_core::proto::encode_invoice(invoice),
+        };
+
+        self.sink.send(&self.topic, &invoice.header.invoice_number, payload)
+    }
+}
+
+/// Connect to the broker named by the URL scheme.
+async fn open_sink(url: &str) -> anyhow::Result<Box<dyn EventSink>> {
+    if let Some(brokers) = url.strip_prefix("kafka://") {
+        #[cfg(feature = "kafka")]
+        return Ok(Box::new(kafka::KafkaSink::connect(brokers)?));
+
+        #[cfg(not(feature = "kafka"))]
+        {
+            let _ = brokers;
+            anyhow::bail!("events.url {} requires incr to be built with the 'kafka' feature", url)
+        }
+    }
+
+    if url.starts_with("nats://") {
+        #[cfg(feature = "nats")]
+        return Ok(Box::new(nats::NatsSink::connect(url).await?));
+
+        #[cfg(not(feature = "nats"))]
+        anyhow::bail!("events.url {} requires incr to be built with the 'nats' feature", url);
+    }
+
+    anyhow::bail!("events.url must start with kafka:// or nats://, got '{}'", url)
+}
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use std::time::Duration;
+
+    use rdkafka::config::ClientConfig;
+    use rdkafka::producer::{BaseRecord, DefaultProducerContext, Producer, ThreadedProducer};
+
+    use super::EventSink;
+
+    /// How long pending messages may take to be delivered on shutdown.
+    const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Kafka producer; delivery happens on a background thread.
+    pub struct KafkaSink {
+        producer: ThreadedProducer<DefaultProducerContext>,
+    }
+
+    impl KafkaSink {
+        /// Create a producer for comma-separated `host:port` brokers.
+        pub fn connect(brokers: &str) -> anyhow::Result<Self> {
+            let producer = ClientConfig::new()
+                .set("bootstrap.servers", brokers)
+                .set("message.timeout.ms", "30000")
+                .create()?;
+            Ok(Self { producer })
+        }
+    }
+
+    impl EventSink for KafkaSink {
+        fn send(&self, topic: &str, key: &str, payload: Vec<u8>) -> anyhow::Result<()> {
+            let record = BaseRecord::to(topic).key(key).payload(&payload);
+            self.producer.send(record).map_err(|(e, _)| e)?;
+            Ok(())
+        }
+    }
+
+    impl Drop for KafkaSink {
+        fn drop(&mut self) {
+            let _ = self.producer.flush(FLUSH_TIMEOUT);
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use tokio::runtime::Handle;
+
+    use super::EventSink;
+
+    /// NATS client; messages are queued and written by its connection task.
+    pub struct NatsSink {
+        client: async_nats::Client,
+        runtime: Handle,
+    }
+
+    impl NatsSink {
+        /// Connect to a `nats://` server.
+        pub async fn connect(url: &str) -> anyhow::Result<Self> {
+            Ok(Self {
+                client: async_nats::connect(url).await?,
+                runtime: Handle::current(),
+            })
+        }
+    }
+
+    impl EventSink for NatsSink {
+        fn send(&self, subject: &str, _key: &str, payload: Vec<u8>) -> anyhow::Result<()> {
+            self.runtime
+                .block_on(self.client.publish(subject.to_string(), payload.into()))?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Export-training-data command - build PaddleOCR det/rec datasets.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use console::style;
+use glob::glob;
+use image::DynamicImage;
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing::{debug, warn};
+
+use incr_core::invoice::{HybridInvoiceParser, InvoiceParser};
+use incr_core::models::config::Preset;
+use incr_core::models::invoice::Invoice;
+use incr_core::pdf::{PdfExtractor, PdfProcessor};
+use incr_core::training::{
+    apply_corrections, crop_text_box, det_label_line, diff_invoices, rec_label_line, DetLabel,
+};
+use incr_core::{create_engine_from_embedded_models, PureOcrEngine};
+
+use super::audit::Auditor;
+use super::{load_config, parser_from_config};
+use super::variant::{embedded_models, get_variant_dir, resolve_variant};
+
+/// Arguments for the export-training-data command.
+#[derive(Args)]
+pub struct ExportTrainingArgs {
+    /// Input files or glob pattern (images or scanned PDFs)
+    #[arg(required = true)]
+    input: String,
+
+    /// Output directory for the dataset
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Directory with corrected invoice JSON files named <file stem>.json
+    #[arg(long, value_name = "DIR")]
+    corrections: Option<PathBuf>,
+
+    /// Boxes below this recognition score are marked difficult and not cropped
+    #[arg(long, default_value = "0.5")]
+    min_score: f32,
+
+    /// Model directory
+    #[arg(short, long)]
+    model_dir: Option<PathBuf>,
+}
+
+/// Open label files of the dataset.
+struct Dataset {
+    root: PathBuf,
+    det_labels: fs::File,
+    rec_labels: fs::File,
+    det_count: usize,
+    rec_count: usize,
+}
+
+pub async fn run(
+    args: ExportTrainingArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let config = load_config(config_path, profile, preset, "export-training-data")?;
+
+    let files: Vec<PathBuf> = glob(&args.input)?
+        .filter_map(|r| r.ok())
+        .filter(|p| {
+            let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+            matches!(ext.to_lowercase().as_str(), "pdf" | "png" | "jpg" | "jpeg" | "tiff" | "bmp")
+        })
+        .collect();
+
+    if files.is_empty() {
+        anyhow::bail!("No matching files found for pattern: {}", args.input);
+    }
+
+    let model_dir = args.model_dir.clone().unwrap_or_else(|| {
+        get_variant_dir(resolve_variant(&config))
+    });
+
+    let engine = if model_dir.join(&config.models.detection_model).exists() {
+        debug!("Using external models from {}", model_dir.display());
+        let (rec_model, dictionary) = config.models.recognition_files();
+        PureOcrEngine::from_dir_with_files(&model_dir, &rec_model, &dictionary, config.ocr.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to load OCR models: {}", e))?
+    } else {
+        let (variant, models) = embedded_models(resolve_variant(&config));
+        debug!("Using embedded {} models", variant);
+        create_engine_from_embedded_models(models, config.ocr.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to load embedded OCR models: {}", e))?
+    };
+
+    let auditor = Auditor::open(&config, &model_dir)?;
+
+    let parser = parser_from_config(&config)?;
+
+    fs::create_dir_all(args.output_dir.join("det/images"))?;
+    fs::create_dir_all(args.output_dir.join("rec/crops"))?;
+
+    let mut dataset = Dataset {
+        root: args.output_dir.clone(),
+        det_labels: fs::File::create(args.output_dir.join("det/Label.txt"))?,
+        rec_labels: fs::File::create(args.output_dir.join("rec/rec_gt.txt"))?,
+        det_count: 0,
+        rec_count: 0,
+    };
+
+    let pb = ProgressBar::new(files.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+
+    for path in &files {
+        if let Err(e) = export_file(path, &args, &engine, &parser, auditor.as_ref(), &mut dataset) {
+            warn!("Failed to export {}: {}", path.display(), e);
+        }
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "{} Exported {} page images and {} text crops to {}",
+        style("✓").green(),
+        dataset.det_count,
+        dataset.rec_count,
+        args.output_dir.display()
+    );
+
+    Ok(())
+}
+
+fn export_file(
+    path: &Path,
+    args: &ExportTrainingArgs,
+    engine: &PureOcrEngine,
+    parser: &HybridInvoiceParser,
+    auditor: Option<&Auditor>,
+    dataset: &mut Dataset,
+) -> anyhow::Result<()> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document")
+        .to_string();
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let pages: Vec<DynamicImage> = if extension == "pdf" {
+        let data = fs::read(path)?;
+        let mut extractor = PdfExtractor::new();
+        extractor.load(&data)?;
+
+        let mut images = Vec::new();
+        for page in 1..=extractor.page_count() {
+            images.extend(extractor.extract_images(page)?);
+        }
+        images
+    } else {
+        vec![image::open(path)?]
+    };
+
+    if pages.is_empty() {
+        anyhow::bail!("No page images found");
+    }
+
+    let corrected = match &args.corrections {
+        Some(dir) => {
+            let corrected_path = dir.join(format!("{}.json", stem));
+            if corrected_path.exists() {
+                let content = fs::read_to_string(&corrected_path)?;
+                Some(serde_json::from_str::<Invoice>(&content)?)
+            } else {
+                debug!("No corrections for {}", stem);
+                None
+            }
+        }
+        None => None,
+    };
+
+    for (i, page) in pages.iter().enumerate() {
+        let name = if pages.len() == 1 {
+            stem.clone()
+        } else {
+            format!("{}_p{}", stem, i + 1)
+        };
+
+        let mut result = engine
+            .process(page)
+            .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+
+        if let Some(corrected) = &corrected {
+            let extracted = parser.parse(&result.text)?.invoice;
+            let corrections = diff_invoices(&extracted, corrected);
+            let changed = apply_corrections(&mut result.boxes, &corrections);
+            debug!(
+                "{}: {} corrections applied to {} boxes",
+                name,
+                corrections.len(),
+                changed
+            );
+
+            if let (Some(auditor), false) = (auditor, corrections.is_empty()) {
+                auditor.correction("export-training-data", path, corrected, corrections)?;
+            }
+        }
+
+        export_page(&name, page, &result.boxes, args.min_score, dataset)?;
+    }
+
+    Ok(())
+}
+
+fn export_page(
+    name: &str,
+    page: &DynamicImage,
+    boxes: &[incr_core::TextBox],
+    min_score: f32,
+    dataset: &mut Dataset,
+) -> anyhow::Result<()> {
+    let image_file = format!("images/{}.png", name);
+    page.save(dataset.root.join("det").join(&image_file))?;
+
+    let mut labels = Vec::new();
+
+    for (i, text_box) in boxes.iter().enumerate() {
+        if text_box.text.trim().is_empty() {
+            continue;
+        }
+
+        let mut label = DetLabel::from(text_box);
+        label.difficult = text_box.recognition_score < min_score;
+
+        let crop = if label.difficult {
+            None
+        } else {
+            crop_text_box(page, text_box)
+        };
+
+        if let Some(crop) = crop {
+            let crop_file = format!("crops/{}_{:03}.png", name, i + 1);
+            crop.save(dataset.root.join("rec").join(&crop_file))?;
+            writeln!(dataset.rec_labels, "{}", rec_label_line(&crop_file, &text_box.text))?;
+            dataset.rec_count += 1;
+        }
+
+        labels.push(label);
+    }
+
+    writeln!(dataset.det_labels, "{}", det_label_line(&image_file, &labels)?)?;
+    dataset.det_count += 1;
+
+    Ok(())
+}
@@ -1,6 +1,212 @@
 //! CLI command implementations.
 
+#[cfg(feature = "full")]
+pub mod ab;
+pub mod audit;
 pub mod process;
 pub mod batch;
+#[cfg(feature = "full")]
 pub mod models;
+#[cfg(feature = "full")]
 pub mod config;
+#[cfg(feature = "full")]
+pub mod doctor;
+pub mod engines;
+#[cfg(feature = "server")]
+pub mod events;
+#[cfg(feature = "full")]
+pub mod export_training;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(any(feature = "server", feature = "scanner"))]
+pub mod pipeline;
+pub mod progress;
+#[cfg(feature = "store")]
+pub mod query;
+#[cfg(feature = "full")]
+pub mod reconcile;
+#[cfg(feature = "full")]
+pub mod render;
+#[cfg(feature = "full")]
+pub mod reparse;
+pub mod resources;
+#[cfg(feature = "scanner")]
+pub mod scan;
+#[cfg(feature = "server")]
+pub mod serve;
+pub mod sink;
+#[cfg(feature = "store")]
+pub mod store;
+#[cfg(feature = "full")]
+pub mod support_bundle;
+pub mod variant;
+pub mod words;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+pub mod work_queue;
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use incr_core::invoice::HybridInvoiceParser;
+use incr_core::models::capabilities::{Capabilities, Stage};
+use incr_core::models::config::{IncrConfig, Preset, Quality};
+use incr_core::models::invoice::Invoice;
+use incr_core::models::language::parse_languages;
+use incr_core::pdf::PdfExtractor;
+
+/// Languages selected with `--lang`, overriding the configuration.
+static LANGUAGES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Select the document languages for the rest of the run (the `--lang`
+/// flag).
+pub fn set_languages(languages: Vec<String>) {
+    let _ = LANGUAGES.set(languages);
+}
+
+/// Load the configuration file (or defaults) and apply the selected preset,
+/// the overrides for `command`, the selected profile and `--lang`.
+///
+/// Without `--config` the user's configuration file is read, if it exists.
+pub fn load_config(
+    path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+    command: &str,
+) -> anyhow::Result<IncrConfig> {
+    let user_config = user_config_path();
+    let config = match path {
+        Some(path) => IncrConfig::from_file(Path::new(path))?,
+        None if user_config.exists() => IncrConfig::from_file(&user_config)?,
+        None => IncrConfig::default(),
+    };
+
+    let mut config = config.resolve_with_preset(Some(command), profile, preset)?;
+    if let Some(languages) = LANGUAGES.get() {
+        config.models.languages = languages.clone();
+    }
+    Ok(config)
+}
+
+/// The user's configuration file: `config.toml` in the incr configuration
+/// directory (`~/.config/incr` on Linux), or the `config.json` written by
+/// older versions if only that exists.
+pub fn user_config_path() -> PathBuf {
+    let dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("incr");
+    let (toml, json) = (dir.join("config.toml"), dir.join("config.json"));
+    if !toml.exists() && json.exists() { json } else { toml }
+}
+
+/// Merge the stage reports of the OCR'd images of a document.
+///
+/// The recognized text is parsed as a whole, so line items never come from
+/// table regions.
+pub fn merge_capabilities<'a>(pages: impl IntoIterator<Item = &'a Capabilities>) -> Capabilities {
+    let mut capabilities = Capabilities::new();
+    for page in pages {
+        capabilities.merge(page);
+    }
+    capabilities.skipped(Stage::Tables, "layout analysis did not run");
+    capabilities
+}
+
+/// The invoice of a hybrid e-invoice, read from the XML embedded in the
+/// PDF rather than from its printed form, with the XML.
+///
+/// Attachments in unknown formats are skipped; one that fails to parse
+/// leaves a warning and the next one is tried.
+pub fn embedded_invoice(extractor: &PdfExtractor) -> Option<(Invoice, String)> {
+    let attachments = match extractor.extract_attachments() {
+        Ok(attachments) => attachments,
+        Err(e) => {
+            warn!("Failed to read PDF attachments: {}", e);
+            return None;
+        }
+    };
+
+    for attachment in attachments.iter().filter(|a| a.is_xml()) {
+        let xml = String::from_utf8_lossy(&attachment.data);
+        match parse_e_invoice(&xml) {
+            Some(Ok(invoice)) => {
+                info!("Using the invoice XML embedded as {}", attachment.name);
+                return Some((invoice, xml.into_owned()));
+            }
+            Some(Err(e)) => {
+                warn!("Failed to read the invoice XML embedded as {}: {}", attachment.name, e)
+            }
+            None => debug!("Attachment {} is not a known e-invoice format", attachment.name),
+        }
+    }
+    None
+}
+
+/// Parse an e-invoice XML document, `None` if it is in no supported format.
+#[cfg_attr(not(any(feature = "ksef", feature = "facturx")), allow(unused_variables))]
+fn parse_e_invoice(xml: &str) -> Option<anyhow::Result<Invoice>> {
+    #[cfg(feature = "ksef")]
+    match Invoice::from_ksef_xml(xml) {
+        Err(incr_core::error::KsefError::NotInvoice(_)) => {}
+        result => return Some(result.map_err(Into::into)),
+    }
+    #[cfg(feature = "facturx")]
+    match Invoice::from_facturx_xml(xml) {
+        Err(incr_core::error::FacturxError::NotInvoice(_)) => {}
+        result => return Some(result.map_err(Into::into)),
+    }
+    None
+}
+
+/// Parse a `--preset` value.
+pub fn parse_preset(value: &str) -> Result<Preset, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+        .map_err(|_| format!("unknown preset '{}' (expected fast or accurate)", value))
+}
+
+/// Parse a `--quality` value.
+pub fn parse_quality(value: &str) -> Result<Quality, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+        .map_err(|_| format!("unknown quality '{}' (expected fast, balanced or best)", value))
+}
+
+/// Parse a `--lang` value (`pl,en,de`).
+pub fn parse_lang(value: &str) -> Result<Vec<String>, String> {
+    parse_languages(value).map_err(|e| e.to_string())
+}
+
+/// JSON name of an enum value ("standard", "23", "zw", ...).
+pub fn json_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// The invoice parser with the `extraction` settings of `config`:
+/// validation, the confidence threshold, vendor templates and own NIPs.
+pub fn parser_from_config(config: &IncrConfig) -> anyhow::Result<HybridInvoiceParser> {
+    let extraction = &config.extraction;
+    Ok(HybridInvoiceParser::new()
+        .with_nip_validation(extraction.validate_nip)
+        .with_regon_validation(extraction.validate_regon)
+        .with_iban_validation(extraction.validate_iban)
+        .with_min_confidence(extraction.min_field_confidence)
+        .with_templates(extraction.load_templates()?)
+        .with_own_nips(extraction.own_nips.clone()))
+}
+
+/// Modification date of a file, the reference for date plausibility
+/// checks. Falls back to today if the file system doesn't record it.
+pub fn file_date(path: &Path) -> NaiveDate {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|modified| DateTime::<Local>::from(modified).date_naive())
+        .unwrap_or_else(|_| Local::now().date_naive())
+}
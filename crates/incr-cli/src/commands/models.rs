@@ -1,14 +1,23 @@
 //! Models command - download and manage OCR models.
 
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use clap::{Args, Subcommand, ValueEnum};
 use console::style;
-use futures_util::StreamExt;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
+use incr_core::models::bundle::{self, BUNDLE_EXTENSION};
+use incr_core::models::downloader::{
+    self, DownloadProgress, FileCheck, ModelDownloader, ModelInfo, VariantModels, MANIFEST_FILE,
+    SUPER_RESOLUTION,
+};
+
+use super::variant::{get_active_variant, get_models_dir, get_variant_dir, ModelVariant};
+
 /// Arguments for the models command.
 #[derive(Args)]
 pub struct ModelsArgs {
@@ -27,28 +36,20 @@ enum ModelsCommand {
     /// Check model status
     Status(StatusArgs),
 
+    /// Check installed models against their SHA-256 checksums
+    Verify(VerifyArgs),
+
     /// Remove downloaded models
     Clean(CleanArgs),
 
     /// Set the active model variant
     Use(UseArgs),
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
-pub enum ModelVariant {
-    /// Mobile models - smaller, faster (~10MB)
-    Mobile,
-    /// Server models - better detection accuracy (~96MB)
-    Server,
-}
+    /// Bundle installed models into a .tar.zst archive for offline machines
+    Export(ExportArgs),
 
-impl std::fmt::Display for ModelVariant {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ModelVariant::Mobile => write!(f, "mobile"),
-            ModelVariant::Server => write!(f, "server"),
-        }
-    }
+    /// Install models from an archive written by `models export`
+    Import(ImportArgs),
 }
 
 #[derive(Args)]
@@ -68,6 +69,10 @@ struct DownloadArgs {
     /// Use mirror URL (for users in China)
     #[arg(long)]
     mirror: bool,
+
+    /// Also download the text super-resolution model (`super-resolution` feature)
+    #[arg(long)]
+    with_sr: bool,
 }
 
 #[derive(Args)]
@@ -77,6 +82,13 @@ struct StatusArgs {
     variant: Option<ModelVariant>,
 }
 
+#[derive(Args)]
+struct VerifyArgs {
+    /// Verify specific variant only
+    #[arg(short, long, value_enum)]
+    variant: Option<ModelVariant>,
+}
+
 #[derive(Args)]
 struct CleanArgs {
     /// Clean specific variant only
@@ -95,107 +107,51 @@ struct UseArgs {
     variant: ModelVariant,
 }
 
-/// Model information with download URLs.
-#[derive(Clone)]
-struct ModelInfo {
-    filename: &'static str,
-    size_bytes: u64,
-    description: &'static str,
-    url: &'static str,
-    mirror_url: &'static str,
-}
+#[derive(Args)]
+struct ExportArgs {
+    /// Variant to export (default: active variant)
+    #[arg(short, long, value_enum)]
+    variant: Option<ModelVariant>,
 
-/// Model variant configuration
-struct VariantConfig {
-    detection: ModelInfo,
-    recognition: ModelInfo,
-    dictionary: ModelInfo,
-    layout: Option<ModelInfo>,
-    table: Option<ModelInfo>,
+    /// Output archive (default: incr-models-<variant>.tar.zst)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 }
 
-fn get_variant_config(variant: ModelVariant) -> VariantConfig {
-    // Models are downloaded from: https://github.com/jakubmatias/incr/tree/main/models
-    match variant {
-        ModelVariant::Mobile => VariantConfig {
-            detection: ModelInfo {
-                filename: "det.onnx",
-                size_bytes: 4_500_000,
-                description: "PP-OCRv3 mobile detection",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/det.onnx",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/det.onnx",
-            },
-            recognition: ModelInfo {
-                filename: "latin_rec.onnx",
-                size_bytes: 7_500_000,
-                description: "Latin recognition",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_rec.onnx",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_rec.onnx",
-            },
-            dictionary: ModelInfo {
-                filename: "latin_dict.txt",
-                size_bytes: 2_000,
-                description: "Latin character dictionary",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_dict.txt",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_dict.txt",
-            },
-            layout: None,
-            table: None,
-        },
-        ModelVariant::Server => VariantConfig {
-            detection: ModelInfo {
-                filename: "det.onnx",
-                size_bytes: 84_000_000,
-                description: "PP-OCRv5 server detection",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/server/det.onnx",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/det.onnx",
-            },
-            recognition: ModelInfo {
-                filename: "latin_rec.onnx",
-                size_bytes: 7_500_000,
-                description: "Latin recognition",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_rec.onnx",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_rec.onnx",
-            },
-            dictionary: ModelInfo {
-                filename: "latin_dict.txt",
-                size_bytes: 2_000,
-                description: "Latin character dictionary",
-                url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_dict.txt",
-                mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_dict.txt",
-            },
-            layout: None,
-            table: None,
-        },
-    }
-}
+#[derive(Args)]
+struct ImportArgs {
+    /// Archive written by `incr models export`
+    archive: PathBuf,
 
-/// Get the model directory for a specific variant
-pub fn get_variant_dir(variant: ModelVariant) -> PathBuf {
-    dirs::data_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("incr")
-        .join("models")
-        .join(variant.to_string())
+    /// Make the imported variant the active one
+    #[arg(long = "use")]
+    activate: bool,
 }
 
-/// Get the active variant from config file
-pub fn get_active_variant() -> ModelVariant {
-    let config_path = dirs::data_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("incr")
-        .join("active_variant");
+impl From<ModelVariant> for downloader::ModelVariant {
+    fn from(variant: ModelVariant) -> Self {
+        match variant {
+            ModelVariant::Mobile => downloader::ModelVariant::Mobile,
+            ModelVariant::Server => downloader::ModelVariant::Server,
+            ModelVariant::Quantized => downloader::ModelVariant::Quantized,
+        }
+    }
+}
 
-    if let Ok(content) = fs::read_to_string(&config_path) {
-        match content.trim() {
-            "server" => ModelVariant::Server,
-            _ => ModelVariant::Mobile,
+impl From<downloader::ModelVariant> for ModelVariant {
+    fn from(variant: downloader::ModelVariant) -> Self {
+        match variant {
+            downloader::ModelVariant::Mobile => ModelVariant::Mobile,
+            downloader::ModelVariant::Server => ModelVariant::Server,
+            downloader::ModelVariant::Quantized => ModelVariant::Quantized,
         }
-    } else {
-        ModelVariant::Mobile
     }
 }
 
+fn get_variant_config(variant: ModelVariant) -> VariantModels {
+    downloader::ModelVariant::from(variant).models()
+}
+
 /// Set the active variant
 fn set_active_variant(variant: ModelVariant) -> anyhow::Result<()> {
     let config_dir = dirs::data_dir()
@@ -213,8 +169,11 @@ pub async fn run(args: ModelsArgs) -> anyhow::Result<()> {
         ModelsCommand::List => list_models(),
         ModelsCommand::Download(download_args) => download_models(download_args).await,
         ModelsCommand::Status(status_args) => check_status(status_args),
+        ModelsCommand::Verify(verify_args) => verify_models(verify_args),
         ModelsCommand::Clean(clean_args) => clean_models(clean_args),
         ModelsCommand::Use(use_args) => use_variant(use_args),
+        ModelsCommand::Export(export_args) => export_models(export_args),
+        ModelsCommand::Import(import_args) => import_models(import_args),
     }
 }
 
@@ -224,22 +183,17 @@ fn list_models() -> anyhow::Result<()> {
 
     let active = get_active_variant();
 
-    for variant in [ModelVariant::Mobile, ModelVariant::Server] {
+    for &variant in ModelVariant::value_variants() {
         let config = get_variant_config(variant);
         let is_active = variant == active;
         let active_marker = if is_active { " (active)" } else { "" };
 
-        let mut total_size = config.detection.size_bytes + config.recognition.size_bytes + config.dictionary.size_bytes;
-        if let Some(ref layout) = config.layout {
-            total_size += layout.size_bytes;
-        }
-        if let Some(ref table) = config.table {
-            total_size += table.size_bytes;
-        }
+        let total_size = config.size_bytes();
 
         let desc = match variant {
             ModelVariant::Mobile => "- faster, smaller",
             ModelVariant::Server => "- better detection accuracy",
+            ModelVariant::Quantized => "- INT8, fastest on CPUs, slightly less accurate",
         };
 
         println!(
@@ -283,7 +237,12 @@ fn list_models() -> anyhow::Result<()> {
     println!("Commands:");
     println!("  incr models download -v mobile    Download mobile models (~18MB)");
     println!("  incr models download -v server    Download server models (~103MB)");
+    println!("  incr models download -v quantized Download INT8 quantized models (~3MB)");
+    println!("  incr models download --with-sr    Also download the super-resolution model");
+    println!("  incr models verify                Check installed models against checksums");
     println!("  incr models use <variant>         Switch active variant");
+    println!("  incr models export                Bundle models for an offline machine");
+    println!("  incr models import <archive>      Install models from a bundle");
 
     Ok(())
 }
@@ -318,10 +277,8 @@ fn use_variant(args: UseArgs) -> anyhow::Result<()> {
 
 async fn download_models(args: DownloadArgs) -> anyhow::Result<()> {
     let variant = args.variant;
-    let config = get_variant_config(variant);
 
     let output_dir = args.output.unwrap_or_else(|| get_variant_dir(variant));
-    fs::create_dir_all(&output_dir)?;
 
     println!(
         "{} Downloading {} models to {}",
@@ -331,123 +288,70 @@ async fn download_models(args: DownloadArgs) -> anyhow::Result<()> {
     );
     println!();
 
-    let client = reqwest::Client::builder()
-        .user_agent("incr-cli/0.1.0")
-        .timeout(std::time::Duration::from_secs(300))
-        .build()?;
-
     let multi_progress = MultiProgress::new();
-    let mut success_count = 0;
-    let mut skip_count = 0;
-    let mut error_count = 0;
-
-    // Collect all models to download
-    let mut models: Vec<&ModelInfo> = vec![&config.detection, &config.recognition, &config.dictionary];
-    if let Some(ref layout) = config.layout {
-        models.push(layout);
-    }
-    if let Some(ref table) = config.table {
-        models.push(table);
-    }
-
-    for model in models {
-        let path = output_dir.join(model.filename);
-
-        // Check if already exists
-        if path.exists() && !args.force {
-            let metadata = fs::metadata(&path)?;
-            // Check if file size is reasonable (at least 50% of expected)
-            if metadata.len() > model.size_bytes / 2 {
-                println!(
-                    "  {} {} (already exists, {})",
-                    style("✓").green(),
-                    model.filename,
-                    format_size(metadata.len())
-                );
-                skip_count += 1;
-                continue;
+    let bars: Arc<Mutex<HashMap<&'static str, ProgressBar>>> = Arc::default();
+
+    let downloader = ModelDownloader::new(get_models_dir())
+        .with_mirror(args.mirror)
+        .with_force(args.force)
+        .with_super_resolution(args.with_sr)
+        .with_progress({
+            let multi_progress = multi_progress.clone();
+            let bars = Arc::clone(&bars);
+            move |progress: &DownloadProgress| {
+                let mut bars = bars.lock().unwrap();
+                let pb = bars
+                    .entry(progress.file)
+                    .or_insert_with(|| progress_bar(&multi_progress, progress.file));
+                pb.set_length(progress.total);
+                pb.set_position(progress.downloaded);
             }
-        }
+        });
 
-        // Select URL based on mirror flag
-        let url = if args.mirror {
-            model.mirror_url
-        } else {
-            model.url
-        };
+    let report = downloader.ensure_in(variant.into(), &output_dir).await?;
 
-        // Create progress bar
-        let pb = multi_progress.add(ProgressBar::new(model.size_bytes));
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("  {spinner:.green} {msg:<30} [{bar:25.cyan/blue}] {bytes}/{total_bytes}")
-                .unwrap()
-                .progress_chars("=>-"),
+    let mut bars = bars.lock().unwrap();
+    for file in &report.present {
+        let size = fs::metadata(output_dir.join(file)).map(|m| m.len()).unwrap_or(0);
+        println!(
+            "  {} {} (already exists, {})",
+            style("✓").green(),
+            file,
+            format_size(size)
         );
-        pb.set_message(model.filename.to_string());
-
-        // Download
-        match download_file(&client, url, &path, &pb).await {
-            Ok(()) => {
-                pb.finish_with_message(format!("{} {}", style("✓").green(), model.filename));
-                success_count += 1;
-            }
-            Err(e) => {
-                pb.finish_with_message(format!("{} {} - {}", style("✗").red(), model.filename, e));
-                error_count += 1;
-
-                // Try mirror if primary failed
-                if !args.mirror {
-                    println!(
-                        "    {} Trying mirror...",
-                        style("↻").yellow()
-                    );
-                    let pb2 = multi_progress.add(ProgressBar::new(model.size_bytes));
-                    pb2.set_style(
-                        ProgressStyle::default_bar()
-                            .template("    {spinner:.green} {msg:<28} [{bar:23.cyan/blue}] {bytes}/{total_bytes}")
-                            .unwrap()
-                            .progress_chars("=>-"),
-                    );
-                    pb2.set_message(format!("(mirror) {}", model.filename));
-
-                    match download_file(&client, model.mirror_url, &path, &pb2).await {
-                        Ok(()) => {
-                            pb2.finish_with_message(format!(
-                                "{} {} (from mirror)",
-                                style("✓").green(),
-                                model.filename
-                            ));
-                            error_count -= 1;
-                            success_count += 1;
-                        }
-                        Err(e2) => {
-                            pb2.finish_with_message(format!(
-                                "{} {} - mirror also failed: {}",
-                                style("✗").red(),
-                                model.filename,
-                                e2
-                            ));
-                        }
-                    }
-                }
-            }
+    }
+    let outcomes = report
+        .downloaded
+        .iter()
+        .map(|file| (file, format!("{} {}", style("✓").green(), file)))
+        .chain(
+            report
+                .failed
+                .iter()
+                .map(|(file, e)| (file, format!("{} {} - {}", style("✗").red(), file, e))),
+        );
+    for (file, message) in outcomes {
+        // Files that failed before any data arrived have no bar
+        match bars.remove(file) {
+            Some(pb) => pb.finish_with_message(message),
+            None => println!("  {}", message),
         }
     }
 
     println!();
 
     // Summary
-    if error_count == 0 {
+    if report.is_complete() {
         println!(
             "{} {} models downloaded successfully!",
             style("✓").green().bold(),
             variant
         );
-        if skip_count > 0 {
+        if !report.present.is_empty() {
             println!(
                 "   {} downloaded, {} already present",
-                success_count, skip_count
+                report.downloaded.len(),
+                report.present.len()
             );
         }
 
@@ -468,11 +372,13 @@ async fn download_models(args: DownloadArgs) -> anyhow::Result<()> {
         );
         println!(
             "   {} downloaded, {} skipped, {} failed",
-            success_count, skip_count, error_count
+            report.downloaded.len(),
+            report.present.len(),
+            report.failed.len()
         );
         println!();
         println!("For failed downloads, you can:");
-        println!("  1. Retry with: incr models download -v {} --force", variant);
+        println!("  1. Retry with: incr models download -v {}", variant);
         println!("  2. Try mirror: incr models download -v {} --mirror", variant);
     }
 
@@ -483,45 +389,16 @@ async fn download_models(args: DownloadArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn download_file(
-    client: &reqwest::Client,
-    url: &str,
-    path: &PathBuf,
-    pb: &ProgressBar,
-) -> anyhow::Result<()> {
-    let response = client.get(url).send().await?;
-
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP {}", response.status());
-    }
-
-    // Get content length if available
-    if let Some(content_length) = response.content_length() {
-        pb.set_length(content_length);
-    }
-
-    // Create temp file first
-    let temp_path = path.with_extension("tmp");
-    let mut file = File::create(&temp_path)?;
-
-    // Stream download with progress
-    let mut stream = response.bytes_stream();
-    let mut downloaded: u64 = 0;
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        file.write_all(&chunk)?;
-        downloaded += chunk.len() as u64;
-        pb.set_position(downloaded);
-    }
-
-    file.flush()?;
-    drop(file);
-
-    // Rename temp to final
-    fs::rename(&temp_path, path)?;
-
-    Ok(())
+fn progress_bar(multi_progress: &MultiProgress, file: &str) -> ProgressBar {
+    let pb = multi_progress.add(ProgressBar::new(0));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  {spinner:.green} {msg:<30} [{bar:25.cyan/blue}] {bytes}/{total_bytes}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb.set_message(file.to_string());
+    pb
 }
 
 fn check_status(args: StatusArgs) -> anyhow::Result<()> {
@@ -534,7 +411,7 @@ fn check_status(args: StatusArgs) -> anyhow::Result<()> {
     let variants: Vec<ModelVariant> = if let Some(v) = args.variant {
         vec![v]
     } else {
-        vec![ModelVariant::Mobile, ModelVariant::Server]
+        ModelVariant::value_variants().to_vec()
     };
 
     for variant in variants {
@@ -555,14 +432,7 @@ fn check_status(args: StatusArgs) -> anyhow::Result<()> {
             active_marker
         );
 
-        // Collect all models to check
-        let mut models: Vec<&ModelInfo> = vec![&config.detection, &config.recognition, &config.dictionary];
-        if let Some(ref layout) = config.layout {
-            models.push(layout);
-        }
-        if let Some(ref table) = config.table {
-            models.push(table);
-        }
+        let models = config.files();
 
         let mut all_present = true;
         let mut total_size: u64 = 0;
@@ -596,6 +466,19 @@ fn check_status(args: StatusArgs) -> anyhow::Result<()> {
             println!("    {} {:<25} {:>10}", status, model.filename, size_str);
         }
 
+        // Optional models are only listed when installed
+        let sr_path = model_dir.join(SUPER_RESOLUTION.filename);
+        if sr_path.exists() {
+            let size = fs::metadata(&sr_path)?.len();
+            total_size += size;
+            println!(
+                "    {} {:<25} {:>10}",
+                style("✓").green(),
+                SUPER_RESOLUTION.filename,
+                format_size(size)
+            );
+        }
+
         if all_present {
             println!(
                 "    {} Ready ({} total)",
@@ -615,9 +498,157 @@ fn check_status(args: StatusArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn verify_models(args: VerifyArgs) -> anyhow::Result<()> {
+    // Without a variant, every installed variant is verified
+    let variants: Vec<ModelVariant> = match args.variant {
+        Some(v) => vec![v],
+        None => ModelVariant::value_variants()
+            .iter()
+            .copied()
+            .filter(|&v| get_variant_dir(v).exists())
+            .collect(),
+    };
+    if variants.is_empty() {
+        println!("{} No models installed.", style("ℹ").blue());
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+
+    for variant in variants {
+        let model_dir = get_variant_dir(variant);
+        println!("{} {}", style(format!("▸ {}", variant)).bold(), model_dir.display());
+
+        for (file, check) in downloader::verify(variant.into(), &model_dir)? {
+            match check {
+                FileCheck::Valid => {
+                    println!("    {} {:<25} verified", style("✓").green(), file);
+                }
+                FileCheck::Unverified => {
+                    println!("    {} {:<25} no checksum", style("?").yellow(), file);
+                }
+                FileCheck::Missing => {
+                    println!("    {} {:<25} missing", style("✗").red(), file);
+                    failed.push(variant);
+                }
+                FileCheck::Corrupt { expected, actual } => {
+                    println!(
+                        "    {} {:<25} corrupt (SHA-256 {}, expected {})",
+                        style("✗").red(),
+                        file,
+                        actual,
+                        expected
+                    );
+                    failed.push(variant);
+                }
+            }
+        }
+        println!();
+    }
+
+    if let Some(variant) = failed.first() {
+        anyhow::bail!(
+            "{} model files failed verification. Run 'incr models download -v {}' to replace them.",
+            failed.len(),
+            variant
+        );
+    }
+
+    println!("{} All model files verified", style("✓").green());
+    Ok(())
+}
+
+fn export_models(args: ExportArgs) -> anyhow::Result<()> {
+    let variant = args.variant.unwrap_or_else(get_active_variant);
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("incr-models-{}.{}", variant, BUNDLE_EXTENSION)));
+
+    println!(
+        "{} Exporting {} models to {}",
+        style("ℹ").blue(),
+        style(variant.to_string()).cyan().bold(),
+        output.display()
+    );
+
+    let writer = BufWriter::new(File::create(&output)?);
+    let exported = match bundle::export(variant.into(), &get_variant_dir(variant), writer) {
+        Ok(exported) => exported,
+        Err(e) => {
+            // Don't leave a truncated archive behind
+            let _ = fs::remove_file(&output);
+            return Err(e.into());
+        }
+    };
+
+    for (file, entry) in &exported.manifest.files {
+        println!(
+            "  {} {:<25} {:>10}",
+            style("✓").green(),
+            file,
+            format_size(entry.size_bytes)
+        );
+    }
+    println!();
+    println!(
+        "{} Wrote {} ({})",
+        style("✓").green().bold(),
+        output.display(),
+        format_size(fs::metadata(&output)?.len())
+    );
+    println!("   Install it with: incr models import {}", output.display());
+
+    Ok(())
+}
+
+fn import_models(args: ImportArgs) -> anyhow::Result<()> {
+    println!(
+        "{} Importing models from {}",
+        style("ℹ").blue(),
+        args.archive.display()
+    );
+
+    let reader = BufReader::new(File::open(&args.archive)?);
+    let imported = bundle::import(reader, &get_models_dir())?;
+    let variant = ModelVariant::from(imported.variant);
+
+    for (file, entry) in &imported.manifest.files {
+        println!(
+            "  {} {:<25} {:>10}  verified",
+            style("✓").green(),
+            file,
+            format_size(entry.size_bytes)
+        );
+    }
+    println!();
+    println!(
+        "{} {} models installed to {}",
+        style("✓").green().bold(),
+        variant,
+        get_variant_dir(variant).display()
+    );
+
+    if args.activate {
+        set_active_variant(variant)?;
+        println!(
+            "{} Switched to {} models",
+            style("✓").green(),
+            style(variant.to_string()).cyan().bold()
+        );
+    } else if get_active_variant() != variant {
+        println!(
+            "{} To use these models, run: incr models use {}",
+            style("ℹ").blue(),
+            variant
+        );
+    }
+
+    Ok(())
+}
+
 fn clean_models(args: CleanArgs) -> anyhow::Result<()> {
     let variants: Vec<ModelVariant> = if args.all {
-        vec![ModelVariant::Mobile, ModelVariant::Server]
+        ModelVariant::value_variants().to_vec()
     } else if let Some(v) = args.variant {
         vec![v]
     } else {
@@ -647,13 +678,8 @@ fn clean_models(args: CleanArgs) -> anyhow::Result<()> {
         let config = get_variant_config(variant);
 
         // Collect all models to clean
-        let mut models: Vec<&ModelInfo> = vec![&config.detection, &config.recognition, &config.dictionary];
-        if let Some(ref layout) = config.layout {
-            models.push(layout);
-        }
-        if let Some(ref table) = config.table {
-            models.push(table);
-        }
+        let mut models: Vec<&ModelInfo> = config.files();
+        models.push(&SUPER_RESOLUTION);
 
         for model in models {
             let path = model_dir.join(model.filename);
@@ -666,6 +692,11 @@ fn clean_models(args: CleanArgs) -> anyhow::Result<()> {
             }
         }
 
+        let manifest = model_dir.join(MANIFEST_FILE);
+        if manifest.exists() {
+            fs::remove_file(&manifest)?;
+        }
+
         // Also remove any .tmp files
         if let Ok(entries) = fs::read_dir(&model_dir) {
             for entry in entries.flatten() {
@@ -0,0 +1,225 @@
+//! Parquet output for batch runs.
+//!
+//! Invoices and their line items are written as two tables that join on
+//! `file` and `invoice_number`. Amounts are decimals with 2 places
+//! (quantities and unit prices with 4), dates are Parquet dates and enums
+//! use their JSON names.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    BooleanBuilder, Date32Builder, Decimal128Builder, Float32Builder, StringBuilder,
+    UInt32Builder, UInt64Builder,
+};
+use arrow_array::types::Date32Type;
+use arrow_array::{ArrayRef, RecordBatch};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use incr_core::models::invoice::{Invoice, PaymentMethod};
+
+use super::json_name;
+
+/// Decimal precision of amount columns.
+const PRECISION: u8 = 18;
+/// Scale of money amounts.
+const MONEY_SCALE: i8 = 2;
+/// Scale of quantities and unit prices.
+const UNIT_SCALE: i8 = 4;
+
+/// Write invoices to `invoices_path` and their line items to `items_path`.
+pub fn write(
+    invoices: &[(&Path, &Invoice)],
+    invoices_path: &Path,
+    items_path: &Path,
+) -> anyhow::Result<()> {
+    write_batch(invoices_path, &invoice_batch(invoices)?)?;
+    write_batch(items_path, &line_item_batch(invoices)?)?;
+    Ok(())
+}
+
+fn write_batch(path: &Path, batch: &RecordBatch) -> anyhow::Result<()> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+fn invoice_batch(invoices: &[(&Path, &Invoice)]) -> anyhow::Result<RecordBatch> {
+    let mut file = StringBuilder::new();
+    let mut invoice_number = StringBuilder::new();
+    let mut invoice_type = StringBuilder::new();
+    let mut issue_date = Date32Builder::new();
+    let mut sale_date = Date32Builder::new();
+    let mut due_date = Date32Builder::new();
+    let mut currency = StringBuilder::new();
+    let mut ksef_number = StringBuilder::new();
+    let mut issuer_name = StringBuilder::new();
+    let mut issuer_nip = StringBuilder::new();
+    let mut issuer_vat_id = StringBuilder::new();
+    let mut receiver_name = StringBuilder::new();
+    let mut receiver_nip = StringBuilder::new();
+    let mut receiver_vat_id = StringBuilder::new();
+    let mut total_net = decimal_builder(MONEY_SCALE)?;
+    let mut total_vat = decimal_builder(MONEY_SCALE)?;
+    let mut total_gross = decimal_builder(MONEY_SCALE)?;
+    let mut amount_due = decimal_builder(MONEY_SCALE)?;
+    let mut payment_method = StringBuilder::new();
+    let mut line_items = UInt32Builder::new();
+    let mut self_invoice = BooleanBuilder::new();
+    let mut confidence = Float32Builder::new();
+    let mut incomplete = BooleanBuilder::new();
+    let mut source_type = StringBuilder::new();
+    let mut processing_time_ms = UInt64Builder::new();
+
+    for (path, invoice) in invoices {
+        let header = &invoice.header;
+        let summary = &invoice.summary;
+
+        file.append_value(file_name(path));
+        invoice_number.append_value(&header.invoice_number);
+        invoice_type.append_value(json_name(&header.invoice_type));
+        issue_date.append_option(header.issue_date.map(Date32Type::from_naive_date));
+        sale_date.append_option(header.sale_date.map(Date32Type::from_naive_date));
+        due_date.append_option(header.due_date.map(Date32Type::from_naive_date));
+        currency.append_value(&header.currency);
+        ksef_number.append_option(header.ksef_number.as_deref());
+        issuer_name.append_value(&invoice.issuer.name);
+        issuer_nip.append_option(invoice.issuer.nip.as_deref());
+        issuer_vat_id.append_option(invoice.issuer.vat_id.as_deref());
+        receiver_name.append_value(&invoice.receiver.name);
+        receiver_nip.append_option(invoice.receiver.nip.as_deref());
+        receiver_vat_id.append_option(invoice.receiver.vat_id.as_deref());
+        total_net.append_value(scaled(summary.total_net, MONEY_SCALE));
+        total_vat.append_value(scaled(summary.total_vat, MONEY_SCALE));
+        total_gross.append_value(scaled(summary.total_gross, MONEY_SCALE));
+        amount_due.append_option(summary.amount_due.map(|a| scaled(a, MONEY_SCALE)));
+        payment_method.append_option(summary.payment_method.as_ref().map(payment_method_name));
+        line_items.append_value(invoice.line_items.len() as u32);
+        self_invoice.append_value(header.self_invoice);
+        confidence.append_value(invoice.metadata.confidence);
+        incomplete.append_value(invoice.metadata.incomplete);
+        source_type.append_value(json_name(&invoice.metadata.source_type));
+        processing_time_ms.append_option(invoice.metadata.processing_time_ms);
+    }
+
+    Ok(RecordBatch::try_from_iter_with_nullable([
+        column("file", file.finish(), false),
+        column("invoice_number", invoice_number.finish(), false),
+        column("invoice_type", invoice_type.finish(), false),
+        column("issue_date", issue_date.finish(), true),
+        column("sale_date", sale_date.finish(), true),
+        column("due_date", due_date.finish(), true),
+        column("currency", currency.finish(), false),
+        column("ksef_number", ksef_number.finish(), true),
+        column("issuer_name", issuer_name.finish(), false),
+        column("issuer_nip", issuer_nip.finish(), true),
+        column("issuer_vat_id", issuer_vat_id.finish(), true),
+        column("receiver_name", receiver_name.finish(), false),
+        column("receiver_nip", receiver_nip.finish(), true),
+        column("receiver_vat_id", receiver_vat_id.finish(), true),
+        column("total_net", total_net.finish(), false),
+        column("total_vat", total_vat.finish(), false),
+        column("total_gross", total_gross.finish(), false),
+        column("amount_due", amount_due.finish(), true),
+        column("payment_method", payment_method.finish(), true),
+        column("line_items", line_items.finish(), false),
+        column("self_invoice", self_invoice.finish(), false),
+        column("confidence", confidence.finish(), false),
+        column("incomplete", incomplete.finish(), false),
+        column("source_type", source_type.finish(), false),
+        column("processing_time_ms", processing_time_ms.finish(), true),
+    ])?)
+}
+
+fn line_item_batch(invoices: &[(&Path, &Invoice)]) -> anyhow::Result<RecordBatch> {
+    let mut file = StringBuilder::new();
+    let mut invoice_number = StringBuilder::new();
+    let mut ordinal = UInt32Builder::new();
+    let mut description = StringBuilder::new();
+    let mut code = StringBuilder::new();
+    let mut quantity = decimal_builder(UNIT_SCALE)?;
+    let mut unit = StringBuilder::new();
+    let mut unit_price_net = decimal_builder(UNIT_SCALE)?;
+    let mut unit_price_gross = decimal_builder(UNIT_SCALE)?;
+    let mut vat_rate = StringBuilder::new();
+    let mut total_net = decimal_builder(MONEY_SCALE)?;
+    let mut vat_amount = decimal_builder(MONEY_SCALE)?;
+    let mut total_gross = decimal_builder(MONEY_SCALE)?;
+    let mut discount_percent = decimal_builder(MONEY_SCALE)?;
+
+    for (path, invoice) in invoices {
+        for item in &invoice.line_items {
+            file.append_value(file_name(path));
+            invoice_number.append_value(&invoice.header.invoice_number);
+            ordinal.append_option(item.ordinal);
+            description.append_value(&item.description);
+            code.append_option(item.code.as_deref());
+            quantity.append_value(scaled(item.quantity, UNIT_SCALE));
+            unit.append_option(item.unit.as_deref());
+            unit_price_net.append_value(scaled(item.unit_price_net, UNIT_SCALE));
+            unit_price_gross.append_option(item.unit_price_gross.map(|p| scaled(p, UNIT_SCALE)));
+            vat_rate.append_value(json_name(&item.vat_rate));
+            total_net.append_value(scaled(item.total_net, MONEY_SCALE));
+            vat_amount.append_value(scaled(item.vat_amount, MONEY_SCALE));
+            total_gross.append_value(scaled(item.total_gross, MONEY_SCALE));
+            discount_percent.append_option(item.discount_percent.map(|d| scaled(d, MONEY_SCALE)));
+        }
+    }
+
+    Ok(RecordBatch::try_from_iter_with_nullable([
+        column("file", file.finish(), false),
+        column("invoice_number", invoice_number.finish(), false),
+        column("ordinal", ordinal.finish(), true),
+        column("description", description.finish(), false),
+        column("code", code.finish(), true),
+        column("quantity", quantity.finish(), false),
+        column("unit", unit.finish(), true),
+        column("unit_price_net", unit_price_net.finish(), false),
+        column("unit_price_gross", unit_price_gross.finish(), true),
+        column("vat_rate", vat_rate.finish(), false),
+        column("total_net", total_net.finish(), false),
+        column("vat_amount", vat_amount.finish(), false),
+        column("total_gross", total_gross.finish(), false),
+        column("discount_percent", discount_percent.finish(), true),
+    ])?)
+}
+
+fn column<A: arrow_array::Array + 'static>(
+    name: &str,
+    array: A,
+    nullable: bool,
+) -> (&str, ArrayRef, bool) {
+    (name, Arc::new(array), nullable)
+}
+
+fn decimal_builder(scale: i8) -> anyhow::Result<Decimal128Builder> {
+    Ok(Decimal128Builder::new().with_precision_and_scale(PRECISION, scale)?)
+}
+
+/// Unscaled value of `value` rounded to `scale` decimal places.
+fn scaled(value: Decimal, scale: i8) -> i128 {
+    let scale = scale as u32;
+    let mut value = value.round_dp_with_strategy(scale, RoundingStrategy::MidpointAwayFromZero);
+    value.rescale(scale);
+    value.mantissa()
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(|s| s.to_str()).unwrap_or("")
+}
+
+fn payment_method_name(method: &PaymentMethod) -> String {
+    match method {
+        PaymentMethod::Other(description) => description.clone(),
+        method => json_name(method),
+    }
+}
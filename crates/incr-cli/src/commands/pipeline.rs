@@ -0,0 +1,175 @@
+//! In-memory document extraction shared by the server and scanner commands.
+
+use tracing::{debug, warn};
+
+use incr_core::invoice::barcodes::apply_barcodes;
+use incr_core::invoice::TextConfidence;
+use incr_core::models::capabilities::{Capabilities, Stage};
+use incr_core::models::config::IncrConfig;
+use incr_core::models::invoice::{Invoice, SourceType};
+use incr_core::ocr::Barcode;
+use incr_core::pdf::{PdfExtractor, PdfProcessor, PdfType};
+use incr_core::progress::{PageProgress, ProgressEvent, ProgressSink, ProgressStage};
+use incr_core::PureOcrEngine;
+
+use super::{embedded_invoice, merge_capabilities, parser_from_config};
+
+/// Extract an invoice from PDF or image bytes.
+pub fn extract_document(
+    data: &[u8],
+    engine: &PureOcrEngine,
+    config: &IncrConfig,
+    progress: &dyn ProgressSink,
+) -> anyhow::Result<Invoice> {
+    progress.report(ProgressEvent::new(ProgressStage::Load, 0, 1, "Loading document"));
+
+    let (text, source_type, capabilities, confidence, barcodes) = if data.starts_with(b"%PDF") {
+        let mut extractor = PdfExtractor::new();
+        extractor.load(data)?;
+        progress.report(ProgressEvent::new(ProgressStage::Load, 1, 1, "PDF loaded"));
+
+        if config.pdf.prefer_embedded_xml {
+            if let Some((invoice, _)) = embedded_invoice(&extractor) {
+                progress.report(ProgressEvent::new(ProgressStage::Done, 1, 1, "Done"));
+                return Ok(invoice);
+            }
+        }
+        extract_pdf_text(&extractor, engine, config, progress)?
+    } else {
+        let image = image::load_from_memory(data)
+            .map_err(|e| anyhow::anyhow!("Unsupported document (expected PDF or image): {}", e))?;
+        progress.report(ProgressEvent::new(ProgressStage::Load, 1, 1, "Image loaded"));
+
+        let result = engine
+            .process_with_progress(&image, progress)
+            .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+        let capabilities = merge_capabilities([&result.capabilities]);
+        let confidence = TextConfidence::from_ocr(&result);
+        let barcodes = vec![(None, result.barcodes)];
+        (result.text, SourceType::Image, capabilities, confidence, barcodes)
+    };
+
+    if text.trim().is_empty() {
+        anyhow::bail!("No text could be extracted from the document");
+    }
+
+    let parser =
+        parser_from_config(config)?.with_reference_date(chrono::Local::now().date_naive());
+
+    let mut invoice = parser
+        .parse_with_text_confidence(&text, &confidence, progress)?
+        .invoice;
+    for (page, barcodes) in &barcodes {
+        apply_barcodes(&mut invoice, *page, barcodes);
+    }
+    invoice.metadata.source_type = source_type;
+    invoice.metadata.capabilities = capabilities;
+
+    progress.report(ProgressEvent::new(ProgressStage::Done, 1, 1, "Done"));
+
+    Ok(invoice)
+}
+
+/// Text of a PDF with its source, the stages that ran, the OCR scores of
+/// its lines and the codes decoded on each page.
+type PdfText = (String, SourceType, Capabilities, TextConfidence, PageBarcodes);
+
+/// Codes decoded on each page (from 1; `None` for an image).
+type PageBarcodes = Vec<(Option<u32>, Vec<Barcode>)>;
+
+fn extract_pdf_text(
+    extractor: &PdfExtractor,
+    engine: &PureOcrEngine,
+    config: &IncrConfig,
+    progress: &dyn ProgressSink,
+) -> anyhow::Result<PdfText> {
+    progress.report(ProgressEvent::new(ProgressStage::Analyze, 0, 1, "Analyzing PDF"));
+    let pdf_type = extractor.analyze();
+    progress.report(ProgressEvent::new(
+        ProgressStage::Analyze,
+        1,
+        1,
+        format!("PDF type: {:?}", pdf_type),
+    ));
+    debug!("PDF type: {:?}", pdf_type);
+
+    let source_type = match pdf_type {
+        PdfType::Text => SourceType::TextPdf,
+        PdfType::Image => SourceType::ImagePdf,
+        PdfType::Hybrid => SourceType::HybridPdf,
+        PdfType::Empty => anyhow::bail!("PDF appears to be empty"),
+    };
+
+    if matches!(pdf_type, PdfType::Text | PdfType::Hybrid) && config.pdf.prefer_embedded_text {
+        progress.report(ProgressEvent::new(ProgressStage::TextExtraction, 0, 1, "Extracting text"));
+        let text = extractor.extract_text()?;
+        progress.report(ProgressEvent::new(ProgressStage::TextExtraction, 1, 1, "Text extracted"));
+
+        if pdf_type == PdfType::Text || text.len() >= config.pdf.min_text_length {
+            return Ok((
+                text,
+                source_type,
+                Capabilities::text_layer(),
+                TextConfidence::default(),
+                Vec::new(),
+            ));
+        }
+        warn!("Hybrid PDF has insufficient embedded text, falling back to OCR");
+    }
+
+    let page_count = extractor.page_count();
+    let mut pages = Vec::new();
+    for page in 1..=page_count {
+        match extractor.page_images(page, config.pdf.render_dpi) {
+            Ok(images) if !images.is_empty() => pages.push((page, images)),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to extract images from page {}: {}", page, e),
+        }
+    }
+
+    if pages.is_empty() {
+        return Ok((
+            extractor.extract_text()?,
+            source_type,
+            Capabilities::text_layer(),
+            TextConfidence::default(),
+            Vec::new(),
+        ));
+    }
+
+    let mut recognized = Vec::with_capacity(pages.len());
+    let mut page_capabilities = Vec::with_capacity(pages.len());
+    let mut barcodes = Vec::new();
+    for (index, (page, images)) in pages.iter().enumerate() {
+        debug!("OCR on page {} ({}/{})", page, index + 1, pages.len());
+        let page_progress = PageProgress::new(progress, index as u64, pages.len() as u64);
+        for image in images {
+            let result = engine
+                .process_with_progress(image, &page_progress)
+                .map_err(|e| anyhow::anyhow!("OCR failed on page {}: {}", page, e))?;
+            page_capabilities.push(result.capabilities.clone());
+            if !result.barcodes.is_empty() {
+                barcodes.push((Some(*page), result.barcodes.clone()));
+            }
+            if !result.text.trim().is_empty() {
+                recognized.push(result);
+            }
+        }
+    }
+
+    let mut capabilities = merge_capabilities(&page_capabilities);
+    let reason = match pdf_type {
+        PdfType::Image => "the PDF has no text layer",
+        _ if config.pdf.prefer_embedded_text => "the text layer is too short",
+        _ => "disabled by pdf.prefer_embedded_text",
+    };
+    capabilities.skipped(Stage::TextLayer, reason);
+
+    let text = recognized
+        .iter()
+        .map(|page| page.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let confidence = TextConfidence::from_pages(&text, &recognized.iter().collect::<Vec<_>>());
+    Ok((text, source_type, capabilities, confidence, barcodes))
+}
@@ -1,21 +1,54 @@
 //! Process command - extract data from a single invoice file.
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::Instant;
 
 use clap::Args;
 use console::style;
 use image::DynamicImage;
-use indicatif::{ProgressBar, ProgressStyle};
 use tracing::{debug, info, warn};
 
-use incr_core::models::config::IncrConfig;
-use incr_core::models::invoice::Invoice;
-use incr_core::invoice::{HybridInvoiceParser, InvoiceParser};
+use incr_core::models::capabilities::{Capabilities, Stage};
+use incr_core::jpk::JpkFa;
+use incr_core::models::config::{IncrConfig, OutputConfig, Preset};
+use incr_core::models::embedded::has_embedded_server_models;
+use incr_core::models::invoice::{Invoice, SourceType};
+use incr_core::models::language::Script;
+use incr_core::models::naming::FieldNaming;
+use incr_core::models::receipt::Receipt;
+use incr_core::models::selection::{PageSet, Region, Selection};
+use incr_core::models::validation::{IssueCode, Severity, ValidationIssue, ValidationProfile};
+use incr_core::invoice::barcodes::apply_barcodes;
+use incr_core::invoice::ensemble::vote_key_fields;
+use incr_core::invoice::{
+    HybridInvoiceParser, InvoiceParser, MultiPageParser, ReceiptParser, TextConfidence,
+};
+use incr_core::ocr::ensemble::{merge_results, DEFAULT_IOU_THRESHOLD};
+use incr_core::ocr::{
+    crop_regions, Barcode, OcrCheckpoint, OcrResult, RegionManifest, RegionManifestEntry,
+    TableStructure,
+};
 use incr_core::pdf::{PdfExtractor, PdfProcessor, PdfType};
-
-use super::models::{get_active_variant, get_variant_dir};
+use incr_core::progress::{PageProgress, ProgressSink};
+#[cfg(feature = "vies")]
+use incr_core::vies::ViesClient;
+#[cfg(feature = "gus")]
+use incr_core::{gus::GusClient, models::config::GusConfig};
+#[cfg(feature = "whitelist")]
+use incr_core::whitelist::WhitelistClient;
+use incr_core::PureOcrEngine;
+
+use super::audit::Auditor;
+use super::engines::shared_engine;
+use super::{embedded_invoice, file_date, load_config, merge_capabilities, parser_from_config};
+use super::variant::{
+    embedded_models, get_variant_dir, resolve_variant, variant_of_dir, ModelVariant,
+};
+use super::progress::{BarProgress, ProgressBar, ProgressStyle};
+use super::resources::check_memory;
 
 /// Arguments for the process command.
 #[derive(Args)]
@@ -32,6 +65,10 @@ pub struct ProcessArgs {
     #[arg(short, long, value_enum, default_value = "json")]
     format: OutputFormat,
 
+    /// Document type: invoice or receipt (paragon fiskalny)
+    #[arg(long, value_enum, default_value = "invoice")]
+    doc_type: DocType,
+
     /// Model directory
     #[arg(short, long)]
     model_dir: Option<PathBuf>,
@@ -40,6 +77,10 @@ pub struct ProcessArgs {
     #[arg(long)]
     text_only: bool,
 
+    /// Extract from the printed invoice even if the PDF embeds its XML
+    #[arg(long)]
+    ignore_attachments: bool,
+
     /// Show extraction confidence scores
     #[arg(long)]
     show_confidence: bool,
@@ -48,34 +89,227 @@ pub struct ProcessArgs {
     #[arg(long)]
     validate: bool,
 
+    /// Validation profile: lenient, strict or ksef (implies --validate)
+    #[arg(long, value_name = "PROFILE")]
+    validate_profile: Option<ValidationProfile>,
+
     /// Keep [UNK] tokens in OCR output instead of replacing with spaces
     #[arg(long)]
     keep_unk: bool,
+
+    /// Save crops of detected layout regions and a manifest.json to this directory
+    /// (needs an OCR engine with layout analysis)
+    #[arg(long, value_name = "DIR")]
+    export_regions: Option<PathBuf>,
+
+    /// JSON field names: snake_case, camel_case or legacy (overrides the config)
+    #[arg(long, value_name = "NAMING")]
+    field_naming: Option<FieldNaming>,
+
+    /// CSV output: one row per line item, with the invoice columns repeated
+    #[arg(long)]
+    include_line_items: bool,
+
+    /// Directory for per-page OCR checkpoints (default: <cache dir>/incr/checkpoints)
+    #[arg(long, value_name = "DIR")]
+    checkpoint_dir: Option<PathBuf>,
+
+    /// Don't checkpoint OCR results; an interrupted run starts over
+    #[arg(long, conflicts_with = "checkpoint_dir")]
+    no_checkpoint: bool,
+
+    /// Only process these PDF pages (e.g. 1-3,7)
+    #[arg(long, value_name = "PAGES")]
+    pages: Option<PageSet>,
+
+    /// Only recognize this area of each image, in its own pixels (x,y,w,h)
+    ///
+    /// For PDFs the region is in pixels of the page rendered at pdf.render_dpi,
+    /// and it is mapped onto every image wherever it is placed on the page.
+    /// Text PDFs are OCRed instead of reading their text layer.
+    #[arg(long, value_name = "X,Y,W,H", conflicts_with = "text_only")]
+    region: Option<Region>,
+
+    /// OCR with both the server and mobile models and vote on the result (slower)
+    #[arg(long, conflicts_with_all = ["text_only", "model_dir"])]
+    ensemble: bool,
+
+    /// Print the bank transfer QR code payload, or save the QR code as a PNG
+    #[arg(long, value_name = "PNG", num_args = 0..=1)]
+    emit_qr: Option<Option<PathBuf>>,
+
+    /// Check the NIPs and the bank account against the white list of VAT taxpayers
+    #[cfg(feature = "whitelist")]
+    #[arg(long)]
+    verify_whitelist: bool,
+
+    /// Check the EU VAT IDs of foreign parties in VIES
+    #[cfg(feature = "vies")]
+    #[arg(long)]
+    verify_vies: bool,
+
+    /// Fill in missing party names, REGONs and addresses from the GUS REGON register
+    #[cfg(feature = "gus")]
+    #[arg(long)]
+    enrich_gus: bool,
+}
+
+/// Text of a PDF, from embedded text or OCR.
+struct PdfText {
+    text: String,
+    /// Text of each page joined in `text`, when read page by page.
+    pages: Vec<String>,
+    /// Pages that could not be processed.
+    missing_pages: Vec<u32>,
+    /// Whether the text was recognized by OCR (and `--region` applied).
+    ocr: bool,
+    /// Pipeline stages that ran.
+    capabilities: Capabilities,
+    /// Text recognized by each engine with `--ensemble`.
+    runs: Vec<String>,
+    /// OCR scores of the lines of `text`.
+    confidence: TextConfidence,
+    /// Codes decoded on each page.
+    barcodes: Vec<(u32, Vec<Barcode>)>,
+}
+
+/// OCR engines that may still be loading on a background thread.
+///
+/// Model loading is started as soon as processing begins so it overlaps
+/// with reading and analyzing the input; the engines are only awaited when
+/// OCR is actually needed. Engines that are never used do not delay exit.
+/// With `--ensemble` there is one engine per model variant.
+struct EngineLoader {
+    model_dir: PathBuf,
+    pending: Option<JoinHandle<anyhow::Result<Vec<Arc<PureOcrEngine>>>>>,
+    engines: Vec<Arc<PureOcrEngine>>,
+}
+
+impl EngineLoader {
+    /// Start loading an engine for each model directory in the background.
+    /// The first directory is the one whose models are required.
+    fn prefetch(model_dirs: Vec<PathBuf>, config: &IncrConfig) -> Self {
+        let model_dir = model_dirs[0].clone();
+        let config = config.clone();
+
+        let handle = std::thread::spawn(move || {
+            let started = Instant::now();
+            let engines = model_dirs
+                .iter()
+                .map(|dir| shared_engine(dir, &config))
+                .collect();
+            debug!("OCR engines loaded in background in {:?}", started.elapsed());
+            engines
+        });
+
+        Self {
+            model_dir,
+            pending: Some(handle),
+            engines: Vec::new(),
+        }
+    }
+
+    /// Create a loader that never loads models (e.g. `--text-only`).
+    fn disabled(model_dir: PathBuf) -> Self {
+        Self {
+            model_dir,
+            pending: None,
+            engines: Vec::new(),
+        }
+    }
+
+    fn model_dir(&self) -> &Path {
+        &self.model_dir
+    }
+
+    /// Wait for the engines to finish loading.
+    async fn get(&mut self, pb: &ProgressBar) -> anyhow::Result<&[Arc<PureOcrEngine>]> {
+        if let Some(handle) = self.pending.take() {
+            pb.set_message("Loading OCR models...");
+            self.engines = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("OCR model loading was interrupted"))??;
+        }
+
+        if self.engines.is_empty() {
+            anyhow::bail!("OCR is disabled for this run");
+        }
+        Ok(&self.engines)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DocType {
+    /// VAT invoice
+    Invoice,
+    /// Fiscal receipt (paragon fiskalny)
+    Receipt,
 }
 
 #[derive(Clone, Copy, Debug, clap::ValueEnum)]
 pub enum OutputFormat {
     /// JSON output
     Json,
+    /// One JSON invoice per line in a single stream (batch only)
+    Ndjson,
     /// CSV output
     Csv,
     /// Plain text summary
     Text,
+    /// Detected table cells as CSV (debug, images only)
+    TableCsv,
+    /// Protobuf `incr.v1.Invoice` messages (batch only)
+    Proto,
+    /// Parquet tables of invoices and line items (batch only)
+    Parquet,
+    /// Excel workbook of invoices and line items (batch only)
+    Xlsx,
+    /// JPK_FA audit file XML (batch: one file for all invoices)
+    JpkFa,
+    /// UBL 2.1 invoice XML (PEPPOL BIS Billing 3.0)
+    Ubl,
+    /// ZUGFeRD/Factur-X CII invoice XML (EN 16931)
+    #[cfg(feature = "facturx")]
+    Facturx,
 }
 
-pub async fn run(args: ProcessArgs, config_path: Option<&str>) -> anyhow::Result<()> {
+pub async fn run(
+    args: ProcessArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
     let start = Instant::now();
 
     // Load configuration
-    let mut config = if let Some(path) = config_path {
-        IncrConfig::from_file(std::path::Path::new(path))?
-    } else {
-        IncrConfig::default()
-    };
+    let mut config = load_config(config_path, profile, preset, "process")?;
 
     if args.keep_unk {
         config.ocr.keep_unk = true;
     }
+    if let Some(naming) = args.field_naming {
+        config.output.field_naming = naming;
+    }
+    if args.include_line_items {
+        config.output.include_line_items = true;
+    }
+    #[cfg(feature = "gus")]
+    if args.enrich_gus && gus_api_key(&config.gus).is_none() {
+        anyhow::bail!("--enrich-gus needs an API key: set gus.api_key or INCR_GUS_API_KEY");
+    }
+
+    match args.format {
+        OutputFormat::Proto => anyhow::bail!("--format proto is only supported by the batch command"),
+        OutputFormat::Parquet => {
+            anyhow::bail!("--format parquet is only supported by the batch command")
+        }
+        OutputFormat::Ndjson => anyhow::bail!("--format ndjson is only supported by the batch command"),
+        OutputFormat::Xlsx => anyhow::bail!("--format xlsx is only supported by the batch command"),
+        _ => {}
+    }
+    if args.doc_type == DocType::Receipt && !matches!(args.format, OutputFormat::Json | OutputFormat::Text) {
+        anyhow::bail!("--doc-type receipt supports json and text output only");
+    }
 
     // Check input file exists
     if !args.input.exists() {
@@ -89,6 +323,10 @@ pub async fn run(args: ProcessArgs, config_path: Option<&str>) -> anyhow::Result
         .unwrap_or("")
         .to_lowercase();
 
+    if args.pages.is_some() && extension != "pdf" {
+        anyhow::bail!("--pages is only supported for PDF input");
+    }
+
     info!("Processing file: {}", args.input.display());
 
     // Create progress bar
@@ -100,38 +338,112 @@ pub async fn run(args: ProcessArgs, config_path: Option<&str>) -> anyhow::Result
             .progress_chars("##-"),
     );
 
-    let invoice = match extension.as_str() {
-        "pdf" => process_pdf(&args, &config, &pb).await?,
-        "png" | "jpg" | "jpeg" | "tiff" | "bmp" => process_image(&args, &config, &pb).await?,
+    // Start loading OCR models while the input is read and analyzed
+    let model_dirs = if args.ensemble {
+        let server = get_variant_dir(ModelVariant::Server);
+        if !server.join(&config.models.detection_model).exists()
+            && !has_embedded_server_models()
+        {
+            anyhow::bail!(
+                "--ensemble needs the server models.\n\n\
+                 Run 'incr models download -v server' to download them."
+            );
+        }
+        vec![server, get_variant_dir(ModelVariant::Mobile)]
+    } else {
+        vec![args.model_dir.clone().unwrap_or_else(|| {
+            get_variant_dir(resolve_variant(&config))
+        })]
+    };
+    let mut engine = if extension == "pdf" && args.text_only {
+        EngineLoader::disabled(model_dirs[0].clone())
+    } else {
+        EngineLoader::prefetch(model_dirs, &config)
+    };
+
+    if let OutputFormat::TableCsv = args.format {
+        let output = match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "tiff" | "bmp" => {
+                process_table_csv(&args, &mut engine, &pb).await?
+            }
+            _ => anyhow::bail!("--format table-csv supports image input only"),
+        };
+        pb.finish_with_message("Done");
+        return write_output(&args, &output);
+    }
+
+    if args.doc_type == DocType::Receipt {
+        let receipt = process_receipt(&args, &config, &mut engine, &pb, &extension).await?;
+        pb.finish_with_message("Done");
+        if args.validate && !receipt.metadata.warnings.is_empty() {
+            eprintln!("{}", style("Validation issues:").yellow());
+            for issue in &receipt.metadata.warnings {
+                let marker = match issue.severity {
+                    Severity::Error => style("error").red(),
+                    Severity::Warning => style("warning").yellow(),
+                };
+                eprintln!("  - [{}] {}: {}", marker, issue.field, issue.message);
+            }
+        }
+        let output = match args.format {
+            OutputFormat::Text => format_receipt_text(&receipt),
+            _ => serde_json::to_string(&config.output.field_naming.apply(&receipt))?,
+        };
+        return write_output(&args, &output);
+    }
+
+    let auditor = Auditor::open(&config, engine.model_dir())?;
+
+    let result = match extension.as_str() {
+        "pdf" => process_pdf(&args, &config, &mut engine, &pb).await,
+        "png" | "jpg" | "jpeg" | "tiff" | "bmp" => {
+            process_image(&args, &config, &mut engine, &pb).await
+        }
         _ => anyhow::bail!("Unsupported file format: {}", extension),
     };
 
+    if let Some(auditor) = &auditor {
+        let outcome = result.as_ref().map_err(|e| e.to_string());
+        auditor.file_extraction("process", &args.input, outcome)?;
+    }
+
+    let invoice = result?;
+    #[cfg(feature = "whitelist")]
+    let invoice = verify_whitelist(&args, invoice, &pb).await;
+    #[cfg(feature = "vies")]
+    let invoice = verify_vies(&args, invoice, &pb).await;
+    #[cfg(feature = "gus")]
+    let invoice = enrich_gus(&args, &config.gus, invoice, &pb).await;
+
     pb.finish_with_message("Done");
 
     // Validate if requested
-    if args.validate {
-        let issues = invoice.validate();
+    if args.validate || args.validate_profile.is_some() {
+        let profile = args.validate_profile.unwrap_or_default();
+        let issues = invoice.validate_profile(profile);
         if !issues.is_empty() {
-            eprintln!("{}", style("Validation issues:").yellow());
+            eprintln!(
+                "{}",
+                style(format!("Validation issues ({} profile):", profile)).yellow()
+            );
             for issue in &issues {
-                eprintln!("  - {}", issue);
+                let marker = match issue.severity {
+                    Severity::Error => style("error").red(),
+                    Severity::Warning => style("warning").yellow(),
+                };
+                eprintln!("  - [{}] {}: {}", marker, issue.field, issue.message);
             }
         }
     }
 
     // Format output
-    let output = format_invoice(&invoice, args.format)?;
+    let output = format_invoice(&invoice, args.format, &config.output)?;
 
     // Write output
-    if let Some(output_path) = &args.output {
-        fs::write(output_path, &output)?;
-        println!(
-            "{} Output written to {}",
-            style("✓").green(),
-            output_path.display()
-        );
-    } else {
-        println!("{}", output);
+    write_output(&args, &output)?;
+
+    if let Some(png) = &args.emit_qr {
+        emit_payment_qr(&invoice, png.as_deref())?;
     }
 
     // Show summary
@@ -156,11 +468,233 @@ pub async fn run(args: ProcessArgs, config_path: Option<&str>) -> anyhow::Result
     Ok(())
 }
 
+/// Look up the parties in the white list of VAT taxpayers if requested.
+/// A failed lookup leaves a warning: the extracted data is still valid.
+#[cfg(feature = "whitelist")]
+async fn verify_whitelist(args: &ProcessArgs, mut invoice: Invoice, pb: &ProgressBar) -> Invoice {
+    if args.verify_whitelist {
+        pb.set_message("Checking the white list...");
+        if let Err(e) = WhitelistClient::new().verify(&mut invoice).await {
+            warn!("White list check failed: {}", e);
+            invoice.metadata.warnings.push(ValidationIssue::warning(
+                IssueCode::WhitelistUnavailable,
+                "",
+                format!("White list check failed: {}", e),
+            ));
+        }
+    }
+    invoice
+}
+
+/// Look up the parties' EU VAT IDs in VIES if requested. A failed lookup
+/// leaves a warning, like [`verify_whitelist`].
+#[cfg(feature = "vies")]
+async fn verify_vies(args: &ProcessArgs, mut invoice: Invoice, pb: &ProgressBar) -> Invoice {
+    if args.verify_vies {
+        pb.set_message("Checking VIES...");
+        if let Err(e) = ViesClient::new().verify(&mut invoice).await {
+            warn!("VIES check failed: {}", e);
+            invoice.metadata.warnings.push(ValidationIssue::warning(
+                IssueCode::ViesUnavailable,
+                "",
+                format!("VIES check failed: {}", e),
+            ));
+        }
+    }
+    invoice
+}
+
+/// API key of the REGON register: `gus.api_key` or `INCR_GUS_API_KEY`.
+#[cfg(feature = "gus")]
+fn gus_api_key(config: &GusConfig) -> Option<String> {
+    config
+        .api_key
+        .clone()
+        .or_else(|| std::env::var("INCR_GUS_API_KEY").ok())
+        .filter(|key| !key.is_empty())
+}
+
+/// Fill in party details from the REGON register if requested. A failed
+/// lookup leaves a warning, like [`verify_whitelist`].
+#[cfg(feature = "gus")]
+async fn enrich_gus(
+    args: &ProcessArgs,
+    config: &GusConfig,
+    mut invoice: Invoice,
+    pb: &ProgressBar,
+) -> Invoice {
+    let Some(api_key) = gus_api_key(config).filter(|_| args.enrich_gus) else {
+        return invoice;
+    };
+
+    pb.set_message("Checking the REGON register...");
+    let mut client = GusClient::new(api_key);
+    if let Some(url) = &config.url {
+        client = client.with_base_url(url);
+    }
+    if let Err(e) = client.enrich(&mut invoice).await {
+        warn!("REGON lookup failed: {}", e);
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::RegonUnavailable,
+            "",
+            format!("REGON lookup failed: {}", e),
+        ));
+    }
+    invoice
+}
+
+/// Print the transfer QR payload to stderr, or save it as a QR code image.
+fn emit_payment_qr(invoice: &Invoice, png: Option<&Path>) -> anyhow::Result<()> {
+    let payload = invoice
+        .payment_qr_payload()
+        .map_err(|e| anyhow::anyhow!("Cannot build a transfer QR code: {}", e))?;
+
+    let Some(png) = png else {
+        eprintln!("{} Transfer QR payload: {}", style("ℹ").blue(), payload);
+        return Ok(());
+    };
+
+    #[cfg(feature = "payment-qr")]
+    {
+        let code = qrcode::QrCode::with_error_correction_level(&payload, qrcode::EcLevel::M)?;
+        let image = code
+            .render::<image::Luma<u8>>()
+            .min_dimensions(320, 320)
+            .build();
+        image.save(png)?;
+        eprintln!("{} Transfer QR code written to {}", style("✓").green(), png.display());
+        Ok(())
+    }
+    #[cfg(not(feature = "payment-qr"))]
+    anyhow::bail!(
+        "--emit-qr {} requires incr to be built with the 'payment-qr' feature",
+        png.display()
+    )
+}
+
+fn write_output(args: &ProcessArgs, output: &str) -> anyhow::Result<()> {
+    if let Some(output_path) = &args.output {
+        fs::write(output_path, output)?;
+        println!(
+            "{} Output written to {}",
+            style("✓").green(),
+            output_path.display()
+        );
+    } else {
+        println!("{}", output);
+    }
+
+    Ok(())
+}
+
 async fn process_pdf(
     args: &ProcessArgs,
     config: &IncrConfig,
+    engine: &mut EngineLoader,
     pb: &ProgressBar,
 ) -> anyhow::Result<Invoice> {
+    if let Some(invoice) = read_embedded_invoice(args, config, pb)? {
+        return Ok(invoice);
+    }
+
+    let (pdf_type, page_count, pdf) = read_pdf(args, config, engine, pb).await?;
+    let PdfText {
+        text,
+        pages,
+        missing_pages,
+        ocr,
+        capabilities,
+        runs,
+        confidence,
+        barcodes,
+    } = pdf;
+
+    pb.set_message("Extracting invoice data...");
+    pb.set_position(70);
+
+    let parser = parser_from_config(config)?.with_reference_date(file_date(&args.input));
+
+    // Line items continued onto the next pages need the page breaks
+    let result = if pages.len() > 1 {
+        let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+        MultiPageParser::new(parser.clone()).parse_pages_with_confidence(
+            &pages,
+            &confidence,
+            &BarProgress::new(pb),
+        )?
+    } else {
+        parser.parse_with_text_confidence(&text, &confidence, &BarProgress::new(pb))?
+    };
+    let mut invoice = result.invoice;
+    vote_ensemble(&parser, &mut invoice, &runs);
+    for (page, barcodes) in &barcodes {
+        apply_barcodes(&mut invoice, Some(*page), barcodes);
+    }
+
+    invoice.metadata.source_type = source_type(pdf_type);
+    invoice.metadata.capabilities = capabilities;
+
+    if !missing_pages.is_empty() {
+        let pages: Vec<String> = missing_pages.iter().map(u32::to_string).collect();
+        invoice.metadata.incomplete = true;
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::IncompletePages,
+            "",
+            format!(
+                "Incomplete result: OCR failed on page(s) {} of {}",
+                pages.join(", "),
+                page_count
+            ),
+        ));
+    }
+
+    let region = args.region.filter(|_| ocr);
+    if args.pages.is_some() || region.is_some() {
+        invoice.metadata.selection = Some(Selection {
+            pages: args.pages.clone(),
+            region,
+        });
+    }
+
+    pb.set_position(100);
+
+    Ok(invoice)
+}
+
+/// The invoice from the XML embedded in a hybrid e-invoice, unless
+/// disabled. `--pages` and `--region` select what to extract from the
+/// printed form, so they disable it too.
+fn read_embedded_invoice(
+    args: &ProcessArgs,
+    config: &IncrConfig,
+    pb: &ProgressBar,
+) -> anyhow::Result<Option<Invoice>> {
+    if args.ignore_attachments
+        || !config.pdf.prefer_embedded_xml
+        || args.pages.is_some()
+        || args.region.is_some()
+    {
+        return Ok(None);
+    }
+
+    pb.set_message("Reading attachments...");
+    let mut extractor = PdfExtractor::new();
+    extractor.load(&fs::read(&args.input)?)?;
+    let invoice = embedded_invoice(&extractor).map(|(invoice, _)| invoice);
+    if invoice.is_some() {
+        pb.set_position(100);
+    }
+    Ok(invoice)
+}
+
+/// Read the text of a PDF from its text layer or by OCR, with the PDF's
+/// type and page count.
+async fn read_pdf(
+    args: &ProcessArgs,
+    config: &IncrConfig,
+    engine: &mut EngineLoader,
+    pb: &ProgressBar,
+) -> anyhow::Result<(PdfType, u32, PdfText)> {
     pb.set_message("Loading PDF...");
     pb.set_position(10);
 
@@ -177,25 +711,40 @@ async fn process_pdf(
     let pdf_type = extractor.analyze();
     debug!("PDF type: {:?}", pdf_type);
 
-    let text = match pdf_type {
-        PdfType::Text | PdfType::Hybrid if config.pdf.prefer_embedded_text || args.text_only => {
+    if let Some(pages) = args.pages.as_ref().filter(|p| p.last() > page_count) {
+        anyhow::bail!("--pages {} is out of range, the PDF has {} pages", pages, page_count);
+    }
+
+    // A region can only be applied to page images, so it takes precedence
+    // over embedded text
+    let embedded_text = args.text_only || (config.pdf.prefer_embedded_text && args.region.is_none());
+
+    let mut pdf = match pdf_type {
+        PdfType::Text | PdfType::Hybrid if embedded_text => {
             pb.set_message("Extracting text...");
             pb.set_position(40);
-            let extracted = extractor.extract_text()?;
+            let extracted = extract_text(&extractor, args)?;
 
             // For hybrid PDFs, check if we got enough text
             if pdf_type == PdfType::Hybrid && extracted.len() < config.pdf.min_text_length {
                 warn!("Hybrid PDF has insufficient embedded text, falling back to OCR");
-                try_ocr_pdf(&extractor, args, config, pb).unwrap_or(extracted)
+                try_ocr_pdf(&extractor, &data, args, config, engine, pb)
+                    .await
+                    .unwrap_or_else(|_| PdfText::embedded(extracted))
+            } else if config.pdf.text_layout {
+                extract_layout(&extractor, args, config).unwrap_or_else(|e| {
+                    warn!("Failed to read the text layout, using plain text: {}", e);
+                    PdfText::embedded(extracted)
+                })
             } else {
-                extracted
+                PdfText::embedded(extracted)
             }
         }
-        PdfType::Image | PdfType::Hybrid if !args.text_only => {
+        PdfType::Image | PdfType::Hybrid | PdfType::Text if !args.text_only => {
             pb.set_message("Running OCR...");
             pb.set_position(40);
 
-            try_ocr_pdf(&extractor, args, config, pb)?
+            try_ocr_pdf(&extractor, &data, args, config, engine, pb).await?
         }
         PdfType::Empty => {
             anyhow::bail!("PDF appears to be empty");
@@ -206,53 +755,54 @@ async fn process_pdf(
         }
     };
 
-    if text.trim().is_empty() {
-        anyhow::bail!("No text could be extracted from the PDF");
+    if args.region.is_some() && !pdf.ocr {
+        warn!("--region was not applied, the text was extracted without OCR");
     }
 
-    pb.set_message("Extracting invoice data...");
-    pb.set_position(70);
-
-    let parser = HybridInvoiceParser::new()
-        .with_nip_validation(config.extraction.validate_nip)
-        .with_regon_validation(config.extraction.validate_regon)
-        .with_iban_validation(config.extraction.validate_iban);
-
-    let result = parser.parse(&text)?;
-    let mut invoice = result.invoice;
-
-    invoice.metadata.source_type = match pdf_type {
-        PdfType::Text => incr_core::models::invoice::SourceType::TextPdf,
-        PdfType::Image => incr_core::models::invoice::SourceType::ImagePdf,
-        PdfType::Hybrid => incr_core::models::invoice::SourceType::HybridPdf,
-        PdfType::Empty => incr_core::models::invoice::SourceType::Unknown,
-    };
+    if pdf.ocr {
+        let reason = match pdf_type {
+            PdfType::Image => "the PDF has no text layer",
+            _ if args.region.is_some() => "--region needs page images",
+            _ if embedded_text => "the text layer is too short",
+            _ => "disabled by pdf.prefer_embedded_text",
+        };
+        pdf.capabilities.skipped(Stage::TextLayer, reason);
+    }
 
-    pb.set_position(100);
+    if pdf.text.trim().is_empty() {
+        anyhow::bail!("No text could be extracted from the PDF");
+    }
 
-    Ok(invoice)
+    Ok((pdf_type, page_count, pdf))
 }
 
-/// Try to run OCR on a PDF by extracting images.
-fn try_ocr_pdf(
+/// OCR the images of every page, resuming from the page checkpoint.
+///
+/// Returns the recognized text and the pages that could not be processed.
+/// Pages are checkpointed as they complete; the checkpoint is removed once
+/// every page succeeded.
+async fn try_ocr_pdf(
     extractor: &PdfExtractor,
+    data: &[u8],
     args: &ProcessArgs,
     config: &IncrConfig,
+    engine: &mut EngineLoader,
     pb: &ProgressBar,
-) -> anyhow::Result<String> {
-    // Get model directory (use active variant if not specified)
-    let model_dir = args.model_dir.clone().unwrap_or_else(|| {
-        get_variant_dir(get_active_variant())
-    });
+) -> anyhow::Result<PdfText> {
+    let model_dir = engine.model_dir();
 
     // Check if models exist
     let det_model = model_dir.join(&config.models.detection_model);
-    let rec_model = model_dir.join(&config.models.recognition_model);
+    let rec_model = model_dir.join(config.models.recognition_files().0);
 
     if !det_model.exists() || !rec_model.exists() {
         // Fall back to text extraction if models not available
         warn!("OCR models not found at {}, falling back to text extraction", model_dir.display());
-        return Ok(extractor.extract_text()?);
+        let mut text = PdfText::embedded(extract_text(extractor, args)?);
+        let reason = format!("OCR models not found at {}", model_dir.display());
+        text.capabilities.failed(Stage::Detection, reason.clone());
+        text.capabilities.failed(Stage::Recognition, reason);
+        return Ok(text);
     }
 
     // Extract images from all PDF pages
@@ -260,77 +810,323 @@ fn try_ocr_pdf(
     pb.set_position(35);
 
     let page_count = extractor.page_count();
-    let mut all_images = Vec::new();
-
-    for page in 1..=page_count {
-        match extractor.extract_images(page) {
-            Ok(images) => all_images.extend(images),
+    let mut pages = Vec::new();
+    let mut missing_pages = Vec::new();
+
+    let selected = (1..=page_count).filter(|&p| args.pages.as_ref().is_none_or(|s| s.contains(p)));
+
+    for page in selected {
+        let dpi = config.pdf.render_dpi;
+        let images = match args.region {
+            Some(region) => match extractor.page_region_images(page, dpi, region) {
+                Ok(images) => Ok(images),
+                Err(e) => anyhow::bail!("cannot apply --region to page {}: {}", page, e),
+            },
+            None => extractor.page_images(page, dpi),
+        };
+        match images {
+            Ok(images) => {
+                if !images.is_empty() {
+                    pages.push((page, images));
+                }
+            }
             Err(e) => {
                 warn!("Failed to extract images from page {}: {}", page, e);
+                missing_pages.push(page);
             }
         }
     }
 
-    if all_images.is_empty() {
+    if pages.is_empty() {
+        if let Some(region) = args.region {
+            anyhow::bail!("--region {} lies outside every page image", region);
+        }
         warn!("No images found in PDF, falling back to text extraction");
-        return Ok(extractor.extract_text()?);
+        return Ok(PdfText::embedded(extract_text(extractor, args)?));
     }
 
-    debug!("Extracted {} images from PDF", all_images.len());
+    debug!("Extracted images from {} of {} PDF pages", pages.len(), page_count);
 
-    // Process each image with OCR
-    let mut all_text = Vec::new();
-    let total_images = all_images.len();
-
-    for (i, image) in all_images.iter().enumerate() {
-        pb.set_message(format!("OCR on image {}/{}", i + 1, total_images));
-        pb.set_position(40 + ((i as u64 * 25) / total_images as u64));
+    let mut checkpoint = open_checkpoint(args, data, config);
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|c| c.completed() > 0) {
+        info!(
+            "Resuming OCR: {} of {} pages restored from {}",
+            checkpoint.completed(),
+            pages.len(),
+            checkpoint.path().display()
+        );
+    }
 
-        // Run OCR on the image directly
-        match run_ocr(image, &model_dir, config, pb) {
-            Ok(text) if !text.trim().is_empty() => {
-                all_text.push(text);
+    let engines = engine.get(pb).await?;
+    check_region_export(args, engines)?;
+
+    // Process each page with OCR
+    let mut recognized_pages = Vec::new();
+    let mut run_texts = vec![Vec::new(); engines.len()];
+    let mut page_capabilities = Vec::new();
+    let mut barcodes = Vec::new();
+    let mut manifest = Vec::new();
+    let mut image_number = 0;
+
+    let bar = BarProgress::new(pb);
+    for (index, (page, images)) in pages.iter().enumerate() {
+        let progress = PageProgress::new(&bar, index as u64, pages.len() as u64);
+
+        let results = match checkpoint.as_ref().and_then(|c| c.page(*page)) {
+            Some(results) => {
+                debug!("Page {} restored from checkpoint", page);
+                results.to_vec()
             }
-            Ok(_) => {
-                debug!("No text detected in image {}", i + 1);
+            None => match images.iter().map(|image| run_ocr(image, engines, &progress)).collect::<anyhow::Result<Vec<_>>>() {
+                Ok(recognized) => {
+                    let (results, runs): (Vec<_>, Vec<_>) = recognized.into_iter().unzip();
+                    if let Some(checkpoint) = checkpoint.as_mut() {
+                        checkpoint.record(*page, &results)?;
+                    }
+                    for image_runs in runs {
+                        for (texts, run) in run_texts.iter_mut().zip(image_runs) {
+                            texts.push(run.text);
+                        }
+                    }
+                    results
+                }
+                Err(e) => {
+                    warn!("OCR failed for page {}: {}", page, e);
+                    missing_pages.push(*page);
+                    image_number += images.len() as u32;
+                    continue;
+                }
+            },
+        };
+
+        for (image, result) in images.iter().zip(results) {
+            image_number += 1;
+
+            if let Some(dir) = &args.export_regions {
+                export_regions(dir, &args.input, image, &result, *page, &mut manifest)?;
             }
-            Err(e) => {
-                warn!("OCR failed for image {}: {}", i + 1, e);
+
+            page_capabilities.push(result.capabilities.clone());
+            if !result.barcodes.is_empty() {
+                barcodes.push((*page, result.barcodes.clone()));
+            }
+
+            if !result.text.trim().is_empty() {
+                recognized_pages.push(result);
+            } else {
+                debug!("No text detected in image {}", image_number);
             }
         }
     }
 
-    if all_text.is_empty() {
+    if let Some(dir) = &args.export_regions {
+        write_region_manifest(dir, &args.input, manifest)?;
+    }
+
+    // Done with OCR: running again would find no more text
+    if let Some(checkpoint) = checkpoint.filter(|_| missing_pages.is_empty()) {
+        checkpoint.remove()?;
+    }
+
+    if recognized_pages.is_empty() {
         anyhow::bail!("No text detected in any PDF images");
     }
 
-    Ok(all_text.join("\n\n"))
+    let pages: Vec<String> = recognized_pages.iter().map(|page| page.text.clone()).collect();
+    let text = pages.join("\n\n");
+    let confidence = TextConfidence::from_pages(&text, &recognized_pages.iter().collect::<Vec<_>>());
+
+    missing_pages.sort_unstable();
+    Ok(PdfText {
+        text,
+        pages,
+        missing_pages,
+        ocr: true,
+        capabilities: merge_capabilities(&page_capabilities),
+        runs: run_texts
+            .iter()
+            .filter(|texts| !texts.is_empty())
+            .map(|texts| texts.join("\n\n"))
+            .collect(),
+        confidence,
+        barcodes,
+    })
+}
+
+impl PdfText {
+    fn embedded(text: String) -> Self {
+        Self {
+            text,
+            pages: Vec::new(),
+            missing_pages: Vec::new(),
+            ocr: false,
+            capabilities: Capabilities::text_layer(),
+            runs: Vec::new(),
+            confidence: TextConfidence::default(),
+            barcodes: Vec::new(),
+        }
+    }
+}
+
+fn source_type(pdf_type: PdfType) -> SourceType {
+    match pdf_type {
+        PdfType::Text => SourceType::TextPdf,
+        PdfType::Image => SourceType::ImagePdf,
+        PdfType::Hybrid => SourceType::HybridPdf,
+        PdfType::Empty => SourceType::Unknown,
+    }
+}
+
+/// Embedded text of the selected pages (all pages without `--pages`).
+fn extract_text(extractor: &PdfExtractor, args: &ProcessArgs) -> anyhow::Result<String> {
+    let Some(pages) = &args.pages else {
+        return Ok(extractor.extract_text()?);
+    };
+
+    let texts = pages
+        .pages()
+        .iter()
+        .map(|&page| extractor.extract_page_text(page))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(texts.join("\n"))
+}
+
+/// Embedded text of the selected pages with the positions of its lines.
+fn extract_layout(
+    extractor: &PdfExtractor,
+    args: &ProcessArgs,
+    config: &IncrConfig,
+) -> anyhow::Result<PdfText> {
+    let pages = match &args.pages {
+        Some(pages) => pages.pages().to_vec(),
+        None => (1..=extractor.page_count()).collect(),
+    };
+    let layouts = pages
+        .iter()
+        .map(|&page| extractor.extract_page_layout(page, config.pdf.render_dpi))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let pages: Vec<String> = layouts.iter().map(|layout| layout.text.clone()).collect();
+    let text = pages.join("\n\n");
+    let confidence = TextConfidence::from_layout(&text, &layouts.iter().collect::<Vec<_>>());
+    Ok(PdfText {
+        pages,
+        confidence,
+        ..PdfText::embedded(text)
+    })
+}
+
+/// The part of `image` inside `region`, or `None` if they don't overlap.
+fn crop_region(image: &DynamicImage, region: Region) -> Option<DynamicImage> {
+    if region.x >= image.width() || region.y >= image.height() {
+        return None;
+    }
+
+    // Clamped to the image bounds
+    Some(image.crop_imm(region.x, region.y, region.width, region.height))
+}
+
+/// Open the OCR checkpoint for a document, unless disabled. Failing to open
+/// it only costs the ability to resume, so it is not an error.
+fn open_checkpoint(args: &ProcessArgs, data: &[u8], config: &IncrConfig) -> Option<OcrCheckpoint> {
+    // Checkpoints hold merged results, but voting needs each engine's
+    if args.no_checkpoint || args.ensemble {
+        return None;
+    }
+
+    let dir = args.checkpoint_dir.clone().unwrap_or_else(|| {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("incr")
+            .join("checkpoints")
+    });
+
+    OcrCheckpoint::open(&dir, data, config, args.region)
+        .inspect_err(|e| warn!("OCR checkpoints disabled: {}", e))
+        .ok()
 }
 
 async fn process_image(
     args: &ProcessArgs,
     config: &IncrConfig,
+    engine: &mut EngineLoader,
     pb: &ProgressBar,
 ) -> anyhow::Result<Invoice> {
+    let (result, runs) = read_image(args, config, engine, pb).await?;
+    let confidence = TextConfidence::from_ocr(&result);
+    let capabilities = merge_capabilities([&result.capabilities]);
+    let barcodes = result.barcodes;
+    let text = result.text;
+
+    pb.set_message("Extracting invoice data...");
+    pb.set_position(70);
+
+    let parser = parser_from_config(config)?.with_reference_date(file_date(&args.input));
+
+    let result = parser.parse_with_text_confidence(&text, &confidence, &BarProgress::new(pb))?;
+    let mut invoice = result.invoice;
+    let runs: Vec<String> = runs.into_iter().map(|run| run.text).collect();
+    vote_ensemble(&parser, &mut invoice, &runs);
+    apply_barcodes(&mut invoice, None, &barcodes);
+
+    invoice.metadata.source_type = SourceType::Image;
+    invoice.metadata.capabilities = capabilities;
+
+    if let Some(region) = args.region {
+        invoice.metadata.selection = Some(Selection {
+            pages: None,
+            region: Some(region),
+        });
+    }
+
+    pb.set_position(100);
+
+    Ok(invoice)
+}
+
+/// OCR an image (or its `--region`), returning the result and, with
+/// `--ensemble`, the result of each model variant.
+async fn read_image(
+    args: &ProcessArgs,
+    config: &IncrConfig,
+    engine: &mut EngineLoader,
+    pb: &ProgressBar,
+) -> anyhow::Result<(OcrResult, Vec<OcrResult>)> {
     pb.set_message("Loading image...");
     pb.set_position(10);
 
-    let image = image::open(&args.input)?;
+    let mut image = image::open(&args.input)?;
+
+    if let Some(region) = args.region {
+        image = crop_region(&image, region).ok_or_else(|| {
+            anyhow::anyhow!(
+                "--region {} lies outside the image ({}x{})",
+                region,
+                image.width(),
+                image.height()
+            )
+        })?;
+    }
 
     pb.set_message("Running OCR...");
     pb.set_position(30);
 
-    // Get model directory (use active variant if not specified)
-    let model_dir = args.model_dir.clone().unwrap_or_else(|| {
-        get_variant_dir(get_active_variant())
-    });
+    let model_dir = engine.model_dir();
 
     // Check if models exist
     let det_model = model_dir.join(&config.models.detection_model);
-    let rec_model = model_dir.join(&config.models.recognition_model);
+    let rec_model = model_dir.join(config.models.recognition_files().0);
 
+    if det_model.exists() && !rec_model.exists() && config.models.script() != Script::Latin {
+        anyhow::bail!(
+            "{} recognition model {} not found in {}.",
+            config.models.script(),
+            config.models.recognition_files().0,
+            model_dir.display()
+        );
+    }
     if !det_model.exists() || !rec_model.exists() {
-        let active = get_active_variant();
+        let active = resolve_variant(config);
         anyhow::bail!(
             "OCR models not found at {}.\n\n\
              Run 'incr models download -v {}' to download {} models.",
@@ -341,63 +1137,243 @@ async fn process_image(
     }
 
     // Run OCR
-    let text = run_ocr(&image, &model_dir, config, pb)?;
+    pb.set_position(35);
+    let engines = engine.get(pb).await?;
+    check_region_export(args, engines)?;
+    let (result, runs) = run_ocr(&image, engines, &BarProgress::new(pb))?;
+
+    if let Some(dir) = &args.export_regions {
+        let mut manifest = Vec::new();
+        export_regions(dir, &args.input, &image, &result, 1, &mut manifest)?;
+        write_region_manifest(dir, &args.input, manifest)?;
+    }
 
-    if text.trim().is_empty() {
+    if result.text.trim().is_empty() {
         anyhow::bail!("No text detected in image");
     }
 
-    pb.set_message("Extracting invoice data...");
-    pb.set_position(70);
+    Ok((result, runs))
+}
 
-    let parser = HybridInvoiceParser::new()
-        .with_nip_validation(config.extraction.validate_nip)
-        .with_regon_validation(config.extraction.validate_regon)
-        .with_iban_validation(config.extraction.validate_iban);
+/// Extract a fiscal receipt from a PDF or image.
+async fn process_receipt(
+    args: &ProcessArgs,
+    config: &IncrConfig,
+    engine: &mut EngineLoader,
+    pb: &ProgressBar,
+    extension: &str,
+) -> anyhow::Result<Receipt> {
+    let (text, confidence, source_type, capabilities) = match extension {
+        "pdf" => {
+            let (pdf_type, _, pdf) = read_pdf(args, config, engine, pb).await?;
+            (pdf.text, pdf.confidence, source_type(pdf_type), pdf.capabilities)
+        }
+        "png" | "jpg" | "jpeg" | "tiff" | "bmp" => {
+            let (result, _) = read_image(args, config, engine, pb).await?;
+            let confidence = TextConfidence::from_ocr(&result);
+            let capabilities = merge_capabilities([&result.capabilities]);
+            (result.text, confidence, SourceType::Image, capabilities)
+        }
+        _ => anyhow::bail!("Unsupported file format: {}", extension),
+    };
 
-    let result = parser.parse(&text)?;
-    let mut invoice = result.invoice;
+    pb.set_message("Extracting receipt data...");
+    pb.set_position(70);
 
-    invoice.metadata.source_type = incr_core::models::invoice::SourceType::Image;
+    let mut receipt = ReceiptParser::new()
+        .with_nip_validation(config.extraction.validate_nip)
+        .parse_with_text_confidence(&text, &confidence)?;
+    receipt.metadata.source_type = source_type;
+    receipt.metadata.capabilities = capabilities;
 
     pb.set_position(100);
 
-    Ok(invoice)
+    Ok(receipt)
 }
 
-/// Run OCR on an image using embedded or external models.
-fn run_ocr(
+/// Save each detected layout region of a page image as a PNG file.
+fn export_regions(
+    dir: &Path,
+    input: &Path,
     image: &DynamicImage,
-    model_dir: &PathBuf,
-    config: &IncrConfig,
+    result: &OcrResult,
+    page: u32,
+    manifest: &mut Vec<RegionManifestEntry>,
+) -> anyhow::Result<()> {
+    let Some(layout) = &result.layout else {
+        warn!("No layout regions detected on page {}, nothing to export", page);
+        return Ok(());
+    };
+
+    fs::create_dir_all(dir)?;
+
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document");
+
+    // Numbered across the document, so several images of a page don't clash
+    let first = manifest.len();
+    for crop in crop_regions(image, layout) {
+        let file = format!(
+            "{}_p{}_{:03}_{}.png",
+            stem, page, manifest.len() + 1, crop.region.region_type
+        );
+        crop.image.save(dir.join(&file))?;
+
+        manifest.push(RegionManifestEntry {
+            file,
+            region_type: crop.region.region_type,
+            bbox: crop.region.bbox,
+            page,
+            confidence: crop.region.confidence,
+        });
+    }
+
+    debug!("Exported {} regions from page {}", manifest.len() - first, page);
+
+    Ok(())
+}
+
+/// Fail `--export-regions` before OCR when an engine has no layout
+/// analysis, instead of exporting nothing.
+fn check_region_export(args: &ProcessArgs, engines: &[Arc<PureOcrEngine>]) -> anyhow::Result<()> {
+    if args.export_regions.is_none() {
+        return Ok(());
+    }
+    for engine in engines {
+        let capabilities = engine.capabilities();
+        let status = capabilities.status(Stage::Layout);
+        if let Some(reason) = status.and_then(|status| status.reason.as_deref()) {
+            anyhow::bail!("--export-regions needs layout analysis ({})", reason);
+        }
+    }
+    Ok(())
+}
+
+/// Write the region manifest for a document next to its crops.
+fn write_region_manifest(
+    dir: &Path,
+    input: &Path,
+    regions: Vec<RegionManifestEntry>,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let manifest = RegionManifest {
+        source: input.display().to_string(),
+        regions,
+    };
+    let path = dir.join("manifest.json");
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+
+    eprintln!(
+        "{} Exported {} regions to {}",
+        style("✓").green(),
+        manifest.regions.len(),
+        dir.display()
+    );
+
+    Ok(())
+}
+
+/// Run OCR on an image and export the detected tables as CSV.
+///
+/// Uses layout table regions when available, otherwise treats all
+/// text boxes as a single table. Tables are separated by a blank line.
+async fn process_table_csv(
+    args: &ProcessArgs,
+    engine: &mut EngineLoader,
     pb: &ProgressBar,
 ) -> anyhow::Result<String> {
-    use incr_core::{create_engine_from_dir, create_engine_from_embedded};
+    pb.set_message("Loading image...");
+    pb.set_position(10);
+
+    let image = image::open(&args.input)?;
 
-    pb.set_message("Loading OCR models...");
     pb.set_position(35);
+    let (result, _) = run_ocr(&image, engine.get(pb).await?, &BarProgress::new(pb))?;
+
+    if result.boxes.is_empty() {
+        anyhow::bail!("No text detected in image");
+    }
+
+    pb.set_message("Building tables...");
+    pb.set_position(80);
+
+    let tables: Vec<TableStructure> = match &result.layout {
+        Some(layout) if !layout.tables.is_empty() => {
+            layout.tables.iter().map(|region| region.table(&result.boxes)).collect()
+        }
+        _ => vec![TableStructure::from_text_boxes(&result.boxes)],
+    };
+
+    debug!("Exporting {} table(s) as CSV", tables.len());
+
+    pb.set_position(100);
+
+    Ok(tables
+        .iter()
+        .map(|t| t.to_csv())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Load the OCR engine from external models, falling back to embedded ones.
+pub fn load_engine(model_dir: &Path, config: &IncrConfig) -> anyhow::Result<PureOcrEngine> {
+    use incr_core::create_engine_from_embedded_models;
+
+    check_memory(model_dir, config);
+
+    // Only Latin recognition models are embedded
+    let script = config.models.script();
+    let (rec_model, dictionary) = config.models.recognition_files();
+    if script != Script::Latin && !model_dir.join(&rec_model).exists() {
+        anyhow::bail!(
+            "{} text needs {} and {} in {}",
+            script,
+            rec_model,
+            dictionary,
+            model_dir.display()
+        );
+    }
 
     // Try external models first if model_dir exists, otherwise use embedded
     let det_model = model_dir.join(&config.models.detection_model);
     let engine = if det_model.exists() {
-        debug!("Using external models from {}", model_dir.display());
-        create_engine_from_dir(model_dir, config.ocr.clone())
+        debug!("Using external models from {} ({} recognition)", model_dir.display(), script);
+        PureOcrEngine::from_dir_with_files(model_dir, &rec_model, &dictionary, config.ocr.clone())
             .map_err(|e| anyhow::anyhow!("Failed to load OCR models: {}", e))?
     } else {
-        debug!("Using embedded mobile models");
-        create_engine_from_embedded(config.ocr.clone())
+        // A variant's own directory falls back to that variant's models
+        let variant = variant_of_dir(model_dir).unwrap_or_else(|| resolve_variant(config));
+        let (variant, models) = embedded_models(variant);
+        debug!("Using embedded {} models", variant);
+        create_engine_from_embedded_models(models, config.ocr.clone())
             .map_err(|e| anyhow::anyhow!("Failed to load embedded OCR models: {}", e))?
     };
 
-    pb.set_message("Detecting text regions...");
-    pb.set_position(45);
+    Ok(engine)
+}
 
-    let result = engine
-        .process(image)
+/// Run OCR on an image with the loaded engines.
+///
+/// With several engines (`--ensemble`) their results are merged, and the
+/// result of each engine is returned as well.
+fn run_ocr(
+    image: &DynamicImage,
+    engines: &[Arc<PureOcrEngine>],
+    progress: &dyn ProgressSink,
+) -> anyhow::Result<(OcrResult, Vec<OcrResult>)> {
+    let mut runs = engines
+        .iter()
+        .map(|engine| engine.process_with_progress(image, progress))
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
 
-    pb.set_message("OCR complete");
-    pb.set_position(60);
+    let result = match runs.len() {
+        1 => runs.remove(0),
+        _ => merge_results(&runs, DEFAULT_IOU_THRESHOLD),
+    };
 
     debug!(
         "OCR detected {} text boxes in {}ms",
@@ -405,13 +1381,36 @@ fn run_ocr(
         result.processing_time_ms
     );
 
-    Ok(result.text)
+    Ok((result, runs))
+}
+
+/// Vote on the key fields with the extraction of each engine's text.
+fn vote_ensemble(parser: &HybridInvoiceParser, invoice: &mut Invoice, runs: &[String]) {
+    if runs.is_empty() {
+        return;
+    }
+
+    // A run that found no invoice data simply casts no votes
+    let extractions: Vec<Invoice> = runs
+        .iter()
+        .filter_map(|text| parser.parse(text).ok())
+        .map(|result| result.invoice)
+        .collect();
+    vote_key_fields(invoice, &extractions);
+    invoice.metadata.ocr_engine = Some(format!("ensemble of {} model variants", runs.len()));
 }
 
-fn format_invoice(invoice: &Invoice, format: OutputFormat) -> anyhow::Result<String> {
+pub fn format_invoice(
+    invoice: &Invoice,
+    format: OutputFormat,
+    output: &OutputConfig,
+) -> anyhow::Result<String> {
     match format {
         OutputFormat::Json => {
-            Ok(serde_json::to_string(invoice)?)
+            Ok(serde_json::to_string(&output.field_naming.apply(invoice))?)
+        }
+        OutputFormat::Csv if output.include_line_items => {
+            format_line_items_csv(invoice)
         }
         OutputFormat::Csv => {
             format_csv(invoice)
@@ -419,10 +1418,111 @@ fn format_invoice(invoice: &Invoice, format: OutputFormat) -> anyhow::Result<Str
         OutputFormat::Text => {
             format_text(invoice)
         }
+        OutputFormat::TableCsv => {
+            anyhow::bail!("table-csv output does not apply to invoices")
+        }
+        OutputFormat::Proto => {
+            anyhow::bail!("proto output is only supported by the batch command")
+        }
+        OutputFormat::Parquet => {
+            anyhow::bail!("parquet output is only supported by the batch command")
+        }
+        OutputFormat::Ndjson => {
+            anyhow::bail!("ndjson output is only supported by the batch command")
+        }
+        OutputFormat::Xlsx => {
+            anyhow::bail!("xlsx output is only supported by the batch command")
+        }
+        OutputFormat::JpkFa => {
+            Ok(JpkFa::new(output.jpk.clone()).write(std::slice::from_ref(invoice))?)
+        }
+        OutputFormat::Ubl => {
+            Ok(invoice.to_ubl_xml()?)
+        }
+        #[cfg(feature = "facturx")]
+        OutputFormat::Facturx => {
+            Ok(invoice.to_facturx_xml()?)
+        }
+    }
+}
+
+/// Long-format CSV: one row per line item, with the invoice number, date,
+/// parties and currency repeated. An invoice without line items gets one
+/// row with empty item columns.
+pub fn format_line_items_csv(invoice: &Invoice) -> anyhow::Result<String> {
+    let Some(issue_date) = invoice.header.issue_date else {
+        anyhow::bail!(
+            "Invoice {} has no issue date; refusing to export it as CSV",
+            invoice.header.invoice_number
+        );
+    };
+
+    let mut wtr = csv::Writer::from_writer(vec![]);
+
+    wtr.write_record([
+        "invoice_number",
+        "issue_date",
+        "issuer_name",
+        "issuer_nip",
+        "receiver_name",
+        "receiver_nip",
+        "currency",
+        "ordinal",
+        "description",
+        "code",
+        "quantity",
+        "unit",
+        "unit_price_net",
+        "unit_price_gross",
+        "vat_rate",
+        "total_net",
+        "vat_amount",
+        "total_gross",
+    ])?;
+
+    let invoice_columns = [
+        invoice.header.invoice_number.clone(),
+        issue_date.to_string(),
+        invoice.issuer.name.clone(),
+        invoice.issuer.nip.clone().unwrap_or_default(),
+        invoice.receiver.name.clone(),
+        invoice.receiver.nip.clone().unwrap_or_default(),
+        invoice.header.currency.clone(),
+    ];
+
+    if invoice.line_items.is_empty() {
+        let empty_item = std::iter::repeat_n(String::new(), 11);
+        wtr.write_record(invoice_columns.iter().cloned().chain(empty_item))?;
     }
+
+    for item in &invoice.line_items {
+        let item_columns = [
+            item.ordinal.map(|o| o.to_string()).unwrap_or_default(),
+            item.description.clone(),
+            item.code.clone().unwrap_or_default(),
+            item.quantity.to_string(),
+            item.unit.clone().unwrap_or_default(),
+            item.unit_price_net.to_string(),
+            item.unit_price_gross.map(|p| p.to_string()).unwrap_or_default(),
+            super::json_name(&item.vat_rate),
+            item.total_net.to_string(),
+            item.vat_amount.to_string(),
+            item.total_gross.to_string(),
+        ];
+        wtr.write_record(invoice_columns.iter().cloned().chain(item_columns))?;
+    }
+
+    Ok(String::from_utf8(wtr.into_inner()?)?)
 }
 
 fn format_csv(invoice: &Invoice) -> anyhow::Result<String> {
+    let Some(issue_date) = invoice.header.issue_date else {
+        anyhow::bail!(
+            "Invoice {} has no issue date; refusing to export it as CSV",
+            invoice.header.invoice_number
+        );
+    };
+
     let mut wtr = csv::Writer::from_writer(vec![]);
 
     // Write header
@@ -442,7 +1542,7 @@ fn format_csv(invoice: &Invoice) -> anyhow::Result<String> {
     // Write data
     wtr.write_record([
         &invoice.header.invoice_number,
-        &invoice.header.issue_date.to_string(),
+        &issue_date.to_string(),
         &invoice.issuer.name,
         &invoice.issuer.nip.clone().unwrap_or_default(),
         &invoice.receiver.name,
@@ -457,11 +1557,57 @@ fn format_csv(invoice: &Invoice) -> anyhow::Result<String> {
     Ok(data)
 }
 
+fn format_receipt_text(receipt: &Receipt) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Receipt: {}\n", receipt.seller.name));
+    if let Some(nip) = &receipt.seller.nip {
+        output.push_str(&format!("  NIP: {}\n", nip));
+    }
+    match (receipt.issue_date, receipt.issue_time) {
+        (Some(date), Some(time)) => output.push_str(&format!("Date: {} {}\n", date, time.format("%H:%M"))),
+        (Some(date), None) => output.push_str(&format!("Date: {}\n", date)),
+        (None, _) => output.push_str("Date: MISSING\n"),
+    }
+    if let Some(nip) = &receipt.buyer_nip {
+        output.push_str(&format!("Buyer NIP: {}\n", nip));
+    }
+
+    output.push_str("\nItems:\n");
+    for item in &receipt.items {
+        output.push_str(&format!(
+            "  {} {} x {} = {} {}\n",
+            item.description, item.quantity, item.unit_price, item.total, item.tax_group
+        ));
+    }
+
+    output.push_str("\nPTU:\n");
+    for group in &receipt.tax_groups {
+        output.push_str(&format!(
+            "  {} {}: sales {}, PTU {}\n",
+            group.group,
+            group.rate.display(),
+            group.sales,
+            group.vat
+        ));
+    }
+    output.push_str(&format!("  Total PTU: {} {}\n", receipt.total_vat, receipt.currency));
+    output.push_str(&format!("\nTotal: {} {}\n", receipt.total, receipt.currency));
+    if let Some(number) = &receipt.fiscal_number {
+        output.push_str(&format!("Cash register: {}\n", number));
+    }
+
+    output
+}
+
 fn format_text(invoice: &Invoice) -> anyhow::Result<String> {
     let mut output = String::new();
 
     output.push_str(&format!("Invoice: {}\n", invoice.header.invoice_number));
-    output.push_str(&format!("Date: {}\n", invoice.header.issue_date));
+    match invoice.header.issue_date {
+        Some(date) => output.push_str(&format!("Date: {}\n", date)),
+        None => output.push_str("Date: MISSING\n"),
+    }
     output.push_str("\n");
 
     output.push_str("Issuer:\n");
@@ -469,6 +1615,9 @@ fn format_text(invoice: &Invoice) -> anyhow::Result<String> {
     if let Some(nip) = &invoice.issuer.nip {
         output.push_str(&format!("  NIP: {}\n", nip));
     }
+    if let Some(vat_id) = &invoice.issuer.vat_id {
+        output.push_str(&format!("  VAT ID: {}\n", vat_id));
+    }
     output.push_str(&format!("  {}\n", invoice.issuer.address.format()));
     output.push_str("\n");
 
@@ -477,16 +1626,56 @@ fn format_text(invoice: &Invoice) -> anyhow::Result<String> {
     if let Some(nip) = &invoice.receiver.nip {
         output.push_str(&format!("  NIP: {}\n", nip));
     }
+    if let Some(vat_id) = &invoice.receiver.vat_id {
+        output.push_str(&format!("  VAT ID: {}\n", vat_id));
+    }
     output.push_str("\n");
 
     output.push_str("Summary:\n");
     output.push_str(&format!("  Net:   {} {}\n", invoice.summary.total_net, invoice.header.currency));
     output.push_str(&format!("  VAT:   {} {}\n", invoice.summary.total_vat, invoice.header.currency));
     output.push_str(&format!("  Gross: {} {}\n", invoice.summary.total_gross, invoice.header.currency));
+    if let Some(info) = &invoice.summary.currency_info {
+        output.push_str(&format!(
+            "  Rate:  {} PLN/{} (VAT {} PLN, gross {} PLN)\n",
+            info.exchange_rate, invoice.header.currency, info.total_vat_pln, info.total_gross_pln
+        ));
+    }
 
     if let Some(due_date) = invoice.header.due_date {
         output.push_str(&format!("\nPayment due: {}\n", due_date));
     }
 
+    if !invoice.metadata.whitelist.is_empty() {
+        output.push_str("\nWhite list:\n");
+        for check in &invoice.metadata.whitelist {
+            let status = check.vat_status.as_deref().unwrap_or("not registered");
+            let account = match check.account_assigned {
+                Some(true) => ", account assigned",
+                Some(false) => ", account NOT assigned",
+                None => "",
+            };
+            output.push_str(&format!(
+                "  {} {}: {}{} (request {})\n",
+                check.party, check.nip, status, account, check.request_id
+            ));
+        }
+    }
+
+    if !invoice.metadata.vies.is_empty() {
+        output.push_str("\nVIES:\n");
+        for check in &invoice.metadata.vies {
+            let status = if check.valid { "valid" } else { "not registered" };
+            let name = check.name.as_deref().map(|name| format!(" ({})", name));
+            output.push_str(&format!(
+                "  {} {}: {}{}\n",
+                check.party,
+                check.vat_id,
+                status,
+                name.unwrap_or_default()
+            ));
+        }
+    }
+
     Ok(output)
 }
@@ -0,0 +1,130 @@
+//! Progress bar adapter for core progress events.
+//!
+//! Without the `progress-bars` feature the bar types are replaced by silent
+//! stand-ins with the same interface that only log messages at debug level.
+
+#[cfg(feature = "progress-bars")]
+pub use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+#[cfg(not(feature = "progress-bars"))]
+pub use plain::{MultiProgress, ProgressBar, ProgressStyle};
+
+use incr_core::progress::{ProgressEvent, ProgressSink, ProgressStage};
+
+/// Drives a 0-100 progress bar from core [`ProgressEvent`]s.
+///
+/// Each stage owns a band of the bar; events move the bar within that band
+/// and never backwards, so multi-page documents don't make it jump around.
+pub struct BarProgress<'a> {
+    bar: &'a ProgressBar,
+}
+
+impl<'a> BarProgress<'a> {
+    pub fn new(bar: &'a ProgressBar) -> Self {
+        Self { bar }
+    }
+}
+
+impl ProgressSink for BarProgress<'_> {
+    fn report(&self, event: ProgressEvent) {
+        let (start, end) = stage_band(event.stage);
+        let position = start + ((end - start) as f32 * event.fraction()) as u64;
+
+        if position > self.bar.position() {
+            self.bar.set_position(position);
+        }
+        self.bar.set_message(event.message);
+    }
+}
+
+/// Bar range (in percent) covered by each stage.
+fn stage_band(stage: ProgressStage) -> (u64, u64) {
+    match stage {
+        ProgressStage::Load => (0, 10),
+        ProgressStage::Analyze => (10, 20),
+        ProgressStage::TextExtraction => (20, 40),
+        ProgressStage::Detection => (35, 45),
+        ProgressStage::Recognition => (45, 60),
+        ProgressStage::Layout => (60, 65),
+        ProgressStage::Parsing => (70, 100),
+        ProgressStage::Done => (100, 100),
+    }
+}
+
+#[cfg(not(feature = "progress-bars"))]
+mod plain {
+    use std::borrow::Cow;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use tracing::debug;
+
+    /// Progress bar that draws nothing.
+    pub struct ProgressBar {
+        position: AtomicU64,
+    }
+
+    impl ProgressBar {
+        pub fn new(_len: u64) -> Self {
+            Self {
+                position: AtomicU64::new(0),
+            }
+        }
+
+        pub fn set_style(&self, _style: ProgressStyle) {}
+
+        pub fn set_message(&self, message: impl Into<Cow<'static, str>>) {
+            debug!("{}", message.into());
+        }
+
+        pub fn set_prefix(&self, _prefix: impl Into<Cow<'static, str>>) {}
+
+        pub fn position(&self) -> u64 {
+            self.position.load(Ordering::Relaxed)
+        }
+
+        pub fn set_position(&self, position: u64) {
+            self.position.store(position, Ordering::Relaxed);
+        }
+
+        pub fn inc(&self, delta: u64) {
+            self.position.fetch_add(delta, Ordering::Relaxed);
+        }
+
+        pub fn finish_with_message(&self, message: impl Into<Cow<'static, str>>) {
+            self.set_message(message);
+        }
+
+        pub fn finish_and_clear(&self) {}
+    }
+
+    /// Bar style; ignored.
+    pub struct ProgressStyle;
+
+    impl ProgressStyle {
+        pub fn default_bar() -> Self {
+            Self
+        }
+
+        pub fn template(self, _template: &str) -> Result<Self, Infallible> {
+            Ok(self)
+        }
+
+        pub fn progress_chars(self, _chars: &str) -> Self {
+            self
+        }
+    }
+
+    /// Group of bars; ignored.
+    #[derive(Default)]
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn add(&self, bar: ProgressBar) -> ProgressBar {
+            bar
+        }
+    }
+}
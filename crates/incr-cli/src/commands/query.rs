@@ -0,0 +1,138 @@
+//! Query command - list past extractions from the results store.
+
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::Args;
+use console::style;
+
+use incr_core::models::config::Preset;
+use incr_core::models::naming::FieldNaming;
+
+use super::load_config;
+use super::store::{QueryFilter, ResultStore, StoredExtraction};
+
+/// Arguments for the query command.
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Results database (overrides `store.path` from the config)
+    #[arg(long, value_name = "PATH")]
+    store: Option<PathBuf>,
+
+    /// Issuer or receiver NIP
+    #[arg(long)]
+    nip: Option<String>,
+
+    /// Invoice number, or part of it
+    #[arg(long)]
+    number: Option<String>,
+
+    /// Invoices issued on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    from: Option<NaiveDate>,
+
+    /// Invoices issued on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    to: Option<NaiveDate>,
+
+    /// Documents processed on or after this date (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE")]
+    since: Option<NaiveDate>,
+
+    /// Only documents whose extraction failed
+    #[arg(long)]
+    failed: bool,
+
+    /// Maximum number of documents listed, most recently processed first
+    #[arg(short = 'n', long, default_value_t = 50)]
+    limit: usize,
+
+    /// Output the documents and their extracted invoices as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// JSON field names: snake_case, camel_case or legacy (overrides the config)
+    #[arg(long, value_name = "NAMING", requires = "json")]
+    field_naming: Option<FieldNaming>,
+}
+
+pub async fn run(
+    args: QueryArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let config = load_config(config_path, profile, preset, "query")?;
+
+    let Some(path) = args.store.clone().or(config.store.path.clone()) else {
+        anyhow::bail!("No results store configured (set store.path or pass --store)");
+    };
+    if !path.exists() {
+        anyhow::bail!("Results store {} does not exist", path.display());
+    }
+
+    let store = ResultStore::open(&path)?;
+    let extractions = store.query(&QueryFilter {
+        nip: args.nip,
+        number: args.number,
+        issued_from: args.from,
+        issued_to: args.to,
+        processed_since: args.since,
+        failed: args.failed,
+        limit: args.limit,
+    })?;
+
+    if args.json {
+        let naming = args.field_naming.unwrap_or(config.output.field_naming);
+        println!("{}", serde_json::to_string_pretty(&naming.apply(&extractions))?);
+        return Ok(());
+    }
+
+    if extractions.is_empty() {
+        println!("{} No matching documents", style("ℹ").blue());
+        return Ok(());
+    }
+
+    println!(
+        "{:<16}  {:<24}  {:<10}  {:<10}  {:>14}  FILE",
+        "PROCESSED", "NUMBER", "ISSUED", "ISSUER NIP", "GROSS"
+    );
+    for extraction in &extractions {
+        print_row(extraction);
+    }
+
+    Ok(())
+}
+
+fn print_row(extraction: &StoredExtraction) {
+    let processed = extraction.processed_at.format("%Y-%m-%d %H:%M");
+
+    if let Some(error) = &extraction.error {
+        println!(
+            "{:<16}  {}  {}",
+            processed,
+            style(format!("{:<64}", "failed")).red(),
+            extraction.path
+        );
+        println!("{:<18}{}", "", style(error).dim());
+        return;
+    }
+
+    let gross = match (&extraction.total_gross, &extraction.currency) {
+        (Some(gross), Some(currency)) => format!("{} {}", gross, currency),
+        (Some(gross), None) => gross.clone(),
+        _ => "-".to_string(),
+    };
+
+    println!(
+        "{:<16}  {:<24}  {:<10}  {:<10}  {:>14}  {}",
+        processed,
+        extraction.invoice_number.as_deref().unwrap_or("-"),
+        extraction
+            .issue_date
+            .map_or_else(|| "-".to_string(), |date| date.to_string()),
+        extraction.issuer_nip.as_deref().unwrap_or("-"),
+        gross,
+        extraction.path
+    );
+}
@@ -0,0 +1,347 @@
+//! Match command - pair extracted invoices with ERP open items.
+//!
+//! Reads an ERP export of open items (CSV) and extraction results, and
+//! lists matched, ambiguous and unmatched invoices. Matched pairs can be
+//! written to an approval file for import back into the ERP.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use clap::Args;
+use console::style;
+use tracing::warn;
+
+use incr_core::invoice::rules::parse_polish_amount;
+use incr_core::reconcile::{Match, MatchReport, Matcher, OpenItem};
+use incr_core::Invoice;
+
+/// Date formats accepted in ERP exports.
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%d.%m.%Y", "%d-%m-%Y", "%d/%m/%Y"];
+
+/// Arguments for the match command.
+#[derive(Args)]
+pub struct MatchArgs {
+    /// Extraction results: JSON Lines files, invoice JSON files or
+    /// directories of them (e.g. `batch --output-dir`)
+    #[arg(required = true)]
+    results: Vec<PathBuf>,
+
+    /// ERP export of open items (CSV with a header row)
+    #[arg(long, required = true)]
+    erp: PathBuf,
+
+    /// Largest gross amount difference that still matches
+    #[arg(long, default_value = "0.01")]
+    amount_tolerance: String,
+
+    /// Days the issue date and the ERP date may be apart
+    #[arg(long, default_value_t = 7)]
+    date_window: i64,
+
+    /// Minimum match score (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.6)]
+    min_score: f32,
+
+    /// Write matched pairs to this CSV approval file
+    #[arg(long)]
+    approvals: Option<PathBuf>,
+
+    /// Only approve matches with at least this score
+    #[arg(long, default_value_t = 0.9)]
+    approve_min_score: f32,
+
+    /// Output the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// An invoice and where it was read from.
+struct Source {
+    label: String,
+    invoice: Invoice,
+}
+
+pub async fn run(args: MatchArgs) -> anyhow::Result<()> {
+    let items = load_open_items(&args.erp)?;
+    let sources = load_results(&args.results)?;
+    if sources.is_empty() {
+        anyhow::bail!("No invoices found in the given results");
+    }
+
+    let tolerance = parse_polish_amount(&args.amount_tolerance)
+        .with_context(|| format!("Invalid --amount-tolerance '{}'", args.amount_tolerance))?;
+    let matcher = Matcher::new()
+        .with_amount_tolerance(tolerance)
+        .with_date_window(args.date_window)
+        .with_min_score(args.min_score);
+
+    let invoices: Vec<Invoice> = sources.iter().map(|s| s.invoice.clone()).collect();
+    let report = matcher.match_invoices(&invoices, &items);
+
+    if let Some(path) = &args.approvals {
+        let approved = write_approvals(path, &report, &sources, args.approve_min_score)?;
+        if !args.json {
+            println!(
+                "{} Wrote {} approval(s) to {}",
+                style("✓").green(),
+                approved,
+                path.display()
+            );
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&json_report(&report, &sources))?);
+    } else {
+        print_report(&report, &sources);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &MatchReport, sources: &[Source]) {
+    let reasons = |m: &Match| {
+        let names: Vec<String> = m
+            .reasons
+            .iter()
+            .filter_map(|r| serde_json::to_value(r).ok())
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect();
+        format!("{:.2}: {}", m.score, names.join(", "))
+    };
+
+    println!("\n{}", style("Matched").bold());
+    for m in &report.matched {
+        println!(
+            "  {} {} → {} ({})",
+            style("✓").green(),
+            sources[m.invoice].label,
+            m.item_id,
+            reasons(m)
+        );
+    }
+
+    println!("\n{}", style("Ambiguous").bold());
+    for ambiguous in &report.ambiguous {
+        println!("  {} {}", style("?").yellow(), sources[ambiguous.invoice].label);
+        for candidate in &ambiguous.candidates {
+            println!("      {} ({})", candidate.item_id, reasons(candidate));
+        }
+    }
+
+    println!("\n{}", style("Unmatched invoices").bold());
+    for &index in &report.unmatched_invoices {
+        println!("  {} {}", style("✗").red(), sources[index].label);
+    }
+
+    println!("\n{}", style("Open items without an invoice").bold());
+    for id in &report.unmatched_items {
+        println!("  {} {}", style("✗").red(), id);
+    }
+
+    println!(
+        "\n{} matched, {} ambiguous, {} unmatched invoices, {} open items without an invoice",
+        report.matched.len(),
+        report.ambiguous.len(),
+        report.unmatched_invoices.len(),
+        report.unmatched_items.len()
+    );
+}
+
+/// The report with invoice indices replaced by their labels.
+fn json_report(report: &MatchReport, sources: &[Source]) -> serde_json::Value {
+    let with_label = |m: &Match| {
+        let mut value = serde_json::to_value(m).unwrap_or_default();
+        value["invoice"] = sources[m.invoice].label.clone().into();
+        value
+    };
+
+    serde_json::json!({
+        "matched": report.matched.iter().map(with_label).collect::<Vec<_>>(),
+        "ambiguous": report.ambiguous.iter().map(|a| serde_json::json!({
+            "invoice": sources[a.invoice].label,
+            "candidates": a.candidates.iter().map(with_label).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "unmatched_invoices": report
+            .unmatched_invoices
+            .iter()
+            .map(|&i| sources[i].label.clone())
+            .collect::<Vec<_>>(),
+        "unmatched_items": report.unmatched_items,
+    })
+}
+
+/// Write matches with a high enough score as an approval CSV.
+fn write_approvals(
+    path: &Path,
+    report: &MatchReport,
+    sources: &[Source],
+    min_score: f32,
+) -> anyhow::Result<usize> {
+    let mut wtr = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    wtr.write_record([
+        "erp_id",
+        "invoice_number",
+        "issuer_nip",
+        "issue_date",
+        "total_gross",
+        "currency",
+        "score",
+        "source",
+    ])?;
+
+    let mut approved = 0;
+    for m in report.matched.iter().filter(|m| m.score >= min_score) {
+        let source = &sources[m.invoice];
+        let invoice = &source.invoice;
+        wtr.write_record([
+            m.item_id.as_str(),
+            &invoice.header.invoice_number,
+            invoice.issuer.nip.as_deref().unwrap_or_default(),
+            &invoice.header.issue_date.map(|d| d.to_string()).unwrap_or_default(),
+            &invoice.summary.total_gross.to_string(),
+            &invoice.header.currency,
+            &format!("{:.2}", m.score),
+            &source.label,
+        ])?;
+        approved += 1;
+    }
+    wtr.flush()?;
+
+    Ok(approved)
+}
+
+/// Read extraction results from JSON Lines files, JSON files and
+/// directories of JSON files.
+fn load_results(paths: &[PathBuf]) -> anyhow::Result<Vec<Source>> {
+    let mut sources = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|e| e == "json"))
+                .collect();
+            files.sort();
+
+            for file in files {
+                // Directories hold other JSON files too (summaries, reports)
+                match read_invoice(&file) {
+                    Ok(invoice) => sources.push(Source {
+                        label: file.display().to_string(),
+                        invoice,
+                    }),
+                    Err(e) => warn!("Skipping {}: {}", file.display(), e),
+                }
+            }
+        } else if path.extension().is_some_and(|e| e == "jsonl") {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            for (n, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let invoice = serde_json::from_str(line)
+                    .with_context(|| format!("{}:{} is not an invoice", path.display(), n + 1))?;
+                sources.push(Source {
+                    label: format!("{}:{}", path.display(), n + 1),
+                    invoice,
+                });
+            }
+        } else {
+            sources.push(Source {
+                label: path.display().to_string(),
+                invoice: read_invoice(path)?,
+            });
+        }
+    }
+
+    Ok(sources)
+}
+
+fn read_invoice(path: &Path) -> anyhow::Result<Invoice> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not an invoice", path.display()))
+}
+
+/// Read open items from an ERP CSV export.
+///
+/// Columns are found by header name (`id`, `nip`, `invoice_number`,
+/// `amount`, `currency`, `date` and common alternatives); only the amount
+/// is required. The delimiter (`,`, `;` or tab) is detected from the header.
+fn load_open_items(path: &Path) -> anyhow::Result<Vec<OpenItem>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let header = content.lines().next().unwrap_or_default();
+    let delimiter = [b';', b'\t', b',']
+        .into_iter()
+        .max_by_key(|d| header.bytes().filter(|b| b == d).count())
+        .unwrap_or(b',');
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = rdr
+        .headers()?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+
+    let id = column(&["id", "item_id", "erp_id", "document_id", "doc_id"]);
+    let nip = column(&["nip", "vendor_nip", "seller_nip", "issuer_nip", "tax_id"]);
+    let number = column(&["invoice_number", "number", "document_number", "invoice", "numer"]);
+    let currency = column(&["currency", "waluta"]);
+    let date = column(&["date", "invoice_date", "issue_date", "data"]);
+    let Some(amount) = column(&["amount", "gross", "total_gross", "amount_gross", "brutto", "kwota"])
+    else {
+        anyhow::bail!(
+            "{} has no amount column (expected one of: amount, gross, total_gross, brutto, kwota)",
+            path.display()
+        );
+    };
+
+    let mut items = Vec::new();
+    for (n, record) in rdr.records().enumerate() {
+        let record = record?;
+        let row = n + 2;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        let raw_amount = field(Some(amount)).unwrap_or_default();
+        let Some(mut value) = parse_polish_amount(raw_amount) else {
+            warn!("{}:{}: skipping row with invalid amount '{}'", path.display(), row, raw_amount);
+            continue;
+        };
+        if raw_amount.starts_with('-') {
+            value = -value;
+        }
+
+        items.push(OpenItem {
+            id: field(id).map_or_else(|| format!("row {}", row), String::from),
+            nip: field(nip).map(String::from),
+            invoice_number: field(number).map(String::from),
+            amount: value,
+            currency: field(currency).map(String::from),
+            date: field(date).and_then(parse_date),
+        });
+    }
+
+    Ok(items)
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
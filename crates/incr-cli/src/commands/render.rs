@@ -0,0 +1,110 @@
+//! Render command - re-issue an extracted invoice as HTML or PDF.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use console::style;
+
+use incr_core::render::{render_pdf, Template};
+use incr_core::Invoice;
+
+/// Arguments for the render command.
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Invoice JSON file (from `incr process -f json`)
+    #[arg(required = true)]
+    input: PathBuf,
+
+    /// Output file (.html or .pdf)
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Output format (default: from the output file extension)
+    #[arg(short, long, value_enum)]
+    format: Option<RenderFormat>,
+
+    /// HTML template to use instead of the built-in one
+    #[arg(short, long)]
+    template: Option<PathBuf>,
+
+    /// Attach the invoice as Factur-X XML, making the PDF a ZUGFeRD /
+    /// Factur-X hybrid invoice
+    #[cfg(feature = "facturx")]
+    #[arg(long)]
+    facturx: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum RenderFormat {
+    Html,
+    Pdf,
+}
+
+pub async fn run(args: RenderArgs) -> anyhow::Result<()> {
+    let content = fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let invoice: Invoice = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not an invoice JSON file", args.input.display()))?;
+
+    let extension = args.output.extension().and_then(|e| e.to_str());
+    let format = match (args.format, extension) {
+        (Some(format), _) => format,
+        (None, Some(ext)) if ext.eq_ignore_ascii_case("pdf") => RenderFormat::Pdf,
+        (None, Some(ext)) if ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm") => {
+            RenderFormat::Html
+        }
+        (None, _) => anyhow::bail!(
+            "Cannot tell the format of {}; use --format html or --format pdf",
+            args.output.display()
+        ),
+    };
+    #[cfg(feature = "facturx")]
+    if args.facturx && matches!(format, RenderFormat::Html) {
+        anyhow::bail!("--facturx applies to PDF output only");
+    }
+
+    let bytes = match format {
+        RenderFormat::Html => {
+            let template = match &args.template {
+                Some(path) => {
+                    let source = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    Template::parse(&source)
+                        .with_context(|| format!("Invalid template {}", path.display()))?
+                }
+                None => Template::builtin(),
+            };
+            template.render(&invoice).into_bytes()
+        }
+        RenderFormat::Pdf => {
+            if args.template.is_some() {
+                anyhow::bail!("--template applies to HTML output only; PDF uses the built-in layout");
+            }
+            with_facturx(&args, &invoice, render_pdf(&invoice)?)?
+        }
+    };
+
+    fs::write(&args.output, bytes)
+        .with_context(|| format!("Failed to write {}", args.output.display()))?;
+    println!(
+        "{} Rendered {} to {}",
+        style("✓").green(),
+        invoice.header.invoice_number,
+        args.output.display()
+    );
+
+    Ok(())
+}
+
+/// The PDF with the invoice attached as Factur-X XML if `--facturx` is set.
+#[cfg_attr(not(feature = "facturx"), allow(unused_variables))]
+fn with_facturx(args: &RenderArgs, invoice: &Invoice, pdf: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    #[cfg(feature = "facturx")]
+    if args.facturx {
+        let xml = invoice.to_facturx_xml()?;
+        return Ok(incr_core::export::facturx::embed_in_pdf(&pdf, &xml)?);
+    }
+    Ok(pdf)
+}
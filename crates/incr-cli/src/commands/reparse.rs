@@ -0,0 +1,161 @@
+//! Reparse command - re-run invoice parsing on stored OCR text.
+//!
+//! Compares field coverage of the configured parser settings against
+//! alternative settings without repeating OCR.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use console::style;
+use tracing::{debug, warn};
+
+use incr_core::invoice::coverage::COVERAGE_FIELDS;
+use incr_core::invoice::{CoverageReport, InvoiceParser};
+use incr_core::models::config::{IncrConfig, Preset};
+
+use super::{file_date, load_config, parser_from_config};
+
+/// Arguments for the reparse command.
+#[derive(Args)]
+pub struct ReparseArgs {
+    /// Directory with <name>.ocr.txt files (from `batch --save-text`)
+    #[arg(required = true)]
+    results_dir: PathBuf,
+
+    /// Minimum extraction confidence for a document to count
+    #[arg(long)]
+    min_confidence: Option<f32>,
+
+    /// Disable NIP checksum validation
+    #[arg(long)]
+    no_validate_nip: bool,
+
+    /// Disable REGON checksum validation
+    #[arg(long)]
+    no_validate_regon: bool,
+
+    /// Disable IBAN checksum validation
+    #[arg(long)]
+    no_validate_iban: bool,
+
+    /// Output the comparison as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn run(
+    args: ReparseArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let config = load_config(config_path, profile, preset, "reparse")?;
+
+    let texts = load_texts(&args.results_dir)?;
+    if texts.is_empty() {
+        anyhow::bail!(
+            "No .ocr.txt files found in {}. Run 'incr batch --save-text' first.",
+            args.results_dir.display()
+        );
+    }
+
+    let baseline = config.clone();
+    let mut candidate = config;
+    if let Some(min_confidence) = args.min_confidence {
+        candidate.extraction.min_field_confidence = min_confidence;
+    }
+    if args.no_validate_nip {
+        candidate.extraction.validate_nip = false;
+    }
+    if args.no_validate_regon {
+        candidate.extraction.validate_regon = false;
+    }
+    if args.no_validate_iban {
+        candidate.extraction.validate_iban = false;
+    }
+
+    let baseline_report = coverage(&texts, &baseline)?;
+    let candidate_report = coverage(&texts, &candidate)?;
+
+    if args.json {
+        let output = serde_json::json!({
+            "baseline": baseline_report,
+            "candidate": candidate_report,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Reparsed {} documents",
+        style("ℹ").blue(),
+        baseline_report.documents
+    );
+    println!();
+    println!("{:<22} {:>9} {:>9} {:>7}", "Field", "Baseline", "Candidate", "Delta");
+    println!("{}", "-".repeat(50));
+
+    print_row("accepted", baseline_report.accepted, candidate_report.accepted);
+    for field in COVERAGE_FIELDS {
+        print_row(field, baseline_report.count(field), candidate_report.count(field));
+    }
+
+    Ok(())
+}
+
+/// Load all stored OCR texts from a results directory.
+fn load_texts(dir: &Path) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut texts = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_ocr_text = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(".ocr.txt"));
+
+        if is_ocr_text {
+            let text = fs::read_to_string(&path)?;
+            texts.push((path, text));
+        }
+    }
+
+    texts.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(texts)
+}
+
+fn coverage(texts: &[(PathBuf, String)], config: &IncrConfig) -> anyhow::Result<CoverageReport> {
+    let parser = parser_from_config(config)?;
+
+    let mut report = CoverageReport::new();
+
+    for (path, text) in texts {
+        let parser = parser.clone().with_reference_date(file_date(path));
+        match parser.parse(text) {
+            Ok(result) => report.add(&result.invoice, config.extraction.min_field_confidence),
+            Err(e) => {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                report.documents += 1;
+            }
+        }
+    }
+
+    debug!(
+        "Coverage: {}/{} documents accepted",
+        report.accepted, report.documents
+    );
+
+    Ok(report)
+}
+
+fn print_row(name: &str, baseline: usize, candidate: usize) {
+    let delta = candidate as i64 - baseline as i64;
+    let delta = match delta {
+        d if d > 0 => style(format!("+{}", d)).green(),
+        d if d < 0 => style(d.to_string()).red(),
+        d => style(d.to_string()).dim(),
+    };
+
+    println!("{:<22} {:>9} {:>9} {:>7}", name, baseline, candidate, delta);
+}
@@ -0,0 +1,83 @@
+//! Memory checks for small devices.
+//!
+//! OCR memory use is dominated by the model weights and the feature maps
+//! of the largest processed image, so the estimate below only looks at
+//! the model files and `ocr.max_image_size`.
+
+use std::path::Path;
+
+use tracing::{debug, warn};
+
+use incr_core::models::config::IncrConfig;
+
+const MB: u64 = 1024 * 1024;
+
+/// Process, runtime and parsing overhead independent of the models.
+const BASE_BYTES: u64 = 64 * MB;
+
+/// Working memory per pixel of the largest image (f32 feature maps).
+const BYTES_PER_PIXEL: u64 = 64;
+
+/// Estimated peak memory for OCR with the models in `model_dir`.
+///
+/// Weights are counted twice: once as loaded bytes and once as the
+/// optimized graph built from them. Embedded models are assumed when
+/// `model_dir` has none.
+pub fn estimate_peak_bytes(model_dir: &Path, config: &IncrConfig) -> u64 {
+    let models = &config.models;
+    let mut model_bytes: u64 = [
+        &models.detection_model,
+        &models.classification_model,
+        &models.recognition_files().0,
+    ]
+    .iter()
+    .filter_map(|name| std::fs::metadata(model_dir.join(name)).ok())
+    .map(|m| m.len())
+    .sum();
+
+    if model_bytes == 0 {
+        model_bytes = 20 * MB;
+    }
+
+    let side = config.ocr.max_image_size as u64;
+    BASE_BYTES + 2 * model_bytes + side * side * BYTES_PER_PIXEL
+}
+
+/// Memory available to new allocations, where the platform reports it.
+pub fn available_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Warn when OCR is unlikely to fit in the available memory.
+pub fn check_memory(model_dir: &Path, config: &IncrConfig) {
+    let required = estimate_peak_bytes(model_dir, config);
+    let Some(available) = available_bytes() else {
+        return;
+    };
+
+    debug!(
+        "Estimated OCR peak memory {} MB, {} MB available",
+        required / MB,
+        available / MB
+    );
+
+    if required > available {
+        warn!(
+            "OCR needs about {} MB but only {} MB is available; \
+             lower ocr.max_image_size or use the mobile models",
+            required / MB,
+            available / MB
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Scan command - acquire pages from a scanner and extract them.
+//!
+//! Pages are acquired with SANE's `scanimage`. Where SANE is unavailable
+//! (e.g. WIA/TWAIN on Windows), `--bridge` runs any command that writes
+//! the scanned image to the `{output}` path.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use clap::{Args, ValueEnum};
+use console::style;
+use tracing::{debug, info};
+
+use incr_core::models::config::Preset;
+
+use super::audit::Auditor;
+use super::load_config;
+use super::pipeline::extract_document;
+use super::engines::shared_engine;
+use super::process::{format_invoice, OutputFormat};
+use super::progress::{BarProgress, ProgressBar, ProgressStyle};
+use super::variant::{get_variant_dir, resolve_variant};
+
+/// Arguments for the scan command.
+#[derive(Args)]
+pub struct ScanArgs {
+    /// SANE device name (see --list-devices; default: first scanner found)
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// List available SANE scanners and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Scan resolution in DPI
+    #[arg(long, default_value = "300")]
+    resolution: u32,
+
+    /// Color mode
+    #[arg(long, value_enum, default_value = "gray")]
+    mode: ScanMode,
+
+    /// Scan source as named by the device (e.g. Flatbed, ADF)
+    #[arg(long)]
+    source: Option<String>,
+
+    /// Acquire with this command instead of scanimage; `{output}` is
+    /// replaced with the path the image must be written to
+    #[arg(long, value_name = "COMMAND")]
+    bridge: Option<String>,
+
+    /// Keep scanning documents until `q` is entered
+    #[arg(long = "loop")]
+    repeat: bool,
+
+    /// Save each scan and its result to this directory
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "json")]
+    format: OutputFormat,
+
+    /// Model directory
+    #[arg(short, long)]
+    model_dir: Option<PathBuf>,
+}
+
+/// Scanner color mode.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ScanMode {
+    Color,
+    Gray,
+    Lineart,
+}
+
+impl ScanMode {
+    fn sane_name(self) -> &'static str {
+        match self {
+            ScanMode::Color => "Color",
+            ScanMode::Gray => "Gray",
+            ScanMode::Lineart => "Lineart",
+        }
+    }
+}
+
+pub async fn run(
+    args: ScanArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    if args.list_devices {
+        return list_devices();
+    }
+
+    if matches!(args.format, OutputFormat::TableCsv) {
+        anyhow::bail!("--format table-csv is only supported by the process command");
+    }
+    if matches!(
+        args.format,
+        OutputFormat::Proto | OutputFormat::Parquet | OutputFormat::Ndjson | OutputFormat::Xlsx
+    ) {
+        anyhow::bail!(
+            "--format proto, parquet, ndjson and xlsx are only supported by the batch command"
+        );
+    }
+
+    let config = load_config(config_path, profile, preset, "scan")?;
+
+    let model_dir = args
+        .model_dir
+        .clone()
+        .unwrap_or_else(|| get_variant_dir(resolve_variant(&config)));
+    let engine = shared_engine(&model_dir, &config)?;
+    let auditor = Auditor::open(&config, &model_dir)?;
+
+    // Continue the numbering of earlier sessions instead of overwriting them
+    let mut count = 0;
+    if let Some(dir) = &args.output_dir {
+        fs::create_dir_all(dir)?;
+        count = last_scan_number(dir)?;
+    }
+
+    loop {
+        count += 1;
+        let name = format!("scan-{:04}", count);
+
+        eprintln!("{} Scanning {}...", style("ℹ").blue(), name);
+        let data = acquire(&args, count)?;
+        info!("Acquired {} bytes from scanner", data.len());
+
+        let pb = ProgressBar::new(100);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {msg}")
+                .unwrap()
+                .progress_chars("##-"),
+        );
+
+        let result = extract_document(&data, &engine, &config, &BarProgress::new(&pb));
+        pb.finish_with_message("Done");
+
+        if let Some(auditor) = &auditor {
+            let outcome = result.as_ref().map_err(|e| e.to_string());
+            auditor.extraction("scan", Some(name.clone()), &data, outcome)?;
+        }
+
+        match result {
+            Ok(invoice) => {
+                let output = format_invoice(&invoice, args.format, &config.output)?;
+                match &args.output_dir {
+                    Some(dir) => save_scan(dir, &name, &data, &output, args.format)?,
+                    None => println!("{}", output),
+                }
+            }
+            // In a session one bad page shouldn't end the run
+            Err(e) if args.repeat => eprintln!("{} {}: {}", style("✗").red(), name, e),
+            Err(e) => return Err(e),
+        }
+
+        if !args.repeat || !prompt_next()? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Acquire one page as image bytes.
+fn acquire(args: &ScanArgs, count: usize) -> anyhow::Result<Vec<u8>> {
+    match &args.bridge {
+        Some(bridge) => acquire_with_bridge(bridge, count),
+        None => acquire_with_sane(args),
+    }
+}
+
+fn acquire_with_sane(args: &ScanArgs) -> anyhow::Result<Vec<u8>> {
+    let mut command = Command::new("scanimage");
+    command
+        .arg("--format=pnm")
+        .arg(format!("--resolution={}", args.resolution))
+        .arg(format!("--mode={}", args.mode.sane_name()));
+
+    if let Some(device) = &args.device {
+        command.arg(format!("--device-name={}", device));
+    }
+    if let Some(source) = &args.source {
+        command.arg(format!("--source={}", source));
+    }
+
+    debug!("Running {:?}", command);
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| scanner_error("scanimage", e))?;
+
+    if !output.status.success() {
+        anyhow::bail!("scanimage failed ({})", output.status);
+    }
+    if output.stdout.is_empty() {
+        anyhow::bail!("scanimage returned no image");
+    }
+
+    Ok(output.stdout)
+}
+
+fn acquire_with_bridge(bridge: &str, count: usize) -> anyhow::Result<Vec<u8>> {
+    let path = std::env::temp_dir().join(format!("incr-scan-{}-{}", std::process::id(), count));
+    let command_line = bridge.replace("{output}", &path.display().to_string());
+
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&command_line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&command_line);
+        command
+    };
+
+    debug!("Running bridge: {}", command_line);
+    let status = command.status().map_err(|e| scanner_error(bridge, e))?;
+    if !status.success() {
+        anyhow::bail!("Scan bridge failed ({})", status);
+    }
+
+    let data = fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Scan bridge wrote no image to {}: {}", path.display(), e))?;
+    let _ = fs::remove_file(&path);
+    Ok(data)
+}
+
+fn list_devices() -> anyhow::Result<()> {
+    let status = Command::new("scanimage")
+        .arg("--list-devices")
+        .status()
+        .map_err(|e| scanner_error("scanimage", e))?;
+
+    if !status.success() {
+        anyhow::bail!("scanimage failed ({})", status);
+    }
+    Ok(())
+}
+
+fn scanner_error(program: &str, error: io::Error) -> anyhow::Error {
+    if error.kind() == io::ErrorKind::NotFound {
+        anyhow::anyhow!(
+            "{} not found; install SANE (e.g. sane-utils) or acquire with --bridge",
+            program
+        )
+    } else {
+        anyhow::anyhow!("Failed to run {}: {}", program, error)
+    }
+}
+
+/// The highest `scan-NNNN` number among the files in `dir`, or 0.
+fn last_scan_number(dir: &Path) -> io::Result<usize> {
+    let mut last = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let number = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix("scan-"))
+            .and_then(|number| number.parse().ok());
+        if let Some(number) = number {
+            last = last.max(number);
+        }
+    }
+    Ok(last)
+}
+
+/// Store the scanned page as PNG next to the extraction result.
+fn save_scan(
+    dir: &Path,
+    name: &str,
+    data: &[u8],
+    output: &str,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let image = image::load_from_memory(data)?;
+    image.save(dir.join(format!("{}.png", name)))?;
+
+    let extension = match format {
+        OutputFormat::Json => "json",
+        OutputFormat::Csv => "csv",
+        // Both are also rejected before scanning starts
+        OutputFormat::TableCsv => {
+            anyhow::bail!("--format table-csv is only supported by the process command")
+        }
+        OutputFormat::Proto | OutputFormat::Parquet | OutputFormat::Ndjson | OutputFormat::Xlsx => {
+            anyhow::bail!(
+                "--format proto, parquet, ndjson and xlsx are only supported by the batch command"
+            )
+        }
+        OutputFormat::Text => "txt",
+        OutputFormat::JpkFa | OutputFormat::Ubl => "xml",
+        #[cfg(feature = "facturx")]
+        OutputFormat::Facturx => "xml",
+    };
+    let result_path = dir.join(format!("{}.{}", name, extension));
+    fs::write(&result_path, output)?;
+
+    eprintln!(
+        "{} {} written to {}",
+        style("✓").green(),
+        name,
+        result_path.display()
+    );
+    Ok(())
+}
+
+/// Ask whether to scan another document. Returns false on `q` or end of input.
+fn prompt_next() -> anyhow::Result<bool> {
+    eprint!("Place the next document and press Enter (q to finish): ");
+    io::stderr().flush()?;
+
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(false);
+    }
+
+    Ok(!line.trim().eq_ignore_ascii_case("q"))
+}
@@ -0,0 +1,335 @@
+//! Job tracking for asynchronous extraction.
+//!
+//! Running jobs live in memory so progress can be streamed; every job is
+//! also recorded in the [`JobDb`] and finished jobs are served from there.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use incr_core::models::invoice::Invoice;
+use incr_core::progress::{ProgressEvent, ProgressSink};
+
+use super::store::JobDb;
+
+/// Lifecycle state of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            _ => anyhow::bail!("unknown job status '{}'", s),
+        }
+    }
+}
+
+/// Event streamed to job subscribers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    /// Pipeline progress.
+    Progress(ProgressEvent),
+    /// Final extraction result.
+    Result { invoice: Box<Invoice> },
+    /// Extraction failed.
+    Error { message: String },
+}
+
+impl JobEvent {
+    /// SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Progress(_) => "progress",
+            JobEvent::Result { .. } => "result",
+            JobEvent::Error { .. } => "error",
+        }
+    }
+
+    /// Whether no further events follow.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, JobEvent::Progress(_))
+    }
+
+    /// Terminal event of a finished job.
+    pub fn from_snapshot(snapshot: JobSnapshot) -> Option<Self> {
+        match snapshot.status {
+            JobStatus::Completed => snapshot.result.map(|invoice| JobEvent::Result {
+                invoice: Box::new(invoice),
+            }),
+            JobStatus::Failed => Some(JobEvent::Error {
+                message: snapshot.error.unwrap_or_else(|| "unknown error".to_string()),
+            }),
+            JobStatus::Queued | JobStatus::Running => None,
+        }
+    }
+}
+
+/// Snapshot of a job returned by the status endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSnapshot {
+    pub id: Uuid,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ProgressEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Invoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct JobState {
+    snapshot: JobSnapshot,
+    history: Vec<JobEvent>,
+}
+
+/// A submitted extraction job.
+pub struct Job {
+    state: Mutex<JobState>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl Job {
+    fn new(id: Uuid, filename: Option<String>) -> Self {
+        let (events, _) = broadcast::channel(64);
+
+        Self {
+            state: Mutex::new(JobState {
+                snapshot: JobSnapshot {
+                    id,
+                    status: JobStatus::Queued,
+                    filename,
+                    created_at: Utc::now(),
+                    finished_at: None,
+                    progress: None,
+                    result: None,
+                    error: None,
+                },
+                history: Vec::new(),
+            }),
+            events,
+        }
+    }
+
+    /// Current job state.
+    pub fn snapshot(&self) -> JobSnapshot {
+        self.lock().snapshot.clone()
+    }
+
+    /// Record an event and forward it to live subscribers.
+    pub fn publish(&self, event: JobEvent) {
+        let mut state = self.lock();
+
+        match &event {
+            JobEvent::Progress(progress) => {
+                state.snapshot.status = JobStatus::Running;
+                state.snapshot.progress = Some(progress.clone());
+            }
+            JobEvent::Result { invoice } => {
+                state.snapshot.status = JobStatus::Completed;
+                state.snapshot.finished_at = Some(Utc::now());
+                state.snapshot.result = Some((**invoice).clone());
+            }
+            JobEvent::Error { message } => {
+                state.snapshot.status = JobStatus::Failed;
+                state.snapshot.finished_at = Some(Utc::now());
+                state.snapshot.error = Some(message.clone());
+            }
+        }
+
+        state.history.push(event.clone());
+        // No receivers is fine: late subscribers replay the history
+        let _ = self.events.send(event);
+    }
+
+    /// Events published so far plus a receiver for the ones that follow.
+    ///
+    /// Both are taken under the same lock, so no event is missed or repeated.
+    pub fn subscribe(&self) -> (Vec<JobEvent>, broadcast::Receiver<JobEvent>) {
+        let state = self.lock();
+        (state.history.clone(), self.events.subscribe())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Progress sink publishing events to a job.
+pub struct JobProgress(pub Arc<Job>);
+
+impl ProgressSink for JobProgress {
+    fn report(&self, event: ProgressEvent) {
+        self.0.publish(JobEvent::Progress(event));
+    }
+}
+
+/// Running jobs plus the persistent job database.
+pub struct JobStore {
+    live: RwLock<HashMap<Uuid, Arc<Job>>>,
+    db: JobDb,
+}
+
+impl JobStore {
+    pub fn new(db: JobDb) -> Self {
+        Self {
+            live: RwLock::new(HashMap::new()),
+            db,
+        }
+    }
+
+    /// Register and persist a new queued job.
+    pub fn create(&self, filename: Option<String>, document: Option<&[u8]>) -> anyhow::Result<Arc<Job>> {
+        let id = Uuid::new_v4();
+        let job = Arc::new(Job::new(id, filename));
+        self.db.insert(&job.snapshot(), document)?;
+
+        self.live
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, job.clone());
+        Ok(job)
+    }
+
+    /// Persist the final state of a job and stop tracking it in memory.
+    pub fn complete(&self, job: &Job) -> anyhow::Result<()> {
+        let snapshot = job.snapshot();
+        self.db.finish(&snapshot)?;
+
+        self.live
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&snapshot.id);
+        Ok(())
+    }
+
+    /// A job that is still queued or running.
+    pub fn live(&self, id: &Uuid) -> Option<Arc<Job>> {
+        self.live
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    /// Current state of a job, running or finished.
+    pub fn snapshot(&self, id: &Uuid) -> anyhow::Result<Option<JobSnapshot>> {
+        match self.live(id) {
+            Some(job) => Ok(Some(job.snapshot())),
+            None => self.db.get(id),
+        }
+    }
+
+    /// The persistent job database.
+    pub fn db(&self) -> &JobDb {
+        &self.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use incr_core::progress::ProgressStage;
+
+    #[test]
+    fn test_job_transitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(JobDb::open(&dir.path().join("jobs.db")).unwrap());
+
+        let job = store.create(Some("fv.pdf".to_string()), None).unwrap();
+        let id = job.snapshot().id;
+        assert_eq!(job.snapshot().status, JobStatus::Queued);
+        assert_eq!(store.db().get(&id).unwrap().unwrap().status, JobStatus::Queued);
+
+        JobProgress(job.clone()).report(ProgressEvent::new(ProgressStage::Load, 1, 2, "Loading"));
+        let snapshot = store.snapshot(&id).unwrap().unwrap();
+        assert_eq!(snapshot.status, JobStatus::Running);
+        assert_eq!(snapshot.progress.unwrap().current, 1);
+
+        // Late subscribers get the history, then the live events
+        let (history, mut events) = job.subscribe();
+        assert_eq!(history.len(), 1);
+        job.publish(JobEvent::Result {
+            invoice: Box::new(Invoice::new()),
+        });
+        assert_eq!(events.try_recv().unwrap().name(), "result");
+
+        let snapshot = job.snapshot();
+        assert_eq!(snapshot.status, JobStatus::Completed);
+        assert!(snapshot.finished_at.is_some());
+        assert!(matches!(
+            JobEvent::from_snapshot(snapshot),
+            Some(JobEvent::Result { .. })
+        ));
+
+        // Finished jobs are served from the database
+        store.complete(&job).unwrap();
+        assert!(store.live(&id).is_none());
+        let stored = store.snapshot(&id).unwrap().unwrap();
+        assert_eq!(stored.status, JobStatus::Completed);
+        assert!(stored.result.is_some());
+    }
+
+    #[test]
+    fn test_failed_job() {
+        let store = JobStore::new(JobDb::in_memory().unwrap());
+        let job = store.create(None, Some(b"data")).unwrap();
+        job.publish(JobEvent::Error {
+            message: "unsupported file".to_string(),
+        });
+        store.complete(&job).unwrap();
+
+        let stored = store.snapshot(&job.snapshot().id).unwrap().unwrap();
+        assert_eq!(stored.status, JobStatus::Failed);
+        let Some(JobEvent::Error { message }) = JobEvent::from_snapshot(stored) else {
+            panic!("expected an error event");
+        };
+        assert_eq!(message, "unsupported file");
+    }
+
+    #[test]
+    fn test_parse_status() {
+        for status in [
+            JobStatus::Queued,
+            JobStatus::Running,
+            JobStatus::Completed,
+            JobStatus::Failed,
+        ] {
+            assert_eq!(status.as_str().parse::<JobStatus>().unwrap(), status);
+        }
+        assert!("done".parse::<JobStatus>().is_err());
+    }
+}
@@ -0,0 +1,659 @@
+//! Serve command - HTTP extraction service.
+//!
+//! Endpoints:
+//! - `POST /extract` - extract an invoice synchronously (PDF or image body,
+//!   or a `multipart/form-data` upload)
+//! - `POST /jobs` - submit a document, returns a job id
+//! - `GET /jobs` - recent jobs (`?status=completed&limit=50`)
+//! - `GET /jobs/{id}` - job status and result
+//! - `DELETE /jobs/{id}` - remove a job
+//! - `GET /jobs/{id}/events` - server-sent progress events and the final result
+//! - `GET /jobs/{id}/document` - the original upload (with `--store-documents`)
+//! - `GET /models` - model variants and whether they are loaded
+//! - `GET /health` - liveness check
+//!
+//! Uploads may name a model variant (`?variant=server`); engines are loaded
+//! once and shared by all requests. Jobs are kept in a SQLite database and
+//! survive restarts.
+
+mod jobs;
+mod pool;
+mod store;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::{Args, ValueEnum};
+use console::style;
+use futures_util::Stream;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use incr_core::models::config::{IncrConfig, Preset};
+use incr_core::models::invoice::Invoice;
+use incr_core::models::naming::FieldNaming;
+use incr_core::progress::NoProgress;
+
+use super::audit::Auditor;
+use super::events::Publisher;
+use super::load_config;
+use super::pipeline;
+use super::variant::{get_variant_dir, resolve_variant, ModelVariant};
+use jobs::{JobEvent, JobProgress, JobStatus, JobStore};
+use pool::ModelPool;
+use store::JobDb;
+
+/// How often expired jobs are purged.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Arguments for the serve command.
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value = "8080")]
+    port: u16,
+
+    /// Model directory of the default variant
+    #[arg(short, long)]
+    model_dir: Option<PathBuf>,
+
+    /// Maximum upload size in megabytes
+    #[arg(long, default_value = "50")]
+    max_upload_mb: usize,
+
+    /// Job database path (default: <data dir>/incr/jobs.sqlite, ":memory:" to disable persistence)
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    /// Delete jobs older than this many days (0 keeps them forever)
+    #[arg(long, default_value = "30")]
+    retention_days: u32,
+
+    /// Keep uploaded documents with their jobs
+    #[arg(long)]
+    store_documents: bool,
+}
+
+/// Shared server state.
+#[derive(Clone)]
+struct AppState {
+    models: Arc<ModelPool>,
+    config: Arc<IncrConfig>,
+    jobs: Arc<JobStore>,
+    audit: Option<Arc<Auditor>>,
+    events: Option<Arc<Publisher>>,
+    store_documents: bool,
+}
+
+/// Optional metadata passed alongside an upload.
+#[derive(Deserialize)]
+struct UploadParams {
+    filename: Option<String>,
+    /// Model variant to extract with (default: the server's variant).
+    variant: Option<String>,
+}
+
+/// A document sent as the request body or as the file of a
+/// `multipart/form-data` form.
+struct Upload {
+    data: Bytes,
+    filename: Option<String>,
+}
+
+/// Filters for the job list.
+#[derive(Deserialize)]
+struct ListParams {
+    status: Option<String>,
+    limit: Option<usize>,
+}
+
+pub async fn run(
+    args: ServeArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    let config = Arc::new(load_config(config_path, profile, preset, "serve")?);
+
+    let variant = resolve_variant(&config);
+    let model_dir = args
+        .model_dir
+        .clone()
+        .unwrap_or_else(|| get_variant_dir(variant));
+
+    let models = ModelPool::new(config.clone(), variant, model_dir)?;
+    let audit = Auditor::open(&config, models.default_dir())?.map(Arc::new);
+    let events = Publisher::connect(&config).await?.map(Arc::new);
+
+    let db = open_db(args.db.as_deref())?;
+
+    let state = AppState {
+        models: Arc::new(models),
+        config,
+        jobs: Arc::new(JobStore::new(db)),
+        audit,
+        events,
+        store_documents: args.store_documents,
+    };
+
+    if args.retention_days > 0 {
+        tokio::spawn(purge_expired(state.jobs.clone(), args.retention_days));
+    }
+
+    let app = router(state, args.max_upload_mb);
+
+    let addr: SocketAddr = format!("{}:{}", args.host, args.port).parse()?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    println!(
+        "{} Listening on http://{}",
+        style("✓").green(),
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Open the job database at `path` (`:memory:` for none) and fail the jobs
+/// a previous run left unfinished.
+fn open_db(path: Option<&std::path::Path>) -> anyhow::Result<JobDb> {
+    let db = match path {
+        Some(path) if path.as_os_str() == ":memory:" => JobDb::in_memory()?,
+        Some(path) => JobDb::open(path)?,
+        None => JobDb::open(&default_db_path())?,
+    };
+
+    let interrupted = db.fail_unfinished()?;
+    if interrupted > 0 {
+        warn!("Marked {} unfinished jobs from a previous run as failed", interrupted);
+    }
+    Ok(db)
+}
+
+fn router(state: AppState, max_upload_mb: usize) -> Router {
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/models", get(list_models))
+        .route("/extract", post(extract))
+        .route("/jobs", post(submit_job).get(list_jobs))
+        .route("/jobs/{id}", get(job_status).delete(delete_job))
+        .route("/jobs/{id}/events", get(job_events))
+        .route("/jobs/{id}/document", get(job_document))
+        .layer(DefaultBodyLimit::max(max_upload_mb * 1024 * 1024))
+        .with_state(state)
+}
+
+/// Extract an invoice and return it directly.
+async fn extract(
+    State(state): State<AppState>,
+    Query(params): Query<UploadParams>,
+    request: Request,
+) -> Response {
+    let variant = match variant(&state, params.variant.as_deref()) {
+        Ok(variant) => variant,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    let upload = match read_upload(request).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let filename = upload.filename.or(params.filename);
+    let body = upload.data;
+
+    let naming = state.config.output.field_naming;
+    let result = tokio::task::spawn_blocking(move || {
+        let result = state.models.engine(variant).and_then(|engine| {
+            pipeline::extract_document(&body, &engine, &state.config, &NoProgress)
+        });
+        audit(&state, filename, &body, &result);
+        publish(&state, &result);
+        result
+    })
+    .await;
+
+    match result {
+        Ok(Ok(invoice)) => Json(naming.apply(&invoice)).into_response(),
+        Ok(Err(e)) => error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Queue a document for extraction and return its job id.
+async fn submit_job(
+    State(state): State<AppState>,
+    Query(params): Query<UploadParams>,
+    request: Request,
+) -> Response {
+    let variant = match variant(&state, params.variant.as_deref()) {
+        Ok(variant) => variant,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    let upload = match read_upload(request).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    let filename = upload.filename.or(params.filename);
+    let body = upload.data;
+
+    let document = state.store_documents.then_some(&body[..]);
+    let job = match state.jobs.create(filename.clone(), document) {
+        Ok(job) => job,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+    let id = job.snapshot().id;
+    let naming = state.config.output.field_naming;
+    info!("Queued job {} ({} bytes)", id, body.len());
+
+    tokio::task::spawn_blocking(move || {
+        let progress = JobProgress(job.clone());
+        let result = state.models.engine(variant).and_then(|engine| {
+            pipeline::extract_document(&body, &engine, &state.config, &progress)
+        });
+        audit(&state, filename, &body, &result);
+        publish(&state, &result);
+
+        let event = match result {
+            Ok(invoice) => JobEvent::Result {
+                invoice: Box::new(invoice),
+            },
+            Err(e) => JobEvent::Error {
+                message: e.to_string(),
+            },
+        };
+        debug!("Job {} finished: {}", id, event.name());
+        job.publish(event);
+
+        if let Err(e) = state.jobs.complete(&job) {
+            warn!("Failed to store result of job {}: {}", id, e);
+        }
+    });
+
+    let body = serde_json::json!({
+        "job_id": id,
+        "status_url": format!("/jobs/{}", id),
+        "events_url": format!("/jobs/{}/events", id),
+    });
+
+    (StatusCode::ACCEPTED, Json(naming.apply(&body))).into_response()
+}
+
+/// Model variants, where their files are and which are loaded.
+async fn list_models(State(state): State<AppState>) -> Response {
+    let models = state.models.list();
+    Json(state.config.output.field_naming.apply(&models)).into_response()
+}
+
+/// Recent jobs, newest first, without results.
+async fn list_jobs(State(state): State<AppState>, Query(params): Query<ListParams>) -> Response {
+    let status = match params.status.as_deref().map(str::parse::<JobStatus>).transpose() {
+        Ok(status) => status,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+
+    match state.jobs.db().list(status, params.limit.unwrap_or(50).min(1000)) {
+        Ok(jobs) => Json(state.config.output.field_naming.apply(&jobs)).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Current status (and result, once finished) of a job.
+async fn job_status(State(state): State<AppState>, Path(id): Path<Uuid>) -> Response {
+    match state.jobs.snapshot(&id) {
+        Ok(Some(job)) => Json(state.config.output.field_naming.apply(&job)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Job not found"),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Delete a finished job and its stored document.
+async fn delete_job(State(state): State<AppState>, Path(id): Path<Uuid>) -> Response {
+    if state.jobs.live(&id).is_some() {
+        return error_response(StatusCode::CONFLICT, "Job is still running");
+    }
+
+    match state.jobs.db().delete(&id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "Job not found"),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// The original uploaded document of a job.
+async fn job_document(State(state): State<AppState>, Path(id): Path<Uuid>) -> Response {
+    match state.jobs.db().document(&id) {
+        Ok(Some(document)) => document.into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "No document stored for this job"),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Stream job events as server-sent events.
+///
+/// Events already published are replayed first, so clients may connect at
+/// any time. The stream ends after the `result` or `error` event.
+async fn job_events(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    let (history, receiver) = match state.jobs.live(&id) {
+        Some(job) => {
+            let (history, receiver) = job.subscribe();
+            (history, Some(receiver))
+        }
+        None => {
+            // Finished job: replay only its outcome
+            let snapshot = state
+                .jobs
+                .snapshot(&id)
+                .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?
+                .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Job not found"))?;
+            (JobEvent::from_snapshot(snapshot).into_iter().collect(), None)
+        }
+    };
+
+    let naming = state.config.output.field_naming;
+    let stream = futures_util::stream::unfold(
+        (history.into_iter(), receiver, false),
+        move |(mut history, mut receiver, finished)| async move {
+            if finished {
+                return None;
+            }
+
+            let event = match (history.next(), receiver.as_mut()) {
+                (Some(event), _) => event,
+                (None, None) => return None,
+                (None, Some(live)) => loop {
+                    match live.recv().await {
+                        Ok(event) => break event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            debug!("SSE subscriber lagged, skipped {} events", skipped);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+
+            let finished = event.is_terminal();
+            Some((Ok(to_sse(&event, naming)), (history, receiver, finished)))
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Periodically delete jobs past the retention period.
+async fn purge_expired(jobs: Arc<JobStore>, retention_days: u32) {
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+        match jobs.db().purge_before(cutoff) {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {} jobs older than {} days", removed, retention_days),
+            Err(e) => warn!("Failed to purge expired jobs: {}", e),
+        }
+    }
+}
+
+/// Append an audit record for an extraction, if auditing is enabled.
+fn audit(state: &AppState, filename: Option<String>, data: &[u8], result: &anyhow::Result<Invoice>) {
+    let Some(auditor) = &state.audit else {
+        return;
+    };
+
+    let outcome = result.as_ref().map_err(|e| e.to_string());
+    if let Err(e) = auditor.extraction("serve", filename, data, outcome) {
+        warn!("Failed to write audit record: {}", e);
+    }
+}
+
+/// Publish a successful extraction, if event publishing is enabled.
+fn publish(state: &AppState, result: &anyhow::Result<Invoice>) {
+    let (Some(events), Ok(invoice)) = (&state.events, result) else {
+        return;
+    };
+    if let Err(e) = events.publish(invoice) {
+        warn!("Failed to publish extraction event: {}", e);
+    }
+}
+
+/// The model variant named in a request, checked to be installed.
+fn variant(state: &AppState, name: Option<&str>) -> Result<Option<ModelVariant>, String> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+
+    let variant = ModelVariant::from_str(name, true)
+        .map_err(|_| format!("Unknown model variant '{}' (expected mobile or server)", name))?;
+    if !state.models.is_available(variant) {
+        return Err(format!("{} models are not installed", variant));
+    }
+    Ok(Some(variant))
+}
+
+/// The uploaded document: the first file of a `multipart/form-data` form
+/// (or its `file` field), otherwise the raw request body.
+async fn read_upload(request: Request) -> Result<Upload, Response> {
+    let multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let upload = if multipart {
+        let bad_request = |e: axum::extract::multipart::MultipartError| {
+            error_response(StatusCode::BAD_REQUEST, &e.body_text())
+        };
+        let mut form = Multipart::from_request(request, &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let mut upload = None;
+        while let Some(field) = form.next_field().await.map_err(bad_request)? {
+            if field.file_name().is_none() && field.name() != Some("file") {
+                continue;
+            }
+            let filename = field.file_name().map(str::to_string);
+            let data = field.bytes().await.map_err(bad_request)?;
+            upload = Some(Upload { data, filename });
+            break;
+        }
+        upload.ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "No file in the form"))?
+    } else {
+        let data = Bytes::from_request(request, &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Upload {
+            data,
+            filename: None,
+        }
+    };
+
+    if upload.data.is_empty() {
+        return Err(error_response(StatusCode::BAD_REQUEST, "Empty request body"));
+    }
+    Ok(upload)
+}
+
+fn default_db_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("incr")
+        .join("jobs.sqlite")
+}
+
+fn to_sse(event: &JobEvent, naming: FieldNaming) -> Event {
+    Event::default()
+        .event(event.name())
+        .json_data(naming.apply(event))
+        .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    /// A server without auditing or events whose jobs are in `db`. Its
+    /// engines are never loaded by these tests.
+    fn app(db: JobDb, dir: &std::path::Path) -> Router {
+        let config = Arc::new(IncrConfig::default());
+        let state = AppState {
+            models: Arc::new(ModelPool::unloaded(
+                config.clone(),
+                ModelVariant::Mobile,
+                dir.to_path_buf(),
+            )),
+            config,
+            jobs: Arc::new(JobStore::new(db)),
+            audit: None,
+            events: None,
+            store_documents: true,
+        };
+        router(state, 1)
+    }
+
+    async fn send(app: &Router, method: &str, uri: &str, body: Body) -> (StatusCode, String) {
+        let request = Request::builder().method(method).uri(uri).body(body).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+        send(app, "GET", uri, Body::empty()).await
+    }
+
+    #[tokio::test]
+    async fn test_finished_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JobDb::open(&dir.path().join("jobs.sqlite")).unwrap();
+        let jobs = JobStore::new(db);
+        let job = jobs.create(Some("fv.pdf".to_string()), Some(b"%PDF-1.7")).unwrap();
+        job.publish(JobEvent::Result {
+            invoice: Box::new(Invoice::new()),
+        });
+        jobs.complete(&job).unwrap();
+        let id = job.snapshot().id;
+        drop(jobs);
+
+        // A restarted server serves the job from the database
+        let app = app(open_db(Some(&dir.path().join("jobs.sqlite"))).unwrap(), dir.path());
+        assert_eq!(get(&app, "/health").await, (StatusCode::OK, "ok".to_string()));
+
+        let (status, body) = get(&app, "/jobs").await;
+        assert_eq!(status, StatusCode::OK);
+        let listed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listed[0]["id"], id.to_string());
+        assert_eq!(listed[0]["status"], "completed");
+        assert!(listed[0].get("result").is_none());
+        assert_eq!(get(&app, "/jobs?status=done").await.0, StatusCode::BAD_REQUEST);
+
+        let (status, body) = get(&app, &format!("/jobs/{}", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let snapshot: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(snapshot["filename"], "fv.pdf");
+        assert!(snapshot.get("result").is_some());
+
+        let document = get(&app, &format!("/jobs/{}/document", id)).await;
+        assert_eq!(document, (StatusCode::OK, "%PDF-1.7".to_string()));
+        let (status, events) = get(&app, &format!("/jobs/{}/events", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(events.starts_with("event: result\n"), "{}", events);
+
+        let (status, _) = send(&app, "DELETE", &format!("/jobs/{}", id), Body::empty()).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(get(&app, &format!("/jobs/{}", id)).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&app, &format!("/jobs/{}/document", id)).await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.sqlite");
+        let id = {
+            let jobs = JobStore::new(JobDb::open(&path).unwrap());
+            jobs.create(None, None).unwrap().snapshot().id
+        };
+
+        let app = app(open_db(Some(&path)).unwrap(), dir.path());
+        let (status, body) = get(&app, &format!("/jobs/{}", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let snapshot: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(snapshot["status"], "failed");
+        let (_, events) = get(&app, &format!("/jobs/{}/events", id)).await;
+        assert!(events.starts_with("event: error\n"), "{}", events);
+        assert!(events.contains("Interrupted by server restart"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = app(open_db(Some(std::path::Path::new(":memory:"))).unwrap(), dir.path());
+
+        let (status, body) = send(&app, "POST", "/jobs", Body::empty()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Empty request body"));
+        let (status, body) = send(&app, "POST", "/extract?variant=tiny", Body::from("x")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Unknown model variant 'tiny'"));
+        // Over the 1 MB limit
+        let (status, _) = send(&app, "POST", "/jobs", Body::from(vec![0u8; 2 << 20])).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(get(&app, "/jobs").await, (StatusCode::OK, "[]".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_read_multipart_upload() {
+        let form = |fields: &str| {
+            Request::builder()
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=X")
+                .body(Body::from(format!("{}--X--\r\n", fields)))
+                .unwrap()
+        };
+        let field = |name: &str, filename: Option<&str>, data: &str| {
+            let filename = filename.map_or(String::new(), |f| format!("; filename=\"{}\"", f));
+            format!(
+                "--X\r\nContent-Disposition: form-data; name=\"{}\"{}\r\n\r\n{}\r\n",
+                name, filename, data
+            )
+        };
+
+        let fields = field("note", None, "skip me") + &field("upload", Some("fv.pdf"), "%PDF");
+        let upload = read_upload(form(&fields)).await.ok().unwrap();
+        assert_eq!(upload.filename.as_deref(), Some("fv.pdf"));
+        assert_eq!(&upload.data[..], b"%PDF");
+
+        let upload = read_upload(form(&field("file", None, "%PDF"))).await.ok().unwrap();
+        assert_eq!(upload.filename, None);
+
+        let response = read_upload(form(&field("note", None, "text"))).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
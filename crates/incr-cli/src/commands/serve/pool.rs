@@ -0,0 +1,160 @@
+//! OCR engines shared by all requests, one per model variant.
+//!
+//! The default variant is loaded at startup; others are loaded on the first
+//! request that asks for them and kept for the lifetime of the server.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use incr_core::models::config::IncrConfig;
+use incr_core::PureOcrEngine;
+
+use crate::commands::engines::{is_loaded, shared_engine};
+use crate::commands::variant::{get_variant_dir, ModelVariant};
+
+/// Engines by variant, kept in the CLI's engine cache.
+pub struct ModelPool {
+    config: Arc<IncrConfig>,
+    default: ModelVariant,
+    default_dir: PathBuf,
+}
+
+/// A model variant as listed by `GET /models`.
+#[derive(Debug, Serialize)]
+pub struct ModelStatus {
+    pub variant: String,
+    pub dir: PathBuf,
+    /// The variant's model files are present.
+    pub installed: bool,
+    /// An engine for the variant is loaded.
+    pub loaded: bool,
+    /// Used when a request names no variant.
+    pub default: bool,
+}
+
+impl ModelPool {
+    /// Load the engine for `default` from `default_dir` and warm it up, so
+    /// the first request is not slowed down by one-time model setup.
+    ///
+    /// Without model files there, the default engine uses the embedded
+    /// mobile models.
+    pub fn new(
+        config: Arc<IncrConfig>,
+        default: ModelVariant,
+        default_dir: PathBuf,
+    ) -> anyhow::Result<Self> {
+        let engine = shared_engine(&default_dir, &config)?;
+        if let Err(e) = engine.warmup() {
+            warn!("Warming up the {} models failed: {}", default, e);
+        }
+
+        Ok(Self::unloaded(config, default, default_dir))
+    }
+
+    /// A pool that loads every engine on first use, the default one too.
+    pub(super) fn unloaded(
+        config: Arc<IncrConfig>,
+        default: ModelVariant,
+        default_dir: PathBuf,
+    ) -> Self {
+        Self {
+            config,
+            default,
+            default_dir,
+        }
+    }
+
+    /// Directory of the default variant's models.
+    pub fn default_dir(&self) -> &PathBuf {
+        &self.default_dir
+    }
+
+    /// Whether `variant` can be served: the default always can, other
+    /// variants once their models are downloaded.
+    pub fn is_available(&self, variant: ModelVariant) -> bool {
+        variant == self.default || self.is_installed(variant)
+    }
+
+    /// The engine for `variant` (default: the default variant), loading it
+    /// on first use.
+    pub fn engine(&self, variant: Option<ModelVariant>) -> anyhow::Result<Arc<PureOcrEngine>> {
+        let variant = variant.unwrap_or(self.default);
+        if !self.is_available(variant) {
+            anyhow::bail!(
+                "{} models are not installed (run `incr models download -v {}`)",
+                variant,
+                variant
+            );
+        }
+
+        let dir = self.dir(variant);
+        if !is_loaded(&dir, &self.config) {
+            info!("Loading {} models", variant);
+        }
+        shared_engine(&dir, &self.config)
+    }
+
+    /// Every variant with its installation and load state.
+    pub fn list(&self) -> Vec<ModelStatus> {
+        ModelVariant::value_variants()
+            .iter()
+            .map(|&variant| ModelStatus {
+                variant: variant.to_string(),
+                dir: self.dir(variant),
+                installed: self.is_installed(variant),
+                loaded: is_loaded(&self.dir(variant), &self.config),
+                default: variant == self.default,
+            })
+            .collect()
+    }
+
+    fn dir(&self, variant: ModelVariant) -> PathBuf {
+        if variant == self.default {
+            self.default_dir.clone()
+        } else {
+            get_variant_dir(variant)
+        }
+    }
+
+    fn is_installed(&self, variant: ModelVariant) -> bool {
+        self.dir(variant).join(&self.config.models.detection_model).exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_variant() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = IncrConfig::default();
+        // Cyrillic models that aren't installed fail to load right away
+        config.models.languages = vec!["uk".to_string()];
+        let pool = ModelPool::unloaded(Arc::new(config), ModelVariant::Mobile, dir.path().into());
+
+        let mobile = |pool: &ModelPool| {
+            pool.list().into_iter().find(|status| status.default).unwrap()
+        };
+        let status = mobile(&pool);
+        assert_eq!(status.variant, "mobile");
+        assert_eq!(status.dir, dir.path());
+        assert!(!status.installed);
+        assert!(!status.loaded);
+        assert_eq!(pool.list().len(), ModelVariant::value_variants().len());
+        // The default variant can always be asked for
+        assert!(pool.is_available(ModelVariant::Mobile));
+
+        std::fs::write(dir.path().join("det.onnx"), b"").unwrap();
+        assert!(mobile(&pool).installed);
+        for _ in 0..2 {
+            let error = pool.engine(None).err().unwrap();
+            assert!(error.to_string().contains("cyrillic_rec.onnx"), "{}", error);
+        }
+        assert!(!mobile(&pool).loaded);
+    }
+}
@@ -0,0 +1,292 @@
+//! SQLite persistence for extraction jobs.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use uuid::Uuid;
+
+use super::jobs::{JobSnapshot, JobStatus};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id          TEXT PRIMARY KEY,
+    status      TEXT NOT NULL,
+    filename    TEXT,
+    created_at  TEXT NOT NULL,
+    finished_at TEXT,
+    result      TEXT,
+    error       TEXT,
+    document    BLOB
+);
+CREATE INDEX IF NOT EXISTS jobs_created_at ON jobs (created_at);
+";
+
+const SNAPSHOT_COLUMNS: &str = "id, status, filename, created_at, finished_at, result, error";
+
+/// Job database.
+pub struct JobDb {
+    conn: Mutex<Connection>,
+}
+
+impl JobDb {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Open a database that lives only as long as the process.
+    pub fn in_memory() -> anyhow::Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Record a newly submitted job, optionally with the uploaded document.
+    pub fn insert(&self, job: &JobSnapshot, document: Option<&[u8]>) -> anyhow::Result<()> {
+        self.lock().execute(
+            "INSERT INTO jobs (id, status, filename, created_at, document) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                job.id.to_string(),
+                job.status.as_str(),
+                job.filename,
+                job.created_at.to_rfc3339(),
+                document,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Store the final state of a job.
+    pub fn finish(&self, job: &JobSnapshot) -> anyhow::Result<()> {
+        let result = job.result.as_ref().map(serde_json::to_string).transpose()?;
+
+        self.lock().execute(
+            "UPDATE jobs SET status = ?2, finished_at = ?3, result = ?4, error = ?5 WHERE id = ?1",
+            params![
+                job.id.to_string(),
+                job.status.as_str(),
+                job.finished_at.map(|t| t.to_rfc3339()),
+                result,
+                job.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Load a job by id.
+    pub fn get(&self, id: &Uuid) -> anyhow::Result<Option<JobSnapshot>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM jobs WHERE id = ?1", SNAPSHOT_COLUMNS))?;
+
+        let job = stmt
+            .query_row(params![id.to_string()], read_snapshot)
+            .optional()?;
+
+        job.transpose()
+    }
+
+    /// Most recent jobs first, without their results.
+    pub fn list(&self, status: Option<JobStatus>, limit: usize) -> anyhow::Result<Vec<JobSnapshot>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM jobs WHERE ?1 IS NULL OR status = ?1 ORDER BY created_at DESC LIMIT ?2",
+            SNAPSHOT_COLUMNS
+        ))?;
+
+        let rows = stmt.query_map(
+            params![status.map(|s| s.as_str()), limit as i64],
+            read_snapshot,
+        )?;
+
+        let mut jobs = Vec::new();
+        for row in rows {
+            let mut job = row??;
+            job.result = None;
+            jobs.push(job);
+        }
+        Ok(jobs)
+    }
+
+    /// The original uploaded document, if it was stored.
+    pub fn document(&self, id: &Uuid) -> anyhow::Result<Option<Vec<u8>>> {
+        let document = self
+            .lock()
+            .query_row(
+                "SELECT document FROM jobs WHERE id = ?1",
+                params![id.to_string()],
+                |row| row.get::<_, Option<Vec<u8>>>(0),
+            )
+            .optional()?;
+
+        Ok(document.flatten())
+    }
+
+    /// Delete a job. Returns whether it existed.
+    pub fn delete(&self, id: &Uuid) -> anyhow::Result<bool> {
+        let deleted = self
+            .lock()
+            .execute("DELETE FROM jobs WHERE id = ?1", params![id.to_string()])?;
+        Ok(deleted > 0)
+    }
+
+    /// Delete jobs created before `cutoff`. Returns the number removed.
+    pub fn purge_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<usize> {
+        let deleted = self.lock().execute(
+            "DELETE FROM jobs WHERE created_at < ?1",
+            params![cutoff.to_rfc3339()],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Mark jobs left unfinished by a previous server run as failed.
+    pub fn fail_unfinished(&self) -> anyhow::Result<usize> {
+        let updated = self.lock().execute(
+            "UPDATE jobs SET status = ?1, error = ?2, finished_at = ?3 WHERE status IN (?4, ?5)",
+            params![
+                JobStatus::Failed.as_str(),
+                "Interrupted by server restart",
+                Utc::now().to_rfc3339(),
+                JobStatus::Queued.as_str(),
+                JobStatus::Running.as_str(),
+            ],
+        )?;
+        Ok(updated)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn read_snapshot(row: &Row<'_>) -> rusqlite::Result<anyhow::Result<JobSnapshot>> {
+    let id: String = row.get(0)?;
+    let status: String = row.get(1)?;
+    let filename: Option<String> = row.get(2)?;
+    let created_at: String = row.get(3)?;
+    let finished_at: Option<String> = row.get(4)?;
+    let result: Option<String> = row.get(5)?;
+    let error: Option<String> = row.get(6)?;
+
+    Ok((|| {
+        Ok(JobSnapshot {
+            id: id.parse()?,
+            status: status.parse()?,
+            filename,
+            created_at: parse_time(&created_at)?,
+            finished_at: finished_at.as_deref().map(parse_time).transpose()?,
+            progress: None,
+            result: result.as_deref().map(serde_json::from_str).transpose()?,
+            error,
+        })
+    })())
+}
+
+fn parse_time(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use incr_core::Invoice;
+
+    fn snapshot(status: JobStatus) -> JobSnapshot {
+        JobSnapshot {
+            id: Uuid::new_v4(),
+            status,
+            filename: Some("fv.pdf".to_string()),
+            created_at: Utc::now(),
+            finished_at: None,
+            progress: None,
+            result: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        // The parent directory is created with the schema
+        let db = JobDb::open(&dir.path().join("state").join("jobs.db")).unwrap();
+
+        let mut job = snapshot(JobStatus::Queued);
+        db.insert(&job, Some(b"%PDF-1.7")).unwrap();
+        assert_eq!(db.get(&job.id).unwrap().unwrap().status, JobStatus::Queued);
+        assert_eq!(db.document(&job.id).unwrap().as_deref(), Some(&b"%PDF-1.7"[..]));
+
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        job.status = JobStatus::Completed;
+        job.finished_at = Some(Utc::now());
+        job.result = Some(invoice);
+        db.finish(&job).unwrap();
+
+        let stored = db.get(&job.id).unwrap().unwrap();
+        assert_eq!(stored.status, JobStatus::Completed);
+        assert_eq!(stored.filename.as_deref(), Some("fv.pdf"));
+        assert!(stored.finished_at.is_some());
+        assert_eq!(stored.result.unwrap().header.invoice_number, "FV/1/2024");
+
+        // Listing leaves the results out
+        let failed = snapshot(JobStatus::Failed);
+        db.insert(&failed, None).unwrap();
+        let jobs = db.list(None, 10).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs.iter().all(|job| job.result.is_none()));
+        let completed = db.list(Some(JobStatus::Completed), 10).unwrap();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].id, job.id);
+        assert_eq!(db.document(&failed.id).unwrap(), None);
+
+        assert!(db.delete(&job.id).unwrap());
+        assert!(!db.delete(&job.id).unwrap());
+        assert!(db.get(&job.id).unwrap().is_none());
+        assert_eq!(db.purge_before(Utc::now() + chrono::Duration::seconds(1)).unwrap(), 1);
+        assert!(db.list(None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restart_fails_unfinished_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.db");
+
+        let (queued, running, failed) = (
+            snapshot(JobStatus::Queued),
+            snapshot(JobStatus::Running),
+            snapshot(JobStatus::Failed),
+        );
+        {
+            let db = JobDb::open(&path).unwrap();
+            for job in [&queued, &running, &failed] {
+                db.insert(job, None).unwrap();
+            }
+        }
+
+        // The jobs survive a restart; the ones that were in flight failed
+        let db = JobDb::open(&path).unwrap();
+        assert_eq!(db.fail_unfinished().unwrap(), 2);
+        for job in [&queued, &running] {
+            let job = db.get(&job.id).unwrap().unwrap();
+            assert_eq!(job.status, JobStatus::Failed);
+            assert_eq!(job.error.as_deref(), Some("Interrupted by server restart"));
+            assert!(job.finished_at.is_some());
+        }
+        let failed = db.get(&failed.id).unwrap().unwrap();
+        assert_eq!(failed.error, None);
+        assert_eq!(db.fail_unfinished().unwrap(), 0);
+    }
+}
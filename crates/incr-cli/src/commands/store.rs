@@ -0,0 +1,313 @@
+//! SQLite store of processed documents and their extraction results.
+//!
+//! Documents are keyed by the SHA-256 of their content, so a file that was
+//! renamed or copied is still recognized. `batch` records every file it
+//! processes and skips the ones already extracted; `query` lists them.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use incr_core::models::invoice::Invoice;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS extractions (
+    sha256             TEXT PRIMARY KEY,
+    path               TEXT NOT NULL,
+    invoice_number     TEXT,
+    issuer_nip         TEXT,
+    receiver_nip       TEXT,
+    issue_date         TEXT,
+    total_gross        TEXT,
+    currency           TEXT,
+    result             TEXT,
+    error              TEXT,
+    first_processed_at TEXT NOT NULL,
+    processed_at       TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS extractions_processed_at ON extractions (processed_at);
+CREATE INDEX IF NOT EXISTS extractions_issuer_nip ON extractions (issuer_nip);
+CREATE INDEX IF NOT EXISTS extractions_receiver_nip ON extractions (receiver_nip);
+";
+
+const COLUMNS: &str = "sha256, path, invoice_number, issuer_nip, receiver_nip, issue_date, \
+    total_gross, currency, result, error, first_processed_at, processed_at";
+
+/// A processed document as stored.
+#[derive(Debug, Serialize)]
+pub struct StoredExtraction {
+    pub sha256: String,
+    /// Path the document was last processed from.
+    pub path: String,
+    pub invoice_number: Option<String>,
+    pub issuer_nip: Option<String>,
+    pub receiver_nip: Option<String>,
+    pub issue_date: Option<NaiveDate>,
+    pub total_gross: Option<String>,
+    pub currency: Option<String>,
+    /// The extracted invoice; `None` if extraction failed.
+    pub invoice: Option<Invoice>,
+    pub error: Option<String>,
+    pub first_processed_at: DateTime<Utc>,
+    pub processed_at: DateTime<Utc>,
+}
+
+/// Conditions for [`ResultStore::query`]; unset fields match everything.
+#[derive(Debug, Default)]
+pub struct QueryFilter {
+    /// Issuer or receiver NIP, in any format.
+    pub nip: Option<String>,
+    /// Part of the invoice number.
+    pub number: Option<String>,
+    /// Issued on or after.
+    pub issued_from: Option<NaiveDate>,
+    /// Issued on or before.
+    pub issued_to: Option<NaiveDate>,
+    /// Last processed on or after (UTC).
+    pub processed_since: Option<NaiveDate>,
+    /// Only documents whose extraction failed.
+    pub failed: bool,
+    pub limit: usize,
+}
+
+/// Results database.
+pub struct ResultStore {
+    conn: Mutex<Connection>,
+}
+
+impl ResultStore {
+    /// Open (or create) the database at `path`.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Whether the document with this hash has been extracted successfully.
+    pub fn is_extracted(&self, sha256: &str) -> anyhow::Result<bool> {
+        let found = self
+            .lock()
+            .query_row(
+                "SELECT 1 FROM extractions WHERE sha256 = ?1 AND error IS NULL",
+                params![sha256],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Record the outcome of processing a document, replacing an earlier
+    /// result for the same content.
+    pub fn record(
+        &self,
+        sha256: &str,
+        path: &Path,
+        outcome: Result<&Invoice, String>,
+    ) -> anyhow::Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let (invoice, error) = match outcome {
+            Ok(invoice) => (Some(invoice), None),
+            Err(error) => (None, Some(error)),
+        };
+        let result = invoice.map(serde_json::to_string).transpose()?;
+
+        self.lock().execute(
+            "INSERT INTO extractions (sha256, path, invoice_number, issuer_nip, receiver_nip, \
+                issue_date, total_gross, currency, result, error, first_processed_at, processed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)
+             ON CONFLICT (sha256) DO UPDATE SET
+                path = excluded.path,
+                invoice_number = excluded.invoice_number,
+                issuer_nip = excluded.issuer_nip,
+                receiver_nip = excluded.receiver_nip,
+                issue_date = excluded.issue_date,
+                total_gross = excluded.total_gross,
+                currency = excluded.currency,
+                result = excluded.result,
+                error = excluded.error,
+                processed_at = excluded.processed_at",
+            params![
+                sha256,
+                path.to_string_lossy(),
+                invoice
+                    .map(|i| i.header.invoice_number.as_str())
+                    .filter(|n| !n.is_empty()),
+                invoice.and_then(|i| i.issuer.nip.as_deref()).map(nip_digits),
+                invoice.and_then(|i| i.receiver.nip.as_deref()).map(nip_digits),
+                invoice.and_then(|i| i.header.issue_date).map(|d| d.to_string()),
+                invoice.map(|i| i.summary.total_gross.to_string()),
+                invoice.map(|i| i.header.currency.as_str()),
+                result,
+                error,
+                now,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Stored documents matching `filter`, most recently processed first.
+    pub fn query(&self, filter: &QueryFilter) -> anyhow::Result<Vec<StoredExtraction>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM extractions
+             WHERE (?1 IS NULL OR issuer_nip = ?1 OR receiver_nip = ?1)
+               AND (?2 IS NULL OR invoice_number LIKE '%' || ?2 || '%')
+               AND (?3 IS NULL OR issue_date >= ?3)
+               AND (?4 IS NULL OR issue_date <= ?4)
+               AND (?5 IS NULL OR processed_at >= ?5)
+               AND (?6 = 0 OR error IS NOT NULL)
+             ORDER BY processed_at DESC
+             LIMIT ?7",
+            COLUMNS
+        ))?;
+
+        let rows = stmt.query_map(
+            params![
+                filter.nip.as_deref().map(nip_digits),
+                filter.number,
+                filter.issued_from.map(|d| d.to_string()),
+                filter.issued_to.map(|d| d.to_string()),
+                filter.processed_since.map(|d| d.to_string()),
+                filter.failed,
+                filter.limit as i64,
+            ],
+            read_extraction,
+        )?;
+
+        let mut extractions = Vec::new();
+        for row in rows {
+            extractions.push(row??);
+        }
+        Ok(extractions)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A NIP as stored: digits only, so `526-104-08-28` and `5261040828` match.
+fn nip_digits(nip: &str) -> String {
+    nip.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn read_extraction(row: &Row<'_>) -> rusqlite::Result<anyhow::Result<StoredExtraction>> {
+    let sha256: String = row.get(0)?;
+    let path: String = row.get(1)?;
+    let invoice_number: Option<String> = row.get(2)?;
+    let issuer_nip: Option<String> = row.get(3)?;
+    let receiver_nip: Option<String> = row.get(4)?;
+    let issue_date: Option<String> = row.get(5)?;
+    let total_gross: Option<String> = row.get(6)?;
+    let currency: Option<String> = row.get(7)?;
+    let result: Option<String> = row.get(8)?;
+    let error: Option<String> = row.get(9)?;
+    let first_processed_at: String = row.get(10)?;
+    let processed_at: String = row.get(11)?;
+
+    Ok((|| {
+        Ok(StoredExtraction {
+            sha256,
+            path,
+            invoice_number,
+            issuer_nip,
+            receiver_nip,
+            issue_date: issue_date.as_deref().map(str::parse).transpose()?,
+            total_gross,
+            currency,
+            invoice: result.as_deref().map(serde_json::from_str).transpose()?,
+            error,
+            first_processed_at: parse_time(&first_processed_at)?,
+            processed_at: parse_time(&processed_at)?,
+        })
+    })())
+}
+
+fn parse_time(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = "FV/12/2024".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 3, 15);
+        invoice.issuer.nip = Some("526-104-08-28".to_string());
+        invoice.summary.total_gross = "123.00".parse().unwrap();
+        invoice
+    }
+
+    fn all() -> QueryFilter {
+        QueryFilter {
+            limit: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store").join("results.sqlite");
+        let store = ResultStore::open(&path).unwrap();
+
+        store.record("aa", Path::new("a.pdf"), Err("unreadable PDF".to_string())).unwrap();
+        assert!(!store.is_extracted("aa").unwrap());
+        let failed = store.query(&QueryFilter { failed: true, ..all() }).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_deref(), Some("unreadable PDF"));
+        assert!(failed[0].invoice.is_none());
+
+        // A later success replaces the failure, from the new path
+        store.record("aa", Path::new("renamed.pdf"), Ok(&invoice())).unwrap();
+        store.record("bb", Path::new("b.pdf"), Ok(&Invoice::new())).unwrap();
+        drop(store);
+
+        let store = ResultStore::open(&path).unwrap();
+        assert!(store.is_extracted("aa").unwrap());
+        assert!(store.query(&QueryFilter { failed: true, ..all() }).unwrap().is_empty());
+
+        let found = store
+            .query(&QueryFilter {
+                nip: Some("5261040828".to_string()),
+                ..all()
+            })
+            .unwrap();
+        let [stored] = found.as_slice() else {
+            panic!("expected one extraction: {:?}", found);
+        };
+        assert_eq!(stored.path, "renamed.pdf");
+        assert_eq!(stored.issuer_nip.as_deref(), Some("5261040828"));
+        assert_eq!(stored.invoice_number.as_deref(), Some("FV/12/2024"));
+        assert_eq!(stored.total_gross.as_deref(), Some("123.00"));
+        assert!(stored.first_processed_at <= stored.processed_at);
+        assert_eq!(stored.invoice.as_ref().unwrap().header.invoice_number, "FV/12/2024");
+
+        let number = |number: &str| QueryFilter {
+            number: Some(number.to_string()),
+            ..all()
+        };
+        assert_eq!(store.query(&number("12/2024")).unwrap().len(), 1);
+        assert!(store.query(&number("13/2024")).unwrap().is_empty());
+        let issued = QueryFilter {
+            issued_from: NaiveDate::from_ymd_opt(2024, 3, 1),
+            issued_to: NaiveDate::from_ymd_opt(2024, 3, 31),
+            ..all()
+        };
+        assert_eq!(store.query(&issued).unwrap().len(), 1);
+        assert_eq!(store.query(&all()).unwrap().len(), 2);
+        assert_eq!(store.query(&QueryFilter { limit: 1, ..all() }).unwrap().len(), 1);
+    }
+}
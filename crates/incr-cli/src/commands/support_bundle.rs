@@ -0,0 +1,336 @@
+//! Support bundle command - package everything needed to reproduce an
+//! extraction problem.
+//!
+//! The document is extracted again while the debug log is captured, and
+//! the document, text layer, OCR results, parsed text, result (or error),
+//! effective configuration, model versions and log are written to one ZIP
+//! archive to attach to a bug report. Nothing is written without the user's
+//! confirmation; `--redact` leaves out the document and masks party data in
+//! everything else.
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+use clap::Args;
+use console::style;
+use serde_json::json;
+use tracing::Level;
+
+use incr_core::archive::ZipWriter;
+use incr_core::audit::{config_hash, sha256_hex};
+use incr_core::invoice::{InvoiceParser, Redactor};
+use incr_core::models::capabilities::Capabilities;
+use incr_core::models::config::{IncrConfig, Preset};
+use incr_core::models::invoice::{Invoice, SourceType};
+use incr_core::ocr::OcrResult;
+use incr_core::pdf::{PdfExtractor, PdfProcessor, PdfType};
+
+use super::audit::model_versions;
+use super::engines::shared_engine;
+use super::variant::{get_variant_dir, resolve_variant};
+use super::{file_date, load_config, merge_capabilities, parser_from_config};
+
+/// Arguments for the support-bundle command.
+#[derive(Args)]
+pub struct SupportBundleArgs {
+    /// Document that extracts incorrectly (PDF or image)
+    #[arg(required = true)]
+    input: PathBuf,
+
+    /// Archive to write (default: incr-support-<timestamp>.zip)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Model directory
+    #[arg(short, long)]
+    model_dir: Option<PathBuf>,
+
+    /// Leave out the document and mask names, identifiers, addresses,
+    /// account numbers and contact data
+    #[arg(long)]
+    redact: bool,
+
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+/// Intermediate results of an extraction.
+#[derive(Default)]
+struct Artifacts {
+    pdf_type: Option<PdfType>,
+    text_layer: Option<String>,
+    /// OCR result of each image, by name (`page-1-1`, `image`).
+    ocr: Vec<(String, OcrResult)>,
+    /// Text the invoice was parsed from.
+    text: Option<String>,
+}
+
+/// Debug log collected while the document is extracted.
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub async fn run(
+    args: SupportBundleArgs,
+    config_path: Option<&str>,
+    profile: Option<&str>,
+    preset: Option<Preset>,
+) -> anyhow::Result<()> {
+    // The settings `process` would use for the document
+    let config = load_config(config_path, profile, preset, "process")?;
+
+    if !args.input.exists() {
+        anyhow::bail!("Input file not found: {}", args.input.display());
+    }
+    let file_name = args
+        .input
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("document")
+        .to_string();
+
+    println!("{}", style("The support bundle will contain:").bold());
+    if args.redact {
+        println!("  - document fingerprint (the document itself is left out)");
+    } else {
+        println!("  - the document {}", file_name);
+    }
+    println!("  - its text layer, OCR results and parsed text");
+    println!("  - the extraction result or error");
+    println!("  - the effective configuration, model versions and environment");
+    println!("  - the debug log of the extraction");
+    if args.redact {
+        println!(
+            "Identifiers, account numbers and email addresses are masked, and so are names,\n\
+             addresses and contact data wherever extraction found them."
+        );
+    } else {
+        println!(
+            "{} Invoices contain personal and business data; use --redact to mask it.",
+            style("!").yellow()
+        );
+    }
+
+    if !args.yes && !confirm("Create the bundle?")? {
+        println!("No bundle written.");
+        return Ok(());
+    }
+
+    let model_dir = args
+        .model_dir
+        .clone()
+        .unwrap_or_else(|| get_variant_dir(resolve_variant(&config)));
+
+    // Capture the debug log of the extraction
+    let log = LogBuffer::default();
+    let writer = log.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_ansi(false)
+        .with_target(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    let mut artifacts = Artifacts::default();
+    let result = tracing::subscriber::with_default(subscriber, || {
+        extract(&args.input, &config, &model_dir, &mut artifacts)
+    });
+    let log = String::from_utf8_lossy(&log.0.lock().unwrap_or_else(|e| e.into_inner())).into_owned();
+
+    let redactor = args.redact.then(|| {
+        let own = config.extraction.own_nips.iter().cloned();
+        let templates = config.extraction.load_templates().unwrap_or_default();
+        let templates = templates.into_iter().map(|t| t.name);
+        let values = own.chain(templates).chain([file_name.clone()]);
+        match &result {
+            Ok(invoice) => Redactor::from_invoice(invoice),
+            Err(_) => Redactor::new(),
+        }
+        .with_values(values)
+    });
+    let redact_text = |text: &str| match &redactor {
+        Some(redactor) => redactor.text(text),
+        None => text.to_string(),
+    };
+
+    let data = fs::read(&args.input)?;
+    let mut zip = ZipWriter::new();
+
+    if !args.redact {
+        zip.add(&format!("input/{}", file_name), &data)?;
+    }
+
+    let environment = json!({
+        "incr_version": env!("CARGO_PKG_VERSION"),
+        "created": Local::now().to_rfc3339(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "profile": profile,
+        "preset": config.preset.map(|p| p.name()),
+        "config_hash": config_hash(&config),
+        "model_dir": model_dir.display().to_string(),
+        "embedded_models": !model_dir.join(&config.models.detection_model).exists(),
+        "models": model_versions(&config, &model_dir),
+        "document": {
+            "name": (!args.redact).then_some(&file_name),
+            "sha256": sha256_hex(&data),
+            "size": data.len(),
+            "pdf_type": artifacts.pdf_type.map(|t| format!("{:?}", t)),
+        },
+    });
+    zip.add("environment.json", &serde_json::to_vec_pretty(&environment)?)?;
+
+    let config_json = serde_json::to_string_pretty(&config.without_secrets())?;
+    zip.add("config.json", redact_text(&config_json).as_bytes())?;
+
+    if let Some(text) = &artifacts.text_layer {
+        zip.add("text-layer.txt", redact_text(text).as_bytes())?;
+    }
+    for (name, ocr) in &mut artifacts.ocr {
+        ocr.text = redact_text(&ocr.text);
+        for text_box in &mut ocr.boxes {
+            text_box.text = redact_text(&text_box.text);
+        }
+        zip.add(&format!("ocr/{}.json", name), &serde_json::to_vec_pretty(ocr)?)?;
+    }
+    if let Some(text) = &artifacts.text {
+        zip.add("text.txt", redact_text(text).as_bytes())?;
+    }
+
+    match &result {
+        Ok(invoice) => {
+            let invoice = match &redactor {
+                Some(redactor) => redactor.invoice(invoice),
+                None => invoice.clone(),
+            };
+            zip.add("result.json", &serde_json::to_vec_pretty(&invoice)?)?;
+        }
+        Err(e) => zip.add("error.txt", redact_text(&format!("{:#}", e)).as_bytes())?,
+    }
+    zip.add("log.txt", redact_text(&log).as_bytes())?;
+
+    let output = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!("incr-support-{}.zip", Local::now().format("%Y%m%d-%H%M%S")))
+    });
+    fs::write(&output, zip.finish()?)?;
+
+    match &result {
+        Ok(_) => println!("{} Document extracted", style("✓").green()),
+        Err(e) => println!("{} Extraction failed: {}", style("✗").red(), e),
+    }
+    println!(
+        "{} Support bundle written to {}",
+        style("✓").green(),
+        output.display()
+    );
+    println!("   Review it before attaching it to a bug report.");
+
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal.
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("Confirmation needed; pass --yes to create the bundle non-interactively");
+    }
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Extract the document as `process` does, keeping the intermediate
+/// results. The OCR engine is only loaded if OCR is needed.
+fn extract(
+    path: &Path,
+    config: &IncrConfig,
+    model_dir: &Path,
+    artifacts: &mut Artifacts,
+) -> anyhow::Result<Invoice> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let (text, source_type, capabilities) = match extension.as_str() {
+        "pdf" => {
+            let mut extractor = PdfExtractor::new();
+            extractor.load(&fs::read(path)?)?;
+            let pdf_type = extractor.analyze();
+            artifacts.pdf_type = Some(pdf_type);
+            artifacts.text_layer = extractor.extract_text().ok();
+
+            let text_layer = artifacts.text_layer.clone().unwrap_or_default();
+            let use_text_layer = matches!(pdf_type, PdfType::Text | PdfType::Hybrid)
+                && config.pdf.prefer_embedded_text
+                && text_layer.len() >= config.pdf.min_text_length;
+
+            let source_type = match pdf_type {
+                PdfType::Text => SourceType::TextPdf,
+                PdfType::Image => SourceType::ImagePdf,
+                PdfType::Hybrid => SourceType::HybridPdf,
+                PdfType::Empty => SourceType::Unknown,
+            };
+
+            if use_text_layer {
+                (text_layer, source_type, Capabilities::text_layer())
+            } else {
+                let engine = shared_engine(model_dir, config)?;
+                let mut texts = Vec::new();
+                for page in 1..=extractor.page_count() {
+                    for (i, image) in extractor.extract_images(page)?.iter().enumerate() {
+                        let result = engine
+                            .process(image)
+                            .map_err(|e| anyhow::anyhow!("OCR failed on page {}: {}", page, e))?;
+                        texts.push(result.text.clone());
+                        artifacts.ocr.push((format!("page-{}-{}", page, i + 1), result));
+                    }
+                }
+                let capabilities = merge_capabilities(artifacts.ocr.iter().map(|(_, r)| &r.capabilities));
+                (texts.join("\n\n"), source_type, capabilities)
+            }
+        }
+        "png" | "jpg" | "jpeg" | "tiff" | "bmp" => {
+            let image = image::open(path)?;
+            let engine = shared_engine(model_dir, config)?;
+            let result = engine
+                .process(&image)
+                .map_err(|e| anyhow::anyhow!("OCR failed: {}", e))?;
+            let capabilities = merge_capabilities([&result.capabilities]);
+            let text = result.text.clone();
+            artifacts.ocr.push(("image".to_string(), result));
+            (text, SourceType::Image, capabilities)
+        }
+        _ => anyhow::bail!("Unsupported file format: {}", extension),
+    };
+
+    artifacts.text = Some(text.clone());
+    if text.trim().is_empty() {
+        anyhow::bail!("No text could be extracted from the document");
+    }
+
+    let parser = parser_from_config(config)?.with_reference_date(file_date(path));
+
+    let mut invoice = parser.parse(&text)?.invoice;
+    invoice.metadata.source_type = source_type;
+    invoice.metadata.capabilities = capabilities;
+    Ok(invoice)
+}
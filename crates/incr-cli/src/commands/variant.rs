@@ -0,0 +1,143 @@
+//! Model variant selection shared by the commands that run OCR.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock};
+
+use clap::ValueEnum;
+use tracing::warn;
+
+use incr_core::models::config::{IncrConfig, Quality};
+use incr_core::models::embedded::EmbeddedModels;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ModelVariant {
+    /// Mobile models - smaller, faster (~10MB)
+    Mobile,
+    /// Server models - better detection accuracy (~96MB)
+    Server,
+    /// INT8 quantized mobile models - fastest on CPUs (~3MB)
+    Quantized,
+}
+
+impl std::fmt::Display for ModelVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelVariant::Mobile => write!(f, "mobile"),
+            ModelVariant::Server => write!(f, "server"),
+            ModelVariant::Quantized => write!(f, "quantized"),
+        }
+    }
+}
+
+/// Variant used until another one is selected: the most accurate one the
+/// binary embeds.
+#[cfg(feature = "embedded-server-models")]
+const DEFAULT_VARIANT: ModelVariant = ModelVariant::Server;
+#[cfg(not(feature = "embedded-server-models"))]
+const DEFAULT_VARIANT: ModelVariant = ModelVariant::Mobile;
+
+/// Quality selected with `--quality`, overriding the configuration.
+static QUALITY: OnceLock<Quality> = OnceLock::new();
+
+/// Select a quality for the rest of the run (the `--quality` flag).
+pub fn set_quality(quality: Quality) {
+    let _ = QUALITY.set(quality);
+}
+
+/// Get the directory holding the model variants
+pub fn get_models_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("incr")
+        .join("models")
+}
+
+/// Get the model directory for a specific variant
+pub fn get_variant_dir(variant: ModelVariant) -> PathBuf {
+    get_models_dir().join(variant.to_string())
+}
+
+/// Get the variant whose default directory is `dir`, if any
+pub fn variant_of_dir(dir: &Path) -> Option<ModelVariant> {
+    ModelVariant::value_variants()
+        .iter()
+        .copied()
+        .find(|&variant| get_variant_dir(variant) == dir)
+}
+
+/// Get the active variant from config file
+pub fn get_active_variant() -> ModelVariant {
+    let config_path = dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("incr")
+        .join("active_variant");
+
+    match fs::read_to_string(&config_path) {
+        Ok(content) => ModelVariant::from_str(content.trim(), true).unwrap_or(DEFAULT_VARIANT),
+        Err(_) => DEFAULT_VARIANT,
+    }
+}
+
+/// Get the embedded models used when `variant` is not installed: the
+/// server models with the `embedded-server-models` feature, otherwise the
+/// mobile models.
+pub fn embedded_models(variant: ModelVariant) -> (ModelVariant, EmbeddedModels) {
+    #[cfg(feature = "embedded-server-models")]
+    if variant == ModelVariant::Server {
+        return (variant, EmbeddedModels::server());
+    }
+    let _ = variant;
+    (ModelVariant::Mobile, EmbeddedModels::mobile())
+}
+
+/// Whether `variant` can be used without downloading anything: it is
+/// installed or embedded in the binary.
+fn is_available(variant: ModelVariant, config: &IncrConfig) -> bool {
+    if embedded_models(variant).0 == variant {
+        return true;
+    }
+    get_variant_dir(variant).join(&config.models.detection_model).exists()
+}
+
+/// Get the most preferred available variant for `quality`.
+fn variant_for_quality(quality: Quality, config: &IncrConfig) -> ModelVariant {
+    let preferred: Vec<ModelVariant> = quality
+        .variants()
+        .iter()
+        .filter_map(|name| ModelVariant::from_str(name, true).ok())
+        .collect();
+    match preferred.iter().copied().find(|&variant| is_available(variant, config)) {
+        Some(variant) => {
+            if variant != preferred[0] {
+                static FALLBACK_WARNING: Once = Once::new();
+                FALLBACK_WARNING.call_once(|| warn!(
+                    "{} models are not installed, using {} models for --quality {} \
+                     (run `incr models download -v {}`)",
+                    preferred[0], variant, quality.name(), preferred[0]
+                ));
+            }
+            variant
+        }
+        None => preferred[0],
+    }
+}
+
+/// Get the variant selected by `--quality` or the configuration, falling
+/// back to the active variant.
+///
+/// `--quality` wins over the configured variant, which wins over the
+/// configured quality.
+pub fn resolve_variant(config: &IncrConfig) -> ModelVariant {
+    if let Some(&quality) = QUALITY.get() {
+        return variant_for_quality(quality, config);
+    }
+    match (config.models.variant.as_deref(), config.models.quality) {
+        (Some(name), _) => ModelVariant::from_str(name, true).unwrap_or_else(|_| {
+            warn!("Unknown model variant '{}' in config, using active variant", name);
+            get_active_variant()
+        }),
+        (None, Some(quality)) => variant_for_quality(quality, config),
+        (None, None) => get_active_variant(),
+    }
+}
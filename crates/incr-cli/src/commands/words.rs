@@ -0,0 +1,30 @@
+//! Write amounts in Polish words.
+
+use anyhow::Context;
+use clap::Args;
+
+use incr_core::invoice::rules::parse_polish_amount;
+use incr_core::words::format_amount_in_words;
+
+/// Arguments for the words command.
+#[derive(Args)]
+pub struct WordsArgs {
+    /// Amount, e.g. 1230.00 or "1 230,00"
+    #[arg(required = true, allow_hyphen_values = true)]
+    amount: String,
+
+    /// Currency code (ISO 4217)
+    #[arg(long, default_value = "PLN")]
+    currency: String,
+}
+
+pub async fn run(args: WordsArgs) -> anyhow::Result<()> {
+    let amount = args.amount.trim();
+    let value = parse_polish_amount(amount)
+        .filter(|_| amount.chars().any(|c| c.is_ascii_digit()))
+        .with_context(|| format!("Invalid amount '{}'", args.amount))?;
+    let value = if amount.starts_with('-') { -value } else { value };
+
+    println!("{}", format_amount_in_words(value, &args.currency));
+    Ok(())
+}
@@ -0,0 +1,314 @@
+//! Work distribution for batch processing.
+//!
+//! Files can be split statically with `--shard K/N`, or pulled from a
+//! shared Redis queue (`--queue`, requires the `redis-queue` feature) so
+//! several machines can work through one archive.
+//!
+//! There is no NATS queue. Core NATS subjects don't keep messages, so a
+//! queue would need a JetStream work-queue stream with explicit acks and
+//! redelivery, which is a different design from the Redis lists below
+//! and would put the async NATS client inside the synchronous batch
+//! workers. NATS is only used to publish extractions from `serve`.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Static shard selection: shard `index` (1-based) of `count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid shard '{}', expected K/N (e.g. 3/8)", s))?;
+
+        let index: usize = index.trim().parse().map_err(|_| format!("invalid shard index '{}'", index))?;
+        let count: usize = count.trim().parse().map_err(|_| format!("invalid shard count '{}'", count))?;
+
+        if count == 0 || index == 0 || index > count {
+            return Err(format!("shard index must be between 1 and {}", count.max(1)));
+        }
+
+        Ok(Self { index, count })
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Keep every `count`-th item starting at position `index`.
+    pub fn select<T>(&self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % self.count == self.index - 1)
+            .map(|(_, item)| item)
+            .collect()
+    }
+}
+
+/// Source of files to process.
+pub trait WorkSource: Send {
+    /// Take the next file, or `None` when no work is left.
+    fn next_job(&mut self) -> anyhow::Result<Option<PathBuf>>;
+
+    /// Acknowledge that a file taken with [`next_job`](Self::next_job) is
+    /// done (extracted or failed), so it isn't handed out again.
+    fn complete(&mut self, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Number of jobs known to be pending, if available.
+    fn pending(&self) -> Option<u64>;
+}
+
+/// Files resolved locally (after optional sharding).
+pub struct LocalSource {
+    files: std::vec::IntoIter<PathBuf>,
+}
+
+impl LocalSource {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self {
+            files: files.into_iter(),
+        }
+    }
+}
+
+impl WorkSource for LocalSource {
+    fn next_job(&mut self) -> anyhow::Result<Option<PathBuf>> {
+        Ok(self.files.next())
+    }
+
+    fn pending(&self) -> Option<u64> {
+        Some(self.files.len() as u64)
+    }
+}
+
+#[cfg(feature = "redis-queue")]
+pub use redis_queue::RedisQueue;
+
+#[cfg(feature = "redis-queue")]
+mod redis_queue {
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use redis::{Commands, Direction, ExistenceCheck, SetExpiry, SetOptions};
+    use tracing::{debug, info, warn};
+
+    use super::WorkSource;
+
+    /// How long workers wait for another worker to finish seeding the queue,
+    /// and how long a seeding claim lasts, so a worker that dies while
+    /// seeding doesn't block the queue. A drained queue is also kept this
+    /// long, so workers of the same run that start late don't seed it again.
+    const SEED_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// How long a job may stay in progress before it is assumed that its
+    /// worker died and the job is handed to another worker.
+    const JOB_LEASE: Duration = Duration::from_secs(30 * 60);
+
+    /// Shared job queue stored in Redis lists.
+    ///
+    /// The first worker to start seeds the `<name>:jobs` list with all
+    /// matched files. Workers move each job they take to the
+    /// `<name>:processing` list and remove it once the file is done; jobs
+    /// left there longer than [`JOB_LEASE`] by a crashed worker are put
+    /// back on the queue. When both lists are empty the queue expires, so
+    /// the next run with the same name seeds it again. Input files must be
+    /// reachable under the same paths on all machines.
+    pub struct RedisQueue {
+        connection: redis::Connection,
+        name: String,
+        jobs_key: String,
+        processing_key: String,
+        /// Hash of job to the Unix time it was taken.
+        leases_key: String,
+        state_key: String,
+        pending: Option<u64>,
+    }
+
+    impl RedisQueue {
+        /// Connect to the queue and seed it with `files` if no worker has yet.
+        pub fn connect(url: &str, name: &str, files: &[PathBuf]) -> anyhow::Result<Self> {
+            let client = redis::Client::open(url)?;
+            let mut connection = client.get_connection()?;
+
+            let jobs_key = format!("{}:jobs", name);
+            let state_key = format!("{}:state", name);
+
+            // The seeding claim expires, so if its worker dies another one
+            // takes over
+            let claim = SetOptions::default()
+                .conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::EX(SEED_TIMEOUT.as_secs()));
+            let start = Instant::now();
+            loop {
+                let seeding: Option<String> = connection.set_options(&state_key, "seeding", claim)?;
+                if seeding.is_some() {
+                    // Drop jobs pushed by a worker that died while seeding
+                    let _: () = connection.del(&jobs_key)?;
+                    let jobs: Vec<String> =
+                        files.iter().map(|p| p.display().to_string()).collect();
+                    if !jobs.is_empty() {
+                        let _: () = connection.rpush(&jobs_key, jobs)?;
+                    }
+                    let _: () = connection.set(&state_key, "ready")?;
+                    info!("Seeded queue '{}' with {} jobs", name, files.len());
+                    break;
+                }
+
+                let state: Option<String> = connection.get(&state_key)?;
+                if state.as_deref() == Some("ready") {
+                    debug!("Joined existing queue '{}'", name);
+                    break;
+                }
+                if start.elapsed() > SEED_TIMEOUT * 2 {
+                    anyhow::bail!("Timed out waiting for queue '{}' to be seeded", name);
+                }
+                std::thread::sleep(Duration::from_millis(200));
+            }
+
+            let pending: u64 = connection.llen(&jobs_key)?;
+
+            Ok(Self {
+                connection,
+                name: name.to_string(),
+                jobs_key,
+                processing_key: format!("{}:processing", name),
+                leases_key: format!("{}:leases", name),
+                state_key,
+                pending: Some(pending),
+            })
+        }
+
+        /// Put jobs whose lease ran out back on the queue. Returns whether
+        /// there were any.
+        fn requeue_expired(&mut self) -> anyhow::Result<bool> {
+            let jobs: Vec<String> = self.connection.lrange(&self.processing_key, 0, -1)?;
+            let now = unix_time();
+            let mut requeued = false;
+            for job in jobs {
+                let taken: Option<u64> = self.connection.hget(&self.leases_key, &job)?;
+                let Some(taken) = taken else {
+                    // Taken a moment ago, or by a worker that died before
+                    // recording the time: start the lease now
+                    let _: bool = self.connection.hset_nx(&self.leases_key, &job, now)?;
+                    continue;
+                };
+                if now.saturating_sub(taken) < JOB_LEASE.as_secs() {
+                    continue;
+                }
+
+                // Only the worker that removes the job puts it back
+                let removed: i64 = self.connection.lrem(&self.processing_key, 1, &job)?;
+                if removed > 0 {
+                    warn!("Job {} was not finished in time, putting it back on the queue", job);
+                    let _: () = self.connection.hdel(&self.leases_key, &job)?;
+                    let _: () = self.connection.rpush(&self.jobs_key, &job)?;
+                    requeued = true;
+                }
+            }
+            Ok(requeued)
+        }
+
+        /// Let the queue expire once no job is pending or in progress.
+        fn expire_if_drained(&mut self) -> anyhow::Result<()> {
+            let in_progress: u64 = self.connection.llen(&self.processing_key)?;
+            if in_progress == 0 {
+                let _: () = self.connection.del(&self.leases_key)?;
+                let _: () =
+                    self.connection.expire(&self.state_key, SEED_TIMEOUT.as_secs() as i64)?;
+                debug!("Queue '{}' drained", self.name);
+            }
+            Ok(())
+        }
+    }
+
+    impl WorkSource for RedisQueue {
+        fn next_job(&mut self) -> anyhow::Result<Option<PathBuf>> {
+            loop {
+                let job: Option<String> = self.connection.lmove(
+                    &self.jobs_key,
+                    &self.processing_key,
+                    Direction::Left,
+                    Direction::Right,
+                )?;
+                if let Some(job) = job {
+                    let _: () = self.connection.hset(&self.leases_key, &job, unix_time())?;
+                    self.pending = self.pending.map(|p| p.saturating_sub(1));
+                    return Ok(Some(PathBuf::from(job)));
+                }
+
+                if !self.requeue_expired()? {
+                    self.expire_if_drained()?;
+                    return Ok(None);
+                }
+            }
+        }
+
+        fn complete(&mut self, path: &Path) -> anyhow::Result<()> {
+            let job = path.display().to_string();
+            let _: () = self.connection.lrem(&self.processing_key, 1, &job)?;
+            let _: () = self.connection.hdel(&self.leases_key, &job)?;
+            Ok(())
+        }
+
+        fn pending(&self) -> Option<u64> {
+            self.pending
+        }
+    }
+
+    /// Seconds since the Unix epoch.
+    fn unix_time() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!("3/8".parse::<Shard>(), Ok(Shard { index: 3, count: 8 }));
+        assert_eq!(" 1 / 1 ".parse::<Shard>(), Ok(Shard { index: 1, count: 1 }));
+        assert_eq!(
+            "0/0".parse::<Shard>(),
+            Err("shard index must be between 1 and 1".to_string())
+        );
+        assert_eq!(
+            "3/2".parse::<Shard>(),
+            Err("shard index must be between 1 and 2".to_string())
+        );
+        assert_eq!(
+            "0/4".parse::<Shard>(),
+            Err("shard index must be between 1 and 4".to_string())
+        );
+        assert_eq!(
+            "3".parse::<Shard>(),
+            Err("invalid shard '3', expected K/N (e.g. 3/8)".to_string())
+        );
+        assert_eq!("a/2".parse::<Shard>(), Err("invalid shard index 'a'".to_string()));
+        assert_eq!("1/-2".parse::<Shard>(), Err("invalid shard count '-2'".to_string()));
+    }
+
+    #[test]
+    fn test_select_shard() {
+        let shard: Shard = "2/3".parse().unwrap();
+        assert_eq!(shard.to_string(), "2/3");
+        assert_eq!(shard.select((1..=8).collect()), vec![2, 5, 8]);
+    }
+}
@@ -0,0 +1,214 @@
+//! Excel output for batch runs.
+//!
+//! One workbook with an `Invoices` sheet of headers and totals and a
+//! `Line items` sheet that joins on `file` and `invoice_number`. Amounts
+//! are number cells with 2 decimal places (quantities and unit prices with
+//! up to 4), dates are date cells and enums use their JSON names.
+
+use std::path::Path;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_xlsxwriter::{ColNum, Format, RowNum, Workbook, Worksheet, XlsxError};
+
+use incr_core::models::invoice::{Invoice, PaymentMethod};
+
+use super::json_name;
+
+const INVOICE_COLUMNS: &[&str] = &[
+    "file",
+    "invoice_number",
+    "invoice_type",
+    "issue_date",
+    "sale_date",
+    "due_date",
+    "currency",
+    "issuer_name",
+    "issuer_nip",
+    "receiver_name",
+    "receiver_nip",
+    "total_net",
+    "total_vat",
+    "total_gross",
+    "amount_due",
+    "payment_method",
+    "line_items",
+    "confidence",
+];
+
+const LINE_ITEM_COLUMNS: &[&str] = &[
+    "file",
+    "invoice_number",
+    "ordinal",
+    "description",
+    "code",
+    "quantity",
+    "unit",
+    "unit_price_net",
+    "unit_price_gross",
+    "vat_rate",
+    "total_net",
+    "vat_amount",
+    "total_gross",
+    "discount_percent",
+];
+
+/// Cell formats shared by both sheets.
+struct Formats {
+    header: Format,
+    money: Format,
+    unit: Format,
+    date: Format,
+    percent: Format,
+}
+
+/// Write invoices and their line items to the workbook at `path`.
+pub fn write(invoices: &[(&Path, &Invoice)], path: &Path) -> anyhow::Result<()> {
+    let formats = Formats {
+        header: Format::new().set_bold(),
+        money: Format::new().set_num_format("#,##0.00"),
+        unit: Format::new().set_num_format("#,##0.####"),
+        date: Format::new().set_num_format("yyyy-mm-dd"),
+        percent: Format::new().set_num_format("0.0%"),
+    };
+
+    let mut workbook = Workbook::new();
+
+    let sheet = workbook.add_worksheet().set_name("Invoices")?;
+    write_header(sheet, INVOICE_COLUMNS, invoices.len(), &formats)?;
+    for (index, (path, invoice)) in invoices.iter().enumerate() {
+        write_invoice(sheet, index as RowNum + 1, path, invoice, &formats)?;
+    }
+    sheet.autofit();
+
+    let sheet = workbook.add_worksheet().set_name("Line items")?;
+    let items: usize = invoices.iter().map(|(_, invoice)| invoice.line_items.len()).sum();
+    write_header(sheet, LINE_ITEM_COLUMNS, items, &formats)?;
+    let mut row = 1;
+    for (path, invoice) in invoices {
+        for index in 0..invoice.line_items.len() {
+            write_line_item(sheet, row, path, invoice, index, &formats)?;
+            row += 1;
+        }
+    }
+    sheet.autofit();
+
+    workbook.save(path)?;
+    Ok(())
+}
+
+/// Bold header row, frozen and with filters over `rows` data rows.
+fn write_header(
+    sheet: &mut Worksheet,
+    columns: &[&str],
+    rows: usize,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    for (col, name) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, col as ColNum, *name, &formats.header)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, rows as RowNum, columns.len() as ColNum - 1)?;
+    Ok(())
+}
+
+fn write_invoice(
+    sheet: &mut Worksheet,
+    row: RowNum,
+    path: &Path,
+    invoice: &Invoice,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let header = &invoice.header;
+    let summary = &invoice.summary;
+
+    sheet.write_string(row, 0, file_name(path))?;
+    sheet.write_string(row, 1, &header.invoice_number)?;
+    sheet.write_string(row, 2, json_name(&header.invoice_type))?;
+    for (col, date) in [(3, header.issue_date), (4, header.sale_date), (5, header.due_date)] {
+        if let Some(date) = date {
+            sheet.write_datetime_with_format(row, col, date, &formats.date)?;
+        }
+    }
+    sheet.write_string(row, 6, &header.currency)?;
+    sheet.write_string(row, 7, &invoice.issuer.name)?;
+    write_optional_string(sheet, row, 8, invoice.issuer.nip.as_deref())?;
+    sheet.write_string(row, 9, &invoice.receiver.name)?;
+    write_optional_string(sheet, row, 10, invoice.receiver.nip.as_deref())?;
+    write_amount(sheet, row, 11, Some(summary.total_net), &formats.money)?;
+    write_amount(sheet, row, 12, Some(summary.total_vat), &formats.money)?;
+    write_amount(sheet, row, 13, Some(summary.total_gross), &formats.money)?;
+    write_amount(sheet, row, 14, summary.amount_due, &formats.money)?;
+    if let Some(method) = &summary.payment_method {
+        sheet.write_string(row, 15, payment_method_name(method))?;
+    }
+    sheet.write_number(row, 16, invoice.line_items.len() as u32)?;
+    sheet.write_number_with_format(row, 17, invoice.metadata.confidence, &formats.percent)?;
+    Ok(())
+}
+
+fn write_line_item(
+    sheet: &mut Worksheet,
+    row: RowNum,
+    path: &Path,
+    invoice: &Invoice,
+    index: usize,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let item = &invoice.line_items[index];
+
+    sheet.write_string(row, 0, file_name(path))?;
+    sheet.write_string(row, 1, &invoice.header.invoice_number)?;
+    if let Some(ordinal) = item.ordinal {
+        sheet.write_number(row, 2, ordinal)?;
+    }
+    sheet.write_string(row, 3, &item.description)?;
+    write_optional_string(sheet, row, 4, item.code.as_deref())?;
+    write_amount(sheet, row, 5, Some(item.quantity), &formats.unit)?;
+    write_optional_string(sheet, row, 6, item.unit.as_deref())?;
+    write_amount(sheet, row, 7, Some(item.unit_price_net), &formats.unit)?;
+    write_amount(sheet, row, 8, item.unit_price_gross, &formats.unit)?;
+    sheet.write_string(row, 9, json_name(&item.vat_rate))?;
+    write_amount(sheet, row, 10, Some(item.total_net), &formats.money)?;
+    write_amount(sheet, row, 11, Some(item.vat_amount), &formats.money)?;
+    write_amount(sheet, row, 12, Some(item.total_gross), &formats.money)?;
+    write_amount(sheet, row, 13, item.discount_percent, &formats.money)?;
+    Ok(())
+}
+
+/// A number cell, left empty for missing values.
+fn write_amount(
+    sheet: &mut Worksheet,
+    row: RowNum,
+    col: ColNum,
+    value: Option<Decimal>,
+    format: &Format,
+) -> Result<(), XlsxError> {
+    if let Some(value) = value.and_then(|v| v.to_f64()) {
+        sheet.write_number_with_format(row, col, value, format)?;
+    }
+    Ok(())
+}
+
+fn write_optional_string(
+    sheet: &mut Worksheet,
+    row: RowNum,
+    col: ColNum,
+    value: Option<&str>,
+) -> Result<(), XlsxError> {
+    if let Some(value) = value {
+        sheet.write_string(row, col, value)?;
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(|s| s.to_str()).unwrap_or("")
+}
+
+fn payment_method_name(method: &PaymentMethod) -> String {
+    match method {
+        PaymentMethod::Other(description) => description.clone(),
+        method => json_name(method),
+    }
+}
@@ -6,7 +6,18 @@ use clap::{Parser, Subcommand};
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
-use commands::{batch, config, models, process};
+use commands::{batch, process, words};
+#[cfg(feature = "full")]
+use commands::{
+    ab, config, doctor, export_training, models, reconcile, render, reparse, support_bundle,
+};
+#[cfg(feature = "scanner")]
+use commands::scan;
+#[cfg(feature = "server")]
+use commands::serve;
+#[cfg(feature = "store")]
+use commands::query;
+use incr_core::models::config::{Preset, Quality};
 
 /// Polish invoice OCR - Extract structured data from Polish invoices
 #[derive(Parser)]
@@ -21,6 +32,25 @@ struct Cli {
     #[arg(short, long, global = true)]
     config: Option<String>,
 
+    /// Named profile from the config file
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Pipeline preset: fast or accurate
+    #[arg(long, global = true, value_parser = commands::parse_preset)]
+    preset: Option<Preset>,
+
+    /// Model quality: fast (INT8 quantized), balanced (mobile) or best
+    /// (server); picks the best installed variant
+    #[arg(long, global = true, value_parser = commands::parse_quality)]
+    quality: Option<Quality>,
+
+    /// Document languages, e.g. pl,en,de; Cyrillic ones (ru, uk, ...) use
+    /// the Cyrillic recognition model
+    // The full path keeps clap from reading a list of values
+    #[arg(long, global = true, value_parser = commands::parse_lang)]
+    lang: Option<::std::vec::Vec<String>>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -34,16 +64,70 @@ enum Commands {
     Batch(batch::BatchArgs),
 
     /// Manage OCR models
+    #[cfg(feature = "full")]
     Models(models::ModelsArgs),
 
     /// Manage configuration
+    #[cfg(feature = "full")]
     Config(config::ConfigArgs),
+
+    /// Check the installation and which pipeline stages can run
+    #[cfg(feature = "full")]
+    Doctor(doctor::DoctorArgs),
+
+    /// Package a document, its extraction and the setup into an archive for bug reports
+    #[cfg(feature = "full")]
+    SupportBundle(support_bundle::SupportBundleArgs),
+
+    /// Export OCR results as PaddleOCR training data
+    #[cfg(feature = "full")]
+    ExportTrainingData(export_training::ExportTrainingArgs),
+
+    /// Re-run parsing on stored OCR text and compare field coverage
+    #[cfg(feature = "full")]
+    Reparse(reparse::ReparseArgs),
+
+    /// Re-extract a corpus with candidate settings and compare with earlier results
+    #[cfg(feature = "full")]
+    Ab(ab::AbArgs),
+
+    /// Render an extracted invoice as HTML or PDF
+    #[cfg(feature = "full")]
+    Render(render::RenderArgs),
+
+    /// Match extracted invoices to open items from an ERP export
+    #[cfg(feature = "full")]
+    Match(reconcile::MatchArgs),
+
+    /// Scan documents from a scanner and extract them
+    #[cfg(feature = "scanner")]
+    Scan(scan::ScanArgs),
+
+    /// Run an HTTP extraction server
+    #[cfg(feature = "server")]
+    Serve(serve::ServeArgs),
+
+    /// List past extractions from the results store
+    #[cfg(feature = "store")]
+    Query(query::QueryArgs),
+
+    /// Write an amount in Polish words
+    Words(words::WordsArgs),
 }
 
+#[cfg(feature = "runtime")]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    run(Cli::parse()).await
+}
+
+/// Minimal builds have no async runtime; commands are driven on the main thread.
+#[cfg(not(feature = "runtime"))]
+fn main() -> anyhow::Result<()> {
+    block_on(run(Cli::parse()))
+}
 
+async fn run(cli: Cli) -> anyhow::Result<()> {
     // Set up logging based on verbosity
     let level = match cli.verbose {
         0 => Level::WARN,
@@ -60,10 +144,73 @@ async fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(subscriber)?;
 
     // Execute command
+    let config_path = cli.config.as_deref();
+    let profile = cli.profile.as_deref();
+    let preset = cli.preset;
+    if let Some(quality) = cli.quality {
+        commands::variant::set_quality(quality);
+    }
+    if let Some(languages) = cli.lang {
+        commands::set_languages(languages);
+    }
+
     match cli.command {
-        Commands::Process(args) => process::run(args, cli.config.as_deref()).await,
-        Commands::Batch(args) => batch::run(args, cli.config.as_deref()).await,
+        Commands::Process(args) => process::run(args, config_path, profile, preset).await,
+        Commands::Batch(args) => batch::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "full")]
         Commands::Models(args) => models::run(args).await,
-        Commands::Config(args) => config::run(args).await,
+        #[cfg(feature = "full")]
+        Commands::Config(args) => config::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "full")]
+        Commands::Doctor(args) => doctor::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "full")]
+        Commands::SupportBundle(args) => {
+            support_bundle::run(args, config_path, profile, preset).await
+        }
+        #[cfg(feature = "full")]
+        Commands::ExportTrainingData(args) => {
+            export_training::run(args, config_path, profile, preset).await
+        }
+        #[cfg(feature = "full")]
+        Commands::Reparse(args) => reparse::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "full")]
+        Commands::Ab(args) => ab::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "full")]
+        Commands::Render(args) => render::run(args).await,
+        #[cfg(feature = "full")]
+        Commands::Match(args) => reconcile::run(args).await,
+        #[cfg(feature = "scanner")]
+        Commands::Scan(args) => scan::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "server")]
+        Commands::Serve(args) => serve::run(args, config_path, profile, preset).await,
+        #[cfg(feature = "store")]
+        Commands::Query(args) => query::run(args, config_path, profile, preset).await,
+        Commands::Words(args) => words::run(args).await,
+    }
+}
+
+/// Poll a future to completion, parking the thread while it waits.
+#[cfg(not(feature = "runtime"))]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct ThreadWaker(std::thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
     }
 }
@@ -0,0 +1,457 @@
+//! Reading ZIP archives one entry at a time.
+//!
+//! Only the central directory is read up front; each entry is read and
+//! decompressed on demand, so processing a large archive needs memory for
+//! one document at a time. The archive itself is accessed through
+//! [`ZipSource`], which lets callers keep it outside the process heap
+//! (e.g. in a JavaScript `ArrayBuffer`).
+//!
+//! Stored and deflated entries are supported; ZIP64 and encrypted
+//! archives are not.
+//!
+//! [`ZipWriter`] builds small archives in memory (e.g. support bundles),
+//! deflating every entry.
+
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+use crate::error::ArchiveError;
+
+/// End of central directory record signature.
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+/// Central directory file header signature.
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// Local file header signature.
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+const EOCD_SIZE: usize = 22;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const LOCAL_HEADER_SIZE: usize = 30;
+
+/// The end record is followed by a comment of at most this many bytes.
+const MAX_COMMENT_SIZE: usize = u16::MAX as usize;
+
+/// Largest entry read by default (256 MB).
+const DEFAULT_MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Random-access bytes of a ZIP archive.
+pub trait ZipSource {
+    /// Size of the archive in bytes.
+    fn size(&self) -> u64;
+
+    /// Read `len` bytes starting at `offset`.
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, ArchiveError>;
+}
+
+impl ZipSource for [u8] {
+    fn size(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, ArchiveError> {
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| self.get(start..start.checked_add(len)?))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| ArchiveError::Corrupt("entry extends past the end of the archive".to_string()))
+    }
+}
+
+impl<S: ZipSource + ?Sized> ZipSource for &S {
+    fn size(&self) -> u64 {
+        (**self).size()
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, ArchiveError> {
+        (**self).read_at(offset, len)
+    }
+}
+
+/// A file or directory in a ZIP archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    /// Path inside the archive, with `/` separators.
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    /// Compressed size in bytes.
+    pub compressed_size: u64,
+    method: u16,
+    crc32: u32,
+    header_offset: u64,
+}
+
+impl ZipEntry {
+    /// Whether the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    /// File name without the directory part.
+    pub fn file_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or(&self.name)
+    }
+}
+
+/// ZIP archive read entry by entry.
+pub struct ZipArchive<S> {
+    source: S,
+    entries: Vec<ZipEntry>,
+    max_entry_size: u64,
+}
+
+impl<S: ZipSource> ZipArchive<S> {
+    /// Open an archive by reading its central directory.
+    pub fn new(source: S) -> Result<Self, ArchiveError> {
+        let (count, offset, size) = read_end_record(&source)?;
+        let directory = source.read_at(offset, size)?;
+
+        let mut entries = Vec::with_capacity(count);
+        let mut pos = 0;
+        for _ in 0..count {
+            let header = directory
+                .get(pos..pos + CENTRAL_HEADER_SIZE)
+                .filter(|h| u32_at(h, 0) == CENTRAL_SIGNATURE)
+                .ok_or_else(|| ArchiveError::Corrupt("invalid central directory".to_string()))?;
+
+            let name_len = u16_at(header, 28) as usize;
+            let extra_len = u16_at(header, 30) as usize;
+            let comment_len = u16_at(header, 32) as usize;
+            let name = directory
+                .get(pos + CENTRAL_HEADER_SIZE..pos + CENTRAL_HEADER_SIZE + name_len)
+                .ok_or_else(|| ArchiveError::Corrupt("truncated central directory".to_string()))?;
+            let name = String::from_utf8_lossy(name).into_owned();
+
+            if u16_at(header, 8) & 1 != 0 {
+                return Err(ArchiveError::Unsupported(format!("{} is encrypted", name)));
+            }
+
+            let compressed_size = u32_at(header, 20);
+            let size = u32_at(header, 24);
+            let header_offset = u32_at(header, 42);
+            if [compressed_size, size, header_offset].contains(&u32::MAX) {
+                return Err(ArchiveError::Unsupported("ZIP64 archives".to_string()));
+            }
+
+            entries.push(ZipEntry {
+                name,
+                size: size as u64,
+                compressed_size: compressed_size as u64,
+                method: u16_at(header, 10),
+                crc32: u32_at(header, 16),
+                header_offset: header_offset as u64,
+            });
+            pos += CENTRAL_HEADER_SIZE + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self {
+            source,
+            entries,
+            max_entry_size: DEFAULT_MAX_ENTRY_SIZE,
+        })
+    }
+
+    /// Set the largest uncompressed entry size that is read.
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+
+    /// Entries in the order they are stored.
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Read and decompress an entry.
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ArchiveError> {
+        if entry.size > self.max_entry_size {
+            return Err(ArchiveError::TooLarge {
+                name: entry.name.clone(),
+                size: entry.size,
+                limit: self.max_entry_size,
+            });
+        }
+
+        let header = self.source.read_at(entry.header_offset, LOCAL_HEADER_SIZE)?;
+        if u32_at(&header, 0) != LOCAL_SIGNATURE {
+            return Err(ArchiveError::Corrupt(format!("invalid local header for {}", entry.name)));
+        }
+        let data_offset = entry.header_offset
+            + LOCAL_HEADER_SIZE as u64
+            + u16_at(&header, 26) as u64
+            + u16_at(&header, 28) as u64;
+        let compressed = self.source.read_at(data_offset, entry.compressed_size as usize)?;
+
+        let data = match entry.method {
+            METHOD_STORED => compressed,
+            METHOD_DEFLATED => {
+                let mut data = Vec::with_capacity(entry.size as usize);
+                DeflateDecoder::new(compressed.as_slice())
+                    .take(entry.size + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|e| ArchiveError::Decompress {
+                        name: entry.name.clone(),
+                        reason: e.to_string(),
+                    })?;
+                data
+            }
+            method => {
+                return Err(ArchiveError::Unsupported(format!(
+                    "compression method {} ({})",
+                    method, entry.name
+                )));
+            }
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&data);
+        if data.len() as u64 != entry.size || crc.sum() != entry.crc32 {
+            return Err(ArchiveError::Decompress {
+                name: entry.name.clone(),
+                reason: "size or checksum mismatch".to_string(),
+            });
+        }
+
+        Ok(data)
+    }
+}
+
+/// ZIP archive built in memory.
+#[derive(Debug, Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    directory: Vec<u8>,
+    count: u16,
+}
+
+impl ZipWriter {
+    /// Create an empty archive.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file; `name` is its path inside the archive, with `/`
+    /// separators.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), ArchiveError> {
+        let unsupported = || ArchiveError::Unsupported("ZIP64 archives".to_string());
+        if self.count == u16::MAX - 1 {
+            return Err(unsupported());
+        }
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| ArchiveError::Compress {
+                name: name.to_string(),
+                reason: e.to_string(),
+            })?;
+        let mut crc = Crc::new();
+        crc.update(data);
+
+        let offset = u32::try_from(self.data.len()).map_err(|_| unsupported())?;
+        let compressed_size = u32::try_from(compressed.len()).map_err(|_| unsupported())?;
+        let size = u32::try_from(data.len()).map_err(|_| unsupported())?;
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| ArchiveError::Unsupported(format!("entry name of {} bytes", name.len())))?;
+
+        // Version 2.0, UTF-8 names, deflated, no timestamp
+        let common = [
+            &20u16.to_le_bytes()[..],
+            &0x0800u16.to_le_bytes(),
+            &METHOD_DEFLATED.to_le_bytes(),
+            &[0; 4],
+            &crc.sum().to_le_bytes(),
+            &compressed_size.to_le_bytes(),
+            &size.to_le_bytes(),
+            &name_len.to_le_bytes(),
+        ]
+        .concat();
+
+        self.data.extend(LOCAL_SIGNATURE.to_le_bytes());
+        self.data.extend(&common);
+        self.data.extend([0; 2]);
+        self.data.extend(name.as_bytes());
+        self.data.extend(&compressed);
+
+        self.directory.extend(CENTRAL_SIGNATURE.to_le_bytes());
+        self.directory.extend(20u16.to_le_bytes());
+        self.directory.extend(&common);
+        self.directory.extend([0; 12]);
+        self.directory.extend(offset.to_le_bytes());
+        self.directory.extend(name.as_bytes());
+
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Finish the archive and return its bytes.
+    pub fn finish(mut self) -> Result<Vec<u8>, ArchiveError> {
+        let unsupported = || ArchiveError::Unsupported("ZIP64 archives".to_string());
+        let directory_offset = u32::try_from(self.data.len()).map_err(|_| unsupported())?;
+        let directory_size = u32::try_from(self.directory.len()).map_err(|_| unsupported())?;
+
+        self.data.extend(&self.directory);
+        self.data.extend(EOCD_SIGNATURE.to_le_bytes());
+        self.data.extend([0; 4]);
+        self.data.extend(self.count.to_le_bytes());
+        self.data.extend(self.count.to_le_bytes());
+        self.data.extend(directory_size.to_le_bytes());
+        self.data.extend(directory_offset.to_le_bytes());
+        self.data.extend([0; 2]);
+        Ok(self.data)
+    }
+}
+
+/// Find the end of central directory record and return the entry count and
+/// the offset and size of the central directory.
+fn read_end_record(source: &impl ZipSource) -> Result<(usize, u64, usize), ArchiveError> {
+    let size = source.size();
+    if size < EOCD_SIZE as u64 {
+        return Err(ArchiveError::NotZip);
+    }
+
+    let tail_len = size.min((EOCD_SIZE + MAX_COMMENT_SIZE) as u64) as usize;
+    let tail = source.read_at(size - tail_len as u64, tail_len)?;
+    let record = (0..=tail_len - EOCD_SIZE)
+        .rev()
+        .map(|i| &tail[i..])
+        .find(|record| u32_at(record, 0) == EOCD_SIGNATURE)
+        .ok_or(ArchiveError::NotZip)?;
+
+    let count = u16_at(record, 10);
+    let directory_size = u32_at(record, 12);
+    let directory_offset = u32_at(record, 16);
+    if count == u16::MAX || directory_offset == u32::MAX {
+        return Err(ArchiveError::Unsupported("ZIP64 archives".to_string()));
+    }
+
+    Ok((count as usize, directory_offset as u64, directory_size as usize))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a ZIP archive with the given entries, deflating those marked.
+    fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+
+        for (name, data, deflate) in files {
+            let (method, stored) = if *deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).unwrap();
+                (METHOD_DEFLATED, encoder.finish().unwrap())
+            } else {
+                (METHOD_STORED, data.to_vec())
+            };
+            let mut crc = Crc::new();
+            crc.update(data);
+
+            let offset = archive.len() as u32;
+            let sizes = [crc.sum(), stored.len() as u32, data.len() as u32];
+
+            archive.extend(LOCAL_SIGNATURE.to_le_bytes());
+            archive.extend([20, 0, 0, 0]);
+            archive.extend(method.to_le_bytes());
+            archive.extend([0; 4]);
+            sizes.iter().for_each(|v| archive.extend(v.to_le_bytes()));
+            archive.extend((name.len() as u16).to_le_bytes());
+            archive.extend([0; 2]);
+            archive.extend(name.as_bytes());
+            archive.extend(&stored);
+
+            directory.extend(CENTRAL_SIGNATURE.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 4]);
+            sizes.iter().for_each(|v| directory.extend(v.to_le_bytes()));
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend([0; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+
+        let directory_offset = archive.len() as u32;
+        archive.extend(&directory);
+        archive.extend(EOCD_SIGNATURE.to_le_bytes());
+        archive.extend([0; 4]);
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend([0; 2]);
+        archive
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let text = "Faktura VAT nr FV/1/2024\n".repeat(20);
+        let bytes = zip(&[
+            ("scans/", b"", false),
+            ("scans/a.txt", text.as_bytes(), true),
+            ("b.txt", b"stored", false),
+        ]);
+        let archive = ZipArchive::new(bytes.as_slice()).unwrap();
+
+        let entries = archive.entries();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].is_dir());
+        assert_eq!(entries[1].file_name(), "a.txt");
+        assert!(entries[1].compressed_size < entries[1].size);
+
+        assert_eq!(archive.read(&entries[1]).unwrap(), text.as_bytes());
+        assert_eq!(archive.read(&entries[2]).unwrap(), b"stored");
+    }
+
+    #[test]
+    fn test_rejects_bad_archives() {
+        assert!(matches!(ZipArchive::new(&b"not a zip"[..]), Err(ArchiveError::NotZip)));
+
+        let mut bytes = zip(&[("a.txt", b"hello", false)]);
+        let archive = ZipArchive::new(bytes.as_slice()).unwrap().with_max_entry_size(4);
+        assert!(matches!(archive.read(&archive.entries()[0]), Err(ArchiveError::TooLarge { .. })));
+
+        // Corrupted data fails the checksum
+        bytes[30 + 5] = b'j';
+        let archive = ZipArchive::new(bytes.as_slice()).unwrap();
+        assert!(matches!(archive.read(&archive.entries()[0]), Err(ArchiveError::Decompress { .. })));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let text = "Sprzedawca: ABC Sp. z o.o.\n".repeat(50);
+        let mut writer = ZipWriter::new();
+        writer.add("result.json", b"{}").unwrap();
+        writer.add("ocr/page-1.txt", text.as_bytes()).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let archive = ZipArchive::new(bytes.as_slice()).unwrap();
+        let entries = archive.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].name, "ocr/page-1.txt");
+        assert!(entries[1].compressed_size < entries[1].size);
+        assert_eq!(archive.read(&entries[0]).unwrap(), b"{}");
+        assert_eq!(archive.read(&entries[1]).unwrap(), text.as_bytes());
+
+        assert!(ZipArchive::new(ZipWriter::new().finish().unwrap().as_slice())
+            .unwrap()
+            .entries()
+            .is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+//! Append-only audit log of extractions and corrections.
+//!
+//! Each line of the log is one JSON [`AuditRecord`]. Records identify the
+//! document, models and configuration by hash, so any extraction can later
+//! be traced to exactly what produced it.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::IncrError;
+use crate::models::config::IncrConfig;
+use crate::models::invoice::Invoice;
+use crate::training::Correction;
+
+/// Kind of audited operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Invoice data was extracted from a document.
+    Extract,
+    /// Extracted data was corrected by a person.
+    Correct,
+}
+
+/// Identity of a processed document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentFingerprint {
+    /// File name or other label, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// SHA-256 of the document bytes.
+    pub sha256: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+impl DocumentFingerprint {
+    /// Fingerprint document bytes.
+    pub fn new(name: Option<String>, data: &[u8]) -> Self {
+        Self {
+            name,
+            sha256: sha256_hex(data),
+            size: data.len() as u64,
+        }
+    }
+}
+
+/// One audit log entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the operation finished.
+    pub timestamp: DateTime<Utc>,
+    /// Operation kind.
+    pub action: AuditAction,
+    /// Frontend command that performed it (e.g. `process`, `serve`).
+    pub command: String,
+    /// Processed document.
+    pub document: DocumentFingerprint,
+    /// Model identifiers (file name to hash, plus the variant).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub models: BTreeMap<String, String>,
+    /// Hash of the effective configuration.
+    pub config_hash: String,
+    /// Hash of the resulting invoice data, if the operation succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+    /// Corrections applied (for `correct` records).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub corrections: Vec<Correction>,
+    /// Person or system responsible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Error message if the operation failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    /// Record an extraction attempt and its outcome.
+    pub fn extraction(
+        command: &str,
+        document: DocumentFingerprint,
+        config: &IncrConfig,
+        result: Result<&Invoice, String>,
+    ) -> Self {
+        let (result_hash, error) = match result {
+            Ok(invoice) => (Some(invoice_hash(invoice)), None),
+            Err(e) => (None, Some(e)),
+        };
+
+        Self {
+            timestamp: Utc::now(),
+            action: AuditAction::Extract,
+            command: command.to_string(),
+            document,
+            models: BTreeMap::new(),
+            config_hash: config_hash(config),
+            result_hash,
+            corrections: Vec::new(),
+            actor: config.audit.actor.clone(),
+            error: None,
+        }
+        .with_error(error)
+    }
+
+    /// Record corrections applied to an extracted invoice.
+    pub fn correction(
+        command: &str,
+        document: DocumentFingerprint,
+        config: &IncrConfig,
+        corrected: &Invoice,
+        corrections: Vec<Correction>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            action: AuditAction::Correct,
+            command: command.to_string(),
+            document,
+            models: BTreeMap::new(),
+            config_hash: config_hash(config),
+            result_hash: Some(invoice_hash(corrected)),
+            corrections,
+            actor: config.audit.actor.clone(),
+            error: None,
+        }
+    }
+
+    /// Set the model identifiers.
+    pub fn with_models(mut self, models: BTreeMap<String, String>) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Set the responsible person or system.
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+}
+
+/// Append-only JSON Lines audit log.
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    /// Open the log for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, IncrError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Log file location.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record as a single line.
+    pub fn append(&self, record: &AuditRecord) -> Result<(), IncrError> {
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| IncrError::Io(std::io::Error::other(e)))?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Lowercase hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Hash of the effective configuration.
+pub fn config_hash(config: &IncrConfig) -> String {
+    sha256_hex(&serde_json::to_vec(config).unwrap_or_default())
+}
+
+/// Hash of the extracted invoice data.
+///
+/// Extraction metadata (timings, warnings) is excluded so that identical
+/// results hash identically across runs.
+pub fn invoice_hash(invoice: &Invoice) -> String {
+    let data = (
+        &invoice.header,
+        &invoice.issuer,
+        &invoice.receiver,
+        &invoice.line_items,
+        &invoice.summary,
+    );
+    sha256_hex(&serde_json::to_vec(&data).unwrap_or_default())
+}
+
+/// Hash the model files present in `model_dir`.
+pub fn model_fingerprints(model_dir: &Path, files: &[&str]) -> BTreeMap<String, String> {
+    files
+        .iter()
+        .filter_map(|name| {
+            let data = std::fs::read(model_dir.join(name)).ok()?;
+            Some((name.to_string(), sha256_hex(&data)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_invoice_hash_ignores_metadata() {
+        let mut a = Invoice::default();
+        let mut b = Invoice::default();
+        a.metadata.processing_time_ms = Some(10);
+        b.metadata.processing_time_ms = Some(20);
+        assert_eq!(invoice_hash(&a), invoice_hash(&b));
+
+        b.header.invoice_number = "FV/1/2024".to_string();
+        assert_ne!(invoice_hash(&a), invoice_hash(&b));
+    }
+
+    #[test]
+    fn test_log_appends_lines() {
+        let path = std::env::temp_dir().join(format!("incr-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let config = IncrConfig::default();
+        let invoice = Invoice::default();
+        let log = AuditLog::open(&path).unwrap();
+
+        let document = DocumentFingerprint::new(Some("a.pdf".to_string()), b"%PDF");
+        log.append(&AuditRecord::extraction("process", document.clone(), &config, Ok(&invoice)))
+            .unwrap();
+        log.append(&AuditRecord::extraction("process", document, &config, Err("failed".to_string())))
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<AuditRecord> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 2);
+        assert!(records[0].result_hash.is_some());
+        assert_eq!(records[1].error.as_deref(), Some("failed"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
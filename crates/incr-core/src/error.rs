@@ -1,5 +1,6 @@
 //! Error types for the incr-core library.
 
+use rust_decimal::Decimal;
 use thiserror::Error;
 
 /// Main error type for the incr library.
@@ -23,6 +24,7 @@ pub enum IncrError {
     Inference(#[from] incr_inference::InferenceError),
 
     /// Image processing error.
+    #[cfg(feature = "pipeline")]
     #[error("image error: {0}")]
     Image(#[from] image::ImageError),
 
@@ -32,7 +34,97 @@ pub enum IncrError {
 
     /// Configuration error.
     #[error("configuration error: {0}")]
-    Config(String),
+    Config(#[from] ConfigError),
+
+    /// Protobuf decoding error.
+    #[cfg(feature = "proto")]
+    #[error("protobuf error: {0}")]
+    Proto(#[from] ProtoError),
+
+    /// KSeF XML import error.
+    #[cfg(feature = "ksef")]
+    #[error("KSeF error: {0}")]
+    Ksef(#[from] KsefError),
+
+    /// Factur-X import or export error.
+    #[cfg(feature = "facturx")]
+    #[error("Factur-X error: {0}")]
+    Facturx(#[from] FacturxError),
+
+    /// ZIP archive error.
+    #[error("archive error: {0}")]
+    Archive(#[from] ArchiveError),
+
+    /// Invoice rendering error.
+    #[error("render error: {0}")]
+    Render(#[from] RenderError),
+
+    /// Model download error.
+    #[cfg(feature = "download")]
+    #[error("download error: {0}")]
+    Download(#[from] DownloadError),
+
+    /// Model bundle error.
+    #[cfg(feature = "bundle")]
+    #[error("model bundle error: {0}")]
+    Bundle(#[from] BundleError),
+
+    /// JPK_FA export error.
+    #[error("JPK_FA error: {0}")]
+    Jpk(#[from] JpkError),
+
+    /// UBL export error.
+    #[error("UBL error: {0}")]
+    Ubl(#[from] UblError),
+
+    /// Payment QR code error.
+    #[error("payment QR error: {0}")]
+    PaymentQr(#[from] PaymentQrError),
+
+    /// White list lookup error.
+    #[cfg(feature = "whitelist")]
+    #[error("white list error: {0}")]
+    Whitelist(#[from] WhitelistError),
+    /// VIES lookup error.
+    #[cfg(feature = "vies")]
+    #[error("VIES error: {0}")]
+    Vies(#[from] ViesError),
+    /// REGON register lookup error.
+    #[cfg(feature = "gus")]
+    #[error("REGON error: {0}")]
+    Gus(#[from] GusError),
+}
+
+/// Errors related to loading configuration.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Failed to read the configuration file.
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Configuration does not match the schema.
+    #[error("{location}: {message}")]
+    Invalid { location: String, message: String },
+
+    /// Requested profile is not defined.
+    #[error("unknown profile '{name}' (available: {available})")]
+    UnknownProfile { name: String, available: String },
+}
+
+/// Errors related to document languages.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LanguageError {
+    /// Language code is not known.
+    #[error("unknown language '{0}' (expected an ISO 639-1 code such as pl, en or de)")]
+    Unknown(String),
+
+    /// Languages are written in different scripts.
+    #[error("languages '{first}' and '{second}' need different recognition models")]
+    MixedScripts { first: String, second: String },
 }
 
 /// Errors related to PDF processing.
@@ -61,6 +153,10 @@ pub enum PdfError {
     /// Invalid page number requested.
     #[error("invalid page number: {0}")]
     InvalidPage(u32),
+
+    /// Failed to render a page.
+    #[error("failed to render page: {0}")]
+    Render(String),
 }
 
 /// Errors related to OCR processing.
@@ -105,6 +201,284 @@ pub enum ExtractionError {
     /// No invoice data could be extracted.
     #[error("no invoice data found")]
     NoData,
+
+    /// A correction patch does not fit the invoice schema.
+    #[error("invalid correction patch: {0}")]
+    InvalidPatch(String),
+
+    /// A vendor template has an invalid field, pattern or column.
+    #[error("invalid vendor template: {0}")]
+    InvalidTemplate(String),
+}
+
+/// Errors related to reading and writing ZIP archives.
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    /// The data is not a ZIP archive.
+    #[error("not a ZIP archive")]
+    NotZip,
+
+    /// The archive structure is damaged.
+    #[error("corrupt ZIP archive: {0}")]
+    Corrupt(String),
+
+    /// The archive uses a feature that is not supported.
+    #[error("unsupported ZIP feature: {0}")]
+    Unsupported(String),
+
+    /// An entry is larger than the configured limit.
+    #[error("{name} is {size} bytes, more than the limit of {limit} bytes")]
+    TooLarge { name: String, size: u64, limit: u64 },
+
+    /// An entry could not be decompressed.
+    #[error("failed to decompress {name}: {reason}")]
+    Decompress { name: String, reason: String },
+
+    /// The archive bytes could not be read.
+    #[error("failed to read archive: {0}")]
+    Read(String),
+
+    /// An entry could not be compressed.
+    #[error("failed to compress {name}: {reason}")]
+    Compress { name: String, reason: String },
+}
+
+/// Errors related to rendering invoices.
+#[derive(Error, Debug)]
+pub enum RenderError {
+    /// A template could not be parsed.
+    #[error("invalid template at line {line}: {message}")]
+    Template { line: usize, message: String },
+
+    /// The PDF document could not be written.
+    #[error("failed to write PDF: {0}")]
+    Pdf(String),
+}
+
+/// Errors related to exporting JPK_FA audit files.
+#[derive(Error, Debug)]
+pub enum JpkError {
+    /// There are no invoices to export.
+    #[error("no invoices to export")]
+    NoInvoices,
+
+    /// A setting required by the schema is neither configured nor found
+    /// on the invoices.
+    #[error("missing JPK setting output.jpk.{0}")]
+    MissingSetting(&'static str),
+
+    /// The tax office code is not four digits.
+    #[error("invalid tax office code: {0}")]
+    InvalidTaxOffice(String),
+
+    /// An invoice lacks a field the schema requires.
+    #[error("invoice {invoice} has no {field}")]
+    MissingField { invoice: String, field: &'static str },
+}
+
+/// Errors related to exporting UBL invoices.
+#[derive(Error, Debug)]
+pub enum UblError {
+    /// The invoice lacks a field UBL requires.
+    #[error("invoice has no {0}")]
+    MissingField(&'static str),
+}
+
+/// Errors related to building bank transfer QR payloads.
+#[derive(Error, Debug)]
+pub enum PaymentQrError {
+    /// The invoice lacks a field the transfer needs.
+    #[error("invoice has no {0}")]
+    MissingField(&'static str),
+
+    /// The invoice is not payable in PLN.
+    #[error("transfer QR codes are PLN only, invoice is in {0}")]
+    Currency(String),
+
+    /// The bank account is not a valid Polish account number.
+    #[error("not a Polish bank account number: {0}")]
+    InvalidAccount(String),
+
+    /// Nothing to pay.
+    #[error("invalid amount to pay: {0}")]
+    InvalidAmount(Decimal),
+
+    /// The amount doesn't fit the 6-digit amount field.
+    #[error("amount {0} exceeds the 9999.99 PLN QR code limit")]
+    AmountTooLarge(Decimal),
+}
+
+/// Errors related to protobuf decoding.
+#[cfg(feature = "proto")]
+#[derive(Error, Debug)]
+pub enum ProtoError {
+    /// The bytes are not a valid message.
+    #[error("failed to decode message: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    /// A field holds a value the models can't represent.
+    #[error("invalid value for {field}: {value}")]
+    InvalidField { field: String, value: String },
+}
+
+/// Errors related to reading KSeF XML invoices.
+#[cfg(feature = "ksef")]
+#[derive(Error, Debug)]
+pub enum KsefError {
+    /// The document is not well-formed XML.
+    #[error("invalid XML: {0}")]
+    Xml(String),
+
+    /// The document is not a KSeF invoice.
+    #[error("not a KSeF invoice: {0}")]
+    NotInvoice(String),
+
+    /// An element holds a value the models can't represent.
+    #[error("invalid value for {element}: {value}")]
+    InvalidValue { element: String, value: String },
+}
+
+/// Errors related to reading and writing Factur-X (CII) invoices.
+#[cfg(feature = "facturx")]
+#[derive(Error, Debug)]
+pub enum FacturxError {
+    /// The document is not well-formed XML.
+    #[error("invalid XML: {0}")]
+    Xml(String),
+
+    /// The document is not a CII invoice.
+    #[error("not a Factur-X invoice: {0}")]
+    NotInvoice(String),
+
+    /// An element holds a value the models can't represent.
+    #[error("invalid value for {element}: {value}")]
+    InvalidValue { element: String, value: String },
+
+    /// The invoice lacks a field the XML requires.
+    #[error("invoice has no {0}")]
+    MissingField(&'static str),
+
+    /// The PDF to embed the XML in could not be read or written.
+    #[error("PDF error: {0}")]
+    Pdf(String),
+}
+
+/// Errors related to downloading models.
+#[cfg(feature = "download")]
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    /// The request failed or the connection broke off.
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error status.
+    #[error("HTTP {status} for {url}")]
+    Status { url: String, status: u16 },
+
+    /// A downloaded file does not have the expected SHA-256 digest.
+    #[error("{file} is corrupt: SHA-256 {actual}, expected {expected}")]
+    Checksum {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// Writing the file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Errors related to exporting and importing model bundles.
+#[cfg(feature = "bundle")]
+#[derive(Error, Debug)]
+pub enum BundleError {
+    /// The archive is not a model bundle.
+    #[error("not a model bundle: {0}")]
+    Invalid(String),
+
+    /// Files a variant needs are missing.
+    #[error("{variant} models are incomplete, missing {}", .missing.join(", "))]
+    Incomplete { variant: String, missing: Vec<String> },
+
+    /// A file does not have the expected SHA-256 digest.
+    #[error("{file} is corrupt: SHA-256 {actual}, expected {expected}")]
+    Checksum {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// Reading or writing a file or the archive failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Errors related to the white list of VAT taxpayers API.
+#[cfg(feature = "whitelist")]
+#[derive(Error, Debug)]
+pub enum WhitelistError {
+    /// The request failed.
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The API rejected the request (e.g. `WL-113` for an invalid NIP).
+    #[error("{code}: {message}")]
+    Api { code: String, message: String },
+
+    /// The server answered with an error status and no API error.
+    #[error("HTTP {status} for {url}")]
+    Status { url: String, status: u16 },
+
+    /// The response is not what the API documents.
+    #[error("unexpected response: {0}")]
+    Response(String),
+}
+
+/// Errors related to the VIES VAT number validation API.
+#[cfg(feature = "vies")]
+#[derive(Error, Debug)]
+pub enum ViesError {
+    /// The request failed.
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// VIES could not answer, e.g. `MS_UNAVAILABLE` when the member
+    /// state's register is down.
+    #[error("VIES unavailable: {0}")]
+    Unavailable(String),
+
+    /// The server answered with an error status.
+    #[error("HTTP {status} for {url}")]
+    Status { url: String, status: u16 },
+
+    /// The response is not what the API documents.
+    #[error("unexpected response: {0}")]
+    Response(String),
+}
+
+/// Errors related to the BIR1 API of the REGON register.
+#[cfg(feature = "gus")]
+#[derive(Error, Debug)]
+pub enum GusError {
+    /// The request failed.
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// No session was opened, e.g. for an invalid user key.
+    #[error("login failed, check the API key")]
+    Login,
+
+    /// The API answered a search with an error.
+    #[error("{code}: {message}")]
+    Api { code: String, message: String },
+
+    /// The server answered with an error status.
+    #[error("HTTP {status} for {url}")]
+    Status { url: String, status: u16 },
+
+    /// The response is not what the API documents.
+    #[error("unexpected response: {0}")]
+    Response(String),
 }
 
 /// Result type for the incr library.
@@ -0,0 +1,1047 @@
+//! ZUGFeRD / Factur-X invoices: EN 16931 Cross Industry Invoice (CII) XML.
+//!
+//! Factur-X (ZUGFeRD 2 in Germany) is a PDF/A-3 invoice that carries its
+//! structured data as an embedded `factur-x.xml` in the UN/CEFACT CII
+//! syntax. [`Invoice::to_facturx_xml`] writes the EN 16931 profile,
+//! [`Invoice::from_facturx_xml`] reads any profile into the invoice model,
+//! and, with the `pipeline` feature, [`embed_in_pdf`] attaches the XML to a
+//! PDF with the metadata that declares it a Factur-X invoice.
+//!
+//! Parties, VAT IDs, addresses, line items, the totals per VAT rate and
+//! the payment details are written and read; allowances and charges are
+//! not. Codes (document type, VAT category, payment means, units) are
+//! shared with the [`ubl`](super::ubl) export.
+
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::ubl::{
+    amount, country_code, digits, payment_means_code, percent, subtotals, tax_category,
+    type_code, unit_code,
+};
+use super::xml::{parse_xml, Element, Xml};
+use crate::error::FacturxError;
+use crate::models::invoice::{
+    Address, Invoice, InvoiceType, LineItem, Party, PaymentMethod, SourceType, VatBreakdown,
+    VatRate,
+};
+use crate::validate::split_vat_id;
+
+const RSM_NAMESPACE: &str = "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100";
+const RAM_NAMESPACE: &str =
+    "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100";
+const UDT_NAMESPACE: &str = "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100";
+
+/// Guideline ID of the EN 16931 (COMFORT) profile.
+const EN16931_GUIDELINE: &str = "urn:cen.eu:en16931:2017";
+
+/// Name of the XML attachment in a Factur-X PDF.
+pub const FACTURX_FILE_NAME: &str = "factur-x.xml";
+
+/// Units of measure read back from UN/ECE Recommendation 20 codes.
+const UNITS: &[(&str, &str)] = &[
+    ("H87", "szt."),
+    ("C62", "szt."),
+    ("HUR", "godz."),
+    ("DAY", "dzień"),
+    ("MON", "mies."),
+    ("KGM", "kg"),
+    ("GRM", "g"),
+    ("TNE", "t"),
+    ("MTR", "m"),
+    ("MTK", "m2"),
+    ("MTQ", "m3"),
+    ("KMT", "km"),
+    ("LTR", "l"),
+    ("SET", "kpl."),
+    ("E48", "usł."),
+    ("XPK", "op."),
+];
+
+impl Invoice {
+    /// Write the invoice as an EN 16931 CII `CrossIndustryInvoice`
+    /// document, the XML of a Factur-X / ZUGFeRD invoice.
+    ///
+    /// Like [`to_ubl_xml`](Self::to_ubl_xml), it needs the issue date.
+    pub fn to_facturx_xml(&self) -> Result<String, FacturxError> {
+        let header = &self.header;
+        let summary = &self.summary;
+        let issue_date = header.issue_date.ok_or(FacturxError::MissingField("issue date"))?;
+        let currency = header.currency.as_str();
+
+        let mut xml = Xml::default();
+        xml.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.line(&format!(
+            r#"<rsm:CrossIndustryInvoice xmlns:rsm="{}" xmlns:ram="{}" xmlns:udt="{}">"#,
+            RSM_NAMESPACE, RAM_NAMESPACE, UDT_NAMESPACE
+        ));
+        xml.depth += 1;
+
+        xml.open("rsm:ExchangedDocumentContext");
+        xml.open("ram:GuidelineSpecifiedDocumentContextParameter");
+        xml.leaf("ram:ID", EN16931_GUIDELINE);
+        xml.close("ram:GuidelineSpecifiedDocumentContextParameter");
+        xml.close("rsm:ExchangedDocumentContext");
+
+        xml.open("rsm:ExchangedDocument");
+        xml.leaf("ram:ID", &header.invoice_number);
+        xml.leaf("ram:TypeCode", type_code(self));
+        write_date(&mut xml, "ram:IssueDateTime", issue_date);
+        xml.close("rsm:ExchangedDocument");
+
+        xml.open("rsm:SupplyChainTradeTransaction");
+        for (index, item) in self.line_items.iter().enumerate() {
+            write_line_item(&mut xml, index, item);
+        }
+
+        xml.open("ram:ApplicableHeaderTradeAgreement");
+        write_party(&mut xml, "ram:SellerTradeParty", &self.issuer);
+        write_party(&mut xml, "ram:BuyerTradeParty", &self.receiver);
+        xml.close("ram:ApplicableHeaderTradeAgreement");
+
+        xml.open("ram:ApplicableHeaderTradeDelivery");
+        if let Some(sale_date) = header.sale_date {
+            xml.open("ram:ActualDeliverySupplyChainEvent");
+            write_date(&mut xml, "ram:OccurrenceDateTime", sale_date);
+            xml.close("ram:ActualDeliverySupplyChainEvent");
+        }
+        xml.close("ram:ApplicableHeaderTradeDelivery");
+
+        xml.open("ram:ApplicableHeaderTradeSettlement");
+        xml.leaf("ram:PaymentReference", &header.invoice_number);
+        // VAT in PLN on foreign-currency invoices
+        let tax_currency = summary.currency_info.as_ref().filter(|_| currency != "PLN");
+        if tax_currency.is_some() {
+            xml.leaf("ram:TaxCurrencyCode", "PLN");
+        }
+        xml.leaf("ram:InvoiceCurrencyCode", currency);
+        write_payment_means(&mut xml, self);
+        for (rate, net, vat) in subtotals(self) {
+            let (category, reason_code, reason) = tax_category(rate);
+            xml.open("ram:ApplicableTradeTax");
+            xml.leaf("ram:CalculatedAmount", &amount(vat));
+            xml.leaf("ram:TypeCode", "VAT");
+            if let Some(reason) = reason {
+                xml.leaf("ram:ExemptionReason", reason);
+            }
+            xml.leaf("ram:BasisAmount", &amount(net));
+            xml.leaf("ram:CategoryCode", category);
+            if let Some(code) = reason_code {
+                xml.leaf("ram:ExemptionReasonCode", code);
+            }
+            if let Some(percent) = percent(rate) {
+                xml.leaf("ram:RateApplicablePercent", &percent);
+            }
+            xml.close("ram:ApplicableTradeTax");
+        }
+        if let Some(due_date) = header.due_date {
+            xml.open("ram:SpecifiedTradePaymentTerms");
+            write_date(&mut xml, "ram:DueDateDateTime", due_date);
+            xml.close("ram:SpecifiedTradePaymentTerms");
+        }
+
+        let line_total = if self.line_items.is_empty() {
+            summary.total_net
+        } else {
+            self.line_items.iter().map(|item| item.total_net).sum()
+        };
+        let paid = summary.amount_paid.unwrap_or_default();
+        xml.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+        xml.leaf("ram:LineTotalAmount", &amount(line_total));
+        xml.leaf("ram:TaxBasisTotalAmount", &amount(summary.total_net));
+        xml.leaf_with(
+            "ram:TaxTotalAmount",
+            &[("currencyID", currency)],
+            &amount(summary.total_vat),
+        );
+        if let Some(info) = tax_currency {
+            xml.leaf_with(
+                "ram:TaxTotalAmount",
+                &[("currencyID", "PLN")],
+                &amount(info.total_vat_pln),
+            );
+        }
+        xml.leaf("ram:GrandTotalAmount", &amount(summary.total_gross));
+        if let Some(paid) = summary.amount_paid {
+            xml.leaf("ram:TotalPrepaidAmount", &amount(paid));
+        }
+        let payable = summary.amount_due.unwrap_or(summary.total_gross - paid);
+        xml.leaf("ram:DuePayableAmount", &amount(payable));
+        xml.close("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+
+        let corrected = header.correction_of.as_ref();
+        if let (InvoiceType::Correction, Some(corrected)) = (header.invoice_type, corrected) {
+            xml.open("ram:InvoiceReferencedDocument");
+            xml.leaf("ram:IssuerAssignedID", corrected);
+            xml.close("ram:InvoiceReferencedDocument");
+        }
+        xml.close("ram:ApplicableHeaderTradeSettlement");
+        xml.close("rsm:SupplyChainTradeTransaction");
+
+        xml.depth -= 1;
+        xml.line("</rsm:CrossIndustryInvoice>");
+        Ok(xml.output)
+    }
+
+    /// Load an invoice from the CII XML of a Factur-X / ZUGFeRD invoice.
+    pub fn from_facturx_xml(xml: &str) -> Result<Self, FacturxError> {
+        parse_invoice(xml)
+    }
+}
+
+/// A date as a `udt:DateTimeString` in format 102 (`YYYYMMDD`).
+fn write_date(xml: &mut Xml, tag: &str, date: NaiveDate) {
+    xml.open(tag);
+    xml.leaf_with("udt:DateTimeString", &[("format", "102")], &date.format("%Y%m%d").to_string());
+    xml.close(tag);
+}
+
+fn write_line_item(xml: &mut Xml, index: usize, item: &LineItem) {
+    let id = item.ordinal.map_or(index + 1, |ordinal| ordinal as usize);
+    xml.open("ram:IncludedSupplyChainTradeLineItem");
+    xml.open("ram:AssociatedDocumentLineDocument");
+    xml.leaf("ram:LineID", &id.to_string());
+    xml.close("ram:AssociatedDocumentLineDocument");
+
+    xml.open("ram:SpecifiedTradeProduct");
+    if let Some(code) = &item.code {
+        xml.leaf("ram:SellerAssignedID", code);
+    }
+    xml.leaf("ram:Name", &item.description);
+    xml.close("ram:SpecifiedTradeProduct");
+
+    xml.open("ram:SpecifiedLineTradeAgreement");
+    if let Some(gross) = item.unit_price_gross {
+        xml.open("ram:GrossPriceProductTradePrice");
+        xml.leaf("ram:ChargeAmount", &amount(gross));
+        xml.close("ram:GrossPriceProductTradePrice");
+    }
+    xml.open("ram:NetPriceProductTradePrice");
+    xml.leaf("ram:ChargeAmount", &amount(item.unit_price_net));
+    xml.close("ram:NetPriceProductTradePrice");
+    xml.close("ram:SpecifiedLineTradeAgreement");
+
+    xml.open("ram:SpecifiedLineTradeDelivery");
+    xml.leaf_with(
+        "ram:BilledQuantity",
+        &[("unitCode", unit_code(item.unit.as_deref()))],
+        &item.quantity.normalize().to_string(),
+    );
+    xml.close("ram:SpecifiedLineTradeDelivery");
+
+    xml.open("ram:SpecifiedLineTradeSettlement");
+    xml.open("ram:ApplicableTradeTax");
+    xml.leaf("ram:TypeCode", "VAT");
+    xml.leaf("ram:CategoryCode", tax_category(item.vat_rate).0);
+    if let Some(percent) = percent(item.vat_rate) {
+        xml.leaf("ram:RateApplicablePercent", &percent);
+    }
+    xml.close("ram:ApplicableTradeTax");
+    xml.open("ram:SpecifiedTradeSettlementLineMonetarySummation");
+    xml.leaf("ram:LineTotalAmount", &amount(item.total_net));
+    xml.close("ram:SpecifiedTradeSettlementLineMonetarySummation");
+    xml.close("ram:SpecifiedLineTradeSettlement");
+    xml.close("ram:IncludedSupplyChainTradeLineItem");
+}
+
+fn write_party(xml: &mut Xml, tag: &str, party: &Party) {
+    let vat_number = party
+        .nip
+        .as_deref()
+        .map(digits)
+        .filter(|nip| nip.len() == 10)
+        .map(|nip| format!("PL{}", nip))
+        .or_else(|| {
+            let (country, number) = split_vat_id(party.vat_id.as_deref()?)?;
+            Some(format!("{}{}", country, number))
+        });
+
+    xml.open(tag);
+    xml.leaf("ram:Name", &party.name);
+    if let Some(id) = party.krs.as_ref().or(party.regon.as_ref()) {
+        xml.open("ram:SpecifiedLegalOrganization");
+        xml.leaf("ram:ID", id);
+        xml.close("ram:SpecifiedLegalOrganization");
+    }
+    if let Some(phone) = &party.phone {
+        xml.open("ram:DefinedTradeContact");
+        xml.open("ram:TelephoneUniversalCommunication");
+        xml.leaf("ram:CompleteNumber", phone);
+        xml.close("ram:TelephoneUniversalCommunication");
+        xml.close("ram:DefinedTradeContact");
+    }
+    write_address(xml, &party.address);
+    if let Some(email) = &party.email {
+        xml.open("ram:URIUniversalCommunication");
+        xml.leaf_with("ram:URIID", &[("schemeID", "EM")], email);
+        xml.close("ram:URIUniversalCommunication");
+    }
+    if let Some(vat_number) = &vat_number {
+        xml.open("ram:SpecifiedTaxRegistration");
+        xml.leaf_with("ram:ID", &[("schemeID", "VA")], vat_number);
+        xml.close("ram:SpecifiedTaxRegistration");
+    }
+    xml.close(tag);
+}
+
+fn write_address(xml: &mut Xml, address: &Address) {
+    xml.open("ram:PostalTradeAddress");
+    if let Some(postal_code) = &address.postal_code {
+        xml.leaf("ram:PostcodeCode", postal_code);
+    }
+    match (&address.street, &address.city, &address.raw) {
+        (Some(street), _, _) => xml.leaf("ram:LineOne", street),
+        // An address that was not split into its parts
+        (None, None, Some(raw)) => xml.leaf("ram:LineOne", raw),
+        _ => {}
+    }
+    if let Some(city) = &address.city {
+        xml.leaf("ram:CityName", city);
+    }
+    xml.leaf("ram:CountryID", &country_code(address.country.as_deref()));
+    xml.close("ram:PostalTradeAddress");
+}
+
+fn write_payment_means(xml: &mut Xml, invoice: &Invoice) {
+    let Some((code, name)) = payment_means_code(invoice) else {
+        return;
+    };
+
+    xml.open("ram:SpecifiedTradeSettlementPaymentMeans");
+    xml.leaf("ram:TypeCode", code);
+    if let Some(name) = name {
+        xml.leaf("ram:Information", name);
+    }
+    if let (Some(account), "30") = (&invoice.issuer.bank_account, code) {
+        let account: String = account.chars().filter(|c| !c.is_whitespace()).collect();
+        xml.open("ram:PayeePartyCreditorFinancialAccount");
+        xml.leaf("ram:IBANID", &account);
+        xml.close("ram:PayeePartyCreditorFinancialAccount");
+    }
+    xml.close("ram:SpecifiedTradeSettlementPaymentMeans");
+}
+
+/// Load an invoice from the CII XML of a Factur-X / ZUGFeRD invoice.
+pub fn parse_invoice(xml: &str) -> Result<Invoice, FacturxError> {
+    let root = parse_xml(xml).map_err(FacturxError::Xml)?;
+    if root.name != "CrossIndustryInvoice" {
+        return Err(FacturxError::NotInvoice(format!(
+            "root element is {}, expected CrossIndustryInvoice",
+            root.name
+        )));
+    }
+    let document = root
+        .find("ExchangedDocument")
+        .ok_or_else(|| FacturxError::NotInvoice("no ExchangedDocument element".to_string()))?;
+    let transaction = root.find("SupplyChainTradeTransaction").ok_or_else(|| {
+        FacturxError::NotInvoice("no SupplyChainTradeTransaction element".to_string())
+    })?;
+    let agreement = transaction.find("ApplicableHeaderTradeAgreement");
+    let settlement = transaction.find("ApplicableHeaderTradeSettlement");
+
+    let mut invoice = Invoice::new();
+
+    let header = &mut invoice.header;
+    header.invoice_number = document.string("ID").unwrap_or_default();
+    header.issue_date = date(document, "IssueDateTime")?;
+    header.sale_date = transaction
+        .find("ApplicableHeaderTradeDelivery/ActualDeliverySupplyChainEvent")
+        .map(|event| date(event, "OccurrenceDateTime"))
+        .transpose()?
+        .flatten();
+    let type_code = document.text("TypeCode");
+    header.invoice_type = match type_code {
+        Some("381" | "384") => InvoiceType::Correction,
+        Some("386") => InvoiceType::Advance,
+        Some("325") => InvoiceType::Proforma,
+        _ => InvoiceType::Standard,
+    };
+
+    if let Some(agreement) = agreement {
+        if let Some(seller) = agreement.find("SellerTradeParty") {
+            invoice.issuer = party(seller);
+        }
+        if let Some(buyer) = agreement.find("BuyerTradeParty") {
+            invoice.receiver = party(buyer);
+        }
+    }
+    invoice.header.self_invoice = type_code == Some("389") || invoice.has_same_nip();
+
+    invoice.line_items = transaction
+        .all("IncludedSupplyChainTradeLineItem")
+        .map(line_item)
+        .collect::<Result<_, _>>()?;
+
+    if let Some(settlement) = settlement {
+        let header = &mut invoice.header;
+        if let Some(currency) = settlement.string("InvoiceCurrencyCode") {
+            header.currency = currency;
+        }
+        header.due_date = settlement
+            .find("SpecifiedTradePaymentTerms")
+            .map(|terms| date(terms, "DueDateDateTime"))
+            .transpose()?
+            .flatten();
+        header.correction_of = settlement.string("InvoiceReferencedDocument/IssuerAssignedID");
+
+        let means = settlement.find("SpecifiedTradeSettlementPaymentMeans");
+        if let Some(means) = means {
+            invoice.summary.payment_method = match means.text("TypeCode") {
+                Some("10") => Some(PaymentMethod::Cash),
+                Some("30" | "31" | "42" | "58" | "59") => Some(PaymentMethod::Transfer),
+                Some("48" | "54" | "55") => Some(PaymentMethod::Card),
+                Some("97") => Some(PaymentMethod::Compensation),
+                _ => means.text("Information").map(PaymentMethod::from_str),
+            };
+            invoice.issuer.bank_account = means
+                .string("PayeePartyCreditorFinancialAccount/IBANID")
+                .or_else(|| means.string("PayeePartyCreditorFinancialAccount/ProprietaryID"));
+        }
+
+        let currency = invoice.header.currency.clone();
+        let summary = &mut invoice.summary;
+        for tax in settlement.all("ApplicableTradeTax") {
+            let net = amount_at(tax, "BasisAmount")?.unwrap_or_default();
+            let vat = amount_at(tax, "CalculatedAmount")?.unwrap_or_default();
+            summary.vat_breakdown.push(VatBreakdown {
+                rate: rate(tax)?,
+                net,
+                vat,
+                gross: net + vat,
+            });
+        }
+
+        let Some(totals) = settlement.find("SpecifiedTradeSettlementHeaderMonetarySummation")
+        else {
+            return Err(FacturxError::NotInvoice(
+                "no SpecifiedTradeSettlementHeaderMonetarySummation element".to_string(),
+            ));
+        };
+        summary.total_net = amount_at(totals, "TaxBasisTotalAmount")?
+            .unwrap_or_else(|| summary.vat_breakdown.iter().map(|row| row.net).sum());
+        // The VAT total in the invoice currency; another one is in the
+        // VAT accounting currency
+        let vat_total = totals
+            .all("TaxTotalAmount")
+            .find(|total| total.attribute("currencyID").is_none_or(|id| id == currency))
+            .map(|total| decimal("TaxTotalAmount", &total.text));
+        summary.total_vat = match vat_total {
+            Some(vat) => vat?,
+            None => summary.vat_breakdown.iter().map(|row| row.vat).sum(),
+        };
+        summary.total_gross = amount_at(totals, "GrandTotalAmount")?
+            .unwrap_or(summary.total_net + summary.total_vat);
+        summary.amount_paid =
+            amount_at(totals, "TotalPrepaidAmount")?.filter(|paid| !paid.is_zero());
+        summary.amount_due = match amount_at(totals, "DuePayableAmount")? {
+            Some(due) if summary.amount_paid.is_some() || due != summary.total_gross => Some(due),
+            _ => None,
+        };
+    }
+
+    invoice.metadata.confidence = 1.0;
+    invoice.metadata.source_type = SourceType::FacturxXml;
+    Ok(invoice)
+}
+
+/// Seller or buyer from a `SellerTradeParty`/`BuyerTradeParty` element.
+fn party(element: &Element) -> Party {
+    let mut party = Party {
+        name: element.string("Name").unwrap_or_default(),
+        phone: element.string("DefinedTradeContact/TelephoneUniversalCommunication/CompleteNumber"),
+        email: element
+            .text("URIUniversalCommunication/URIID")
+            .or_else(|| element.text("DefinedTradeContact/EmailURIUniversalCommunication/URIID"))
+            .map(str::to_lowercase),
+        ..Party::default()
+    };
+
+    // VAT IDs (VA) and tax numbers (FC); a Polish one is a NIP
+    for registration in element.all("SpecifiedTaxRegistration") {
+        let Some(id) = registration.text("ID") else {
+            continue;
+        };
+        let id: String = id.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        let nip = id.strip_prefix("PL").unwrap_or(&id);
+        if nip.len() == 10 && nip.bytes().all(|b| b.is_ascii_digit()) {
+            party.nip.get_or_insert_with(|| nip.to_string());
+        } else if registration.find("ID").and_then(|e| e.attribute("schemeID")) == Some("VA") {
+            party.vat_id.get_or_insert(id);
+        }
+    }
+
+    // Polish company registers: KRS has 10 digits, REGON 9 or 14
+    if let Some(id) = element.text("SpecifiedLegalOrganization/ID") {
+        match id.len() {
+            10 if id.bytes().all(|b| b.is_ascii_digit()) => party.krs = Some(id.to_string()),
+            9 | 14 if id.bytes().all(|b| b.is_ascii_digit()) => party.regon = Some(id.to_string()),
+            _ => {}
+        }
+    }
+
+    if let Some(address) = element.find("PostalTradeAddress") {
+        let lines: Vec<&str> = ["LineOne", "LineTwo", "LineThree"]
+            .into_iter()
+            .filter_map(|line| address.text(line))
+            .collect();
+        party.address = Address {
+            street: (!lines.is_empty()).then(|| lines.join(", ")),
+            postal_code: address.string("PostcodeCode"),
+            city: address.string("CityName"),
+            country: address.string("CountryID"),
+            raw: None,
+        };
+    }
+    party
+}
+
+/// Line item from an `IncludedSupplyChainTradeLineItem` element.
+fn line_item(row: &Element) -> Result<LineItem, FacturxError> {
+    let tax = row.find("SpecifiedLineTradeSettlement/ApplicableTradeTax");
+    let vat_rate = tax.map(rate).transpose()?.unwrap_or(VatRate::NotApplicable);
+
+    let quantity = row.find("SpecifiedLineTradeDelivery/BilledQuantity");
+    let unit = quantity
+        .and_then(|quantity| quantity.attribute("unitCode"))
+        .map(|code| match UNITS.iter().find(|(unit_code, _)| *unit_code == code) {
+            Some((_, unit)) => unit.to_string(),
+            None => code.to_string(),
+        });
+    let quantity = match quantity.map(|q| q.text.trim()).filter(|q| !q.is_empty()) {
+        Some(quantity) => decimal("BilledQuantity", quantity)?,
+        None => Decimal::ONE,
+    };
+
+    let agreement = row.find("SpecifiedLineTradeAgreement");
+    let price = |path: &str| agreement.map(|a| amount_at(a, path)).transpose().map(Option::flatten);
+    let total_net = row
+        .find("SpecifiedLineTradeSettlement/SpecifiedTradeSettlementLineMonetarySummation")
+        .map(|totals| amount_at(totals, "LineTotalAmount"))
+        .transpose()?
+        .flatten()
+        .unwrap_or_default();
+    let unit_price_net = match price("NetPriceProductTradePrice/ChargeAmount")? {
+        Some(price) => price,
+        None if !quantity.is_zero() => (total_net / quantity).round_dp(2),
+        None => Decimal::ZERO,
+    };
+    let vat_amount = (total_net * vat_rate.as_decimal()).round_dp(2);
+
+    Ok(LineItem {
+        ordinal: row
+            .text("AssociatedDocumentLineDocument/LineID")
+            .and_then(|id| id.parse().ok()),
+        description: row.string("SpecifiedTradeProduct/Name").unwrap_or_default(),
+        code: ["SellerAssignedID", "GlobalID", "BuyerAssignedID"]
+            .into_iter()
+            .find_map(|id| row.string(&format!("SpecifiedTradeProduct/{}", id))),
+        quantity,
+        unit,
+        unit_price_net,
+        unit_price_gross: price("GrossPriceProductTradePrice/ChargeAmount")?,
+        vat_rate,
+        total_net,
+        vat_amount,
+        total_gross: total_net + vat_amount,
+        discount_percent: None,
+        gtu_codes: Vec::new(),
+    })
+}
+
+/// VAT rate of an `ApplicableTradeTax` element, from its category and
+/// percentage.
+fn rate(tax: &Element) -> Result<VatRate, FacturxError> {
+    let category = tax.text("CategoryCode").unwrap_or("S");
+    Ok(match category {
+        "Z" | "K" | "G" => VatRate::Zero,
+        "E" => VatRate::Exempt,
+        "AE" => VatRate::ReverseCharge,
+        "O" => VatRate::NotApplicable,
+        _ => {
+            let percent = tax.text("RateApplicablePercent").unwrap_or("0");
+            let rate = decimal("RateApplicablePercent", percent)?.normalize().to_string();
+            VatRate::from_str(&rate).ok_or_else(|| invalid("RateApplicablePercent", percent))?
+        }
+    })
+}
+
+/// A `udt:DateTimeString` below `path`, in format 102 (`YYYYMMDD`) or as
+/// an ISO date.
+fn date(element: &Element, path: &str) -> Result<Option<NaiveDate>, FacturxError> {
+    let Some(text) = element.text(&format!("{}/DateTimeString", path)) else {
+        return Ok(None);
+    };
+    NaiveDate::parse_from_str(text, "%Y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d"))
+        .map(Some)
+        .map_err(|_| invalid(path, text))
+}
+
+fn amount_at(element: &Element, path: &str) -> Result<Option<Decimal>, FacturxError> {
+    element.text(path).map(|text| decimal(path, text)).transpose()
+}
+
+fn decimal(element: &str, text: &str) -> Result<Decimal, FacturxError> {
+    Decimal::from_str(text.trim()).map_err(|_| invalid(element, text))
+}
+
+fn invalid(element: &str, value: &str) -> FacturxError {
+    FacturxError::InvalidValue {
+        element: element.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Attach the CII XML of an invoice to a PDF as `factur-x.xml`, making it
+/// a Factur-X / ZUGFeRD invoice.
+///
+/// The attachment is added to the document's embedded files and
+/// associated files (relationship `Alternative`), and the XMP metadata is
+/// replaced with one declaring the Factur-X EN 16931 profile.
+///
+/// The PDF itself is not converted to PDF/A. Only when its metadata
+/// already declares PDF/A (embedded fonts, an output intent) is the result
+/// declared PDF/A-3 with the same conformance level, which PDF/A-3 needs
+/// for embedded XML; other PDFs are not claimed to be PDF/A at all.
+#[cfg(feature = "pipeline")]
+pub fn embed_in_pdf(pdf: &[u8], xml: &str) -> Result<Vec<u8>, FacturxError> {
+    use lopdf::{dictionary, text_string, Document, Object, Stream, StringFormat};
+
+    let pdf_error = |e: lopdf::Error| FacturxError::Pdf(e.to_string());
+    let mut doc = Document::load_mem(pdf).map_err(pdf_error)?;
+    if doc.version.as_str() < "1.7" {
+        doc.version = "1.7".to_string();
+    }
+
+    let date = chrono::Utc::now();
+    let pdf_date = date.format("D:%Y%m%d%H%M%SZ").to_string();
+    let mut file = Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Subtype" => "text/xml",
+            "Params" => dictionary! {
+                "Size" => xml.len() as i64,
+                "ModDate" => Object::string_literal(pdf_date),
+            },
+        },
+        xml.as_bytes().to_vec(),
+    );
+    let _ = file.compress();
+    let file = doc.add_object(file);
+    let spec = doc.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(FACTURX_FILE_NAME),
+        "UF" => text_string(FACTURX_FILE_NAME),
+        "Desc" => Object::string_literal("Factur-X invoice"),
+        "AFRelationship" => "Alternative",
+        "EF" => dictionary! { "F" => file, "UF" => file },
+    });
+
+    let conformance = pdfa_conformance(&doc);
+    let mut metadata = Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        xmp_metadata(&date.format("%Y-%m-%dT%H:%M:%SZ").to_string(), conformance.as_deref())
+            .into_bytes(),
+    );
+    metadata.allows_compression = false;
+    let metadata = doc.add_object(metadata);
+
+    // Existing attachments are kept in a flat name tree, except an earlier
+    // factur-x.xml, which is also dropped from the associated files
+    let catalog = doc.catalog().map_err(pdf_error)?;
+    let (replaced, mut names): (Vec<_>, Vec<_>) = embedded_files(&doc, catalog)
+        .into_iter()
+        .partition(|(name, _)| name == FACTURX_FILE_NAME.as_bytes());
+    names.push((FACTURX_FILE_NAME.as_bytes().to_vec(), spec.into()));
+    names.sort_by(|a, b| a.0.cmp(&b.0));
+    let names: Vec<Object> = names
+        .into_iter()
+        .flat_map(|(name, spec)| [Object::String(name, StringFormat::Literal), spec])
+        .collect();
+
+    let mut associated = match catalog.get(b"AF").map(|af| doc.dereference(af)) {
+        Ok(Ok((_, Object::Array(files)))) => files.clone(),
+        _ => Vec::new(),
+    };
+    associated.retain(|file| !replaced.iter().any(|(_, spec)| spec == file));
+    associated.push(spec.into());
+    let mut name_dictionary = match catalog.get(b"Names").map(|names| doc.dereference(names)) {
+        Ok(Ok((_, Object::Dictionary(dict)))) => dict.clone(),
+        _ => lopdf::Dictionary::new(),
+    };
+    name_dictionary.set("EmbeddedFiles", dictionary! { "Names" => names });
+
+    let catalog = doc.catalog_mut().map_err(pdf_error)?;
+    catalog.set("Names", name_dictionary);
+    catalog.set("AF", associated);
+    catalog.set("Metadata", metadata);
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output).map_err(|e| FacturxError::Pdf(e.to_string()))?;
+    Ok(output)
+}
+
+/// Names and file specs of a document's embedded files.
+#[cfg(feature = "pipeline")]
+fn embedded_files(
+    doc: &lopdf::Document,
+    catalog: &lopdf::Dictionary,
+) -> Vec<(Vec<u8>, lopdf::Object)> {
+    use lopdf::{Dictionary, Document, Object};
+
+    fn collect(
+        doc: &Document,
+        node: &Dictionary,
+        names: &mut Vec<(Vec<u8>, Object)>,
+        depth: usize,
+    ) {
+        let get = |key: &[u8]| node.get(key).ok().and_then(|o| doc.dereference(o).ok());
+        if let Some((_, Object::Array(pairs))) = get(b"Names") {
+            for pair in pairs.chunks_exact(2) {
+                if let Ok(name) = pair[0].as_str() {
+                    names.push((name.to_vec(), pair[1].clone()));
+                }
+            }
+        }
+        if let Some((_, Object::Array(kids))) = get(b"Kids").filter(|_| depth < 16) {
+            for kid in kids {
+                if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                    collect(doc, kid, names, depth + 1);
+                }
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    let tree = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|names| names.get(b"EmbeddedFiles").ok())
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok());
+    if let Some(tree) = tree {
+        collect(doc, tree, &mut names, 0);
+    }
+    names
+}
+
+/// The PDF/A conformance level (`A`, `B` or `U`) a document's XMP metadata
+/// declares, if it declares PDF/A.
+#[cfg(feature = "pipeline")]
+fn pdfa_conformance(doc: &lopdf::Document) -> Option<String> {
+    let metadata = doc.catalog().ok()?.get(b"Metadata").ok()?;
+    let stream = doc.dereference(metadata).ok()?.1.as_stream().ok()?;
+    let xmp = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+    let xmp = String::from_utf8_lossy(&xmp);
+    xmp_property(&xmp, "pdfaid:part")?;
+    let conformance = xmp_property(&xmp, "pdfaid:conformance")?.to_ascii_uppercase();
+    matches!(conformance.as_str(), "A" | "B" | "U").then_some(conformance)
+}
+
+/// The value of a simple XMP property, written either as an element
+/// (`<pdfaid:part>3</pdfaid:part>`) or as an attribute (`pdfaid:part="3"`).
+#[cfg(feature = "pipeline")]
+fn xmp_property<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    let element = format!("<{}>", name);
+    let attribute = format!("{}=", name);
+    if let Some(start) = xmp.find(&element) {
+        let value = &xmp[start + element.len()..];
+        return value.find('<').map(|end| value[..end].trim());
+    }
+    let start = xmp.find(&attribute)? + attribute.len();
+    let quote = xmp[start..].chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &xmp[start + 1..];
+    value.find(quote).map(|end| value[..end].trim())
+}
+
+/// XMP metadata of a Factur-X invoice, with the extension schema PDF/A
+/// requires for the `fx` properties, declaring PDF/A-3 with `conformance`
+/// if given.
+#[cfg(feature = "pipeline")]
+fn xmp_metadata(date: &str, conformance: Option<&str>) -> String {
+    let pdfaid = match conformance {
+        Some(conformance) => format!(
+            r#"
+    <rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
+      <pdfaid:part>3</pdfaid:part>
+      <pdfaid:conformance>{}</pdfaid:conformance>
+    </rdf:Description>"#,
+            conformance
+        ),
+        None => String::new(),
+    };
+    format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">{pdfaid}
+    <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+      <xmp:CreatorTool>incr</xmp:CreatorTool>
+      <xmp:ModifyDate>{date}</xmp:ModifyDate>
+      <xmp:MetadataDate>{date}</xmp:MetadataDate>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#">
+      <fx:DocumentType>INVOICE</fx:DocumentType>
+      <fx:DocumentFileName>{file}</fx:DocumentFileName>
+      <fx:Version>1.0</fx:Version>
+      <fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>
+    </rdf:Description>
+    <rdf:Description rdf:about=""
+        xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/"
+        xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#"
+        xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+      <pdfaExtension:schemas>
+        <rdf:Bag>
+          <rdf:li rdf:parseType="Resource">
+            <pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
+            <pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
+            <pdfaSchema:prefix>fx</pdfaSchema:prefix>
+            <pdfaSchema:property>
+              <rdf:Seq>
+                <rdf:li rdf:parseType="Resource">
+                  <pdfaProperty:name>DocumentFileName</pdfaProperty:name>
+                  <pdfaProperty:valueType>Text</pdfaProperty:valueType>
+                  <pdfaProperty:category>external</pdfaProperty:category>
+                  <pdfaProperty:description>The name of the embedded XML document</pdfaProperty:description>
+                </rdf:li>
+                <rdf:li rdf:parseType="Resource">
+                  <pdfaProperty:name>DocumentType</pdfaProperty:name>
+                  <pdfaProperty:valueType>Text</pdfaProperty:valueType>
+                  <pdfaProperty:category>external</pdfaProperty:category>
+                  <pdfaProperty:description>The type of the hybrid document in capital letters, e.g. INVOICE or ORDER</pdfaProperty:description>
+                </rdf:li>
+                <rdf:li rdf:parseType="Resource">
+                  <pdfaProperty:name>Version</pdfaProperty:name>
+                  <pdfaProperty:valueType>Text</pdfaProperty:valueType>
+                  <pdfaProperty:category>external</pdfaProperty:category>
+                  <pdfaProperty:description>The actual version of the standard applying to the embedded XML document</pdfaProperty:description>
+                </rdf:li>
+                <rdf:li rdf:parseType="Resource">
+                  <pdfaProperty:name>ConformanceLevel</pdfaProperty:name>
+                  <pdfaProperty:valueType>Text</pdfaProperty:valueType>
+                  <pdfaProperty:category>external</pdfaProperty:category>
+                  <pdfaProperty:description>The conformance level of the embedded XML document</pdfaProperty:description>
+                </rdf:li>
+              </rdf:Seq>
+            </pdfaSchema:property>
+          </rdf:li>
+        </rdf:Bag>
+      </pdfaExtension:schemas>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        pdfaid = pdfaid,
+        date = date,
+        file = FACTURX_FILE_NAME,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::invoice::CurrencyInfo;
+
+    fn item(description: &str, rate: VatRate, net: i64, vat: i64) -> LineItem {
+        LineItem {
+            ordinal: None,
+            description: description.to_string(),
+            code: None,
+            quantity: Decimal::from(2),
+            unit: Some("szt.".to_string()),
+            unit_price_net: Decimal::new(net, 2) / Decimal::from(2),
+            unit_price_gross: None,
+            vat_rate: rate,
+            total_net: Decimal::new(net, 2),
+            vat_amount: Decimal::new(vat, 2),
+            total_gross: Decimal::new(net + vat, 2),
+            discount_percent: None,
+            gtu_codes: Vec::new(),
+        }
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.header.sale_date = NaiveDate::from_ymd_opt(2024, 1, 14);
+        invoice.header.due_date = NaiveDate::from_ymd_opt(2024, 1, 29);
+        invoice.header.currency = "PLN".to_string();
+        invoice.issuer.name = "ABC & Syn Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("526-104-08-28".to_string());
+        invoice.issuer.krs = Some("0000123456".to_string());
+        invoice.issuer.address.street = Some("ul. Długa 5".to_string());
+        invoice.issuer.address.city = Some("Gdańsk".to_string());
+        invoice.issuer.address.postal_code = Some("80-831".to_string());
+        invoice.issuer.bank_account = Some("PL61 1090 1014 0000 0712 1981 2874".to_string());
+        invoice.issuer.email = Some("biuro@abc.pl".to_string());
+        invoice.receiver.name = "Müller GmbH".to_string();
+        invoice.receiver.vat_id = Some("DE136695976".to_string());
+        invoice.receiver.address.street = Some("Hauptstraße 1".to_string());
+        invoice.receiver.address.city = Some("Berlin".to_string());
+        invoice.receiver.address.postal_code = Some("10115".to_string());
+        invoice.receiver.address.country = Some("DE".to_string());
+        invoice.line_items = vec![
+            item("Usługa", VatRate::Standard23, 100000, 23000),
+            item("Książka", VatRate::Reduced5, 10000, 500),
+            item("Szkolenie", VatRate::Exempt, 5000, 0),
+        ];
+        invoice.summary.total_net = Decimal::new(115000, 2);
+        invoice.summary.total_vat = Decimal::new(23500, 2);
+        invoice.summary.total_gross = Decimal::new(138500, 2);
+        invoice.summary.payment_method = Some(PaymentMethod::Transfer);
+        invoice
+    }
+
+    #[test]
+    fn test_document_structure() {
+        let xml = invoice().to_facturx_xml().unwrap();
+
+        assert!(xml.contains("<ram:ID>urn:cen.eu:en16931:2017</ram:ID>"));
+        assert!(xml.contains("<ram:TypeCode>380</ram:TypeCode>"));
+        assert!(xml.contains(r#"<udt:DateTimeString format="102">20240115</udt:DateTimeString>"#));
+        assert!(xml.contains("<ram:Name>ABC &amp; Syn Sp. z o.o.</ram:Name>"));
+        assert!(xml.contains(r#"<ram:ID schemeID="VA">PL5261040828</ram:ID>"#));
+        assert!(xml.contains(r#"<ram:ID schemeID="VA">DE136695976</ram:ID>"#));
+        assert!(xml.contains("<ram:CountryID>DE</ram:CountryID>"));
+        assert!(xml.contains("<ram:IBANID>PL61109010140000071219812874</ram:IBANID>"));
+        assert!(xml.contains(r#"<ram:BilledQuantity unitCode="H87">2</ram:BilledQuantity>"#));
+        assert!(xml.contains(r#"<ram:TaxTotalAmount currencyID="PLN">235.00</"#));
+        assert!(xml.contains("<ram:DuePayableAmount>1385.00</ram:DuePayableAmount>"));
+        assert!(xml.contains("<ram:ExemptionReason>Zwolnienie z VAT</ram:ExemptionReason>"));
+        assert_eq!(xml.matches("<ram:IncludedSupplyChainTradeLineItem>").count(), 3);
+
+        // Lines before the header agreement, seller before buyer
+        let position = |tag: &str| xml.find(tag).unwrap();
+        let agreement = position("ApplicableHeaderTradeAgreement");
+        assert!(position("IncludedSupplyChainTradeLineItem") < agreement);
+        assert!(position("SellerTradeParty") < position("BuyerTradeParty"));
+        assert!(position("ram:TypeCode>30<") < position("ram:CalculatedAmount"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut original = invoice();
+        original.header.currency = "EUR".to_string();
+        original.summary.currency_info = Some(CurrencyInfo {
+            exchange_rate: Decimal::new(43, 1),
+            rate_date: None,
+            rate_table: None,
+            total_net_pln: Decimal::new(494500, 2),
+            total_vat_pln: Decimal::new(101050, 2),
+            total_gross_pln: Decimal::new(595550, 2),
+        });
+        let invoice = Invoice::from_facturx_xml(&original.to_facturx_xml().unwrap()).unwrap();
+
+        assert_eq!(invoice.metadata.source_type, SourceType::FacturxXml);
+        assert_eq!(invoice.header.invoice_number, "FV/1/2024");
+        assert_eq!(invoice.header.issue_date, original.header.issue_date);
+        assert_eq!(invoice.header.sale_date, original.header.sale_date);
+        assert_eq!(invoice.header.due_date, original.header.due_date);
+        assert_eq!(invoice.header.currency, "EUR");
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5261040828"));
+        assert_eq!(invoice.issuer.krs.as_deref(), Some("0000123456"));
+        assert_eq!(invoice.issuer.email.as_deref(), Some("biuro@abc.pl"));
+        assert_eq!(invoice.issuer.address.postal_code.as_deref(), Some("80-831"));
+        assert_eq!(invoice.issuer.bank_account.as_deref(), Some("PL61109010140000071219812874"));
+        assert_eq!(invoice.receiver.vat_id.as_deref(), Some("DE136695976"));
+        assert_eq!(invoice.receiver.nip, None);
+        assert_eq!(invoice.receiver.address.city.as_deref(), Some("Berlin"));
+        assert_eq!(invoice.summary.payment_method, Some(PaymentMethod::Transfer));
+
+        assert_eq!(invoice.line_items.len(), 3);
+        let book = &invoice.line_items[1];
+        assert_eq!(book.ordinal, Some(2));
+        assert_eq!(book.description, "Książka");
+        assert_eq!(book.unit.as_deref(), Some("szt."));
+        assert_eq!(book.vat_rate, VatRate::Reduced5);
+        assert_eq!(book.total_net, Decimal::new(10000, 2));
+        assert_eq!(book.vat_amount, Decimal::new(500, 2));
+        assert_eq!(invoice.line_items[2].vat_rate, VatRate::Exempt);
+
+        // The VAT total in the invoice currency, not the one in PLN
+        assert_eq!(invoice.summary.total_vat, Decimal::new(23500, 2));
+        assert_eq!(invoice.summary.total_gross, Decimal::new(138500, 2));
+        assert_eq!(invoice.summary.vat_breakdown.len(), 3);
+        assert_eq!(invoice.summary.amount_due, None);
+    }
+
+    #[test]
+    fn test_not_an_invoice() {
+        let error = Invoice::from_facturx_xml("<Faktura><Fa/></Faktura>").unwrap_err();
+        assert!(matches!(error, FacturxError::NotInvoice(_)));
+        assert!(matches!(Invoice::from_facturx_xml("<a>"), Err(FacturxError::Xml(_))));
+        assert!(matches!(
+            Invoice::default().to_facturx_xml(),
+            Err(FacturxError::MissingField("issue date"))
+        ));
+    }
+
+    #[cfg(feature = "pipeline")]
+    #[test]
+    fn test_embed_in_pdf() {
+        let pdf = crate::render::render_pdf(&invoice()).unwrap();
+        let xml = invoice().to_facturx_xml().unwrap();
+        let output = embed_in_pdf(&pdf, &xml).unwrap();
+
+        let mut extractor = crate::pdf::PdfExtractor::new();
+        crate::pdf::PdfProcessor::load(&mut extractor, &output).unwrap();
+        let attachments = extractor.extract_attachments().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].name, FACTURX_FILE_NAME);
+        assert_eq!(attachments[0].relationship.as_deref(), Some("Alternative"));
+        assert_eq!(attachments[0].data, xml.as_bytes());
+
+        // Embedding again replaces the XML
+        let again = embed_in_pdf(&output, &xml).unwrap();
+        let mut extractor = crate::pdf::PdfExtractor::new();
+        crate::pdf::PdfProcessor::load(&mut extractor, &again).unwrap();
+        assert_eq!(extractor.extract_attachments().unwrap().len(), 1);
+
+        // The rendered PDF is not PDF/A, so the result doesn't claim to be
+        let doc = lopdf::Document::load_mem(&output).unwrap();
+        let metadata = metadata(&doc);
+        assert!(!metadata.contains("pdfaid"));
+        assert!(metadata.contains("<fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>"));
+        assert_eq!(pdfa_conformance(&doc), None);
+    }
+
+    #[cfg(feature = "pipeline")]
+    fn metadata(doc: &lopdf::Document) -> String {
+        let metadata = doc.catalog().unwrap().get(b"Metadata").unwrap().as_reference().unwrap();
+        let metadata = doc.get_object(metadata).unwrap().as_stream().unwrap();
+        String::from_utf8_lossy(&metadata.content).into_owned()
+    }
+
+    #[cfg(feature = "pipeline")]
+    #[test]
+    fn test_embed_in_pdfa() {
+        use lopdf::{dictionary, Stream};
+
+        // A PDF/A-2U, declared with attributes as many producers do
+        let pdf = crate::render::render_pdf(&invoice()).unwrap();
+        let mut doc = lopdf::Document::load_mem(&pdf).unwrap();
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF
+            xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description
+            rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+            pdfaid:part="2" pdfaid:conformance="U"/></rdf:RDF></x:xmpmeta>"#;
+        let metadata_id = doc.add_object(Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            xmp.as_bytes().to_vec(),
+        ));
+        doc.catalog_mut().unwrap().set("Metadata", metadata_id);
+        assert_eq!(pdfa_conformance(&doc).as_deref(), Some("U"));
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+
+        let output = embed_in_pdf(&pdf, &invoice().to_facturx_xml().unwrap()).unwrap();
+        let doc = lopdf::Document::load_mem(&output).unwrap();
+        let metadata = metadata(&doc);
+        assert!(metadata.contains("<pdfaid:part>3</pdfaid:part>"));
+        assert!(metadata.contains("<pdfaid:conformance>U</pdfaid:conformance>"));
+        assert_eq!(pdfa_conformance(&doc).as_deref(), Some("U"));
+    }
+}
@@ -0,0 +1,17 @@
+//! Export of invoices to formats read by other systems.
+//!
+//! - [`ubl`]: UBL 2.1 invoices (PEPPOL BIS Billing 3.0), via
+//!   [`Invoice::to_ubl_xml`](crate::Invoice::to_ubl_xml)
+//! - [`facturx`]: ZUGFeRD/Factur-X CII invoices, via
+//!   [`Invoice::to_facturx_xml`](crate::Invoice::to_facturx_xml) and
+//!   [`Invoice::from_facturx_xml`](crate::Invoice::from_facturx_xml)
+//! - [`payment_qr`]: payload of the Polish bank transfer QR code, via
+//!   [`Invoice::payment_qr_payload`](crate::Invoice::payment_qr_payload)
+//!
+//! JPK_FA audit files are written by [`jpk`](crate::jpk).
+
+#[cfg(feature = "facturx")]
+pub mod facturx;
+pub mod payment_qr;
+pub mod ubl;
+pub(crate) mod xml;
@@ -0,0 +1,245 @@
+//! Payload of the Polish bank transfer 2D code ("kod QR do przelewu").
+//!
+//! Polish banking apps read transfer details from a QR code following the
+//! Polish Bank Association (ZBP) recommendation: nine fields separated by
+//! `|` in the order recipient NIP, country code, account number (NRB),
+//! amount in grosze (6 digits), recipient name (up to 20 characters),
+//! transfer title (up to 32 characters) and three reserved fields:
+//!
+//! ```text
+//! 5261040828|PL|61109010140000071219812874|001230|ABC & Syn Sp. z o.o.|FV/1/2024|||
+//! ```
+//!
+//! Only PLN transfers to Polish accounts can be encoded, and the amount is
+//! limited to 9 999.99 PLN.
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::error::PaymentQrError;
+use crate::models::invoice::Invoice;
+use crate::validate::{validate_iban, validate_nip};
+
+/// Largest amount the 6-digit field holds, in grosze.
+const MAX_AMOUNT: u64 = 999_999;
+
+const MAX_NAME_LEN: usize = 20;
+const MAX_TITLE_LEN: usize = 32;
+
+impl Invoice {
+    /// Payload of a QR code for paying the invoice by bank transfer.
+    ///
+    /// The transfer goes to the issuer's bank account, for the amount due
+    /// (the gross total if none was extracted), with the invoice number as
+    /// its title. A NIP that fails the checksum is left out.
+    ///
+    /// ```
+    /// use incr_core::Invoice;
+    ///
+    /// let invoice = Invoice::default();
+    /// assert!(invoice.payment_qr_payload().is_err()); // no bank account
+    /// ```
+    pub fn payment_qr_payload(&self) -> Result<String, PaymentQrError> {
+        let currency = self.header.currency.trim();
+        if !currency.is_empty() && !currency.eq_ignore_ascii_case("PLN") {
+            return Err(PaymentQrError::Currency(currency.to_string()));
+        }
+
+        let account = self
+            .issuer
+            .bank_account
+            .as_deref()
+            .ok_or(PaymentQrError::MissingField("bank account"))?;
+        let account = account_number(account)
+            .ok_or_else(|| PaymentQrError::InvalidAccount(account.to_string()))?;
+
+        let title = field(&self.header.invoice_number, MAX_TITLE_LEN);
+        if title.is_empty() {
+            return Err(PaymentQrError::MissingField("invoice number"));
+        }
+        let name = field(&self.issuer.name, MAX_NAME_LEN);
+        if name.is_empty() {
+            return Err(PaymentQrError::MissingField("issuer name"));
+        }
+
+        let nip: String = self
+            .issuer
+            .nip
+            .as_deref()
+            .filter(|nip| validate_nip(nip))
+            .map(|nip| nip.chars().filter(char::is_ascii_digit).collect())
+            .unwrap_or_default();
+
+        let amount = self.summary.amount_due.unwrap_or(self.summary.total_gross);
+        let grosze = (amount * Decimal::ONE_HUNDRED)
+            .round()
+            .to_u64()
+            .filter(|&grosze| grosze > 0)
+            .ok_or(PaymentQrError::InvalidAmount(amount))?;
+        if grosze > MAX_AMOUNT {
+            return Err(PaymentQrError::AmountTooLarge(amount));
+        }
+
+        Ok(format!(
+            "{}|PL|{}|{:06}|{}|{}|||",
+            nip, account, grosze, name, title
+        ))
+    }
+}
+
+/// Transfer details read from a QR code payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferDetails {
+    /// Recipient NIP (digits), if given.
+    pub nip: Option<String>,
+    /// 26-digit account number (NRB).
+    pub account: String,
+    /// Amount in PLN.
+    pub amount: Decimal,
+    /// Recipient name.
+    pub recipient: String,
+    /// Transfer title.
+    pub title: String,
+}
+
+/// Read a payload in the format [`Invoice::payment_qr_payload`] writes;
+/// `None` if it is not a Polish transfer code.
+///
+/// ```
+/// use incr_core::export::payment_qr::parse_payload;
+///
+/// let details = parse_payload(
+///     "5261040828|PL|61109010140000071219812874|012300|ABC Sp. z o.o.|FV/1/2024|||",
+/// )
+/// .unwrap();
+/// assert_eq!(details.amount.to_string(), "123.00");
+/// assert_eq!(details.title, "FV/1/2024");
+/// ```
+pub fn parse_payload(payload: &str) -> Option<TransferDetails> {
+    let fields: Vec<&str> = payload.trim().split('|').collect();
+    if fields.len() < 6 || fields[1] != "PL" {
+        return None;
+    }
+
+    let nip = fields[0].trim();
+    let nip = match nip.len() {
+        0 => None,
+        10 if nip.chars().all(|c| c.is_ascii_digit()) => Some(nip.to_string()),
+        _ => return None,
+    };
+    let account = account_number(fields[2])?;
+    if fields[3].is_empty() || !fields[3].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let grosze: i64 = fields[3].parse().ok()?;
+
+    Some(TransferDetails {
+        nip,
+        account,
+        amount: Decimal::new(grosze, 2),
+        recipient: fields[4].trim().to_string(),
+        title: fields[5].trim().to_string(),
+    })
+}
+
+/// The 26-digit NRB of a Polish account given as an NRB or a `PL` IBAN.
+fn account_number(account: &str) -> Option<String> {
+    let compact: String = account
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase();
+    let nrb = compact.strip_prefix("PL").unwrap_or(&compact);
+
+    let valid = nrb.len() == 26
+        && nrb.chars().all(|c| c.is_ascii_digit())
+        && validate_iban(&format!("PL{}", nrb));
+    valid.then(|| nrb.to_string())
+}
+
+/// A text field: separators removed, whitespace collapsed and cut to `max`
+/// characters.
+fn field(value: &str, max: usize) -> String {
+    let value = value.replace('|', " ");
+    let words: Vec<&str> = value.split_whitespace().collect();
+    let joined = words.join(" ");
+    joined.chars().take(max).collect::<String>().trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.header.currency = "PLN".to_string();
+        invoice.issuer.name = "ABC & Syn Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("526-104-08-28".to_string());
+        invoice.issuer.bank_account = Some("PL61 1090 1014 0000 0712 1981 2874".to_string());
+        invoice.summary.total_gross = Decimal::new(123000, 2);
+        invoice
+    }
+
+    #[test]
+    fn test_payload() {
+        assert_eq!(
+            invoice().payment_qr_payload().unwrap(),
+            "5261040828|PL|61109010140000071219812874|123000|ABC & Syn Sp. z o.o.|FV/1/2024|||"
+        );
+    }
+
+    #[test]
+    fn test_amount_due_preferred() {
+        let mut invoice = invoice();
+        invoice.summary.amount_due = Some(Decimal::new(1005, 2));
+        let payload = invoice.payment_qr_payload().unwrap();
+        assert_eq!(payload.split('|').nth(3), Some("001005"));
+    }
+
+    #[test]
+    fn test_fields_shortened() {
+        let mut invoice = invoice();
+        invoice.issuer.name = "Przedsiębiorstwo  Handlowo-Usługowe | Kowalski".to_string();
+        invoice.header.invoice_number = "FV/2024/01/000123/KRAKOW/ODDZIAL-POLUDNIE".to_string();
+        invoice.issuer.nip = Some("1234567890".to_string());
+
+        let payload = invoice.payment_qr_payload().unwrap();
+        let fields: Vec<&str> = payload.split('|').collect();
+        assert_eq!(fields.len(), 9);
+        assert_eq!(fields[0], "");
+        assert_eq!(fields[4], "Przedsiębiorstwo Han");
+        assert_eq!(fields[5], "FV/2024/01/000123/KRAKOW/ODDZIAL");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let details = parse_payload(&invoice().payment_qr_payload().unwrap()).unwrap();
+        assert_eq!(details.nip.as_deref(), Some("5261040828"));
+        assert_eq!(details.account, "61109010140000071219812874");
+        assert_eq!(details.amount, Decimal::new(123000, 2));
+        assert_eq!(details.recipient, "ABC & Syn Sp. z o.o.");
+
+        assert!(parse_payload("https://example.com").is_none());
+        assert!(parse_payload("|DE|61109010140000071219812874|012300|A|B|||").is_none());
+    }
+
+    #[test]
+    fn test_rejected() {
+        let mut foreign = invoice();
+        foreign.header.currency = "EUR".to_string();
+        assert!(matches!(foreign.payment_qr_payload(), Err(PaymentQrError::Currency(_))));
+
+        let mut german = invoice();
+        german.issuer.bank_account = Some("DE89 3704 0044 0532 0130 00".to_string());
+        assert!(matches!(german.payment_qr_payload(), Err(PaymentQrError::InvalidAccount(_))));
+
+        let mut large = invoice();
+        large.summary.total_gross = Decimal::new(1_000_000, 2);
+        assert!(matches!(large.payment_qr_payload(), Err(PaymentQrError::AmountTooLarge(_))));
+
+        let mut paid = invoice();
+        paid.summary.amount_due = Some(Decimal::ZERO);
+        assert!(matches!(paid.payment_qr_payload(), Err(PaymentQrError::InvalidAmount(_))));
+    }
+}
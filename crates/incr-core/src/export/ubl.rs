@@ -0,0 +1,556 @@
+//! Export of invoices as UBL 2.1 documents (PEPPOL BIS Billing 3.0).
+//!
+//! UBL (Universal Business Language) is the XML invoice format most ERP
+//! systems import and the PEPPOL network exchanges. The issuer and
+//! receiver become `AccountingSupplierParty` and `AccountingCustomerParty`,
+//! every VAT rate a `TaxSubtotal`, the payment method and the issuer's
+//! bank account `PaymentMeans`, and every line item an `InvoiceLine`.
+//!
+//! Polish NIPs are written as VAT numbers with the `PL` prefix and as
+//! PEPPOL endpoint IDs (scheme 9945); a foreign party's EU VAT ID is used
+//! the same way, with its country's scheme. Addresses whose country is not given
+//! as an ISO 3166 code are assumed to be in Poland. Units of measure are
+//! mapped to UN/ECE Recommendation 20 codes, `C62` ("one") when unknown.
+//!
+//! The document is written from the invoice model alone, so UBL files can
+//! be produced for stored results without running extraction again; the
+//! codes shared with Factur-X (VAT categories, units, payment means) are
+//! defined here.
+
+use rust_decimal::Decimal;
+
+use super::xml::Xml;
+use crate::error::UblError;
+use crate::models::invoice::{Address, Invoice, InvoiceType, Party, PaymentMethod, VatRate};
+use crate::validate::split_vat_id;
+
+const NAMESPACE: &str = "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2";
+const CAC_NAMESPACE: &str =
+    "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2";
+const CBC_NAMESPACE: &str =
+    "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2";
+const CUSTOMIZATION_ID: &str =
+    "urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0";
+const PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+/// PEPPOL electronic address scheme of Polish VAT numbers.
+const NIP_SCHEME: &str = "9945";
+
+/// PEPPOL electronic address schemes of the foreign VAT IDs the parser
+/// recognizes.
+const VAT_ID_SCHEMES: &[(&str, &str)] =
+    &[("DE", "9930"), ("CZ", "9929"), ("SK", "9950"), ("FR", "9957")];
+
+impl Invoice {
+    /// Write the invoice as a UBL 2.1 `Invoice` document.
+    ///
+    /// The issue date is the only field UBL requires that an extraction
+    /// may lack.
+    ///
+    /// ```
+    /// use incr_core::Invoice;
+    ///
+    /// let invoice = Invoice::default();
+    /// assert!(invoice.to_ubl_xml().is_err()); // no issue date
+    /// ```
+    pub fn to_ubl_xml(&self) -> Result<String, UblError> {
+        let header = &self.header;
+        let summary = &self.summary;
+        let issue_date = header.issue_date.ok_or(UblError::MissingField("issue date"))?;
+        let currency = header.currency.as_str();
+        let money = |xml: &mut Xml, tag: &str, value: Decimal, currency: &str| {
+            xml.leaf_with(tag, &[("currencyID", currency)], &amount(value));
+        };
+
+        let mut xml = Xml::default();
+        xml.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.line(&format!(
+            r#"<Invoice xmlns="{}" xmlns:cac="{}" xmlns:cbc="{}">"#,
+            NAMESPACE, CAC_NAMESPACE, CBC_NAMESPACE
+        ));
+        xml.depth += 1;
+
+        xml.leaf("cbc:CustomizationID", CUSTOMIZATION_ID);
+        xml.leaf("cbc:ProfileID", PROFILE_ID);
+        xml.leaf("cbc:ID", &header.invoice_number);
+        xml.leaf("cbc:IssueDate", &issue_date.to_string());
+        if let Some(due_date) = header.due_date {
+            xml.leaf("cbc:DueDate", &due_date.to_string());
+        }
+        xml.leaf("cbc:InvoiceTypeCode", type_code(self));
+        xml.leaf("cbc:DocumentCurrencyCode", currency);
+        // VAT in PLN on foreign-currency invoices
+        let tax_currency = summary.currency_info.as_ref().filter(|_| currency != "PLN");
+        if tax_currency.is_some() {
+            xml.leaf("cbc:TaxCurrencyCode", "PLN");
+        }
+
+        if let (InvoiceType::Correction, Some(corrected)) = (header.invoice_type, &header.correction_of) {
+            xml.open("cac:BillingReference");
+            xml.open("cac:InvoiceDocumentReference");
+            xml.leaf("cbc:ID", corrected);
+            xml.close("cac:InvoiceDocumentReference");
+            xml.close("cac:BillingReference");
+        }
+
+        write_party(&mut xml, "cac:AccountingSupplierParty", &self.issuer);
+        write_party(&mut xml, "cac:AccountingCustomerParty", &self.receiver);
+
+        if let Some(sale_date) = header.sale_date {
+            xml.open("cac:Delivery");
+            xml.leaf("cbc:ActualDeliveryDate", &sale_date.to_string());
+            xml.close("cac:Delivery");
+        }
+
+        write_payment_means(&mut xml, self);
+
+        xml.open("cac:TaxTotal");
+        money(&mut xml, "cbc:TaxAmount", summary.total_vat, currency);
+        for (rate, net, vat) in subtotals(self) {
+            xml.open("cac:TaxSubtotal");
+            money(&mut xml, "cbc:TaxableAmount", net, currency);
+            money(&mut xml, "cbc:TaxAmount", vat, currency);
+            write_category(&mut xml, "cac:TaxCategory", rate, true);
+            xml.close("cac:TaxSubtotal");
+        }
+        xml.close("cac:TaxTotal");
+        if let Some(info) = tax_currency {
+            xml.open("cac:TaxTotal");
+            money(&mut xml, "cbc:TaxAmount", info.total_vat_pln, "PLN");
+            xml.close("cac:TaxTotal");
+        }
+
+        let line_total = if self.line_items.is_empty() {
+            summary.total_net
+        } else {
+            self.line_items.iter().map(|item| item.total_net).sum()
+        };
+        let paid = summary.amount_paid.unwrap_or_default();
+        xml.open("cac:LegalMonetaryTotal");
+        money(&mut xml, "cbc:LineExtensionAmount", line_total, currency);
+        money(&mut xml, "cbc:TaxExclusiveAmount", summary.total_net, currency);
+        money(&mut xml, "cbc:TaxInclusiveAmount", summary.total_gross, currency);
+        if let Some(paid) = summary.amount_paid {
+            money(&mut xml, "cbc:PrepaidAmount", paid, currency);
+        }
+        let payable = summary.amount_due.unwrap_or(summary.total_gross - paid);
+        money(&mut xml, "cbc:PayableAmount", payable, currency);
+        xml.close("cac:LegalMonetaryTotal");
+
+        for (index, item) in self.line_items.iter().enumerate() {
+            let id = item.ordinal.map_or(index + 1, |ordinal| ordinal as usize);
+            xml.open("cac:InvoiceLine");
+            xml.leaf("cbc:ID", &id.to_string());
+            xml.leaf_with(
+                "cbc:InvoicedQuantity",
+                &[("unitCode", unit_code(item.unit.as_deref()))],
+                &item.quantity.normalize().to_string(),
+            );
+            money(&mut xml, "cbc:LineExtensionAmount", item.total_net, currency);
+            xml.open("cac:Item");
+            xml.leaf("cbc:Name", &item.description);
+            if let Some(code) = &item.code {
+                xml.open("cac:SellersItemIdentification");
+                xml.leaf("cbc:ID", code);
+                xml.close("cac:SellersItemIdentification");
+            }
+            write_category(&mut xml, "cac:ClassifiedTaxCategory", item.vat_rate, false);
+            xml.close("cac:Item");
+            xml.open("cac:Price");
+            money(&mut xml, "cbc:PriceAmount", item.unit_price_net, currency);
+            xml.close("cac:Price");
+            xml.close("cac:InvoiceLine");
+        }
+
+        xml.depth -= 1;
+        xml.line("</Invoice>");
+        Ok(xml.output)
+    }
+}
+
+/// UNCL1001 document type code.
+pub(super) fn type_code(invoice: &Invoice) -> &'static str {
+    if invoice.header.self_invoice {
+        return "389";
+    }
+    match invoice.header.invoice_type {
+        InvoiceType::Correction => "384",
+        InvoiceType::Advance => "386",
+        InvoiceType::Proforma => "325",
+        InvoiceType::Standard | InvoiceType::Final | InvoiceType::Margin => "380",
+    }
+}
+
+fn write_party(xml: &mut Xml, tag: &str, party: &Party) {
+    let vat_number = party
+        .nip
+        .as_deref()
+        .map(digits)
+        .filter(|nip| nip.len() == 10)
+        .map(|nip| (NIP_SCHEME, format!("PL{}", nip)))
+        .or_else(|| {
+            let (country, number) = split_vat_id(party.vat_id.as_deref()?)?;
+            let (_, scheme) = VAT_ID_SCHEMES.iter().find(|(code, _)| *code == country)?;
+            Some((*scheme, format!("{}{}", country, number)))
+        });
+
+    xml.open(tag);
+    xml.open("cac:Party");
+    match (&vat_number, &party.email) {
+        (Some((scheme, vat_number)), _) => {
+            xml.leaf_with("cbc:EndpointID", &[("schemeID", scheme)], vat_number)
+        }
+        (None, Some(email)) => xml.leaf_with("cbc:EndpointID", &[("schemeID", "EM")], email),
+        (None, None) => {}
+    }
+    if !party.name.is_empty() {
+        xml.open("cac:PartyName");
+        xml.leaf("cbc:Name", &party.name);
+        xml.close("cac:PartyName");
+    }
+    write_address(xml, &party.address);
+    if let Some((_, vat_number)) = &vat_number {
+        xml.open("cac:PartyTaxScheme");
+        xml.leaf("cbc:CompanyID", vat_number);
+        write_tax_scheme(xml);
+        xml.close("cac:PartyTaxScheme");
+    }
+    xml.open("cac:PartyLegalEntity");
+    xml.leaf("cbc:RegistrationName", &party.name);
+    if let Some(id) = party.krs.as_ref().or(party.regon.as_ref()) {
+        xml.leaf("cbc:CompanyID", id);
+    }
+    xml.close("cac:PartyLegalEntity");
+    if party.phone.is_some() || party.email.is_some() {
+        xml.open("cac:Contact");
+        if let Some(phone) = &party.phone {
+            xml.leaf("cbc:Telephone", phone);
+        }
+        if let Some(email) = &party.email {
+            xml.leaf("cbc:ElectronicMail", email);
+        }
+        xml.close("cac:Contact");
+    }
+    xml.close("cac:Party");
+    xml.close(tag);
+}
+
+fn write_address(xml: &mut Xml, address: &Address) {
+    xml.open("cac:PostalAddress");
+    if let Some(street) = &address.street {
+        xml.leaf("cbc:StreetName", street);
+    }
+    if let Some(city) = &address.city {
+        xml.leaf("cbc:CityName", city);
+    }
+    if let Some(postal_code) = &address.postal_code {
+        xml.leaf("cbc:PostalZone", postal_code);
+    }
+    // An address that was not split into its parts
+    if let (None, None, Some(raw)) = (&address.street, &address.city, &address.raw) {
+        xml.open("cac:AddressLine");
+        xml.leaf("cbc:Line", raw);
+        xml.close("cac:AddressLine");
+    }
+    xml.open("cac:Country");
+    xml.leaf("cbc:IdentificationCode", &country_code(address.country.as_deref()));
+    xml.close("cac:Country");
+    xml.close("cac:PostalAddress");
+}
+
+/// `PaymentMeans` with the UNCL4461 code of the payment method; a bank
+/// account without a stated method means a transfer.
+fn write_payment_means(xml: &mut Xml, invoice: &Invoice) {
+    let account = invoice.issuer.bank_account.as_deref();
+    let Some((code, name)) = payment_means_code(invoice) else {
+        return;
+    };
+
+    xml.open("cac:PaymentMeans");
+    match name {
+        Some(name) => xml.leaf_with("cbc:PaymentMeansCode", &[("name", name)], code),
+        None => xml.leaf("cbc:PaymentMeansCode", code),
+    }
+    xml.leaf("cbc:PaymentID", &invoice.header.invoice_number);
+    if let (Some(account), "30") = (account, code) {
+        xml.open("cac:PayeeFinancialAccount");
+        let account: String = account.chars().filter(|c| !c.is_whitespace()).collect();
+        xml.leaf("cbc:ID", &account);
+        xml.close("cac:PayeeFinancialAccount");
+    }
+    xml.close("cac:PaymentMeans");
+}
+
+/// UNCL4461 code of the payment method (with its name when not one of the
+/// codes); a bank account without a stated method means a transfer.
+pub(super) fn payment_means_code(invoice: &Invoice) -> Option<(&'static str, Option<&str>)> {
+    let account = invoice.issuer.bank_account.as_deref();
+    Some(match (&invoice.summary.payment_method, account) {
+        (Some(PaymentMethod::Transfer), _) | (None, Some(_)) => ("30", None),
+        (Some(PaymentMethod::Cash), _) => ("10", None),
+        (Some(PaymentMethod::Card), _) => ("48", None),
+        (Some(PaymentMethod::Compensation), _) => ("97", None),
+        (Some(PaymentMethod::Other(name)), _) => ("ZZZ", Some(name.as_str())),
+        (None, None) => return None,
+    })
+}
+
+/// Net and VAT totals per rate, from the VAT breakdown or else the line
+/// items, in order of first appearance.
+pub(super) fn subtotals(invoice: &Invoice) -> Vec<(VatRate, Decimal, Decimal)> {
+    let rows: Vec<(VatRate, Decimal, Decimal)> = if invoice.summary.vat_breakdown.is_empty() {
+        invoice
+            .line_items
+            .iter()
+            .map(|item| (item.vat_rate, item.total_net, item.vat_amount))
+            .collect()
+    } else {
+        invoice
+            .summary
+            .vat_breakdown
+            .iter()
+            .map(|row| (row.rate, row.net, row.vat))
+            .collect()
+    };
+
+    let mut totals: Vec<(VatRate, Decimal, Decimal)> = Vec::new();
+    for (rate, net, vat) in rows {
+        match totals.iter_mut().find(|(r, _, _)| *r == rate) {
+            Some((_, total_net, total_vat)) => {
+                *total_net += net;
+                *total_vat += vat;
+            }
+            None => totals.push((rate, net, vat)),
+        }
+    }
+    totals
+}
+
+/// Write a VAT category; exemption reasons are only allowed in
+/// `TaxSubtotal` categories, not in those of line items.
+fn write_category(xml: &mut Xml, tag: &str, rate: VatRate, reasons: bool) {
+    let (id, reason_code, reason) = tax_category(rate);
+
+    xml.open(tag);
+    xml.leaf("cbc:ID", id);
+    if let Some(percent) = percent(rate) {
+        xml.leaf("cbc:Percent", &percent);
+    }
+    if reasons {
+        if let Some(code) = reason_code {
+            xml.leaf("cbc:TaxExemptionReasonCode", code);
+        }
+        if let Some(reason) = reason {
+            xml.leaf("cbc:TaxExemptionReason", reason);
+        }
+    }
+    write_tax_scheme(xml);
+    xml.close(tag);
+}
+
+/// UNCL5305 category code of a rate, with the VATEX code and text of the
+/// exemption reason where one is due.
+pub(super) fn tax_category(
+    rate: VatRate,
+) -> (&'static str, Option<&'static str>, Option<&'static str>) {
+    match rate {
+        VatRate::Zero => ("Z", None, None),
+        VatRate::Exempt => ("E", None, Some("Zwolnienie z VAT")),
+        VatRate::ReverseCharge => ("AE", Some("VATEX-EU-AE"), Some("Odwrotne obciążenie")),
+        VatRate::NotApplicable => ("O", Some("VATEX-EU-O"), Some("Niepodlegające opodatkowaniu")),
+        _ => ("S", None, None),
+    }
+}
+
+/// Rate in percent; services outside the scope of VAT have none.
+pub(super) fn percent(rate: VatRate) -> Option<String> {
+    (rate != VatRate::NotApplicable)
+        .then(|| (rate.as_decimal() * Decimal::ONE_HUNDRED).normalize().to_string())
+}
+
+fn write_tax_scheme(xml: &mut Xml) {
+    xml.open("cac:TaxScheme");
+    xml.leaf("cbc:ID", "VAT");
+    xml.close("cac:TaxScheme");
+}
+
+/// ISO 3166 code of an address country, Poland unless given as a code.
+pub(super) fn country_code(country: Option<&str>) -> String {
+    match country.map(str::trim) {
+        Some(code) if code.len() == 2 && code.bytes().all(|b| b.is_ascii_alphabetic()) => {
+            code.to_uppercase()
+        }
+        _ => "PL".to_string(),
+    }
+}
+
+/// UN/ECE Recommendation 20 code of a Polish or English unit of measure.
+pub(super) fn unit_code(unit: Option<&str>) -> &'static str {
+    let unit = unit.unwrap_or_default().trim().trim_end_matches('.').to_lowercase();
+    match unit.as_str() {
+        "szt" | "sztuka" | "sztuk" | "pcs" | "pc" => "H87",
+        "h" | "godz" | "godzina" | "godzin" | "hour" => "HUR",
+        "dzień" | "dni" | "doba" | "day" => "DAY",
+        "mc" | "m-c" | "mies" | "miesiąc" | "month" => "MON",
+        "kg" => "KGM",
+        "g" => "GRM",
+        "t" => "TNE",
+        "m" | "mb" => "MTR",
+        "m2" | "m²" => "MTK",
+        "m3" | "m³" => "MTQ",
+        "km" => "KMT",
+        "l" | "litr" => "LTR",
+        "kpl" | "komplet" | "set" => "SET",
+        "usł" | "usl" | "usługa" => "E48",
+        "op" | "opak" | "opakowanie" => "XPK",
+        _ => "C62",
+    }
+}
+
+/// Amount with two decimal places.
+pub(super) fn amount(value: Decimal) -> String {
+    format!("{:.2}", value.round_dp(2))
+}
+
+pub(super) fn digits(nip: &str) -> String {
+    nip.chars().filter(char::is_ascii_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::invoice::{CurrencyInfo, LineItem};
+    use chrono::NaiveDate;
+
+    fn item(description: &str, rate: VatRate, net: i64, vat: i64) -> LineItem {
+        LineItem {
+            ordinal: None,
+            description: description.to_string(),
+            code: None,
+            quantity: Decimal::from(2),
+            unit: Some("szt.".to_string()),
+            unit_price_net: Decimal::new(net, 2) / Decimal::from(2),
+            unit_price_gross: None,
+            vat_rate: rate,
+            total_net: Decimal::new(net, 2),
+            vat_amount: Decimal::new(vat, 2),
+            total_gross: Decimal::new(net + vat, 2),
+            discount_percent: None,
+            gtu_codes: Vec::new(),
+        }
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.header.due_date = NaiveDate::from_ymd_opt(2024, 1, 29);
+        invoice.header.currency = "PLN".to_string();
+        invoice.issuer.name = "ABC & Syn Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("526-104-08-28".to_string());
+        invoice.issuer.address.street = Some("ul. Długa 5".to_string());
+        invoice.issuer.address.city = Some("Gdańsk".to_string());
+        invoice.issuer.address.postal_code = Some("80-831".to_string());
+        invoice.issuer.bank_account = Some("PL61 1090 1014 0000 0712 1981 2874".to_string());
+        invoice.receiver.name = "XYZ S.A.".to_string();
+        invoice.receiver.nip = Some("6750000007".to_string());
+        invoice.receiver.address.raw = Some("Kraków, Rynek 1".to_string());
+        invoice.line_items = vec![
+            item("Usługa", VatRate::Standard23, 100000, 23000),
+            item("Książka", VatRate::Reduced5, 10000, 500),
+            item("Szkolenie", VatRate::Exempt, 5000, 0),
+        ];
+        invoice.summary.total_net = Decimal::new(115000, 2);
+        invoice.summary.total_vat = Decimal::new(23500, 2);
+        invoice.summary.total_gross = Decimal::new(138500, 2);
+        invoice
+    }
+
+    #[test]
+    fn test_document_structure() {
+        let xml = invoice().to_ubl_xml().unwrap();
+
+        assert!(xml.contains("<cbc:ID>FV/1/2024</cbc:ID>"));
+        assert!(xml.contains("<cbc:IssueDate>2024-01-15</cbc:IssueDate>"));
+        assert!(xml.contains("<cbc:DueDate>2024-01-29</cbc:DueDate>"));
+        assert!(xml.contains("<cbc:InvoiceTypeCode>380</cbc:InvoiceTypeCode>"));
+        assert!(xml.contains(r#"<cbc:EndpointID schemeID="9945">PL5261040828</cbc:EndpointID>"#));
+        assert!(xml.contains("<cbc:RegistrationName>ABC &amp; Syn Sp. z o.o.</cbc:RegistrationName>"));
+        assert!(xml.contains("<cbc:Line>Kraków, Rynek 1</cbc:Line>"));
+        assert!(xml.contains("<cbc:PaymentMeansCode>30</cbc:PaymentMeansCode>"));
+        assert!(xml.contains("<cbc:ID>PL61109010140000071219812874</cbc:ID>"));
+        assert!(xml.contains(r#"<cbc:InvoicedQuantity unitCode="H87">2</cbc:InvoicedQuantity>"#));
+        assert!(xml.contains(r#"<cbc:LineExtensionAmount currencyID="PLN">1150.00</cbc:LineExtensionAmount>"#));
+        assert!(xml.contains(r#"<cbc:PayableAmount currencyID="PLN">1385.00</cbc:PayableAmount>"#));
+        assert_eq!(xml.matches("<cac:InvoiceLine>").count(), 3);
+        assert!(!xml.contains("TaxCurrencyCode"));
+
+        // Supplier before customer, totals before lines
+        let position = |tag: &str| xml.find(tag).unwrap();
+        assert!(position("AccountingSupplierParty") < position("AccountingCustomerParty"));
+        assert!(position("cac:PaymentMeans") < position("cac:TaxTotal"));
+        assert!(position("cac:LegalMonetaryTotal") < position("cac:InvoiceLine"));
+    }
+
+    #[test]
+    fn test_tax_subtotals() {
+        let xml = invoice().to_ubl_xml().unwrap();
+
+        assert_eq!(xml.matches("<cac:TaxSubtotal>").count(), 3);
+        assert!(xml.contains(r#"<cbc:TaxAmount currencyID="PLN">235.00</cbc:TaxAmount>"#));
+        assert!(xml.contains(r#"<cbc:TaxableAmount currencyID="PLN">100.00</cbc:TaxableAmount>"#));
+        assert!(xml.contains("<cbc:Percent>5</cbc:Percent>"));
+        assert!(xml.contains("<cbc:ID>E</cbc:ID>"));
+        // The reason appears in the subtotal only, not in the line's category
+        assert_eq!(xml.matches("<cbc:TaxExemptionReason>").count(), 1);
+    }
+
+    #[test]
+    fn test_foreign_currency_and_correction() {
+        let mut invoice = invoice();
+        invoice.header.currency = "EUR".to_string();
+        invoice.header.invoice_type = InvoiceType::Correction;
+        invoice.header.correction_of = Some("FV/0/2024".to_string());
+        invoice.summary.payment_method = Some(PaymentMethod::Cash);
+        invoice.summary.currency_info = Some(CurrencyInfo {
+            exchange_rate: Decimal::new(43, 1),
+            rate_date: None,
+            rate_table: None,
+            total_net_pln: Decimal::new(494500, 2),
+            total_vat_pln: Decimal::new(101050, 2),
+            total_gross_pln: Decimal::new(595550, 2),
+        });
+
+        let xml = invoice.to_ubl_xml().unwrap();
+        assert!(xml.contains("<cbc:InvoiceTypeCode>384</cbc:InvoiceTypeCode>"));
+        assert!(xml.contains("<cbc:TaxCurrencyCode>PLN</cbc:TaxCurrencyCode>"));
+        assert!(xml.contains(r#"<cbc:TaxAmount currencyID="PLN">1010.50</cbc:TaxAmount>"#));
+        assert!(xml.contains(r#"<cbc:TaxAmount currencyID="EUR">235.00</cbc:TaxAmount>"#));
+        assert!(xml.contains("<cbc:ID>FV/0/2024</cbc:ID>"));
+        assert!(xml.contains("<cbc:PaymentMeansCode>10</cbc:PaymentMeansCode>"));
+        assert!(!xml.contains("PayeeFinancialAccount"));
+    }
+
+    #[test]
+    fn test_unit_and_country_codes() {
+        assert_eq!(unit_code(Some("szt.")), "H87");
+        assert_eq!(unit_code(Some("godz.")), "HUR");
+        assert_eq!(unit_code(Some("m²")), "MTK");
+        assert_eq!(unit_code(Some("paleta")), "C62");
+        assert_eq!(unit_code(None), "C62");
+        assert_eq!(country_code(Some("de")), "DE");
+        assert_eq!(country_code(Some("Polska")), "PL");
+        assert_eq!(country_code(None), "PL");
+    }
+
+    #[test]
+    fn test_foreign_vat_id() {
+        let mut invoice = invoice();
+        invoice.receiver.nip = None;
+        invoice.receiver.vat_id = Some("DE136695976".to_string());
+        let xml = invoice.to_ubl_xml().unwrap();
+
+        assert!(xml.contains(r#"<cbc:EndpointID schemeID="9930">DE136695976</cbc:EndpointID>"#));
+        assert!(xml.contains("<cbc:CompanyID>DE136695976</cbc:CompanyID>"));
+    }
+}
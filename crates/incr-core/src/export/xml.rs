@@ -0,0 +1,170 @@
+//! Minimal indented XML writer shared by the exporters, and the element
+//! tree the importers read documents into.
+
+/// Indented XML output.
+#[derive(Default)]
+pub(crate) struct Xml {
+    pub(crate) output: String,
+    pub(crate) depth: usize,
+}
+
+impl Xml {
+    pub(crate) fn line(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("  ");
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    pub(crate) fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    pub(crate) fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    pub(crate) fn leaf(&mut self, tag: &str, value: &str) {
+        self.leaf_with(tag, &[], value);
+    }
+
+    /// Element with attributes, e.g. `<cbc:TaxAmount currencyID="PLN">`.
+    pub(crate) fn leaf_with(&mut self, tag: &str, attributes: &[(&str, &str)], value: &str) {
+        let mut line = format!("<{}", tag);
+        for (name, value) in attributes {
+            line.push_str(&format!(" {}=\"", name));
+            escape(&mut line, value, true);
+            line.push('"');
+        }
+        line.push('>');
+        escape(&mut line, value, false);
+        line.push_str(&format!("</{}>", tag));
+        self.line(&line);
+    }
+}
+
+/// Append `value` with markup characters escaped, and quotes too in
+/// attribute values.
+fn escape(output: &mut String, value: &str, attribute: bool) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' if attribute => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+}
+
+/// An XML element with its text, attributes and child elements.
+#[cfg(any(feature = "ksef", feature = "facturx"))]
+#[derive(Debug, Default)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) text: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+}
+
+#[cfg(any(feature = "ksef", feature = "facturx"))]
+impl Element {
+    /// First descendant at a `/`-separated path of element names.
+    pub(crate) fn find(&self, path: &str) -> Option<&Element> {
+        path.split('/').try_fold(self, |element, name| {
+            element.children.iter().find(|c| c.name == name)
+        })
+    }
+
+    /// Child elements with a name.
+    pub(crate) fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Trimmed text at a path, `None` if missing or empty.
+    pub(crate) fn text(&self, path: &str) -> Option<&str> {
+        self.find(path)
+            .map(|e| e.text.trim())
+            .filter(|t| !t.is_empty())
+    }
+
+    pub(crate) fn string(&self, path: &str) -> Option<String> {
+        self.text(path).map(str::to_string)
+    }
+
+    /// Value of an attribute, by its local name.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse a document into its root element, dropping namespace prefixes.
+#[cfg(any(feature = "ksef", feature = "facturx"))]
+pub(crate) fn parse_xml(xml: &str) -> Result<Element, String> {
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let name = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    let element = |start: &BytesStart| -> Result<Element, String> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(|e| e.to_string())?;
+                let value = attribute.unescape_value().map_err(|e| e.to_string())?;
+                Ok((name(attribute.key.local_name().as_ref()), value.into_owned()))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Element {
+            name: name(start.local_name().as_ref()),
+            attributes,
+            ..Element::default()
+        })
+    };
+
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(start) => stack.push(element(&start)?),
+            Event::Empty(empty) => {
+                let element = element(&empty)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| e.to_string())?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().filter(|_| !stack.is_empty());
+                match (element, stack.last_mut()) {
+                    (Some(element), Some(parent)) => parent.children.push(element),
+                    _ => return Err("unbalanced end tag".to_string()),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let document = stack.pop().filter(|_| stack.is_empty());
+    document
+        .and_then(|mut document| document.children.pop())
+        .ok_or_else(|| "no root element".to_string())
+}
@@ -0,0 +1,486 @@
+//! Looking up companies in the REGON register of Statistics Poland (GUS).
+//!
+//! The BIR1 API (*Baza Internetowa REGON*) gives the registered name, REGON
+//! and address of a company by its NIP. [`GusClient::enrich`] looks up the
+//! NIPs of both parties and fills in the name, REGON and address where the
+//! invoice lacks them; each filled field is listed in
+//! `metadata.corrections`. Where the invoice disagrees with the register,
+//! or a NIP is not registered, a warning is added to `metadata.warnings`.
+//!
+//! The API needs a user key, issued by GUS on request. The test
+//! environment ([`TEST_API_URL`]) accepts [`TEST_API_KEY`].
+//!
+//! ```no_run
+//! # async fn example(mut invoice: incr_core::Invoice) -> Result<(), incr_core::error::GusError> {
+//! use incr_core::gus::GusClient;
+//!
+//! GusClient::new("your-api-key").enrich(&mut invoice).await?;
+//! for correction in &invoice.metadata.corrections {
+//!     println!("{}", correction);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::error::GusError;
+use crate::models::invoice::{Address, Invoice, Party};
+use crate::models::validation::{IssueCode, ValidationIssue};
+use crate::validate::validate_nip;
+
+/// Production endpoint of the BIR1 API.
+pub const DEFAULT_API_URL: &str =
+    "https://wyszukiwarkaregon.stat.gov.pl/wsBIR/UslugaBIRzewnPubl.svc";
+
+/// Endpoint of the BIR1 test environment, with made-up companies.
+pub const TEST_API_URL: &str =
+    "https://wyszukiwarkaregontest.stat.gov.pl/wsBIR/UslugaBIRzewnPubl.svc";
+
+/// Public user key of the test environment.
+pub const TEST_API_KEY: &str = "abcde12345abcde12345";
+
+/// Namespace of the BIR1 operations.
+const NAMESPACE: &str = "http://CIS/BIR/PUBL/2014/07";
+
+/// Error code of a search that found nothing.
+const NOT_FOUND: &str = "4";
+
+/// Words of legal forms, ignored when comparing company names.
+const LEGAL_FORMS: &[&str] = &[
+    "SP", "SPÓŁKA", "SPOLKA", "Z", "O", "OO", "OGRANICZONĄ", "OGRANICZONA",
+    "ODPOWIEDZIALNOŚCIĄ", "ODPOWIEDZIALNOSCIA", "AKCYJNA", "SA", "JAWNA", "J",
+    "KOMANDYTOWA", "K", "KOMANDYTOWO", "SKA", "CYWILNA", "C", "PARTNERSKA", "P",
+];
+
+/// A company as registered in REGON.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegonEntity {
+    /// REGON (9 digits, or 14 for a local unit).
+    pub regon: String,
+    /// NIP (digits only).
+    pub nip: String,
+    /// Registered name.
+    pub name: String,
+    /// Street with the building and flat number (`ul. Długa 1/2`).
+    pub street: Option<String>,
+    /// Postal code (`00-001`).
+    pub postal_code: Option<String>,
+    /// Post office town.
+    pub city: Option<String>,
+}
+
+impl RegonEntity {
+    /// The registered address.
+    pub fn address(&self) -> Address {
+        Address {
+            street: self.street.clone(),
+            postal_code: self.postal_code.clone(),
+            city: self.city.clone(),
+            country: Some("PL".to_string()),
+            raw: None,
+        }
+    }
+}
+
+/// Client of the BIR1 API.
+///
+/// A session is opened with the user key on the first lookup and reused
+/// until the API lets it expire.
+#[derive(Debug)]
+pub struct GusClient {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    session: Mutex<Option<String>>,
+}
+
+impl GusClient {
+    /// Create a client of the production API with a user key.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("incr/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            url: DEFAULT_API_URL.to_string(),
+            api_key: api_key.into(),
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Use another endpoint, e.g. [`TEST_API_URL`].
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Look up the NIPs of the parties of `invoice`, fill in missing names,
+    /// REGONs and addresses, and warn about data that differs from the
+    /// register.
+    ///
+    /// Parties without a NIP, or with one that fails its checksum, are
+    /// skipped.
+    pub async fn enrich(&self, invoice: &mut Invoice) -> Result<(), GusError> {
+        for role in ["issuer", "receiver"] {
+            let party = if role == "issuer" { &invoice.issuer } else { &invoice.receiver };
+            let Some(nip) = party.nip.clone().filter(|nip| validate_nip(nip)) else {
+                continue;
+            };
+            let entity = self.lookup(&nip).await?;
+            record(invoice, role, &nip, entity.as_ref());
+        }
+        Ok(())
+    }
+
+    /// Look up a company by NIP; `None` if it is not registered.
+    pub async fn lookup(&self, nip: &str) -> Result<Option<RegonEntity>, GusError> {
+        let nip: String = nip.chars().filter(char::is_ascii_digit).collect();
+        let body = format!(
+            "<ns:DaneSzukajPodmioty><ns:pParametryWyszukiwania>\
+             <dat:Nip>{}</dat:Nip></ns:pParametryWyszukiwania></ns:DaneSzukajPodmioty>",
+            nip
+        );
+
+        let cached = self.session.lock().expect("session lock").clone();
+        let session = match cached.clone() {
+            Some(session) => session,
+            None => self.login().await?,
+        };
+        let mut result = self.call("DaneSzukajPodmioty", &body, Some(&session)).await?;
+        if result.is_empty() && cached.is_some() {
+            // The session expired, sessions last an hour
+            let session = self.login().await?;
+            result = self.call("DaneSzukajPodmioty", &body, Some(&session)).await?;
+        }
+
+        parse_search(&result)
+    }
+
+    /// Open a session with the user key.
+    async fn login(&self) -> Result<String, GusError> {
+        let body = format!(
+            "<ns:Zaloguj><ns:pKluczUzytkownika>{}</ns:pKluczUzytkownika></ns:Zaloguj>",
+            quick_xml::escape::escape(self.api_key.as_str())
+        );
+        let session = self.call("Zaloguj", &body, None).await?;
+        if session.is_empty() {
+            return Err(GusError::Login);
+        }
+
+        *self.session.lock().expect("session lock") = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Call an operation and return its result.
+    async fn call(
+        &self,
+        operation: &str,
+        body: &str,
+        session: Option<&str>,
+    ) -> Result<String, GusError> {
+        let envelope = format!(
+            "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\" \
+             xmlns:ns=\"{ns}\" xmlns:dat=\"{ns}/DataContract\">\
+             <soap:Header xmlns:wsa=\"http://www.w3.org/2005/08/addressing\">\
+             <wsa:To>{url}</wsa:To>\
+             <wsa:Action>{ns}/IUslugaBIRzewnPubl/{operation}</wsa:Action>\
+             </soap:Header><soap:Body>{body}</soap:Body></soap:Envelope>",
+            ns = NAMESPACE,
+            url = self.url,
+            operation = operation,
+            body = body,
+        );
+
+        debug!("BIR1 request: {}", operation);
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/soap+xml; charset=utf-8")
+            .body(envelope);
+        if let Some(session) = session {
+            request = request.header("sid", session);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(GusError::Status {
+                url: self.url.clone(),
+                status: status.as_u16(),
+            });
+        }
+
+        // The envelope may be wrapped in a multipart (MTOM) message
+        element(&text, &format!("{}Result", operation))
+            .ok_or_else(|| GusError::Response(format!("no {}Result in the response", operation)))
+    }
+}
+
+/// Unescaped text of the first `<name>` element in `xml`; empty for an
+/// empty element.
+fn element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    while let Some(found) = xml[from..].find(&open) {
+        let start = from + found + open.len();
+        from = start;
+        let rest = &xml[start..];
+        // Skip longer names sharing the prefix, and read past attributes
+        let tag_end = rest.find('>')?;
+        if !rest.starts_with(['>', ' ', '/']) {
+            continue;
+        }
+        if rest[..tag_end].ends_with('/') {
+            return Some(String::new());
+        }
+        let content = &rest[tag_end + 1..];
+        let end = content.find(&format!("</{}>", name))?;
+        let value = &content[..end];
+        return Some(
+            quick_xml::escape::unescape(value)
+                .map_or_else(|_| value.to_string(), |value| value.into_owned()),
+        );
+    }
+    None
+}
+
+/// Parse the result of a `DaneSzukajPodmioty` search.
+fn parse_search(result: &str) -> Result<Option<RegonEntity>, GusError> {
+    let Some(data) = element(result, "dane") else {
+        return Err(GusError::Response("no data in the search result".to_string()));
+    };
+    let field = |name: &str| {
+        element(&data, name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    if let Some(code) = field("ErrorCode") {
+        if code == NOT_FOUND {
+            return Ok(None);
+        }
+        let message = field("ErrorMessageEn").or_else(|| field("ErrorMessagePl"));
+        return Err(GusError::Api {
+            code,
+            message: message.unwrap_or_default(),
+        });
+    }
+
+    let mut street = field("Ulica").or_else(|| field("Miejscowosc"));
+    if let (Some(street), Some(number)) = (&mut street, field("NrNieruchomosci")) {
+        street.push(' ');
+        street.push_str(&number);
+        if let Some(flat) = field("NrLokalu") {
+            street.push('/');
+            street.push_str(&flat);
+        }
+    }
+    let postal_code = field("KodPocztowy").map(|code| {
+        if code.len() == 5 && code.chars().all(|c| c.is_ascii_digit()) {
+            format!("{}-{}", &code[..2], &code[2..])
+        } else {
+            code
+        }
+    });
+
+    Ok(Some(RegonEntity {
+        regon: field("Regon").unwrap_or_default(),
+        nip: field("Nip").unwrap_or_default(),
+        name: field("Nazwa").unwrap_or_default(),
+        street,
+        postal_code,
+        city: field("MiejscowoscPoczty").or_else(|| field("Miejscowosc")),
+    }))
+}
+
+/// Fill in the party `role` (`issuer` or `receiver`) from its register
+/// entry and warn about differences.
+fn record(invoice: &mut Invoice, role: &str, nip: &str, entity: Option<&RegonEntity>) {
+    let title = if role == "issuer" { "Issuer" } else { "Receiver" };
+    let Some(entity) = entity else {
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::NotInRegon,
+            format!("{}.nip", role),
+            format!("{} NIP {} is not in the REGON register", title, nip),
+        ));
+        return;
+    };
+
+    let party: &mut Party =
+        if role == "issuer" { &mut invoice.issuer } else { &mut invoice.receiver };
+    let mut filled = Vec::new();
+    let mut mismatches = Vec::new();
+
+    if party.name.trim().is_empty() {
+        party.name = entity.name.clone();
+        filled.push("name");
+    } else if !names_match(&party.name, &entity.name) {
+        let message = format!("{} name differs from the REGON register: {}", title, entity.name);
+        mismatches.push(("name", message));
+    }
+
+    let regon: String = party
+        .regon
+        .as_deref()
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    if regon.is_empty() && !entity.regon.is_empty() {
+        party.regon = Some(entity.regon.clone());
+        filled.push("regon");
+    } else if !entity.regon.is_empty() && regon.get(..9) != entity.regon.get(..9) {
+        let message = format!("{} REGON differs from the register: {}", title, entity.regon);
+        mismatches.push(("regon", message));
+    }
+
+    if party.address.is_empty() && entity.city.is_some() {
+        party.address = entity.address();
+        filled.push("address");
+    }
+
+    for field in filled {
+        invoice
+            .metadata
+            .corrections
+            .push(format!("{}.{} filled in from the REGON register", role, field));
+    }
+    for (field, message) in mismatches {
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::RegonMismatch,
+            format!("{}.{}", role, field),
+            message,
+        ));
+    }
+}
+
+/// Whether two company names share a word other than a legal form, e.g.
+/// `ABC Sp. z o.o.` and `ABC SPÓŁKA Z OGRANICZONĄ ODPOWIEDZIALNOŚCIĄ`.
+/// Names of legal forms alone are taken to match.
+fn names_match(a: &str, b: &str) -> bool {
+    fn words(name: &str) -> Vec<String> {
+        name.split(|c: char| !c.is_alphanumeric())
+            .map(str::to_uppercase)
+            .filter(|word| !word.is_empty() && !LEGAL_FORMS.contains(&word.as_str()))
+            .collect()
+    }
+
+    let (a, b) = (words(a), words(b));
+    a.is_empty() || b.is_empty() || a.iter().any(|word| b.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Requests, Response};
+
+    /// Answer one request per response, in order. Returns the URL and the
+    /// requests.
+    fn serve(responses: &'static [&'static str]) -> (String, Requests) {
+        let mut answers = responses.iter();
+        let (url, requests) = test_support::serve(responses.len(), move |_| {
+            Response::ok("application/soap+xml", *answers.next().unwrap())
+        });
+        (format!("{}/wsBIR/UslugaBIRzewnPubl.svc", url), requests)
+    }
+
+    const LOGIN: &str = "<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\"><s:Body>\
+        <ZalogujResponse xmlns=\"http://CIS/BIR/PUBL/2014/07\"><ZalogujResult>abc123session</ZalogujResult>\
+        </ZalogujResponse></s:Body></s:Envelope>";
+    const FOUND: &str = "--uuid:1\r\nContent-Type: application/xop+xml\r\n\r\n\
+        <s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\"><s:Body>\
+        <DaneSzukajPodmiotyResponse xmlns=\"http://CIS/BIR/PUBL/2014/07\"><DaneSzukajPodmiotyResult>\
+        &lt;root&gt;&lt;dane&gt;&lt;Regon&gt;012345678&lt;/Regon&gt;&lt;Nip&gt;5261040828&lt;/Nip&gt;\
+        &lt;Nazwa&gt;ABC SPÓŁKA Z OGRANICZONĄ ODPOWIEDZIALNOŚCIĄ&lt;/Nazwa&gt;\
+        &lt;Miejscowosc&gt;Warszawa&lt;/Miejscowosc&gt;&lt;KodPocztowy&gt;00-001&lt;/KodPocztowy&gt;\
+        &lt;Ulica&gt;ul. Długa&lt;/Ulica&gt;&lt;NrNieruchomosci&gt;1&lt;/NrNieruchomosci&gt;\
+        &lt;NrLokalu&gt;2&lt;/NrLokalu&gt;&lt;MiejscowoscPoczty&gt;Warszawa&lt;/MiejscowoscPoczty&gt;\
+        &lt;/dane&gt;&lt;/root&gt;</DaneSzukajPodmiotyResult></DaneSzukajPodmiotyResponse>\
+        </s:Body></s:Envelope>\r\n--uuid:1--";
+    const NOT_FOUND_RESULT: &str = "<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\"><s:Body>\
+        <DaneSzukajPodmiotyResponse xmlns=\"http://CIS/BIR/PUBL/2014/07\"><DaneSzukajPodmiotyResult>\
+        &lt;root&gt;&lt;dane&gt;&lt;ErrorCode&gt;4&lt;/ErrorCode&gt;&lt;ErrorMessagePl&gt;Nie znaleziono \
+        podmiotu.&lt;/ErrorMessagePl&gt;&lt;/dane&gt;&lt;/root&gt;</DaneSzukajPodmiotyResult>\
+        </DaneSzukajPodmiotyResponse></s:Body></s:Envelope>";
+    const EXPIRED: &str = "<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\"><s:Body>\
+        <DaneSzukajPodmiotyResponse xmlns=\"http://CIS/BIR/PUBL/2014/07\"><DaneSzukajPodmiotyResult/>\
+        </DaneSzukajPodmiotyResponse></s:Body></s:Envelope>";
+
+    #[tokio::test]
+    async fn test_enrich_fills_missing_fields() {
+        let (url, requests) = serve(&[LOGIN, FOUND, NOT_FOUND_RESULT]);
+        let mut invoice = Invoice::new();
+        invoice.issuer.nip = Some("526-104-08-28".to_string());
+        invoice.receiver.nip = Some("6750000007".to_string());
+        invoice.receiver.name = "XYZ S.A.".to_string();
+
+        GusClient::new(TEST_API_KEY).with_base_url(url).enrich(&mut invoice).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].body.contains("<ns:pKluczUzytkownika>abcde12345abcde12345<"));
+        assert_eq!(requests[1].header("sid"), Some("abc123session"));
+        assert!(requests[1].body.contains("<dat:Nip>5261040828</dat:Nip>"));
+        // The session is reused
+        assert_eq!(requests[2].header("sid"), Some("abc123session"));
+
+        let issuer = &invoice.issuer;
+        assert_eq!(issuer.name, "ABC SPÓŁKA Z OGRANICZONĄ ODPOWIEDZIALNOŚCIĄ");
+        assert_eq!(issuer.regon.as_deref(), Some("012345678"));
+        assert_eq!(issuer.address.street.as_deref(), Some("ul. Długa 1/2"));
+        assert_eq!(issuer.address.postal_code.as_deref(), Some("00-001"));
+        assert_eq!(invoice.metadata.corrections.len(), 3);
+        assert_eq!(invoice.metadata.corrections[0], "issuer.name filled in from the REGON register");
+
+        let [warning] = invoice.metadata.warnings.as_slice() else {
+            panic!("expected one warning: {:?}", invoice.metadata.warnings);
+        };
+        assert_eq!(warning.code, IssueCode::NotInRegon);
+        assert_eq!(warning.field, "receiver.nip");
+    }
+
+    #[tokio::test]
+    async fn test_expired_session() {
+        let (url, requests) = serve(&[EXPIRED, LOGIN, FOUND]);
+        let client = GusClient::new(TEST_API_KEY).with_base_url(url);
+        *client.session.lock().unwrap() = Some("old".to_string());
+
+        let entity = client.lookup("5261040828").await.unwrap().unwrap();
+        assert_eq!(entity.nip, "5261040828");
+        assert_eq!(entity.city.as_deref(), Some("Warszawa"));
+
+        let requests = requests.lock().unwrap();
+        let sessions: Vec<_> = requests.iter().map(|r| r.header("sid")).collect();
+        assert_eq!(sessions, [Some("old"), None, Some("abc123session")]);
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let entity = RegonEntity {
+            regon: "012345678".to_string(),
+            nip: "5261040828".to_string(),
+            name: "ABC SPÓŁKA Z OGRANICZONĄ ODPOWIEDZIALNOŚCIĄ".to_string(),
+            city: Some("Warszawa".to_string()),
+            ..Default::default()
+        };
+        let mut invoice = Invoice::new();
+        invoice.issuer.name = "ABC Sp. z o.o.".to_string();
+        invoice.issuer.regon = Some("012345678".to_string());
+        record(&mut invoice, "issuer", "5261040828", Some(&entity));
+        assert!(invoice.metadata.warnings.is_empty());
+
+        invoice.issuer.name = "DEF Sp. z o.o.".to_string();
+        invoice.issuer.regon = Some("123456785".to_string());
+        record(&mut invoice, "issuer", "5261040828", Some(&entity));
+        let fields: Vec<&str> = invoice.metadata.warnings.iter().map(|w| w.field.as_str()).collect();
+        assert_eq!(fields, ["issuer.name", "issuer.regon"]);
+        assert!(invoice.metadata.warnings.iter().all(|w| w.code == IssueCode::RegonMismatch));
+    }
+}
@@ -0,0 +1,317 @@
+//! Checking extracted fields against the QR codes printed on an invoice.
+//!
+//! Two payloads are understood: the Polish bank transfer code (issuer NIP,
+//! bank account, amount and title, see
+//! [`payment_qr`](crate::export::payment_qr)) and the KSeF verification
+//! link (`https://qr.ksef.mf.gov.pl/invoice/{NIP}/{DD-MM-YYYY}/{hash}`),
+//! which holds the issuer NIP and the issue date.
+//!
+//! A value the code confirms gets full confidence in `field_confidence`.
+//! A field that was not extracted is taken from the code. A value that
+//! differs is kept and reported as `barcode_mismatch`: OCR may have misread
+//! it, but the code may as well belong to another document in the scan.
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::export::payment_qr::{parse_payload, TransferDetails};
+use crate::models::invoice::{BarcodeKind, DecodedBarcode, Invoice};
+use crate::models::validation::{IssueCode, ValidationIssue};
+use crate::ocr::Barcode;
+
+/// Field confidence of a value confirmed by a code.
+pub const CONFIRMED_CONFIDENCE: f32 = 1.0;
+
+/// Hosts of the KSeF verification links (production and test).
+const KSEF_HOSTS: &[&str] = &["ksef.mf.gov.pl", "ksef-test.mf.gov.pl", "ksef-demo.mf.gov.pl"];
+
+/// Fields read from a KSeF verification link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KsefLink {
+    nip: String,
+    issue_date: NaiveDate,
+}
+
+/// Check `invoice` against the codes decoded from `page` (from 1) and list
+/// them in `metadata.barcodes`.
+pub fn apply_barcodes(invoice: &mut Invoice, page: Option<u32>, barcodes: &[Barcode]) {
+    for barcode in barcodes {
+        let already_listed = invoice
+            .metadata
+            .barcodes
+            .iter()
+            .any(|b| b.format == barcode.format && b.payload == barcode.payload);
+        if already_listed {
+            continue;
+        }
+
+        let mut check = Check {
+            invoice: &mut *invoice,
+            confirmed: Vec::new(),
+        };
+        let kind = if let Some(transfer) = parse_payload(&barcode.payload) {
+            check.transfer(&transfer);
+            Some(BarcodeKind::Transfer)
+        } else if let Some(link) = parse_ksef_link(&barcode.payload) {
+            check.ksef(&link);
+            Some(BarcodeKind::Ksef)
+        } else {
+            None
+        };
+        let confirmed_fields = check.confirmed;
+
+        invoice.metadata.barcodes.push(DecodedBarcode {
+            format: barcode.format.clone(),
+            payload: barcode.payload.clone(),
+            kind,
+            page,
+            confirmed_fields,
+        });
+    }
+}
+
+/// Comparison of an invoice with one code.
+struct Check<'a> {
+    invoice: &'a mut Invoice,
+    confirmed: Vec<String>,
+}
+
+impl Check<'_> {
+    fn transfer(&mut self, transfer: &TransferDetails) {
+        const SOURCE: &str = "transfer QR code";
+
+        if let Some(nip) = &transfer.nip {
+            let current = self.invoice.issuer.nip.clone();
+            match compare(current.as_deref(), nip, digits) {
+                Some(true) => self.confirm("issuer.nip"),
+                Some(false) => self.mismatch("issuer.nip", SOURCE, nip),
+                None => {
+                    self.invoice.issuer.nip = Some(nip.clone());
+                    self.fill("issuer.nip", SOURCE);
+                }
+            }
+        }
+
+        let account = format!("PL{}", transfer.account);
+        let current = self.invoice.issuer.bank_account.clone();
+        match compare(current.as_deref(), &account, digits) {
+            Some(true) => self.confirm("issuer.bank_account"),
+            Some(false) => self.mismatch("issuer.bank_account", SOURCE, &account),
+            None => {
+                self.invoice.issuer.bank_account = Some(account);
+                self.fill("issuer.bank_account", SOURCE);
+            }
+        }
+
+        let summary = &self.invoice.summary;
+        if summary.amount_due == Some(transfer.amount) {
+            self.confirm("summary.amount_due");
+        } else if summary.total_gross == transfer.amount {
+            self.confirm("summary.total_gross");
+        } else if summary.total_gross != Decimal::ZERO {
+            let amount = transfer.amount.to_string();
+            self.mismatch("summary.total_gross", SOURCE, &amount);
+        }
+
+        // Titles are free text: only a title naming the invoice confirms it
+        let number = compact(&self.invoice.header.invoice_number);
+        if !number.is_empty() && number != "UNKNOWN" && compact(&transfer.title).contains(&number) {
+            self.confirm("header.invoice_number");
+        }
+    }
+
+    fn ksef(&mut self, link: &KsefLink) {
+        const SOURCE: &str = "KSeF QR code";
+
+        let current = self.invoice.issuer.nip.clone();
+        match compare(current.as_deref(), &link.nip, digits) {
+            Some(true) => self.confirm("issuer.nip"),
+            Some(false) => self.mismatch("issuer.nip", SOURCE, &link.nip),
+            None => {
+                self.invoice.issuer.nip = Some(link.nip.clone());
+                self.fill("issuer.nip", SOURCE);
+            }
+        }
+
+        match self.invoice.header.issue_date {
+            Some(date) if date == link.issue_date => self.confirm("header.issue_date"),
+            Some(_) => self.mismatch("header.issue_date", SOURCE, &link.issue_date.to_string()),
+            None => {
+                self.invoice.header.issue_date = Some(link.issue_date);
+                self.fill("header.issue_date", SOURCE);
+            }
+        }
+    }
+
+    fn confirm(&mut self, field: &str) {
+        self.invoice
+            .metadata
+            .field_confidence
+            .insert(field.to_string(), CONFIRMED_CONFIDENCE);
+        self.confirmed.push(field.to_string());
+    }
+
+    /// Record a value taken from the code.
+    fn fill(&mut self, field: &str, source: &str) {
+        let metadata = &mut self.invoice.metadata;
+        metadata.missing_fields.retain(|missing| missing != field);
+        metadata.corrections.push(format!("{} taken from the {}", field, source));
+        self.confirm(field);
+    }
+
+    fn mismatch(&mut self, field: &str, source: &str, value: &str) {
+        self.invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::BarcodeMismatch,
+            field,
+            format!("The {} gives {} as {}", source, field, value),
+        ));
+    }
+}
+
+/// Whether the extracted `current` value equals `value` once normalized;
+/// `None` if nothing was extracted.
+fn compare(current: Option<&str>, value: &str, normalize: fn(&str) -> String) -> Option<bool> {
+    current
+        .map(normalize)
+        .filter(|current| !current.is_empty())
+        .map(|current| current == normalize(value))
+}
+
+fn digits(value: &str) -> String {
+    value.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Uppercase without whitespace, for comparing invoice numbers.
+fn compact(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Read a KSeF verification link: `https://{host}/invoice/{NIP}/{DD-MM-YYYY}/{hash}`.
+fn parse_ksef_link(payload: &str) -> Option<KsefLink> {
+    let rest = payload.trim().strip_prefix("https://")?;
+    let (host, path) = rest.split_once('/')?;
+    if !KSEF_HOSTS.iter().any(|known| host == *known || host.ends_with(&format!(".{}", known))) {
+        return None;
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let [.., "invoice", nip, date, _hash] = segments.as_slice() else {
+        return None;
+    };
+    if nip.len() != 10 || !nip.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(KsefLink {
+        nip: nip.to_string(),
+        issue_date: NaiveDate::parse_from_str(date, "%d-%m-%Y").ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSFER: &str =
+        "5261040828|PL|61109010140000071219812874|123000|ABC Sp. z o.o.|Faktura FV/1/2024|||";
+    const KSEF: &str =
+        "https://qr.ksef.mf.gov.pl/invoice/5261040828/15-01-2024/UtQp9Gpc51y-u3xApZjIjgkpZ01js-J8KflSPW8WzIE";
+
+    fn barcode(payload: &str) -> Barcode {
+        Barcode {
+            format: "qr_code".to_string(),
+            payload: payload.to_string(),
+            bbox: [0.0, 0.0, 100.0, 100.0],
+        }
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.issuer.nip = Some("5261040828".to_string());
+        invoice.issuer.bank_account = Some("PL61109010140000071219812874".to_string());
+        invoice.summary.total_gross = Decimal::new(123000, 2);
+        invoice.metadata.field_confidence.insert("issuer.nip".to_string(), 0.62);
+        invoice
+    }
+
+    #[test]
+    fn test_transfer_confirms_fields() {
+        let mut invoice = invoice();
+        apply_barcodes(&mut invoice, Some(1), &[barcode(TRANSFER)]);
+
+        let decoded = &invoice.metadata.barcodes[0];
+        assert_eq!(decoded.kind, Some(BarcodeKind::Transfer));
+        assert_eq!(decoded.page, Some(1));
+        assert_eq!(
+            decoded.confirmed_fields,
+            [
+                "issuer.nip",
+                "issuer.bank_account",
+                "summary.total_gross",
+                "header.invoice_number"
+            ]
+        );
+        assert_eq!(invoice.metadata.field_confidence["issuer.nip"], CONFIRMED_CONFIDENCE);
+        assert!(invoice.metadata.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_missing_fields_filled() {
+        let mut invoice = invoice();
+        invoice.issuer.bank_account = None;
+        invoice.header.issue_date = None;
+        invoice.metadata.missing_fields.push("header.issue_date".to_string());
+
+        apply_barcodes(&mut invoice, None, &[barcode(TRANSFER), barcode(KSEF)]);
+
+        assert_eq!(
+            invoice.issuer.bank_account.as_deref(),
+            Some("PL61109010140000071219812874")
+        );
+        assert_eq!(invoice.header.issue_date, NaiveDate::from_ymd_opt(2024, 1, 15));
+        assert!(invoice.metadata.missing_fields.is_empty());
+        assert_eq!(invoice.metadata.corrections.len(), 2);
+        assert_eq!(invoice.metadata.barcodes[1].kind, Some(BarcodeKind::Ksef));
+    }
+
+    #[test]
+    fn test_mismatch_reported() {
+        let mut invoice = invoice();
+        invoice.issuer.nip = Some("5261040823".to_string());
+
+        apply_barcodes(&mut invoice, None, &[barcode(KSEF)]);
+
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5261040823"));
+        assert_eq!(invoice.metadata.barcodes[0].confirmed_fields, ["header.issue_date"]);
+        let warning = &invoice.metadata.warnings[0];
+        assert_eq!(warning.code, IssueCode::BarcodeMismatch);
+        assert_eq!(warning.field, "issuer.nip");
+    }
+
+    #[test]
+    fn test_other_payloads_listed() {
+        let mut invoice = invoice();
+        let codes = [barcode("https://example.com/pay"), barcode("https://example.com/pay")];
+        apply_barcodes(&mut invoice, Some(2), &codes);
+
+        assert_eq!(invoice.metadata.barcodes.len(), 1);
+        assert_eq!(invoice.metadata.barcodes[0].kind, None);
+        assert!(invoice.metadata.barcodes[0].confirmed_fields.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ksef_link() {
+        let link = parse_ksef_link(KSEF).unwrap();
+        assert_eq!(link.nip, "5261040828");
+        assert_eq!(link.issue_date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+
+        assert!(parse_ksef_link("https://ksef.example.com/invoice/5261040828/15-01-2024/x").is_none());
+        assert!(parse_ksef_link("https://qr.ksef.mf.gov.pl/invoice/526104/15-01-2024/x").is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Scored field candidates.
+//!
+//! A field value can appear several times in a document (a date in the
+//! header and again in the footer, a total under several labels), and the
+//! first match is not always the right one. Every match becomes a
+//! [`Candidate`] scored from label proximity, validity, OCR confidence and
+//! where the field usually appears; the best one wins and the rest are kept
+//! for debugging.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::rules::ExtractionMatch;
+use crate::ocr::OcrResult;
+
+const LABEL_WEIGHT: f32 = 0.4;
+const VALIDITY_WEIGHT: f32 = 0.25;
+const OCR_WEIGHT: f32 = 0.2;
+const POSITION_WEIGHT: f32 = 0.15;
+
+/// Share of a duplicate's score added when the same value is found again.
+const AGREEMENT_WEIGHT: f32 = 0.5;
+
+/// A possible value for a field with the evidence behind it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candidate<T> {
+    /// Candidate value.
+    pub value: T,
+    /// Combined score (0.0 - 1.0).
+    pub score: f32,
+    /// Closeness to the field's label (1.0 right after it, 0.0 unlabeled).
+    pub label: f32,
+    /// Format, checksum and consistency checks (0.0 - 1.0).
+    pub validity: f32,
+    /// OCR recognition confidence of the line the value was read from.
+    pub ocr: f32,
+    /// Prior for the field appearing at this position (0.0 - 1.0).
+    pub position: f32,
+    /// Number of times the value was found.
+    pub occurrences: usize,
+    /// Byte range of the best occurrence in the parsed text.
+    pub span: (usize, usize),
+    /// Matched source text.
+    pub source: String,
+}
+
+impl<T> Candidate<T> {
+    /// Create an unlabeled, valid candidate with neutral priors.
+    pub fn new(value: T, span: (usize, usize), source: impl Into<String>) -> Self {
+        let mut candidate = Self {
+            value,
+            score: 0.0,
+            label: 0.0,
+            validity: 1.0,
+            ocr: 1.0,
+            position: 0.5,
+            occurrences: 1,
+            span,
+            source: source.into(),
+        };
+        candidate.rescore();
+        candidate
+    }
+
+    /// Set the label proximity factor.
+    pub fn with_label(mut self, label: f32) -> Self {
+        self.label = label.clamp(0.0, 1.0);
+        self.rescore();
+        self
+    }
+
+    /// Set the validity factor.
+    pub fn with_validity(mut self, validity: f32) -> Self {
+        self.validity = validity.clamp(0.0, 1.0);
+        self.rescore();
+        self
+    }
+
+    /// Set the OCR confidence factor.
+    pub fn with_ocr(mut self, ocr: f32) -> Self {
+        self.ocr = ocr.clamp(0.0, 1.0);
+        self.rescore();
+        self
+    }
+
+    /// Set the positional prior.
+    pub fn with_position(mut self, position: f32) -> Self {
+        self.position = position.clamp(0.0, 1.0);
+        self.rescore();
+        self
+    }
+
+    fn rescore(&mut self) {
+        self.score = LABEL_WEIGHT * self.label
+            + VALIDITY_WEIGHT * self.validity
+            + OCR_WEIGHT * self.ocr
+            + POSITION_WEIGHT * self.position;
+    }
+
+    /// Convert to a plain match with the score as confidence.
+    pub fn into_match(self) -> ExtractionMatch<T> {
+        ExtractionMatch::new(self.value, self.score, self.source).with_position(self.span.0, self.span.1)
+    }
+
+    /// Copy with the value rendered as text, for debug output.
+    pub fn describe(&self) -> Candidate<String>
+    where
+        T: Display,
+    {
+        Candidate {
+            value: self.value.to_string(),
+            score: self.score,
+            label: self.label,
+            validity: self.validity,
+            ocr: self.ocr,
+            position: self.position,
+            occurrences: self.occurrences,
+            span: self.span,
+            source: self.source.clone(),
+        }
+    }
+}
+
+/// Merge candidates with equal values and sort them best first.
+///
+/// A merged candidate keeps the evidence of its best occurrence, and its
+/// score rises with each further occurrence (never above 1.0). Ties keep
+/// the order candidates were given in.
+pub fn rank<T: PartialEq>(candidates: Vec<Candidate<T>>) -> Vec<Candidate<T>> {
+    let mut merged: Vec<Candidate<T>> = Vec::new();
+
+    for candidate in candidates {
+        match merged.iter_mut().find(|c| c.value == candidate.value) {
+            Some(existing) => {
+                let weaker = existing.score.min(candidate.score);
+                let occurrences = existing.occurrences + candidate.occurrences;
+                if candidate.score > existing.score {
+                    *existing = candidate;
+                }
+                existing.occurrences = occurrences;
+                existing.score = 1.0 - (1.0 - existing.score) * (1.0 - AGREEMENT_WEIGHT * weaker);
+            }
+            None => merged.push(candidate),
+        }
+    }
+
+    merged.sort_by(|a, b| b.score.total_cmp(&a.score));
+    merged
+}
+
+/// Scale down the score of candidates whose value is `implausible` by
+/// `factor` and re-sort them, best first.
+pub fn demote<T>(candidates: &mut [Candidate<T>], factor: f32, implausible: impl Fn(&T) -> bool) {
+    for candidate in candidates.iter_mut().filter(|c| implausible(&c.value)) {
+        candidate.score *= factor;
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+/// OCR confidence by position in text built from OCR lines.
+#[derive(Debug, Clone, Default)]
+pub struct TextConfidence {
+    line_starts: Vec<usize>,
+    scores: Vec<f32>,
+    detection: Vec<f32>,
+    handwritten: Vec<bool>,
+    /// Bounding rectangle of each line (x1, y1, x2, y2), pages stacked top
+    /// to bottom.
+    rects: Vec<[f32; 4]>,
+}
+
+impl TextConfidence {
+    /// Confidence for `text` whose lines have the given scores, one per
+    /// line (as in [`OcrResult::text`](crate::ocr::OcrResult::text)).
+    /// Lines without a score count as fully confident.
+    pub fn new(text: &str, scores: &[f32]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+        Self {
+            line_starts,
+            scores: scores.to_vec(),
+            detection: Vec::new(),
+            handwritten: Vec::new(),
+            rects: Vec::new(),
+        }
+    }
+
+    /// Confidence for the text of an OCR result, from its boxes.
+    pub fn from_ocr(result: &OcrResult) -> Self {
+        Self::from_pages(&result.text, &[result])
+    }
+
+    /// Confidence for the text of several OCR results (e.g. pages) joined
+    /// with blank lines.
+    pub fn from_pages(text: &str, pages: &[&OcrResult]) -> Self {
+        let mut scores = Vec::new();
+        let mut detection = Vec::new();
+        let mut handwritten = Vec::new();
+        let mut rects = Vec::new();
+        let mut top = 0.0;
+
+        for (i, page) in pages.iter().enumerate() {
+            if i > 0 {
+                // The blank line between pages
+                scores.push(1.0);
+                detection.push(1.0);
+                handwritten.push(false);
+                rects.push([0.0, top, 0.0, top]);
+            }
+            scores.extend(page.boxes.iter().map(|b| b.recognition_score));
+            detection.extend(page.boxes.iter().map(|b| b.detection_score));
+            handwritten.extend(page.boxes.iter().map(|b| b.handwritten));
+            rects.extend(page.boxes.iter().map(|b| {
+                let (x1, y1, x2, y2) = b.rect();
+                [x1, top + y1, x2, top + y2]
+            }));
+            top += page.image_size.1 as f32;
+        }
+
+        Self::new(text, &scores)
+            .with_detection(&detection)
+            .with_handwriting(&handwritten)
+            .with_rects(&rects)
+    }
+
+    /// Positions of the lines of text PDF pages read as OCR results (see
+    /// [`PdfExtractor::extract_page_layout`](crate::pdf::PdfExtractor::extract_page_layout)),
+    /// joined with blank lines. Their text is exact, so it has no scores.
+    pub fn from_layout(text: &str, pages: &[&OcrResult]) -> Self {
+        let rects = Self::from_pages(text, pages).rects;
+        Self::new(text, &[]).with_rects(&rects)
+    }
+
+    /// Set the detection score of each line.
+    pub fn with_detection(mut self, scores: &[f32]) -> Self {
+        self.detection = scores.to_vec();
+        self
+    }
+
+    /// Mark lines read from handwriting, one flag per line.
+    pub fn with_handwriting(mut self, handwritten: &[bool]) -> Self {
+        self.handwritten = handwritten.to_vec();
+        self
+    }
+
+    /// Set the bounding rectangle (x1, y1, x2, y2) of each line on the
+    /// page, e.g. to tell the columns of a two-column layout apart.
+    pub fn with_rects(mut self, rects: &[[f32; 4]]) -> Self {
+        self.rects = rects.to_vec();
+        self
+    }
+
+    /// Confidence for `text` made of the given lines of the original text,
+    /// in order (e.g. with running headers removed).
+    pub(super) fn select(&self, text: &str, lines: &[usize]) -> Self {
+        fn pick<T: Copy>(values: &[T], lines: &[usize], default: T) -> Vec<T> {
+            if values.is_empty() {
+                return Vec::new();
+            }
+            lines.iter().map(|&line| values.get(line).copied().unwrap_or(default)).collect()
+        }
+
+        Self::new(text, &pick(&self.scores, lines, 1.0))
+            .with_detection(&pick(&self.detection, lines, 1.0))
+            .with_handwriting(&pick(&self.handwritten, lines, false))
+            .with_rects(&pick(&self.rects, lines, [0.0; 4]))
+    }
+
+    /// Confidence at a byte offset.
+    pub fn at(&self, offset: usize) -> f32 {
+        self.scores.get(self.line(offset)).copied().unwrap_or(1.0)
+    }
+
+    /// Whether any line has a score, i.e. the text comes from OCR.
+    pub fn has_scores(&self) -> bool {
+        !self.scores.is_empty() || !self.detection.is_empty()
+    }
+
+    /// Confidence of a value at a byte range: the lowest recognition or
+    /// detection score of the lines it spans.
+    pub fn span(&self, (start, end): (usize, usize)) -> f32 {
+        let last = self.line(end.saturating_sub(1).max(start));
+        (self.line(start)..=last)
+            .flat_map(|line| [self.scores.get(line), self.detection.get(line)])
+            .flatten()
+            .fold(1.0, |lowest, score| lowest.min(*score))
+    }
+
+    /// Whether the text at a byte offset was read from handwriting.
+    pub fn is_handwritten(&self, offset: usize) -> bool {
+        self.handwritten.get(self.line(offset)).copied().unwrap_or(false)
+    }
+
+    /// Bounding rectangle of the line at a byte offset, if known.
+    pub(super) fn rect(&self, offset: usize) -> Option<[f32; 4]> {
+        self.rects.get(self.line(offset)).copied()
+    }
+
+    /// Median height of the lines with a rectangle.
+    pub(super) fn line_height(&self) -> Option<f32> {
+        let mut heights: Vec<f32> = self
+            .rects
+            .iter()
+            .map(|r| r[3] - r[1])
+            .filter(|&h| h > 0.0)
+            .collect();
+        heights.sort_by(f32::total_cmp);
+        heights.get(heights.len() / 2).copied()
+    }
+
+    pub(super) fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset).saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_confidence() {
+        let text = "NIP: 526-104-\n08-28\nrazem";
+        let confidence = TextConfidence::new(text, &[0.9, 0.8, 0.3]).with_detection(&[0.95, 0.7]);
+
+        assert_eq!(confidence.span((5, 9)), 0.9);
+        assert_eq!(confidence.span((5, 19)), 0.7);
+        assert_eq!(confidence.span((20, 25)), 0.3);
+        assert!(!TextConfidence::new(text, &[]).has_scores());
+    }
+
+    #[test]
+    fn test_rank_prefers_labeled_candidate() {
+        let unlabeled = Candidate::new("a", (0, 1), "a").with_position(1.0);
+        let labeled = Candidate::new("b", (10, 11), "b").with_label(1.0);
+
+        let ranked = rank(vec![unlabeled, labeled]);
+        assert_eq!(ranked[0].value, "b");
+        assert_eq!(ranked.len(), 2);
+    }
+
+    #[test]
+    fn test_rank_merges_duplicates() {
+        let first = Candidate::new("a", (0, 1), "a").with_ocr(0.5);
+        let second = Candidate::new("a", (20, 21), "a");
+        let best = second.score;
+
+        let ranked = rank(vec![first, second]);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].occurrences, 2);
+        assert_eq!(ranked[0].span, (20, 21));
+        assert!(ranked[0].score > best && ranked[0].score <= 1.0);
+    }
+
+    #[test]
+    fn test_text_confidence() {
+        let confidence = TextConfidence::new("ab\ncd\nef", &[0.9, 0.5]);
+        assert_eq!(confidence.at(1), 0.9);
+        assert_eq!(confidence.at(3), 0.5);
+        assert_eq!(confidence.at(7), 1.0);
+
+        let confidence = confidence.with_handwriting(&[false, true]);
+        assert!(!confidence.is_handwritten(1));
+        assert!(confidence.is_handwritten(3));
+        assert!(!confidence.is_handwritten(7));
+    }
+}
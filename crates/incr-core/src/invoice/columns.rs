@@ -0,0 +1,143 @@
+//! Reading order of two-column party blocks.
+//!
+//! OCR lines come out row by row, so seller and buyer blocks printed side by
+//! side are interleaved in the text ("Sprzedawca:", "Nabywca:",
+//! "ABC Sp. z o.o.", "XYZ S.A.", ...) and slicing it between the headers
+//! mixes the parties. With the boxes of the lines, the lines from the row of
+//! the two headers down are split into columns by their x-coordinate and the
+//! block is read one column after the other.
+
+use regex::Regex;
+
+use super::candidates::TextConfidence;
+use super::rules::patterns::{BUYER_SECTION, SELLER_SECTION};
+
+/// Vertical gap, in line heights, that ends a two-column block.
+const MAX_ROW_GAP: f32 = 3.0;
+
+/// How far, in line heights, a line of the right column may start left of
+/// its header, and a line of the left column may reach into the right one.
+const COLUMN_SLACK: f32 = 1.0;
+
+/// A line of the text with its box, if known.
+struct Line<'a> {
+    text: &'a str,
+    rect: Option<[f32; 4]>,
+}
+
+/// `text` with the two-column party block read column by column, or `None`
+/// when the seller and buyer headers aren't side by side or the lines have
+/// no boxes.
+pub(super) fn read_columns(text: &str, confidence: &TextConfidence) -> Option<String> {
+    let height = confidence.line_height()?;
+
+    let mut offset = 0;
+    let lines: Vec<Line> = text
+        .split('\n')
+        .map(|line| {
+            let rect = confidence
+                .rect(offset)
+                .filter(|r| r[2] > r[0] && r[3] > r[1] && !line.trim().is_empty());
+            offset += line.len() + 1;
+            Line { text: line, rect }
+        })
+        .collect();
+
+    let header = |pattern: &Regex| {
+        lines.iter().position(|line| line.rect.is_some() && pattern.is_match(line.text))
+    };
+    let (seller, buyer) = (header(&SELLER_SECTION)?, header(&BUYER_SECTION)?);
+    let [s, b] = [seller, buyer].map(|i| lines[i].rect.expect("header has a box"));
+
+    // The headers must be on the same row, one column after the other
+    let centre = |r: [f32; 4]| (r[1] + r[3]) / 2.0;
+    if (centre(s) - centre(b)).abs() > height / 2.0 {
+        return None;
+    }
+    let (left, right) = if s[0] < b[0] { (s, b) } else { (b, s) };
+    if left[2] > right[0] {
+        return None;
+    }
+    let split = right[0] - height * COLUMN_SLACK;
+    let reach = right[0] + height * COLUMN_SLACK;
+
+    let start = seller.min(buyer);
+    let mut end = start;
+    let mut bottom = left[3].max(right[3]);
+    let mut columns: [Vec<&str>; 2] = [Vec::new(), Vec::new()];
+    for line in &lines[start..] {
+        // A line without a box, spanning both columns or far below the
+        // block ends it
+        let Some([x1, y1, x2, y2]) = line.rect else {
+            break;
+        };
+        if (x1 < split && x2 > reach) || y1 - bottom > height * MAX_ROW_GAP {
+            break;
+        }
+        columns[usize::from(x1 >= split)].push(line.text);
+        bottom = bottom.max(y2);
+        end += 1;
+    }
+
+    let [left_lines, right_lines] = columns;
+    let ordered: Vec<&str> = lines[..start]
+        .iter()
+        .map(|line| line.text)
+        .chain(left_lines)
+        .chain(right_lines)
+        .chain(lines[end..].iter().map(|line| line.text))
+        .collect();
+    Some(ordered.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text and confidence of OCR lines with their boxes, 20px high.
+    fn ocr(lines: &[(&str, f32, f32, f32)]) -> (String, TextConfidence) {
+        let text = lines.iter().map(|l| l.0).collect::<Vec<_>>().join("\n");
+        let rects: Vec<[f32; 4]> =
+            lines.iter().map(|&(_, x1, x2, y)| [x1, y, x2, y + 20.0]).collect();
+        let confidence = TextConfidence::new(&text, &[]).with_rects(&rects);
+        (text, confidence)
+    }
+
+    #[test]
+    fn test_read_columns() {
+        let (text, confidence) = ocr(&[
+            ("Faktura VAT nr FV/1/2024", 100.0, 500.0, 20.0),
+            ("Sprzedawca:", 50.0, 200.0, 100.0),
+            ("Nabywca:", 450.0, 560.0, 100.0),
+            ("ABC Sp. z o.o.", 50.0, 250.0, 130.0),
+            ("XYZ S.A.", 450.0, 540.0, 130.0),
+            ("ul. Długa 1, 00-001 Warszawa", 50.0, 420.0, 160.0),
+            ("ul. Krótka 2, 30-001 Kraków", 440.0, 800.0, 160.0),
+            ("NIP: 526-104-08-28", 50.0, 280.0, 190.0),
+            ("Lp. Nazwa towaru lub usługi Ilość Cena Wartość", 50.0, 800.0, 260.0),
+        ]);
+
+        assert_eq!(
+            read_columns(&text, &confidence).unwrap(),
+            "Faktura VAT nr FV/1/2024\nSprzedawca:\nABC Sp. z o.o.\n\
+             ul. Długa 1, 00-001 Warszawa\nNIP: 526-104-08-28\nNabywca:\nXYZ S.A.\n\
+             ul. Krótka 2, 30-001 Kraków\nLp. Nazwa towaru lub usługi Ilość Cena Wartość"
+        );
+    }
+
+    #[test]
+    fn test_read_columns_single_column() {
+        // Headers one below the other
+        let (text, confidence) = ocr(&[
+            ("Sprzedawca:", 50.0, 200.0, 100.0),
+            ("ABC Sp. z o.o.", 50.0, 250.0, 130.0),
+            ("Nabywca:", 50.0, 160.0, 180.0),
+            ("XYZ S.A.", 50.0, 140.0, 210.0),
+        ]);
+        assert_eq!(read_columns(&text, &confidence), None);
+
+        // Without boxes
+        let text = "Sprzedawca:    Nabywca:\nABC Sp. z o.o.    XYZ S.A.";
+        assert_eq!(read_columns(text, &TextConfidence::new(text, &[])), None);
+    }
+}
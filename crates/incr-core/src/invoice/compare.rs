@@ -0,0 +1,182 @@
+//! Field-level comparison of two extraction runs over the same corpus.
+//!
+//! Before rolling out new models or extraction settings, the corpus is
+//! extracted again and every document compared with the results of the
+//! current setup. [`Comparison`] counts per field how often both runs agree,
+//! and lists the regressions: values the baseline found that the candidate
+//! lost or changed.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::ensemble::is_set;
+use super::field::FieldKind;
+use super::patch::pointer;
+use crate::models::invoice::Invoice;
+
+/// Per-field results of comparing a candidate run with a baseline.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Comparison {
+    /// Documents compared.
+    pub documents: usize,
+    /// Documents the candidate could not process.
+    pub failed: usize,
+    /// Agreement of each field, by field path.
+    pub fields: BTreeMap<String, FieldAgreement>,
+    /// Baseline values the candidate lost or changed.
+    pub regressions: Vec<Regression>,
+}
+
+/// How the two runs compare on one field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FieldAgreement {
+    /// Both runs extracted the same value.
+    pub agreed: usize,
+    /// Both runs extracted a value, but different ones.
+    pub changed: usize,
+    /// Only the baseline extracted a value.
+    pub lost: usize,
+    /// Only the candidate extracted a value.
+    pub gained: usize,
+}
+
+/// A baseline value the candidate did not reproduce.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Regression {
+    /// Document name.
+    pub document: String,
+    /// Field path (e.g. `issuer.nip`).
+    pub field: String,
+    /// Value in the baseline.
+    pub baseline: Value,
+    /// Value extracted by the candidate, if any.
+    pub candidate: Option<Value>,
+}
+
+impl FieldAgreement {
+    /// Documents where at least one run extracted the field.
+    pub fn extracted(&self) -> usize {
+        self.agreed + self.changed + self.lost + self.gained
+    }
+
+    /// Percentage of those documents where both runs agree.
+    pub fn agreement(&self) -> Option<f32> {
+        match self.extracted() {
+            0 => None,
+            n => Some(self.agreed as f32 * 100.0 / n as f32),
+        }
+    }
+}
+
+impl Comparison {
+    /// Create an empty comparison.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare the candidate extraction of a document with its baseline.
+    pub fn add(&mut self, document: &str, baseline: &Invoice, candidate: &Invoice) {
+        let candidate = serde_json::to_value(candidate).unwrap_or_default();
+        self.compare(document, baseline, &candidate);
+    }
+
+    /// Record a document the candidate could not process; every field the
+    /// baseline extracted counts as lost.
+    pub fn add_failure(&mut self, document: &str, baseline: &Invoice) {
+        self.failed += 1;
+        self.compare(document, baseline, &Value::Null);
+    }
+
+    /// Number of fields that regressed.
+    pub fn regressed(&self) -> usize {
+        self.fields.values().map(|f| f.lost + f.changed).sum()
+    }
+
+    fn compare(&mut self, document: &str, baseline: &Invoice, candidate: &Value) {
+        self.documents += 1;
+        let baseline = serde_json::to_value(baseline).unwrap_or_default();
+
+        for field in FieldKind::ALL {
+            let path = pointer(field.path());
+            let old = baseline.pointer(&path).filter(|v| is_set(v));
+            let new = candidate.pointer(&path).filter(|v| is_set(v));
+
+            let agreement = self.fields.entry(field.path().to_string()).or_default();
+            match (old, new) {
+                (None, None) => continue,
+                (None, Some(_)) => agreement.gained += 1,
+                (Some(old), new) if Some(old) != new => {
+                    match new {
+                        Some(_) => agreement.changed += 1,
+                        None => agreement.lost += 1,
+                    }
+                    self.regressions.push(Regression {
+                        document: document.to_string(),
+                        field: field.path().to_string(),
+                        baseline: old.clone(),
+                        candidate: new.cloned(),
+                    });
+                }
+                _ => agreement.agreed += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn invoice(number: &str, nip: Option<&str>, gross: i64) -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = number.to_string();
+        invoice.issuer.nip = nip.map(str::to_string);
+        invoice.summary.total_gross = Decimal::new(gross, 2);
+        invoice
+    }
+
+    #[test]
+    fn test_compare_fields() {
+        let mut comparison = Comparison::new();
+        comparison.add(
+            "a.pdf",
+            &invoice("FV/1/2024", Some("5261040828"), 123000),
+            &invoice("FV/1/2024", Some("5261040823"), 0),
+        );
+        comparison.add(
+            "b.pdf",
+            &invoice("FV/2/2024", None, 50000),
+            &invoice("FV/2/2024", Some("6750000007"), 50000),
+        );
+
+        assert_eq!(comparison.documents, 2);
+        let number = comparison.fields["header.invoice_number"];
+        assert_eq!((number.agreed, number.agreement()), (2, Some(100.0)));
+
+        let nip = comparison.fields["issuer.nip"];
+        assert_eq!((nip.changed, nip.gained, nip.agreement()), (1, 1, Some(0.0)));
+
+        let gross = comparison.fields["summary.total_gross"];
+        assert_eq!((gross.agreed, gross.lost), (1, 1));
+        assert_eq!(comparison.fields["receiver.nip"].agreement(), None);
+
+        assert_eq!(comparison.regressed(), 2);
+        assert_eq!(comparison.regressions[0].field, "issuer.nip");
+        assert_eq!(comparison.regressions[0].candidate, Some(Value::from("5261040823")));
+        assert_eq!(comparison.regressions[1].field, "summary.total_gross");
+        assert_eq!(comparison.regressions[1].candidate, None);
+    }
+
+    #[test]
+    fn test_failure_loses_fields() {
+        let mut comparison = Comparison::new();
+        comparison.add_failure("a.png", &invoice("FV/1/2024", Some("5261040828"), 0));
+
+        assert_eq!((comparison.documents, comparison.failed), (1, 1));
+        assert_eq!(comparison.fields["header.invoice_number"].lost, 1);
+        assert_eq!(comparison.regressions.len(), 2);
+    }
+}
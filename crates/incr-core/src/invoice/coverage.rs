@@ -0,0 +1,128 @@
+//! Field coverage statistics over sets of extracted invoices.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::models::invoice::Invoice;
+
+/// Fields tracked by [`CoverageReport`], in display order.
+pub const COVERAGE_FIELDS: &[&str] = &[
+    "invoice_number",
+    "issue_date",
+    "sale_date",
+    "due_date",
+    "issuer.name",
+    "issuer.nip",
+    "issuer.regon",
+    "issuer.bank_account",
+    "receiver.name",
+    "receiver.nip",
+    "line_items",
+    "total_net",
+    "total_vat",
+    "total_gross",
+    "payment_method",
+];
+
+/// Check whether a tracked field was extracted.
+///
+/// Parser placeholders (empty or `UNKNOWN` invoice number, zero totals) count
+/// as missing.
+pub fn has_field(invoice: &Invoice, field: &str) -> bool {
+    match field {
+        "invoice_number" => {
+            let number = &invoice.header.invoice_number;
+            !number.is_empty() && number != "UNKNOWN"
+        }
+        "issue_date" => invoice.header.issue_date.is_some(),
+        "sale_date" => invoice.header.sale_date.is_some(),
+        "due_date" => invoice.header.due_date.is_some(),
+        "issuer.name" => !invoice.issuer.name.is_empty(),
+        "issuer.nip" => invoice.issuer.nip.is_some(),
+        "issuer.regon" => invoice.issuer.regon.is_some(),
+        "issuer.bank_account" => invoice.issuer.bank_account.is_some(),
+        "receiver.name" => !invoice.receiver.name.is_empty(),
+        "receiver.nip" => invoice.receiver.nip.is_some(),
+        "line_items" => !invoice.line_items.is_empty(),
+        "total_net" => !invoice.summary.total_net.is_zero(),
+        "total_vat" => !invoice.summary.total_vat.is_zero(),
+        "total_gross" => !invoice.summary.total_gross.is_zero(),
+        "payment_method" => invoice.summary.payment_method.is_some(),
+        _ => false,
+    }
+}
+
+/// Number of documents with each field extracted.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CoverageReport {
+    /// Total number of documents.
+    pub documents: usize,
+    /// Documents meeting the minimum confidence.
+    pub accepted: usize,
+    /// Per-field count of accepted documents with the field present.
+    pub fields: BTreeMap<String, usize>,
+}
+
+impl CoverageReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an invoice; fields only count if its confidence reaches `min_confidence`.
+    pub fn add(&mut self, invoice: &Invoice, min_confidence: f32) {
+        self.documents += 1;
+
+        if invoice.metadata.confidence < min_confidence {
+            return;
+        }
+
+        self.accepted += 1;
+        for field in COVERAGE_FIELDS {
+            if has_field(invoice, field) {
+                *self.fields.entry(field.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Number of accepted documents with the field present.
+    pub fn count(&self, field: &str) -> usize {
+        self.fields.get(field).copied().unwrap_or(0)
+    }
+
+    /// Fraction of all documents with the field present.
+    pub fn ratio(&self, field: &str) -> f32 {
+        if self.documents == 0 {
+            0.0
+        } else {
+            self.count(field) as f32 / self.documents as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_report() {
+        let mut complete = Invoice::default();
+        complete.header.invoice_number = "FV/1/2024".to_string();
+        complete.issuer.nip = Some("5260250274".to_string());
+        complete.metadata.confidence = 0.9;
+
+        let mut weak = complete.clone();
+        weak.metadata.confidence = 0.4;
+
+        let mut report = CoverageReport::new();
+        report.add(&complete, 0.5);
+        report.add(&weak, 0.5);
+        report.add(&Invoice::default(), 0.0);
+
+        assert_eq!(report.documents, 3);
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.count("issuer.nip"), 1);
+        assert!((report.ratio("issuer.nip") - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,165 @@
+//! Voting on key fields across the extractions of several OCR runs.
+//!
+//! When several model variants read the same document, the text of each run
+//! is parsed on its own next to the merged text. A key field takes the value
+//! most of these extractions agree on; ties keep the value parsed from the
+//! merged text. Fields the extractions disagree on are reported, and
+//! `field_confidence` records the share of extractions behind each value.
+
+use serde_json::{Map, Value};
+
+use super::patch::{merge_patch, pointer, set_path};
+use crate::models::invoice::Invoice;
+use crate::models::validation::{IssueCode, ValidationIssue};
+
+/// Fields decided by voting.
+pub const KEY_FIELDS: &[&str] = &[
+    "header.invoice_number",
+    "header.issue_date",
+    "header.due_date",
+    "issuer.nip",
+    "issuer.bank_account",
+    "receiver.nip",
+    "summary.total_net",
+    "summary.total_vat",
+    "summary.total_gross",
+];
+
+/// Replace the key fields of `invoice`, extracted from the merged text,
+/// with the values most extractions agree on.
+pub fn vote_key_fields(invoice: &mut Invoice, runs: &[Invoice]) {
+    let Ok(current) = serde_json::to_value(&*invoice) else {
+        return;
+    };
+    let runs: Vec<Value> = runs
+        .iter()
+        .filter_map(|run| serde_json::to_value(run).ok())
+        .collect();
+    let total = runs.len() + 1;
+
+    let mut patch = Value::Object(Map::new());
+    let mut corrections = Vec::new();
+    let mut warnings = Vec::new();
+
+    for &field in KEY_FIELDS {
+        let path = pointer(field);
+
+        // Distinct values in order of appearance, the merged text's first
+        let mut tally: Vec<(&Value, usize)> = Vec::new();
+        let values = std::iter::once(&current)
+            .chain(&runs)
+            .filter_map(|v| v.pointer(&path))
+            .filter(|v| is_set(v));
+        for value in values {
+            match tally.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, votes)) => *votes += 1,
+                None => tally.push((value, 1)),
+            }
+        }
+
+        let Some((winner, votes)) = tally
+            .iter()
+            .copied()
+            .reduce(|best, next| if next.1 > best.1 { next } else { best })
+        else {
+            continue;
+        };
+
+        if tally.len() > 1 {
+            warnings.push(ValidationIssue::warning(
+                IssueCode::EnsembleDisagreement,
+                field,
+                format!("Ensemble extractions disagree on {}", field),
+            ));
+        }
+        invoice
+            .metadata
+            .field_confidence
+            .insert(field.to_string(), votes as f32 / total as f32);
+
+        if current.pointer(&path) != Some(winner) {
+            set_path(&mut patch, field, winner.clone());
+            corrections.push(format!(
+                "{} taken from {} of {} ensemble extractions",
+                field, votes, total
+            ));
+        }
+    }
+
+    if !corrections.is_empty() {
+        let mut voted = current;
+        merge_patch(&mut voted, &patch);
+        if let Ok(voted) = serde_json::from_value::<Invoice>(voted) {
+            let metadata = std::mem::take(&mut invoice.metadata);
+            *invoice = voted;
+            invoice.metadata = metadata;
+            invoice.metadata.corrections.extend(corrections);
+        }
+    }
+    invoice.metadata.warnings.extend(warnings);
+}
+
+/// Whether a field holds an extracted value; missing text and amounts are
+/// serialized as empty strings and zero.
+pub(super) fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.is_empty() && s.parse::<f64>() != Ok(0.0),
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn invoice(number: &str, nip: Option<&str>, gross: i64) -> Invoice {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = number.to_string();
+        invoice.issuer.nip = nip.map(str::to_string);
+        invoice.summary.total_gross = Decimal::new(gross, 2);
+        invoice
+    }
+
+    #[test]
+    fn test_majority_wins() {
+        let mut merged = invoice("FV/1/2024", Some("5261040823"), 123000);
+        let runs = [
+            invoice("FV/1/2024", Some("5261040828"), 123000),
+            invoice("FV/7/2024", Some("5261040828"), 0),
+        ];
+
+        vote_key_fields(&mut merged, &runs);
+
+        assert_eq!(merged.issuer.nip.as_deref(), Some("5261040828"));
+        assert_eq!(merged.header.invoice_number, "FV/1/2024");
+        assert_eq!(merged.summary.total_gross, Decimal::new(123000, 2));
+
+        let confidence = &merged.metadata.field_confidence;
+        assert!((confidence["issuer.nip"] - 2.0 / 3.0).abs() < 1e-6);
+        // A run that found no total does not count against the others
+        assert_eq!(confidence["summary.total_gross"], 2.0 / 3.0);
+        assert!(!confidence.contains_key("receiver.nip"));
+
+        assert_eq!(
+            merged.metadata.corrections,
+            vec!["issuer.nip taken from 2 of 3 ensemble extractions"]
+        );
+        assert!(merged.metadata.warnings.iter().any(|w| {
+            w.code == IssueCode::EnsembleDisagreement && w.field == "header.invoice_number"
+        }));
+    }
+
+    #[test]
+    fn test_tie_keeps_merged_value() {
+        let mut merged = invoice("FV/1/2024", None, 0);
+        let runs = [invoice("FV/7/2024", None, 0)];
+
+        vote_key_fields(&mut merged, &runs);
+
+        assert_eq!(merged.header.invoice_number, "FV/1/2024");
+        assert!(merged.metadata.corrections.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Extraction of a single field.
+//!
+//! Parsing a whole invoice runs every extractor, including line item and
+//! VAT table parsing. Callers that need one value (e.g. a form filler
+//! asking for the seller's NIP) can call
+//! [`HybridInvoiceParser::extract_field`], which runs only the extractors
+//! that field depends on. The value is the one a full parse would report
+//! before vendor templates are applied; totals are not derived from line
+//! items.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::candidates::TextConfidence;
+use super::columns::read_columns;
+use super::parser::{party_ids, HybridInvoiceParser, Sections};
+use super::rules::{
+    amounts::extract_amounts_with_confidence, dates::extract_dates_with_confidence,
+    iban::extract_iban, ksef::extract_ksef_number, pesel::PeselExtractor,
+};
+use crate::ocr::OcrResult;
+
+/// A field that can be extracted on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    /// Invoice number.
+    InvoiceNumber,
+    /// Issue date.
+    IssueDate,
+    /// Sale (delivery) date.
+    SaleDate,
+    /// Payment due date.
+    DueDate,
+    /// Number assigned by KSeF.
+    KsefNumber,
+    /// Issuer (seller) NIP.
+    IssuerNip,
+    /// Issuer name.
+    IssuerName,
+    /// Issuer bank account.
+    IssuerBankAccount,
+    /// Receiver (buyer) NIP.
+    ReceiverNip,
+    /// Receiver name.
+    ReceiverName,
+    /// Total net amount.
+    TotalNet,
+    /// Total VAT amount.
+    TotalVat,
+    /// Total gross amount.
+    TotalGross,
+}
+
+impl FieldKind {
+    /// All fields.
+    pub const ALL: [FieldKind; 13] = [
+        FieldKind::InvoiceNumber,
+        FieldKind::IssueDate,
+        FieldKind::SaleDate,
+        FieldKind::DueDate,
+        FieldKind::KsefNumber,
+        FieldKind::IssuerNip,
+        FieldKind::IssuerName,
+        FieldKind::IssuerBankAccount,
+        FieldKind::ReceiverNip,
+        FieldKind::ReceiverName,
+        FieldKind::TotalNet,
+        FieldKind::TotalVat,
+        FieldKind::TotalGross,
+    ];
+
+    /// Path of the field in the invoice (e.g. `issuer.nip`).
+    pub fn path(self) -> &'static str {
+        match self {
+            FieldKind::InvoiceNumber => "header.invoice_number",
+            FieldKind::IssueDate => "header.issue_date",
+            FieldKind::SaleDate => "header.sale_date",
+            FieldKind::DueDate => "header.due_date",
+            FieldKind::KsefNumber => "header.ksef_number",
+            FieldKind::IssuerNip => "issuer.nip",
+            FieldKind::IssuerName => "issuer.name",
+            FieldKind::IssuerBankAccount => "issuer.bank_account",
+            FieldKind::ReceiverNip => "receiver.nip",
+            FieldKind::ReceiverName => "receiver.name",
+            FieldKind::TotalNet => "summary.total_net",
+            FieldKind::TotalVat => "summary.total_vat",
+            FieldKind::TotalGross => "summary.total_gross",
+        }
+    }
+
+    /// The field's name (e.g. `issuer_nip`).
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldKind::InvoiceNumber => "invoice_number",
+            FieldKind::IssueDate => "issue_date",
+            FieldKind::SaleDate => "sale_date",
+            FieldKind::DueDate => "due_date",
+            FieldKind::KsefNumber => "ksef_number",
+            FieldKind::IssuerNip => "issuer_nip",
+            FieldKind::IssuerName => "issuer_name",
+            FieldKind::IssuerBankAccount => "issuer_bank_account",
+            FieldKind::ReceiverNip => "receiver_nip",
+            FieldKind::ReceiverName => "receiver_name",
+            FieldKind::TotalNet => "total_net",
+            FieldKind::TotalVat => "total_vat",
+            FieldKind::TotalGross => "total_gross",
+        }
+    }
+
+    /// Whether the field belongs to the issuer rather than the receiver.
+    fn is_issuer(self) -> bool {
+        matches!(
+            self,
+            FieldKind::IssuerNip | FieldKind::IssuerName | FieldKind::IssuerBankAccount
+        )
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FieldKind {
+    type Err = String;
+
+    /// Parse a field name (`issuer_nip`, `issuerNip`) or path (`issuer.nip`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s.trim().replace('-', "_").to_lowercase();
+        FieldKind::ALL
+            .into_iter()
+            .find(|f| {
+                key == f.name() || key == f.path() || key == f.name().replace('_', "")
+            })
+            .ok_or_else(|| format!("unknown field '{}'", s))
+    }
+}
+
+/// Value of a single extracted field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    /// Text (numbers, NIPs, names, bank accounts).
+    Text(String),
+    /// Date.
+    Date(NaiveDate),
+    /// Amount.
+    Amount(Decimal),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Text(text) => f.write_str(text),
+            FieldValue::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            FieldValue::Amount(amount) => write!(f, "{}", amount),
+        }
+    }
+}
+
+impl HybridInvoiceParser {
+    /// Extract a single field from text, running only the extractors it
+    /// needs.
+    pub fn extract_field(&self, text: &str, field: FieldKind) -> Option<FieldValue> {
+        self.extract_field_with_confidence(text, &TextConfidence::new(text, &[]), field)
+    }
+
+    /// Extract a single field from an OCR result. As in a full parse,
+    /// values read from low-confidence lines lose to the same field found
+    /// elsewhere.
+    pub fn extract_ocr_field(&self, ocr_result: &OcrResult, field: FieldKind) -> Option<FieldValue> {
+        let scores: Vec<f32> = ocr_result.boxes.iter().map(|b| b.recognition_score).collect();
+        let rects: Vec<[f32; 4]> = ocr_result
+            .boxes
+            .iter()
+            .map(|b| {
+                let (x1, y1, x2, y2) = b.rect();
+                [x1, y1, x2, y2]
+            })
+            .collect();
+        let confidence = TextConfidence::new(&ocr_result.text, &scores).with_rects(&rects);
+        self.extract_field_with_confidence(&ocr_result.text, &confidence, field)
+    }
+
+    fn extract_field_with_confidence(
+        &self,
+        text: &str,
+        confidence: &TextConfidence,
+        field: FieldKind,
+    ) -> Option<FieldValue> {
+        match field {
+            FieldKind::InvoiceNumber => self.extract_invoice_number(text).map(FieldValue::Text),
+            FieldKind::KsefNumber => extract_ksef_number(text).map(FieldValue::Text),
+            FieldKind::IssueDate | FieldKind::SaleDate | FieldKind::DueDate => {
+                let mut dates = extract_dates_with_confidence(text, confidence);
+                dates.apply_constraints(self.reference_date);
+                let date = match field {
+                    FieldKind::IssueDate => dates.issue_date,
+                    FieldKind::SaleDate => dates.sale_date,
+                    _ => dates.due_date,
+                };
+                date.map(|m| FieldValue::Date(m.value))
+            }
+            FieldKind::TotalNet | FieldKind::TotalVat | FieldKind::TotalGross => {
+                let amounts = extract_amounts_with_confidence(text, confidence);
+                let net = amounts.total_net.map(|m| m.value);
+                let gross = amounts.total_gross.map(|m| m.value);
+                let amount = match field {
+                    FieldKind::TotalNet => net,
+                    FieldKind::TotalGross => gross,
+                    _ => amounts
+                        .total_vat
+                        .map(|m| m.value)
+                        .or_else(|| Some(gross? - net?)),
+                };
+                amount.map(FieldValue::Amount)
+            }
+            _ => self.extract_party_field(text, confidence, field),
+        }
+    }
+
+    /// NIP, name or bank account of a party. The parties are swapped, as
+    /// in a full parse, when the issuer is one of the own companies.
+    fn extract_party_field(
+        &self,
+        text: &str,
+        confidence: &TextConfidence,
+        field: FieldKind,
+    ) -> Option<FieldValue> {
+        let columns = read_columns(text, confidence);
+        let party_text = columns.as_deref().unwrap_or(text);
+        let sections = Sections::find(party_text);
+        let (issuer_pesel, receiver_pesel) = party_ids(
+            &PeselExtractor::new(),
+            sections.seller,
+            sections.buyer,
+            party_text,
+            sections.sectioned,
+        );
+        let (issuer_nip, receiver_nip) = self.extract_nips(
+            text,
+            confidence,
+            issuer_pesel.is_some(),
+            receiver_pesel.is_some(),
+        );
+
+        // Whether the value comes from the seller's side of the document
+        let swapped = self.parties_swapped(issuer_nip.as_deref(), receiver_nip.as_deref());
+        let seller = field.is_issuer() != swapped;
+
+        let value = match field {
+            FieldKind::IssuerNip | FieldKind::ReceiverNip if seller => issuer_nip,
+            FieldKind::IssuerNip | FieldKind::ReceiverNip => receiver_nip,
+            FieldKind::IssuerName | FieldKind::ReceiverName => {
+                let section = if seller { sections.seller } else { sections.buyer };
+                Some(self.extract_party_name(section)).filter(|name| !name.is_empty())
+            }
+            // Only the seller's bank account is extracted
+            _ if seller => extract_iban(sections.seller).or_else(|| extract_iban(text)),
+            _ => None,
+        };
+        value.map(FieldValue::Text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoice::InvoiceParser;
+
+    const TEXT: &str = "FAKTURA VAT nr FV/001/2024\n\
+        Sprzedawca:\nABC Sp. z o.o.\nNIP: 526-104-08-28\n\
+        Konto: PL61 1090 1014 0000 0712 1981 2874\n\
+        Nabywca:\nXYZ S.A.\nNIP: 675-000-00-07\n\
+        Data wystawienia: 15.01.2024\nTermin płatności: 29.01.2024\n\
+        Razem netto: 1 000,00 zł\nVAT 23%: 230,00 zł\nRazem do zapłaty: 1 230,00 zł\n";
+
+    #[test]
+    fn test_fields_match_full_parse() {
+        let parser = HybridInvoiceParser::new();
+        let invoice = serde_json::to_value(parser.parse(TEXT).unwrap().invoice).unwrap();
+
+        for field in FieldKind::ALL {
+            let expected = invoice.pointer(&format!("/{}", field.path().replace('.', "/")));
+            let value = parser
+                .extract_field(TEXT, field)
+                .map(|v| serde_json::to_value(v).unwrap());
+            assert_eq!(value.as_ref(), expected.filter(|v| !v.is_null()), "{}", field);
+        }
+    }
+
+    #[test]
+    fn test_own_company_swapped() {
+        let parser = HybridInvoiceParser::new().with_own_nips(vec!["5261040828".to_string()]);
+
+        assert_eq!(
+            parser.extract_field(TEXT, FieldKind::IssuerNip),
+            Some(FieldValue::Text("6750000007".to_string()))
+        );
+        assert_eq!(
+            parser.extract_field(TEXT, FieldKind::ReceiverName),
+            Some(FieldValue::Text("ABC Sp. z o.o.".to_string()))
+        );
+        assert_eq!(parser.extract_field(TEXT, FieldKind::IssuerBankAccount), None);
+    }
+
+    #[test]
+    fn test_parse_field_kind() {
+        assert_eq!("issuer_nip".parse(), Ok(FieldKind::IssuerNip));
+        assert_eq!("issuerNip".parse(), Ok(FieldKind::IssuerNip));
+        assert_eq!("summary.total_gross".parse(), Ok(FieldKind::TotalGross));
+        assert!("nip".parse::<FieldKind>().is_err());
+    }
+}
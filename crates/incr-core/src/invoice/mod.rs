@@ -1,9 +1,34 @@
 //! Invoice field extraction module.
 
+pub mod barcodes;
+pub mod candidates;
+mod columns;
+pub mod compare;
+pub mod coverage;
+pub mod ensemble;
+mod field;
+mod multipage;
 mod parser;
+mod parties;
+pub mod patch;
+mod receipt;
+pub mod redact;
 pub mod rules;
+pub mod stats;
+pub mod table_items;
+mod template;
 
+pub use candidates::{rank, Candidate, TextConfidence};
+pub use compare::Comparison;
+pub use coverage::CoverageReport;
+pub use field::{FieldKind, FieldValue};
+pub use multipage::MultiPageParser;
 pub use parser::{HybridInvoiceParser, InvoiceParser, ExtractionResult};
+pub use receipt::ReceiptParser;
+pub use stats::{BatchStats, StatsSummary};
+pub use redact::Redactor;
+pub use patch::{FieldConflict, FieldProvenance, InvoicePatch, MergeReport, PatchRole};
+pub use template::{find_template, match_template};
 
 use crate::error::ExtractionError;
 use crate::models::invoice::Invoice;
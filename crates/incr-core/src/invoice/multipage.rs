@@ -0,0 +1,274 @@
+//! Parsing of invoices spanning several pages.
+//!
+//! Long invoices continue their line item table onto the next pages,
+//! repeating the company header, the table header and a page number on
+//! each, and often close every page with a subtotal carried over to the
+//! next. Parsed as one blob, the repeated header restarts the table and
+//! the carried-over subtotal ends it, losing the items that follow.
+//!
+//! [`MultiPageParser`] removes these repeated lines before parsing the
+//! pages as one document, joins item descriptions wrapped onto the next
+//! page and records the page each field was read from in
+//! `metadata.field_pages`.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use super::candidates::TextConfidence;
+use super::ensemble::{is_set, KEY_FIELDS};
+use super::parser::{is_table_header, ExtractionResult, HybridInvoiceParser, InvoiceParser};
+use super::patch::pointer;
+use super::rules::patterns::{AMOUNT_PATTERN, CARRIED_OVER, PAGE_NUMBER};
+use super::Result;
+use crate::models::invoice::{Invoice, LineItem};
+use crate::progress::{NoProgress, ProgressSink};
+
+/// Lines at the top and bottom of a page searched for running headers
+/// and footers.
+const MARGIN_LINES: usize = 4;
+
+/// A line kept from one of the pages.
+struct PageLine<'a> {
+    /// Page number, from 1.
+    page: u32,
+    /// Index of the line in the pages joined with blank lines.
+    index: usize,
+    text: &'a str,
+}
+
+/// Invoice parser for documents whose pages were read separately.
+#[derive(Clone, Default)]
+pub struct MultiPageParser {
+    parser: HybridInvoiceParser,
+}
+
+impl MultiPageParser {
+    /// Parse pages with the given single-document parser.
+    pub fn new(parser: HybridInvoiceParser) -> Self {
+        Self { parser }
+    }
+
+    /// Parse an invoice from the text of each of its pages.
+    pub fn parse_pages(&self, pages: &[&str]) -> Result<ExtractionResult> {
+        self.parse_pages_with_confidence(pages, &TextConfidence::default(), &NoProgress)
+    }
+
+    /// Parse an invoice from the OCR text of each of its pages, with the
+    /// confidence of the pages joined with blank lines (as built by
+    /// [`TextConfidence::from_pages`]).
+    pub fn parse_pages_with_confidence(
+        &self,
+        pages: &[&str],
+        confidence: &TextConfidence,
+        progress: &dyn ProgressSink,
+    ) -> Result<ExtractionResult> {
+        let lines = clean(pages);
+        let text = lines.iter().map(|line| line.text).collect::<Vec<_>>().join("\n");
+        let kept: Vec<usize> = lines.iter().map(|line| line.index).collect();
+        let mut result = self.parser.parse_with_text_confidence(
+            &text,
+            &confidence.select(&text, &kept),
+            progress,
+        )?;
+
+        let items = self.line_items(&lines);
+        let mut field_pages = HashMap::new();
+        for (i, (page, _)) in items.iter().enumerate() {
+            field_pages.insert(format!("line_items.{}", i), *page);
+        }
+        if !items.is_empty() {
+            result.invoice.line_items = items.into_iter().map(|(_, item)| item).collect();
+        }
+
+        if pages.len() > 1 {
+            field_pages.extend(self.key_field_pages(&result.invoice, &lines, pages.len()));
+            result.invoice.metadata.field_pages = field_pages;
+        }
+        Ok(result)
+    }
+
+    /// Line items of the table continued across pages, with the page of
+    /// each.
+    fn line_items(&self, lines: &[PageLine]) -> Vec<(u32, LineItem)> {
+        let mut items: Vec<(u32, LineItem)> = Vec::new();
+        let mut in_table = false;
+
+        for line in lines {
+            let text = line.text.trim();
+            if is_table_header(text) {
+                in_table = true;
+                continue;
+            }
+            if !in_table || text.is_empty() {
+                continue;
+            }
+            if text.starts_with("Razem") || text.starts_with("SUMA") {
+                break;
+            }
+
+            match self.parser.parse_line_item(text) {
+                // The last row of a page repeated at the top of the next
+                Some(item) if items.iter().any(|(_, other)| is_repeat(other, &item)) => {}
+                Some(item) => items.push((line.page, item)),
+                // A description wrapped onto the next page, before its
+                // first item
+                None => match items.last_mut() {
+                    Some((page, last)) if *page < line.page && !AMOUNT_PATTERN.is_match(text) => {
+                        last.description.push(' ');
+                        last.description.push_str(text);
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        items
+    }
+
+    /// Page each key field of `invoice` was read from: the first page
+    /// that gives the same value parsed on its own.
+    fn key_field_pages(&self, invoice: &Invoice, lines: &[PageLine], pages: usize) -> HashMap<String, u32> {
+        let Ok(merged) = serde_json::to_value(invoice) else {
+            return HashMap::new();
+        };
+        let parsed: Vec<Option<Value>> = (1..=pages as u32)
+            .map(|page| {
+                let text: Vec<&str> = lines.iter().filter(|l| l.page == page).map(|l| l.text).collect();
+                let result = self.parser.parse(&text.join("\n")).ok()?;
+                serde_json::to_value(result.invoice).ok()
+            })
+            .collect();
+
+        let mut field_pages = HashMap::new();
+        for &field in KEY_FIELDS {
+            let path = pointer(field);
+            let Some(value) = merged.pointer(&path).filter(|v| is_set(v) && *v != "UNKNOWN") else {
+                continue;
+            };
+            let page = parsed
+                .iter()
+                .position(|page| page.as_ref().and_then(|p| p.pointer(&path)) == Some(value));
+            if let Some(page) = page {
+                field_pages.insert(field.to_string(), page as u32 + 1);
+            }
+        }
+        field_pages
+    }
+}
+
+/// Lines of the pages without page numbers, carried-over subtotals and
+/// the repeats of running headers, footers and the table header.
+fn clean<'a>(pages: &[&'a str]) -> Vec<PageLine<'a>> {
+    let pages: Vec<Vec<&str>> = pages.iter().map(|page| page.split('\n').collect()).collect();
+
+    // Pages each line appears in the margins of
+    let mut margins: HashMap<&str, usize> = HashMap::new();
+    for lines in &pages {
+        let content: Vec<&str> = lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+        let count = content.len();
+        let margin: HashSet<&str> = content
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i < MARGIN_LINES || i + MARGIN_LINES >= count)
+            .map(|(_, line)| *line)
+            .collect();
+        for line in margin {
+            *margins.entry(line).or_default() += 1;
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut table_header = false;
+    let mut kept = Vec::new();
+    let mut index = 0;
+    for (page, lines) in pages.iter().enumerate() {
+        if page > 0 {
+            // The blank line between pages
+            index += 1;
+        }
+        for line in lines {
+            let text = line.trim();
+            let current = index;
+            index += 1;
+
+            if PAGE_NUMBER.is_match(text) || CARRIED_OVER.is_match(text) {
+                continue;
+            }
+            let repeated = if is_table_header(text) {
+                std::mem::replace(&mut table_header, true)
+            } else {
+                margins.get(text).is_some_and(|&n| n > 1) && !seen.insert(text)
+            };
+            if !repeated {
+                kept.push(PageLine { page: page as u32 + 1, index: current, text: line });
+            }
+        }
+    }
+
+    kept
+}
+
+/// Whether two items are the same numbered row.
+fn is_repeat(a: &LineItem, b: &LineItem) -> bool {
+    a.ordinal.is_some() && a.ordinal == b.ordinal && a.total_gross == b.total_gross
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    const PAGE_1: &str = "ACME Sp. z o.o.
+Faktura VAT nr FV/2024/100
+Data wystawienia: 2024-03-01
+Sprzedawca: ACME Sp. z o.o. NIP: 526-000-12-46
+Nabywca: Klient S.A. NIP: 123-456-32-18
+Lp | Nazwa | Ilość | Cena | Netto | VAT | Brutto
+1 | Usługa wdrożeniowa | 1 | 100,00 | 100,00 | 23,00 | 123,00
+2 | Licencja oprogramowania | 1 | 200,00 | 200,00 | 46,00 | 246,00
+3 | Szkolenie dla | 1 | 300,00 | 300,00 | 69,00 | 369,00
+Razem do przeniesienia: 600,00 138,00 738,00
+Strona 1 z 2";
+
+    const PAGE_2: &str = "ACME Sp. z o.o.
+Lp | Nazwa | Ilość | Cena | Netto | VAT | Brutto
+Z przeniesienia: 600,00 138,00 738,00
+pracowników działu IT
+4 | Wsparcie techniczne | 1 | 400,00 | 400,00 | 92,00 | 492,00
+Razem netto: 1000,00
+VAT: 230,00
+Do zapłaty: 1230,00 PLN
+Strona 2 z 2";
+
+    #[test]
+    fn test_clean_pages() {
+        let lines = clean(&[PAGE_1, PAGE_2]);
+        let text: Vec<&str> = lines.iter().map(|l| l.text).collect();
+
+        assert_eq!(text.iter().filter(|l| **l == "ACME Sp. z o.o.").count(), 1);
+        assert_eq!(text.iter().filter(|l| is_table_header(l)).count(), 1);
+        assert!(!text.iter().any(|l| l.starts_with("Strona") || l.contains("przeniesienia")));
+
+        // Indexes count the blank line between pages
+        let continued = lines.iter().find(|l| l.text == "pracowników działu IT").unwrap();
+        assert_eq!((continued.page, continued.index), (2, 15));
+    }
+
+    #[test]
+    fn test_parse_pages() {
+        let result = MultiPageParser::default().parse_pages(&[PAGE_1, PAGE_2]).unwrap();
+        let invoice = result.invoice;
+
+        assert_eq!(invoice.header.invoice_number, "FV/2024/100");
+        assert_eq!(invoice.line_items.len(), 4);
+        assert_eq!(invoice.line_items[2].description, "Szkolenie dla pracowników działu IT");
+        assert_eq!(invoice.line_items[3].total_gross, Decimal::new(49200, 2));
+
+        let pages = &invoice.metadata.field_pages;
+        assert_eq!(pages.get("line_items.2"), Some(&1));
+        assert_eq!(pages.get("line_items.3"), Some(&2));
+        assert_eq!(pages.get("header.invoice_number"), Some(&1));
+        assert_eq!(pages.get("summary.total_gross"), Some(&2));
+    }
+}
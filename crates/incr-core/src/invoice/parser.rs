@@ -1,25 +1,47 @@
 //! Hybrid invoice parser combining rule-based and ML extraction.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::time::Instant;
 
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use tracing::{debug, info};
 
+use crate::error::ConfigError;
+use crate::models::capabilities::Stage;
+use crate::models::config::VendorTemplate;
 use crate::models::invoice::*;
+use crate::models::validation::{IssueCode, ValidationIssue};
 use crate::ocr::OcrResult;
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink, ProgressStage};
 
 use super::rules::{
-    amounts::extract_amounts,
-    dates::extract_dates,
+    amounts::{extract_amounts, extract_amounts_with_confidence},
+    annotations::{extract_gtu_codes, is_split_payment},
+    contacts::extract_contacts,
+    correction::extract_correction,
+    currency::{extract_currency, extract_exchange_rate},
+    dates::extract_dates_with_confidence,
     iban::extract_iban,
-    nip::NipExtractor,
+    invoice_type::classify_invoice_type,
+    krs::KrsExtractor,
+    ksef::extract_ksef_number,
+    nip::{NipExtractor, VatIdExtractor},
     patterns::*,
+    pesel::PeselExtractor,
     regon::extract_regon,
     vat::extract_vat_rates,
+    ExtractionMatch,
     FieldExtractor,
 };
+use super::barcodes::apply_barcodes;
+use super::candidates::{Candidate, TextConfidence};
+use super::columns::read_columns;
+use super::parties::{assign_nips, score_nips};
+use super::patch::FieldProvenance;
+use super::table_items::extract_line_items as extract_table_items;
+use super::template::{digits, match_template};
 use super::{InvoiceExtractor, Result};
 
 /// Result of invoice extraction.
@@ -29,19 +51,37 @@ pub struct ExtractionResult {
     pub invoice: Invoice,
     /// Raw extracted text.
     pub raw_text: String,
-    /// Extraction warnings.
-    pub warnings: Vec<String>,
+    /// Extraction warnings, followed by the issues found by
+    /// [`Invoice::validate`].
+    pub warnings: Vec<ValidationIssue>,
     /// Processing time in milliseconds.
     pub processing_time_ms: u64,
+    /// Origin of corrected fields, keyed by field path (e.g. `issuer.nip`).
+    pub provenance: BTreeMap<String, FieldProvenance>,
+    /// Ranked candidates for scored fields, best first, keyed by field path.
+    pub candidates: BTreeMap<String, Vec<Candidate<String>>>,
 }
 
 /// Trait for invoice parsing.
 pub trait InvoiceParser {
     /// Parse invoice from text.
     fn parse(&self, text: &str) -> Result<ExtractionResult>;
+
+    /// Parse invoice from text, reporting progress to `progress`.
+    fn parse_with_progress(
+        &self,
+        text: &str,
+        progress: &dyn ProgressSink,
+    ) -> Result<ExtractionResult> {
+        progress.report(ProgressEvent::new(ProgressStage::Parsing, 0, 1, "Extracting invoice data"));
+        let result = self.parse(text)?;
+        progress.report(ProgressEvent::new(ProgressStage::Parsing, 1, 1, "Invoice data extracted"));
+        Ok(result)
+    }
 }
 
 /// Hybrid invoice parser combining rules and optional ML.
+#[derive(Clone)]
 pub struct HybridInvoiceParser {
     /// Whether to validate NIP checksums.
     validate_nip: bool,
@@ -51,6 +91,12 @@ pub struct HybridInvoiceParser {
     validate_iban: bool,
     /// Minimum confidence for accepting fields.
     min_confidence: f32,
+    /// Vendor templates applied after extraction.
+    templates: Vec<VendorTemplate>,
+    /// Own company NIPs (digits only), never the issuer.
+    own_nips: Vec<String>,
+    /// Date the document was created or received, for date plausibility.
+    pub(super) reference_date: Option<NaiveDate>,
 }
 
 impl HybridInvoiceParser {
@@ -61,6 +107,9 @@ impl HybridInvoiceParser {
             validate_regon: true,
             validate_iban: true,
             min_confidence: 0.5,
+            templates: Vec::new(),
+            own_nips: Vec::new(),
+            reference_date: None,
         }
     }
 
@@ -88,7 +137,47 @@ impl HybridInvoiceParser {
         self
     }
 
-    fn extract_invoice_number(&self, text: &str) -> Option<String> {
+    /// Set vendor templates, matched by issuer NIP or keywords.
+    pub fn with_templates(mut self, templates: Vec<VendorTemplate>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Add the vendor templates in `dir` (`*.toml` and `*.json` files).
+    pub fn with_template_dir(mut self, dir: &Path) -> std::result::Result<Self, ConfigError> {
+        self.templates.extend(VendorTemplate::load_dir(dir)?);
+        Ok(self)
+    }
+
+    /// Set own company NIPs. Invoices are then read as purchase invoices:
+    /// if an own NIP is extracted as the issuer, the parties are swapped.
+    pub fn with_own_nips(mut self, nips: Vec<String>) -> Self {
+        self.own_nips = nips.iter().map(|nip| digits(nip)).collect();
+        self
+    }
+
+    /// Set the date the document was created or received (e.g. the file's
+    /// modification date). Issue and sale dates far from it are demoted.
+    pub fn with_reference_date(mut self, date: NaiveDate) -> Self {
+        self.reference_date = Some(date);
+        self
+    }
+
+    fn is_own(&self, party: &Party) -> bool {
+        self.is_own_nip(party.nip.as_deref())
+    }
+
+    fn is_own_nip(&self, nip: Option<&str>) -> bool {
+        nip.is_some_and(|nip| self.own_nips.contains(&digits(nip)))
+    }
+
+    /// Whether the parties were read the wrong way round: the issuer is an
+    /// own company and the receiver is not.
+    pub(super) fn parties_swapped(&self, issuer_nip: Option<&str>, receiver_nip: Option<&str>) -> bool {
+        self.is_own_nip(issuer_nip) && !self.is_own_nip(receiver_nip)
+    }
+
+    pub(super) fn extract_invoice_number(&self, text: &str) -> Option<String> {
         // Try labeled pattern first
         if let Some(caps) = INVOICE_NUMBER.captures(text) {
             return Some(caps[1].trim().to_string());
@@ -102,49 +191,39 @@ impl HybridInvoiceParser {
         None
     }
 
-    fn extract_parties(&self, text: &str) -> (Party, Party) {
+    fn extract_parties(&self, text: &str, confidence: &TextConfidence) -> (Party, Party) {
         let mut issuer = Party::default();
         let mut receiver = Party::default();
 
-        // Find seller/buyer section boundaries
-        let seller_pos = SELLER_SECTION.find(text).map(|m| m.start());
-        let buyer_pos = BUYER_SECTION.find(text).map(|m| m.start());
-
-        // Determine text regions
-        let (seller_text, buyer_text) = match (seller_pos, buyer_pos) {
-            (Some(s), Some(b)) if s < b => {
-                let seller_text = &text[s..b];
-                let buyer_text = &text[b..];
-                (seller_text, buyer_text)
-            }
-            (Some(s), Some(b)) => {
-                let buyer_text = &text[b..s];
-                let seller_text = &text[s..];
-                (seller_text, buyer_text)
-            }
-            (Some(s), None) => (&text[s..], ""),
-            (None, Some(b)) => ("", &text[b..]),
-            (None, None) => {
-                // No clear sections, try to extract from whole text
-                (text, text)
-            }
-        };
-
-        // Extract NIPs
-        let nip_extractor = NipExtractor::new().with_validation(self.validate_nip);
-        let all_nips = nip_extractor.extract_all(text);
-
-        // Assign first NIP to issuer, second to receiver (common pattern)
-        if let Some(nip) = nip_extractor.extract(seller_text) {
-            issuer.nip = Some(nip.value);
-        } else if !all_nips.is_empty() {
-            issuer.nip = Some(all_nips[0].value.clone());
-        }
+        // Seller and buyer blocks side by side are read column by column
+        let columns = read_columns(text, confidence);
+        let party_text = columns.as_deref().unwrap_or(text);
+        let Sections {
+            seller: seller_text,
+            buyer: buyer_text,
+            sectioned,
+        } = Sections::find(party_text);
+
+        // Extract PESEL and KRS numbers
+        (issuer.pesel, receiver.pesel) =
+            party_ids(&PeselExtractor::new(), seller_text, buyer_text, party_text, sectioned);
+        (issuer.krs, receiver.krs) =
+            party_ids(&KrsExtractor::new(), seller_text, buyer_text, party_text, sectioned);
+
+        (issuer.nip, receiver.nip) = self.extract_nips(
+            text,
+            confidence,
+            issuer.pesel.is_some(),
+            receiver.pesel.is_some(),
+        );
 
-        if let Some(nip) = nip_extractor.extract(buyer_text) {
-            receiver.nip = Some(nip.value);
-        } else if all_nips.len() > 1 {
-            receiver.nip = Some(all_nips[1].value.clone());
+        // Foreign parties are identified by an EU VAT ID instead. Without
+        // sections a single one belongs to the party without a NIP.
+        (issuer.vat_id, receiver.vat_id) =
+            party_ids(&VatIdExtractor::new(), seller_text, buyer_text, party_text, sectioned);
+        let single = receiver.vat_id.is_none() && issuer.vat_id.is_some();
+        if !sectioned && single && issuer.nip.is_some() && receiver.nip.is_none() {
+            receiver.vat_id = issuer.vat_id.take();
         }
 
         // Extract REGONs
@@ -162,12 +241,11 @@ impl HybridInvoiceParser {
             issuer.bank_account = Some(iban);
         }
 
-        // Extract email and phone
-        if let Some(email) = EMAIL.find(seller_text) {
-            issuer.email = Some(email.as_str().to_string());
-        }
-        if let Some(phone) = PHONE.find(seller_text) {
-            issuer.phone = Some(phone.as_str().to_string());
+        // Extract contact details. Without sections the whole text is
+        // attributed to the issuer.
+        set_contacts(&mut issuer, seller_text);
+        if sectioned {
+            set_contacts(&mut receiver, buyer_text);
         }
 
         // Extract names (first line after section header)
@@ -181,7 +259,41 @@ impl HybridInvoiceParser {
         (issuer, receiver)
     }
 
-    fn extract_party_name(&self, text: &str) -> String {
+    /// Issuer and receiver NIPs, assigned by the context of each NIP
+    /// (see [`parties`](super::parties)). `confidence` gives the positions of
+    /// the lines on the page, when known. `issuer_pesel` and
+    /// `receiver_pesel` tell whether the party is identified by PESEL
+    /// instead.
+    pub(super) fn extract_nips(
+        &self,
+        text: &str,
+        confidence: &TextConfidence,
+        issuer_pesel: bool,
+        receiver_pesel: bool,
+    ) -> (Option<String>, Option<String>) {
+        // A KRS number or the digits of a Czech or Slovak VAT ID have the
+        // same length as a NIP and may pass the NIP checksum, so they are
+        // skipped.
+        let vat_ids = VatIdExtractor::new().extract_all(text);
+        let other_ids: Vec<String> = KrsExtractor::new()
+            .extract_all(text)
+            .into_iter()
+            .map(|m| m.value)
+            .chain(vat_ids.into_iter().map(|m| m.value[2..].to_string()))
+            .collect();
+        let nips: Vec<String> = NipExtractor::new()
+            .with_validation(self.validate_nip)
+            .extract_all(text)
+            .into_iter()
+            .map(|m| m.value)
+            .filter(|nip| !other_ids.contains(nip))
+            .collect();
+
+        let occurrences = score_nips(text, confidence, |nip| nips.iter().any(|n| n == nip));
+        assign_nips(&occurrences, issuer_pesel, receiver_pesel)
+    }
+
+    pub(super) fn extract_party_name(&self, text: &str) -> String {
         // Skip section header and get first non-empty line
         let lines: Vec<&str> = text
             .lines()
@@ -265,7 +377,7 @@ impl HybridInvoiceParser {
             let line = line.trim();
 
             // Detect table header
-            if line.contains("Lp") && (line.contains("Nazwa") || line.contains("Opis")) {
+            if is_table_header(line) {
                 in_table = true;
                 continue;
             }
@@ -317,6 +429,7 @@ impl HybridInvoiceParser {
                         vat_amount,
                         total_gross,
                         discount_percent: None,
+                        gtu_codes: Vec::new(),
                     });
                 }
             }
@@ -325,7 +438,7 @@ impl HybridInvoiceParser {
         items
     }
 
-    fn parse_line_item(&self, line: &str) -> Option<LineItem> {
+    pub(super) fn parse_line_item(&self, line: &str) -> Option<LineItem> {
         // Try to parse a tabular line
         // Expected format: ordinal | description | quantity | unit | price | ... | gross
 
@@ -430,10 +543,11 @@ impl HybridInvoiceParser {
             vat_amount,
             total_gross,
             discount_percent: None,
+            gtu_codes: extract_gtu_codes(line),
         })
     }
 
-    fn extract_payment_info(&self, text: &str) -> (Option<PaymentMethod>, Option<Decimal>) {
+    pub(super) fn extract_payment_info(&self, text: &str) -> (Option<PaymentMethod>, Option<Decimal>) {
         let payment_method = PAYMENT_METHOD
             .captures(text)
             .map(|c| PaymentMethod::from_str(&c[1]));
@@ -444,6 +558,121 @@ impl HybridInvoiceParser {
     }
 }
 
+/// Keep a field's ranked candidates for debugging.
+/// Whether a line is the header row of a line item table, e.g.
+/// `Lp. | Nazwa | Ilość | Jm. | Cena | Wartość | VAT`.
+pub(super) fn is_table_header(line: &str) -> bool {
+    line.contains("Lp") && (line.contains("Nazwa") || line.contains("Opis"))
+}
+
+fn record_candidates<T: std::fmt::Display>(
+    candidates: &mut BTreeMap<String, Vec<Candidate<String>>>,
+    field: &str,
+    ranked: &[Candidate<T>],
+) {
+    if !ranked.is_empty() {
+        candidates.insert(field.to_string(), ranked.iter().map(Candidate::describe).collect());
+    }
+}
+
+/// Byte range of the first occurrence of `value` in `text`, ignoring
+/// spaces and dashes (NIP `5261040828` read as `526-104-08-28`).
+fn locate(text: &str, value: &str) -> Option<(usize, usize)> {
+    if let Some(start) = text.find(value).filter(|_| !value.is_empty()) {
+        return Some((start, start + value.len()));
+    }
+
+    let separator = |c: &char| matches!(c, ' ' | '-');
+    let wanted: Vec<char> = value.chars().filter(|c| !separator(c)).collect();
+    if wanted.is_empty() {
+        return None;
+    }
+
+    let chars: Vec<(usize, char)> = text.char_indices().filter(|(_, c)| !separator(c)).collect();
+    chars
+        .windows(wanted.len())
+        .find(|window| window.iter().map(|(_, c)| *c).eq(wanted.iter().copied()))
+        .map(|window| {
+            let (last, c) = window[window.len() - 1];
+            (window[0].0, last + c.len_utf8())
+        })
+}
+
+/// Fill a party's contact details from its section of the text.
+fn set_contacts(party: &mut Party, text: &str) {
+    let contacts = extract_contacts(text);
+
+    let mut emails = contacts.emails.into_iter();
+    party.email = emails.next();
+    party.additional_emails = emails.collect();
+
+    let mut phones = contacts.phones.into_iter();
+    party.phone = phones.next();
+    party.additional_phones = phones.collect();
+
+    party.website = contacts.websites.into_iter().next();
+}
+
+/// Seller and buyer sections of an invoice text.
+#[derive(Clone, Copy)]
+pub(super) struct Sections<'a> {
+    /// Text from the seller header (the whole text without sections).
+    pub seller: &'a str,
+    /// Text from the buyer header (the whole text without sections).
+    pub buyer: &'a str,
+    /// Whether a seller or buyer header was found.
+    pub sectioned: bool,
+}
+
+impl<'a> Sections<'a> {
+    pub(super) fn find(text: &'a str) -> Self {
+        // Find seller/buyer section boundaries
+        let seller_pos = SELLER_SECTION.find(text).map(|m| m.start());
+        let buyer_pos = BUYER_SECTION.find(text).map(|m| m.start());
+
+        // Determine text regions
+        let (seller, buyer) = match (seller_pos, buyer_pos) {
+            (Some(s), Some(b)) if s < b => (&text[s..b], &text[b..]),
+            (Some(s), Some(b)) => (&text[s..], &text[b..s]),
+            (Some(s), None) => (&text[s..], ""),
+            (None, Some(b)) => ("", &text[b..]),
+            (None, None) => {
+                // No clear sections, try to extract from whole text
+                (text, text)
+            }
+        };
+
+        Self {
+            seller,
+            buyer,
+            sectioned: seller_pos.is_some() || buyer_pos.is_some(),
+        }
+    }
+}
+
+/// Find an identifier for each party: in its own section, or in document
+/// order (issuer first) when the text has no seller/buyer sections.
+pub(super) fn party_ids<E>(
+    extractor: &E,
+    seller_text: &str,
+    buyer_text: &str,
+    text: &str,
+    sectioned: bool,
+) -> (Option<String>, Option<String>)
+where
+    E: FieldExtractor<Output = ExtractionMatch<String>>,
+{
+    if sectioned {
+        (
+            extractor.extract(seller_text).map(|m| m.value),
+            extractor.extract(buyer_text).map(|m| m.value),
+        )
+    } else {
+        let mut found = extractor.extract_all(text).into_iter().map(|m| m.value);
+        (found.next(), found.next())
+    }
+}
+
 impl Default for HybridInvoiceParser {
     fn default() -> Self {
         Self::new()
@@ -452,44 +681,155 @@ impl Default for HybridInvoiceParser {
 
 impl InvoiceParser for HybridInvoiceParser {
     fn parse(&self, text: &str) -> Result<ExtractionResult> {
+        self.parse_with_progress(text, &NoProgress)
+    }
+
+    fn parse_with_progress(
+        &self,
+        text: &str,
+        progress: &dyn ProgressSink,
+    ) -> Result<ExtractionResult> {
+        self.parse_with_line_confidence(text, &[], progress)
+    }
+}
+
+impl HybridInvoiceParser {
+    /// Parse invoice from OCR text with one recognition score per line.
+    ///
+    /// Candidates read from low-confidence lines lose to the same field
+    /// found elsewhere; lines without a score count as fully confident.
+    pub fn parse_with_line_confidence(
+        &self,
+        text: &str,
+        line_confidence: &[f32],
+        progress: &dyn ProgressSink,
+    ) -> Result<ExtractionResult> {
+        self.parse_with_text_confidence(text, &TextConfidence::new(text, line_confidence), progress)
+    }
+
+    /// Parse invoice from OCR text with per-line recognition scores and
+    /// handwriting flags.
+    ///
+    /// Fields whose best candidate was read from a handwritten line are
+    /// listed in `metadata.handwritten_fields`. Key fields get the lowest
+    /// score of the lines their value was read from in
+    /// `metadata.field_confidence`.
+    pub fn parse_with_text_confidence(
+        &self,
+        text: &str,
+        confidence: &TextConfidence,
+        progress: &dyn ProgressSink,
+    ) -> Result<ExtractionResult> {
+        const STEPS: u64 = 6;
+        let step = |current: u64, message: &str| {
+            progress.report(ProgressEvent::new(ProgressStage::Parsing, current, STEPS, message));
+        };
+
         let start = Instant::now();
         let mut warnings = Vec::new();
+        let mut candidates = BTreeMap::new();
+        // Where the value of each field was read, for its OCR confidence
+        let mut spans = Vec::new();
 
         info!("Parsing invoice from {} characters of text", text.len());
 
         // Extract invoice number
+        step(0, "Extracting invoice number");
         let invoice_number = self.extract_invoice_number(text);
         if invoice_number.is_none() {
-            warnings.push("Could not extract invoice number".to_string());
+            warnings.push(ValidationIssue::warning(
+                IssueCode::MissingInvoiceNumber,
+                "header.invoice_number",
+                "Could not extract invoice number",
+            ));
         }
 
-        // Extract dates
-        let dates = extract_dates(text);
-        let has_issue_date = dates.issue_date.is_some();
-        let issue_date = dates
-            .issue_date
-            .map(|m| m.value)
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
+        // Number assigned by KSeF, for invoices issued through it
+        let ksef_number = extract_ksef_number(text);
+
+        // Invoice type from the title; correction invoices also name the
+        // invoice they correct
+        let invoice_type = classify_invoice_type(text);
+        let (correction_of, correction) = match extract_correction(text) {
+            Some(c) => (c.correction_of, Some(c.details)),
+            None => (None, None),
+        };
 
-        if !has_issue_date {
-            warnings.push("Could not extract issue date".to_string());
+        // Extract dates
+        step(1, "Extracting dates");
+        let mut dates = extract_dates_with_confidence(text, confidence);
+        warnings.extend(dates.apply_constraints(self.reference_date));
+        record_candidates(&mut candidates, "header.issue_date", &dates.candidates.issue_date);
+        record_candidates(&mut candidates, "header.sale_date", &dates.candidates.sale_date);
+        record_candidates(&mut candidates, "header.due_date", &dates.candidates.due_date);
+        spans.extend(
+            [
+                ("header.issue_date", &dates.issue_date),
+                ("header.sale_date", &dates.sale_date),
+                ("header.due_date", &dates.due_date),
+            ]
+            .into_iter()
+            .filter_map(|(field, m)| Some((field, m.as_ref()?.position?))),
+        );
+        let issue_date = dates.issue_date.map(|m| m.value);
+
+        if issue_date.is_none() {
+            warnings.push(ValidationIssue::warning(
+                IssueCode::MissingIssueDate,
+                "header.issue_date",
+                "Could not extract issue date",
+            ));
         }
 
         // Extract parties
-        let (issuer, receiver) = self.extract_parties(text);
+        step(2, "Extracting parties");
+        let (mut issuer, mut receiver) = self.extract_parties(text, confidence);
+
+        // A purchase invoice with the parties read the wrong way round
+        let mut corrections = Vec::new();
+        if self.is_own(&issuer) && !self.is_own(&receiver) {
+            info!("Issuer NIP is an own company, swapping issuer and receiver");
+            corrections.push(format!(
+                "Issuer and receiver swapped: NIP {} is an own company",
+                issuer.nip.as_deref().unwrap_or_default()
+            ));
+            std::mem::swap(&mut issuer, &mut receiver);
+        }
 
-        if issuer.nip.is_none() {
-            warnings.push("Could not extract issuer NIP".to_string());
+        if issuer.nip.is_none() && issuer.vat_id.is_none() {
+            warnings.push(ValidationIssue::warning(
+                IssueCode::MissingIssuerNip,
+                "issuer.nip",
+                "Could not extract issuer NIP",
+            ));
         }
 
         // Extract line items
+        step(3, "Extracting line items");
         let line_items = self.extract_line_items(text);
         if line_items.is_empty() {
-            warnings.push("Could not extract line items".to_string());
+            warnings.push(ValidationIssue::warning(
+                IssueCode::MissingLineItems,
+                "line_items",
+                "Could not extract line items",
+            ));
         }
 
         // Extract amounts
-        let amounts = extract_amounts(text);
+        step(4, "Extracting amounts");
+        let amounts = extract_amounts_with_confidence(text, confidence);
+        record_candidates(&mut candidates, "summary.total_net", &amounts.candidates.total_net);
+        record_candidates(&mut candidates, "summary.total_vat", &amounts.candidates.total_vat);
+        record_candidates(&mut candidates, "summary.total_gross", &amounts.candidates.total_gross);
+        spans.extend(
+            [
+                ("summary.total_net", &amounts.total_net),
+                ("summary.total_vat", &amounts.total_vat),
+                ("summary.total_gross", &amounts.total_gross),
+            ]
+            .into_iter()
+            .filter_map(|(field, m)| Some((field, m.as_ref()?.position?))),
+        );
         let total_net = amounts.total_net.map(|m| m.value).unwrap_or_else(|| {
             line_items.iter().map(|i| i.total_net).sum()
         });
@@ -506,6 +846,65 @@ impl InvoiceParser for HybridInvoiceParser {
         // Extract payment info
         let (payment_method, amount_due) = self.extract_payment_info(text);
 
+        // Extract currency, with the PLN totals of foreign-currency invoices
+        let currency = extract_currency(text).unwrap_or_else(|| "PLN".to_string());
+        let currency_info = if currency == "PLN" {
+            None
+        } else {
+            let exchange_rate = extract_exchange_rate(text);
+            if exchange_rate.is_none() {
+                warnings.push(ValidationIssue::warning(
+                    IssueCode::MissingExchangeRate,
+                    "summary.currency_info",
+                    format!("Could not extract exchange rate for {}", currency),
+                ));
+            }
+            exchange_rate.map(|rate| {
+                let to_pln = |amount: Decimal| (amount * rate.rate).round_dp(2);
+                CurrencyInfo {
+                    exchange_rate: rate.rate,
+                    rate_date: rate.date,
+                    rate_table: rate.table,
+                    total_net_pln: to_pln(total_net),
+                    total_vat_pln: to_pln(total_vat),
+                    total_gross_pln: to_pln(total_gross),
+                }
+            })
+        };
+
+        // Values read from handwritten lines
+        let handwritten_fields = candidates
+            .iter()
+            .filter(|(_, ranked)| ranked.first().is_some_and(|c| confidence.is_handwritten(c.span.0)))
+            .map(|(field, _)| field.clone())
+            .collect();
+
+        // Values kept as read are found in the text again
+        let read_values = [
+            ("header.invoice_number", invoice_number.as_deref()),
+            ("header.ksef_number", ksef_number.as_deref()),
+            ("issuer.nip", issuer.nip.as_deref()),
+            ("issuer.vat_id", issuer.vat_id.as_deref()),
+            ("issuer.name", Some(issuer.name.as_str())),
+            ("issuer.bank_account", issuer.bank_account.as_deref()),
+            ("receiver.nip", receiver.nip.as_deref()),
+            ("receiver.vat_id", receiver.vat_id.as_deref()),
+            ("receiver.name", Some(receiver.name.as_str())),
+        ];
+        spans.extend(
+            read_values
+                .into_iter()
+                .filter_map(|(field, value)| Some((field, locate(text, value?)?))),
+        );
+        let field_confidence = if confidence.has_scores() {
+            spans
+                .into_iter()
+                .map(|(field, span)| (field.to_string(), confidence.span(span)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
         // Build invoice
         let invoice = Invoice {
             header: InvoiceHeader {
@@ -513,9 +912,12 @@ impl InvoiceParser for HybridInvoiceParser {
                 issue_date,
                 sale_date: dates.sale_date.map(|m| m.value),
                 due_date: dates.due_date.map(|m| m.value),
-                invoice_type: InvoiceType::Standard,
-                currency: "PLN".to_string(),
-                correction_of: None,
+                invoice_type,
+                currency,
+                correction_of,
+                correction,
+                self_invoice: false,
+                ksef_number,
             },
             issuer,
             receiver,
@@ -529,6 +931,8 @@ impl InvoiceParser for HybridInvoiceParser {
                 amount_paid: None,
                 amount_due,
                 amount_in_words: None,
+                currency_info,
+                split_payment: is_split_payment(text),
             },
             metadata: ExtractionMetadata {
                 confidence: 0.0, // Will be calculated
@@ -537,7 +941,16 @@ impl InvoiceParser for HybridInvoiceParser {
                 ocr_engine: None,
                 warnings: warnings.clone(),
                 missing_fields: Vec::new(),
-                field_confidence: HashMap::new(),
+                corrections,
+                field_confidence,
+                incomplete: false,
+                selection: None,
+                handwritten_fields,
+                capabilities: Default::default(),
+                whitelist: Vec::new(),
+                field_pages: HashMap::new(),
+                barcodes: Vec::new(),
+                vies: Vec::new(),
             },
         };
 
@@ -559,28 +972,60 @@ impl InvoiceParser for HybridInvoiceParser {
         let mut invoice = invoice;
         invoice.metadata.confidence = confidence.max(0.0);
 
+        if invoice.has_same_nip() {
+            debug!("Issuer and receiver share a NIP, marking as self-invoice");
+            invoice.header.self_invoice = true;
+        }
+
+        let mut result = ExtractionResult {
+            invoice,
+            raw_text: text.to_string(),
+            warnings,
+            processing_time_ms: 0,
+            provenance: BTreeMap::new(),
+            candidates,
+        };
+
+        if let Some(template) = match_template(&self.templates, &result.invoice, text) {
+            match result.apply_template(template, self.min_confidence) {
+                Ok(report) => debug!(
+                    "Template '{}' set {} fields",
+                    template.name,
+                    report.applied.len()
+                ),
+                Err(e) => result.warnings.push(ValidationIssue::warning(
+                    IssueCode::TemplateNotApplied,
+                    "",
+                    format!("Template '{}' not applied: {}", template.name, e),
+                )),
+            }
+        }
+
         // Validate
-        let validation_issues = invoice.validate();
+        step(5, "Validating");
+        let validation_issues = result.invoice.validate();
         if !validation_issues.is_empty() {
-            warnings.extend(validation_issues);
+            result.warnings.extend(validation_issues);
         }
 
         debug!(
             "Extracted invoice {} with confidence {:.2}",
-            invoice.header.invoice_number, invoice.metadata.confidence
+            result.invoice.header.invoice_number, result.invoice.metadata.confidence
         );
 
-        Ok(ExtractionResult {
-            invoice,
-            raw_text: text.to_string(),
-            warnings,
-            processing_time_ms: start.elapsed().as_millis() as u64,
-        })
+        step(STEPS, "Invoice data extracted");
+
+        result.processing_time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
     }
 }
 
 impl InvoiceExtractor for HybridInvoiceParser {
     fn extract(&self, ocr_result: &OcrResult) -> Result<Invoice> {
+        let confidence = TextConfidence::from_ocr(ocr_result);
+        let parse = || self.parse_with_text_confidence(&ocr_result.text, &confidence, &NoProgress);
+        let mut capabilities = ocr_result.capabilities.clone();
+
         // Check if we have layout information with table regions
         let result = if let Some(ref layout) = ocr_result.layout {
             if !layout.tables.is_empty() {
@@ -589,26 +1034,41 @@ impl InvoiceExtractor for HybridInvoiceParser {
                 debug!("Extracted {} chars from {} table regions", table_text.len(), layout.tables.len());
 
                 // Parse with table-specific text
-                let mut parse_result = self.parse(&ocr_result.text)?;
-
-                // Re-extract line items from table regions if we found any
-                if !table_text.is_empty() {
-                    let table_items = self.extract_line_items(&table_text);
-                    if !table_items.is_empty() {
-                        parse_result.invoice.line_items = table_items;
-                    }
+                let mut parse_result = parse()?;
+
+                // Re-extract line items from table regions if we found any:
+                // by column when a table header is recognized (in the
+                // recognized structure, or a grid of the region's boxes),
+                // otherwise from the text lines of the regions
+                let mut table_items: Vec<LineItem> = layout
+                    .tables
+                    .iter()
+                    .flat_map(|region| extract_table_items(&region.table(&ocr_result.boxes)))
+                    .collect();
+                if table_items.is_empty() && !table_text.is_empty() {
+                    table_items = self.extract_line_items(&table_text);
+                }
+                if table_items.is_empty() {
+                    capabilities.skipped(Stage::Tables, "no line items found in table regions");
+                } else {
+                    parse_result.invoice.line_items = table_items;
+                    capabilities.ran(Stage::Tables);
                 }
 
                 parse_result
             } else {
-                self.parse(&ocr_result.text)?
+                capabilities.skipped(Stage::Tables, "no table regions detected");
+                parse()?
             }
         } else {
-            self.parse(&ocr_result.text)?
+            capabilities.skipped(Stage::Tables, "layout analysis did not run");
+            parse()?
         };
 
         let mut invoice = result.invoice;
+        apply_barcodes(&mut invoice, None, &ocr_result.barcodes);
         invoice.metadata.ocr_engine = Some("PaddleOCR".to_string());
+        invoice.metadata.capabilities = capabilities;
         invoice.metadata.processing_time_ms =
             Some(result.processing_time_ms + ocr_result.processing_time_ms);
 
@@ -721,6 +1181,298 @@ mod tests {
         assert!(result.invoice.receiver.nip.is_some());
     }
 
+    #[test]
+    fn test_foreign_currency_invoice() {
+        let text = r#"
+            FAKTURA VAT nr FV/002/2024
+            Data wystawienia: 15.01.2024
+            Waluta: EUR
+
+            Razem netto: 1 000,00 EUR
+            VAT 23%: 230,00 EUR
+            Razem do zapłaty: 1 230,00 EUR
+            Kurs NBP z dnia 12.01.2024 (tabela 009/A/NBP/2024): 4,3123
+        "#;
+
+        let result = HybridInvoiceParser::new().parse(text).unwrap();
+        let invoice = &result.invoice;
+
+        assert_eq!(invoice.header.currency, "EUR");
+        let info = invoice.summary.currency_info.as_ref().unwrap();
+        assert_eq!(info.exchange_rate, Decimal::new(43123, 4));
+        assert_eq!(info.rate_table.as_deref(), Some("009/A/NBP/2024"));
+        assert_eq!(info.total_vat_pln, Decimal::new(99183, 2));
+        assert_eq!(info.total_gross_pln, Decimal::new(530413, 2));
+
+        let domestic = HybridInvoiceParser::new().parse("Razem do zapłaty: 123,00 zł").unwrap();
+        assert_eq!(domestic.invoice.header.currency, "PLN");
+        assert!(domestic.invoice.summary.currency_info.is_none());
+    }
+
+    #[test]
+    fn test_ksef_number() {
+        let text = "Faktura VAT nr FV/15/2025\n\
+            Numer KSeF: 5265877635-20250826-0100001AF629-AF\n\
+            Sprzedawca:\nABC Sp. z o.o.\nNIP: 526-587-76-35\n\
+            Nabywca:\nXYZ S.A.\nNIP: 675-000-00-07\n";
+
+        let invoice = HybridInvoiceParser::new().parse(text).unwrap().invoice;
+        assert_eq!(
+            invoice.header.ksef_number.as_deref(),
+            Some("5265877635-20250826-0100001AF629-AF")
+        );
+        assert_eq!(invoice.header.invoice_number, "FV/15/2025");
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5265877635"));
+        assert_eq!(invoice.receiver.nip.as_deref(), Some("6750000007"));
+    }
+
+    #[test]
+    fn test_foreign_vat_id_parties() {
+        let parser = HybridInvoiceParser::new();
+
+        let text = "Sprzedawca:\nABC Sp. z o.o.\nNIP: 526-104-08-28\n\n\
+            Nabywca:\nMüller GmbH\nUSt-IdNr.: DE 136 695 976";
+        let (issuer, receiver) = parser.extract_parties(text, &TextConfidence::default());
+        assert_eq!(issuer.nip.as_deref(), Some("5261040828"));
+        assert_eq!(issuer.vat_id, None);
+        assert_eq!(receiver.nip, None);
+        assert_eq!(receiver.vat_id.as_deref(), Some("DE136695976"));
+
+        // Without sections the foreign ID belongs to the buyer
+        let text = "NIP: 526-104-08-28\nVAT ID: CZ25123891";
+        let (issuer, receiver) = parser.extract_parties(text, &TextConfidence::default());
+        assert_eq!(issuer.vat_id, None);
+        assert_eq!(receiver.vat_id.as_deref(), Some("CZ25123891"));
+
+        // A foreign seller has no NIP to miss
+        let text = "Sprzedawca:\nDupont SARL\nN° TVA: FR40303265045\n\nNabywca:\nXYZ S.A.\nNIP: 675-000-00-07";
+        let result = parser.parse(text).unwrap();
+        assert_eq!(result.invoice.issuer.vat_id.as_deref(), Some("FR40303265045"));
+        assert!(
+            !result
+                .invoice
+                .metadata
+                .warnings
+                .iter()
+                .any(|w| w.code == IssueCode::MissingIssuerNip)
+        );
+    }
+
+    #[test]
+    fn test_pesel_and_krs_parties() {
+        let text = r#"
+            Sprzedawca:
+            ABC Sp. z o.o.
+            KRS: 0000123458
+
+            Nabywca:
+            Jan Kowalski
+            PESEL: 44051401359
+            NIP: 675-000-00-07
+        "#;
+
+        let parser = HybridInvoiceParser::new();
+        let (issuer, receiver) = parser.extract_parties(text, &TextConfidence::default());
+
+        // 0000123458 passes the NIP checksum but is labeled as KRS
+        assert_eq!(issuer.krs.as_deref(), Some("0000123458"));
+        assert_eq!(issuer.nip, None);
+        assert_eq!(receiver.pesel.as_deref(), Some("44051401359"));
+        assert_eq!(receiver.nip.as_deref(), Some("6750000007"));
+        assert_eq!(receiver.krs, None);
+
+        let text = "Sprzedawca:\nJan Kowalski\nPESEL: 44051401359\n\nNabywca:\nXYZ S.A.\nNIP: 526-104-08-28";
+        let (issuer, receiver) = parser.extract_parties(text, &TextConfidence::default());
+
+        // The buyer's NIP isn't assigned to a seller identified by PESEL
+        assert_eq!(issuer.nip, None);
+        assert_eq!(issuer.pesel.as_deref(), Some("44051401359"));
+        assert_eq!(receiver.nip.as_deref(), Some("5261040828"));
+    }
+
+    #[test]
+    fn test_parties_inverted_layout() {
+        // Buyer block printed first, bank NIP in the footer
+        let text = "Nabywca:\nXYZ S.A.\nNIP: 675-000-00-07\n\nSprzedawca:\nABC Sp. z o.o.\n\
+                    NIP: 526-104-08-28\n\nPłatność na rachunek w Bank S.A., NIP 7770003274";
+
+        let invoice = HybridInvoiceParser::new().parse(text).unwrap().invoice;
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5261040828"));
+        assert_eq!(invoice.receiver.nip.as_deref(), Some("6750000007"));
+    }
+
+    #[test]
+    fn test_parties_two_columns() {
+        // OCR lines of seller and buyer blocks side by side, read row by row
+        let lines = [
+            ("Sprzedawca:", 50.0, 100.0),
+            ("Nabywca:", 450.0, 100.0),
+            ("ABC Sp. z o.o.", 50.0, 130.0),
+            ("XYZ S.A.", 450.0, 130.0),
+            ("ul. Długa 1", 50.0, 160.0),
+            ("ul. Krótka 2", 450.0, 160.0),
+            ("00-001 Warszawa", 50.0, 190.0),
+            ("30-001 Kraków", 450.0, 190.0),
+        ];
+        let text = lines.map(|l| l.0).join("\n");
+        let rects = lines.map(|(_, x, y)| [x, y, x + 200.0, y + 20.0]);
+        let confidence = TextConfidence::new(&text, &[]).with_rects(&rects);
+
+        let parser = HybridInvoiceParser::new();
+        let (issuer, receiver) = parser.extract_parties(&text, &confidence);
+        assert_eq!(issuer.name, "ABC Sp. z o.o.");
+        assert_eq!(issuer.address.city.as_deref(), Some("Warszawa"));
+        assert_eq!(receiver.name, "XYZ S.A.");
+        assert_eq!(receiver.address.city.as_deref(), Some("Kraków"));
+
+        // Read as plain text, the columns are mixed
+        let (issuer, _) = parser.extract_parties(&text, &TextConfidence::default());
+        assert_eq!(issuer.name, "");
+    }
+
+    #[test]
+    fn test_own_company_issuer_swapped() {
+        let text = "Sprzedawca:\nMoja Firma Sp. z o.o.\nNIP: 526-104-08-28\n\nNabywca:\nDostawca S.A.\nNIP: 675-000-00-07";
+
+        let parser = HybridInvoiceParser::new().with_own_nips(vec!["526-104-08-28".to_string()]);
+        let invoice = parser.parse(text).unwrap().invoice;
+
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("6750000007"));
+        assert_eq!(invoice.receiver.nip.as_deref(), Some("5261040828"));
+        assert_eq!(invoice.metadata.corrections.len(), 1);
+
+        // Invoices between two own companies are left alone
+        let parser = HybridInvoiceParser::new()
+            .with_own_nips(vec!["5261040828".to_string(), "6750000007".to_string()]);
+        let invoice = parser.parse(text).unwrap().invoice;
+
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5261040828"));
+        assert!(invoice.metadata.corrections.is_empty());
+    }
+
+    #[test]
+    fn test_self_invoice_flagged() {
+        let text = "Sprzedawca:\nFirma Sp. z o.o. Oddział Kraków\nNIP: 526-104-08-28\n\nNabywca:\nFirma Sp. z o.o. Oddział Gdańsk\nNIP: 5261040828";
+
+        let invoice = HybridInvoiceParser::new().parse(text).unwrap().invoice;
+        assert!(invoice.header.self_invoice);
+        assert!(!invoice.validate().iter().any(|i| i.code == IssueCode::SameNip));
+
+        // Without sections a single NIP belongs to the issuer only
+        let invoice = HybridInvoiceParser::new().parse("Firma\nNIP: 526-104-08-28").unwrap().invoice;
+        assert_eq!(invoice.receiver.nip, None);
+        assert!(!invoice.header.self_invoice);
+    }
+
+    #[test]
+    fn test_correction_invoice() {
+        let text = "Faktura korygująca nr KOR/3/2024\ndo faktury nr FV/12/2024 z dnia 15.01.2024\n\
+                    Przyczyna korekty: rabat\nRóżnica: -100,00 -23,00 -123,00";
+
+        let header = HybridInvoiceParser::new().parse(text).unwrap().invoice.header;
+        assert_eq!(header.invoice_number, "KOR/3/2024");
+        assert_eq!(header.invoice_type, InvoiceType::Correction);
+        assert_eq!(header.correction_of.as_deref(), Some("FV/12/2024"));
+        let correction = header.correction.unwrap();
+        assert_eq!(correction.reason.as_deref(), Some("rabat"));
+        assert_eq!(correction.difference.unwrap().gross, Decimal::new(-12300, 2));
+    }
+
+    #[test]
+    fn test_split_payment_and_gtu_codes() {
+        let text = "Faktura VAT nr FV/9/2024\n\
+                    Lp. Nazwa Ilość Cena Wartość netto VAT Wartość brutto\n\
+                    1 | Laptop GTU_06 | 1 | 16 000,00 | 16 000,00 | 3 680,00 | 19 680,00\n\
+                    Razem 16 000,00 3 680,00 19 680,00\n\
+                    Mechanizm podzielonej płatności";
+
+        let invoice = HybridInvoiceParser::new().parse(text).unwrap().invoice;
+        assert!(invoice.summary.split_payment);
+        assert_eq!(invoice.line_items[0].gtu_codes, ["GTU_06"]);
+    }
+
+    #[test]
+    fn test_invoice_type_from_title() {
+        let parser = HybridInvoiceParser::new();
+        for (text, number, invoice_type) in [
+            ("Faktura pro forma nr PF/2/2024", "PF/2/2024", InvoiceType::Proforma),
+            ("Faktura zaliczkowa nr FZ/1/2024", "FZ/1/2024", InvoiceType::Advance),
+            ("Faktura VAT marża nr FM/7/2024", "FM/7/2024", InvoiceType::Margin),
+        ] {
+            let header = parser.parse(text).unwrap().invoice.header;
+            assert_eq!(header.invoice_number, number);
+            assert_eq!(header.invoice_type, invoice_type);
+        }
+    }
+
+    #[test]
+    fn test_line_confidence_candidates() {
+        let text = "Faktura VAT nr FV/1/2024\nData wystawienia: 15.01.2024\nData wystawienia: 16.01.2024";
+        let result = HybridInvoiceParser::new()
+            .parse_with_line_confidence(text, &[0.99, 0.3, 0.95], &NoProgress)
+            .unwrap();
+
+        assert_eq!(result.invoice.header.issue_date, NaiveDate::from_ymd_opt(2024, 1, 16));
+        let ranked = &result.candidates["header.issue_date"];
+        assert_eq!(ranked[0].value, "2024-01-16");
+        assert_eq!(ranked[1].value, "2024-01-15");
+    }
+
+    #[test]
+    fn test_handwritten_fields() {
+        let text = "Faktura VAT nr FV/1/2024\nData wystawienia: 15.01.2024\nRazem brutto: 123,00";
+        let confidence = TextConfidence::new(text, &[0.99, 0.99, 0.8]).with_handwriting(&[false, false, true]);
+        let invoice = HybridInvoiceParser::new()
+            .parse_with_text_confidence(text, &confidence, &NoProgress)
+            .unwrap()
+            .invoice;
+
+        assert_eq!(invoice.metadata.handwritten_fields, vec!["summary.total_gross".to_string()]);
+    }
+
+    #[test]
+    fn test_field_confidence_from_boxes() {
+        let text = "Faktura VAT nr FV/1/2024\nNIP: 526-104-08-28\nData wystawienia: 15.01.2024\nRazem brutto: 123,00";
+        let boxes = [(0.98, 0.9), (0.95, 0.6), (0.99, 0.99), (0.7, 0.9)]
+            .into_iter()
+            .zip(text.lines())
+            .map(|((recognition, detection), line)| crate::ocr::TextBox {
+                bbox: [0.0; 8],
+                text: line.to_string(),
+                detection_score: detection,
+                recognition_score: recognition,
+                angle: 0,
+                handwritten: false,
+            })
+            .collect();
+        let ocr_result = OcrResult {
+            boxes,
+            text: text.to_string(),
+            processing_time_ms: 0,
+            image_size: (800, 200),
+            layout: None,
+            capabilities: Default::default(),
+            barcodes: Vec::new(),
+        };
+
+        let invoice = HybridInvoiceParser::new().extract(&ocr_result).unwrap();
+        let confidence = &invoice.metadata.field_confidence;
+        assert_eq!(confidence["header.invoice_number"], 0.9);
+        assert_eq!(confidence["issuer.nip"], 0.6);
+        assert_eq!(confidence["header.issue_date"], 0.99);
+        assert_eq!(confidence["summary.total_gross"], 0.7);
+
+        let parsed = HybridInvoiceParser::new().parse(text).unwrap().invoice;
+        assert!(parsed.metadata.field_confidence.is_empty());
+    }
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate("NIP: 526-104-08-28", "5261040828"), Some((5, 18)));
+        assert_eq!(locate("nr FV/1/2024", "FV/1/2024"), Some((3, 12)));
+        assert_eq!(locate("nr FV/1/2024", ""), None);
+    }
+
     #[test]
     fn test_extract_invoice_number() {
         let parser = HybridInvoiceParser::new();
@@ -0,0 +1,408 @@
+//! Assignment of NIPs to the issuer and receiver.
+//!
+//! Every NIP on the invoice is scored for both parties from its context: a
+//! seller or buyer label before it on its line ("NIP nabywcy"), the nearest
+//! section header above it in the same column, whether its line names a
+//! bank (banks print their own NIP in footers) and, as a tie-breaker,
+//! document order. The pair of NIPs with the highest total score is
+//! assigned; with two parties, trying every pair is an exact assignment.
+//!
+//! Positions are measured in line heights. With OCR boxes they come from
+//! the page, so the columns of a two-column layout are told apart even
+//! though its cells are separate lines of the text; otherwise from line and
+//! column numbers in the text.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::candidates::TextConfidence;
+use super::rules::patterns::{NIP_PATTERN, NIP_STANDALONE};
+
+lazy_static! {
+    /// Seller headers and labels, in any case form ("Sprzedawca",
+    /// "NIP sprzedawcy").
+    static ref SELLER_LABEL: Regex =
+        Regex::new(r"(?i)\b(?:sprzedawc|wystawc|dostawc)\w*").unwrap();
+
+    /// Buyer headers and labels ("Nabywca", "NIP nabywcy").
+    static ref BUYER_LABEL: Regex =
+        Regex::new(r"(?i)\b(?:nabywc|kupuj[aą]c|odbiorc|zamawiaj[aą]c)\w*").unwrap();
+
+    /// Banks, whose NIP is printed next to the account or in the footer.
+    static ref BANK: Regex = Regex::new(r"(?i)\w*bank\b|\bbanku\b|\bBIC\b|\bSWIFT\b").unwrap();
+}
+
+/// Score of a seller or buyer label before the NIP on its line.
+const LABEL_WEIGHT: f32 = 3.0;
+
+/// Score of the section header the NIP belongs to, before distance decay.
+const HEADER_WEIGHT: f32 = 2.0;
+
+/// Distance below a header, in lines, at which its score halves.
+const HEADER_HALF_DISTANCE: f32 = 8.0;
+
+/// Horizontal distance from a header, in line heights, at which its score
+/// halves.
+const COLUMN_HALF_DISTANCE: f32 = 6.0;
+
+/// Score of document order: the first NIP for the issuer, the second for
+/// the receiver.
+const ORDER_WEIGHT: f32 = 0.5;
+
+/// Factor for the scores of NIPs on or below a line naming a bank.
+const BANK_FACTOR: f32 = 0.1;
+
+/// Lowest score at which a NIP is assigned.
+const MIN_SCORE: f32 = 0.25;
+
+/// Text columns per line height, for positions without OCR boxes.
+const COLUMNS_PER_LINE_HEIGHT: f32 = 2.0;
+
+/// Party a label or score refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Issuer,
+    Receiver,
+}
+
+/// Position of a piece of text, in line heights.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+/// A seller or buyer header or label.
+struct Label {
+    role: Role,
+    offset: usize,
+    line: usize,
+    at: Point,
+}
+
+/// A NIP found in the text, scored for both parties.
+#[derive(Debug, Clone)]
+pub(super) struct NipOccurrence {
+    /// The NIP's digits.
+    pub value: String,
+    /// Byte range in the text.
+    pub span: (usize, usize),
+    /// Score from labels and headers, issuer first.
+    evidence: [f32; 2],
+    /// Score from document order, issuer first.
+    order: [f32; 2],
+    /// Whether the NIP is on or below a line naming a bank.
+    bank: bool,
+}
+
+impl NipOccurrence {
+    /// Score for `role`, or `None` if the NIP shouldn't be assigned to it.
+    /// A party identified by PESEL (`pesel`) only takes a NIP labeled or
+    /// found in its section.
+    fn score(&self, role: Role, pesel: bool) -> Option<f32> {
+        let index = role as usize;
+        let factor = if self.bank { BANK_FACTOR } else { 1.0 };
+        let score = (self.evidence[index] + self.order[index]) * factor;
+        let labeled = self.evidence[index] > 0.0;
+        (score >= MIN_SCORE && (labeled || !pesel)).then_some(score)
+    }
+}
+
+/// Positions of text offsets: from the OCR boxes of the lines when known,
+/// otherwise from line and column numbers.
+struct Layout<'a> {
+    text: &'a str,
+    confidence: &'a TextConfidence,
+    line_height: Option<f32>,
+}
+
+impl<'a> Layout<'a> {
+    fn new(text: &'a str, confidence: &'a TextConfidence) -> Self {
+        Self {
+            text,
+            confidence,
+            line_height: confidence.line_height(),
+        }
+    }
+
+    /// Line number of an offset.
+    fn line(&self, offset: usize) -> usize {
+        self.text[..offset].matches('\n').count()
+    }
+
+    /// Text of the line containing `offset`, and where it starts.
+    fn line_at(&self, offset: usize) -> (usize, &'a str) {
+        let start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[offset..].find('\n').map_or(self.text.len(), |i| offset + i);
+        (start, &self.text[start..end])
+    }
+
+    fn point(&self, offset: usize) -> Point {
+        let (start, line) = self.line_at(offset);
+        let column = self.text[start..offset].chars().count() as f32;
+
+        let rect = self.confidence.rect(offset).filter(|r| r[3] > r[1]);
+        match (rect, self.line_height) {
+            (Some([x1, y1, x2, _]), Some(height)) => {
+                // Characters are spread evenly over the box
+                let chars = line.chars().count().max(1) as f32;
+                Point {
+                    x: (x1 + (x2 - x1) * column / chars) / height,
+                    y: y1 / height,
+                }
+            }
+            _ => Point {
+                x: column / COLUMNS_PER_LINE_HEIGHT,
+                y: self.line(offset) as f32,
+            },
+        }
+    }
+}
+
+/// Find the NIPs in `text` that pass `is_nip` and score them for both
+/// parties. Every occurrence is listed, in document order.
+pub(super) fn score_nips(
+    text: &str,
+    confidence: &TextConfidence,
+    is_nip: impl Fn(&str) -> bool,
+) -> Vec<NipOccurrence> {
+    let layout = Layout::new(text, confidence);
+
+    let labels: Vec<Label> = [(Role::Issuer, &*SELLER_LABEL), (Role::Receiver, &*BUYER_LABEL)]
+        .into_iter()
+        .flat_map(|(role, pattern)| {
+            pattern.find_iter(text).map(move |m| (role, m.start()))
+        })
+        .map(|(role, offset)| Label {
+            role,
+            offset,
+            line: layout.line(offset),
+            at: layout.point(offset),
+        })
+        .collect();
+
+    let mut occurrences: Vec<NipOccurrence> = Vec::new();
+    for pattern in [&*NIP_PATTERN, &*NIP_STANDALONE] {
+        for caps in pattern.captures_iter(text) {
+            let whole = caps.get(0).expect("match");
+            let value = format!("{}{}{}{}", &caps[1], &caps[2], &caps[3], &caps[4]);
+            let span = (caps.get(1).expect("group").start(), whole.end());
+            let overlaps = occurrences.iter().any(|o| o.span.0 < span.1 && span.0 < o.span.1);
+            if overlaps || !is_nip(&value) {
+                continue;
+            }
+            occurrences.push(NipOccurrence {
+                evidence: evidence(&layout, &labels, span.0),
+                order: [0.0; 2],
+                bank: names_bank(&layout, span.0),
+                value,
+                span,
+            });
+        }
+    }
+    occurrences.sort_by_key(|o| o.span.0);
+
+    // Document order among NIPs other than the banks'
+    let mut distinct: Vec<&str> = Vec::new();
+    for occurrence in occurrences.iter().filter(|o| !o.bank) {
+        if !distinct.contains(&occurrence.value.as_str()) {
+            distinct.push(&occurrence.value);
+        }
+    }
+    let ranks: Vec<Option<usize>> = occurrences
+        .iter()
+        .map(|o| distinct.iter().position(|&value| value == o.value))
+        .collect();
+    for (occurrence, rank) in occurrences.iter_mut().zip(ranks) {
+        match rank {
+            Some(0) => occurrence.order[Role::Issuer as usize] = ORDER_WEIGHT,
+            Some(1) => occurrence.order[Role::Receiver as usize] = ORDER_WEIGHT,
+            _ => {}
+        }
+    }
+
+    occurrences
+}
+
+/// Score from labels and headers for a NIP at `offset`, issuer first.
+///
+/// A label before the NIP on its line decides; otherwise the header above
+/// it that is nearest, counting horizontal distance, gives the section it
+/// belongs to.
+fn evidence(layout: &Layout, labels: &[Label], offset: usize) -> [f32; 2] {
+    let mut scores = [0.0; 2];
+    let line = layout.line(offset);
+
+    let labeled = labels
+        .iter()
+        .filter(|label| label.line == line && label.offset < offset)
+        .max_by_key(|label| label.offset);
+    if let Some(label) = labeled {
+        scores[label.role as usize] = LABEL_WEIGHT;
+        return scores;
+    }
+
+    let at = layout.point(offset);
+    let header = labels
+        .iter()
+        .filter(|label| label.line != line && label.at.y < at.y)
+        .map(|label| {
+            let below = (at.y - label.at.y) / HEADER_HALF_DISTANCE;
+            let aside = (at.x - label.at.x).abs() / COLUMN_HALF_DISTANCE;
+            (label.role, HEADER_WEIGHT / ((1.0 + below) * (1.0 + aside)))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((role, score)) = header {
+        scores[role as usize] = score;
+    }
+    scores
+}
+
+/// Whether the line of `offset` or the line above names a bank.
+fn names_bank(layout: &Layout, offset: usize) -> bool {
+    let (start, line) = layout.line_at(offset);
+    let above = match start.checked_sub(1) {
+        Some(end) => layout.line_at(end).1,
+        None => "",
+    };
+    BANK.is_match(line) || BANK.is_match(above)
+}
+
+/// The issuer's and receiver's NIPs: the pair of occurrences with the
+/// highest total score. `issuer_pesel` and `receiver_pesel` tell whether
+/// the party is identified by PESEL instead, so it may have no NIP.
+///
+/// Both parties get the same NIP only when it is labeled or found in each
+/// party's section (a self-invoice between branches of one company).
+pub(super) fn assign_nips(
+    occurrences: &[NipOccurrence],
+    issuer_pesel: bool,
+    receiver_pesel: bool,
+) -> (Option<String>, Option<String>) {
+    let options = |role: Role, pesel: bool| {
+        let scored = occurrences
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, o)| o.score(role, pesel).map(|score| (Some(i), score)));
+        std::iter::once((None, 0.0)).chain(scored).collect::<Vec<_>>()
+    };
+    let issuers = options(Role::Issuer, issuer_pesel);
+    let receivers = options(Role::Receiver, receiver_pesel);
+
+    let mut best = (None, None, 0.0);
+    for &(issuer, issuer_score) in &issuers {
+        for &(receiver, receiver_score) in &receivers {
+            if let (Some(i), Some(r)) = (issuer, receiver) {
+                let (a, b) = (&occurrences[i], &occurrences[r]);
+                let labeled = a.evidence[Role::Issuer as usize] > 0.0
+                    && b.evidence[Role::Receiver as usize] > 0.0;
+                if i == r || (a.value == b.value && !labeled) {
+                    continue;
+                }
+            }
+            let total = issuer_score + receiver_score;
+            if total > best.2 {
+                best = (issuer, receiver, total);
+            }
+        }
+    }
+
+    let value = |index: Option<usize>| index.map(|i| occurrences[i].value.clone());
+    (value(best.0), value(best.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assign(text: &str, confidence: &TextConfidence) -> (Option<String>, Option<String>) {
+        let occurrences = score_nips(text, confidence, |_| true);
+        assign_nips(&occurrences, false, false)
+    }
+
+    fn nips(issuer: &str, receiver: &str) -> (Option<String>, Option<String>) {
+        (Some(issuer.to_string()), Some(receiver.to_string()))
+    }
+
+    #[test]
+    fn test_buyer_block_first() {
+        let text = "Nabywca:\nXYZ S.A.\nNIP: 675-000-00-07\n\nSprzedawca:\nABC Sp. z o.o.\nNIP: 526-104-08-28";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "6750000007"));
+    }
+
+    #[test]
+    fn test_labels_without_sections() {
+        let text = "Faktura VAT 1/2024\nNIP nabywcy: 675-000-00-07\nNIP sprzedawcy: 526-104-08-28";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "6750000007"));
+    }
+
+    #[test]
+    fn test_bank_footer_ignored() {
+        // Without sections the second NIP used to be taken as the buyer's
+        let text = "ABC Sp. z o.o.\nNIP: 526-104-08-28\nRazem: 123,00 zł\nmBank S.A.\nNIP 526-021-50-88";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), (Some("5261040828".to_string()), None));
+
+        let text = "Sprzedawca:\nABC Sp. z o.o.\nNIP: 526-104-08-28\nNabywca:\nXYZ S.A.\n\
+                    NIP: 675-000-00-07\nKonto: Bank Pekao S.A., NIP 526-000-62-91";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "6750000007"));
+    }
+
+    #[test]
+    fn test_two_columns_in_text() {
+        // Columns extracted from a PDF side by side
+        let text = "Sprzedawca:                 Nabywca:\n\
+                    ABC Sp. z o.o.              XYZ S.A.\n\
+                    NIP: 526-104-08-28          NIP: 675-000-00-07";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "6750000007"));
+
+        // The buyer on the left
+        let text = "Nabywca:                    Sprzedawca:\n\
+                    XYZ S.A.                    ABC Sp. z o.o.\n\
+                    NIP: 675-000-00-07          NIP: 526-104-08-28";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "6750000007"));
+    }
+
+    #[test]
+    fn test_two_columns_from_boxes() {
+        // OCR reads each cell as a line; only the boxes tell the columns
+        // apart. The buyer is on the left and read first.
+        let cells = [
+            ("Nabywca:", [50.0, 100.0, 200.0, 130.0]),
+            ("Sprzedawca:", [900.0, 100.0, 1100.0, 130.0]),
+            ("XYZ S.A.", [50.0, 140.0, 250.0, 170.0]),
+            ("ABC Sp. z o.o.", [900.0, 140.0, 1150.0, 170.0]),
+            ("NIP: 675-000-00-07", [50.0, 180.0, 350.0, 210.0]),
+            ("NIP: 526-104-08-28", [900.0, 180.0, 1200.0, 210.0]),
+        ];
+        let text = cells.iter().map(|(text, _)| *text).collect::<Vec<_>>().join("\n");
+        let rects: Vec<[f32; 4]> = cells.iter().map(|(_, rect)| *rect).collect();
+
+        let confidence = TextConfidence::new(&text, &[]).with_rects(&rects);
+        assert_eq!(assign(&text, &confidence), nips("5261040828", "6750000007"));
+    }
+
+    #[test]
+    fn test_same_nip_needs_both_sections() {
+        let text = "Sprzedawca:\nFirma Oddział Kraków\nNIP: 526-104-08-28\n\nNabywca:\nFirma Oddział Gdańsk\nNIP: 5261040828";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), nips("5261040828", "5261040828"));
+
+        // A NIP repeated in the footer doesn't become the buyer's
+        let text = "ABC Sp. z o.o.\nNIP: 526-104-08-28\nRazem 100,00\nABC Sp. z o.o. NIP: 526-104-08-28";
+        let confidence = TextConfidence::new(text, &[]);
+        assert_eq!(assign(text, &confidence), (Some("5261040828".to_string()), None));
+    }
+
+    #[test]
+    fn test_pesel_party_takes_only_its_own_nip() {
+        let text = "Sprzedawca:\nJan Kowalski\nPESEL: 44051401359\n\nNabywca:\nXYZ S.A.\nNIP: 526-104-08-28";
+        let occurrences = score_nips(text, &TextConfidence::new(text, &[]), |_| true);
+        assert_eq!(assign_nips(&occurrences, true, false), (None, Some("5261040828".into())));
+    }
+}
@@ -0,0 +1,478 @@
+//! Precedence-aware merging of corrections into extraction results.
+//!
+//! Corrections are JSON Merge Patches (RFC 7396) over the [`Invoice`]
+//! schema: objects are merged recursively, `null` removes an optional
+//! field and arrays such as `line_items` are replaced as a whole. Every
+//! applied field is recorded in [`ExtractionResult::provenance`], so
+//! human-confirmed values are never silently overwritten by later
+//! machine output.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::ExtractionError;
+use crate::models::invoice::Invoice;
+
+use super::{ExtractionResult, Result};
+
+/// Who produced a set of changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchRole {
+    /// Rules or ML output, e.g. a re-run with a newer model.
+    Machine,
+    /// A vendor template from the configuration.
+    Template,
+    /// A person reviewing the invoice.
+    #[default]
+    Human,
+}
+
+/// Origin of a corrected field value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldProvenance {
+    /// Role that set the value.
+    pub role: PatchRole,
+    /// Person or system that set the value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// When the value was set.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FieldProvenance {
+    /// Whether the value was set or confirmed by a person.
+    pub fn is_human_confirmed(&self) -> bool {
+        self.role == PatchRole::Human
+    }
+}
+
+/// Corrections to an invoice as a JSON Merge Patch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoicePatch {
+    changes: Map<String, Value>,
+    role: PatchRole,
+    author: Option<String>,
+}
+
+impl InvoicePatch {
+    /// Create a human correction from a merge patch object.
+    pub fn new(changes: Value) -> Result<Self> {
+        match changes {
+            Value::Object(changes) => Ok(Self {
+                changes,
+                role: PatchRole::default(),
+                author: None,
+            }),
+            other => Err(ExtractionError::InvalidPatch(format!(
+                "expected a JSON object, got {}",
+                other
+            ))),
+        }
+    }
+
+    /// Parse a merge patch document.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let changes = serde_json::from_str(json)
+            .map_err(|e| ExtractionError::InvalidPatch(e.to_string()))?;
+        Self::new(changes)
+    }
+
+    /// Patch turning `from` into `to`, ignoring extraction metadata.
+    pub fn between(from: &Invoice, to: &Invoice) -> Self {
+        let mut from = serde_json::to_value(from).unwrap_or_default();
+        let mut to = serde_json::to_value(to).unwrap_or_default();
+        for value in [&mut from, &mut to] {
+            if let Some(object) = value.as_object_mut() {
+                object.remove("metadata");
+            }
+        }
+
+        let changes = match diff(&from, &to) {
+            Some(Value::Object(changes)) => changes,
+            _ => Map::new(),
+        };
+
+        Self {
+            changes,
+            role: PatchRole::default(),
+            author: None,
+        }
+    }
+
+    /// Set the role the changes come from.
+    pub fn with_role(mut self, role: PatchRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Set the person or system making the changes.
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Role the changes come from.
+    pub fn role(&self) -> PatchRole {
+        self.role
+    }
+
+    /// Whether the patch changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Paths of the fields the patch sets or removes (e.g. `issuer.nip`).
+    pub fn fields(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        collect_leaves(&self.changes, "", &mut |path, _| fields.push(path));
+        fields
+    }
+
+    /// The merge patch document.
+    pub fn to_value(&self) -> Value {
+        Value::Object(self.changes.clone())
+    }
+}
+
+/// A patched field whose current value was confirmed by a person.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldConflict {
+    /// Field path.
+    pub field: String,
+    /// Value before the patch.
+    pub current: Value,
+    /// Value proposed by the patch (`null` for removal).
+    pub proposed: Value,
+    /// Whether the proposed value replaced the confirmed one.
+    pub applied: bool,
+}
+
+/// Outcome of [`ExtractionResult::apply_corrections`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    /// Fields set or removed by the patch.
+    pub applied: Vec<String>,
+    /// Fields that disagreed with a human-confirmed value.
+    pub conflicts: Vec<FieldConflict>,
+}
+
+impl MergeReport {
+    /// Whether any conflicts were found.
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+impl ExtractionResult {
+    /// Merge corrections into the extracted invoice.
+    ///
+    /// Human corrections always apply; where they replace a different
+    /// human-confirmed value the change is reported as a conflict. Machine
+    /// and template changes never override human-confirmed fields; those are
+    /// skipped and reported. The patched invoice must still match the schema, otherwise
+    /// nothing is changed.
+    pub fn apply_corrections(&mut self, corrections: InvoicePatch) -> Result<MergeReport> {
+        let current = serde_json::to_value(&self.invoice)
+            .map_err(|e| ExtractionError::InvalidPatch(e.to_string()))?;
+
+        let mut leaves = Vec::new();
+        collect_leaves(&corrections.changes, "", &mut |path, value| {
+            leaves.push((path, value.clone()))
+        });
+
+        let mut report = MergeReport::default();
+        let mut accepted = Value::Object(Map::new());
+
+        for (field, proposed) in leaves {
+            if field == "metadata" || field.starts_with("metadata.") {
+                return Err(ExtractionError::InvalidPatch(format!(
+                    "'{}' is extraction metadata and cannot be corrected",
+                    field
+                )));
+            }
+
+            let existing = current.pointer(&pointer(&field)).cloned().unwrap_or(Value::Null);
+            // Replacing an object replaces the fields in it, and a field in
+            // a confirmed object is confirmed as well
+            let confirmed = self.provenance.iter().any(|(path, provenance)| {
+                provenance.is_human_confirmed()
+                    && (path == &field || is_within(path, &field) || is_within(&field, path))
+            });
+
+            if confirmed && existing != proposed {
+                let applied = corrections.role == PatchRole::Human;
+                report.conflicts.push(FieldConflict {
+                    field: field.clone(),
+                    current: existing,
+                    proposed: proposed.clone(),
+                    applied,
+                });
+                if !applied {
+                    continue;
+                }
+            }
+
+            set_path(&mut accepted, &field, proposed);
+            report.applied.push(field);
+        }
+
+        let mut merged = current;
+        merge_patch(&mut merged, &accepted);
+        let invoice: Invoice = serde_json::from_value(merged)
+            .map_err(|e| ExtractionError::InvalidPatch(e.to_string()))?;
+
+        // Keys the schema does not know are dropped by deserialization
+        let roundtrip = serde_json::to_value(&invoice)
+            .map_err(|e| ExtractionError::InvalidPatch(e.to_string()))?;
+        for field in &report.applied {
+            let set = accepted.pointer(&pointer(field)).is_some_and(|v| !v.is_null());
+            if set && roundtrip.pointer(&pointer(field)).is_none() {
+                return Err(ExtractionError::InvalidPatch(format!("unknown field '{}'", field)));
+            }
+        }
+
+        self.invoice = invoice;
+
+        let now = Utc::now();
+        for field in &report.applied {
+            self.provenance.insert(
+                field.clone(),
+                FieldProvenance {
+                    role: corrections.role,
+                    author: corrections.author.clone(),
+                    updated_at: now,
+                },
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Fields set or confirmed by a person.
+    pub fn human_confirmed(&self) -> impl Iterator<Item = &str> {
+        self.provenance
+            .iter()
+            .filter(|(_, p)| p.is_human_confirmed())
+            .map(|(field, _)| field.as_str())
+    }
+}
+
+/// Whether the dotted `path` is a field inside the object at `parent`.
+fn is_within(path: &str, parent: &str) -> bool {
+    path.strip_prefix(parent).is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Visit leaf values (anything but a non-empty object) with their dotted path.
+pub(super) fn collect_leaves(object: &Map<String, Value>, prefix: &str, visit: &mut impl FnMut(String, &Value)) {
+    for (key, value) in object {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match value {
+            Value::Object(child) if !child.is_empty() => collect_leaves(child, &path, visit),
+            _ => visit(path, value),
+        }
+    }
+}
+
+/// JSON Pointer for a dotted field path.
+pub(super) fn pointer(field: &str) -> String {
+    format!("/{}", field.replace('.', "/"))
+}
+
+pub(super) fn set_path(target: &mut Value, field: &str, value: Value) {
+    let mut node = target;
+    let mut parts = field.split('.').peekable();
+
+    while let Some(part) = parts.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let Value::Object(object) = node else {
+            return;
+        };
+
+        if parts.peek().is_none() {
+            object.insert(part.to_string(), value);
+            return;
+        }
+        node = object.entry(part).or_insert(Value::Null);
+    }
+}
+
+/// Apply a JSON Merge Patch (RFC 7396).
+pub(super) fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Merge patch turning `from` into `to`, or `None` if they are equal.
+fn diff(from: &Value, to: &Value) -> Option<Value> {
+    if from == to {
+        return None;
+    }
+
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            let mut changes = Map::new();
+            for (key, old) in from {
+                match to.get(key) {
+                    Some(new) => {
+                        if let Some(change) = diff(old, new) {
+                            changes.insert(key.clone(), change);
+                        }
+                    }
+                    None => {
+                        changes.insert(key.clone(), Value::Null);
+                    }
+                }
+            }
+            for (key, new) in to {
+                if !from.contains_key(key) {
+                    changes.insert(key.clone(), new.clone());
+                }
+            }
+            Some(Value::Object(changes))
+        }
+        _ => Some(to.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn result() -> ExtractionResult {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.issuer.nip = Some("5260250274".to_string());
+
+        ExtractionResult {
+            invoice,
+            raw_text: String::new(),
+            warnings: Vec::new(),
+            processing_time_ms: 0,
+            provenance: BTreeMap::new(),
+            candidates: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_apply_human_correction() {
+        let mut result = result();
+        let patch = InvoicePatch::new(json!({
+            "header": { "invoice_number": "FV/2/2024" },
+            "issuer": { "nip": null }
+        }))
+        .unwrap()
+        .with_author("anna");
+
+        let report = result.apply_corrections(patch).unwrap();
+
+        assert_eq!(report.applied, vec!["header.invoice_number", "issuer.nip"]);
+        assert!(!report.has_conflicts());
+        assert_eq!(result.invoice.header.invoice_number, "FV/2/2024");
+        assert_eq!(result.invoice.issuer.nip, None);
+        assert_eq!(result.provenance["issuer.nip"].author.as_deref(), Some("anna"));
+        assert_eq!(result.human_confirmed().count(), 2);
+    }
+
+    #[test]
+    fn test_machine_does_not_override_human() {
+        let mut result = result();
+        let human = InvoicePatch::new(json!({ "header": { "invoice_number": "FV/2/2024" } })).unwrap();
+        result.apply_corrections(human).unwrap();
+
+        let machine = InvoicePatch::new(json!({
+            "header": { "invoice_number": "FV/3/2024", "currency": "EUR" }
+        }))
+        .unwrap()
+        .with_role(PatchRole::Machine);
+        let report = result.apply_corrections(machine).unwrap();
+
+        assert_eq!(result.invoice.header.invoice_number, "FV/2/2024");
+        assert_eq!(result.invoice.header.currency, "EUR");
+        assert_eq!(report.applied, vec!["header.currency"]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(!report.conflicts[0].applied);
+        assert_eq!(report.conflicts[0].proposed, json!("FV/3/2024"));
+    }
+
+    #[test]
+    fn test_machine_does_not_clear_object_with_human_fields() {
+        let mut result = result();
+        let human = InvoicePatch::new(json!({
+            "header": { "correction": { "reason": "Zwrot towaru" } }
+        }))
+        .unwrap();
+        result.apply_corrections(human).unwrap();
+
+        let machine = InvoicePatch::new(json!({ "header": { "correction": null } }))
+            .unwrap()
+            .with_role(PatchRole::Machine);
+        let report = result.apply_corrections(machine).unwrap();
+
+        assert!(report.applied.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].field, "header.correction");
+        let correction = result.invoice.header.correction.as_ref().unwrap();
+        assert_eq!(correction.reason.as_deref(), Some("Zwrot towaru"));
+    }
+
+    #[test]
+    fn test_invalid_patch_leaves_result_unchanged() {
+        let mut result = result();
+
+        let unknown = InvoicePatch::new(json!({ "issuer": { "tax_id": "PL1" } })).unwrap();
+        assert!(result.apply_corrections(unknown).is_err());
+
+        let wrong_type = InvoicePatch::new(json!({ "header": { "issue_date": "yesterday" } })).unwrap();
+        assert!(result.apply_corrections(wrong_type).is_err());
+
+        let metadata = InvoicePatch::new(json!({ "metadata": { "confidence": 1.0 } })).unwrap();
+        assert!(result.apply_corrections(metadata).is_err());
+
+        assert_eq!(result.invoice.header.invoice_number, "FV/1/2024");
+        assert!(result.provenance.is_empty());
+    }
+
+    #[test]
+    fn test_patch_between_invoices() {
+        let extracted = result().invoice;
+        let mut corrected = extracted.clone();
+        corrected.issuer.nip = None;
+        corrected.receiver.name = "ACME Sp. z o.o.".to_string();
+        corrected.metadata.confidence = 1.0;
+
+        let patch = InvoicePatch::between(&extracted, &corrected);
+        assert_eq!(patch.fields(), vec!["issuer.nip", "receiver.name"]);
+
+        let mut result = result();
+        result.apply_corrections(patch).unwrap();
+        assert_eq!(result.invoice.receiver.name, "ACME Sp. z o.o.");
+    }
+}
@@ -0,0 +1,372 @@
+//! Extraction of fiscal receipts (*paragony fiskalne*).
+//!
+//! Receipts are read line by line: the block above `PARAGON FISKALNY`
+//! names the seller, item lines end with their gross value and PTU group
+//! letter, and the summary lists the taxable sales and PTU of each group
+//! before `SUMA PTU` and `SUMA PLN`.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use chrono::NaiveTime;
+use rust_decimal::Decimal;
+use tracing::debug;
+
+use super::candidates::TextConfidence;
+use super::rules::amounts::parse_polish_amount;
+use super::rules::dates::DateExtractor;
+use super::rules::nip::NipExtractor;
+use super::rules::patterns::{
+    BUYER_NIP, FISCAL_NUMBER, POSTAL_CODE, PRINT_NUMBER, PTU_GROUP, RECEIPT_HEADER,
+    RECEIPT_ITEM, RECEIPT_ITEM_SHORT, RECEIPT_TOTAL, TAXABLE_SALES, TIME, TOTAL_PTU,
+};
+use super::rules::FieldExtractor;
+use super::Result;
+use crate::error::ExtractionError;
+use crate::models::invoice::{Address, PaymentMethod, VatRate};
+use crate::models::receipt::{Receipt, ReceiptItem, TaxGroup};
+
+/// Parser for fiscal receipts.
+#[derive(Clone)]
+pub struct ReceiptParser {
+    /// Whether to validate NIP checksums.
+    validate_nip: bool,
+}
+
+impl ReceiptParser {
+    /// Create a new receipt parser.
+    pub fn new() -> Self {
+        Self { validate_nip: true }
+    }
+
+    /// Set whether to validate NIP checksums.
+    pub fn with_nip_validation(mut self, validate: bool) -> Self {
+        self.validate_nip = validate;
+        self
+    }
+
+    /// Parse a receipt from its text.
+    pub fn parse(&self, text: &str) -> Result<Receipt> {
+        self.parse_with_text_confidence(text, &TextConfidence::default())
+    }
+
+    /// Parse a receipt from OCR text with per-line confidence; the seller
+    /// and buyer NIPs, date and totals get the lowest score of the lines
+    /// they were read from in `metadata.field_confidence`.
+    pub fn parse_with_text_confidence(
+        &self,
+        text: &str,
+        confidence: &TextConfidence,
+    ) -> Result<Receipt> {
+        let start = Instant::now();
+        let mut receipt = Receipt::new();
+        // Where the value of each field was read, for its OCR confidence
+        let mut spans: Vec<(&str, (usize, usize))> = Vec::new();
+
+        let lines = lines(text);
+        let has_header = lines.iter().any(|(_, line)| RECEIPT_HEADER.is_match(line));
+        let mut in_items = !has_header;
+        let mut seller_lines = Vec::new();
+        let mut pending_name: Option<&str> = None;
+        let mut sales: BTreeMap<char, Decimal> = BTreeMap::new();
+        let mut ptu: BTreeMap<char, (VatRate, Decimal)> = BTreeMap::new();
+        let mut total_vat = None;
+
+        for &(offset, line) in &lines {
+            let span = (offset, offset + line.len());
+
+            if RECEIPT_HEADER.is_match(line) {
+                in_items = true;
+                continue;
+            }
+            if let Some(caps) = BUYER_NIP.captures(line) {
+                receipt.buyer_nip = Some(format!("{}{}{}{}", &caps[1], &caps[2], &caps[3], &caps[4]));
+                spans.push(("buyer_nip", span));
+                continue;
+            }
+            if let Some(caps) = TAXABLE_SALES.captures(line) {
+                in_items = false;
+                if let Some(amount) = parse_polish_amount(&caps[2]) {
+                    sales.insert(group(&caps[1]), amount);
+                }
+                continue;
+            }
+            if let Some(caps) = PTU_GROUP.captures(line) {
+                in_items = false;
+                if let (Some(rate), Some(vat)) = (rate(&caps[2]), parse_polish_amount(&caps[3])) {
+                    ptu.insert(group(&caps[1]), (rate, vat));
+                }
+                continue;
+            }
+            if let Some(caps) = TOTAL_PTU.captures(line) {
+                in_items = false;
+                total_vat = parse_polish_amount(&caps[1]);
+                continue;
+            }
+            if let Some(total) = RECEIPT_TOTAL.captures(line).and_then(|c| parse_polish_amount(&c[1])) {
+                in_items = false;
+                receipt.total = total;
+                spans.push(("total", span));
+                continue;
+            }
+
+            if in_items {
+                match item(line, pending_name.take()) {
+                    Some(item) => receipt.items.push(item),
+                    // Names too long for one line are printed above the
+                    // quantity and price
+                    None => pending_name = Some(line),
+                }
+                continue;
+            }
+
+            if receipt.print_number.is_none() {
+                receipt.print_number = PRINT_NUMBER.captures(line).map(|c| c[1].to_string());
+            }
+            if let Some(caps) = FISCAL_NUMBER.captures(line) {
+                receipt.fiscal_number = Some(caps[1].to_string());
+            } else if !receipt.total.is_zero() && receipt.payment_method.is_none() {
+                receipt.payment_method = Some(PaymentMethod::from_str(line))
+                    .filter(|m| matches!(m, PaymentMethod::Cash | PaymentMethod::Card));
+            } else if receipt.items.is_empty() {
+                seller_lines.push(line);
+            }
+        }
+
+        // Seller: name, address lines, then NIP, above the receipt header
+        let nips = NipExtractor::new().with_validation(self.validate_nip).extract_all(text);
+        if let Some(nip) = nips.into_iter().find(|m| Some(&m.value) != receipt.buyer_nip.as_ref()) {
+            if let Some(position) = nip.position {
+                spans.push(("seller.nip", position));
+            }
+            receipt.seller.nip = Some(nip.value);
+        }
+        let seller_lines: Vec<&str> = seller_lines
+            .into_iter()
+            .take_while(|line| !line.to_uppercase().contains("NIP"))
+            .collect();
+        if let Some((name, address)) = seller_lines.split_first() {
+            receipt.seller.name = name.to_string();
+            receipt.seller.address = parse_address(address);
+        }
+
+        if let Some(date) = DateExtractor::new().extract_all(text).into_iter().next() {
+            if let Some(position) = date.position {
+                spans.push(("issue_date", position));
+            }
+            receipt.issue_date = Some(date.value);
+        }
+        receipt.issue_time = TIME.captures(text).and_then(|caps| {
+            NaiveTime::from_hms_opt(caps[1].parse().ok()?, caps[2].parse().ok()?, 0)
+        });
+
+        let groups: Vec<char> = sales.keys().chain(ptu.keys()).copied().collect();
+        for group in groups {
+            if receipt.tax_groups.iter().any(|g| g.group == group) {
+                continue;
+            }
+            let (rate, vat) = match ptu.get(&group) {
+                Some(&(rate, vat)) => (rate, vat),
+                None => match TaxGroup::default_rate(group) {
+                    Some(rate) => (rate, Decimal::ZERO),
+                    None => continue,
+                },
+            };
+            receipt.tax_groups.push(TaxGroup {
+                group,
+                rate,
+                sales: sales.get(&group).copied().unwrap_or_default(),
+                vat,
+            });
+        }
+        receipt.tax_groups.sort_by_key(|g| g.group);
+        receipt.total_vat = total_vat.unwrap_or_else(|| receipt.tax_groups.iter().map(|g| g.vat).sum());
+
+        if receipt.total.is_zero() && receipt.items.is_empty() {
+            return Err(ExtractionError::NoData);
+        }
+
+        let mut missing = Vec::new();
+        for (field, found) in [
+            ("seller.nip", receipt.seller.nip.is_some()),
+            ("issue_date", receipt.issue_date.is_some()),
+            ("total", !receipt.total.is_zero()),
+            ("items", !receipt.items.is_empty()),
+        ] {
+            if !found {
+                missing.push(field.to_string());
+            }
+        }
+
+        let metadata = &mut receipt.metadata;
+        metadata.confidence = (1.0 - 0.2 * missing.len() as f32).max(0.0);
+        metadata.missing_fields = missing;
+        metadata.processing_time_ms = Some(start.elapsed().as_millis() as u64);
+        if confidence.has_scores() {
+            metadata.field_confidence = spans
+                .into_iter()
+                .map(|(field, span)| (field.to_string(), confidence.span(span)))
+                .collect();
+        }
+        receipt.metadata.warnings = receipt.validate();
+
+        debug!(
+            "Extracted receipt with {} items, total {}",
+            receipt.items.len(),
+            receipt.total
+        );
+        Ok(receipt)
+    }
+}
+
+impl Default for ReceiptParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Non-empty trimmed lines with their byte offsets.
+fn lines(text: &str) -> Vec<(usize, &str)> {
+    let mut offset = 0;
+    let mut lines = Vec::new();
+    for line in text.split('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            let start = offset + line.find(trimmed).unwrap_or(0);
+            lines.push((start, trimmed));
+        }
+        offset += line.len() + 1;
+    }
+    lines
+}
+
+/// An item line, named by `pending` when the name was printed above it.
+fn item(line: &str, pending: Option<&str>) -> Option<ReceiptItem> {
+    if let Some(caps) = RECEIPT_ITEM.captures(line) {
+        let description = caps.get(1).map(|m| m.as_str()).or(pending)?;
+        return Some(ReceiptItem {
+            description: description.to_string(),
+            quantity: parse_polish_amount(&caps[2])?,
+            unit_price: parse_polish_amount(&caps[3])?,
+            total: signed_amount(&caps[4])?,
+            tax_group: group(&caps[5]),
+        });
+    }
+
+    let caps = RECEIPT_ITEM_SHORT.captures(line)?;
+    let total = signed_amount(&caps[2])?;
+    Some(ReceiptItem {
+        description: caps[1].to_string(),
+        quantity: Decimal::ONE,
+        unit_price: total,
+        total,
+        tax_group: group(&caps[3]),
+    })
+}
+
+/// Amount that may be negative (discounts).
+fn signed_amount(s: &str) -> Option<Decimal> {
+    let amount = parse_polish_amount(s)?;
+    Some(if s.starts_with('-') { -amount } else { amount })
+}
+
+fn group(s: &str) -> char {
+    s.chars().next().unwrap_or('A').to_ascii_uppercase()
+}
+
+/// VAT rate from a printed percentage such as `23,00`.
+fn rate(s: &str) -> Option<VatRate> {
+    let percent = parse_polish_amount(s)?.normalize();
+    VatRate::from_str(&percent.to_string())
+}
+
+/// Address from the lines between the seller's name and NIP.
+fn parse_address(lines: &[&str]) -> Address {
+    let mut address = Address::default();
+    for line in lines {
+        match POSTAL_CODE.find(line) {
+            Some(code) if address.postal_code.is_none() => {
+                address.postal_code = Some(code.as_str().to_string());
+                let city = line[code.end()..].trim();
+                address.city = (!city.is_empty()).then(|| city.to_string());
+            }
+            _ if address.street.is_none() => address.street = Some(line.trim_end_matches(',').to_string()),
+            _ => {}
+        }
+    }
+    if !lines.is_empty() {
+        address.raw = Some(lines.join(", "));
+    }
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECEIPT: &str = "SKLEP SPOŻYWCZY JAN KOWALSKI
+ul. Długa 5
+00-950 Warszawa
+NIP 526-104-08-28
+2024-03-01 14:32                nr wydr. 012345
+PARAGON FISKALNY
+Chleb 1 x3,50 3,50C
+Mleko UHT 3,2% 1L
+2 x2,99 5,98C
+Piwo jasne 4 x4,50 18,00A
+Rabat -1,00A
+Sprzedaż opodatkowana A 17,00
+PTU A 23,00 % 3,18
+Sprzedaż opodatkowana C 9,48
+PTU C 5,00 % 0,45
+SUMA PTU 3,63
+SUMA PLN 26,48
+NIP nabywcy: 123-456-32-18
+Karta 26,48
+PL BFA 12345678";
+
+    #[test]
+    fn test_parse_receipt() {
+        let receipt = ReceiptParser::new().parse(RECEIPT).unwrap();
+
+        assert_eq!(receipt.seller.name, "SKLEP SPOŻYWCZY JAN KOWALSKI");
+        assert_eq!(receipt.seller.address.city.as_deref(), Some("Warszawa"));
+        assert_eq!(receipt.seller.nip.as_deref(), Some("5261040828"));
+        assert_eq!(receipt.buyer_nip.as_deref(), Some("1234563218"));
+        assert_eq!(receipt.issue_date.unwrap().to_string(), "2024-03-01");
+        assert_eq!(receipt.issue_time, NaiveTime::from_hms_opt(14, 32, 0));
+        assert_eq!(receipt.print_number.as_deref(), Some("012345"));
+        assert_eq!(receipt.fiscal_number.as_deref(), Some("BFA 12345678"));
+        assert_eq!(receipt.payment_method, Some(PaymentMethod::Card));
+
+        let items: Vec<(&str, Decimal, char)> = receipt
+            .items
+            .iter()
+            .map(|i| (i.description.as_str(), i.total, i.tax_group))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                ("Chleb", Decimal::new(350, 2), 'C'),
+                ("Mleko UHT 3,2% 1L", Decimal::new(598, 2), 'C'),
+                ("Piwo jasne", Decimal::new(1800, 2), 'A'),
+                ("Rabat", Decimal::new(-100, 2), 'A'),
+            ]
+        );
+
+        assert_eq!(receipt.tax_groups.len(), 2);
+        assert_eq!(receipt.tax_groups[0].rate, VatRate::Standard23);
+        assert_eq!(receipt.tax_groups[1].sales, Decimal::new(948, 2));
+        assert_eq!(receipt.total_vat, Decimal::new(363, 2));
+        assert_eq!(receipt.total, Decimal::new(2648, 2));
+        assert!(receipt.metadata.warnings.is_empty(), "{:?}", receipt.metadata.warnings);
+    }
+
+    #[test]
+    fn test_parse_empty_receipt() {
+        assert!(matches!(
+            ReceiptParser::new().parse("PARAGON FISKALNY\nDziękujemy"),
+            Err(ExtractionError::NoData)
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+//! Masking personal and company data before documents are shared.
+//!
+//! Redaction keeps the shape of the data, which is what extraction bugs
+//! usually depend on: letters become `X` and digits `0`, while spacing,
+//! punctuation and line breaks are kept. Everything the parties can be
+//! identified by is masked, using the values found in the extracted invoice
+//! plus, in free text, every number long enough to be an identifier or
+//! account number and every email address. Amounts, dates and invoice
+//! numbers are kept.
+
+use crate::models::invoice::{Invoice, Party};
+
+/// Digits in a number (ignoring single spaces and dashes between them) from
+/// which it is masked as a possible NIP, PESEL, REGON, phone or account
+/// number.
+pub const MIN_IDENTIFIER_DIGITS: usize = 9;
+
+/// Values shorter than this are not searched for in text.
+const MIN_VALUE_LEN: usize = 3;
+
+/// Masks the identifying data of an invoice in the invoice and in texts.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// Values to mask in text, longest first.
+    values: Vec<String>,
+}
+
+impl Redactor {
+    /// Create a redactor that masks only numbers and email addresses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a redactor that also masks the party data of `invoice`
+    /// wherever it occurs in text.
+    pub fn from_invoice(invoice: &Invoice) -> Self {
+        let values = [&invoice.issuer, &invoice.receiver]
+            .into_iter()
+            .flat_map(party_values)
+            .map(str::to_string);
+        Self::new().with_values(values)
+    }
+
+    /// Also mask these values (e.g. the own company's NIPs).
+    pub fn with_values(mut self, values: impl IntoIterator<Item = String>) -> Self {
+        self.values.extend(
+            values
+                .into_iter()
+                .map(|v| v.trim().to_string())
+                .filter(|v| v.chars().count() >= MIN_VALUE_LEN),
+        );
+        self.values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        self.values.dedup();
+        self
+    }
+
+    /// Mask known values, long numbers and email addresses in `text`.
+    pub fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for value in &self.values {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), &mask(value));
+            }
+        }
+
+        let text = mask_numbers(&text);
+        text.split_inclusive(char::is_whitespace)
+            .map(|word| if word.contains('@') { mask(word) } else { word.to_string() })
+            .collect()
+    }
+
+    /// The invoice with both parties masked. Warnings and corrections,
+    /// which may quote extracted values, are masked like text.
+    pub fn invoice(&self, invoice: &Invoice) -> Invoice {
+        let mut invoice = invoice.clone();
+        redact_party(&mut invoice.issuer);
+        redact_party(&mut invoice.receiver);
+
+        let metadata = &mut invoice.metadata;
+        let warnings = metadata.warnings.iter_mut().map(|warning| &mut warning.message);
+        for message in warnings.chain(&mut metadata.corrections) {
+            *message = self.text(message);
+        }
+        invoice
+    }
+}
+
+/// Mask a value, keeping its shape.
+pub fn mask(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_digit() => '0',
+            c if c.is_alphabetic() => 'X',
+            c => c,
+        })
+        .collect()
+}
+
+/// Identifying values of a party.
+fn party_values(party: &Party) -> impl Iterator<Item = &str> {
+    let address = &party.address;
+    [&party.nip, &party.regon, &party.pesel, &party.krs, &party.bank_account]
+        .into_iter()
+        .chain([&party.email, &party.phone, &party.website])
+        .chain([&address.street, &address.postal_code, &address.city, &address.raw])
+        .filter_map(|v| v.as_deref())
+        .chain(std::iter::once(party.name.as_str()))
+        .chain(party.additional_emails.iter().map(String::as_str))
+        .chain(party.additional_phones.iter().map(String::as_str))
+}
+
+fn redact_party(party: &mut Party) {
+    party.name = mask(&party.name);
+
+    let address = &mut party.address;
+    let fields = [&mut party.nip, &mut party.regon, &mut party.pesel, &mut party.krs]
+        .into_iter()
+        .chain([&mut party.bank_account, &mut party.email, &mut party.phone])
+        .chain([&mut party.website, &mut address.street, &mut address.postal_code])
+        .chain([&mut address.city, &mut address.raw]);
+    for value in fields.flatten() {
+        *value = mask(value);
+    }
+
+    for value in party
+        .additional_emails
+        .iter_mut()
+        .chain(&mut party.additional_phones)
+    {
+        *value = mask(value);
+    }
+}
+
+/// Mask the digits of numbers with at least [`MIN_IDENTIFIER_DIGITS`]
+/// digits; single spaces and dashes may separate digit groups
+/// (`526-104-08-28`, `PL61 1090 1014 ...`).
+fn mask_numbers(text: &str) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+
+    let mut start = 0;
+    while start < chars.len() {
+        if !chars[start].is_ascii_digit() {
+            start += 1;
+            continue;
+        }
+
+        let mut end = start;
+        let mut digits = 0;
+        let mut pos = start;
+        while pos < chars.len() {
+            if chars[pos].is_ascii_digit() {
+                digits += 1;
+                pos += 1;
+                end = pos;
+            } else if matches!(chars[pos], ' ' | '-')
+                && chars.get(pos + 1).is_some_and(char::is_ascii_digit)
+            {
+                pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        if digits >= MIN_IDENTIFIER_DIGITS {
+            for c in chars[start..end].iter_mut().filter(|c| c.is_ascii_digit()) {
+                *c = '0';
+            }
+        }
+        start = end;
+    }
+
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::validation::{IssueCode, ValidationIssue};
+
+    #[test]
+    fn test_redact_text() {
+        let mut invoice = Invoice::default();
+        invoice.issuer.name = "ABC Sp. z o.o.".to_string();
+        invoice.issuer.address.city = Some("Kraków".to_string());
+
+        let text = "Sprzedawca: ABC Sp. z o.o., 30-001 Kraków\n\
+            NIP: 526-104-08-28 tel. 601 234 567\n\
+            Konto: PL61 1090 1014 0000 0712 1981 2874\n\
+            biuro@abc.pl\n\
+            FV/001/2024 z 15.01.2024, razem 1 230,00 zł";
+
+        let redactor = Redactor::from_invoice(&invoice).with_values(vec!["30-001".to_string()]);
+        assert_eq!(
+            redactor.text(text),
+            "Sprzedawca: XXX XX. X X.X., 00-000 XXXXXX\n\
+             NIP: 000-000-00-00 tel. 000 000 000\n\
+             Konto: PL00 0000 0000 0000 0000 0000 0000\n\
+             XXXXX@XXX.XX\n\
+             FV/001/2024 z 15.01.2024, razem 1 230,00 zł"
+        );
+    }
+
+    #[test]
+    fn test_redact_invoice() {
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.issuer.name = "ABC Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("5261040828".to_string());
+        invoice.receiver.email = Some("jan.kowalski@example.com".to_string());
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::InvalidNip,
+            "issuer.nip",
+            "Issuer NIP 5261040828 failed validation",
+        ));
+
+        let redacted = Redactor::from_invoice(&invoice).invoice(&invoice);
+        assert_eq!(redacted.header.invoice_number, "FV/1/2024");
+        assert_eq!(redacted.issuer.name, "XXX XX. X X.X.");
+        assert_eq!(redacted.issuer.nip.as_deref(), Some("0000000000"));
+        assert_eq!(redacted.receiver.email.as_deref(), Some("XXX.XXXXXXXX@XXXXXXX.XXX"));
+        assert_eq!(
+            redacted.metadata.warnings[0].message,
+            "Issuer NIP 0000000000 failed validation"
+        );
+    }
+}
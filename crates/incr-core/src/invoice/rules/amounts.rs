@@ -1,8 +1,10 @@
 //! Amount extraction for Polish invoices.
 
+use regex::Regex;
 use rust_decimal::Decimal;
 use std::str::FromStr;
 
+use super::super::candidates::{rank, Candidate, TextConfidence};
 use super::{ExtractionMatch, FieldExtractor};
 use super::patterns::{AMOUNT_PATTERN, TOTAL_GROSS, TOTAL_NET, TOTAL_VAT};
 
@@ -60,35 +62,108 @@ pub struct InvoiceAmounts {
     pub total_gross: Option<ExtractionMatch<Decimal>>,
     /// All detected amounts.
     pub all_amounts: Vec<ExtractionMatch<Decimal>>,
+    /// All candidates for each total, best first.
+    pub candidates: TotalCandidates,
+}
+
+/// Ranked candidates for each invoice total, best first.
+#[derive(Debug, Clone, Default)]
+pub struct TotalCandidates {
+    pub total_net: Vec<Candidate<Decimal>>,
+    pub total_vat: Vec<Candidate<Decimal>>,
+    pub total_gross: Vec<Candidate<Decimal>>,
 }
 
 /// Extract amounts from invoice text.
 pub fn extract_amounts(text: &str) -> InvoiceAmounts {
+    extract_amounts_with_confidence(text, &TextConfidence::default())
+}
+
+/// Extract amounts, weighing candidates by the OCR confidence of their line.
+///
+/// Every labeled total is a candidate, favouring the end of the document
+/// where summaries are printed and totals that add up (net + VAT = gross).
+/// The largest amount is an unlabeled gross candidate.
+pub fn extract_amounts_with_confidence(text: &str, confidence: &TextConfidence) -> InvoiceAmounts {
     let mut result = InvoiceAmounts::default();
     let extractor = AmountExtractor::new();
 
     // Extract all amounts first
     result.all_amounts = extractor.extract_all(text);
 
-    // Extract labeled amounts
-    if let Some(caps) = TOTAL_GROSS.captures(text) {
-        if let Some(amount) = parse_polish_amount(&caps[1]) {
-            result.total_gross = Some(ExtractionMatch::new(amount, 0.95, &caps[0]));
-        }
-    }
+    let labeled = |label: &Regex| -> Vec<Candidate<Decimal>> {
+        label
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let (full_match, value) = (caps.get(0).unwrap(), caps.get(1).unwrap());
+                let amount = parse_polish_amount(value.as_str())?;
+                Some(
+                    Candidate::new(amount, (value.start(), value.end()), full_match.as_str())
+                        .with_label(1.0)
+                        .with_ocr(confidence.at(value.start()))
+                        .with_position(value.start() as f32 / text.len().max(1) as f32),
+                )
+            })
+            .collect()
+    };
 
-    if let Some(caps) = TOTAL_NET.captures(text) {
-        if let Some(amount) = parse_polish_amount(&caps[1]) {
-            result.total_net = Some(ExtractionMatch::new(amount, 0.95, &caps[0]));
-        }
-    }
+    let mut candidates = TotalCandidates {
+        total_net: labeled(&TOTAL_NET),
+        total_vat: labeled(&TOTAL_VAT),
+        total_gross: labeled(&TOTAL_GROSS),
+    };
 
-    if let Some(caps) = TOTAL_VAT.captures(text) {
-        if let Some(amount) = parse_polish_amount(&caps[1]) {
-            result.total_vat = Some(ExtractionMatch::new(amount, 0.95, &caps[0]));
-        }
+    // The largest amount is usually the gross total
+    if let Some(max) = result.all_amounts.iter().max_by(|a, b| a.value.cmp(&b.value)) {
+        let span = max.position.unwrap_or_default();
+        candidates.total_gross.push(
+            Candidate::new(max.value, span, max.source.clone())
+                .with_ocr(confidence.at(span.0))
+                .with_position(span.0 as f32 / text.len().max(1) as f32),
+        );
     }
 
+    // Prefer totals that add up with the other totals' candidates
+    let nets: Vec<Decimal> = candidates.total_net.iter().map(|c| c.value).collect();
+    let vats: Vec<Decimal> = candidates.total_vat.iter().map(|c| c.value).collect();
+    let grosses: Vec<Decimal> = candidates.total_gross.iter().map(|c| c.value).collect();
+    let adds_up = |net: Decimal, vat: Decimal, gross: Decimal| (net + vat - gross).abs() <= Decimal::new(1, 2);
+
+    candidates.total_net = candidates
+        .total_net
+        .into_iter()
+        .map(|c| {
+            let validity = consistency(&vats, &grosses, |vat, gross| adds_up(c.value, vat, gross));
+            c.with_validity(validity)
+        })
+        .collect();
+    candidates.total_vat = candidates
+        .total_vat
+        .into_iter()
+        .map(|c| {
+            let validity = consistency(&nets, &grosses, |net, gross| adds_up(net, c.value, gross));
+            c.with_validity(validity)
+        })
+        .collect();
+    candidates.total_gross = candidates
+        .total_gross
+        .into_iter()
+        .map(|c| {
+            let validity = consistency(&nets, &vats, |net, vat| adds_up(net, vat, c.value));
+            c.with_validity(validity)
+        })
+        .collect();
+
+    candidates.total_net = rank(candidates.total_net);
+    candidates.total_vat = rank(candidates.total_vat);
+    candidates.total_gross = rank(candidates.total_gross);
+
+    let best = |c: &[Candidate<Decimal>]| c.first().cloned().map(Candidate::into_match);
+    result.total_net = best(&candidates.total_net);
+    result.total_vat = best(&candidates.total_vat);
+    result.total_gross = best(&candidates.total_gross);
+    result.candidates = candidates;
+
     // If we have gross and net but not VAT, calculate it
     if result.total_vat.is_none() {
         if let (Some(gross), Some(net)) = (&result.total_gross, &result.total_net) {
@@ -105,17 +180,22 @@ pub fn extract_amounts(text: &str) -> InvoiceAmounts {
         }
     }
 
-    // If we only have gross, try to identify it from the largest amount
-    if result.total_gross.is_none() && !result.all_amounts.is_empty() {
-        let max_amount = result
-            .all_amounts
-            .iter()
-            .max_by(|a, b| a.value.cmp(&b.value))
-            .cloned();
-        result.total_gross = max_amount;
+    result
+}
+
+/// Validity of a total given the other two totals' candidates: 1.0 if any
+/// pair adds up, 0.5 if none does, 0.8 if there is nothing to check against.
+fn consistency(
+    first: &[Decimal],
+    second: &[Decimal],
+    adds_up: impl Fn(Decimal, Decimal) -> bool,
+) -> f32 {
+    if first.is_empty() || second.is_empty() {
+        return 0.8;
     }
 
-    result
+    let consistent = first.iter().any(|&a| second.iter().any(|&b| adds_up(a, b)));
+    if consistent { 1.0 } else { 0.5 }
 }
 
 /// Parse a Polish-formatted amount (e.g., "1 234,56" or "1234.56").
@@ -229,4 +309,13 @@ mod tests {
         let results = extractor.extract_all(text);
         assert_eq!(results.len(), 2);
     }
+
+    #[test]
+    fn test_consistent_total_preferred() {
+        let text = "Razem netto: 1 000,00\nRazem VAT: 230,00\nSuma: 1 320,00\nDo zapłaty: 1 230,00";
+        let amounts = extract_amounts(text);
+
+        assert_eq!(amounts.total_gross.unwrap().value, Decimal::from_str("1230.00").unwrap());
+        assert_eq!(amounts.candidates.total_gross.len(), 2);
+    }
 }
@@ -0,0 +1,61 @@
+//! VAT annotations: split payment and GTU codes.
+//!
+//! Invoices for goods and services listed in annex 15 of the VAT act over
+//! 15 000 PLN must carry the annotation `mechanizm podzielonej płatności`
+//! (split payment, MPP). Line items may be marked with the GTU group code
+//! (`GTU_01` to `GTU_13`) the seller reports in JPK_V7.
+
+use super::patterns::{GTU_CODE, SPLIT_PAYMENT};
+
+/// Whether the text carries the split payment annotation.
+pub fn is_split_payment(text: &str) -> bool {
+    SPLIT_PAYMENT.is_match(text)
+}
+
+/// GTU codes in the text as `GTU_01` to `GTU_13`, without duplicates, in
+/// text order.
+pub fn extract_gtu_codes(text: &str) -> Vec<String> {
+    let mut codes = Vec::new();
+    for caps in GTU_CODE.captures_iter(text) {
+        if let Some(code) = parse_gtu_code(&caps[1]).filter(|code| !codes.contains(code)) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// A GTU code written as `GTU_12`, `GTU 12` or a bare group number `12`,
+/// normalized to `GTU_12`; `None` outside groups 1 to 13.
+pub fn parse_gtu_code(value: &str) -> Option<String> {
+    let number = value
+        .trim()
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic() || matches!(c, '_' | '-' | ' '));
+    match number.parse::<u8>() {
+        Ok(group @ 1..=13) => Some(format!("GTU_{:02}", group)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_payment() {
+        assert!(is_split_payment("Uwagi: mechanizm podzielonej płatności"));
+        assert!(is_split_payment("MECHANIZM PODZIELONEJ PLATNOSCI"));
+        assert!(is_split_payment("Metoda płatności: przelew (MPP)"));
+        assert!(!is_split_payment("Płatność: przelew 14 dni"));
+    }
+
+    #[test]
+    fn test_gtu_codes() {
+        assert_eq!(
+            extract_gtu_codes("Laptop GTU_07 GTU-12 gtu 7 GTU_14"),
+            ["GTU_07", "GTU_12"]
+        );
+        assert_eq!(parse_gtu_code("6"), Some("GTU_06".to_string()));
+        assert_eq!(parse_gtu_code("GTU 13"), Some("GTU_13".to_string()));
+        assert_eq!(parse_gtu_code("0"), None);
+    }
+}
@@ -0,0 +1,189 @@
+//! Contact details (email, phone, website) extraction and normalization.
+
+use super::patterns::{EMAIL, PHONE, PHONE_LABELED, WEBSITE};
+
+/// Contact details found in a text fragment, normalized and without
+/// duplicates, in document order (labeled phone numbers first).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Contacts {
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub websites: Vec<String>,
+}
+
+/// Extract all contact details from text.
+///
+/// Unlabeled phone numbers are only taken when written with a country
+/// code or separators, since bare 9-digit numbers are usually REGONs.
+pub fn extract_contacts(text: &str) -> Contacts {
+    let mut contacts = Contacts::default();
+
+    for m in EMAIL.find_iter(text) {
+        push_unique(&mut contacts.emails, normalize_email(m.as_str()));
+    }
+
+    for caps in PHONE_LABELED.captures_iter(text) {
+        push_unique(&mut contacts.phones, normalize_phone(&caps[1]));
+    }
+    for m in PHONE.find_iter(text) {
+        let raw = m.as_str();
+        if raw.contains(|c: char| !c.is_ascii_digit()) || raw.starts_with("00") {
+            push_unique(&mut contacts.phones, normalize_phone(raw));
+        }
+    }
+
+    for m in WEBSITE.find_iter(text) {
+        push_unique(&mut contacts.websites, normalize_website(m.as_str()));
+    }
+
+    contacts
+}
+
+fn push_unique(values: &mut Vec<String>, value: Option<String>) {
+    if let Some(value) = value.filter(|v| !values.contains(v)) {
+        values.push(value);
+    }
+}
+
+/// Normalize a phone number to E.164, assuming +48 when no country code
+/// is given.
+///
+/// Labels such as `tel.:` and separators are dropped. Returns `None` if
+/// the digits don't form a Polish (9 digits) or international number.
+pub fn normalize_phone(raw: &str) -> Option<String> {
+    // Drop any label before the number
+    let start = raw.find(|c: char| c == '+' || c.is_ascii_digit())?;
+    let number = &raw[start..];
+
+    let international = number.starts_with('+') || number.starts_with("00");
+    let number = number.strip_prefix("00").unwrap_or(number);
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+
+    let national = if international {
+        match digits.strip_prefix("48") {
+            Some(national) => national.to_string(),
+            // Other countries: keep as given if the length fits E.164
+            None if (8..=15).contains(&digits.len()) => return Some(format!("+{}", digits)),
+            None => return None,
+        }
+    } else if digits.len() == 11 && digits.starts_with("48") {
+        digits[2..].to_string()
+    } else if digits.len() == 10 && digits.starts_with('0') {
+        // Old trunk prefix (e.g. 022 123 45 67)
+        digits[1..].to_string()
+    } else {
+        digits
+    };
+
+    (national.len() == 9).then(|| format!("+48{}", national))
+}
+
+/// Normalize an email address: strip a `mailto:` prefix and trailing
+/// punctuation, and lowercase it. Returns `None` if it isn't a valid
+/// address.
+pub fn normalize_email(raw: &str) -> Option<String> {
+    let email = raw.trim();
+    let email = email
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("mailto:"))
+        .map_or(email, |_| &email[7..]);
+    let email = email.trim_end_matches(['.', ',', ';']).to_lowercase();
+
+    let (local, domain) = email.split_once('@')?;
+    let valid = !local.is_empty()
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !email.contains(char::is_whitespace)
+        && !domain.contains('@')
+        && is_valid_host(domain);
+
+    valid.then_some(email)
+}
+
+/// Validate and normalize a website URL.
+///
+/// The scheme and host are lowercased and trailing punctuation removed;
+/// the path is kept as written. Returns `None` if the host isn't a valid
+/// domain name.
+pub fn normalize_website(raw: &str) -> Option<String> {
+    let url = raw.trim().trim_end_matches(['.', ',', ';', ')']);
+
+    let (scheme, rest) = match url.find("://") {
+        Some(i) => (Some(url[..i].to_ascii_lowercase()), &url[i + 3..]),
+        None => (None, url),
+    };
+    if scheme.as_deref().is_some_and(|s| s != "http" && s != "https") {
+        return None;
+    }
+
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let host = host.to_ascii_lowercase();
+    // `www.` alone is not a domain (www.example, not www.example.pl)
+    let bare = host.strip_prefix("www.").unwrap_or(&host);
+    if !is_valid_host(bare) {
+        return None;
+    }
+
+    Some(match scheme {
+        Some(scheme) => format!("{}://{}{}", scheme, host, path),
+        None => format!("{}{}", host, path),
+    })
+}
+
+/// Check a domain name: dot-separated labels of letters, digits and
+/// inner hyphens, ending in an alphabetic TLD of at least two letters.
+fn is_valid_host(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 {
+        return false;
+    }
+
+    let tld = labels[labels.len() - 1];
+    let labels_valid = labels.iter().all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+
+    labels_valid && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(normalize_phone("tel.: 22 123 45 67").as_deref(), Some("+48221234567"));
+        assert_eq!(normalize_phone("+48 601-234-567").as_deref(), Some("+48601234567"));
+        assert_eq!(normalize_phone("0048 601 234 567").as_deref(), Some("+48601234567"));
+        assert_eq!(normalize_phone("(022) 123 45 67").as_deref(), Some("+48221234567"));
+        assert_eq!(normalize_phone("+49 30 1234567").as_deref(), Some("+49301234567"));
+        assert_eq!(normalize_phone("12 34"), None);
+    }
+
+    #[test]
+    fn test_normalize_email_and_website() {
+        assert_eq!(normalize_email("mailto:Biuro@ABC.pl.").as_deref(), Some("biuro@abc.pl"));
+        assert_eq!(normalize_email("jan@localhost"), None);
+
+        assert_eq!(normalize_website("WWW.ABC.pl/Kontakt,").as_deref(), Some("www.abc.pl/Kontakt"));
+        assert_eq!(normalize_website("HTTPS://Abc.PL").as_deref(), Some("https://abc.pl"));
+        assert_eq!(normalize_website("ftp://abc.pl"), None);
+        assert_eq!(normalize_website("www.abc"), None);
+    }
+
+    #[test]
+    fn test_extract_multiple_contacts() {
+        let text = "Tel./fax: 22 123 45 67, kom. 601 234 567\n\
+                    REGON: 123456785\n\
+                    biuro@abc.pl, Faktury@ABC.pl\n\
+                    www.abc.pl";
+        let contacts = extract_contacts(text);
+
+        assert_eq!(contacts.phones, vec!["+48221234567", "+48601234567"]);
+        assert_eq!(contacts.emails, vec!["biuro@abc.pl", "faktury@abc.pl"]);
+        assert_eq!(contacts.websites, vec!["www.abc.pl"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Correction invoice (*faktura korygująca*) extraction.
+//!
+//! A correction invoice names the invoice it corrects (`do faktury nr
+//! FV/12/2024 z dnia 15.01.2024`), usually states why (`Przyczyna korekty:
+//! zwrot towaru`) and lists the totals before and after the correction and
+//! their difference on rows such as `Przed korektą`, `Po korekcie` and
+//! `Różnica`.
+
+use regex::Regex;
+use rust_decimal::Decimal;
+
+use super::dates::DateExtractor;
+use super::patterns::{
+    AMOUNT_PATTERN, CORRECTED_INVOICE, CORRECTION_AFTER, CORRECTION_BEFORE, CORRECTION_DIFFERENCE,
+    CORRECTION_HEADER, CORRECTION_REASON,
+};
+use super::FieldExtractor;
+use crate::models::invoice::{CorrectionAmounts, CorrectionDetails};
+
+/// What a correction invoice says about the invoice it corrects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Correction {
+    /// Number of the corrected invoice.
+    pub correction_of: Option<String>,
+    /// Reason, corrected invoice date and change of the totals.
+    pub details: CorrectionDetails,
+}
+
+/// Whether the text is a correction invoice.
+pub fn is_correction(text: &str) -> bool {
+    CORRECTION_HEADER.is_match(text)
+}
+
+/// Extract the corrected invoice, reason and totals of a correction
+/// invoice, or `None` if the text is not one.
+pub fn extract_correction(text: &str) -> Option<Correction> {
+    if !is_correction(text) {
+        return None;
+    }
+
+    let mut correction = Correction::default();
+
+    // The first reference with a digit: "korekta faktury" may also be a title
+    let reference = CORRECTED_INVOICE
+        .captures_iter(text)
+        .find(|caps| caps[1].chars().any(|c| c.is_ascii_digit()));
+    if let Some(caps) = reference {
+        let number = caps.get(1).unwrap();
+        correction.correction_of = Some(number.as_str().trim_end_matches(['.', ',']).to_string());
+
+        // "z dnia 15.01.2024" on the rest of the line
+        let rest = &text[number.end()..];
+        let rest = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        correction.details.corrected_issue_date =
+            DateExtractor::new().extract(rest).map(|date| date.value);
+    }
+
+    correction.details.reason = CORRECTION_REASON
+        .captures(text)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|reason| !reason.is_empty());
+
+    let details = &mut correction.details;
+    details.before = row(text, &CORRECTION_BEFORE);
+    details.after = row(text, &CORRECTION_AFTER);
+    details.difference = row(text, &CORRECTION_DIFFERENCE).or_else(|| {
+        let (before, after) = (details.before.as_ref()?, details.after.as_ref()?);
+        let minus = |a: Option<Decimal>, b: Option<Decimal>| Some(a? - b?);
+        Some(CorrectionAmounts {
+            net: minus(after.net, before.net),
+            vat: minus(after.vat, before.vat),
+            gross: after.gross - before.gross,
+        })
+    });
+
+    Some(correction)
+}
+
+/// Totals on the first row matching `pattern`: net, VAT and gross when
+/// there are three amounts or more (the last three), net and gross when
+/// there are two, gross alone otherwise.
+fn row(text: &str, pattern: &Regex) -> Option<CorrectionAmounts> {
+    let amounts = pattern.captures_iter(text).map(|caps| signed_amounts(&caps[1])).find(|a| !a.is_empty())?;
+
+    Some(match *amounts.as_slice() {
+        [gross] => CorrectionAmounts { net: None, vat: None, gross },
+        [net, gross] => CorrectionAmounts {
+            net: Some(net),
+            vat: Some(gross - net),
+            gross,
+        },
+        [.., net, vat, gross] => CorrectionAmounts {
+            net: Some(net),
+            vat: Some(vat),
+            gross,
+        },
+        [] => return None,
+    })
+}
+
+/// Amounts in a row, negative when preceded by a minus sign; percentages
+/// (VAT rates) are skipped.
+fn signed_amounts(row: &str) -> Vec<Decimal> {
+    AMOUNT_PATTERN
+        .captures_iter(row)
+        .filter(|caps| !row[caps.get(0).unwrap().end()..].trim_start().starts_with('%'))
+        .filter_map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let int_part = caps[1].replace([' ', '\u{00a0}'], "");
+            let amount: Decimal = format!("{}.{}", int_part, &caps[2]).parse().ok()?;
+            let negative = row[..whole.start()].trim_end().ends_with(['-', '−']);
+            Some(if negative { -amount } else { amount })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_extract_correction() {
+        let text = "FAKTURA KORYGUJĄCA nr KOR/3/2024
+do faktury nr FV/12/2024 z dnia 15.01.2024
+Przyczyna korekty: zwrot części towaru
+Przed korektą: netto 1 000,00 VAT 230,00 brutto 1 230,00
+Po korekcie: netto 800,00 VAT 184,00 brutto 984,00
+Różnica: netto -200,00 VAT -46,00 brutto -246,00";
+
+        let correction = extract_correction(text).unwrap();
+        assert_eq!(correction.correction_of.as_deref(), Some("FV/12/2024"));
+
+        let details = correction.details;
+        assert_eq!(details.corrected_issue_date, NaiveDate::from_ymd_opt(2024, 1, 15));
+        assert_eq!(details.reason.as_deref(), Some("zwrot części towaru"));
+        assert_eq!(details.before.unwrap().gross, Decimal::new(123000, 2));
+        assert_eq!(details.after.unwrap().vat, Some(Decimal::new(18400, 2)));
+        assert_eq!(
+            details.difference,
+            Some(CorrectionAmounts {
+                net: Some(Decimal::new(-20000, 2)),
+                vat: Some(Decimal::new(-4600, 2)),
+                gross: Decimal::new(-24600, 2),
+            })
+        );
+    }
+
+    #[test]
+    fn test_correction_difference_computed() {
+        let text = "Faktura VAT korygująca KOR/1/2024\nDotyczy faktury: FV/7/2024\n\
+                    Było: 100,00 23% 23,00 123,00\nPowinno być: 50,00 23% 11,50 61,50";
+
+        let details = extract_correction(text).unwrap().details;
+        assert_eq!(details.reason, None);
+        assert_eq!(details.before.unwrap().net, Some(Decimal::new(10000, 2)));
+        assert_eq!(details.difference.unwrap().gross, Decimal::new(-6150, 2));
+    }
+
+    #[test]
+    fn test_not_a_correction() {
+        assert!(!is_correction("Faktura VAT nr FV/1/2024\nDo zapłaty: 123,00"));
+        assert_eq!(extract_correction("Faktura VAT nr FV/1/2024"), None);
+    }
+}
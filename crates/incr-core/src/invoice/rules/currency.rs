@@ -0,0 +1,227 @@
+//! Currency and exchange rate extraction.
+//!
+//! The currency of an invoice is read from a `Waluta` label or, without
+//! one, from the currency most amounts are written in. Foreign-currency
+//! invoices state the rate used to convert VAT to PLN on a line such as
+//! `Kurs NBP z dnia 12.01.2024 (tabela 009/A/NBP/2024): 4,3123`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::dates::DateExtractor;
+use super::patterns::{CURRENCY_AMOUNT, CURRENCY_LABEL, EXCHANGE_RATE, NBP_TABLE};
+use super::{ExtractionMatch, FieldExtractor};
+
+/// Currency field extractor producing ISO 4217 codes.
+pub struct CurrencyExtractor;
+
+impl CurrencyExtractor {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for CurrencyExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldExtractor for CurrencyExtractor {
+    type Output = ExtractionMatch<String>;
+
+    fn extract(&self, text: &str) -> Option<Self::Output> {
+        self.extract_all(text).into_iter().next()
+    }
+
+    /// Labeled currencies first, then the currencies of amounts, most
+    /// frequent first.
+    fn extract_all(&self, text: &str) -> Vec<Self::Output> {
+        let mut results: Vec<Self::Output> = Vec::new();
+
+        for caps in CURRENCY_LABEL.captures_iter(text) {
+            let words = [caps.get(1), caps.get(2)];
+            let Some(code) = words.into_iter().flatten().find_map(|w| currency_code(w.as_str()))
+            else {
+                continue;
+            };
+            if results.iter().any(|r| r.value == code) {
+                continue;
+            }
+            let full_match = caps.get(0).unwrap();
+            results.push(
+                ExtractionMatch::new(code, 0.95, full_match.as_str())
+                    .with_position(full_match.start(), full_match.end()),
+            );
+        }
+
+        // Count per code, keeping the first occurrence
+        let mut counts: HashMap<String, (usize, ExtractionMatch<String>)> = HashMap::new();
+        for caps in CURRENCY_AMOUNT.captures_iter(text) {
+            let symbol = caps.get(1).or_else(|| caps.get(2)).unwrap();
+            let Some(code) = currency_code(symbol.as_str()) else {
+                continue;
+            };
+            let full_match = caps.get(0).unwrap();
+            counts
+                .entry(code.clone())
+                .or_insert_with(|| {
+                    let found = ExtractionMatch::new(code, 0.8, full_match.as_str())
+                        .with_position(full_match.start(), full_match.end());
+                    (0, found)
+                })
+                .0 += 1;
+        }
+
+        let mut counted: Vec<_> = counts.into_values().collect();
+        counted.sort_by_key(|(count, found)| (std::cmp::Reverse(*count), found.position));
+        for (_, found) in counted {
+            if !results.iter().any(|r| r.value == found.value) {
+                results.push(found);
+            }
+        }
+
+        results
+    }
+}
+
+/// Exchange rate stated on an invoice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    /// PLN per unit of the invoice currency.
+    pub rate: Decimal,
+    /// Date of the rate.
+    pub date: Option<NaiveDate>,
+    /// NBP table number (`009/A/NBP/2024`).
+    pub table: Option<String>,
+}
+
+/// Extract the invoice currency from text.
+pub fn extract_currency(text: &str) -> Option<String> {
+    CurrencyExtractor::new().extract(text).map(|m| m.value)
+}
+
+/// Extract the exchange rate from the first `kurs` line that states one.
+pub fn extract_exchange_rate(text: &str) -> Option<ExchangeRate> {
+    text.lines()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            lower.contains("kurs") || lower.contains("exchange rate")
+        })
+        .find_map(exchange_rate_in_line)
+}
+
+fn exchange_rate_in_line(line: &str) -> Option<ExchangeRate> {
+    let dates = DateExtractor::new().extract_all(line);
+
+    // "12.01.2024" would otherwise read as the rate "01.2024"
+    let mut blanked = line.to_string();
+    for (start, end) in dates.iter().filter_map(|d| d.position) {
+        blanked.replace_range(start..end, &" ".repeat(end - start));
+    }
+    let table = NBP_TABLE.captures(&blanked).map(|caps| caps[1].to_uppercase());
+    let blanked = NBP_TABLE.replace_all(&blanked, "");
+
+    let rate = EXCHANGE_RATE.captures(&blanked)?;
+    let rate = Decimal::from_str(&rate[1].replace(',', ".")).ok()?;
+    if rate.is_zero() {
+        return None;
+    }
+
+    Some(ExchangeRate {
+        rate,
+        date: dates.first().map(|d| d.value),
+        table,
+    })
+}
+
+/// Active ISO 4217 currency codes.
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XCG", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWG",
+];
+
+/// ISO 4217 code of a currency symbol, code or name.
+fn currency_code(token: &str) -> Option<String> {
+    let token = token.trim().to_lowercase();
+    let code = match token.as_str() {
+        "pln" | "zł" | "zl" => "PLN",
+        t if t.starts_with("złot") || t.starts_with("zlot") => "PLN",
+        "eur" | "euro" | "€" => "EUR",
+        "usd" | "$" | "dolar" | "dolary" => "USD",
+        "gbp" | "£" | "funt" | "funty" => "GBP",
+        "chf" | "frank" | "franki" => "CHF",
+        t => {
+            let code = t.to_uppercase();
+            return ISO_4217.contains(&code.as_str()).then_some(code);
+        }
+    };
+    Some(code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_currency() {
+        assert_eq!(extract_currency("Waluta: EUR\nRazem: 100,00 PLN"), Some("EUR".to_string()));
+        assert_eq!(extract_currency("waluta faktury: złoty"), Some("PLN".to_string()));
+        assert_eq!(extract_currency("Waluta płatności: EUR"), Some("EUR".to_string()));
+        assert_eq!(
+            extract_currency("Netto 100,00 USD\nVAT 23,00 PLN\nBrutto 123,00 USD"),
+            Some("USD".to_string())
+        );
+        assert_eq!(extract_currency("Do zapłaty: £ 120.00"), Some("GBP".to_string()));
+        assert_eq!(extract_currency("Razem 1 000,00 CHF"), Some("CHF".to_string()));
+        assert_eq!(extract_currency("Razem 1 000,00"), None);
+    }
+
+    #[test]
+    fn test_currency_label_without_colon() {
+        assert_eq!(extract_currency("Waluta PLN\nRazem: 100,00 EUR"), Some("PLN".to_string()));
+        assert_eq!(extract_currency("Waluta rozliczenia SEK"), Some("SEK".to_string()));
+        // The value on the line below the label
+        assert_eq!(
+            extract_currency("Waluta faktury:\nEUR\nRazem: 100,00 PLN"),
+            Some("EUR".to_string())
+        );
+        assert_eq!(extract_currency("Waluta\nUSD"), Some("USD".to_string()));
+        // Words after the label that are not currencies
+        assert_eq!(
+            extract_currency("Waluta nie dotyczy\nRazem: 100,00 USD"),
+            Some("USD".to_string())
+        );
+        assert_eq!(extract_currency("Waluta zlecenia: EUR"), Some("EUR".to_string()));
+        assert_eq!(extract_currency("Waluta: ABC"), None);
+    }
+
+    #[test]
+    fn test_extract_exchange_rate() {
+        let text = "Brutto: 123,00 EUR\nKurs NBP z dnia 12.01.2024 (tabela 009/A/NBP/2024): 4,3123 PLN";
+        let rate = extract_exchange_rate(text).unwrap();
+        assert_eq!(rate.rate, Decimal::new(43123, 4));
+        assert_eq!(rate.date, NaiveDate::from_ymd_opt(2024, 1, 12));
+        assert_eq!(rate.table.as_deref(), Some("009/A/NBP/2024"));
+
+        let rate = extract_exchange_rate("Kurs wymiany: 1 USD = 3.9876 PLN").unwrap();
+        assert_eq!(rate.rate, Decimal::new(39876, 4));
+        assert_eq!(rate.date, None);
+
+        assert_eq!(extract_exchange_rate("Kurs z dnia 12.01.2024"), None);
+        assert_eq!(extract_exchange_rate("Razem 4,3123"), None);
+    }
+}
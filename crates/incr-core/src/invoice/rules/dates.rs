@@ -1,7 +1,10 @@
 //! Date extraction for Polish invoices.
 
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
 
+use super::super::candidates::{demote, rank, Candidate, TextConfidence};
+use crate::models::validation::{IssueCode, ValidationIssue};
 use super::{ExtractionMatch, FieldExtractor};
 use super::patterns::{DATE_DMY, DATE_YMD, DATE_POLISH_LONG, ISSUE_DATE, SALE_DATE, DUE_DATE};
 
@@ -98,46 +101,189 @@ pub struct InvoiceDates {
     pub sale_date: Option<ExtractionMatch<NaiveDate>>,
     /// Due date (termin płatności).
     pub due_date: Option<ExtractionMatch<NaiveDate>>,
+    /// All candidates for each date, best first.
+    pub candidates: DateCandidates,
 }
 
-/// Extract all labeled dates from invoice text.
-pub fn extract_dates(text: &str) -> InvoiceDates {
-    let mut result = InvoiceDates::default();
-    let date_extractor = DateExtractor::new();
-
-    // Extract issue date
-    if let Some(caps) = ISSUE_DATE.captures(text) {
-        let date_text = &caps[1];
-        if let Some(date) = date_extractor.extract(date_text) {
-            result.issue_date = Some(ExtractionMatch::new(date.value, 0.95, date_text));
+/// Days a sale date may be from the issue date.
+pub const MAX_SALE_DATE_OFFSET_DAYS: i64 = 60;
+
+/// Days an issue or sale date may be after the reference date.
+const MAX_DAYS_AFTER_REFERENCE: i64 = 366;
+
+/// Days an issue or sale date may be before the reference date (10 years).
+const MAX_DAYS_BEFORE_REFERENCE: i64 = 3653;
+
+/// Score multiplier for candidates breaking a date constraint.
+const IMPLAUSIBLE_PENALTY: f32 = 0.75;
+
+impl InvoiceDates {
+    /// Demote candidates breaking the date constraints, select the best
+    /// remaining ones and return a warning for each selected date that
+    /// still breaks a constraint.
+    ///
+    /// The issue date must not be after the due date and the sale date must
+    /// be within 60 days of the issue date. With a `reference` date (e.g. the
+    /// file's modification date), issue and sale dates more than a year after
+    /// or ten years before it are implausible too.
+    pub fn apply_constraints(&mut self, reference: Option<NaiveDate>) -> Vec<ValidationIssue> {
+        let in_range = |date: &NaiveDate| {
+            reference.is_none_or(|reference| {
+                let days = (*date - reference).num_days();
+                (-MAX_DAYS_BEFORE_REFERENCE..=MAX_DAYS_AFTER_REFERENCE).contains(&days)
+            })
+        };
+        let candidates = &mut self.candidates;
+
+        let latest_due = candidates.due_date.iter().map(|c| c.value).max();
+        demote(&mut candidates.issue_date, IMPLAUSIBLE_PENALTY, |date| {
+            !in_range(date) || latest_due.is_some_and(|due| *date > due)
+        });
+
+        let issue = candidates.issue_date.first().map(|c| c.value);
+        let sale_offset = |date: &NaiveDate| issue.map_or(0, |issue| (*date - issue).num_days().abs());
+        demote(&mut candidates.due_date, IMPLAUSIBLE_PENALTY, |date| {
+            issue.is_some_and(|issue| *date < issue)
+        });
+        demote(&mut candidates.sale_date, IMPLAUSIBLE_PENALTY, |date| {
+            !in_range(date) || sale_offset(date) > MAX_SALE_DATE_OFFSET_DAYS
+        });
+
+        let best = |c: &[Candidate<NaiveDate>]| c.first().cloned().map(Candidate::into_match);
+        self.issue_date = best(&candidates.issue_date);
+        self.sale_date = best(&candidates.sale_date);
+        self.due_date = best(&candidates.due_date);
+
+        let mut warnings = Vec::new();
+        let issue = self.issue_date.as_ref().map(|m| m.value);
+        let sale = self.sale_date.as_ref().map(|m| m.value);
+        let due = self.due_date.as_ref().map(|m| m.value);
+
+        for (name, field, date) in [
+            ("Issue", "header.issue_date", issue),
+            ("Sale", "header.sale_date", sale),
+        ] {
+            if let (Some(date), Some(reference)) = (date.filter(|d| !in_range(d)), reference) {
+                warnings.push(ValidationIssue::warning(
+                    IssueCode::ImplausibleDate,
+                    field,
+                    format!("{} date {} is implausible for a document dated {}", name, date, reference),
+                ));
+            }
+        }
+        if let Some((issue, due)) = issue.zip(due).filter(|(issue, due)| due < issue) {
+            warnings.push(ValidationIssue::warning(
+                IssueCode::DueDateBeforeIssueDate,
+                "header.due_date",
+                format!("Due date {} is before issue date {}", due, issue),
+            ));
         }
+        if let Some((issue, sale)) = issue
+            .zip(sale)
+            .filter(|(_, sale)| sale_offset(sale) > MAX_SALE_DATE_OFFSET_DAYS)
+        {
+            warnings.push(ValidationIssue::warning(
+                IssueCode::SaleDateFarFromIssueDate,
+                "header.sale_date",
+                format!(
+                    "Sale date {} is more than {} days from issue date {}",
+                    sale, MAX_SALE_DATE_OFFSET_DAYS, issue
+                ),
+            ));
+        }
+
+        warnings
     }
+}
 
-    // Extract sale date
-    if let Some(caps) = SALE_DATE.captures(text) {
-        let date_text = &caps[1];
-        if let Some(date) = date_extractor.extract(date_text) {
-            result.sale_date = Some(ExtractionMatch::new(date.value, 0.95, date_text));
+/// Ranked candidates for each invoice date, best first.
+#[derive(Debug, Clone, Default)]
+pub struct DateCandidates {
+    pub issue_date: Vec<Candidate<NaiveDate>>,
+    pub sale_date: Vec<Candidate<NaiveDate>>,
+    pub due_date: Vec<Candidate<NaiveDate>>,
+}
+
+/// Extract all labeled dates from invoice text.
+pub fn extract_dates(text: &str) -> InvoiceDates {
+    extract_dates_with_confidence(text, &TextConfidence::default())
+}
+
+/// Extract dates, weighing candidates by the OCR confidence of their line.
+///
+/// Every date after a label is a candidate for that field, scored higher
+/// the closer it follows the label. Dates outside any label are issue date
+/// candidates too, favouring the top of the document.
+pub fn extract_dates_with_confidence(text: &str, confidence: &TextConfidence) -> InvoiceDates {
+    let mut candidates = DateCandidates {
+        issue_date: labeled_dates(text, &ISSUE_DATE, confidence),
+        sale_date: labeled_dates(text, &SALE_DATE, confidence),
+        due_date: labeled_dates(text, &DUE_DATE, confidence),
+    };
+
+    for date in DateExtractor::new().extract_all(text) {
+        let span = date.position.unwrap_or_default();
+        if candidates.issue_date.iter().any(|c| c.span == span) {
+            continue;
         }
+
+        candidates.issue_date.push(
+            Candidate::new(date.value, span, date.source)
+                .with_validity(date_validity(date.value))
+                .with_ocr(confidence.at(span.0))
+                .with_position(1.0 - span.0 as f32 / text.len().max(1) as f32),
+        );
     }
 
-    // Extract due date
-    if let Some(caps) = DUE_DATE.captures(text) {
-        let date_text = &caps[1];
-        if let Some(date) = date_extractor.extract(date_text) {
-            result.due_date = Some(ExtractionMatch::new(date.value, 0.95, date_text));
-        }
+    candidates.issue_date = rank(candidates.issue_date);
+    candidates.sale_date = rank(candidates.sale_date);
+    candidates.due_date = rank(candidates.due_date);
+
+    let best = |c: &[Candidate<NaiveDate>]| c.first().cloned().map(Candidate::into_match);
+    InvoiceDates {
+        issue_date: best(&candidates.issue_date),
+        sale_date: best(&candidates.sale_date),
+        due_date: best(&candidates.due_date),
+        candidates,
     }
+}
 
-    // If no labeled dates found, try to extract any dates
-    if result.issue_date.is_none() {
-        let all_dates = date_extractor.extract_all(text);
-        if let Some(first) = all_dates.into_iter().next() {
-            result.issue_date = Some(first);
+/// Dates following each match of `label`.
+fn labeled_dates(text: &str, label: &Regex, confidence: &TextConfidence) -> Vec<Candidate<NaiveDate>> {
+    let extractor = DateExtractor::new();
+    let mut candidates = Vec::new();
+
+    for caps in label.captures_iter(text) {
+        let (label_match, value) = (caps.get(0).unwrap(), caps.get(1).unwrap());
+
+        // Value on the line below the label (e.g. table headers)
+        let next_line = text[label_match.start()..value.start()].contains('\n');
+
+        for date in extractor.extract_all(value.as_str()) {
+            let (start, end) = date.position.unwrap_or_default();
+            let gap = value.as_str()[..start].chars().count() as f32;
+            let proximity = if next_line { 0.8 } else { 1.0 } / (1.0 + gap / 20.0);
+
+            let span = (value.start() + start, value.start() + end);
+            candidates.push(
+                Candidate::new(date.value, span, date.source)
+                    .with_label(proximity)
+                    .with_validity(date_validity(date.value))
+                    .with_ocr(confidence.at(span.0)),
+            );
         }
     }
 
-    result
+    candidates
+}
+
+/// Dates of current invoices are the most plausible.
+fn date_validity(date: NaiveDate) -> f32 {
+    match date.year() {
+        2000..=2100 => 1.0,
+        1990..=1999 => 0.7,
+        _ => 0.2,
+    }
 }
 
 fn parse_year(s: &str) -> i32 {
@@ -203,6 +349,41 @@ mod tests {
         assert_eq!(result.unwrap().value, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
     }
 
+    #[test]
+    fn test_low_confidence_date_loses() {
+        let text = "Data wystawienia: 15.01.2024\nData wystawienia: 16.01.2024";
+        let confidence = TextConfidence::new(text, &[0.3, 0.95]);
+
+        let dates = extract_dates_with_confidence(text, &confidence);
+        assert_eq!(dates.issue_date.unwrap().value, NaiveDate::from_ymd_opt(2024, 1, 16).unwrap());
+        assert_eq!(dates.candidates.issue_date.len(), 2);
+    }
+
+    #[test]
+    fn test_date_constraints() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        // An unlabeled date after the due date can't be the issue date
+        let text = "Wydruk 05.02.2024\nFaktura z dnia 15.01.2024\nTermin płatności: 29.01.2024";
+        let mut dates = extract_dates(text);
+        assert_eq!(dates.issue_date.as_ref().unwrap().value, date(2, 5));
+        assert!(dates.apply_constraints(None).is_empty());
+        assert_eq!(dates.issue_date.unwrap().value, date(1, 15));
+
+        let text = "Data wystawienia: 15.01.2024\nData sprzedaży: 15.06.2024\nTermin płatności: 10.01.2024";
+        let mut dates = extract_dates(text);
+        let warnings = dates.apply_constraints(Some(date(1, 20)));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].code, IssueCode::DueDateBeforeIssueDate);
+        assert!(warnings[0].message.contains("Due date 2024-01-10"));
+        assert_eq!(warnings[1].field, "header.sale_date");
+        assert!(warnings[1].message.contains("Sale date 2024-06-15"));
+
+        let mut dates = extract_dates("Data wystawienia: 15.01.2034");
+        let warnings = dates.apply_constraints(Some(date(1, 20)));
+        assert!(warnings[0].message.contains("implausible for a document dated 2024-01-20"));
+    }
+
     #[test]
     fn test_extract_labeled_dates() {
         let text = r#"
@@ -3,6 +3,8 @@
 use super::{ExtractionMatch, FieldExtractor};
 use super::patterns::{IBAN_PATTERN, BANK_ACCOUNT};
 
+pub use crate::validate::{format_iban, validate_iban};
+
 /// IBAN field extractor.
 pub struct IbanExtractor {
     validate: bool,
@@ -95,80 +97,6 @@ pub fn extract_iban(text: &str) -> Option<String> {
     IbanExtractor::new().extract(text).map(|m| m.value)
 }
 
-/// Validate an IBAN using the checksum algorithm.
-///
-/// Algorithm:
-/// 1. Move first 4 characters to the end
-/// 2. Replace letters with numbers (A=10, B=11, ..., Z=35)
-/// 3. The resulting number mod 97 should equal 1
-pub fn validate_iban(iban: &str) -> bool {
-    // Remove spaces and convert to uppercase
-    let iban: String = iban
-        .chars()
-        .filter(|c| !c.is_whitespace())
-        .collect::<String>()
-        .to_uppercase();
-
-    // Check minimum length (country code + check digits + BBAN)
-    if iban.len() < 5 {
-        return false;
-    }
-
-    // Check country code is letters and check digits are numbers
-    let country_code = &iban[..2];
-    let check_digits = &iban[2..4];
-
-    if !country_code.chars().all(|c| c.is_ascii_alphabetic()) {
-        return false;
-    }
-    if !check_digits.chars().all(|c| c.is_ascii_digit()) {
-        return false;
-    }
-
-    // Move first 4 characters to the end
-    let rearranged = format!("{}{}", &iban[4..], &iban[..4]);
-
-    // Convert to number string (letters become 10-35)
-    let mut number_str = String::new();
-    for c in rearranged.chars() {
-        if c.is_ascii_digit() {
-            number_str.push(c);
-        } else if c.is_ascii_alphabetic() {
-            let value = (c as u32) - ('A' as u32) + 10;
-            number_str.push_str(&value.to_string());
-        } else {
-            return false;
-        }
-    }
-
-    // Calculate mod 97 using string arithmetic (number is too large for u64)
-    mod97(&number_str) == 1
-}
-
-fn mod97(number_str: &str) -> u32 {
-    let mut remainder: u32 = 0;
-
-    for c in number_str.chars() {
-        let digit = c.to_digit(10).unwrap_or(0);
-        remainder = (remainder * 10 + digit) % 97;
-    }
-
-    remainder
-}
-
-/// Format IBAN in groups of 4 characters.
-pub fn format_iban(iban: &str) -> String {
-    let cleaned: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
-
-    cleaned
-        .chars()
-        .collect::<Vec<char>>()
-        .chunks(4)
-        .map(|chunk| chunk.iter().collect::<String>())
-        .collect::<Vec<String>>()
-        .join(" ")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,94 @@
+//! Invoice type classification from the document title and annotations.
+//!
+//! The type is stated in the title (`Faktura korygująca`, `Faktura
+//! zaliczkowa`, `Faktura końcowa`, `Faktura pro forma`) or, for margin
+//! invoices, in the mandatory annotation (`procedura marży - towary
+//! używane`) or a `Faktura VAT marża` title.
+
+use super::correction::is_correction;
+use super::patterns::{ADVANCE_INVOICE, FINAL_INVOICE, MARGIN_INVOICE, PROFORMA_INVOICE};
+use crate::models::invoice::InvoiceType;
+
+/// Classify an invoice by its header keywords, [`InvoiceType::Standard`]
+/// when none match.
+///
+/// A correction of an advance invoice is a correction; a final invoice
+/// that settles advance invoices is a final invoice.
+pub fn classify_invoice_type(text: &str) -> InvoiceType {
+    if is_correction(text) {
+        InvoiceType::Correction
+    } else if PROFORMA_INVOICE.is_match(text) {
+        InvoiceType::Proforma
+    } else if FINAL_INVOICE.is_match(text) {
+        InvoiceType::Final
+    } else if ADVANCE_INVOICE.is_match(text) {
+        InvoiceType::Advance
+    } else if MARGIN_INVOICE.is_match(text) {
+        InvoiceType::Margin
+    } else {
+        InvoiceType::Standard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proforma() {
+        for text in [
+            "FAKTURA PRO FORMA nr PF/3/2024",
+            "Faktura proforma nr PF/3/2024",
+            "PRO-FORMA\nNr PF/3/2024",
+        ] {
+            assert_eq!(classify_invoice_type(text), InvoiceType::Proforma, "{}", text);
+        }
+        // A standard invoice referring to the proforma it follows
+        assert_eq!(
+            classify_invoice_type("Faktura VAT nr FV/5/2024\nDo faktury proforma PF/3/2024"),
+            InvoiceType::Standard
+        );
+    }
+
+    #[test]
+    fn test_advance_and_final() {
+        assert_eq!(
+            classify_invoice_type("Faktura VAT zaliczkowa nr FZ/1/2024\nZaliczka: 1 230,00"),
+            InvoiceType::Advance
+        );
+        assert_eq!(
+            classify_invoice_type(
+                "Faktura końcowa nr FK/1/2024\nRozliczenie faktury zaliczkowej nr FZ/1/2024"
+            ),
+            InvoiceType::Final
+        );
+        assert_eq!(
+            classify_invoice_type("FAKTURA ROZLICZENIOWA FR/2/2024"),
+            InvoiceType::Final
+        );
+
+        // A correction of an advance invoice is a correction
+        assert_eq!(
+            classify_invoice_type(
+                "Faktura korygująca nr KOR/1/2024\nFaktura zaliczkowa korygowana: FZ/1/2024"
+            ),
+            InvoiceType::Correction
+        );
+    }
+
+    #[test]
+    fn test_margin() {
+        for text in [
+            "Faktura VAT marża nr FM/7/2024",
+            "Faktura VAT-MARŻA FM/7/2024",
+            "Faktura nr 7/2024\nProcedura marży - towary używane",
+            "Faktura nr 7/2024\nmarża - biura podróży",
+        ] {
+            assert_eq!(classify_invoice_type(text), InvoiceType::Margin, "{}", text);
+        }
+        assert_eq!(
+            classify_invoice_type("Faktura VAT nr FV/1/2024\nMarża handlowa 12%"),
+            InvoiceType::Standard
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! KRS (National Court Register number) extraction and validation.
+//!
+//! KRS numbers have no checksum and the same length as a NIP, so only
+//! labeled numbers are extracted.
+
+use super::{ExtractionMatch, FieldExtractor};
+use super::patterns::KRS_PATTERN;
+
+pub use crate::validate::validate_krs;
+
+/// KRS field extractor.
+pub struct KrsExtractor;
+
+impl KrsExtractor {
+    /// Create a new KRS extractor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for KrsExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldExtractor for KrsExtractor {
+    type Output = ExtractionMatch<String>;
+
+    fn extract(&self, text: &str) -> Option<Self::Output> {
+        self.extract_all(text).into_iter().next()
+    }
+
+    fn extract_all(&self, text: &str) -> Vec<Self::Output> {
+        let mut results: Vec<Self::Output> = Vec::new();
+
+        for caps in KRS_PATTERN.captures_iter(text) {
+            let krs = caps[1].to_string();
+
+            if results.iter().any(|r| r.value == krs) || !validate_krs(&krs) {
+                continue;
+            }
+
+            let full_match = caps.get(0).unwrap();
+            results.push(
+                ExtractionMatch::new(krs, 0.95, full_match.as_str())
+                    .with_position(full_match.start(), full_match.end()),
+            );
+        }
+
+        results
+    }
+}
+
+/// Extract KRS from text.
+pub fn extract_krs(text: &str) -> Option<String> {
+    KrsExtractor::new().extract(text).map(|m| m.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_krs_labeled() {
+        assert_eq!(extract_krs("KRS: 0000123456"), Some("0000123456".to_string()));
+        assert_eq!(extract_krs("Sąd Rejonowy, nr KRS 0000654321"), Some("0000654321".to_string()));
+    }
+
+    #[test]
+    fn test_extract_krs_rejected() {
+        assert_eq!(extract_krs("KRS: 0000000000"), None);
+        assert_eq!(extract_krs("KRS: 123456"), None);
+        assert_eq!(extract_krs("NIP: 0000123456"), None);
+    }
+}
@@ -0,0 +1,95 @@
+//! KSeF number extraction and validation.
+//!
+//! Invoices issued through the National e-Invoice System (KSeF) carry the
+//! 35-character number KSeF assigned to them, e.g.
+//! `5265877635-20250826-0100001AF629-AF`. The CRC-8 checksum makes a bare
+//! number reliable, so no label is required.
+
+use super::{ExtractionMatch, FieldExtractor};
+use super::patterns::KSEF_NUMBER_PATTERN;
+
+pub use crate::validate::validate_ksef_number;
+
+/// KSeF number field extractor.
+pub struct KsefNumberExtractor;
+
+impl KsefNumberExtractor {
+    /// Create a new KSeF number extractor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for KsefNumberExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldExtractor for KsefNumberExtractor {
+    type Output = ExtractionMatch<String>;
+
+    fn extract(&self, text: &str) -> Option<Self::Output> {
+        self.extract_all(text).into_iter().next()
+    }
+
+    fn extract_all(&self, text: &str) -> Vec<Self::Output> {
+        let mut results: Vec<Self::Output> = Vec::new();
+
+        for caps in KSEF_NUMBER_PATTERN.captures_iter(text) {
+            let number = normalize_ksef_number(&caps[1]);
+
+            if results.iter().any(|r| r.value == number) || !validate_ksef_number(&number) {
+                continue;
+            }
+
+            let full_match = caps.get(0).unwrap();
+            results.push(
+                ExtractionMatch::new(number, 0.98, full_match.as_str())
+                    .with_position(full_match.start(), full_match.end()),
+            );
+        }
+
+        results
+    }
+}
+
+/// Extract the KSeF number from text.
+pub fn extract_ksef_number(text: &str) -> Option<String> {
+    KsefNumberExtractor::new().extract(text).map(|m| m.value)
+}
+
+/// Uppercase, without the whitespace OCR leaves around the dashes.
+fn normalize_ksef_number(number: &str) -> String {
+    number
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_ksef_number() {
+        let text = "Numer KSeF: 5265877635-20250826-0100001AF629-AF\nFaktura VAT FV/1/2025";
+        assert_eq!(
+            extract_ksef_number(text),
+            Some("5265877635-20250826-0100001AF629-AF".to_string())
+        );
+        assert_eq!(
+            extract_ksef_number("KSeF 5261040828 - 20240115 - 8a3f21c0d9e4 - 7e"),
+            Some("5261040828-20240115-8A3F21C0D9E4-7E".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_ksef_number_rejected() {
+        // Misread checksum
+        assert_eq!(extract_ksef_number("5265877635-20250826-0100001AF629-A8"), None);
+        // A longer identifier that merely contains the format
+        assert_eq!(extract_ksef_number("5265877635-20250826-0100001AF629-AF12"), None);
+    }
+}
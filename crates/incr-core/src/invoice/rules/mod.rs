@@ -2,18 +2,40 @@
 
 pub mod nip;
 pub mod regon;
+pub mod pesel;
+pub mod krs;
+pub mod ksef;
 pub mod dates;
 pub mod amounts;
 pub mod vat;
 pub mod iban;
+pub mod contacts;
+pub mod currency;
+pub mod correction;
+pub mod annotations;
+pub mod invoice_type;
 pub mod patterns;
 
-pub use nip::{extract_nip, validate_nip, format_nip, NipExtractor};
+pub use nip::{
+    extract_nip, extract_vat_id, validate_nip, validate_vat_id, format_nip, NipExtractor,
+    VatIdExtractor,
+};
 pub use regon::{extract_regon, validate_regon, RegonExtractor};
-pub use dates::{extract_dates, DateExtractor};
-pub use amounts::{extract_amounts, parse_polish_amount, format_polish_amount, AmountExtractor};
+pub use pesel::{extract_pesel, pesel_birth_date, validate_pesel, PeselExtractor};
+pub use krs::{extract_krs, validate_krs, KrsExtractor};
+pub use ksef::{extract_ksef_number, validate_ksef_number, KsefNumberExtractor};
+pub use dates::{extract_dates, extract_dates_with_confidence, DateCandidates, DateExtractor};
+pub use amounts::{
+    extract_amounts, extract_amounts_with_confidence, parse_polish_amount, format_polish_amount,
+    AmountExtractor, TotalCandidates,
+};
 pub use vat::{extract_vat_rates, VatExtractor};
 pub use iban::{extract_iban, validate_iban, format_iban, IbanExtractor};
+pub use contacts::{extract_contacts, normalize_email, normalize_phone, normalize_website, Contacts};
+pub use correction::{extract_correction, is_correction, Correction};
+pub use annotations::{extract_gtu_codes, is_split_payment, parse_gtu_code};
+pub use invoice_type::classify_invoice_type;
+pub use currency::{extract_currency, extract_exchange_rate, CurrencyExtractor, ExchangeRate};
 pub use patterns::*;
 
 
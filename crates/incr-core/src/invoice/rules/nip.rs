@@ -1,7 +1,10 @@
-//! NIP (Polish Tax Identification Number) extraction and validation.
+//! NIP (Polish Tax Identification Number) extraction and validation, and
+//! the EU VAT IDs that take its place on cross-border invoices.
 
 use super::{ExtractionMatch, FieldExtractor};
-use super::patterns::{NIP_PATTERN, NIP_STANDALONE};
+use super::patterns::{NIP_PATTERN, NIP_STANDALONE, VAT_ID_LABEL, VAT_ID_PATTERN};
+
+pub use crate::validate::{format_nip, split_vat_id, validate_nip, validate_vat_id};
 
 /// NIP field extractor.
 pub struct NipExtractor {
@@ -83,54 +86,61 @@ pub fn extract_nip(text: &str) -> Option<String> {
     NipExtractor::new().extract(text).map(|m| m.value)
 }
 
-/// Validate a Polish NIP using the checksum algorithm.
+/// Extractor of foreign EU VAT IDs (DE, CZ, SK, FR).
 ///
-/// NIP format: 10 digits where the last digit is a checksum.
-/// Weights: 6, 5, 7, 2, 3, 4, 5, 6, 7
-pub fn validate_nip(nip: &str) -> bool {
-    let digits: Vec<u32> = nip
-        .chars()
-        .filter(|c| c.is_ascii_digit())
-        .filter_map(|c| c.to_digit(10))
-        .collect();
+/// Values are normalized to the country code followed by the number
+/// (`DE136695976`). Polish VAT IDs are NIPs and left to [`NipExtractor`].
+pub struct VatIdExtractor;
 
-    if digits.len() != 10 {
-        return false;
+impl VatIdExtractor {
+    /// Create a new VAT ID extractor.
+    pub fn new() -> Self {
+        Self
     }
+}
 
-    let weights = [6, 5, 7, 2, 3, 4, 5, 6, 7];
-    let sum: u32 = digits
-        .iter()
-        .take(9)
-        .zip(weights.iter())
-        .map(|(d, w)| d * w)
-        .sum();
+impl Default for VatIdExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    let checksum = sum % 11;
+impl FieldExtractor for VatIdExtractor {
+    type Output = ExtractionMatch<String>;
 
-    // If checksum is 10, the NIP is invalid
-    if checksum == 10 {
-        return false;
+    fn extract(&self, text: &str) -> Option<Self::Output> {
+        self.extract_all(text).into_iter().next()
     }
 
-    checksum == digits[9]
-}
+    fn extract_all(&self, text: &str) -> Vec<Self::Output> {
+        let mut results: Vec<Self::Output> = Vec::new();
 
-/// Format NIP with dashes (XXX-XXX-XX-XX).
-pub fn format_nip(nip: &str) -> String {
-    let digits: String = nip.chars().filter(|c| c.is_ascii_digit()).collect();
+        for caps in VAT_ID_PATTERN.captures_iter(text) {
+            let vat_id: String = caps[1].chars().filter(|c| !c.is_whitespace()).collect();
 
-    if digits.len() != 10 {
-        return nip.to_string();
+            if results.iter().any(|r| r.value == vat_id) || !validate_vat_id(&vat_id) {
+                continue;
+            }
+
+            // The checksum makes bare numbers likely, a label certain
+            let full_match = caps.get(0).unwrap();
+            let line_start = text[..full_match.start()].rfind('\n').map_or(0, |i| i + 1);
+            let labeled = VAT_ID_LABEL.is_match(&text[line_start..full_match.start()]);
+            let confidence = if labeled { 0.95 } else { 0.8 };
+
+            results.push(
+                ExtractionMatch::new(vat_id, confidence, full_match.as_str())
+                    .with_position(full_match.start(), full_match.end()),
+            );
+        }
+
+        results
     }
+}
 
-    format!(
-        "{}-{}-{}-{}",
-        &digits[0..3],
-        &digits[3..6],
-        &digits[6..8],
-        &digits[8..10]
-    )
+/// Extract a foreign EU VAT ID from text.
+pub fn extract_vat_id(text: &str) -> Option<String> {
+    VatIdExtractor::new().extract(text).map(|m| m.value)
 }
 
 #[cfg(test)]
@@ -167,6 +177,25 @@ mod tests {
         assert!(!results.is_empty());
     }
 
+    #[test]
+    fn test_extract_vat_id() {
+        let text = "Nabywca:\nMüller GmbH\nUSt-IdNr.: DE 136 695 976\nBerlin";
+        let found = VatIdExtractor::new().extract(text).unwrap();
+        assert_eq!(found.value, "DE136695976");
+        assert_eq!(found.confidence, 0.95);
+
+        assert_eq!(extract_vat_id("DIČ: CZ25123891"), Some("CZ25123891".to_string()));
+        assert_eq!(extract_vat_id("IČ DPH SK2020273893"), Some("SK2020273893".to_string()));
+        assert_eq!(extract_vat_id("N° TVA FR 40 303 265 045"), Some("FR40303265045".to_string()));
+    }
+
+    #[test]
+    fn test_extract_vat_id_rejected() {
+        assert_eq!(extract_vat_id("NIP: PL5261040828"), None); // A NIP
+        assert_eq!(extract_vat_id("USt-IdNr.: DE136695977"), None); // Checksum
+        assert_eq!(extract_vat_id("INDE136695976"), None);
+    }
+
     #[test]
     fn test_format_nip() {
         assert_eq!(format_nip("5261040828"), "526-104-08-28");
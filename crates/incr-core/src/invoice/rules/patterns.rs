@@ -13,6 +13,17 @@ lazy_static! {
         r"\b(\d{3})[- ]?(\d{3})[- ]?(\d{2})[- ]?(\d{2})\b"
     ).unwrap();
 
+    // EU VAT IDs of the countries with checksums in `validate_vat_id`,
+    // other than PL (written as a NIP)
+    pub static ref VAT_ID_PATTERN: Regex = Regex::new(
+        r"\b(DE\s?\d{3}\s?\d{3}\s?\d{3}|CZ\s?\d{8,10}|SK\s?\d{10}|FR\s?[0-9A-HJ-NP-Z]{2}\s?\d{3}\s?\d{3}\s?\d{3})\b"
+    ).unwrap();
+
+    // Labels of VAT IDs: VAT ID, USt-IdNr., DIČ, IČ DPH, N° TVA
+    pub static ref VAT_ID_LABEL: Regex = Regex::new(
+        r"(?i)(?:VAT|USt\.?-?Id(?:Nr)?\.?|DI[ČC]|I[ČC]\s?DPH|TVA|NIP|UID)[\s.:\-]*(?:ID|UE|EU|No\.?|Nr\.?|number|intracom\w*)?[\s.:]*$"
+    ).unwrap();
+
     // REGON patterns (Polish statistical ID)
     pub static ref REGON_PATTERN: Regex = Regex::new(
         r"(?i)(?:REGON|REG\.?)[\s:]*(\d{9}|\d{14})"
@@ -22,6 +33,21 @@ lazy_static! {
         r"\b(\d{9})\b|\b(\d{14})\b"
     ).unwrap();
 
+    // PESEL and KRS patterns (labeled only; bare 10-11 digit numbers are ambiguous)
+    pub static ref PESEL_PATTERN: Regex = Regex::new(
+        r"(?i)PESEL[\s:]*(\d{11})\b"
+    ).unwrap();
+
+    pub static ref KRS_PATTERN: Regex = Regex::new(
+        r"(?i)\bKRS[\s:]*(?:nr\.?\s*)?(\d{10})\b"
+    ).unwrap();
+
+    // KSeF number: NIP-YYYYMMDD-12 hex digits-2 hex digits of checksum,
+    // with the spaces OCR puts around dashes
+    pub static ref KSEF_NUMBER_PATTERN: Regex = Regex::new(
+        r"(?i)\b(\d{10}\s*-\s*\d{8}\s*-\s*[0-9A-F]{12}\s*-\s*[0-9A-F]{2})\b"
+    ).unwrap();
+
     // Polish date patterns
     pub static ref DATE_DMY: Regex = Regex::new(
         r"\b(\d{1,2})[./\-](\d{1,2})[./\-](\d{4}|\d{2})\b"
@@ -57,6 +83,27 @@ lazy_static! {
         r"(\d{1,3}(?:[\s\u{00a0}]?\d{3})*)[,.](\d{2})\s*(PLN|zł|EUR|€|USD|\$|GBP|£)"
     ).unwrap();
 
+    // Currency named by a label ("Waluta: EUR", "Waluta PLN", "Waluta
+    // faktury:" with the code on the next line). The word after the label
+    // may be the currency or a qualifier.
+    pub static ref CURRENCY_LABEL: Regex = Regex::new(
+        r"(?i)\bwaluta\b(?:[ \t]+(\w+))?[ \t]*:?\s*([a-zł]{2,6}\b|€|\$|£)?"
+    ).unwrap();
+
+    // Currency after an amount ("100,00 EUR") or a symbol before it ("€ 100,00")
+    pub static ref CURRENCY_AMOUNT: Regex = Regex::new(
+        r"(?i)\d[,.]\d{2}\s*(PLN|z[łl]|EUR|€|USD|\$|GBP|£|CHF)|(€|\$|£)\s*\d"
+    ).unwrap();
+
+    // Exchange rate: 1-3 integer digits and 4-6 decimals ("4,3123")
+    pub static ref EXCHANGE_RATE: Regex = Regex::new(
+        r"\b(\d{1,3}[,.]\d{4,6})\b"
+    ).unwrap();
+
+    pub static ref NBP_TABLE: Regex = Regex::new(
+        r"(?i)\b(\d{1,3}/[ABC]/NBP/\d{4})\b"
+    ).unwrap();
+
     // Total amounts
     pub static ref TOTAL_GROSS: Regex = Regex::new(
         r"(?i)(?:razem|suma|do\s+zap[łl]aty|kwota\s+brutto|warto[śs][ćc]\s+brutto)[\s:]*(\d{1,3}(?:[\s\u{00a0}]?\d{3})*[,.]\d{2})"
@@ -90,7 +137,50 @@ lazy_static! {
 
     // Invoice number patterns
     pub static ref INVOICE_NUMBER: Regex = Regex::new(
-        r"(?i)(?:faktura\s+(?:VAT\s+)?(?:nr|numer)|nr\s+faktury|numer\s+faktury)[\s:]*([A-Za-z0-9/\-_]+)"
+        r"(?i)(?:faktura\s+(?:VAT[\s\-]+)?(?:(?:koryguj[ąa]ca|zaliczkowa|ko[ńn]cowa|rozliczeniowa|pro[\s\-]?forma|mar[żz]a)\s+)?(?:nr|numer)|nr\s+faktury|numer\s+faktury)[\s:]*([A-Za-z0-9/\-_]+)"
+    ).unwrap();
+
+    // Invoice types stated in the title or annotations
+    pub static ref PROFORMA_INVOICE: Regex = Regex::new(
+        r"(?im)\bfaktura\s+(?:VAT\s+)?pro[\s\-]?forma\b|^\s*pro[\s\-]?forma\b"
+    ).unwrap();
+
+    pub static ref ADVANCE_INVOICE: Regex = Regex::new(
+        r"(?i)\bfaktura\s+(?:VAT\s+)?zaliczkowa\b"
+    ).unwrap();
+
+    pub static ref FINAL_INVOICE: Regex = Regex::new(
+        r"(?i)\bfaktura\s+(?:VAT\s+)?(?:ko[ńn]cowa|rozliczeniowa)\b"
+    ).unwrap();
+
+    pub static ref MARGIN_INVOICE: Regex = Regex::new(
+        r"(?i)\bVAT[\s\-]+mar[żz]a\b|\bprocedura\s+mar[żz]y\b|\bmar[żz]a\s*[-–]\s*(?:towary|dzie[łl]a|przedmioty|biura)\b"
+    ).unwrap();
+
+    // Correction invoice (faktura korygująca)
+    pub static ref CORRECTION_HEADER: Regex = Regex::new(
+        r"(?i)\bfaktura\s+(?:VAT\s+)?koryguj[ąa]ca\b|\bkorekta\s+faktury\b|\bfaktura\s+korekta\b"
+    ).unwrap();
+
+    pub static ref CORRECTED_INVOICE: Regex = Regex::new(
+        r"(?i)(?:do\s+faktury|dotyczy\s+faktury|korekta\s+faktury|faktur[ay]\s+korygowan(?:a|ej))(?:\s+(?:VAT|nr\.?|numer))*[\s:]*([A-Za-z0-9][A-Za-z0-9/\-_.]*)"
+    ).unwrap();
+
+    pub static ref CORRECTION_REASON: Regex = Regex::new(
+        r"(?i)(?:przyczyna|pow[óo]d|tytu[łl])\s+korekty[\s:]*([^\n]+)"
+    ).unwrap();
+
+    // Rows of totals before and after the correction and their difference
+    pub static ref CORRECTION_BEFORE: Regex = Regex::new(
+        r"(?im)^\s*(?:warto[śs][ćc]\s+)?(?:przed\s+korekt[ąa]|by[łl]o)\b[\s:]*(.*)$"
+    ).unwrap();
+
+    pub static ref CORRECTION_AFTER: Regex = Regex::new(
+        r"(?im)^\s*(?:warto[śs][ćc]\s+)?(?:po\s+korekcie|powinno\s+by[ćc])\b[\s:]*(.*)$"
+    ).unwrap();
+
+    pub static ref CORRECTION_DIFFERENCE: Regex = Regex::new(
+        r"(?im)^\s*(?:r[óo][żz]nica|kwota\s+korekty|warto[śs][ćc]\s+korekty)\b[\s:]*(.*)$"
     ).unwrap();
 
     pub static ref INVOICE_NUMBER_STANDALONE: Regex = Regex::new(
@@ -111,6 +201,16 @@ lazy_static! {
         r"(?i)(?:forma\s+p[łl]atno[śs]ci|spos[óo]b\s+p[łl]atno[śs]ci|metoda\s+p[łl]atno[śs]ci)[\s:]*(\w+)"
     ).unwrap();
 
+    // Split payment annotation (mechanizm podzielonej płatności)
+    pub static ref SPLIT_PAYMENT: Regex = Regex::new(
+        r"(?i)\bmechanizm(?:u|em)?\s+podzielonej\s+p[łl]atno[śs]ci\b|\bsplit\s+payment\b|\bMPP\b"
+    ).unwrap();
+
+    // GTU goods and services group code (GTU_01 to GTU_13)
+    pub static ref GTU_CODE: Regex = Regex::new(
+        r"(?i)\bGTU[\s_\-]?(\d{1,2})\b"
+    ).unwrap();
+
     // Postal code pattern
     pub static ref POSTAL_CODE: Regex = Regex::new(
         r"\b(\d{2})-(\d{3})\b"
@@ -123,6 +223,75 @@ lazy_static! {
 
     // Phone pattern (Polish format)
     pub static ref PHONE: Regex = Regex::new(
-        r"(?:\+48[\s\-]?)?(?:\d{3}[\s\-]?\d{3}[\s\-]?\d{3}|\d{2}[\s\-]?\d{3}[\s\-]?\d{2}[\s\-]?\d{2})"
+        r"(?:(?:\+|\b00)48[\s\-]?|\b)(?:\d{3}[\s\-]?\d{3}[\s\-]?\d{3}|\d{2}[\s\-]?\d{3}[\s\-]?\d{2}[\s\-]?\d{2})\b"
+    ).unwrap();
+
+    // Phone number after a label (tel., telefon, fax, kom., mobile)
+    pub static ref PHONE_LABELED: Regex = Regex::new(
+        r"(?i)\b(?:tel(?:efon)?|fax|kom(?:[óo]rka)?|mobile?)\.?(?:\s*/\s*fax\.?)?[ \t:.]*((?:\+|00)?[\d \-()]{8,20}\d)"
+    ).unwrap();
+
+    // Website pattern (scheme or www. prefix required)
+    pub static ref WEBSITE: Regex = Regex::new(
+        r"(?i)\b(?:https?://|www\.)[a-z0-9][a-z0-9.\-]*\.[a-z]{2,}(?:/[^\s,;]*)?"
+    ).unwrap();
+
+    // Page number line (Strona 2 z 3, Str. 2/3, Page 2 of 3, - 2 -)
+    pub static ref PAGE_NUMBER: Regex = Regex::new(
+        r"(?i)^(?:(?:strona|str\.|page)\s*\d+(?:\s*(?:z|/|of)\s*\d+)?|-\s*\d+\s*-|\d+\s*/\s*\d+)$"
+    ).unwrap();
+
+    // Subtotal carried over between pages (Do przeniesienia, Z przeniesienia)
+    pub static ref CARRIED_OVER: Regex = Regex::new(
+        r"(?i)\b(?:do|z)\s+przeniesienia\b|\bprzeniesienie\b|\b(?:carried|brought)\s+forward\b"
+    ).unwrap();
+
+    // Fiscal receipt (paragon fiskalny) layout
+    pub static ref RECEIPT_HEADER: Regex = Regex::new(
+        r"(?i)paragon\s+fiskalny"
+    ).unwrap();
+
+    pub static ref BUYER_NIP: Regex = Regex::new(
+        r"(?i)NIP\s+nabywcy[\s:]*(?:PL)?\s*(\d{3})[- ]?(\d{3})[- ]?(\d{2})[- ]?(\d{2})"
+    ).unwrap();
+
+    // Item with quantity and unit price: "Mleko 2 x2,99 5,98C"; the name
+    // may be on the line above
+    pub static ref RECEIPT_ITEM: Regex = Regex::new(
+        r"^(?:(.*?)\s+)?(\d+(?:[.,]\d+)?)\s*(?:szt\.?|kg|op\.?)?\s*[x*×]\s*(\d+[.,]\d{2})\s+(-?\d+[.,]\d{2})\s*([A-G])$"
+    ).unwrap();
+
+    // Item without quantity or a discount: "Chleb 3,50A", "Rabat -0,50A"
+    pub static ref RECEIPT_ITEM_SHORT: Regex = Regex::new(
+        r"^(.+?)\s+(-?\d+[.,]\d{2})\s*([A-G])$"
+    ).unwrap();
+
+    pub static ref TAXABLE_SALES: Regex = Regex::new(
+        r"(?i)^(?:sprzeda[żz]\s+opodatk(?:owana|\.)?|sp\.\s*op\.)\s*([A-G])\s+(\d[\d ]*[.,]\d{2})"
+    ).unwrap();
+
+    pub static ref PTU_GROUP: Regex = Regex::new(
+        r"(?i)^PTU\s+([A-G])\s+(\d+(?:[.,]\d+)?)\s*%\s+(\d[\d ]*[.,]\d{2})"
+    ).unwrap();
+
+    pub static ref TOTAL_PTU: Regex = Regex::new(
+        r"(?i)^(?:SUMA\s+PTU|[łl][ąa]czna\s+kwota\s+PTU)[\s:]*(\d[\d ]*[.,]\d{2})"
+    ).unwrap();
+
+    pub static ref RECEIPT_TOTAL: Regex = Regex::new(
+        r"(?i)^SUMA\s*(?:PLN)?[\s:]*(\d[\d ]*[.,]\d{2})\s*(?:PLN|z[łl])?$"
+    ).unwrap();
+
+    pub static ref PRINT_NUMBER: Regex = Regex::new(
+        r"(?i)(?:nr\s+wydr\.?|wydruk\s+nr|paragon\s+(?:fiskalny\s+)?nr)[\s:.]*(\d+)"
+    ).unwrap();
+
+    // Unique number of the cash register, next to the "PL" fiscal logo
+    pub static ref FISCAL_NUMBER: Regex = Regex::new(
+        r"^(?:PL\s+)?([A-Z]{3}\s?\d{8})$"
+    ).unwrap();
+
+    pub static ref TIME: Regex = Regex::new(
+        r"\b([01]?\d|2[0-3]):([0-5]\d)\b"
     ).unwrap();
 }
@@ -0,0 +1,98 @@
+//! PESEL (Polish personal identification number) extraction and validation.
+//!
+//! Sole traders sometimes print their PESEL instead of, or next to, the NIP.
+//! Only labeled numbers are extracted: bare 11-digit numbers are too common
+//! (phone numbers, account fragments) to be told apart from a PESEL.
+
+use super::{ExtractionMatch, FieldExtractor};
+use super::patterns::PESEL_PATTERN;
+
+pub use crate::validate::{pesel_birth_date, validate_pesel};
+
+/// PESEL field extractor.
+pub struct PeselExtractor {
+    validate: bool,
+}
+
+impl PeselExtractor {
+    /// Create a new PESEL extractor.
+    pub fn new() -> Self {
+        Self { validate: true }
+    }
+
+    /// Set whether to validate PESEL checksums and birth dates.
+    pub fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+}
+
+impl Default for PeselExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldExtractor for PeselExtractor {
+    type Output = ExtractionMatch<String>;
+
+    fn extract(&self, text: &str) -> Option<Self::Output> {
+        self.extract_all(text).into_iter().next()
+    }
+
+    fn extract_all(&self, text: &str) -> Vec<Self::Output> {
+        let mut results: Vec<Self::Output> = Vec::new();
+
+        for caps in PESEL_PATTERN.captures_iter(text) {
+            let pesel = caps[1].to_string();
+
+            if results.iter().any(|r| r.value == pesel) {
+                continue;
+            }
+
+            if !self.validate || validate_pesel(&pesel) {
+                let full_match = caps.get(0).unwrap();
+                results.push(
+                    ExtractionMatch::new(pesel, 0.95, full_match.as_str())
+                        .with_position(full_match.start(), full_match.end()),
+                );
+            }
+        }
+
+        results
+    }
+}
+
+/// Extract PESEL from text.
+pub fn extract_pesel(text: &str) -> Option<String> {
+    PeselExtractor::new().extract(text).map(|m| m.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_extract_pesel_labeled() {
+        let text = "Jan Kowalski Usługi Remontowe\nPESEL: 44051401359\nNIP: 526-104-08-28";
+        assert_eq!(extract_pesel(text), Some("44051401359".to_string()));
+
+        let pesel = extract_pesel(text).unwrap();
+        assert_eq!(pesel_birth_date(&pesel), NaiveDate::from_ymd_opt(1944, 5, 14));
+    }
+
+    #[test]
+    fn test_extract_pesel_invalid() {
+        let text = "PESEL 44051401358";
+        assert_eq!(extract_pesel(text), None);
+
+        let extractor = PeselExtractor::new().with_validation(false);
+        assert_eq!(extractor.extract_all(text).len(), 1);
+    }
+
+    #[test]
+    fn test_unlabeled_number_ignored() {
+        assert_eq!(extract_pesel("Tel. 44051401359"), None);
+    }
+}
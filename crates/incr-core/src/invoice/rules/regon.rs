@@ -3,6 +3,8 @@
 use super::{ExtractionMatch, FieldExtractor};
 use super::patterns::{REGON_PATTERN, REGON_STANDALONE};
 
+pub use crate::validate::validate_regon;
+
 /// REGON field extractor.
 pub struct RegonExtractor {
     validate: bool,
@@ -85,61 +87,6 @@ pub fn extract_regon(text: &str) -> Option<String> {
     RegonExtractor::new().extract(text).map(|m| m.value)
 }
 
-/// Validate a Polish REGON using the checksum algorithm.
-///
-/// REGON can be 9 or 14 digits.
-/// - 9 digits: weights [8, 9, 2, 3, 4, 5, 6, 7]
-/// - 14 digits: first 9 validated as above, then [2, 4, 8, 5, 0, 9, 7, 3, 6, 1, 2, 4, 8]
-pub fn validate_regon(regon: &str) -> bool {
-    let digits: Vec<u32> = regon
-        .chars()
-        .filter(|c| c.is_ascii_digit())
-        .filter_map(|c| c.to_digit(10))
-        .collect();
-
-    match digits.len() {
-        9 => validate_regon_9(&digits),
-        14 => validate_regon_14(&digits),
-        _ => false,
-    }
-}
-
-fn validate_regon_9(digits: &[u32]) -> bool {
-    let weights = [8, 9, 2, 3, 4, 5, 6, 7];
-    let sum: u32 = digits
-        .iter()
-        .take(8)
-        .zip(weights.iter())
-        .map(|(d, w)| d * w)
-        .sum();
-
-    let checksum = sum % 11;
-    let expected = if checksum == 10 { 0 } else { checksum };
-
-    expected == digits[8]
-}
-
-fn validate_regon_14(digits: &[u32]) -> bool {
-    // First validate the 9-digit base
-    if !validate_regon_9(&digits[..9].to_vec()) {
-        return false;
-    }
-
-    // Then validate the full 14-digit number
-    let weights = [2, 4, 8, 5, 0, 9, 7, 3, 6, 1, 2, 4, 8];
-    let sum: u32 = digits
-        .iter()
-        .take(13)
-        .zip(weights.iter())
-        .map(|(d, w)| d * w)
-        .sum();
-
-    let checksum = sum % 11;
-    let expected = if checksum == 10 { 0 } else { checksum };
-
-    expected == digits[13]
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
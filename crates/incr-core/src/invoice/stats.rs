@@ -0,0 +1,303 @@
+//! Quality statistics over a batch of extractions.
+//!
+//! [`BatchStats`] collects every result of a run and summarizes field
+//! coverage, confidence, the most frequent warnings, the suppliers whose
+//! invoices extract worst and processing time percentiles.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::coverage::{CoverageReport, COVERAGE_FIELDS};
+use crate::models::invoice::Invoice;
+use crate::models::validation::IssueCode;
+
+/// Number of suppliers listed in [`StatsSummary::top_failing_suppliers`].
+pub const TOP_SUPPLIERS: usize = 10;
+
+/// Accumulates results of a batch run.
+#[derive(Debug, Clone, Default)]
+pub struct BatchStats {
+    min_confidence: f32,
+    failed: usize,
+    confidences: Vec<f32>,
+    times_ms: Vec<u64>,
+    coverage: CoverageReport,
+    warnings: BTreeMap<String, WarningCount>,
+    suppliers: BTreeMap<String, SupplierStats>,
+}
+
+/// Summary of a batch run, written as `stats.json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsSummary {
+    /// Documents processed.
+    pub documents: usize,
+    /// Documents extracted.
+    pub succeeded: usize,
+    /// Documents that could not be processed.
+    pub failed: usize,
+    /// Mean extraction confidence of the extracted documents.
+    pub average_confidence: Option<f32>,
+    /// Extracted documents below the minimum confidence.
+    pub low_confidence: usize,
+    /// Percentage of extracted documents with each field present.
+    pub field_coverage: BTreeMap<String, f32>,
+    /// Warnings by code, most frequent first.
+    pub warnings: Vec<WarningCount>,
+    /// Suppliers with the most low-confidence documents.
+    pub top_failing_suppliers: Vec<SupplierStats>,
+    /// Processing time percentiles over all documents.
+    pub processing_time_ms: Option<Percentiles>,
+}
+
+/// How often a warning occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WarningCount {
+    /// Issue code (e.g. `due_date_before_issue_date`); for other warnings
+    /// the message with values removed (see [`warning_code`]).
+    pub code: String,
+    /// Number of occurrences.
+    pub count: usize,
+    /// First message seen with this code.
+    pub example: String,
+}
+
+/// Extraction quality of one supplier's invoices.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupplierStats {
+    /// Issuer NIP, or name if no NIP was found.
+    pub supplier: String,
+    /// Issuer name.
+    pub name: String,
+    /// Documents from the supplier.
+    pub documents: usize,
+    /// Documents below the minimum confidence.
+    pub low_confidence: usize,
+    /// Mean extraction confidence.
+    pub average_confidence: f32,
+}
+
+/// Processing time percentiles, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Percentiles {
+    /// Median.
+    pub p50: u64,
+    /// 90th percentile.
+    pub p90: u64,
+    /// 99th percentile.
+    pub p99: u64,
+    /// Slowest document.
+    pub max: u64,
+}
+
+impl BatchStats {
+    /// Create empty statistics; documents below `min_confidence` count as
+    /// low confidence.
+    pub fn new(min_confidence: f32) -> Self {
+        Self {
+            min_confidence,
+            ..Self::default()
+        }
+    }
+
+    /// Add an extracted document.
+    pub fn add(&mut self, invoice: &Invoice, processing_time_ms: u64) {
+        let confidence = invoice.metadata.confidence;
+        self.confidences.push(confidence);
+        self.times_ms.push(processing_time_ms);
+        self.coverage.add(invoice, 0.0);
+
+        for warning in &invoice.metadata.warnings {
+            let code = match warning.code {
+                IssueCode::Other => warning_code(&warning.message),
+                code => code.to_string(),
+            };
+            self.warnings
+                .entry(code.clone())
+                .or_insert_with(|| WarningCount {
+                    code,
+                    count: 0,
+                    example: warning.message.clone(),
+                })
+                .count += 1;
+        }
+
+        let supplier = match (&invoice.issuer.nip, invoice.issuer.name.trim()) {
+            (Some(nip), _) => nip.clone(),
+            (None, "") => "unknown".to_string(),
+            (None, name) => name.to_string(),
+        };
+        let stats = self.suppliers.entry(supplier.clone()).or_insert_with(|| SupplierStats {
+            supplier,
+            name: invoice.issuer.name.clone(),
+            documents: 0,
+            low_confidence: 0,
+            average_confidence: 0.0,
+        });
+        stats.average_confidence = (stats.average_confidence * stats.documents as f32
+            + confidence)
+            / (stats.documents + 1) as f32;
+        stats.documents += 1;
+        if confidence < self.min_confidence {
+            stats.low_confidence += 1;
+        }
+    }
+
+    /// Add a document that could not be processed.
+    pub fn add_failure(&mut self, processing_time_ms: u64) {
+        self.failed += 1;
+        self.times_ms.push(processing_time_ms);
+    }
+
+    /// Summarize the documents added so far.
+    pub fn summary(&self) -> StatsSummary {
+        let succeeded = self.confidences.len();
+
+        let field_coverage = COVERAGE_FIELDS
+            .iter()
+            .map(|&field| {
+                let percent = match succeeded {
+                    0 => 0.0,
+                    n => self.coverage.count(field) as f32 * 100.0 / n as f32,
+                };
+                (field.to_string(), percent)
+            })
+            .collect();
+
+        let mut warnings: Vec<WarningCount> = self.warnings.values().cloned().collect();
+        warnings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+
+        let mut suppliers: Vec<SupplierStats> = self
+            .suppliers
+            .values()
+            .filter(|s| s.low_confidence > 0)
+            .cloned()
+            .collect();
+        suppliers.sort_by(|a, b| {
+            b.low_confidence
+                .cmp(&a.low_confidence)
+                .then_with(|| a.average_confidence.total_cmp(&b.average_confidence))
+        });
+        suppliers.truncate(TOP_SUPPLIERS);
+
+        StatsSummary {
+            documents: succeeded + self.failed,
+            succeeded,
+            failed: self.failed,
+            average_confidence: (succeeded > 0)
+                .then(|| self.confidences.iter().sum::<f32>() / succeeded as f32),
+            low_confidence: self
+                .confidences
+                .iter()
+                .filter(|&&c| c < self.min_confidence)
+                .count(),
+            field_coverage,
+            warnings,
+            top_failing_suppliers: suppliers,
+            processing_time_ms: percentiles(&self.times_ms),
+        }
+    }
+}
+
+/// Nearest-rank percentiles of `values`.
+fn percentiles(values: &[u64]) -> Option<Percentiles> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let max = *sorted.last()?;
+
+    let rank = |p: usize| sorted[(p * sorted.len()).div_ceil(100).max(1) - 1];
+    Some(Percentiles {
+        p50: rank(50),
+        p90: rank(90),
+        p99: rank(99),
+        max,
+    })
+}
+
+/// Code for a warning message without an [`IssueCode`]: the text before
+/// any `:` detail, without quoted names and words containing digits, as
+/// lowercase words joined by underscores.
+pub fn warning_code(message: &str) -> String {
+    let head = message.split(':').next().unwrap_or_default();
+
+    // Drop quoted parts such as template names
+    let unquoted: Vec<&str> = head.split('\'').step_by(2).collect();
+
+    unquoted
+        .join(" ")
+        .split_whitespace()
+        .filter(|word| !word.chars().any(|c| c.is_ascii_digit()))
+        .flat_map(|word| word.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::validation::ValidationIssue;
+
+    fn invoice(nip: &str, confidence: f32, warnings: &[&str]) -> Invoice {
+        let due_date = |message: &&str| {
+            ValidationIssue::warning(IssueCode::DueDateBeforeIssueDate, "header.due_date", *message)
+        };
+        let mut invoice = Invoice::default();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.issuer.nip = Some(nip.to_string());
+        invoice.metadata.confidence = confidence;
+        invoice.metadata.warnings = warnings.iter().map(due_date).collect();
+        invoice
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut stats = BatchStats::new(0.5);
+        stats.add(&invoice("5261040828", 0.9, &[]), 100);
+        stats.add(
+            &invoice("6750000007", 0.3, &["Due date 2024-01-01 is before issue date 2024-02-01"]),
+            300,
+        );
+        stats.add(
+            &invoice("6750000007", 0.4, &["Due date 2024-03-01 is before issue date 2024-04-01"]),
+            200,
+        );
+        stats.add_failure(1000);
+
+        let summary = stats.summary();
+        assert_eq!((summary.documents, summary.succeeded, summary.failed), (4, 3, 1));
+        assert_eq!(summary.low_confidence, 2);
+        assert!((summary.average_confidence.unwrap() - 1.6 / 3.0).abs() < 1e-6);
+        assert_eq!(summary.field_coverage["invoice_number"], 100.0);
+        assert_eq!(summary.field_coverage["due_date"], 0.0);
+
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(summary.warnings[0].code, "due_date_before_issue_date");
+        assert_eq!(summary.warnings[0].count, 2);
+
+        assert_eq!(summary.top_failing_suppliers.len(), 1);
+        assert_eq!(summary.top_failing_suppliers[0].supplier, "6750000007");
+        assert_eq!(summary.top_failing_suppliers[0].low_confidence, 2);
+
+        let times = summary.processing_time_ms.unwrap();
+        assert_eq!((times.p50, times.p90, times.max), (200, 1000, 1000));
+    }
+
+    #[test]
+    fn test_warning_code() {
+        assert_eq!(warning_code("Could not extract issuer NIP"), "could_not_extract_issuer_nip");
+        assert_eq!(
+            warning_code("Template 'ACME Sp. z o.o.' not applied: unknown field 'x'"),
+            "template_not_applied"
+        );
+        assert_eq!(
+            warning_code("Line item 3 has no description (P_7)"),
+            "line_item_has_no_description"
+        );
+        assert_eq!(
+            warning_code("Ensemble extractions disagree on issuer.nip"),
+            "ensemble_extractions_disagree_on_issuer_nip"
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! Line items from recognized tables.
+//!
+//! A [`TableStructure`] whose cells were filled by OCR keeps every value in
+//! its column, so line items can be read by column instead of guessing
+//! which number on a text line is which. The header row tells what each
+//! column holds (Polish and English labels, with or without diacritics);
+//! values missing from the table (e.g. the gross amount when only net and
+//! VAT are printed) are derived from the others.
+//!
+//! Tables without a usable header can still be read when the column order
+//! is known, e.g. from a vendor template: see [`extract_text_line_items`].
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use super::rules::amounts::parse_polish_amount;
+use super::rules::annotations::{extract_gtu_codes, parse_gtu_code};
+use crate::models::invoice::{LineItem, VatRate};
+use crate::ocr::TableStructure;
+
+/// Rows searched for the header.
+const HEADER_ROWS: usize = 3;
+
+/// Labels in the header needed to accept a row as the header.
+const MIN_HEADER_COLUMNS: usize = 3;
+
+/// Meaning of a table column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// Line number (Lp.).
+    Ordinal,
+    /// Product or service name.
+    Description,
+    /// Product code (PKWiU, EAN, symbol).
+    Code,
+    /// Quantity.
+    Quantity,
+    /// Unit of measure.
+    Unit,
+    /// Net unit price.
+    UnitPriceNet,
+    /// Gross unit price.
+    UnitPriceGross,
+    /// VAT rate.
+    VatRate,
+    /// VAT amount of the line.
+    VatAmount,
+    /// Net value of the line.
+    Net,
+    /// Gross value of the line.
+    Gross,
+    /// Discount percentage.
+    Discount,
+    /// GTU group code.
+    Gtu,
+}
+
+impl Column {
+    /// The column a header label stands for, `None` if it is not known.
+    pub fn from_header(label: &str) -> Option<Self> {
+        let text = normalize(label);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let has = |keys: &[&str]| keys.iter().any(|k| text.contains(k));
+        let word = |keys: &[&str]| words.iter().any(|w| keys.contains(w));
+
+        if words.is_empty() {
+            return None;
+        }
+
+        let column = if matches!(words.as_slice(), ["lp"] | ["l", "p"] | ["nr"] | ["no"]) {
+            Column::Ordinal
+        } else if word(&["gtu"]) {
+            Column::Gtu
+        } else if has(&["rabat", "upust", "discount"]) {
+            Column::Discount
+        } else if has(&["stawka"]) || label.contains('%') {
+            Column::VatRate
+        } else if has(&["cena", "price"]) && has(&["brutto", "gross"]) {
+            Column::UnitPriceGross
+        } else if has(&["cena", "price"]) {
+            Column::UnitPriceNet
+        } else if word(&["vat", "podatek", "tax"]) {
+            Column::VatAmount
+        } else if has(&["brutto", "gross"]) {
+            Column::Gross
+        } else if has(&["netto", "wartosc", "value", "amount"]) || word(&["net"]) {
+            Column::Net
+        } else if has(&["ilosc", "quantity"]) || word(&["il", "ilo", "qty"]) {
+            Column::Quantity
+        } else if has(&["jedn", "miary", "unit"]) || word(&["jm"]) || words == ["j", "m"] {
+            Column::Unit
+        } else if has(&["pkwiu", "kod", "symbol", "indeks", "ean", "sku"]) {
+            Column::Code
+        } else if has(&["nazwa", "opis", "towar", "uslug", "produkt", "artykul"])
+            || word(&["description", "item", "name"])
+        {
+            Column::Description
+        } else {
+            return None;
+        };
+        Some(column)
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    /// Parse a column name (`unit_price_net`, `vat-rate`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let column = match s.trim().replace('-', "_").to_lowercase().as_str() {
+            "ordinal" | "lp" => Column::Ordinal,
+            "description" => Column::Description,
+            "code" => Column::Code,
+            "quantity" => Column::Quantity,
+            "unit" => Column::Unit,
+            "unit_price_net" | "unit_price" => Column::UnitPriceNet,
+            "unit_price_gross" => Column::UnitPriceGross,
+            "vat_rate" => Column::VatRate,
+            "vat_amount" | "vat" => Column::VatAmount,
+            "net" => Column::Net,
+            "gross" => Column::Gross,
+            "discount" => Column::Discount,
+            "gtu" => Column::Gtu,
+            _ => return Err(format!("unknown column '{}'", s)),
+        };
+        Ok(column)
+    }
+}
+
+/// Line items of a table with a recognizable header, in row order.
+///
+/// Rows after the header become line items until a summary row (`Razem`,
+/// `Suma`, `Total`). Rows without amounts continue the description of the
+/// item above them. Returns no items if no header is found.
+pub fn extract_line_items(table: &TableStructure) -> Vec<LineItem> {
+    let grid = table.to_grid();
+    let Some((header, columns)) = grid
+        .rows
+        .iter()
+        .take(HEADER_ROWS)
+        .enumerate()
+        .map(|(i, row)| (i, header_columns(row)))
+        .find(|(_, columns)| is_header(columns))
+    else {
+        return Vec::new();
+    };
+
+    let mut items: Vec<LineItem> = Vec::new();
+    for row in &grid.rows[header + 1..] {
+        if row.iter().any(|cell| is_summary(cell)) {
+            break;
+        }
+
+        let cell = |column: Column| {
+            columns
+                .iter()
+                .position(|c| *c == Some(column))
+                .and_then(|i| row.get(i))
+                .map(|text| text.trim())
+                .filter(|text| !text.is_empty())
+        };
+
+        match line_item(&cell) {
+            Some(item) => items.push(item),
+            None => {
+                // A description wrapped onto the next row
+                if let (Some(previous), Some(rest)) = (items.last_mut(), cell(Column::Description)) {
+                    previous.description = format!("{} {}", previous.description, rest);
+                }
+            }
+        }
+    }
+
+    items
+}
+
+/// Line items from text lines whose cells follow `columns`, left to right.
+///
+/// Cells are separated by `|`, tabs or runs of two or more spaces. Lines
+/// split by single spaces only are read word by word, the description
+/// taking the words left over. `None` columns are ignored; lines with
+/// another number of cells or without amounts are skipped, and reading
+/// stops at a summary line once items were found.
+pub fn extract_text_line_items(text: &str, columns: &[Option<Column>]) -> Vec<LineItem> {
+    let mut items = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim().trim_matches('|').trim();
+        if line.is_empty() {
+            continue;
+        }
+        if is_summary(line) {
+            if items.is_empty() {
+                continue;
+            }
+            break;
+        }
+
+        let Some(cells) = split_cells(line, columns) else {
+            continue;
+        };
+        let cell = |column: Column| {
+            columns
+                .iter()
+                .position(|c| *c == Some(column))
+                .map(|i| cells[i].as_str())
+                .filter(|text| !text.is_empty())
+        };
+        if let Some(item) = line_item(&cell) {
+            items.push(item);
+        }
+    }
+
+    items
+}
+
+/// Cells of a text line, one per column, `None` if the line does not fit.
+fn split_cells(line: &str, columns: &[Option<Column>]) -> Option<Vec<String>> {
+    let cells: Vec<String> = line
+        .split(['|', '\t'])
+        .flat_map(|part| part.split("  "))
+        .map(str::trim)
+        .filter(|cell| !cell.is_empty())
+        .map(str::to_string)
+        .collect();
+    if cells.len() == columns.len() {
+        return Some(cells);
+    }
+
+    // Single-space separated: the description takes the extra words
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let description = columns.iter().position(|c| *c == Some(Column::Description))?;
+    let extra = words.len().checked_sub(columns.len())?;
+    let end = description + extra + 1;
+
+    let mut cells: Vec<String> = words[..description].iter().map(|w| w.to_string()).collect();
+    cells.push(words[description..end].join(" "));
+    cells.extend(words[end..].iter().map(|w| w.to_string()));
+    Some(cells)
+}
+
+/// Column of each header cell; a column type is only assigned once.
+fn header_columns(row: &[String]) -> Vec<Option<Column>> {
+    let mut columns: Vec<Option<Column>> = Vec::with_capacity(row.len());
+    for label in row {
+        let column = Column::from_header(label).filter(|c| !columns.contains(&Some(*c)));
+        columns.push(column);
+    }
+    columns
+}
+
+fn is_header(columns: &[Option<Column>]) -> bool {
+    let amount = columns
+        .iter()
+        .any(|c| matches!(c, Some(Column::Net | Column::Gross | Column::UnitPriceNet)));
+    amount && columns.iter().flatten().count() >= MIN_HEADER_COLUMNS
+}
+
+fn is_summary(cell: &str) -> bool {
+    let text = normalize(cell);
+    ["razem", "suma", "ogolem", "total", "w tym"]
+        .iter()
+        .any(|label| text.starts_with(label))
+}
+
+/// A line item from the cells of a row, `None` if it has no amounts.
+fn line_item<'a>(cell: &impl Fn(Column) -> Option<&'a str>) -> Option<LineItem> {
+    let amount = |column: Column| cell(column).and_then(parse_amount);
+
+    // A "VAT" column may hold rates rather than amounts
+    let (vat_rate, vat_amount) = match cell(Column::VatAmount) {
+        Some(text) if text.contains('%') || !text.chars().any(|c| c.is_ascii_digit()) => {
+            (VatRate::from_str(text), None)
+        }
+        _ => (cell(Column::VatRate).and_then(VatRate::from_str), amount(Column::VatAmount)),
+    };
+
+    let quantity = amount(Column::Quantity).filter(|q| !q.is_zero());
+    let unit_price_net = amount(Column::UnitPriceNet);
+    let unit_price_gross = amount(Column::UnitPriceGross);
+    let mut net = amount(Column::Net);
+    let mut gross = amount(Column::Gross);
+
+    if net.is_none() && gross.is_none() && unit_price_net.is_none() && unit_price_gross.is_none() {
+        return None;
+    }
+
+    let quantity_or_one = quantity.unwrap_or(Decimal::ONE);
+    let rate = vat_rate.map(|r| r.as_decimal());
+
+    net = net
+        .or_else(|| Some(unit_price_net? * quantity_or_one))
+        .or_else(|| match (gross, vat_amount, rate) {
+            (Some(gross), Some(vat), _) => Some(gross - vat),
+            (Some(gross), None, Some(rate)) => Some(gross / (Decimal::ONE + rate)),
+            _ => None,
+        });
+    gross = gross
+        .or_else(|| Some(unit_price_gross? * quantity_or_one))
+        .or_else(|| match (net, vat_amount, rate) {
+            (Some(net), Some(vat), _) => Some(net + vat),
+            (Some(net), None, Some(rate)) => Some(net * (Decimal::ONE + rate)),
+            _ => None,
+        });
+    let net = net.or(gross)?.round_dp(2);
+    let gross = gross.unwrap_or(net).round_dp(2);
+
+    let vat_rate = vat_rate
+        .or_else(|| rate_of(net, vat_amount.unwrap_or(gross - net)))
+        .unwrap_or(VatRate::Standard23);
+
+    Some(LineItem {
+        ordinal: cell(Column::Ordinal).and_then(|text| text.trim_end_matches('.').parse().ok()),
+        description: cell(Column::Description).unwrap_or("Item").to_string(),
+        code: cell(Column::Code).map(str::to_string),
+        quantity: quantity_or_one,
+        unit: cell(Column::Unit).map(str::to_string),
+        unit_price_net: unit_price_net.unwrap_or_else(|| (net / quantity_or_one).round_dp(2)),
+        unit_price_gross,
+        vat_rate,
+        total_net: net,
+        vat_amount: vat_amount.unwrap_or(gross - net),
+        total_gross: gross,
+        discount_percent: amount(Column::Discount),
+        gtu_codes: match cell(Column::Gtu) {
+            Some(text) => text.split([',', ';']).filter_map(parse_gtu_code).collect(),
+            None => cell(Column::Description).map(extract_gtu_codes).unwrap_or_default(),
+        },
+    })
+}
+
+/// Amount in a cell, ignoring a trailing currency or unit (`12,50 zł`, `2 szt.`).
+fn parse_amount(text: &str) -> Option<Decimal> {
+    let text = text.trim_end_matches(|c: char| !c.is_ascii_digit());
+    if text.is_empty() {
+        return None;
+    }
+    parse_polish_amount(text)
+}
+
+/// The standard rate closest to `vat / net`, if it is within a percentage point.
+fn rate_of(net: Decimal, vat: Decimal) -> Option<VatRate> {
+    if net.is_zero() {
+        return None;
+    }
+    let percent = vat / net * Decimal::ONE_HUNDRED;
+    [VatRate::Standard23, VatRate::Reduced8, VatRate::Reduced5, VatRate::Zero]
+        .into_iter()
+        .find(|rate| (rate.as_decimal() * Decimal::ONE_HUNDRED - percent).abs() < Decimal::ONE)
+}
+
+/// Lowercase ASCII words of a label: Polish letters lose their diacritics
+/// and punctuation becomes spaces (`Wartość netto` -> `wartosc netto`).
+fn normalize(label: &str) -> String {
+    label
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ą' => 'a',
+            'ć' => 'c',
+            'ę' => 'e',
+            'ł' => 'l',
+            'ń' => 'n',
+            'ó' => 'o',
+            'ś' => 's',
+            'ź' | 'ż' => 'z',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::TableCell;
+
+    fn table(rows: &[&[&str]]) -> TableStructure {
+        let cells = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| {
+                cells.iter().enumerate().map(move |(col, content)| TableCell {
+                    row,
+                    col,
+                    row_span: 1,
+                    col_span: 1,
+                    bbox: [0.0; 4],
+                    content: content.to_string(),
+                    confidence: 1.0,
+                })
+            })
+            .collect();
+
+        TableStructure {
+            num_rows: rows.len(),
+            num_cols: rows.iter().map(|r| r.len()).max().unwrap_or(0),
+            cells,
+            html: String::new(),
+            bbox: [0.0; 4],
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_column_from_header() {
+        assert_eq!(Column::from_header("Lp."), Some(Column::Ordinal));
+        assert_eq!(Column::from_header("Nazwa towaru lub usługi"), Some(Column::Description));
+        assert_eq!(Column::from_header("Ilosc"), Some(Column::Quantity));
+        assert_eq!(Column::from_header("J.m."), Some(Column::Unit));
+        assert_eq!(Column::from_header("Cena jedn. netto"), Some(Column::UnitPriceNet));
+        assert_eq!(Column::from_header("Stawka VAT"), Some(Column::VatRate));
+        assert_eq!(Column::from_header("Kwota VAT"), Some(Column::VatAmount));
+        assert_eq!(Column::from_header("Wartość netto"), Some(Column::Net));
+        assert_eq!(Column::from_header("Wartość brutto"), Some(Column::Gross));
+        assert_eq!(Column::from_header("PKWiU"), Some(Column::Code));
+        assert_eq!(Column::from_header("Kod GTU"), Some(Column::Gtu));
+        assert_eq!(Column::from_header("Uwagi"), None);
+    }
+
+    #[test]
+    fn test_extract_line_items() {
+        let table = table(&[
+            &["Faktura VAT FV/1/2024"],
+            &["Lp.", "Nazwa", "Ilość", "J.m.", "Cena netto", "Stawka", "Wartość netto", "VAT", "Brutto"],
+            &["1", "Usługa programistyczna", "10", "godz.", "150,00", "23%", "1 500,00", "345,00", "1 845,00"],
+            &["2", "Licencja", "1", "szt.", "200,00", "8%", "200,00", "16,00", "216,00"],
+            &["", "roczna", "", "", "", "", "", "", ""],
+            &["Razem", "", "", "", "", "", "1 700,00", "361,00", "2 061,00"],
+        ]);
+
+        let items = extract_line_items(&table);
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(items[0].ordinal, Some(1));
+        assert_eq!(items[0].description, "Usługa programistyczna");
+        assert_eq!(items[0].quantity, Decimal::from(10));
+        assert_eq!(items[0].unit.as_deref(), Some("godz."));
+        assert_eq!(items[0].unit_price_net, Decimal::new(15000, 2));
+        assert_eq!(items[0].vat_rate, VatRate::Standard23);
+        assert_eq!(items[0].total_net, Decimal::new(150000, 2));
+        assert_eq!(items[0].vat_amount, Decimal::new(34500, 2));
+        assert_eq!(items[0].total_gross, Decimal::new(184500, 2));
+
+        assert_eq!(items[1].description, "Licencja roczna");
+        assert_eq!(items[1].vat_rate, VatRate::Reduced8);
+    }
+
+    #[test]
+    fn test_derived_amounts() {
+        let table = table(&[
+            &["Opis", "Ilość", "Cena", "VAT"],
+            &["Abonament", "2", "50,00 zł", "23%"],
+        ]);
+
+        let items = extract_line_items(&table);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].total_net, Decimal::new(10000, 2));
+        assert_eq!(items[0].vat_rate, VatRate::Standard23);
+        assert_eq!(items[0].vat_amount, Decimal::new(2300, 2));
+        assert_eq!(items[0].total_gross, Decimal::new(12300, 2));
+    }
+
+    #[test]
+    fn test_extract_text_line_items() {
+        let columns: Vec<Option<Column>> = ["ordinal", "description", "quantity", "unit", "gross"]
+            .iter()
+            .map(|name| Some(name.parse().unwrap()))
+            .collect();
+        let text = "Pozycje zamówienia\n\
+            1 | Abonament internetowy | 2 | mc | 123,00\n\
+            2  Instalacja  1  szt.  61,50\n\
+            3 Router Wi-Fi 6 1 szt. 246,00\n\
+            Razem: 430,50\n\
+            4 | Po podsumowaniu | 1 | szt. | 1,00\n";
+
+        let items = extract_text_line_items(text, &columns);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].description, "Abonament internetowy");
+        assert_eq!(items[0].quantity, Decimal::from(2));
+        assert_eq!(items[0].total_gross, Decimal::new(12300, 2));
+        assert_eq!(items[1].unit.as_deref(), Some("szt."));
+        assert_eq!(items[2].ordinal, Some(3));
+        assert_eq!(items[2].description, "Router Wi-Fi 6");
+        assert_eq!(items[2].total_gross, Decimal::new(24600, 2));
+
+        assert!("price".parse::<Column>().is_err());
+    }
+
+    #[test]
+    fn test_no_header() {
+        let table = table(&[&["Sprzedawca", "Nabywca"], &["ABC", "XYZ"]]);
+        assert!(extract_line_items(&table).is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+//! Vendor templates applied on top of extraction.
+//!
+//! A template is matched by issuer NIP or, when the NIP is misread, by
+//! keywords from the vendor's documents. Besides fixed values it can carry
+//! its own field patterns and the column order of the vendor's line item
+//! table, for layouts the built-in rules get wrong.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::ExtractionError;
+use crate::models::config::{FieldPattern, VendorTemplate};
+use crate::models::invoice::Invoice;
+
+use super::field::{FieldKind, FieldValue};
+use super::patch::{collect_leaves, pointer, set_path, InvoicePatch, MergeReport, PatchRole};
+use super::rules::amounts::parse_polish_amount;
+use super::rules::dates::DateExtractor;
+use super::rules::ksef::validate_ksef_number;
+use super::rules::FieldExtractor;
+use super::table_items::{extract_text_line_items, Column};
+use super::{ExtractionResult, Result};
+
+/// Find the template for the invoice issuer, matching NIPs by digits only.
+pub fn find_template<'a>(templates: &'a [VendorTemplate], invoice: &Invoice) -> Option<&'a VendorTemplate> {
+    let nip = digits(invoice.issuer.nip.as_deref()?);
+    if nip.is_empty() {
+        return None;
+    }
+
+    templates.iter().find(|t| digits(&t.nip) == nip)
+}
+
+/// Find the template for an invoice read from `text`: by issuer NIP, or
+/// else the first template whose keywords all appear in the text.
+pub fn match_template<'a>(
+    templates: &'a [VendorTemplate],
+    invoice: &Invoice,
+    text: &str,
+) -> Option<&'a VendorTemplate> {
+    find_template(templates, invoice).or_else(|| {
+        let text = text.to_lowercase();
+        templates.iter().find(|t| {
+            !t.keywords.is_empty() && t.keywords.iter().all(|k| text.contains(&k.to_lowercase()))
+        })
+    })
+}
+
+impl ExtractionResult {
+    /// Apply a vendor template.
+    ///
+    /// Locked values always replace extracted ones; defaults only fill
+    /// fields that are missing or whose confidence is below
+    /// `min_confidence`. Values found by the template's field patterns and
+    /// line items read in its column order replace extracted ones too,
+    /// unless locked. Applied fields are recorded in the provenance as
+    /// [`PatchRole::Template`] with the template name as author, and never
+    /// override human-confirmed values.
+    pub fn apply_template(&mut self, template: &VendorTemplate, min_confidence: f32) -> Result<MergeReport> {
+        let current = serde_json::to_value(&self.invoice).unwrap_or_default();
+        let mut changes = Value::Object(Map::new());
+
+        collect_leaves(&template.defaults, "", &mut |field, value| {
+            let missing = match current.pointer(&pointer(&field)) {
+                None | Some(Value::Null) => true,
+                Some(Value::String(s)) => s.trim().is_empty(),
+                Some(Value::Array(items)) => items.is_empty(),
+                Some(_) => false,
+            };
+            let uncertain = self
+                .invoice
+                .metadata
+                .field_confidence
+                .get(&field)
+                .is_some_and(|c| *c < min_confidence);
+
+            if missing || uncertain {
+                set_path(&mut changes, &field, value.clone());
+            }
+        });
+
+        for (name, pattern) in &template.fields {
+            let field: FieldKind = name.parse().map_err(ExtractionError::InvalidTemplate)?;
+            let value = find_field(pattern, &self.raw_text)
+                .map_err(|e| ExtractionError::InvalidTemplate(format!("field '{}': {}", name, e)))?
+                .and_then(|found| field_value(field, &found));
+            if let Some(value) = value {
+                let value = serde_json::to_value(value).unwrap_or_default();
+                set_path(&mut changes, field.path(), value);
+            }
+        }
+
+        if !template.columns.is_empty() {
+            let columns = template
+                .columns
+                .iter()
+                .map(|name| match name.as_str() {
+                    "skip" => Ok(None),
+                    name => name.parse::<Column>().map(Some),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(ExtractionError::InvalidTemplate)?;
+
+            let items = extract_text_line_items(&self.raw_text, &columns);
+            if !items.is_empty() {
+                let items = serde_json::to_value(items).unwrap_or_default();
+                set_path(&mut changes, "line_items", items);
+            }
+        }
+
+        collect_leaves(&template.locked, "", &mut |field, value| {
+            set_path(&mut changes, &field, value.clone());
+        });
+
+        let patch = InvoicePatch::new(changes)?
+            .with_role(PatchRole::Template)
+            .with_author(&template.name);
+
+        self.apply_corrections(patch)
+    }
+}
+
+/// Text matched by a field pattern within its region, `None` if the
+/// region or the pattern is not found.
+fn find_field(pattern: &FieldPattern, text: &str) -> std::result::Result<Option<String>, regex::Error> {
+    let mut region = text;
+
+    if let Some(after) = &pattern.after {
+        match Regex::new(after)?.find(region) {
+            Some(found) => region = &region[found.end()..],
+            None => return Ok(None),
+        }
+    }
+    let end = match &pattern.before {
+        Some(before) => Regex::new(before)?.find(region).map(|found| found.start()),
+        None => None,
+    };
+    if let Some(end) = end {
+        region = &region[..end];
+    }
+
+    let found = Regex::new(&pattern.pattern)?.captures(region).and_then(|caps| {
+        caps.get(1)
+            .or_else(|| caps.get(0))
+            .map(|m| m.as_str().trim().to_string())
+    });
+    Ok(found.filter(|value| !value.is_empty()))
+}
+
+/// A matched value in the field's type, `None` if it does not parse.
+fn field_value(field: FieldKind, found: &str) -> Option<FieldValue> {
+    match field {
+        FieldKind::IssueDate | FieldKind::SaleDate | FieldKind::DueDate => {
+            DateExtractor::new().extract(found).map(|m| FieldValue::Date(m.value))
+        }
+        FieldKind::TotalNet | FieldKind::TotalVat | FieldKind::TotalGross => {
+            let amount = found.trim_end_matches(|c: char| !c.is_ascii_digit());
+            parse_polish_amount(amount).map(FieldValue::Amount)
+        }
+        FieldKind::IssuerNip | FieldKind::ReceiverNip => {
+            Some(digits(found)).filter(|nip| nip.len() == 10).map(FieldValue::Text)
+        }
+        FieldKind::IssuerBankAccount => {
+            let account: String = found.chars().filter(|c| !c.is_whitespace()).collect();
+            Some(FieldValue::Text(account.to_uppercase()))
+        }
+        FieldKind::KsefNumber => {
+            let number: String = found.chars().filter(|c| !c.is_whitespace()).collect();
+            Some(number.to_uppercase()).filter(|n| validate_ksef_number(n)).map(FieldValue::Text)
+        }
+        FieldKind::InvoiceNumber | FieldKind::IssuerName | FieldKind::ReceiverName => {
+            Some(FieldValue::Text(found.to_string()))
+        }
+    }
+}
+
+pub(super) fn digits(value: &str) -> String {
+    value.chars().filter(char::is_ascii_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoice::patch::FieldProvenance;
+    use crate::models::invoice::PaymentMethod;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn template() -> VendorTemplate {
+        let value = json!({
+            "name": "acme",
+            "nip": "526-025-02-74",
+            "locked": { "header": { "currency": "EUR" } },
+            "defaults": { "summary": { "payment_method": "transfer" }, "issuer": { "name": "ACME" } }
+        });
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn result(invoice: Invoice) -> ExtractionResult {
+        ExtractionResult {
+            invoice,
+            raw_text: String::new(),
+            warnings: Vec::new(),
+            processing_time_ms: 0,
+            provenance: BTreeMap::new(),
+            candidates: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_locked_and_default_values() {
+        let mut invoice = Invoice::default();
+        invoice.issuer.nip = Some("5260250274".to_string());
+        invoice.issuer.name = "ACME Sp. z o.o.".to_string();
+
+        let templates = vec![template()];
+        let template = find_template(&templates, &invoice).unwrap();
+
+        let mut result = result(invoice);
+        let report = result.apply_template(template, 0.5).unwrap();
+
+        assert_eq!(report.applied, vec!["header.currency", "summary.payment_method"]);
+        assert_eq!(result.invoice.header.currency, "EUR");
+        assert_eq!(result.invoice.summary.payment_method, Some(PaymentMethod::Transfer));
+        assert_eq!(result.invoice.issuer.name, "ACME Sp. z o.o.");
+
+        let provenance = &result.provenance["header.currency"];
+        assert_eq!(provenance.role, PatchRole::Template);
+        assert_eq!(provenance.author.as_deref(), Some("acme"));
+        assert!(!provenance.is_human_confirmed());
+    }
+
+    #[test]
+    fn test_human_value_kept() {
+        let mut invoice = Invoice::default();
+        invoice.issuer.nip = Some("5260250274".to_string());
+        let mut result = result(invoice);
+
+        let human = InvoicePatch::new(json!({ "header": { "currency": "USD" } })).unwrap();
+        result.apply_corrections(human).unwrap();
+
+        let report = result.apply_template(&template(), 0.5).unwrap();
+        assert_eq!(result.invoice.header.currency, "USD");
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(result.provenance.values().any(FieldProvenance::is_human_confirmed));
+    }
+
+    #[test]
+    fn test_field_patterns_and_columns() {
+        let telekom: VendorTemplate = toml::from_str(
+            r#"
+            name = "telekom"
+            keywords = ["Telekom Polska", "Numer klienta"]
+            columns = ["description", "skip", "net", "vat_rate", "gross"]
+
+            [fields.invoice_number]
+            pattern = 'Dokument:\s*(\S+)'
+
+            [fields.due_date]
+            pattern = '(\d{2}\.\d{2}\.\d{4})'
+            after = 'Płatność'
+
+            [fields.total_gross]
+            pattern = 'Do zapłaty:\s*([\d ]+,\d{2})'
+            "#,
+        )
+        .unwrap();
+
+        let text = "TELEKOM POLSKA S.A.\nNumer klienta: 1234\n\
+            Dokument: TP/2024/01/0042 z dnia 02.01.2024\n\
+            Internet  1 mc  100,00  23%  123,00\n\
+            Telewizja  1 mc  50,00  23%  61,50\n\
+            Razem  150,00  34,50  184,50\n\
+            Płatność przelewem do 16.01.2024\nDo zapłaty: 184,50 zł\n";
+
+        let mut result = result(Invoice::default());
+        result.raw_text = text.to_string();
+        let templates = vec![template()];
+        assert!(match_template(&templates, &result.invoice, text).is_none());
+
+        let templates = vec![template(), telekom];
+        let matched = match_template(&templates, &result.invoice, text).unwrap();
+        assert_eq!(matched.name, "telekom");
+
+        result.apply_template(matched, 0.5).unwrap();
+        let invoice = &result.invoice;
+        assert_eq!(invoice.header.invoice_number, "TP/2024/01/0042");
+        assert_eq!(invoice.header.due_date, chrono::NaiveDate::from_ymd_opt(2024, 1, 16));
+        assert_eq!(invoice.summary.total_gross, rust_decimal::Decimal::new(18450, 2));
+        assert_eq!(invoice.line_items.len(), 2);
+        assert_eq!(invoice.line_items[1].description, "Telewizja");
+        assert_eq!(invoice.line_items[1].total_net, rust_decimal::Decimal::new(5000, 2));
+        assert_eq!(result.provenance["line_items"].role, PatchRole::Template);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let mut template = template();
+        template.fields.insert(
+            "invoice_number".to_string(),
+            FieldPattern { pattern: "Nr (".to_string(), ..Default::default() },
+        );
+
+        let mut result = result(Invoice::default());
+        let error = result.apply_template(&template, 0.5).unwrap_err();
+        assert!(error.to_string().contains("field 'invoice_number'"));
+    }
+
+    #[test]
+    fn test_no_template_for_other_vendor() {
+        let mut invoice = Invoice::default();
+        invoice.issuer.nip = Some("1234563218".to_string());
+        assert!(find_template(&[template()], &invoice).is_none());
+    }
+}
@@ -0,0 +1,594 @@
+//! Export of invoices as a JPK_FA(4) audit file.
+//!
+//! JPK_FA (Jednolity Plik Kontrolny dla faktur) is the XML file a taxpayer
+//! hands over to the tax office during an audit: a header naming the
+//! period and the office (`Naglowek`), the taxpayer (`Podmiot1`), one
+//! `Faktura` element per issued invoice with its per-rate totals
+//! (`P_13_*`, `P_14_*`) and one `FakturaWiersz` element per line item,
+//! each list followed by its control sums.
+//!
+//! Taxpayer details that invoices don't carry come from [`JpkConfig`].
+//! Proforma invoices are not VAT documents and are left out.
+//!
+//! The file is written from invoices already extracted, so it can be
+//! built from stored JSON results as well as by `incr batch --format
+//! jpk-fa`. Signing and submitting it is left to the tax office's tools.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+
+use crate::error::JpkError;
+use crate::export::xml::Xml;
+use crate::models::config::JpkConfig;
+use crate::models::invoice::{Invoice, InvoiceType, Party, VatRate};
+use crate::validate::split_vat_id;
+
+const NAMESPACE: &str = "http://jpk.mf.gov.pl/wzor/2022/02/17/02171/";
+const ETD_NAMESPACE: &str =
+    "http://crd.gov.pl/xml/schematy/dziedzinowe/mf/2021/06/08/eD/DefinicjeTypy/";
+
+/// Net (`P_13_*`) and VAT (`P_14_*`) totals per rate, in schema order.
+/// Buckets without a VAT element hold untaxed sales.
+const BUCKETS: [(&str, Option<&str>); 11] = [
+    ("P_13_1", Some("P_14_1")),
+    ("P_13_2", Some("P_14_2")),
+    ("P_13_3", Some("P_14_3")),
+    ("P_13_4", Some("P_14_4")),
+    ("P_13_5", Some("P_14_5")),
+    ("P_13_6", None),
+    ("P_13_7", None),
+    ("P_13_8", None),
+    ("P_13_9", None),
+    ("P_13_10", None),
+    ("P_13_11", None),
+];
+
+/// Builder of JPK_FA documents.
+///
+/// ```
+/// use incr_core::jpk::JpkFa;
+/// use incr_core::models::config::JpkConfig;
+///
+/// let settings = JpkConfig {
+///     tax_office: Some("1471".to_string()),
+///     ..Default::default()
+/// };
+/// let result = JpkFa::new(settings).write(&[]);
+/// assert!(result.is_err()); // nothing to export
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JpkFa {
+    settings: JpkConfig,
+    period: Option<(NaiveDate, NaiveDate)>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl JpkFa {
+    /// Create a builder with the taxpayer details from `settings`.
+    pub fn new(settings: JpkConfig) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    /// Set the period covered by the file (`DataOd`, `DataDo`).
+    ///
+    /// Defaults to the range of the invoices' issue dates.
+    pub fn with_period(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.period = Some((from, to));
+        self
+    }
+
+    /// Set the creation time of the file (default: now).
+    pub fn with_created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Write `invoices` as a JPK_FA document.
+    ///
+    /// Every exported invoice needs an issue date; the seller of the first
+    /// one fills in taxpayer details missing from the settings.
+    pub fn write(&self, invoices: &[Invoice]) -> Result<String, JpkError> {
+        let invoices: Vec<&Invoice> = invoices
+            .iter()
+            .filter(|invoice| invoice.header.invoice_type != InvoiceType::Proforma)
+            .collect();
+        let first = invoices.first().ok_or(JpkError::NoInvoices)?;
+
+        let mut dates = Vec::with_capacity(invoices.len());
+        for invoice in &invoices {
+            let date = invoice.header.issue_date.ok_or_else(|| JpkError::MissingField {
+                invoice: invoice.header.invoice_number.clone(),
+                field: "issue date",
+            })?;
+            dates.push(date);
+        }
+
+        let tax_office = self
+            .settings
+            .tax_office
+            .as_deref()
+            .ok_or(JpkError::MissingSetting("tax_office"))?;
+        if tax_office.len() != 4 || !tax_office.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(JpkError::InvalidTaxOffice(tax_office.to_string()));
+        }
+
+        let (from, to) = match self.period {
+            Some(period) => period,
+            None => (
+                dates.iter().min().copied().unwrap_or_default(),
+                dates.iter().max().copied().unwrap_or_default(),
+            ),
+        };
+        let created_at = self.created_at.unwrap_or_else(Utc::now);
+
+        let mut xml = Xml::default();
+        xml.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.line(&format!(
+            r#"<JPK xmlns="{}" xmlns:etd="{}">"#,
+            NAMESPACE, ETD_NAMESPACE
+        ));
+        xml.depth += 1;
+
+        xml.open("Naglowek");
+        xml.line(r#"<KodFormularza kodSystemowy="JPK_FA (4)" wersjaSchemy="1-0">JPK_FA</KodFormularza>"#);
+        xml.leaf("WariantFormularza", "4");
+        xml.leaf("CelZlozenia", "1");
+        xml.leaf(
+            "DataWytworzeniaJPK",
+            &created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        );
+        xml.leaf("DataOd", &from.to_string());
+        xml.leaf("DataDo", &to.to_string());
+        xml.leaf("KodUrzedu", tax_office);
+        xml.close("Naglowek");
+
+        self.write_subject(&mut xml, &first.issuer)?;
+
+        let mut invoices_total = Decimal::ZERO;
+        for (invoice, date) in invoices.iter().zip(&dates) {
+            write_invoice(&mut xml, invoice, *date);
+            invoices_total += invoice.summary.total_gross;
+        }
+
+        xml.open("FakturaCtrl");
+        xml.leaf("LiczbaFaktur", &invoices.len().to_string());
+        xml.leaf("WartoscFaktur", &amount(invoices_total));
+        xml.close("FakturaCtrl");
+
+        let mut rows = 0;
+        let mut rows_total = Decimal::ZERO;
+        for invoice in &invoices {
+            for item in &invoice.line_items {
+                xml.open("FakturaWiersz");
+                xml.leaf("P_2B", &invoice.header.invoice_number);
+                xml.leaf("P_7", &item.description);
+                if let Some(unit) = &item.unit {
+                    xml.leaf("P_8A", unit);
+                }
+                xml.leaf("P_8B", &item.quantity.normalize().to_string());
+                xml.leaf("P_9A", &amount(item.unit_price_net));
+                if let Some(price) = item.unit_price_gross {
+                    xml.leaf("P_9B", &amount(price));
+                }
+                xml.leaf("P_11", &amount(item.total_net));
+                if !item.total_gross.is_zero() {
+                    xml.leaf("P_11A", &amount(item.total_gross));
+                }
+                xml.leaf("P_12", &rate_code(item.vat_rate));
+                xml.close("FakturaWiersz");
+
+                rows += 1;
+                rows_total += item.total_net;
+            }
+        }
+
+        xml.open("FakturaWierszCtrl");
+        xml.leaf("LiczbaWierszyFaktur", &rows.to_string());
+        xml.leaf("WartoscWierszyFaktur", &amount(rows_total));
+        xml.close("FakturaWierszCtrl");
+
+        xml.depth -= 1;
+        xml.line("</JPK>");
+        Ok(xml.output)
+    }
+
+    /// Write `Podmiot1`, falling back to `seller` for unset details.
+    fn write_subject(&self, xml: &mut Xml, seller: &Party) -> Result<(), JpkError> {
+        let settings = &self.settings;
+        let (street, house_number, flat_number) = split_street(seller.address.street.as_deref());
+
+        let nip = settings.nip.clone().or_else(|| seller.nip.clone());
+        let name = settings
+            .name
+            .clone()
+            .or_else(|| Some(seller.name.clone()).filter(|name| !name.is_empty()));
+        let street = settings.street.clone().or(street);
+        let house_number = settings.house_number.clone().or(house_number);
+        let flat_number = settings.flat_number.clone().or(flat_number);
+        let city = settings.city.clone().or_else(|| seller.address.city.clone());
+        let postal_code = settings
+            .postal_code
+            .clone()
+            .or_else(|| seller.address.postal_code.clone());
+
+        let nip = nip.ok_or(JpkError::MissingSetting("nip"))?;
+        let name = name.ok_or(JpkError::MissingSetting("name"))?;
+        let voivodeship = required(&settings.voivodeship, "voivodeship")?;
+        let county = required(&settings.county, "county")?;
+        let commune = required(&settings.commune, "commune")?;
+        let house_number = house_number.ok_or(JpkError::MissingSetting("house_number"))?;
+        let city = city.ok_or(JpkError::MissingSetting("city"))?;
+        let postal_code = postal_code.ok_or(JpkError::MissingSetting("postal_code"))?;
+
+        xml.open("Podmiot1");
+        xml.open("IdentyfikatorPodmiotu");
+        xml.leaf("etd:NIP", &digits(&nip));
+        xml.leaf("etd:PelnaNazwa", &name);
+        xml.close("IdentyfikatorPodmiotu");
+        xml.open("AdresPodmiotu");
+        xml.leaf("KodKraju", "PL");
+        xml.leaf("Wojewodztwo", voivodeship);
+        xml.leaf("Powiat", county);
+        xml.leaf("Gmina", commune);
+        if let Some(street) = &street {
+            xml.leaf("Ulica", street);
+        }
+        xml.leaf("NrDomu", &house_number);
+        if let Some(flat_number) = &flat_number {
+            xml.leaf("NrLokalu", flat_number);
+        }
+        xml.leaf("Miejscowosc", &city);
+        xml.leaf("KodPocztowy", &postal_code);
+        xml.close("AdresPodmiotu");
+        xml.close("Podmiot1");
+        Ok(())
+    }
+}
+
+/// Write the `Faktura` element of `invoice` issued on `date`.
+fn write_invoice(xml: &mut Xml, invoice: &Invoice, date: NaiveDate) {
+    let header = &invoice.header;
+    let margin = header.invoice_type == InvoiceType::Margin;
+
+    xml.open("Faktura");
+    xml.leaf("KodWaluty", &header.currency);
+    xml.leaf("P_1", &date.to_string());
+    xml.leaf("P_2A", &header.invoice_number);
+    xml.leaf("P_3A", &invoice.receiver.name);
+    xml.leaf("P_3B", &invoice.receiver.address.format());
+    xml.leaf("P_3C", &invoice.issuer.name);
+    xml.leaf("P_3D", &invoice.issuer.address.format());
+    if let Some(nip) = &invoice.issuer.nip {
+        xml.leaf("P_4B", &digits(nip));
+    }
+    if let Some(nip) = &invoice.receiver.nip {
+        xml.leaf("P_5B", &digits(nip));
+    } else if let Some((country, number)) = invoice.receiver.vat_id.as_deref().and_then(split_vat_id)
+    {
+        xml.leaf("P_5A", &country);
+        xml.leaf("P_5B", &number);
+    }
+    if let Some(sale_date) = header.sale_date.filter(|sale_date| *sale_date != date) {
+        xml.leaf("P_6", &sale_date.to_string());
+    }
+
+    let mut totals: [Option<(Decimal, Decimal)>; BUCKETS.len()] = [None; BUCKETS.len()];
+    let mut add = |rate: VatRate, net: Decimal, vat: Decimal| {
+        let bucket = if margin { 10 } else { bucket_of(rate) };
+        let (total_net, total_vat) = totals[bucket].get_or_insert((Decimal::ZERO, Decimal::ZERO));
+        *total_net += net;
+        *total_vat += vat;
+    };
+    if !invoice.summary.vat_breakdown.is_empty() {
+        for row in &invoice.summary.vat_breakdown {
+            add(row.rate, row.net, row.vat);
+        }
+    } else {
+        for item in &invoice.line_items {
+            add(item.vat_rate, item.total_net, item.vat_amount);
+        }
+    }
+
+    for ((net_tag, vat_tag), total) in BUCKETS.iter().zip(totals) {
+        let Some((net, vat)) = total else {
+            continue;
+        };
+        xml.leaf(net_tag, &amount(net));
+        if let Some(vat_tag) = vat_tag {
+            xml.leaf(vat_tag, &amount(vat));
+        }
+    }
+    xml.leaf("P_15", &amount(invoice.summary.total_gross));
+
+    let has_rate = |rate: VatRate| {
+        invoice.summary.vat_breakdown.iter().any(|row| row.rate == rate)
+            || invoice.line_items.iter().any(|item| item.vat_rate == rate)
+    };
+    xml.leaf("P_16", "false");
+    // Self-billing (samofakturowanie) isn't extracted; `self_invoice` marks
+    // invoices between units of one taxpayer, which are not self-billed
+    xml.leaf("P_17", "false");
+    xml.leaf("P_18", flag(has_rate(VatRate::ReverseCharge)));
+    xml.leaf("P_18A", flag(invoice.summary.split_payment));
+    xml.leaf("P_19", flag(has_rate(VatRate::Exempt)));
+    xml.leaf("P_20", "false");
+    xml.leaf("P_21", "false");
+    xml.leaf("P_22", "false");
+    xml.leaf("P_23", "false");
+    xml.leaf("P_106E_2", "false");
+    xml.leaf("P_106E_3", flag(margin));
+
+    match header.invoice_type {
+        InvoiceType::Correction => {
+            xml.leaf("RodzajFaktury", "KOREKTA");
+            if let Some(corrected) = &header.correction_of {
+                xml.leaf("NrFaKorygowanej", corrected);
+            }
+        }
+        InvoiceType::Advance => xml.leaf("RodzajFaktury", "ZAL"),
+        _ => xml.leaf("RodzajFaktury", "VAT"),
+    }
+    xml.close("Faktura");
+}
+
+/// Index into [`BUCKETS`] of sales at `rate`.
+fn bucket_of(rate: VatRate) -> usize {
+    match rate {
+        VatRate::Standard23 | VatRate::Other(22) => 0,
+        VatRate::Reduced8 | VatRate::Other(7) => 1,
+        VatRate::Reduced5 => 2,
+        VatRate::Other(3 | 4) => 3,
+        // Rates of other EU countries (OSS procedure)
+        VatRate::Other(_) => 4,
+        VatRate::Zero => 5,
+        VatRate::Exempt => 6,
+        VatRate::NotApplicable => 7,
+        VatRate::ReverseCharge => 9,
+    }
+}
+
+/// `P_12` code of `rate`.
+fn rate_code(rate: VatRate) -> String {
+    match rate {
+        VatRate::Exempt => "zw".to_string(),
+        VatRate::NotApplicable => "np".to_string(),
+        VatRate::ReverseCharge => "oo".to_string(),
+        rate => (rate.as_decimal() * Decimal::ONE_HUNDRED).normalize().to_string(),
+    }
+}
+
+/// Split `ul. Długa 5/3` or `Polna 12 m. 4` into street, house and flat
+/// number.
+fn split_street(street: Option<&str>) -> (Option<String>, Option<String>, Option<String>) {
+    let Some(street) = street.map(str::trim).filter(|s| !s.is_empty()) else {
+        return (None, None, None);
+    };
+    let starts_with_digit = |token: &&str| token.starts_with(|c: char| c.is_ascii_digit());
+
+    let mut tokens: Vec<&str> = street.split_whitespace().collect();
+    let flat = match tokens[..] {
+        [.., house, "m" | "m." | "lok" | "lok.", number] if starts_with_digit(&house) => {
+            tokens.truncate(tokens.len() - 2);
+            Some(number.to_string())
+        }
+        _ => None,
+    };
+
+    match tokens.split_last() {
+        Some((number, name)) if !name.is_empty() && starts_with_digit(number) => {
+            let (house, flat) = match number.split_once('/') {
+                Some((house, flat)) => (house, Some(flat.to_string())),
+                None => (*number, flat),
+            };
+            (Some(name.join(" ")), Some(house.to_string()), flat)
+        }
+        _ => (Some(street.to_string()), None, None),
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, key: &'static str) -> Result<&'a str, JpkError> {
+    value
+        .as_deref()
+        .filter(|value| !value.is_empty())
+        .ok_or(JpkError::MissingSetting(key))
+}
+
+/// Amount with two decimal places.
+fn amount(value: Decimal) -> String {
+    format!("{:.2}", value.round_dp(2))
+}
+
+fn digits(nip: &str) -> String {
+    nip.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn flag(value: bool) -> &'static str {
+    if value { "true" } else { "false" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::invoice::{Address, InvoiceSummary, LineItem, VatBreakdown};
+    use chrono::TimeZone;
+
+    fn settings() -> JpkConfig {
+        JpkConfig {
+            tax_office: Some("1471".to_string()),
+            voivodeship: Some("mazowieckie".to_string()),
+            county: Some("Warszawa".to_string()),
+            commune: Some("Warszawa".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn invoice(number: &str, day: u32) -> Invoice {
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = number.to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 3, day);
+        invoice.issuer = Party {
+            name: "Firma & Syn Sp. z o.o.".to_string(),
+            nip: Some("526-104-08-28".to_string()),
+            address: Address {
+                street: Some("ul. Długa 5/3".to_string()),
+                postal_code: Some("00-001".to_string()),
+                city: Some("Warszawa".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        invoice.receiver.name = "Klient S.A.".to_string();
+        invoice.line_items.push(LineItem {
+            ordinal: Some(1),
+            description: "Usługa".to_string(),
+            code: None,
+            quantity: Decimal::from(2),
+            unit: Some("szt".to_string()),
+            unit_price_net: Decimal::from(50),
+            unit_price_gross: None,
+            vat_rate: VatRate::Standard23,
+            total_net: Decimal::from(100),
+            vat_amount: Decimal::from(23),
+            total_gross: Decimal::from(123),
+            discount_percent: None,
+            gtu_codes: Vec::new(),
+        });
+        invoice.summary = InvoiceSummary {
+            total_net: Decimal::from(100),
+            total_vat: Decimal::from(23),
+            total_gross: Decimal::from(123),
+            ..Default::default()
+        };
+        invoice
+    }
+
+    fn write(invoices: &[Invoice]) -> String {
+        let created_at = Utc.with_ymd_and_hms(2024, 4, 2, 10, 0, 0).unwrap();
+        JpkFa::new(settings())
+            .with_created_at(created_at)
+            .write(invoices)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_document_structure() {
+        let mut split = invoice("FV/2/2024", 20);
+        split.summary.split_payment = true;
+        // Internal invoice between units of one taxpayer, not self-billed
+        split.header.self_invoice = true;
+        let xml = write(&[split, invoice("FV/1/2024", 5)]);
+
+        assert!(xml.contains("<DataWytworzeniaJPK>2024-04-02T10:00:00Z</DataWytworzeniaJPK>"));
+        assert!(xml.contains("<DataOd>2024-03-05</DataOd>"));
+        assert!(xml.contains("<DataDo>2024-03-20</DataDo>"));
+        assert!(xml.contains("<KodUrzedu>1471</KodUrzedu>"));
+        assert!(xml.contains("<etd:NIP>5261040828</etd:NIP>"));
+        assert!(xml.contains("<etd:PelnaNazwa>Firma &amp; Syn Sp. z o.o.</etd:PelnaNazwa>"));
+        assert!(xml.contains("<Ulica>ul. Długa</Ulica>"));
+        assert!(xml.contains("<NrDomu>5</NrDomu>"));
+        assert!(xml.contains("<NrLokalu>3</NrLokalu>"));
+
+        assert_eq!(xml.matches("<Faktura>").count(), 2);
+        assert!(xml.contains("<P_13_1>100.00</P_13_1>"));
+        assert!(xml.contains("<P_14_1>23.00</P_14_1>"));
+        assert!(xml.contains("<LiczbaFaktur>2</LiczbaFaktur>"));
+        assert!(xml.contains("<WartoscFaktur>246.00</WartoscFaktur>"));
+        assert_eq!(xml.matches("<P_18A>true</P_18A>").count(), 1);
+        assert_eq!(xml.matches("<P_17>false</P_17>").count(), 2);
+
+        assert!(xml.contains("<P_8B>2</P_8B>"));
+        assert!(xml.contains("<P_12>23</P_12>"));
+        assert!(xml.contains("<LiczbaWierszyFaktur>2</LiczbaWierszyFaktur>"));
+        assert!(xml.contains("<WartoscWierszyFaktur>200.00</WartoscWierszyFaktur>"));
+        assert!(xml.trim_end().ends_with("</JPK>"));
+    }
+
+    #[test]
+    fn test_rate_buckets() {
+        let mut mixed = invoice("FV/3/2024", 1);
+        mixed.summary.vat_breakdown = vec![
+            VatBreakdown {
+                rate: VatRate::Reduced8,
+                net: Decimal::from(50),
+                vat: Decimal::from(4),
+                gross: Decimal::from(54),
+            },
+            VatBreakdown {
+                rate: VatRate::Exempt,
+                net: Decimal::from(10),
+                vat: Decimal::ZERO,
+                gross: Decimal::from(10),
+            },
+        ];
+        mixed.header.invoice_type = InvoiceType::Correction;
+        mixed.header.correction_of = Some("FV/1/2024".to_string());
+
+        let xml = write(&[mixed]);
+        assert!(xml.contains("<P_13_2>50.00</P_13_2>"));
+        assert!(xml.contains("<P_14_2>4.00</P_14_2>"));
+        assert!(xml.contains("<P_13_7>10.00</P_13_7>"));
+        assert!(!xml.contains("<P_13_1>"));
+        assert!(xml.contains("<P_19>true</P_19>"));
+        assert!(xml.contains("<RodzajFaktury>KOREKTA</RodzajFaktury>"));
+        assert!(xml.contains("<NrFaKorygowanej>FV/1/2024</NrFaKorygowanej>"));
+    }
+
+    #[test]
+    fn test_required_data() {
+        let mut proforma = invoice("PRO/1", 1);
+        proforma.header.invoice_type = InvoiceType::Proforma;
+        assert!(matches!(
+            JpkFa::new(settings()).write(&[proforma]),
+            Err(JpkError::NoInvoices)
+        ));
+
+        let mut undated = invoice("FV/4/2024", 1);
+        undated.header.issue_date = None;
+        assert!(matches!(
+            JpkFa::new(settings()).write(&[undated]),
+            Err(JpkError::MissingField { .. })
+        ));
+
+        let no_office = JpkConfig {
+            tax_office: None,
+            ..settings()
+        };
+        assert!(matches!(
+            JpkFa::new(no_office).write(&[invoice("FV/5/2024", 1)]),
+            Err(JpkError::MissingSetting("tax_office"))
+        ));
+
+        let no_commune = JpkConfig {
+            commune: None,
+            ..settings()
+        };
+        assert!(matches!(
+            JpkFa::new(no_commune).write(&[invoice("FV/5/2024", 1)]),
+            Err(JpkError::MissingSetting("commune"))
+        ));
+    }
+
+    #[test]
+    fn test_split_street() {
+        assert_eq!(
+            split_street(Some("Aleje Jerozolimskie 123A m. 4")),
+            (
+                Some("Aleje Jerozolimskie".to_string()),
+                Some("123A".to_string()),
+                Some("4".to_string())
+            )
+        );
+        assert_eq!(
+            split_street(Some("ul. 3 Maja")),
+            (Some("ul. 3 Maja".to_string()), None, None)
+        );
+        assert_eq!(
+            split_street(Some("Polna 12")),
+            (Some("Polna".to_string()), Some("12".to_string()), None)
+        );
+        assert_eq!(split_street(None), (None, None, None));
+    }
+}
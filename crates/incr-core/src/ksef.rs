@@ -0,0 +1,432 @@
+//! Import of KSeF FA(3) XML invoices.
+//!
+//! An invoice filed with KSeF (Krajowy System e-Faktur) is the legally
+//! binding version of the document. Loading it into the [`Invoice`] model
+//! lets an OCR extraction of the printed copy be compared with what was
+//! actually filed, field by field.
+//!
+//! The seller (`Podmiot1`), buyer (`Podmiot2`), header fields, line items
+//! (`FaWiersz`), the per-rate totals (`P_13_*`, `P_14_*`) and payment
+//! details are read; other parties (`Podmiot3`), attachments and the
+//! remaining annotations are ignored. Element namespaces are not checked,
+//! so FA(2) documents load as well where the schemas agree.
+
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::error::KsefError;
+use crate::export::xml::{parse_xml, Element};
+use crate::models::invoice::{
+    Address, CorrectionDetails, Invoice, InvoiceType, LineItem, Party, PaymentMethod, SourceType,
+    VatBreakdown, VatRate,
+};
+
+/// Net (`P_13_*`) and VAT (`P_14_*`) elements of the totals per rate.
+const RATE_TOTALS: &[(&str, Option<&str>, VatRate)] = &[
+    ("P_13_1", Some("P_14_1"), VatRate::Standard23),
+    ("P_13_2", Some("P_14_2"), VatRate::Reduced8),
+    ("P_13_3", Some("P_14_3"), VatRate::Reduced5),
+    ("P_13_4", Some("P_14_4"), VatRate::Other(4)),
+    ("P_13_5", Some("P_14_5"), VatRate::NotApplicable),
+    ("P_13_6_1", None, VatRate::Zero),
+    ("P_13_6_2", None, VatRate::Zero),
+    ("P_13_6_3", None, VatRate::Zero),
+    ("P_13_7", None, VatRate::Exempt),
+    ("P_13_8", None, VatRate::NotApplicable),
+    ("P_13_9", None, VatRate::NotApplicable),
+    ("P_13_10", None, VatRate::ReverseCharge),
+    ("P_13_11", None, VatRate::NotApplicable),
+];
+
+impl Element {
+    fn date(&self, path: &str) -> Result<Option<NaiveDate>, KsefError> {
+        self.text(path)
+            .map(|text| {
+                NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| invalid(path, text))
+            })
+            .transpose()
+    }
+
+    fn amount(&self, path: &str) -> Result<Option<Decimal>, KsefError> {
+        self.text(path)
+            .map(|text| Decimal::from_str(text).map_err(|_| invalid(path, text)))
+            .transpose()
+    }
+}
+
+impl Invoice {
+    /// Load an invoice from a KSeF FA(3) XML document.
+    pub fn from_ksef_xml(xml: &str) -> Result<Self, KsefError> {
+        parse_invoice(xml)
+    }
+}
+
+/// Load an invoice from a KSeF FA(3) XML document.
+pub fn parse_invoice(xml: &str) -> Result<Invoice, KsefError> {
+    let root = parse_xml(xml).map_err(KsefError::Xml)?;
+    if root.name != "Faktura" {
+        return Err(KsefError::NotInvoice(format!(
+            "root element is {}, expected Faktura",
+            root.name
+        )));
+    }
+    let fa = root
+        .find("Fa")
+        .ok_or_else(|| KsefError::NotInvoice("no Fa element".to_string()))?;
+
+    let mut invoice = Invoice::new();
+
+    let header = &mut invoice.header;
+    header.invoice_number = fa.string("P_2").unwrap_or_default();
+    header.issue_date = fa.date("P_1")?;
+    header.sale_date = match fa.date("P_6")? {
+        Some(date) => Some(date),
+        None => fa.date("OkresFa/P_6_Do")?,
+    };
+    header.due_date = fa.date("Platnosc/TerminPlatnosci/Termin")?;
+    if let Some(currency) = fa.string("KodWaluty") {
+        header.currency = currency;
+    }
+    header.invoice_type = match fa.text("RodzajFaktury") {
+        Some("KOR" | "KOR_ZAL" | "KOR_ROZ") => InvoiceType::Correction,
+        Some("ZAL") => InvoiceType::Advance,
+        Some("ROZ") => InvoiceType::Final,
+        _ if fa.text("Adnotacje/PMarzy/P_PMarzy") == Some("1") => InvoiceType::Margin,
+        _ => InvoiceType::Standard,
+    };
+    header.correction_of = fa.string("DaneFaKorygowanej/NrFaKorygowanej");
+    if header.invoice_type == InvoiceType::Correction {
+        header.correction = Some(CorrectionDetails {
+            corrected_issue_date: fa.date("DaneFaKorygowanej/DataWystFaKorygowanej")?,
+            reason: fa.string("PrzyczynaKorekty"),
+            ..Default::default()
+        });
+    }
+
+    if let Some(seller) = root.find("Podmiot1") {
+        invoice.issuer = party(seller);
+    }
+    if let Some(buyer) = root.find("Podmiot2") {
+        invoice.receiver = party(buyer);
+    }
+    invoice.issuer.bank_account = fa
+        .text("Platnosc/RachunekBankowy/NrRB")
+        .map(|account| account.split_whitespace().collect());
+    invoice.issuer.bank_name = fa.string("Platnosc/RachunekBankowy/NazwaBanku");
+    invoice.header.self_invoice = invoice.has_same_nip();
+
+    invoice.line_items = fa.all("FaWiersz").map(line_item).collect::<Result<_, _>>()?;
+
+    let summary = &mut invoice.summary;
+    for &(net_element, vat_element, rate) in RATE_TOTALS {
+        let Some(net) = fa.amount(net_element)? else {
+            continue;
+        };
+        let vat = match vat_element {
+            Some(element) => fa.amount(element)?.unwrap_or_default(),
+            None => Decimal::ZERO,
+        };
+        summary.total_net += net;
+        summary.total_vat += vat;
+        summary.vat_breakdown.push(VatBreakdown {
+            rate,
+            net,
+            vat,
+            gross: net + vat,
+        });
+    }
+    summary.split_payment = fa.text("Adnotacje/P_18A") == Some("1");
+    summary.total_gross = fa
+        .amount("P_15")?
+        .unwrap_or(summary.total_net + summary.total_vat);
+
+    summary.payment_method = match fa.text("Platnosc/FormaPlatnosci") {
+        Some("1") => Some(PaymentMethod::Cash),
+        Some("2") => Some(PaymentMethod::Card),
+        Some("3") => Some(PaymentMethod::Other("bon".to_string())),
+        Some("4") => Some(PaymentMethod::Other("czek".to_string())),
+        Some("5") => Some(PaymentMethod::Other("kredyt".to_string())),
+        Some("6") => Some(PaymentMethod::Transfer),
+        Some("7") => Some(PaymentMethod::Other("mobilna".to_string())),
+        _ => fa.text("Platnosc/OpisPlatnosci").map(PaymentMethod::from_str),
+    };
+
+    let partial = fa
+        .find("Platnosc")
+        .into_iter()
+        .flat_map(|p| p.all("ZaplataCzesciowa"))
+        .map(|p| p.amount("KwotaZaplatyCzesciowej"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let paid = if fa.text("Platnosc/Zaplacono") == Some("1") {
+        Some(summary.total_gross)
+    } else if partial.iter().any(Option::is_some) {
+        Some(partial.into_iter().flatten().sum())
+    } else {
+        None
+    };
+    if let Some(paid) = paid {
+        summary.amount_paid = Some(paid);
+        summary.amount_due = Some(summary.total_gross - paid);
+    }
+
+    invoice.metadata.confidence = 1.0;
+    invoice.metadata.source_type = SourceType::KsefXml;
+    Ok(invoice)
+}
+
+/// Seller or buyer from a `Podmiot1`/`Podmiot2` element.
+fn party(element: &Element) -> Party {
+    let address = element.find("Adres");
+    let line1 = address.and_then(|a| a.string("AdresL1"));
+    let line2 = address.and_then(|a| a.string("AdresL2"));
+
+    // AdresL2 usually holds "00-001 Warszawa"
+    let (postal_code, city) = match line2.as_deref().and_then(|l| l.split_once(' ')) {
+        Some((code, city)) if is_postal_code(code) => {
+            (Some(code.to_string()), Some(city.trim().to_string()))
+        }
+        _ => (None, None),
+    };
+    let raw = match (&line1, &line2) {
+        (Some(line1), Some(line2)) if postal_code.is_none() => {
+            Some(format!("{}, {}", line1, line2))
+        }
+        (None, Some(line2)) if postal_code.is_none() => Some(line2.clone()),
+        _ => None,
+    };
+
+    Party {
+        name: element
+            .string("DaneIdentyfikacyjne/Nazwa")
+            .unwrap_or_default(),
+        nip: element.string("DaneIdentyfikacyjne/NIP"),
+        // EU buyers: KodUE holds the country code, NrVatUE the rest
+        vat_id: element
+            .string("DaneIdentyfikacyjne/KodUE")
+            .zip(element.string("DaneIdentyfikacyjne/NrVatUE"))
+            .map(|(country, number)| format!("{}{}", country, number)),
+        address: Address {
+            street: line1,
+            postal_code,
+            city,
+            country: address.and_then(|a| a.string("KodKraju")),
+            raw,
+        },
+        email: element
+            .text("DaneKontaktowe/Email")
+            .map(str::to_lowercase),
+        phone: element.string("DaneKontaktowe/Telefon"),
+        ..Party::default()
+    }
+}
+
+/// Line item from a `FaWiersz` element.
+fn line_item(row: &Element) -> Result<LineItem, KsefError> {
+    let vat_rate = match row.text("P_12") {
+        None => VatRate::NotApplicable,
+        Some(rate) => ksef_rate(rate).ok_or_else(|| invalid("FaWiersz/P_12", rate))?,
+    };
+
+    let quantity = row.amount("P_8B")?.unwrap_or(Decimal::ONE);
+    let unit_price_gross = row.amount("P_9B")?;
+    let total_gross = row.amount("P_11A")?;
+    let rate = vat_rate.as_decimal();
+
+    // Prices are net (P_9A, P_11) or, on gross invoices, gross (P_9B, P_11A)
+    let total_net = match row.amount("P_11")? {
+        Some(net) => net,
+        None => total_gross
+            .map(|gross| (gross / (Decimal::ONE + rate)).round_dp(2))
+            .unwrap_or_default(),
+    };
+    let unit_price_net = match row.amount("P_9A")? {
+        Some(price) => price,
+        None if !quantity.is_zero() => (total_net / quantity).round_dp(2),
+        None => Decimal::ZERO,
+    };
+    let vat_amount = match row.amount("P_11Vat")? {
+        Some(vat) => vat,
+        None => match total_gross {
+            Some(gross) => gross - total_net,
+            None => (total_net * rate).round_dp(2),
+        },
+    };
+
+    Ok(LineItem {
+        ordinal: row.text("NrWierszaFa").and_then(|n| n.parse().ok()),
+        description: row.string("P_7").unwrap_or_default(),
+        code: ["GTIN", "PKWiU", "CN", "Indeks"]
+            .into_iter()
+            .find_map(|element| row.string(element)),
+        quantity,
+        unit: row.string("P_8A"),
+        unit_price_net,
+        unit_price_gross,
+        vat_rate,
+        total_net,
+        vat_amount,
+        total_gross: total_gross.unwrap_or(total_net + vat_amount),
+        discount_percent: None,
+        gtu_codes: row.string("GTU").into_iter().collect(),
+    })
+}
+
+/// VAT rate of a `P_12` value.
+fn ksef_rate(value: &str) -> Option<VatRate> {
+    match value {
+        "23" | "22" => Some(VatRate::Standard23),
+        "8" | "7" => Some(VatRate::Reduced8),
+        "5" => Some(VatRate::Reduced5),
+        "0 KR" | "0 WDT" | "0 EX" | "0" => Some(VatRate::Zero),
+        "zw" => Some(VatRate::Exempt),
+        "oo" => Some(VatRate::ReverseCharge),
+        "np I" | "np II" | "np" => Some(VatRate::NotApplicable),
+        other => other.parse().ok().map(VatRate::Other),
+    }
+}
+
+fn is_postal_code(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    bytes.len() == 6
+        && bytes[2] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 2 || b.is_ascii_digit())
+}
+
+fn invalid(element: &str, value: &str) -> KsefError {
+    KsefError::InvalidValue {
+        element: element.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FA3: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Faktura xmlns="http://crd.gov.pl/wzor/2025/06/25/13775/">
+  <Naglowek>
+    <KodFormularza kodSystemowy="FA (3)" wersjaSchemy="1-0E">FA</KodFormularza>
+    <WariantFormularza>3</WariantFormularza>
+  </Naglowek>
+  <Podmiot1>
+    <DaneIdentyfikacyjne><NIP>5261040828</NIP><Nazwa>ABC Sp. z o.o.</Nazwa></DaneIdentyfikacyjne>
+    <Adres><KodKraju>PL</KodKraju><AdresL1>ul. Prosta 1</AdresL1><AdresL2>00-001 Warszawa</AdresL2></Adres>
+    <DaneKontaktowe><Email>Biuro@ABC.pl</Email></DaneKontaktowe>
+  </Podmiot1>
+  <Podmiot2>
+    <DaneIdentyfikacyjne><NIP>6750000007</NIP><Nazwa>XYZ S.A. &amp; Partnerzy</Nazwa></DaneIdentyfikacyjne>
+    <Adres><KodKraju>PL</KodKraju><AdresL1>Rynek 5, Kraków</AdresL1></Adres>
+  </Podmiot2>
+  <Fa>
+    <KodWaluty>PLN</KodWaluty>
+    <P_1>2024-01-15</P_1>
+    <P_2>FV/001/2024</P_2>
+    <P_6>2024-01-14</P_6>
+    <P_13_1>1000.00</P_13_1>
+    <P_14_1>230.00</P_14_1>
+    <P_13_3>100.00</P_13_3>
+    <P_14_3>5.00</P_14_3>
+    <P_15>1335.00</P_15>
+    <Adnotacje><P_16>2</P_16><P_17>2</P_17><P_18>2</P_18><P_18A>1</P_18A></Adnotacje>
+    <RodzajFaktury>VAT</RodzajFaktury>
+    <FaWiersz>
+      <NrWierszaFa>1</NrWierszaFa>
+      <P_7>Usługa programistyczna</P_7>
+      <P_8A>godz.</P_8A>
+      <P_8B>10</P_8B>
+      <P_9A>100.00</P_9A>
+      <P_11>1000.00</P_11>
+      <P_12>23</P_12>
+      <GTU>GTU_12</GTU>
+    </FaWiersz>
+    <FaWiersz>
+      <NrWierszaFa>2</NrWierszaFa>
+      <P_7>Książka</P_7>
+      <P_8B>1</P_8B>
+      <P_11>100.00</P_11>
+      <P_12>5</P_12>
+      <GTIN>5901234123457</GTIN>
+    </FaWiersz>
+    <Platnosc>
+      <TerminPlatnosci><Termin>2024-01-29</Termin></TerminPlatnosci>
+      <FormaPlatnosci>6</FormaPlatnosci>
+      <RachunekBankowy><NrRB>PL61 1090 1014 0000 0712 1981 2874</NrRB></RachunekBankowy>
+    </Platnosc>
+  </Fa>
+</Faktura>"#;
+
+    #[test]
+    fn test_parse_fa3() {
+        let invoice = Invoice::from_ksef_xml(FA3).unwrap();
+
+        assert_eq!(invoice.header.invoice_number, "FV/001/2024");
+        assert_eq!(invoice.header.issue_date, NaiveDate::from_ymd_opt(2024, 1, 15));
+        assert_eq!(invoice.header.sale_date, NaiveDate::from_ymd_opt(2024, 1, 14));
+        assert_eq!(invoice.header.due_date, NaiveDate::from_ymd_opt(2024, 1, 29));
+        assert_eq!(invoice.header.invoice_type, InvoiceType::Standard);
+
+        assert_eq!(invoice.issuer.nip.as_deref(), Some("5261040828"));
+        assert_eq!(invoice.issuer.address.postal_code.as_deref(), Some("00-001"));
+        assert_eq!(invoice.issuer.address.city.as_deref(), Some("Warszawa"));
+        assert_eq!(invoice.issuer.email.as_deref(), Some("biuro@abc.pl"));
+        assert_eq!(
+            invoice.issuer.bank_account.as_deref(),
+            Some("PL61109010140000071219812874")
+        );
+        assert_eq!(invoice.receiver.name, "XYZ S.A. & Partnerzy");
+        assert_eq!(invoice.receiver.address.street.as_deref(), Some("Rynek 5, Kraków"));
+
+        assert_eq!(invoice.line_items.len(), 2);
+        assert_eq!(invoice.line_items[0].gtu_codes, ["GTU_12"]);
+        let book = &invoice.line_items[1];
+        assert_eq!(book.vat_rate, VatRate::Reduced5);
+        assert_eq!(book.unit_price_net, Decimal::new(10000, 2));
+        assert_eq!(book.vat_amount, Decimal::new(500, 2));
+        assert_eq!(book.code.as_deref(), Some("5901234123457"));
+
+        let summary = &invoice.summary;
+        assert_eq!(summary.total_net, Decimal::new(110000, 2));
+        assert_eq!(summary.total_vat, Decimal::new(23500, 2));
+        assert_eq!(summary.total_gross, Decimal::new(133500, 2));
+        assert_eq!(summary.vat_breakdown.len(), 2);
+        assert_eq!(summary.payment_method, Some(PaymentMethod::Transfer));
+        assert!(summary.split_payment);
+        assert_eq!(invoice.metadata.source_type, SourceType::KsefXml);
+    }
+
+    #[test]
+    fn test_eu_buyer() {
+        let xml = FA3.replace(
+            "<NIP>6750000007</NIP>",
+            "<KodUE>DE</KodUE><NrVatUE>136695976</NrVatUE>",
+        );
+        let invoice = Invoice::from_ksef_xml(&xml).unwrap();
+
+        assert_eq!(invoice.receiver.nip, None);
+        assert_eq!(invoice.receiver.vat_id.as_deref(), Some("DE136695976"));
+    }
+
+    #[test]
+    fn test_rejects_other_documents() {
+        assert!(matches!(
+            Invoice::from_ksef_xml("<Invoice><ID>1</ID></Invoice>"),
+            Err(KsefError::NotInvoice(_))
+        ));
+        assert!(matches!(
+            Invoice::from_ksef_xml("<Faktura><Fa></Faktura>"),
+            Err(KsefError::Xml(_))
+        ));
+
+        let bad_date = FA3.replace("<P_1>2024-01-15</P_1>", "<P_1>15.01.2024</P_1>");
+        assert!(matches!(
+            Invoice::from_ksef_xml(&bad_date),
+            Err(KsefError::InvalidValue { element, .. }) if element == "P_1"
+        ));
+    }
+}
@@ -5,23 +5,82 @@
 //! - OCR pipeline using PaddleOCR models
 //! - Polish invoice field extraction (NIP, REGON, dates, amounts, VAT)
 //! - Invoice data models compatible with KSeF FA(3)
+//! - Checksum validation of Polish identifiers ([`validate`])
+//! - Amounts in Polish words ([`words`])
+//! - Reading ZIP archives entry by entry and writing them ([`archive`])
+//! - Rendering invoices as HTML and PDF ([`render`])
+//! - Matching invoices to ERP open items ([`reconcile`])
+//! - Exporting invoices as JPK_FA audit files ([`jpk`]) and UBL 2.1
+//!   ([`export`])
+//! - Protobuf encoding of invoices and OCR results (`proto` feature)
+//! - Importing KSeF FA(3) XML invoices (`ksef` feature)
+//! - Downloading OCR models with resume and checksums (`download` feature)
+//! - Checking NIPs and bank accounts against the white list of VAT taxpayers
+//!   (`whitelist` feature)
+//! - Checking EU VAT IDs in VIES (`vies` feature)
+//! - Filling in party details from the REGON register (`gus` feature)
+//!
+//! Everything except [`validate`], [`words`], [`reconcile`], [`jpk`],
+//! [`export`] and the data models needs the `pipeline` feature (enabled by
+//! `native` and `wasm`).
 
+#[cfg(feature = "pipeline")]
+pub mod archive;
+#[cfg(feature = "pipeline")]
+pub mod audit;
 pub mod error;
+pub mod export;
+#[cfg(feature = "gus")]
+pub mod gus;
 pub mod models;
+#[cfg(feature = "pipeline")]
 pub mod pdf;
+#[cfg(feature = "pipeline")]
 pub mod ocr;
+#[cfg(feature = "pipeline")]
 pub mod invoice;
+pub mod jpk;
+#[cfg(feature = "ksef")]
+pub mod ksef;
+pub mod progress;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod reconcile;
+#[cfg(feature = "pipeline")]
+pub mod render;
+#[cfg(feature = "pipeline")]
+pub mod training;
+#[cfg(all(
+    test,
+    any(feature = "download", feature = "gus", feature = "vies", feature = "whitelist")
+))]
+mod test_support;
+pub mod validate;
+#[cfg(feature = "vies")]
+pub mod vies;
+#[cfg(feature = "whitelist")]
+pub mod whitelist;
+pub mod words;
 
 pub use error::{IncrError, Result};
 pub use models::invoice::{Invoice, InvoiceHeader, InvoiceSummary, Party, LineItem, VatRate};
+#[cfg(feature = "pipeline")]
 pub use pdf::{PdfProcessor, PdfContent, PdfType};
+#[cfg(feature = "pipeline")]
 pub use ocr::{OcrResult, TextBox};
 #[cfg(feature = "native")]
-pub use ocr::{create_engine_from_dir, create_engine_from_embedded, PureOcrEngine};
+pub use ocr::{
+    create_engine_from_dir, create_engine_from_embedded, create_engine_from_embedded_models,
+    PureOcrEngine,
+};
 #[cfg(feature = "wasm")]
 pub use ocr::{OcrEngine, OcrEngineBuilder};
+#[cfg(feature = "pipeline")]
 pub use invoice::{InvoiceParser, InvoiceExtractor, ExtractionResult};
+pub use progress::{NoProgress, PageProgress, ProgressEvent, ProgressSink, ProgressStage};
 
 /// Re-export inference types (WASM only).
 #[cfg(feature = "wasm")]
-pub use incr_inference::{InferenceBackend, InputTensor, OutputTensor, TractBackend};
+pub use incr_inference::{
+    InferenceBackend, InferenceOptions, InputTensor, OutputTensor, TractBackend,
+};
@@ -0,0 +1,453 @@
+//! Model bundles for machines without internet access.
+//!
+//! A bundle is a zstd-compressed tar archive (`.tar.zst`) of one installed
+//! model variant: a `<variant>/` directory holding a [`ModelManifest`] with
+//! the digest of every file, followed by the files. [`export`] checks the
+//! installed files against the catalog digests before packing them.
+//! [`import`] unpacks a bundle into a staging directory, checks every file
+//! against the bundled manifest and the catalog, and only then moves the
+//! files into the variant's directory, so a damaged bundle never replaces
+//! working models.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), incr_core::error::BundleError> {
+//! use std::fs::File;
+//! use std::path::Path;
+//! use incr_core::models::bundle;
+//! use incr_core::models::downloader::ModelVariant;
+//!
+//! // On a machine with the models
+//! let dir = Path::new("models/mobile");
+//! bundle::export(ModelVariant::Mobile, dir, File::create("mobile.tar.zst")?)?;
+//! // On the offline machine
+//! let bundle = bundle::import(File::open("mobile.tar.zst")?, Path::new("models"))?;
+//! println!("installed {} models", bundle.variant);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::BundleError;
+
+use super::downloader::{
+    sha256_file, ModelInfo, ModelManifest, ModelVariant, MANIFEST_FILE, SUPER_RESOLUTION,
+};
+
+/// File extension of model bundles.
+pub const BUNDLE_EXTENSION: &str = "tar.zst";
+
+/// Directory under the model root a bundle is unpacked into before its
+/// files are checked.
+const STAGING_DIR: &str = ".import";
+
+/// A bundle that was written or installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bundle {
+    /// Variant the bundle holds.
+    pub variant: ModelVariant,
+    /// The files of the bundle with their digests.
+    pub manifest: ModelManifest,
+}
+
+/// The files a bundle must hold, and those it may hold.
+struct Catalog {
+    required: Vec<ModelInfo>,
+    optional: Vec<ModelInfo>,
+}
+
+impl Catalog {
+    fn of(variant: ModelVariant) -> Self {
+        Self {
+            required: variant.models().files().into_iter().copied().collect(),
+            optional: vec![SUPER_RESOLUTION],
+        }
+    }
+
+    /// Every file with whether it is required.
+    fn files(&self) -> impl Iterator<Item = (&ModelInfo, bool)> {
+        let required = self.required.iter().map(|model| (model, true));
+        required.chain(self.optional.iter().map(|model| (model, false)))
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.files().any(|(model, _)| model.filename == name)
+    }
+}
+
+/// Write the files of `variant` installed in `dir` as a bundle to `writer`.
+///
+/// Every file must match its catalog digest, or for files the catalog has
+/// none for, the digest recorded when it was downloaded. The
+/// super-resolution model is included when installed.
+pub fn export<W: Write>(
+    variant: ModelVariant,
+    dir: &Path,
+    writer: W,
+) -> Result<Bundle, BundleError> {
+    write_bundle(variant, &Catalog::of(variant), dir, writer)
+}
+
+/// Install the bundle read from `reader` into its variant's directory
+/// under `root`.
+///
+/// Files already installed are replaced. Nothing is installed unless every
+/// file of the variant is in the bundle and matches its digest.
+pub fn import<R: Read>(reader: R, root: &Path) -> Result<Bundle, BundleError> {
+    read_bundle(reader, root, Catalog::of)
+}
+
+fn write_bundle<W: Write>(
+    variant: ModelVariant,
+    catalog: &Catalog,
+    dir: &Path,
+    writer: W,
+) -> Result<Bundle, BundleError> {
+    let installed = ModelManifest::load(dir);
+    let mut manifest = ModelManifest::default();
+    let mut missing = Vec::new();
+
+    for (model, required) in catalog.files() {
+        let path = dir.join(model.filename);
+        if !path.exists() {
+            if required {
+                missing.push(model.filename.to_string());
+            }
+            continue;
+        }
+
+        let actual = sha256_file(&path)?;
+        let expected = model
+            .sha256
+            .map(str::to_string)
+            .or_else(|| installed.files.get(model.filename).map(|e| e.sha256.clone()));
+        if let Some(expected) = expected.filter(|expected| *expected != actual) {
+            return Err(BundleError::Checksum {
+                file: model.filename.to_string(),
+                expected,
+                actual,
+            });
+        }
+        manifest.record(&path, actual)?;
+    }
+
+    if !missing.is_empty() {
+        return Err(BundleError::Incomplete {
+            variant: variant.to_string(),
+            missing,
+        });
+    }
+
+    let encoder = zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    // The manifest goes first, so it can be read without unpacking the models
+    let json = serde_json::to_vec_pretty(&manifest).map_err(std::io::Error::other)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    );
+    builder.append_data(&mut header, format!("{}/{}", variant, MANIFEST_FILE), json.as_slice())?;
+
+    for name in manifest.files.keys() {
+        builder.append_path_with_name(dir.join(name), format!("{}/{}", variant, name))?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok(Bundle { variant, manifest })
+}
+
+fn read_bundle<R: Read>(
+    reader: R,
+    root: &Path,
+    catalog: impl Fn(ModelVariant) -> Catalog,
+) -> Result<Bundle, BundleError> {
+    let staging = root.join(STAGING_DIR);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    let result = unpack(reader, &staging, &catalog).and_then(|variant| {
+        let manifest = check(variant, &catalog(variant), &staging)?;
+        install(&manifest, &staging, &root.join(variant.to_string()))?;
+        Ok(Bundle { variant, manifest })
+    });
+
+    // Best effort: a leftover staging directory is cleared by the next import
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Unpack the files of a bundle into `staging`, returning its variant.
+///
+/// Only the manifest and files of the catalog are accepted, all in one
+/// variant directory.
+fn unpack<R: Read>(
+    reader: R,
+    staging: &Path,
+    catalog: &impl Fn(ModelVariant) -> Catalog,
+) -> Result<ModelVariant, BundleError> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(reader)?);
+    let mut bundle: Option<(ModelVariant, Catalog)> = None;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            continue;
+        }
+
+        let Some((dir, name)) = split_entry(&path).filter(|_| kind.is_file()) else {
+            return Err(BundleError::Invalid(format!(
+                "unexpected entry {}",
+                path.display()
+            )));
+        };
+        let variant = ModelVariant::ALL
+            .into_iter()
+            .find(|variant| variant.to_string() == dir)
+            .ok_or_else(|| BundleError::Invalid(format!("unknown model variant {}", dir)))?;
+
+        let (expected, files) = bundle.get_or_insert_with(|| (variant, catalog(variant)));
+        if *expected != variant {
+            return Err(BundleError::Invalid(format!(
+                "holds both {} and {} models",
+                expected, variant
+            )));
+        }
+        if name != MANIFEST_FILE && !files.contains(&name) {
+            return Err(BundleError::Invalid(format!(
+                "unexpected file {}",
+                path.display()
+            )));
+        }
+
+        entry.unpack(staging.join(&name))?;
+    }
+
+    bundle
+        .map(|(variant, _)| variant)
+        .ok_or_else(|| BundleError::Invalid("the archive is empty".to_string()))
+}
+
+/// The variant directory and file name of an archive entry, for entries
+/// of the form `<variant>/<file>`.
+fn split_entry(path: &Path) -> Option<(String, String)> {
+    let mut parts = path
+        .components()
+        .filter(|part| !matches!(part, Component::CurDir));
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Component::Normal(dir)), Some(Component::Normal(name)), None) => Some((
+            dir.to_string_lossy().into_owned(),
+            name.to_string_lossy().into_owned(),
+        )),
+        _ => None,
+    }
+}
+
+/// Check the unpacked files against the bundled manifest and the catalog,
+/// returning the manifest of the files.
+fn check(
+    variant: ModelVariant,
+    catalog: &Catalog,
+    staging: &Path,
+) -> Result<ModelManifest, BundleError> {
+    let data = fs::read(staging.join(MANIFEST_FILE))
+        .map_err(|_| BundleError::Invalid(format!("no {}", MANIFEST_FILE)))?;
+    let bundled: ModelManifest = serde_json::from_slice(&data)
+        .map_err(|e| BundleError::Invalid(format!("invalid {}: {}", MANIFEST_FILE, e)))?;
+
+    let mut manifest = ModelManifest::default();
+    let mut missing = Vec::new();
+
+    for (model, required) in catalog.files() {
+        let path = staging.join(model.filename);
+        if !path.exists() {
+            if required {
+                missing.push(model.filename.to_string());
+            }
+            continue;
+        }
+
+        let entry = bundled.files.get(model.filename).ok_or_else(|| {
+            BundleError::Invalid(format!("{} is not in the manifest", model.filename))
+        })?;
+        let actual = sha256_file(&path)?;
+        let listed = model.sha256.unwrap_or(&entry.sha256);
+        for expected in [entry.sha256.as_str(), listed] {
+            if expected != actual {
+                return Err(BundleError::Checksum {
+                    file: model.filename.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        manifest.record(&path, actual)?;
+    }
+
+    if !missing.is_empty() {
+        return Err(BundleError::Incomplete {
+            variant: variant.to_string(),
+            missing,
+        });
+    }
+    Ok(manifest)
+}
+
+/// Move the checked files from `staging` into `dir` and record them in its
+/// manifest.
+fn install(manifest: &ModelManifest, staging: &Path, dir: &Path) -> Result<(), BundleError> {
+    fs::create_dir_all(dir)?;
+    for name in manifest.files.keys() {
+        fs::rename(staging.join(name), dir.join(name))?;
+    }
+
+    let mut installed = ModelManifest::load(dir);
+    installed.files.extend(manifest.files.clone());
+    installed.save(dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::downloader::ManifestEntry;
+
+    fn model(filename: &'static str, sha256: Option<&'static str>) -> ModelInfo {
+        ModelInfo {
+            filename,
+            size_bytes: 9,
+            description: "test model",
+            url: "",
+            mirror_url: "",
+            sha256,
+        }
+    }
+
+    fn catalog(_: ModelVariant) -> Catalog {
+        Catalog {
+            required: vec![model("det.onnx", None), model("dict.txt", None)],
+            optional: vec![model("sr.onnx", None)],
+        }
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("incr-bundle-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A bundle of `entries` written without any checks; the paths are
+    /// stored as given, `..` included.
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0).unwrap());
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = temp_dir("source");
+        fs::write(source.join("det.onnx"), b"detection").unwrap();
+        fs::write(source.join("dict.txt"), b"dictionary").unwrap();
+        fs::write(source.join("unrelated.tmp"), b"partial").unwrap();
+
+        let mut data = Vec::new();
+        let exported =
+            write_bundle(ModelVariant::Mobile, &catalog(ModelVariant::Mobile), &source, &mut data)
+                .unwrap();
+        assert_eq!(
+            exported.manifest.files.keys().collect::<Vec<_>>(),
+            vec!["det.onnx", "dict.txt"]
+        );
+
+        let root = temp_dir("root");
+        let imported = read_bundle(data.as_slice(), &root, catalog).unwrap();
+        assert_eq!(imported, exported);
+
+        let dir = root.join("mobile");
+        assert_eq!(fs::read(dir.join("det.onnx")).unwrap(), b"detection");
+        assert!(!dir.join("unrelated.tmp").exists());
+        assert_eq!(ModelManifest::load(&dir), exported.manifest);
+        assert!(!root.join(STAGING_DIR).exists());
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_export_incomplete() {
+        let source = temp_dir("incomplete");
+        fs::write(source.join("det.onnx"), b"detection").unwrap();
+
+        let catalog = catalog(ModelVariant::Mobile);
+        let error = write_bundle(ModelVariant::Mobile, &catalog, &source, Vec::new()).unwrap_err();
+        assert!(matches!(
+            error,
+            BundleError::Incomplete { ref missing, .. } if missing == &["dict.txt"]
+        ));
+        fs::remove_dir_all(&source).unwrap();
+    }
+
+    #[test]
+    fn test_import_rejects_tampered_file() {
+        let mut manifest = ModelManifest::default();
+        for name in ["det.onnx", "dict.txt"] {
+            manifest.files.insert(
+                name.to_string(),
+                ManifestEntry {
+                    size_bytes: 9,
+                    sha256: "00".to_string(),
+                },
+            );
+        }
+        let manifest = serde_json::to_vec(&manifest).unwrap();
+        let data = archive(&[
+            ("mobile/manifest.json", &manifest),
+            ("mobile/det.onnx", b"tampered!"),
+            ("mobile/dict.txt", b"dictionary"),
+        ]);
+
+        let root = temp_dir("tampered");
+        let error = read_bundle(data.as_slice(), &root, catalog).unwrap_err();
+        assert!(matches!(error, BundleError::Checksum { ref file, .. } if file == "det.onnx"));
+        // Nothing is installed
+        assert!(!root.join("mobile").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_import_rejects_unexpected_entries() {
+        let root = temp_dir("entries");
+        for path in ["mobile/../../evil.onnx", "mobile/evil.sh", "desktop/det.onnx", "det.onnx"] {
+            let data = archive(&[(path, b"x")]);
+            let error = read_bundle(data.as_slice(), &root, catalog).unwrap_err();
+            assert!(matches!(error, BundleError::Invalid(_)), "{}: {:?}", path, error);
+        }
+        assert!(!root.join("evil.onnx").exists());
+
+        let error = read_bundle(archive(&[]).as_slice(), &root, catalog).unwrap_err();
+        assert!(matches!(error, BundleError::Invalid(_)));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+//! Which pipeline stages ran for a result.
+//!
+//! Optional models (angle classification, layout, super-resolution,
+//! handwriting) and the barcode decoder are used when they are installed
+//! and skipped otherwise. [`Capabilities`] records for every stage whether
+//! it ran and, if not, why, so a poor result can be traced to a missing
+//! model or a disabled setting.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A stage of the extraction pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Reading the embedded text layer of a PDF.
+    TextLayer,
+    /// Straightening pages scanned or photographed at an angle.
+    Deskew,
+    /// Turning pages scanned sideways or upside down upright.
+    Orientation,
+    /// Text line detection.
+    Detection,
+    /// Rotated text detection (angle classification).
+    Classification,
+    /// Text recognition.
+    Recognition,
+    /// Upscaling images with small text before recognition.
+    Upscaling,
+    /// Enhancing tiny print with the super-resolution model.
+    SuperResolution,
+    /// Recognizing low-confidence lines again from an upscaled crop.
+    SecondPass,
+    /// Routing handwritten text to the handwriting model.
+    Handwriting,
+    /// Layout analysis (text, table and figure regions).
+    Layout,
+    /// Decoding QR codes and barcodes (in figure regions when layout
+    /// analysis ran).
+    Barcodes,
+    /// Line items read from detected table regions.
+    Tables,
+}
+
+impl Stage {
+    /// All stages in pipeline order.
+    pub const ALL: [Stage; 13] = [
+        Stage::TextLayer,
+        Stage::Deskew,
+        Stage::Orientation,
+        Stage::Detection,
+        Stage::Classification,
+        Stage::Recognition,
+        Stage::Upscaling,
+        Stage::SuperResolution,
+        Stage::SecondPass,
+        Stage::Handwriting,
+        Stage::Layout,
+        Stage::Barcodes,
+        Stage::Tables,
+    ];
+
+    /// The stage's name as used in JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::TextLayer => "text_layer",
+            Stage::Deskew => "deskew",
+            Stage::Orientation => "orientation",
+            Stage::Detection => "detection",
+            Stage::Classification => "classification",
+            Stage::Recognition => "recognition",
+            Stage::Upscaling => "upscaling",
+            Stage::SuperResolution => "super_resolution",
+            Stage::SecondPass => "second_pass",
+            Stage::Handwriting => "handwriting",
+            Stage::Layout => "layout",
+            Stage::Barcodes => "barcodes",
+            Stage::Tables => "tables",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Whether a stage ran, and why not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageStatus {
+    /// The stage ran (on at least one page).
+    pub ran: bool,
+
+    /// Why the stage was skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Status of each pipeline stage, serialized as an object keyed by stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Capabilities {
+    stages: BTreeMap<Stage, StageStatus>,
+}
+
+impl Capabilities {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report for text read from a PDF text layer: no OCR stage runs.
+    pub fn text_layer() -> Self {
+        let mut capabilities = Self::new();
+        capabilities.ran(Stage::TextLayer);
+        for stage in Stage::ALL.into_iter().filter(|&s| s != Stage::TextLayer) {
+            capabilities.skipped(stage, "text read from the PDF text layer");
+        }
+        capabilities
+    }
+
+    /// Record that `stage` ran.
+    pub fn ran(&mut self, stage: Stage) {
+        self.stages.insert(
+            stage,
+            StageStatus {
+                ran: true,
+                reason: None,
+            },
+        );
+    }
+
+    /// Record that `stage` was skipped, unless it already ran.
+    pub fn skipped(&mut self, stage: Stage, reason: impl Into<String>) {
+        if self.has_run(stage) {
+            return;
+        }
+        self.stages.insert(
+            stage,
+            StageStatus {
+                ran: false,
+                reason: Some(reason.into()),
+            },
+        );
+    }
+
+    /// Record that `stage` failed, even if it was expected to run.
+    pub fn failed(&mut self, stage: Stage, reason: impl Into<String>) {
+        self.stages.insert(
+            stage,
+            StageStatus {
+                ran: false,
+                reason: Some(reason.into()),
+            },
+        );
+    }
+
+    /// Record a stage that needs an enabled setting and a loaded model:
+    /// it runs if both are there, otherwise the missing one is the reason.
+    pub fn model_stage(
+        &mut self,
+        stage: Stage,
+        setting: (&str, bool),
+        model: (&str, bool),
+    ) {
+        match (setting, model) {
+            ((name, false), _) => self.skipped(stage, format!("disabled by {}", name)),
+            (_, (name, false)) => self.skipped(stage, format!("{} not installed", name)),
+            _ => self.ran(stage),
+        }
+    }
+
+    /// Merge the report of another page: a stage ran if it ran on any page.
+    pub fn merge(&mut self, other: &Capabilities) {
+        for (&stage, status) in &other.stages {
+            match &status.reason {
+                Some(reason) if !status.ran => self.skipped(stage, reason.clone()),
+                _ => self.ran(stage),
+            }
+        }
+    }
+
+    /// Status of `stage`, if it was recorded.
+    pub fn status(&self, stage: Stage) -> Option<&StageStatus> {
+        self.stages.get(&stage)
+    }
+
+    /// Whether `stage` ran.
+    pub fn has_run(&self, stage: Stage) -> bool {
+        self.status(stage).is_some_and(|s| s.ran)
+    }
+
+    /// Recorded stages in pipeline order.
+    pub fn iter(&self) -> impl Iterator<Item = (Stage, &StageStatus)> {
+        self.stages.iter().map(|(&stage, status)| (stage, status))
+    }
+
+    /// Skipped stages and the reasons, in pipeline order.
+    pub fn skipped_stages(&self) -> impl Iterator<Item = (Stage, &str)> {
+        self.iter()
+            .filter_map(|(stage, status)| status.reason.as_deref().map(|r| (stage, r)))
+    }
+
+    /// Whether nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_pages() {
+        let mut first = Capabilities::new();
+        first.ran(Stage::Detection);
+        first.skipped(Stage::Upscaling, "text large enough");
+        first.model_stage(Stage::Layout, ("ocr.enable_layout", true), ("layout.onnx", false));
+
+        let mut second = Capabilities::new();
+        second.ran(Stage::Upscaling);
+        second.model_stage(
+            Stage::Classification,
+            ("ocr.enable_classification", false),
+            ("cls.onnx", true),
+        );
+
+        first.merge(&second);
+        assert!(first.has_run(Stage::Upscaling));
+        assert!(first.has_run(Stage::Detection));
+
+        let skipped: Vec<_> = first.skipped_stages().collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (Stage::Classification, "disabled by ocr.enable_classification"),
+                (Stage::Layout, "layout.onnx not installed"),
+            ]
+        );
+
+        // A stage that ran is not downgraded by a later skip
+        first.skipped(Stage::Detection, "no pages");
+        assert!(first.has_run(Stage::Detection));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut capabilities = Capabilities::new();
+        capabilities.skipped(Stage::Tables, "no layout");
+        capabilities.ran(Stage::Recognition);
+
+        let json = serde_json::to_string(&capabilities).unwrap();
+        assert_eq!(
+            json,
+            r#"{"recognition":{"ran":true},"tables":{"ran":false,"reason":"no layout"}}"#
+        );
+        assert_eq!(serde_json::from_str::<Capabilities>(&json).unwrap(), capabilities);
+    }
+}
@@ -0,0 +1,698 @@
+//! Downloading the OCR models.
+//!
+//! [`ModelDownloader`] installs the files of a [`ModelVariant`] into a
+//! directory, skipping files that are already there. Each file is
+//! downloaded to a `.tmp` file next to it, and a retried download continues
+//! where the last one broke off. The file is moved into place only once it
+//! is complete and, where the catalog lists a digest, its SHA-256 matches.
+//! A file that fails from its primary URL is tried from its mirror.
+//!
+//! The digests of installed files are recorded in a [`ModelManifest`] in the
+//! model directory, so later runs can tell complete files from truncated or
+//! replaced ones without hashing them again. [`verify`] re-hashes the files
+//! of a variant.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::RANGE;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::error::DownloadError;
+
+/// Model variant, a set of detection and recognition models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelVariant {
+    /// Mobile models - smaller, faster.
+    Mobile,
+    /// Server models - better detection accuracy.
+    Server,
+    /// Mobile models quantized to INT8 - fastest on CPUs, slightly less
+    /// accurate.
+    Quantized,
+}
+
+/// A model file with its download locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// File name in the model directory.
+    pub filename: &'static str,
+    /// Expected size in bytes.
+    pub size_bytes: u64,
+    /// Human-readable description.
+    pub description: &'static str,
+    /// Primary download URL.
+    pub url: &'static str,
+    /// Fallback download URL.
+    pub mirror_url: &'static str,
+    /// Lowercase hex SHA-256 of the file, if known.
+    pub sha256: Option<&'static str>,
+}
+
+/// The files of a model variant.
+#[derive(Debug, Clone, Copy)]
+pub struct VariantModels {
+    /// Text detection model.
+    pub detection: ModelInfo,
+    /// Text recognition model.
+    pub recognition: ModelInfo,
+    /// Character dictionary of the recognition model.
+    pub dictionary: ModelInfo,
+    /// Layout detection model (PP-Structure).
+    pub layout: Option<ModelInfo>,
+    /// Table structure model (PP-Structure).
+    pub table: Option<ModelInfo>,
+}
+
+/// Optional text super-resolution model, shared by all variants.
+pub const SUPER_RESOLUTION: ModelInfo = ModelInfo {
+    filename: "sr.onnx",
+    size_bytes: 250_000,
+    description: "Text super-resolution (2x, optional)",
+    url: "https://github.com/jakubmatias/incr/raw/main/models/sr/sr.onnx",
+    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/sr/sr.onnx",
+    sha256: None,
+};
+
+impl ModelVariant {
+    /// All variants.
+    pub const ALL: [ModelVariant; 3] =
+        [ModelVariant::Mobile, ModelVariant::Server, ModelVariant::Quantized];
+
+    /// The files of this variant.
+    pub fn models(self) -> VariantModels {
+        // Models are downloaded from: https://github.com/jakubmatias/incr/tree/main/models
+        match self {
+            ModelVariant::Mobile => VariantModels {
+                detection: ModelInfo {
+                    filename: "det.onnx",
+                    size_bytes: 4_500_000,
+                    description: "PP-OCRv3 mobile detection",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/det.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/det.onnx",
+                    sha256: Some("ca3014670099126189c9519ef770470c03bf41695fb138c6bc19737bd4ba2875"),
+                },
+                recognition: ModelInfo {
+                    filename: "latin_rec.onnx",
+                    size_bytes: 7_500_000,
+                    description: "Latin recognition",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_rec.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_rec.onnx",
+                    sha256: Some("614ffc2d6d3902d360fad7f1b0dd455ee45e877069d14c4e51a99dc4ef144409"),
+                },
+                dictionary: ModelInfo {
+                    filename: "latin_dict.txt",
+                    size_bytes: 2_000,
+                    description: "Latin character dictionary",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_dict.txt",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/mobile/latin_dict.txt",
+                    sha256: Some("3c0a8a79b612653c25f765271714f71281e4e955962c153e272b7b8c1d2b13ff"),
+                },
+                layout: None,
+                table: None,
+            },
+            ModelVariant::Server => VariantModels {
+                detection: ModelInfo {
+                    filename: "det.onnx",
+                    size_bytes: 84_000_000,
+                    description: "PP-OCRv5 server detection",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/server/det.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/det.onnx",
+                    sha256: Some("61824840edf6e74581898930b8091b1b2318f4b2705a2e8a40ad3de7ac480133"),
+                },
+                recognition: ModelInfo {
+                    filename: "latin_rec.onnx",
+                    size_bytes: 7_500_000,
+                    description: "Latin recognition",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_rec.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_rec.onnx",
+                    sha256: Some("614ffc2d6d3902d360fad7f1b0dd455ee45e877069d14c4e51a99dc4ef144409"),
+                },
+                dictionary: ModelInfo {
+                    filename: "latin_dict.txt",
+                    size_bytes: 2_000,
+                    description: "Latin character dictionary",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_dict.txt",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/server/latin_dict.txt",
+                    sha256: Some("3c0a8a79b612653c25f765271714f71281e4e955962c153e272b7b8c1d2b13ff"),
+                },
+                layout: None,
+                table: None,
+            },
+            ModelVariant::Quantized => VariantModels {
+                detection: ModelInfo {
+                    filename: "det.onnx",
+                    size_bytes: 1_100_000,
+                    description: "PP-OCRv3 mobile detection, INT8",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/det.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/det.onnx",
+                    sha256: None,
+                },
+                recognition: ModelInfo {
+                    filename: "latin_rec.onnx",
+                    size_bytes: 1_900_000,
+                    description: "Latin recognition, INT8",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/latin_rec.onnx",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/latin_rec.onnx",
+                    sha256: None,
+                },
+                dictionary: ModelInfo {
+                    filename: "latin_dict.txt",
+                    size_bytes: 2_000,
+                    description: "Latin character dictionary",
+                    url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/latin_dict.txt",
+                    mirror_url: "https://github.com/jakubmatias/incr/raw/main/models/quantized/latin_dict.txt",
+                    sha256: Some("3c0a8a79b612653c25f765271714f71281e4e955962c153e272b7b8c1d2b13ff"),
+                },
+                layout: None,
+                table: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ModelVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelVariant::Mobile => write!(f, "mobile"),
+            ModelVariant::Server => write!(f, "server"),
+            ModelVariant::Quantized => write!(f, "quantized"),
+        }
+    }
+}
+
+impl VariantModels {
+    /// All files, the optional structure models included.
+    pub fn files(&self) -> Vec<&ModelInfo> {
+        let mut files = vec![&self.detection, &self.recognition, &self.dictionary];
+        files.extend(self.layout.iter().chain(self.table.iter()));
+        files
+    }
+
+    /// Total expected size in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.files().iter().map(|m| m.size_bytes).sum()
+    }
+}
+
+impl ModelInfo {
+    /// Whether `path` looks like a complete copy of this file, judged by
+    /// size only. Files of at least half the expected size count, as the
+    /// catalog sizes are approximate; [`verify`] checks the contents.
+    pub fn is_installed(&self, path: &Path) -> bool {
+        fs::metadata(path).is_ok_and(|m| m.len() > self.size_bytes / 2)
+    }
+}
+
+/// Name of the manifest in each model directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Digests of the files installed in a model directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelManifest {
+    /// Installed files by name.
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// An installed file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Size in bytes when installed.
+    pub size_bytes: u64,
+    /// Lowercase hex SHA-256 when installed.
+    pub sha256: String,
+}
+
+impl ModelManifest {
+    /// Load the manifest of `dir`; empty if there is none or it is unreadable.
+    pub fn load(dir: &Path) -> Self {
+        let Ok(data) = fs::read(dir.join(MANIFEST_FILE)) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            warn!("Ignoring invalid {} in {}: {}", MANIFEST_FILE, dir.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Write the manifest to `dir`.
+    pub fn save(&self, dir: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(dir.join(MANIFEST_FILE), json)
+    }
+
+    /// Record `path` as installed with digest `sha256`.
+    pub fn record(&mut self, path: &Path, sha256: String) -> std::io::Result<()> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let size_bytes = fs::metadata(path)?.len();
+        self.files.insert(name, ManifestEntry { size_bytes, sha256 });
+        Ok(())
+    }
+
+    /// Whether `path` was recorded with digest `sha256` and still has the
+    /// recorded size.
+    fn holds(&self, path: &Path, sha256: &str) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let size = fs::metadata(path).map(|m| m.len()).ok();
+        self.files
+            .get(name.as_ref())
+            .is_some_and(|e| e.sha256 == sha256 && Some(e.size_bytes) == size)
+    }
+}
+
+/// Result of checking one installed file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileCheck {
+    /// The file matches its digest.
+    Valid,
+    /// The file is not installed.
+    Missing,
+    /// The file does not match its digest.
+    Corrupt { expected: String, actual: String },
+    /// Neither the catalog nor the manifest has a digest for the file.
+    Unverified,
+}
+
+/// Hash the installed files of `variant` in `dir` and compare them with the
+/// catalog digests, or for files the catalog has none for, the digests
+/// recorded when they were downloaded. The super-resolution model is
+/// checked when installed.
+pub fn verify(variant: ModelVariant, dir: &Path) -> std::io::Result<Vec<(&'static str, FileCheck)>> {
+    let manifest = ModelManifest::load(dir);
+    let models = variant.models();
+    let mut files = models.files();
+    if dir.join(SUPER_RESOLUTION.filename).exists() {
+        files.push(&SUPER_RESOLUTION);
+    }
+
+    let mut checks = Vec::with_capacity(files.len());
+    for model in files {
+        let path = dir.join(model.filename);
+        let expected = model
+            .sha256
+            .map(str::to_string)
+            .or_else(|| manifest.files.get(model.filename).map(|e| e.sha256.clone()));
+
+        let check = if !path.exists() {
+            FileCheck::Missing
+        } else if let Some(expected) = expected {
+            let actual = sha256_file(&path)?;
+            if actual == expected {
+                FileCheck::Valid
+            } else {
+                FileCheck::Corrupt { expected, actual }
+            }
+        } else {
+            FileCheck::Unverified
+        };
+        checks.push((model.filename, check));
+    }
+
+    Ok(checks)
+}
+
+/// Progress of a file download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// File being downloaded.
+    pub file: &'static str,
+    /// Bytes downloaded so far, including those of an earlier attempt.
+    pub downloaded: u64,
+    /// Total bytes (the catalog size if the server does not say).
+    pub total: u64,
+}
+
+/// Outcome of [`ModelDownloader::ensure`].
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    /// Files downloaded.
+    pub downloaded: Vec<&'static str>,
+    /// Files that were already installed.
+    pub present: Vec<&'static str>,
+    /// Files that could not be downloaded.
+    pub failed: Vec<(&'static str, DownloadError)>,
+}
+
+impl DownloadReport {
+    /// All files are installed.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/// Downloads model variants into a model directory.
+pub struct ModelDownloader {
+    root: PathBuf,
+    client: reqwest::Client,
+    mirror: bool,
+    force: bool,
+    super_resolution: bool,
+    progress: Option<ProgressCallback>,
+}
+
+impl ModelDownloader {
+    /// Create a downloader installing variants into subdirectories of
+    /// `root` (`<root>/mobile`, `<root>/server`).
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("incr/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(300))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            root: root.into(),
+            client,
+            mirror: false,
+            force: false,
+            super_resolution: false,
+            progress: None,
+        }
+    }
+
+    /// Try the mirror URLs first.
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Download files again even if they are installed.
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Also install the text super-resolution model.
+    pub fn with_super_resolution(mut self, enabled: bool) -> Self {
+        self.super_resolution = enabled;
+        self
+    }
+
+    /// Call `progress` as download data arrives.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&DownloadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Directory a variant is installed into.
+    pub fn variant_dir(&self, variant: ModelVariant) -> PathBuf {
+        self.root.join(variant.to_string())
+    }
+
+    /// Install the files of `variant` that are missing from its directory.
+    pub async fn ensure(&self, variant: ModelVariant) -> Result<DownloadReport, DownloadError> {
+        self.ensure_in(variant, &self.variant_dir(variant)).await
+    }
+
+    /// Install the files of `variant` that are missing from `dir`, or do
+    /// not match their digest.
+    ///
+    /// A file that fails is reported in [`DownloadReport::failed`] and the
+    /// remaining files are still downloaded. The digests of the installed
+    /// files are recorded in the directory's [`ModelManifest`].
+    pub async fn ensure_in(
+        &self,
+        variant: ModelVariant,
+        dir: &Path,
+    ) -> Result<DownloadReport, DownloadError> {
+        fs::create_dir_all(dir)?;
+        let mut manifest = ModelManifest::load(dir);
+
+        let models = variant.models();
+        let mut files = models.files();
+        if self.super_resolution {
+            files.push(&SUPER_RESOLUTION);
+        }
+
+        let mut report = DownloadReport::default();
+        for model in files {
+            let path = dir.join(model.filename);
+            if self.force {
+                let _ = fs::remove_file(partial_path(&path));
+            } else if installed(model, &path, &mut manifest)? {
+                report.present.push(model.filename);
+                continue;
+            }
+
+            match self.download(model, &path).await {
+                Ok(sha256) => {
+                    manifest.record(&path, sha256)?;
+                    report.downloaded.push(model.filename);
+                }
+                Err(e) => report.failed.push((model.filename, e)),
+            }
+        }
+
+        manifest.save(dir)?;
+        Ok(report)
+    }
+
+    /// Download one file to `path`, from the mirror if the primary URL fails.
+    /// Returns the file's SHA-256.
+    pub async fn download(&self, model: &ModelInfo, path: &Path) -> Result<String, DownloadError> {
+        let (first, second) = if self.mirror {
+            (model.mirror_url, model.url)
+        } else {
+            (model.url, model.mirror_url)
+        };
+
+        match self.fetch(model, first, path).await {
+            Err(e) if second != first => {
+                warn!("Downloading {} from {} failed: {}, trying {}", model.filename, first, e, second);
+                self.fetch(model, second, path).await
+            }
+            result => result,
+        }
+    }
+
+    async fn fetch(&self, model: &ModelInfo, url: &str, path: &Path) -> Result<String, DownloadError> {
+        let partial = partial_path(path);
+        let mut offset = fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+
+        let mut response = self.request(url, offset).await?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file is not a prefix of this one
+            fs::remove_file(&partial)?;
+            offset = 0;
+            response = self.request(url, 0).await?;
+        }
+        if !response.status().is_success() {
+            return Err(DownloadError::Status {
+                url: url.to_string(),
+                status: response.status().as_u16(),
+            });
+        }
+
+        let mut file = if response.status() == StatusCode::PARTIAL_CONTENT {
+            debug!("Resuming {} at {} bytes", model.filename, offset);
+            OpenOptions::new().append(true).open(&partial)?
+        } else {
+            offset = 0;
+            File::create(&partial)?
+        };
+
+        let total = response
+            .content_length()
+            .map_or(model.size_bytes, |length| offset + length);
+        let mut downloaded = offset;
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk)?;
+            downloaded += chunk.len() as u64;
+
+            if let Some(progress) = &self.progress {
+                progress(&DownloadProgress {
+                    file: model.filename,
+                    downloaded,
+                    total: total.max(downloaded),
+                });
+            }
+        }
+
+        file.flush()?;
+        drop(file);
+
+        let actual = sha256_file(&partial)?;
+        if let Some(expected) = model.sha256.filter(|&expected| expected != actual) {
+            fs::remove_file(&partial)?;
+            return Err(DownloadError::Checksum {
+                file: model.filename.to_string(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+
+        fs::rename(&partial, path)?;
+        Ok(actual)
+    }
+
+    async fn request(&self, url: &str, offset: u64) -> Result<Response, DownloadError> {
+        let mut request = self.client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        Ok(request.send().await?)
+    }
+}
+
+/// Whether `path` holds `model`. Files with a catalog digest must match
+/// it: files the manifest does not vouch for (installed before manifests
+/// were kept, or changed since) are hashed once and recorded.
+fn installed(model: &ModelInfo, path: &Path, manifest: &mut ModelManifest) -> std::io::Result<bool> {
+    let Some(expected) = model.sha256 else {
+        return Ok(model.is_installed(path));
+    };
+    if !path.exists() {
+        return Ok(false);
+    }
+    if manifest.holds(path, expected) {
+        return Ok(true);
+    }
+
+    let actual = sha256_file(path)?;
+    if actual != expected {
+        warn!("{} does not match its checksum, downloading it again", path.display());
+        return Ok(false);
+    }
+    manifest.record(path, actual)?;
+    Ok(true)
+}
+
+/// Lowercase hex SHA-256 of a file, read in chunks.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Where an incomplete download of `path` is kept.
+fn partial_path(path: &Path) -> PathBuf {
+    path.with_extension("tmp")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Requests, Response};
+    use std::sync::{Arc, Mutex};
+
+    /// Serve `body` to `requests` connections, honouring `Range` headers.
+    /// Returns the base URL and the requests.
+    fn serve(body: &'static [u8], requests: usize) -> (String, Requests) {
+        test_support::serve(requests, move |request| {
+            match request.header("range").and_then(|r| r.strip_prefix("bytes=")) {
+                Some(range) => {
+                    let start: usize = range.trim_end_matches('-').parse().unwrap();
+                    Response::new("206 Partial Content", None, &body[start..])
+                }
+                None => Response::new("200 OK", None, body),
+            }
+        })
+    }
+
+    fn model(url: String, sha256: Option<&'static str>) -> ModelInfo {
+        let url: &'static str = Box::leak(url.into_boxed_str());
+        ModelInfo {
+            filename: "det.onnx",
+            size_bytes: 11,
+            description: "test model",
+            url,
+            mirror_url: url,
+            sha256,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_partial_file() {
+        let (url, requests) = serve(b"hello world", 1);
+        let dir = std::env::temp_dir().join(format!("incr-download-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("det.onnx");
+        fs::write(partial_path(&path), b"hello ").unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        let downloader = ModelDownloader::new(&dir)
+            .with_progress(move |p| seen.lock().unwrap().push((p.downloaded, p.total)));
+        // sha256("hello world")
+        let digest = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        downloader
+            .download(&model(format!("{}/det.onnx", url), Some(digest)), &path)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+        assert!(!partial_path(&path).exists());
+        let requests = requests.lock().unwrap();
+        let ranges: Vec<_> = requests.iter().map(|r| r.header("range")).collect();
+        assert_eq!(ranges, [Some("bytes=6-")]);
+        assert_eq!(events.lock().unwrap().last(), Some(&(11, 11)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_and_manifest() {
+        let dir = std::env::temp_dir().join(format!("incr-verify-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let models = ModelVariant::Mobile.models();
+        fs::write(dir.join(models.dictionary.filename), b"truncated").unwrap();
+        fs::write(dir.join(SUPER_RESOLUTION.filename), b"sr").unwrap();
+
+        // The super-resolution model is checked against the manifest
+        let mut manifest = ModelManifest::default();
+        manifest.record(&dir.join(SUPER_RESOLUTION.filename), "00".to_string()).unwrap();
+        manifest.save(&dir).unwrap();
+        assert_eq!(ModelManifest::load(&dir), manifest);
+
+        let checks = verify(ModelVariant::Mobile, &dir).unwrap();
+        assert_eq!(checks[0], ("det.onnx", FileCheck::Missing));
+        assert!(matches!(checks[2], ("latin_dict.txt", FileCheck::Corrupt { .. })));
+        assert!(matches!(checks[3], ("sr.onnx", FileCheck::Corrupt { .. })));
+        assert!(!installed(&models.dictionary, &dir.join("latin_dict.txt"), &mut manifest).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_wrong_checksum() {
+        let (url, _) = serve(b"truncated", 1);
+        let dir = std::env::temp_dir().join(format!("incr-checksum-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("det.onnx");
+
+        let downloader = ModelDownloader::new(&dir);
+        let error = downloader
+            .download(&model(format!("{}/det.onnx", url), Some("00")), &path)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, DownloadError::Checksum { .. }));
+        assert!(!path.exists());
+        assert!(!partial_path(&path).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
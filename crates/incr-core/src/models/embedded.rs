@@ -1,7 +1,8 @@
 //! Embedded model data for standalone binary distribution.
 //!
 //! Mobile models are embedded directly in the binary for easy distribution.
-//! Server models must be downloaded separately due to size.
+//! Server models (~96MB) are embedded only with the `embedded-server-models`
+//! feature; otherwise they must be downloaded separately.
 
 /// Embedded mobile detection model (~4.5MB)
 pub static MOBILE_DET: &[u8] = include_bytes!("../../../../models/mobile/det.onnx");
@@ -12,13 +13,31 @@ pub static MOBILE_REC: &[u8] = include_bytes!("../../../../models/mobile/latin_r
 /// Embedded mobile dictionary (~1.6KB)
 pub static MOBILE_DICT: &str = include_str!("../../../../models/mobile/latin_dict.txt");
 
+/// Embedded server detection model (~88MB)
+#[cfg(feature = "embedded-server-models")]
+pub static SERVER_DET: &[u8] = include_bytes!("../../../../models/server/det.onnx");
+
+/// Embedded server recognition model (~7.5MB)
+#[cfg(feature = "embedded-server-models")]
+pub static SERVER_REC: &[u8] = include_bytes!("../../../../models/server/latin_rec.onnx");
+
+/// Embedded server dictionary (~1.6KB)
+#[cfg(feature = "embedded-server-models")]
+pub static SERVER_DICT: &str = include_str!("../../../../models/server/latin_dict.txt");
+
 /// Check if embedded models are available.
 pub fn has_embedded_models() -> bool {
     // Always true when compiled with embedded models
     !MOBILE_DET.is_empty()
 }
 
-/// Get embedded model data for mobile variant.
+/// Check if the server models are embedded.
+pub fn has_embedded_server_models() -> bool {
+    cfg!(feature = "embedded-server-models")
+}
+
+/// Embedded model data of a variant.
+#[derive(Debug, Clone, Copy)]
 pub struct EmbeddedModels {
     pub detection: &'static [u8],
     pub recognition: &'static [u8],
@@ -34,4 +53,14 @@ impl EmbeddedModels {
             dictionary: MOBILE_DICT,
         }
     }
+
+    /// Get server embedded models.
+    #[cfg(feature = "embedded-server-models")]
+    pub fn server() -> Self {
+        Self {
+            detection: SERVER_DET,
+            recognition: SERVER_REC,
+            dictionary: SERVER_DICT,
+        }
+    }
 }
@@ -4,6 +4,10 @@ use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use super::capabilities::Capabilities;
+use super::selection::Selection;
+use super::validation::{ValidationIssue, ValidationProfile};
+
 /// A complete invoice representation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Invoice {
@@ -32,8 +36,8 @@ pub struct InvoiceHeader {
     /// Invoice number/identifier.
     pub invoice_number: String,
 
-    /// Date the invoice was issued.
-    pub issue_date: NaiveDate,
+    /// Date the invoice was issued, `None` if it could not be extracted.
+    pub issue_date: Option<NaiveDate>,
 
     /// Date of sale/service (may differ from issue date).
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -53,12 +57,65 @@ pub struct InvoiceHeader {
     /// Reference to corrected invoice (for correction invoices).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub correction_of: Option<String>,
+
+    /// Reason and change of the totals, for correction invoices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correction: Option<CorrectionDetails>,
+
+    /// Issuer and receiver are the same taxpayer: a self-invoice or an
+    /// internal transfer between units of one company.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub self_invoice: bool,
+
+    /// Number assigned by KSeF, for invoices issued through it
+    /// (`NIP-YYYYMMDD-XXXXXXXXXXXX-CC`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ksef_number: Option<String>,
 }
 
 fn default_currency() -> String {
     "PLN".to_string()
 }
 
+/// Details of a correction invoice (faktura korygująca).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorrectionDetails {
+    /// Issue date of the corrected invoice.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrected_issue_date: Option<NaiveDate>,
+
+    /// Reason for the correction (przyczyna korekty).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Totals of the corrected invoice (przed korektą).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<CorrectionAmounts>,
+
+    /// Totals after the correction (po korekcie).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<CorrectionAmounts>,
+
+    /// Change of the totals, negative for a decrease (różnica).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difference: Option<CorrectionAmounts>,
+}
+
+/// Totals on one row of a correction; net and VAT may be left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorrectionAmounts {
+    /// Net amount.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub net: Option<Decimal>,
+
+    /// VAT amount.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vat: Option<Decimal>,
+
+    /// Gross amount.
+    pub gross: Decimal,
+}
+
 /// Type of invoice document.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -93,10 +150,24 @@ pub struct Party {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub nip: Option<String>,
 
+    /// EU VAT identification number of a foreign party, with its country
+    /// code (e.g. `DE136695976`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vat_id: Option<String>,
+
     /// Polish statistical number (REGON).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub regon: Option<String>,
 
+    /// Personal identification number (PESEL), shown by sole traders
+    /// and private persons.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pesel: Option<String>,
+
+    /// National Court Register number (KRS).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub krs: Option<String>,
+
     /// Full address.
     pub address: Address,
 
@@ -108,15 +179,23 @@ pub struct Party {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bank_name: Option<String>,
 
-    /// Email address.
+    /// Email address (lowercased).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
 
-    /// Phone number.
+    /// Further email addresses found for the party.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_emails: Vec<String>,
+
+    /// Phone number in E.164 format (e.g. `+48221234567`).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub phone: Option<String>,
 
-    /// Website.
+    /// Further phone numbers found for the party, in E.164 format.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_phones: Vec<String>,
+
+    /// Website (validated; scheme and host lowercased).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub website: Option<String>,
 }
@@ -221,6 +300,11 @@ pub struct LineItem {
     /// Discount percentage if applicable.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discount_percent: Option<Decimal>,
+
+    /// GTU goods and services group codes (`GTU_01` to `GTU_13`) reported
+    /// in JPK_V7 and KSeF.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gtu_codes: Vec<String>,
 }
 
 /// Polish VAT rates.
@@ -339,6 +423,39 @@ pub struct InvoiceSummary {
     /// Amount in words (Polish: słownie).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub amount_in_words: Option<String>,
+
+    /// Exchange rate and PLN totals of a foreign-currency invoice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_info: Option<CurrencyInfo>,
+
+    /// Paid under the split payment mechanism (*mechanizm podzielonej
+    /// płatności*, MPP).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub split_payment: bool,
+}
+
+/// Exchange rate of a foreign-currency invoice and its totals in PLN.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyInfo {
+    /// PLN per unit of the invoice currency.
+    pub exchange_rate: Decimal,
+
+    /// Date the rate was published (e.g. `kurs NBP z dnia ...`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_date: Option<NaiveDate>,
+
+    /// NBP table the rate was taken from (e.g. `052/A/NBP/2024`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_table: Option<String>,
+
+    /// Total net amount in PLN.
+    pub total_net_pln: Decimal,
+
+    /// Total VAT amount in PLN.
+    pub total_vat_pln: Decimal,
+
+    /// Total gross amount in PLN.
+    pub total_gross_pln: Decimal,
 }
 
 /// VAT breakdown by rate.
@@ -411,15 +528,156 @@ pub struct ExtractionMetadata {
 
     /// Warnings or issues encountered during extraction.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub warnings: Vec<String>,
+    pub warnings: Vec<ValidationIssue>,
 
     /// Fields that could not be extracted.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub missing_fields: Vec<String>,
 
+    /// Corrections applied automatically after extraction (e.g. swapped parties).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub corrections: Vec<String>,
+
     /// Field-level confidence scores.
     #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
     pub field_confidence: std::collections::HashMap<String, f32>,
+
+    /// Some pages could not be processed; the data comes from the rest of
+    /// the document (see `warnings` for which pages are missing).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub incomplete: bool,
+
+    /// Pages and region the extraction was limited to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<Selection>,
+
+    /// Fields whose value was read from text classified as handwritten,
+    /// e.g. an amount filled in by hand on a printed form.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub handwritten_fields: Vec<String>,
+
+    /// Pipeline stages that ran, and why others were skipped (e.g. a
+    /// missing layout model leaves line items to the plain-text parser).
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    pub capabilities: Capabilities,
+
+    /// Results of checking the parties against the white list of VAT
+    /// taxpayers, if requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub whitelist: Vec<WhitelistCheck>,
+
+    /// Page (from 1) each field was read from, for invoices spanning
+    /// several pages, e.g. `line_items.12` continued onto page 2.
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub field_pages: std::collections::HashMap<String, u32>,
+
+    /// QR codes and barcodes decoded from the page images.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub barcodes: Vec<DecodedBarcode>,
+
+    /// Results of checking the parties' EU VAT IDs in VIES, if requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vies: Vec<ViesCheck>,
+}
+
+/// Result of looking up a party in the white list of VAT taxpayers
+/// (*Biała Lista*) of the Ministry of Finance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WhitelistCheck {
+    /// Party checked: `issuer` or `receiver`.
+    pub party: String,
+
+    /// NIP looked up (digits only).
+    pub nip: String,
+
+    /// Day the register was checked for (the issue date where known).
+    pub date: NaiveDate,
+
+    /// VAT status in the register (`Czynny`, `Zwolniony`); `None` if the
+    /// NIP is not registered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vat_status: Option<String>,
+
+    /// Name of the taxpayer in the register.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Whether the invoice's bank account is assigned to the taxpayer;
+    /// `None` if no Polish account was checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_assigned: Option<bool>,
+
+    /// Request ID of the lookup, proof of the check for the tax office.
+    pub request_id: String,
+
+    /// Request ID of the bank account check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_request_id: Option<String>,
+}
+
+impl WhitelistCheck {
+    /// Whether the taxpayer is registered as an active VAT payer.
+    pub fn is_active(&self) -> bool {
+        self.vat_status.as_deref() == Some("Czynny")
+    }
+}
+
+/// Result of looking up a party's EU VAT ID in VIES, the VAT Information
+/// Exchange System of the European Commission.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViesCheck {
+    /// Party checked: `issuer` or `receiver`.
+    pub party: String,
+
+    /// VAT ID looked up, with its country code.
+    pub vat_id: String,
+
+    /// Day of the lookup; VIES only answers for the current day.
+    pub date: NaiveDate,
+
+    /// Whether the number is registered for intra-EU transactions.
+    pub valid: bool,
+
+    /// Name of the trader, where its country discloses it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Address of the trader, where its country discloses it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+/// A QR code or barcode decoded from the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodedBarcode {
+    /// Symbology, e.g. `qr_code`, `data_matrix` or `code_128`.
+    pub format: String,
+
+    /// Decoded text.
+    pub payload: String,
+
+    /// What the payload was recognized as; `None` for other content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<BarcodeKind>,
+
+    /// Page (from 1) the code was found on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+
+    /// Fields whose extracted value the code confirms or supplied, e.g.
+    /// `issuer.bank_account`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub confirmed_fields: Vec<String>,
+}
+
+/// Known kinds of barcode payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeKind {
+    /// Polish bank transfer code (NIP, account, amount and title).
+    Transfer,
+    /// KSeF invoice verification link (issuer NIP and issue date).
+    Ksef,
 }
 
 /// Source document type.
@@ -436,6 +694,10 @@ pub enum SourceType {
     Image,
     /// Scanned with PP-Structure layout detection.
     ScannedWithLayout,
+    /// KSeF FA(3) XML document (not extracted).
+    KsefXml,
+    /// ZUGFeRD/Factur-X CII XML document (not extracted).
+    FacturxXml,
     /// Unknown source.
     #[default]
     Unknown,
@@ -447,12 +709,15 @@ impl Invoice {
         Self {
             header: InvoiceHeader {
                 invoice_number: String::new(),
-                issue_date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+                issue_date: None,
                 sale_date: None,
                 due_date: None,
                 invoice_type: InvoiceType::Standard,
                 currency: "PLN".to_string(),
                 correction_of: None,
+                correction: None,
+                self_invoice: false,
+                ksef_number: None,
             },
             issuer: Party::default(),
             receiver: Party::default(),
@@ -462,53 +727,29 @@ impl Invoice {
         }
     }
 
-    /// Validate the invoice data and return any issues found.
-    pub fn validate(&self) -> Vec<String> {
-        let mut issues = Vec::new();
-
-        if self.header.invoice_number.is_empty() {
-            issues.push("Missing invoice number".to_string());
-        }
-
-        if self.issuer.name.is_empty() {
-            issues.push("Missing issuer name".to_string());
-        }
+    /// Whether issuer and receiver have the same NIP (compared by digits).
+    pub fn has_same_nip(&self) -> bool {
+        let digits = |nip: &str| nip.chars().filter(char::is_ascii_digit).collect::<String>();
 
-        if self.issuer.nip.is_none() {
-            issues.push("Missing issuer NIP".to_string());
-        }
-
-        if self.receiver.name.is_empty() && self.receiver.nip.is_none() {
-            issues.push("Missing receiver information".to_string());
-        }
-
-        if self.line_items.is_empty() {
-            issues.push("No line items".to_string());
-        }
-
-        if self.summary.total_gross == Decimal::ZERO {
-            issues.push("Total gross is zero".to_string());
-        }
-
-        // Validate line item totals
-        let calculated_net: Decimal = self.line_items.iter().map(|i| i.total_net).sum();
-        let calculated_gross: Decimal = self.line_items.iter().map(|i| i.total_gross).sum();
-
-        if (calculated_net - self.summary.total_net).abs() > Decimal::new(1, 2) {
-            issues.push(format!(
-                "Line item net total ({}) differs from summary ({})",
-                calculated_net, self.summary.total_net
-            ));
+        match (&self.issuer.nip, &self.receiver.nip) {
+            (Some(issuer), Some(receiver)) => {
+                let issuer = digits(issuer);
+                !issuer.is_empty() && issuer == digits(receiver)
+            }
+            _ => false,
         }
+    }
 
-        if (calculated_gross - self.summary.total_gross).abs() > Decimal::new(1, 2) {
-            issues.push(format!(
-                "Line item gross total ({}) differs from summary ({})",
-                calculated_gross, self.summary.total_gross
-            ));
-        }
+    /// Validate the invoice data and return any issues found.
+    ///
+    /// Uses the default (strict) profile; see [`Invoice::validate_profile`].
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.validate_profile(ValidationProfile::default())
+    }
 
-        issues
+    /// Validate the invoice against a named rule set.
+    pub fn validate_profile(&self, profile: ValidationProfile) -> Vec<ValidationIssue> {
+        super::validation::validate(self, profile)
     }
 }
 
@@ -0,0 +1,131 @@
+//! Document languages and the recognition models that read them.
+//!
+//! A recognition model reads one script, so the languages of a document
+//! decide which model and character dictionary are loaded. Polish, English,
+//! German or Czech product names are all read by the Latin model; Russian or
+//! Ukrainian text needs the Cyrillic one.
+
+use std::fmt;
+
+use crate::error::LanguageError;
+
+/// Writing system read by a recognition model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Script {
+    /// Latin alphabet with the diacritics of European languages.
+    #[default]
+    Latin,
+    /// Cyrillic alphabet.
+    Cyrillic,
+}
+
+/// Languages written in Latin script, as ISO 639-1 codes.
+const LATIN: &[&str] = &[
+    "pl", "en", "de", "cs", "sk", "fr", "es", "it", "pt", "nl", "sv", "da", "no", "fi", "hu",
+    "ro", "hr", "sl", "lt", "lv", "et", "tr", "sq", "is", "ga",
+];
+
+/// Languages written in Cyrillic script, as ISO 639-1 codes.
+const CYRILLIC: &[&str] = &["ru", "uk", "be", "bg", "sr", "mk", "kk", "mn"];
+
+impl Script {
+    /// The script `language` (an ISO 639-1 code such as "pl") is written in.
+    pub fn of_language(language: &str) -> Result<Self, LanguageError> {
+        let code = language.trim().to_lowercase();
+        if LATIN.contains(&code.as_str()) {
+            Ok(Script::Latin)
+        } else if CYRILLIC.contains(&code.as_str()) {
+            Ok(Script::Cyrillic)
+        } else {
+            Err(LanguageError::Unknown(language.to_string()))
+        }
+    }
+
+    /// The script all of `languages` are written in; Latin without
+    /// languages.
+    ///
+    /// Fails for languages that need different scripts, since one
+    /// recognition model reads one script.
+    pub fn of_languages<S: AsRef<str>>(languages: &[S]) -> Result<Self, LanguageError> {
+        let mut selected: Option<(Script, &str)> = None;
+        for language in languages {
+            let language = language.as_ref();
+            let script = Script::of_language(language)?;
+            match selected {
+                Some((first, first_language)) if first != script => {
+                    return Err(LanguageError::MixedScripts {
+                        first: first_language.to_string(),
+                        second: language.to_string(),
+                    });
+                }
+                Some(_) => {}
+                None => selected = Some((script, language)),
+            }
+        }
+        Ok(selected.map(|(script, _)| script).unwrap_or_default())
+    }
+
+    /// The script's name ("latin", "cyrillic").
+    pub fn name(self) -> &'static str {
+        match self {
+            Script::Latin => "latin",
+            Script::Cyrillic => "cyrillic",
+        }
+    }
+
+    /// File name of the script's recognition model.
+    pub fn recognition_model(self) -> String {
+        format!("{}_rec.onnx", self.name())
+    }
+
+    /// File name of the script's character dictionary.
+    pub fn dictionary(self) -> String {
+        format!("{}_dict.txt", self.name())
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Split a comma-separated list of languages (`pl,en,de`).
+pub fn parse_languages(value: &str) -> Result<Vec<String>, LanguageError> {
+    let languages: Vec<String> = value
+        .split(',')
+        .map(|language| language.trim().to_lowercase())
+        .filter(|language| !language.is_empty())
+        .collect();
+    Script::of_languages(&languages)?;
+    Ok(languages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_of_languages() {
+        assert_eq!(Script::of_languages::<&str>(&[]), Ok(Script::Latin));
+        assert_eq!(Script::of_languages(&["pl", "en", "DE", "cs"]), Ok(Script::Latin));
+        assert_eq!(Script::of_languages(&["uk", "ru"]), Ok(Script::Cyrillic));
+        assert_eq!(
+            Script::of_languages(&["pl", "uk"]),
+            Err(LanguageError::MixedScripts { first: "pl".into(), second: "uk".into() })
+        );
+        assert_eq!(
+            Script::of_languages(&["pl", "xx"]),
+            Err(LanguageError::Unknown("xx".into()))
+        );
+
+        assert_eq!(Script::Cyrillic.recognition_model(), "cyrillic_rec.onnx");
+        assert_eq!(Script::Latin.dictionary(), "latin_dict.txt");
+    }
+
+    #[test]
+    fn test_parse_languages() {
+        assert_eq!(parse_languages("pl, EN,de,").unwrap(), ["pl", "en", "de"]);
+        assert!(parse_languages("pl,ru").is_err());
+    }
+}
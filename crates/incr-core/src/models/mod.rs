@@ -1,5 +1,16 @@
 //! Data models for invoices and related structures.
 
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod capabilities;
 pub mod config;
+#[cfg(feature = "download")]
+pub mod downloader;
+#[cfg(feature = "pipeline")]
 pub mod embedded;
 pub mod invoice;
+pub mod language;
+pub mod naming;
+pub mod receipt;
+pub mod selection;
+pub mod validation;
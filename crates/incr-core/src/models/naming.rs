@@ -0,0 +1,170 @@
+//! Field naming conventions for serialized output.
+//!
+//! Models serialize with snake_case field names. Consumers that expect
+//! other names select a [`FieldNaming`] at serialization time and wrap the
+//! value with [`FieldNaming::apply`]; only keys are renamed, never values.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// Field names used by legacy integrations, as (model name, legacy name).
+const LEGACY_NAMES: &[(&str, &str)] = &[
+    ("invoice_number", "number"),
+    ("issue_date", "date"),
+    ("issuer", "seller"),
+    ("receiver", "buyer"),
+    ("line_items", "items"),
+    ("total_net", "net"),
+    ("total_vat", "vat"),
+    ("total_gross", "gross"),
+];
+
+/// Naming convention for serialized field names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldNaming {
+    /// Field names as defined by the models (`invoice_number`).
+    #[default]
+    SnakeCase,
+    /// camelCase field names (`invoiceNumber`).
+    CamelCase,
+    /// Short names of legacy integrations (`number`, `seller`, `buyer`,
+    /// `items`, `gross`); other fields keep their snake_case names.
+    Legacy,
+}
+
+impl FromStr for FieldNaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "snake_case" | "snake" => Ok(FieldNaming::SnakeCase),
+            "camel_case" | "camelcase" | "camel" => Ok(FieldNaming::CamelCase),
+            "legacy" => Ok(FieldNaming::Legacy),
+            other => Err(format!(
+                "unknown field naming '{}' (expected snake_case, camel_case or legacy)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FieldNaming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldNaming::SnakeCase => write!(f, "snake_case"),
+            FieldNaming::CamelCase => write!(f, "camel_case"),
+            FieldNaming::Legacy => write!(f, "legacy"),
+        }
+    }
+}
+
+impl FieldNaming {
+    /// Wrap `value` so it serializes with this naming convention.
+    pub fn apply<T: ?Sized>(self, value: &T) -> Named<'_, T> {
+        Named {
+            value,
+            naming: self,
+        }
+    }
+
+    /// Rename all object keys in a JSON value, recursively.
+    pub fn rename(self, value: Value) -> Value {
+        match value {
+            Value::Object(map) if self != FieldNaming::SnakeCase => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (self.field_name(&key), self.rename(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.rename(item)).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// Name of a snake_case model field under this convention.
+    pub fn field_name(self, name: &str) -> String {
+        match self {
+            FieldNaming::SnakeCase => name.to_string(),
+            FieldNaming::CamelCase => camel_case(name),
+            FieldNaming::Legacy => LEGACY_NAMES
+                .iter()
+                .find(|(model, _)| *model == name)
+                .map_or(name, |(_, legacy)| legacy)
+                .to_string(),
+        }
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = false;
+
+    for c in name.chars() {
+        if c == '_' && !result.is_empty() {
+            upper = true;
+        } else if upper {
+            result.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// A value serialized with a [`FieldNaming`] convention.
+///
+/// Works with any serde serializer (JSON files, HTTP responses, WASM values).
+#[derive(Debug, Clone, Copy)]
+pub struct Named<'a, T: ?Sized> {
+    value: &'a T,
+    naming: FieldNaming,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Named<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.naming == FieldNaming::SnakeCase {
+            return self.value.serialize(serializer);
+        }
+
+        let value = serde_json::to_value(self.value).map_err(serde::ser::Error::custom)?;
+        self.naming.rename(value).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::invoice::Invoice;
+
+    #[test]
+    fn test_camel_case_invoice() {
+        let json = serde_json::to_value(FieldNaming::CamelCase.apply(&Invoice::new())).unwrap();
+
+        assert!(json["header"].get("invoiceNumber").is_some());
+        assert!(json["summary"].get("totalGross").is_some());
+        assert_eq!(json["metadata"]["sourceType"], "unknown"); // Values unchanged
+    }
+
+    #[test]
+    fn test_legacy_names() {
+        let json = serde_json::to_value(FieldNaming::Legacy.apply(&Invoice::new())).unwrap();
+
+        assert!(json.get("seller").is_some() && json.get("issuer").is_none());
+        assert!(json["header"].get("number").is_some());
+        assert!(json["header"].get("currency").is_some());
+    }
+
+    #[test]
+    fn test_field_naming_from_str() {
+        assert_eq!("camelCase".parse::<FieldNaming>(), Ok(FieldNaming::CamelCase));
+        assert_eq!("snake-case".parse::<FieldNaming>(), Ok(FieldNaming::SnakeCase));
+        assert!("kebab".parse::<FieldNaming>().is_err());
+    }
+}
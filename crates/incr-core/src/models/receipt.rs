@@ -0,0 +1,210 @@
+//! Fiscal receipt (*paragon fiskalny*) data model.
+//!
+//! Receipts printed by cash registers follow a fixed layout: the seller's
+//! name, address and NIP, the items with their PTU (VAT) group letter, the
+//! taxable sales and PTU of each group, the total (`SUMA PLN`) and the
+//! register's unique number. The buyer's NIP (`NIP nabywcy`) is printed on
+//! request, making a receipt of up to 450 PLN a simplified invoice.
+
+use chrono::{NaiveDate, NaiveTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::invoice::{ExtractionMetadata, Party, PaymentMethod, VatRate};
+use super::validation::{tolerance, IssueCode, Severity, ValidationIssue};
+use crate::validate::validate_nip;
+
+/// A fiscal receipt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Seller: name, address and NIP from the top of the receipt.
+    pub seller: Party,
+
+    /// Buyer's NIP (`NIP nabywcy`), printed on request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_nip: Option<String>,
+
+    /// Date of sale, `None` if it could not be extracted.
+    pub issue_date: Option<NaiveDate>,
+
+    /// Time of sale.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_time: Option<NaiveTime>,
+
+    /// Consecutive number of the printout (`nr wydr.`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub print_number: Option<String>,
+
+    /// Unique number of the cash register (e.g. `BFA 12345678`), printed
+    /// next to the fiscal logo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiscal_number: Option<String>,
+
+    /// Items sold.
+    pub items: Vec<ReceiptItem>,
+
+    /// Taxable sales and PTU of each tax group.
+    pub tax_groups: Vec<TaxGroup>,
+
+    /// Total PTU (`SUMA PTU`).
+    pub total_vat: Decimal,
+
+    /// Total to pay (`SUMA PLN`).
+    pub total: Decimal,
+
+    /// Currency code.
+    pub currency: String,
+
+    /// Payment method (cash, card).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_method: Option<PaymentMethod>,
+
+    /// Extraction metadata.
+    pub metadata: ExtractionMetadata,
+}
+
+/// An item on a receipt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptItem {
+    /// Item name as printed (often abbreviated).
+    pub description: String,
+
+    /// Quantity sold.
+    pub quantity: Decimal,
+
+    /// Gross unit price.
+    pub unit_price: Decimal,
+
+    /// Gross value of the item.
+    pub total: Decimal,
+
+    /// PTU group letter (`A` to `G`).
+    pub tax_group: char,
+}
+
+/// Taxable sales and PTU of one tax group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxGroup {
+    /// Group letter (`A` to `G`).
+    pub group: char,
+
+    /// VAT rate of the group.
+    pub rate: VatRate,
+
+    /// Gross taxable sales (`Sprzedaż opodatkowana`).
+    pub sales: Decimal,
+
+    /// PTU of the group.
+    pub vat: Decimal,
+}
+
+impl TaxGroup {
+    /// Usual rate of a group letter, for receipts that leave it out:
+    /// A 23%, B 8%, C 5%, D 0%, E exempt.
+    pub fn default_rate(group: char) -> Option<VatRate> {
+        match group {
+            'A' => Some(VatRate::Standard23),
+            'B' => Some(VatRate::Reduced8),
+            'C' => Some(VatRate::Reduced5),
+            'D' => Some(VatRate::Zero),
+            'E' => Some(VatRate::Exempt),
+            _ => None,
+        }
+    }
+}
+
+impl Receipt {
+    /// Create an empty receipt in PLN.
+    pub fn new() -> Self {
+        Self {
+            currency: "PLN".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Check the receipt's totals and NIPs.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        use IssueCode::*;
+        use Severity::*;
+
+        let mut issues = Vec::new();
+
+        if self.total.is_zero() {
+            issues.push(ValidationIssue::new(ZeroTotal, Warning, "total", "Total is zero"));
+        }
+
+        if !self.items.is_empty() {
+            let items: Decimal = self.items.iter().map(|i| i.total).sum();
+            if (items - self.total).abs() > tolerance() {
+                issues.push(ValidationIssue::new(
+                    GrossTotalMismatch,
+                    Warning,
+                    "total",
+                    format!("Items add up to {}, the total is {}", items, self.total),
+                ));
+            }
+        }
+
+        if !self.tax_groups.is_empty() {
+            let vat: Decimal = self.tax_groups.iter().map(|g| g.vat).sum();
+            if (vat - self.total_vat).abs() > tolerance() {
+                issues.push(ValidationIssue::new(
+                    VatTotalMismatch,
+                    Warning,
+                    "total_vat",
+                    format!("PTU of the groups adds up to {}, SUMA PTU is {}", vat, self.total_vat),
+                ));
+            }
+        }
+
+        for (field, nip) in [("seller.nip", &self.seller.nip), ("buyer_nip", &self.buyer_nip)] {
+            if let Some(nip) = nip.as_deref().filter(|nip| !validate_nip(nip)) {
+                issues.push(ValidationIssue::new(
+                    InvalidNip,
+                    Error,
+                    field,
+                    format!("Invalid NIP checksum: {}", nip),
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt() -> Receipt {
+        let mut receipt = Receipt::new();
+        receipt.seller.nip = Some("5261040828".to_string());
+        receipt.items = vec![ReceiptItem {
+            description: "Chleb".to_string(),
+            quantity: Decimal::ONE,
+            unit_price: Decimal::new(350, 2),
+            total: Decimal::new(350, 2),
+            tax_group: 'C',
+        }];
+        receipt.tax_groups = vec![TaxGroup {
+            group: 'C',
+            rate: VatRate::Reduced5,
+            sales: Decimal::new(350, 2),
+            vat: Decimal::new(17, 2),
+        }];
+        receipt.total_vat = Decimal::new(17, 2);
+        receipt.total = Decimal::new(350, 2);
+        receipt
+    }
+
+    #[test]
+    fn test_validate_receipt() {
+        assert!(receipt().validate().is_empty());
+
+        let mut wrong = receipt();
+        wrong.total = Decimal::new(450, 2);
+        wrong.buyer_nip = Some("1234567890".to_string());
+        let codes: Vec<IssueCode> = wrong.validate().iter().map(|i| i.code).collect();
+        assert_eq!(codes, [IssueCode::GrossTotalMismatch, IssueCode::InvalidNip]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Selection of the part of a document to process.
+//!
+//! Large mixed documents often hold the invoice on a few pages, or in one
+//! area of a scan. A [`Selection`] limits OCR to those pages and that
+//! region, and is recorded in the extraction metadata.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Pages and page region that were processed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    /// Processed pages; all pages when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages: Option<PageSet>,
+
+    /// Area of each page image that was recognized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+/// Set of 1-based page numbers, written as ranges (`1-3,7`).
+///
+/// Serializes as the sorted list of page numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<u32>")]
+pub struct PageSet(Vec<u32>);
+
+impl From<Vec<u32>> for PageSet {
+    fn from(mut pages: Vec<u32>) -> Self {
+        pages.retain(|&page| page > 0);
+        pages.sort_unstable();
+        pages.dedup();
+        Self(pages)
+    }
+}
+
+impl PageSet {
+    /// Whether `page` is selected.
+    pub fn contains(&self, page: u32) -> bool {
+        self.0.binary_search(&page).is_ok()
+    }
+
+    /// Highest selected page.
+    pub fn last(&self) -> u32 {
+        self.0.last().copied().unwrap_or(0)
+    }
+
+    /// Selected pages in ascending order.
+    pub fn pages(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for PageSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pages = Vec::new();
+
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (first, last) = part.split_once('-').unwrap_or((part, part));
+            let first = parse_page(first)?;
+            let last = parse_page(last)?;
+
+            if first > last {
+                return Err(format!("invalid page range '{}' (start is after end)", part));
+            }
+            pages.extend(first..=last);
+        }
+
+        if pages.is_empty() {
+            return Err("no pages selected, expected e.g. 1-3,7".to_string());
+        }
+
+        Ok(pages.into())
+    }
+}
+
+fn parse_page(s: &str) -> Result<u32, String> {
+    match s.trim().parse() {
+        Ok(0) => Err("page numbers start at 1".to_string()),
+        Ok(page) => Ok(page),
+        Err(_) => Err(format!("invalid page number '{}'", s.trim())),
+    }
+}
+
+impl fmt::Display for PageSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pages = self.0.iter().copied().peekable();
+        let mut first_range = true;
+
+        while let Some(start) = pages.next() {
+            let mut end = start;
+            while pages.peek() == Some(&(end + 1)) {
+                end = pages.next().unwrap_or(end);
+            }
+
+            if !first_range {
+                write!(f, ",")?;
+            }
+            first_range = false;
+
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rectangle in page image pixels, from the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid region '{}', expected x,y,w,h in pixels", s))?;
+
+        let [x, y, width, height] = values[..] else {
+            return Err(format!("invalid region '{}', expected x,y,w,h in pixels", s));
+        };
+
+        if width == 0 || height == 0 {
+            return Err("region width and height must be positive".to_string());
+        }
+
+        Ok(Self { x, y, width, height })
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_set_from_str() {
+        let pages: PageSet = "7, 1-3,2".parse().unwrap();
+
+        assert_eq!(pages.pages(), &[1, 2, 3, 7]);
+        assert!(pages.contains(2) && !pages.contains(4));
+        assert_eq!(pages.last(), 7);
+        assert_eq!(pages.to_string(), "1-3,7");
+
+        assert!("0-2".parse::<PageSet>().is_err());
+        assert!("3-1".parse::<PageSet>().is_err());
+        assert!("a".parse::<PageSet>().is_err());
+        assert!("".parse::<PageSet>().is_err());
+    }
+
+    #[test]
+    fn test_region_from_str() {
+        let region: Region = "10, 20,300,400".parse().unwrap();
+
+        assert_eq!(region, Region { x: 10, y: 20, width: 300, height: 400 });
+        assert_eq!(region.to_string(), "10,20,300,400");
+
+        assert!("10,20,300".parse::<Region>().is_err());
+        assert!("10,20,0,400".parse::<Region>().is_err());
+        assert!("-1,20,300,400".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_selection_json() {
+        let selection = Selection {
+            pages: Some("1-2".parse().unwrap()),
+            region: None,
+        };
+
+        let json = serde_json::to_value(&selection).unwrap();
+        assert_eq!(json, serde_json::json!({ "pages": [1, 2] }));
+        assert_eq!(serde_json::from_value::<Selection>(json).unwrap(), selection);
+    }
+}
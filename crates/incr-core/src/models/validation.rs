@@ -0,0 +1,826 @@
+//! Invoice validation profiles.
+//!
+//! Profiles select a rule set for [`Invoice::validate_profile`]:
+//! - `lenient`: only flags fields that indicate a failed extraction
+//! - `strict`: complete and arithmetically consistent data for accounting
+//! - `ksef`: strict rules plus the mandatory KSeF FA(3) fields and formats
+//!
+//! Findings are [`ValidationIssue`]s with a stable [`IssueCode`]. Extraction
+//! warnings (`metadata.warnings`) use the same type.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::invoice::{Invoice, InvoiceType};
+use crate::validate::{validate_nip, validate_vat_id};
+
+/// Named set of validation rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationProfile {
+    /// Extraction QA: only missing key fields.
+    Lenient,
+    /// Accounting: complete data with consistent totals.
+    #[default]
+    Strict,
+    /// KSeF: all mandatory FA(3) fields present with correct formats.
+    Ksef,
+}
+
+impl FromStr for ValidationProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lenient" => Ok(ValidationProfile::Lenient),
+            "strict" => Ok(ValidationProfile::Strict),
+            "ksef" => Ok(ValidationProfile::Ksef),
+            other => Err(format!(
+                "unknown validation profile '{}' (expected lenient, strict or ksef)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ValidationProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationProfile::Lenient => write!(f, "lenient"),
+            ValidationProfile::Strict => write!(f, "strict"),
+            ValidationProfile::Ksef => write!(f, "ksef"),
+        }
+    }
+}
+
+/// Machine-readable code of a validation issue or extraction warning.
+///
+/// Codes are stable, so consumers can filter and translate issues without
+/// parsing messages. Unknown codes deserialize as [`IssueCode::Other`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCode {
+    /// No invoice number.
+    MissingInvoiceNumber,
+    /// No issue date.
+    MissingIssueDate,
+    /// No issuer name.
+    MissingIssuerName,
+    /// No issuer NIP.
+    MissingIssuerNip,
+    /// No issuer address.
+    MissingIssuerAddress,
+    /// Neither receiver name nor NIP.
+    MissingReceiver,
+    /// No receiver name.
+    MissingReceiverName,
+    /// No line items.
+    MissingLineItems,
+    /// A line item without description.
+    MissingDescription,
+    /// A line item without unit of measure.
+    MissingUnit,
+    /// Correction invoice without the corrected invoice number.
+    MissingCorrectedInvoice,
+    /// Foreign-currency invoice without an exchange rate.
+    MissingExchangeRate,
+    /// Total gross amount is zero.
+    ZeroTotal,
+    /// NIP fails the checksum.
+    InvalidNip,
+    /// EU VAT ID fails the checksum of its country.
+    InvalidVatId,
+    /// Currency is not an ISO 4217 code.
+    InvalidCurrency,
+    /// A line item quantity is not positive.
+    InvalidQuantity,
+    /// Invoice number too long for KSeF.
+    InvoiceNumberTooLong,
+    /// Line item net amounts don't add up to the net total.
+    NetTotalMismatch,
+    /// Line item gross amounts don't add up to the gross total.
+    GrossTotalMismatch,
+    /// Net total plus VAT differs from the gross total.
+    VatTotalMismatch,
+    /// Pro forma invoice, which is not a tax document and is not sent to
+    /// KSeF.
+    ProformaInvoice,
+    /// Issuer and receiver share a NIP on an invoice not marked as a
+    /// self-invoice.
+    SameNip,
+    /// A date far from the reference date.
+    ImplausibleDate,
+    /// Due date before the issue date.
+    DueDateBeforeIssueDate,
+    /// Sale date too far from the issue date.
+    SaleDateFarFromIssueDate,
+    /// A vendor template could not be applied.
+    TemplateNotApplied,
+    /// Ensemble extractions disagree on a field.
+    EnsembleDisagreement,
+    /// Some pages could not be processed.
+    IncompletePages,
+    /// Party is not an active VAT payer on the white list.
+    NotActiveVatPayer,
+    /// Bank account is not on the white list for the issuer.
+    AccountNotWhitelisted,
+    /// The white list could not be checked.
+    WhitelistUnavailable,
+    /// A QR code printed on the invoice gives a different value.
+    BarcodeMismatch,
+    /// An EU VAT ID is not registered in VIES.
+    VatIdNotRegistered,
+    /// VIES could not be checked.
+    ViesUnavailable,
+    /// A NIP is not in the REGON register.
+    NotInRegon,
+    /// Party details differ from the REGON register.
+    RegonMismatch,
+    /// The REGON register could not be checked.
+    RegonUnavailable,
+    /// Any other issue, e.g. a plain message from an older version.
+    #[serde(other)]
+    Other,
+}
+
+impl IssueCode {
+    /// The code as serialized (e.g. `missing_issue_date`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IssueCode::MissingInvoiceNumber => "missing_invoice_number",
+            IssueCode::MissingIssueDate => "missing_issue_date",
+            IssueCode::MissingIssuerName => "missing_issuer_name",
+            IssueCode::MissingIssuerNip => "missing_issuer_nip",
+            IssueCode::MissingIssuerAddress => "missing_issuer_address",
+            IssueCode::MissingReceiver => "missing_receiver",
+            IssueCode::MissingReceiverName => "missing_receiver_name",
+            IssueCode::MissingLineItems => "missing_line_items",
+            IssueCode::MissingDescription => "missing_description",
+            IssueCode::MissingUnit => "missing_unit",
+            IssueCode::MissingCorrectedInvoice => "missing_corrected_invoice",
+            IssueCode::MissingExchangeRate => "missing_exchange_rate",
+            IssueCode::ZeroTotal => "zero_total",
+            IssueCode::InvalidNip => "invalid_nip",
+            IssueCode::InvalidVatId => "invalid_vat_id",
+            IssueCode::InvalidCurrency => "invalid_currency",
+            IssueCode::InvalidQuantity => "invalid_quantity",
+            IssueCode::InvoiceNumberTooLong => "invoice_number_too_long",
+            IssueCode::NetTotalMismatch => "net_total_mismatch",
+            IssueCode::GrossTotalMismatch => "gross_total_mismatch",
+            IssueCode::VatTotalMismatch => "vat_total_mismatch",
+            IssueCode::ProformaInvoice => "proforma_invoice",
+            IssueCode::SameNip => "same_nip",
+            IssueCode::ImplausibleDate => "implausible_date",
+            IssueCode::DueDateBeforeIssueDate => "due_date_before_issue_date",
+            IssueCode::SaleDateFarFromIssueDate => "sale_date_far_from_issue_date",
+            IssueCode::TemplateNotApplied => "template_not_applied",
+            IssueCode::EnsembleDisagreement => "ensemble_disagreement",
+            IssueCode::IncompletePages => "incomplete_pages",
+            IssueCode::NotActiveVatPayer => "not_active_vat_payer",
+            IssueCode::AccountNotWhitelisted => "account_not_whitelisted",
+            IssueCode::WhitelistUnavailable => "whitelist_unavailable",
+            IssueCode::BarcodeMismatch => "barcode_mismatch",
+            IssueCode::VatIdNotRegistered => "vat_id_not_registered",
+            IssueCode::ViesUnavailable => "vies_unavailable",
+            IssueCode::NotInRegon => "not_in_regon",
+            IssueCode::RegonMismatch => "regon_mismatch",
+            IssueCode::RegonUnavailable => "regon_unavailable",
+            IssueCode::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for IssueCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Severity of a validation issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Data is usable but should be reviewed.
+    Warning,
+    /// Data does not satisfy the profile.
+    Error,
+}
+
+/// A validation finding or extraction warning.
+///
+/// Also deserializes from a plain message, the format of warnings written
+/// by older versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "IssueRepr")]
+pub struct ValidationIssue {
+    /// What kind of problem this is.
+    pub code: IssueCode,
+    /// Severity of the problem.
+    pub severity: Severity,
+    /// Field path (e.g. `issuer.nip`, `line_items[2].quantity`); empty for
+    /// issues of the whole document.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub field: String,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl ValidationIssue {
+    /// Create an issue.
+    pub fn new(
+        code: IssueCode,
+        severity: Severity,
+        field: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            code,
+            severity,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create a warning.
+    pub fn warning(code: IssueCode, field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(code, Severity::Warning, field, message)
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IssueRepr {
+    Message(String),
+    Issue {
+        code: IssueCode,
+        severity: Severity,
+        #[serde(default)]
+        field: String,
+        message: String,
+    },
+}
+
+impl From<IssueRepr> for ValidationIssue {
+    fn from(repr: IssueRepr) -> Self {
+        match repr {
+            IssueRepr::Message(message) => Self::warning(IssueCode::Other, "", message),
+            IssueRepr::Issue {
+                code,
+                severity,
+                field,
+                message,
+            } => Self::new(code, severity, field, message),
+        }
+    }
+}
+
+/// Tolerance for comparing monetary totals.
+pub(crate) fn tolerance() -> Decimal {
+    Decimal::new(1, 2)
+}
+
+pub(crate) fn validate(invoice: &Invoice, profile: ValidationProfile) -> Vec<ValidationIssue> {
+    match profile {
+        ValidationProfile::Lenient => lenient(invoice),
+        ValidationProfile::Strict => strict(invoice),
+        ValidationProfile::Ksef => {
+            let mut issues = strict(invoice);
+            issues.extend(ksef(invoice));
+            issues
+        }
+    }
+}
+
+fn lenient(invoice: &Invoice) -> Vec<ValidationIssue> {
+    use IssueCode::*;
+    use Severity::Warning;
+
+    let mut issues = Vec::new();
+
+    if invoice.header.invoice_number.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingInvoiceNumber,
+            Warning,
+            "header.invoice_number",
+            "Missing invoice number",
+        ));
+    }
+
+    if invoice.header.issue_date.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingIssueDate,
+            Warning,
+            "header.issue_date",
+            "Missing issue date",
+        ));
+    }
+
+    // A foreign issuer is identified by its VAT ID
+    if invoice.issuer.nip.is_none() && invoice.issuer.vat_id.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingIssuerNip,
+            Warning,
+            "issuer.nip",
+            "Missing issuer NIP",
+        ));
+    }
+
+    if invoice.summary.total_gross == Decimal::ZERO {
+        issues.push(ValidationIssue::new(
+            ZeroTotal,
+            Warning,
+            "summary.total_gross",
+            "Total gross is zero",
+        ));
+    }
+
+    issues
+}
+
+fn strict(invoice: &Invoice) -> Vec<ValidationIssue> {
+    use IssueCode::*;
+    use Severity::Error;
+
+    let mut issues = Vec::new();
+
+    if invoice.header.invoice_number.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingInvoiceNumber,
+            Error,
+            "header.invoice_number",
+            "Missing invoice number",
+        ));
+    }
+
+    if invoice.header.issue_date.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingIssueDate,
+            Error,
+            "header.issue_date",
+            "Missing issue date",
+        ));
+    }
+
+    if invoice.issuer.name.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingIssuerName,
+            Error,
+            "issuer.name",
+            "Missing issuer name",
+        ));
+    }
+
+    if invoice.issuer.nip.is_none() && invoice.issuer.vat_id.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingIssuerNip,
+            Error,
+            "issuer.nip",
+            "Missing issuer NIP",
+        ));
+    }
+
+    let receiver = &invoice.receiver;
+    if receiver.name.is_empty() && receiver.nip.is_none() && receiver.vat_id.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingReceiver,
+            Error,
+            "receiver",
+            "Missing receiver information",
+        ));
+    }
+
+    if invoice.line_items.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingLineItems,
+            Error,
+            "line_items",
+            "No line items",
+        ));
+    }
+
+    if invoice.summary.total_gross == Decimal::ZERO {
+        issues.push(ValidationIssue::new(
+            ZeroTotal,
+            Error,
+            "summary.total_gross",
+            "Total gross is zero",
+        ));
+    }
+
+    // Advance invoices list the ordered goods but total the advance
+    // received; margin invoices show no VAT, so net amounts are not
+    // comparable
+    let invoice_type = invoice.header.invoice_type;
+    let check_vat = invoice_type != InvoiceType::Margin;
+    let check_gross = invoice_type != InvoiceType::Advance;
+    let check_net = check_gross && check_vat;
+
+    // Validate line item totals
+    let calculated_net: Decimal = invoice.line_items.iter().map(|i| i.total_net).sum();
+    let calculated_gross: Decimal = invoice.line_items.iter().map(|i| i.total_gross).sum();
+
+    if check_net && (calculated_net - invoice.summary.total_net).abs() > tolerance() {
+        issues.push(ValidationIssue::new(
+            NetTotalMismatch,
+            Error,
+            "summary.total_net",
+            format!(
+                "Line item net total ({}) differs from summary ({})",
+                calculated_net, invoice.summary.total_net
+            ),
+        ));
+    }
+
+    if check_gross && (calculated_gross - invoice.summary.total_gross).abs() > tolerance() {
+        issues.push(ValidationIssue::new(
+            GrossTotalMismatch,
+            Error,
+            "summary.total_gross",
+            format!(
+                "Line item gross total ({}) differs from summary ({})",
+                calculated_gross, invoice.summary.total_gross
+            ),
+        ));
+    }
+
+    let summary = &invoice.summary;
+    let vat_difference = summary.total_net + summary.total_vat - summary.total_gross;
+    if check_vat && vat_difference.abs() > tolerance() {
+        issues.push(ValidationIssue::new(
+            VatTotalMismatch,
+            Error,
+            "summary.total_vat",
+            format!(
+                "Net ({}) plus VAT ({}) differs from gross ({})",
+                summary.total_net, summary.total_vat, summary.total_gross
+            ),
+        ));
+    }
+
+    if let Some(nip) = invoice
+        .issuer
+        .nip
+        .as_deref()
+        .filter(|nip| !validate_nip(nip))
+    {
+        issues.push(ValidationIssue::new(
+            InvalidNip,
+            Error,
+            "issuer.nip",
+            format!("Invalid issuer NIP checksum: {}", nip),
+        ));
+    }
+
+    for (party, details) in [("issuer", &invoice.issuer), ("receiver", &invoice.receiver)] {
+        if let Some(vat_id) = details.vat_id.as_deref().filter(|id| !validate_vat_id(id)) {
+            issues.push(ValidationIssue::new(
+                InvalidVatId,
+                Error,
+                format!("{}.vat_id", party),
+                format!("Invalid {} VAT ID: {}", party, vat_id),
+            ));
+        }
+    }
+
+    // Flagged self-invoices are expected to repeat the NIP
+    if invoice.has_same_nip() && !invoice.header.self_invoice {
+        issues.push(ValidationIssue::new(
+            SameNip,
+            Severity::Warning,
+            "receiver.nip",
+            "Issuer and receiver have the same NIP but the invoice is not marked as a self-invoice",
+        ));
+    }
+
+    issues
+}
+
+fn ksef(invoice: &Invoice) -> Vec<ValidationIssue> {
+    use IssueCode::*;
+    use Severity::Error;
+
+    let mut issues = Vec::new();
+    let header = &invoice.header;
+
+    if header.issue_date.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingIssueDate,
+            Error,
+            "header.issue_date",
+            "Missing issue date (P_1)",
+        ));
+    }
+
+    if header.invoice_number.chars().count() > 256 {
+        issues.push(ValidationIssue::new(
+            InvoiceNumberTooLong,
+            Error,
+            "header.invoice_number",
+            "Invoice number longer than 256 characters (P_2)",
+        ));
+    }
+
+    if header.currency.len() != 3 || !header.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        issues.push(ValidationIssue::new(
+            InvalidCurrency,
+            Error,
+            "header.currency",
+            format!(
+                "Currency '{}' is not an ISO 4217 code (KodWaluty)",
+                header.currency
+            ),
+        ));
+    }
+
+    if header.invoice_type == InvoiceType::Proforma {
+        issues.push(ValidationIssue::new(
+            ProformaInvoice,
+            Error,
+            "header.invoice_type",
+            "Pro forma invoices are not issued in KSeF",
+        ));
+    }
+
+    if header.invoice_type == InvoiceType::Correction && header.correction_of.is_none() {
+        issues.push(ValidationIssue::new(
+            MissingCorrectedInvoice,
+            Error,
+            "header.correction_of",
+            "Correction invoice without corrected invoice number",
+        ));
+    }
+
+    // Only Polish taxpayers issue invoices in KSeF
+    if invoice.issuer.nip.is_none() && invoice.issuer.vat_id.is_some() {
+        issues.push(ValidationIssue::new(
+            MissingIssuerNip,
+            Error,
+            "issuer.nip",
+            "Missing issuer NIP, a VAT ID is not enough (Podmiot1)",
+        ));
+    }
+
+    if invoice.issuer.address.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingIssuerAddress,
+            Error,
+            "issuer.address",
+            "Missing issuer address (Podmiot1)",
+        ));
+    }
+
+    if invoice.receiver.name.is_empty() {
+        issues.push(ValidationIssue::new(
+            MissingReceiverName,
+            Error,
+            "receiver.name",
+            "Missing receiver name (Podmiot2)",
+        ));
+    }
+
+    if let Some(nip) = invoice
+        .receiver
+        .nip
+        .as_deref()
+        .filter(|nip| !validate_nip(nip))
+    {
+        issues.push(ValidationIssue::new(
+            InvalidNip,
+            Error,
+            "receiver.nip",
+            format!("Invalid receiver NIP checksum: {}", nip),
+        ));
+    }
+
+    for (i, item) in invoice.line_items.iter().enumerate() {
+        if item.description.trim().is_empty() {
+            issues.push(ValidationIssue::new(
+                MissingDescription,
+                Error,
+                format!("line_items[{}].description", i),
+                format!("Line item {} has no description (P_7)", i + 1),
+            ));
+        }
+
+        if item.quantity <= Decimal::ZERO {
+            issues.push(ValidationIssue::new(
+                InvalidQuantity,
+                Error,
+                format!("line_items[{}].quantity", i),
+                format!("Line item {} has non-positive quantity (P_8B)", i + 1),
+            ));
+        }
+
+        if item.unit.is_none() {
+            issues.push(ValidationIssue::new(
+                MissingUnit,
+                Error,
+                format!("line_items[{}].unit", i),
+                format!("Line item {} has no unit of measure (P_8A)", i + 1),
+            ));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::invoice::{LineItem, VatRate};
+
+    fn complete_invoice() -> Invoice {
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = "FV/1/2024".to_string();
+        invoice.header.issue_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.issuer.name = "Firma Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("5261040828".to_string());
+        invoice.issuer.address.raw = Some("ul. Prosta 1, 00-001 Warszawa".to_string());
+        invoice.receiver.name = "Klient S.A.".to_string();
+        invoice.line_items.push(LineItem {
+            ordinal: Some(1),
+            description: "Usługa".to_string(),
+            code: None,
+            quantity: Decimal::ONE,
+            unit: Some("szt.".to_string()),
+            unit_price_net: Decimal::new(100, 0),
+            unit_price_gross: None,
+            vat_rate: VatRate::Standard23,
+            total_net: Decimal::new(100, 0),
+            vat_amount: Decimal::new(23, 0),
+            total_gross: Decimal::new(123, 0),
+            discount_percent: None,
+            gtu_codes: Vec::new(),
+        });
+        invoice.summary.total_net = Decimal::new(100, 0);
+        invoice.summary.total_vat = Decimal::new(23, 0);
+        invoice.summary.total_gross = Decimal::new(123, 0);
+        invoice
+    }
+
+    #[test]
+    fn test_complete_invoice_passes_all_profiles() {
+        let invoice = complete_invoice();
+        for profile in [
+            ValidationProfile::Lenient,
+            ValidationProfile::Strict,
+            ValidationProfile::Ksef,
+        ] {
+            assert!(invoice.validate_profile(profile).is_empty(), "{}", profile);
+        }
+    }
+
+    #[test]
+    fn test_profiles_differ() {
+        let mut invoice = complete_invoice();
+        invoice.issuer.address.raw = None;
+        invoice.summary.total_vat = Decimal::new(20, 0);
+
+        assert!(
+            invoice
+                .validate_profile(ValidationProfile::Lenient)
+                .is_empty()
+        );
+
+        let strict = invoice.validate_profile(ValidationProfile::Strict);
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].code, IssueCode::VatTotalMismatch);
+
+        let ksef = invoice.validate_profile(ValidationProfile::Ksef);
+        assert!(ksef.iter().any(|i| i.field == "issuer.address"));
+    }
+
+    #[test]
+    fn test_invoice_type_rules() {
+        // Margin invoice: gross only, no VAT shown
+        let mut margin = complete_invoice();
+        margin.header.invoice_type = InvoiceType::Margin;
+        margin.line_items[0].total_net = Decimal::new(123, 0);
+        margin.line_items[0].vat_amount = Decimal::ZERO;
+        margin.summary.total_net = Decimal::ZERO;
+        margin.summary.total_vat = Decimal::ZERO;
+        assert!(margin.validate_profile(ValidationProfile::Strict).is_empty());
+
+        // Advance invoice: ordered goods worth 123, advance of 50 received
+        let mut advance = complete_invoice();
+        advance.header.invoice_type = InvoiceType::Advance;
+        advance.summary.total_net = Decimal::new(4065, 2);
+        advance.summary.total_vat = Decimal::new(935, 2);
+        advance.summary.total_gross = Decimal::new(50, 0);
+        assert!(advance.validate_profile(ValidationProfile::Strict).is_empty());
+
+        let mut proforma = complete_invoice();
+        proforma.header.invoice_type = InvoiceType::Proforma;
+        assert!(proforma.validate_profile(ValidationProfile::Strict).is_empty());
+        let ksef = proforma.validate_profile(ValidationProfile::Ksef);
+        assert_eq!(ksef.len(), 1);
+        assert_eq!(ksef[0].code, IssueCode::ProformaInvoice);
+    }
+
+    #[test]
+    fn test_same_nip_needs_self_invoice_flag() {
+        let mut invoice = complete_invoice();
+        invoice.receiver.nip = Some("526-104-08-28".to_string());
+
+        let issues = invoice.validate_profile(ValidationProfile::Strict);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+
+        invoice.header.self_invoice = true;
+        assert!(invoice.validate_profile(ValidationProfile::Strict).is_empty());
+    }
+
+    #[test]
+    fn test_foreign_parties() {
+        let mut invoice = complete_invoice();
+        invoice.receiver.nip = None;
+        invoice.receiver.vat_id = Some("DE136695976".to_string());
+        for profile in [
+            ValidationProfile::Lenient,
+            ValidationProfile::Strict,
+            ValidationProfile::Ksef,
+        ] {
+            assert!(invoice.validate_profile(profile).is_empty(), "{}", profile);
+        }
+
+        invoice.receiver.vat_id = Some("DE136695977".to_string());
+        let strict = invoice.validate_profile(ValidationProfile::Strict);
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].code, IssueCode::InvalidVatId);
+        assert_eq!(strict[0].field, "receiver.vat_id");
+
+        // A foreign seller can't issue a KSeF invoice
+        let mut invoice = complete_invoice();
+        invoice.issuer.nip = None;
+        invoice.issuer.vat_id = Some("FR40303265045".to_string());
+        assert!(invoice.validate_profile(ValidationProfile::Strict).is_empty());
+        let ksef = invoice.validate_profile(ValidationProfile::Ksef);
+        assert_eq!(ksef.len(), 1);
+        assert_eq!(ksef[0].code, IssueCode::MissingIssuerNip);
+    }
+
+    #[test]
+    fn test_missing_issue_date() {
+        let mut invoice = complete_invoice();
+        invoice.header.issue_date = None;
+
+        let lenient = invoice.validate_profile(ValidationProfile::Lenient);
+        assert_eq!(lenient.len(), 1);
+        assert_eq!(lenient[0].field, "header.issue_date");
+        assert_eq!(lenient[0].severity, Severity::Warning);
+
+        let strict = invoice.validate_profile(ValidationProfile::Strict);
+        assert_eq!(strict[0].severity, Severity::Error);
+
+        let json = serde_json::to_value(&invoice).unwrap();
+        assert!(json["header"]["issue_date"].is_null());
+    }
+
+    #[test]
+    fn test_issue_serialization() {
+        let issue = ValidationIssue::warning(
+            IssueCode::MissingIssueDate,
+            "header.issue_date",
+            "Could not extract issue date",
+        );
+        let json = serde_json::to_value(&issue).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "missing_issue_date",
+                "severity": "warning",
+                "field": "header.issue_date",
+                "message": "Could not extract issue date",
+            })
+        );
+        assert_eq!(serde_json::from_value::<ValidationIssue>(json).unwrap(), issue);
+        assert_eq!(issue.code.to_string(), "missing_issue_date");
+
+        // Plain messages of older versions, and codes of newer ones
+        let old: ValidationIssue = serde_json::from_str(r#""Total gross is zero""#).unwrap();
+        assert_eq!(old, ValidationIssue::warning(IssueCode::Other, "", "Total gross is zero"));
+        let newer: ValidationIssue = serde_json::from_str(
+            r#"{"code": "something_new", "severity": "error", "message": "?"}"#,
+        )
+        .unwrap();
+        assert_eq!(newer.code, IssueCode::Other);
+    }
+
+    #[test]
+    fn test_profile_from_str() {
+        assert_eq!(
+            "KSeF".parse::<ValidationProfile>(),
+            Ok(ValidationProfile::Ksef)
+        );
+        assert!("relaxed".parse::<ValidationProfile>().is_err());
+    }
+}
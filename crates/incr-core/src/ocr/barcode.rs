@@ -0,0 +1,246 @@
+//! Decoding QR codes and barcodes on page images.
+//!
+//! Invoices print a QR code for paying by bank transfer or for verifying
+//! the invoice in KSeF. When layout analysis ran, only its figure regions
+//! (with a margin) are decoded; otherwise the whole page is. Decoding is
+//! done by a [`BarcodeDecoder`]; with the `barcode` feature,
+//! [`RxingDecoder`] decodes QR codes, Data Matrix, Aztec, PDF417 and the
+//! common linear symbologies.
+
+use image::{DynamicImage, GrayImage};
+
+#[cfg(any(feature = "native", feature = "wasm"))]
+use crate::models::capabilities::{Capabilities, Stage};
+#[cfg(any(feature = "native", feature = "wasm"))]
+use crate::models::config::OcrConfig;
+
+use super::{Barcode, RegionBox};
+
+/// Margin added around figure regions, as a fraction of their size: the
+/// quiet zone a decoder needs is often cut off by the layout model.
+const FIGURE_MARGIN: f32 = 0.1;
+
+/// A symbol decoded from an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSymbol {
+    /// Symbology, e.g. `qr_code`.
+    pub format: String,
+    /// Decoded text.
+    pub payload: String,
+}
+
+/// Decodes the barcodes in an image.
+pub trait BarcodeDecoder: Send + Sync {
+    /// Every symbol found in `image`; empty if there is none.
+    fn decode(&self, image: &GrayImage) -> Vec<DecodedSymbol>;
+}
+
+/// Decode the barcodes on a page.
+///
+/// With `figures` from layout analysis only those regions are decoded,
+/// otherwise the whole image. Each code is listed once, with the region
+/// it was found in.
+pub fn scan_barcodes(
+    decoder: &dyn BarcodeDecoder,
+    image: &DynamicImage,
+    figures: Option<&[RegionBox]>,
+) -> Vec<Barcode> {
+    let (width, height) = (image.width(), image.height());
+    let regions: Vec<[f32; 4]> = match figures {
+        Some(figures) => figures
+            .iter()
+            .map(|figure| with_margin(figure.bbox, width, height))
+            .collect(),
+        None => vec![[0.0, 0.0, width as f32, height as f32]],
+    };
+
+    let mut barcodes: Vec<Barcode> = Vec::new();
+    for bbox in regions {
+        let [x1, y1, x2, y2] = bbox;
+        let (w, h) = ((x2 - x1) as u32, (y2 - y1) as u32);
+        if w == 0 || h == 0 {
+            continue;
+        }
+        let crop = image.crop_imm(x1 as u32, y1 as u32, w, h).to_luma8();
+
+        for symbol in decoder.decode(&crop) {
+            let seen = barcodes
+                .iter()
+                .any(|b| b.format == symbol.format && b.payload == symbol.payload);
+            if !seen {
+                barcodes.push(Barcode {
+                    format: symbol.format,
+                    payload: symbol.payload,
+                    bbox,
+                });
+            }
+        }
+    }
+    barcodes
+}
+
+/// `bbox` grown by [`FIGURE_MARGIN`], clamped to the image.
+fn with_margin(bbox: [f32; 4], width: u32, height: u32) -> [f32; 4] {
+    let [x1, y1, x2, y2] = bbox;
+    let dx = (x2 - x1) * FIGURE_MARGIN;
+    let dy = (y2 - y1) * FIGURE_MARGIN;
+    [
+        (x1 - dx).max(0.0).floor(),
+        (y1 - dy).max(0.0).floor(),
+        (x2 + dx).min(width as f32).ceil(),
+        (y2 + dy).min(height as f32).ceil(),
+    ]
+}
+
+/// Decoder backed by `rxing`, a Rust port of ZXing.
+#[cfg(feature = "barcode")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RxingDecoder;
+
+#[cfg(feature = "barcode")]
+impl BarcodeDecoder for RxingDecoder {
+    fn decode(&self, image: &GrayImage) -> Vec<DecodedSymbol> {
+        let (width, height) = image.dimensions();
+        // "Not found" is the error for images without a code
+        rxing::helpers::detect_multiple_in_luma(image.as_raw().clone(), width, height)
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|result| DecodedSymbol {
+                        format: format!("{:?}", result.getBarcodeFormat()).to_lowercase(),
+                        payload: result.getText().to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// The decoder an engine uses: `custom`, or the built-in one with the
+/// `barcode` feature; none if `ocr.decode_barcodes` is off.
+#[cfg(any(feature = "native", feature = "wasm"))]
+pub(crate) fn engine_decoder(
+    config: &OcrConfig,
+    custom: Option<Box<dyn BarcodeDecoder>>,
+) -> Option<Box<dyn BarcodeDecoder>> {
+    if !config.decode_barcodes {
+        return None;
+    }
+    #[cfg(feature = "barcode")]
+    let custom = custom.or_else(|| Some(Box::new(RxingDecoder)));
+    custom
+}
+
+/// Why an engine has no decoder.
+#[cfg(any(feature = "native", feature = "wasm"))]
+pub(crate) fn no_decoder_reason(config: &OcrConfig) -> &'static str {
+    if config.decode_barcodes {
+        "built without the barcode feature"
+    } else {
+        "disabled by ocr.decode_barcodes"
+    }
+}
+
+/// Decode the barcodes on a page with the engine's decoder, recording the
+/// stage in `capabilities`.
+#[cfg(any(feature = "native", feature = "wasm"))]
+pub(crate) fn decode_page(
+    decoder: Option<&dyn BarcodeDecoder>,
+    image: &DynamicImage,
+    figures: Option<&[RegionBox]>,
+    capabilities: &mut Capabilities,
+) -> Vec<Barcode> {
+    let Some(decoder) = decoder else {
+        return Vec::new();
+    };
+
+    let barcodes = scan_barcodes(decoder, image, figures);
+    match figures {
+        _ if !barcodes.is_empty() => capabilities.ran(Stage::Barcodes),
+        Some([]) => capabilities.skipped(Stage::Barcodes, "no figure regions detected"),
+        _ => capabilities.skipped(Stage::Barcodes, "no codes found"),
+    }
+    barcodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+    use std::sync::Mutex;
+
+    /// Reports a fixed symbol for every image at least `min_width` wide,
+    /// and records the sizes it was given.
+    struct FakeDecoder {
+        min_width: u32,
+        sizes: Mutex<Vec<(u32, u32)>>,
+    }
+
+    impl BarcodeDecoder for FakeDecoder {
+        fn decode(&self, image: &GrayImage) -> Vec<DecodedSymbol> {
+            self.sizes.lock().unwrap().push(image.dimensions());
+            if image.width() < self.min_width {
+                return Vec::new();
+            }
+            vec![DecodedSymbol {
+                format: "qr_code".to_string(),
+                payload: "hello".to_string(),
+            }]
+        }
+    }
+
+    fn decoder(min_width: u32) -> FakeDecoder {
+        FakeDecoder {
+            min_width,
+            sizes: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn figure(bbox: [f32; 4]) -> RegionBox {
+        RegionBox {
+            region_type: "figure".to_string(),
+            bbox,
+            confidence: 0.9,
+            structure: None,
+        }
+    }
+
+    fn page() -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(400, 300, Luma([255])))
+    }
+
+    #[test]
+    fn test_whole_page_without_layout() {
+        let decoder = decoder(1);
+        let barcodes = scan_barcodes(&decoder, &page(), None);
+
+        assert_eq!(barcodes.len(), 1);
+        assert_eq!(barcodes[0].bbox, [0.0, 0.0, 400.0, 300.0]);
+        assert_eq!(*decoder.sizes.lock().unwrap(), vec![(400, 300)]);
+    }
+
+    #[test]
+    fn test_figures_with_margin() {
+        let decoder = decoder(1);
+        let figures = [figure([100.0, 100.0, 200.0, 200.0]), figure([350.0, 0.0, 400.0, 50.0])];
+        let barcodes = scan_barcodes(&decoder, &page(), Some(&figures));
+
+        // The same payload found in both regions is listed once
+        assert_eq!(barcodes.len(), 1);
+        assert_eq!(barcodes[0].bbox, [90.0, 90.0, 210.0, 210.0]);
+        assert_eq!(*decoder.sizes.lock().unwrap(), vec![(120, 120), (55, 55)]);
+    }
+
+    #[test]
+    fn test_no_figures() {
+        let decoder = decoder(1);
+        assert!(scan_barcodes(&decoder, &page(), Some(&[])).is_empty());
+        assert!(decoder.sizes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_nothing_decoded() {
+        let figures = [figure([0.0, 0.0, 50.0, 50.0])];
+        assert!(scan_barcodes(&decoder(100), &page(), Some(&figures)).is_empty());
+    }
+}
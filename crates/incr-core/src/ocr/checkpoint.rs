@@ -0,0 +1,266 @@
+//! Per-page OCR checkpoints for long documents.
+//!
+//! The OCR results of every completed page are appended to a JSON Lines
+//! journal named after the document hash. A run that crashes or is killed
+//! part way through a large PDF resumes from the pages already in the
+//! journal instead of starting over. Journals not touched for a week are
+//! deleted.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::OcrResult;
+use crate::audit::sha256_hex;
+use crate::error::IncrError;
+use crate::models::config::IncrConfig;
+use crate::models::selection::Region;
+
+/// Journals left unchanged this long are deleted when a checkpoint is
+/// opened in their directory: their run was abandoned, or failed in a way
+/// resuming doesn't fix.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// First journal line: what the results were produced with.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Header {
+    /// Hash of the OCR and model settings.
+    settings: String,
+}
+
+/// One completed page.
+#[derive(Debug, Serialize, Deserialize)]
+struct PageEntry {
+    /// Page number (1-based).
+    page: u32,
+    /// OCR results of the page images, in order.
+    results: Vec<OcrResult>,
+}
+
+/// Journal of OCR results for one document.
+pub struct OcrCheckpoint {
+    path: PathBuf,
+    file: File,
+    pages: BTreeMap<u32, Vec<OcrResult>>,
+}
+
+impl OcrCheckpoint {
+    /// Open the journal for `document` in `dir`, loading the pages completed
+    /// by earlier runs. `region` is the page area being recognized, if OCR
+    /// is limited to one.
+    ///
+    /// Pages recorded with different OCR or model settings or another region
+    /// are discarded, as is a last line left incomplete by a crash. Stale
+    /// journals of other documents are deleted.
+    pub fn open(
+        dir: &Path,
+        document: &[u8],
+        config: &IncrConfig,
+        region: Option<Region>,
+    ) -> Result<Self, IncrError> {
+        std::fs::create_dir_all(dir)?;
+        prune(dir);
+
+        let path = dir.join(format!("{}.jsonl", sha256_hex(document)));
+        let header = Header {
+            settings: sha256_hex(
+                &serde_json::to_vec(&(&config.ocr, &config.models, region)).unwrap_or_default(),
+            ),
+        };
+
+        let pages = match File::open(&path) {
+            Ok(file) => read_pages(file, &header).unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Rewrite the journal so new pages don't follow a partial line. The
+        // copy replaces the old journal only once complete, so a crash now
+        // doesn't lose the pages.
+        let temp_path = path.with_extension("jsonl.tmp");
+        let mut checkpoint = Self {
+            file: File::create(&temp_path)?,
+            path,
+            pages: BTreeMap::new(),
+        };
+        checkpoint.write_line(&header)?;
+        for (page, results) in pages {
+            let entry = PageEntry { page, results };
+            checkpoint.write_line(&entry)?;
+            checkpoint.pages.insert(entry.page, entry.results);
+        }
+        checkpoint.file.sync_data()?;
+        std::fs::rename(&temp_path, &checkpoint.path)?;
+
+        Ok(checkpoint)
+    }
+
+    /// Journal file location.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Results of a page completed earlier, if any.
+    pub fn page(&self, page: u32) -> Option<&[OcrResult]> {
+        self.pages.get(&page).map(Vec::as_slice)
+    }
+
+    /// Number of completed pages.
+    pub fn completed(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Record the results of a completed page. The line is synced to disk
+    /// before returning.
+    pub fn record(&mut self, page: u32, results: &[OcrResult]) -> Result<(), IncrError> {
+        let entry = PageEntry {
+            page,
+            results: results.to_vec(),
+        };
+        self.write_line(&entry)?;
+        self.file.sync_data()?;
+
+        self.pages.insert(page, entry.results);
+        Ok(())
+    }
+
+    /// Delete the journal once the document has been fully processed.
+    pub fn remove(self) -> Result<(), IncrError> {
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> Result<(), IncrError> {
+        let mut line = serde_json::to_string(value)
+            .map_err(|e| IncrError::Io(std::io::Error::other(e)))?;
+        line.push('\n');
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Delete journals (and copies left by a crash while rewriting one) in
+/// `dir` older than [`MAX_AGE`]. Failures only leave files behind, so they
+/// are ignored.
+fn prune(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_journal = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(".jsonl") || name.ends_with(".jsonl.tmp"));
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > MAX_AGE);
+        if is_journal && stale {
+            debug!("Deleting stale OCR checkpoint {}", path.display());
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+/// Completed pages of a journal, or `None` if it was written with other
+/// settings or can't be read.
+fn read_pages(file: File, header: &Header) -> Option<BTreeMap<u32, Vec<OcrResult>>> {
+    let mut lines = BufReader::new(file).lines();
+
+    let first: Header = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+    if first != *header {
+        return None;
+    }
+
+    // A crash can leave the last line incomplete; that page is redone.
+    Some(
+        lines
+            .map_while(Result::ok)
+            .map_while(|line| serde_json::from_str::<PageEntry>(&line).ok())
+            .map(|entry| (entry.page, entry.results))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+
+    fn result(text: &str) -> OcrResult {
+        OcrResult {
+            boxes: Vec::new(),
+            text: text.to_string(),
+            processing_time_ms: 1,
+            image_size: (10, 10),
+            layout: None,
+            capabilities: Default::default(),
+            barcodes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_resume_from_journal() {
+        let dir = std::env::temp_dir().join(format!("incr-checkpoint-{}", std::process::id()));
+        let config = IncrConfig::default();
+
+        let mut checkpoint = OcrCheckpoint::open(&dir, b"%PDF-1", &config, None).unwrap();
+        checkpoint.record(1, &[result("page one")]).unwrap();
+        checkpoint.record(2, &[result("page two")]).unwrap();
+        drop(checkpoint);
+
+        // Simulate a crash while writing page 3
+        let path = dir.join(format!("{}.jsonl", sha256_hex(b"%PDF-1")));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"page\":3,\"resu").unwrap();
+
+        let checkpoint = OcrCheckpoint::open(&dir, b"%PDF-1", &config, None).unwrap();
+        assert_eq!(checkpoint.completed(), 2);
+        assert_eq!(checkpoint.page(2).unwrap()[0].text, "page two");
+        assert!(checkpoint.page(3).is_none());
+
+        // Another region or other settings invalidate the journal
+        let region = "0,0,100,100".parse().ok();
+        let checkpoint = OcrCheckpoint::open(&dir, b"%PDF-1", &config, region).unwrap();
+        assert_eq!(checkpoint.completed(), 0);
+
+        let mut other = IncrConfig::default();
+        other.ocr.max_image_size = 1024;
+        let checkpoint = OcrCheckpoint::open(&dir, b"%PDF-1", &other, None).unwrap();
+        assert_eq!(checkpoint.completed(), 0);
+
+        checkpoint.remove().unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stale_journals_pruned() {
+        let dir =
+            std::env::temp_dir().join(format!("incr-checkpoint-prune-{}", std::process::id()));
+        let config = IncrConfig::default();
+
+        let checkpoint = OcrCheckpoint::open(&dir, b"%PDF-old", &config, None).unwrap();
+        let stale = checkpoint.path().to_path_buf();
+        drop(checkpoint);
+        let week_ago = SystemTime::now() - MAX_AGE - Duration::from_secs(60);
+        File::options().write(true).open(&stale).unwrap().set_modified(week_ago).unwrap();
+
+        let checkpoint = OcrCheckpoint::open(&dir, b"%PDF-new", &config, None).unwrap();
+        assert!(!stale.exists());
+        assert!(checkpoint.path().exists());
+        assert!(!checkpoint.path().with_extension("jsonl.tmp").exists());
+
+        checkpoint.remove().unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -1,12 +1,19 @@
 //! Angle classification for text regions.
 
-use image::DynamicImage;
+use image::{DynamicImage, GenericImageView};
 use tracing::debug;
 
 use crate::error::OcrError;
 use incr_inference::{InferenceBackend, InputTensor, OutputTensor};
 
 use super::preprocessing::ImagePreprocessor;
+use super::VERTICAL_ASPECT;
+
+/// Whether a crop is at least [`VERTICAL_ASPECT`] times taller than wide.
+fn is_tall(image: &DynamicImage) -> bool {
+    let (width, height) = image.dimensions();
+    height as f32 >= width as f32 * VERTICAL_ASPECT
+}
 
 /// Angle classifier for detecting text orientation.
 pub struct AngleClassifier<B: InferenceBackend> {
@@ -25,6 +32,16 @@ impl<B: InferenceBackend> AngleClassifier<B> {
         }
     }
 
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
     /// Set classification threshold.
     pub fn with_threshold(mut self, threshold: f32) -> Self {
         self.threshold = threshold;
@@ -76,6 +93,69 @@ impl<B: InferenceBackend> AngleClassifier<B> {
         Ok((angle, confidence))
     }
 
+    /// Turn a text line crop upright, returning it with the clockwise
+    /// rotation (0, 90, 180 or 270) its text was found in.
+    ///
+    /// Crops at least [`VERTICAL_ASPECT`] times taller than wide are read
+    /// as vertical lines and turned counter-clockwise before the 0/180
+    /// classification, unless they are shorter than two lines of
+    /// `line_height`: a lone digit in a table cell is tall too.
+    pub fn upright(
+        &self,
+        line: DynamicImage,
+        line_height: f32,
+    ) -> Result<(DynamicImage, i32), OcrError> {
+        let vertical = is_tall(&line) && line.height() as f32 >= 2.0 * line_height;
+        let (line, base) = if vertical {
+            (line.rotate270(), 90)
+        } else {
+            (line, 0)
+        };
+
+        let (angle, _confidence) = self.classify(&line)?;
+        if angle == 180 {
+            Ok((line.rotate180(), base + 180))
+        } else {
+            Ok((line, base))
+        }
+    }
+
+    /// Orientation of a page from crops of a sample of its text lines:
+    /// the clockwise rotation (0, 90, 180 or 270) its text appears in.
+    ///
+    /// Most lines of a page scanned sideways are taller than wide; they
+    /// are turned counter-clockwise and classified, and the majority
+    /// tells 90 from 270. An upright page is upside down only if most of
+    /// its lines are classified as 180 above the threshold.
+    pub fn page_orientation(&self, lines: &[DynamicImage]) -> Result<i32, OcrError> {
+        let tall: Vec<&DynamicImage> = lines.iter().filter(|line| is_tall(line)).collect();
+        let sideways = tall.len() * 2 > lines.len();
+
+        let sample = if sideways { tall } else { lines.iter().collect() };
+        let mut flipped = 0;
+        for line in &sample {
+            let (angle, confidence) = if sideways {
+                self.classify(&line.rotate270())?
+            } else {
+                self.classify(line)?
+            };
+            if angle == 180 && (sideways || confidence > self.threshold) {
+                flipped += 1;
+            }
+        }
+        let flipped = flipped * 2 > sample.len();
+
+        let orientation = match (sideways, flipped) {
+            (false, false) => 0,
+            (false, true) => 180,
+            (true, false) => 90,
+            (true, true) => 270,
+        };
+        debug!("Page orientation: {}° from {} lines", orientation, lines.len());
+
+        Ok(orientation)
+    }
+
     /// Classify multiple images in a batch.
     pub fn classify_batch(&self, images: &[DynamicImage]) -> Result<Vec<(i32, f32)>, OcrError> {
         // For simplicity, process one at a time
@@ -98,3 +178,85 @@ impl<B: InferenceBackend> AngleClassifier<B> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+    use ndarray::ArrayD;
+
+    /// Classifies a line as 180 when its right half is darker: text
+    /// starts at the left.
+    struct InkSide {
+        names: Vec<String>,
+    }
+
+    impl InferenceBackend for InkSide {
+        fn run(
+            &self,
+            inputs: &[(&str, InputTensor)],
+        ) -> incr_inference::Result<Vec<(String, OutputTensor)>> {
+            let InputTensor::Float32(tensor) = &inputs[0].1 else {
+                unreachable!()
+            };
+            let width = tensor.shape()[3];
+            let (mut left, mut right) = (0.0, 0.0);
+            for (index, value) in tensor.indexed_iter() {
+                if index[3] < width / 2 {
+                    left += value;
+                } else {
+                    right += value;
+                }
+            }
+            let probs = if right < left { vec![0.0, 1.0] } else { vec![1.0, 0.0] };
+            let output = ArrayD::from_shape_vec(vec![1, 2], probs).unwrap();
+            Ok(vec![("output".to_string(), OutputTensor::Float32(output))])
+        }
+
+        fn input_names(&self) -> &[String] {
+            &self.names
+        }
+
+        fn output_names(&self) -> &[String] {
+            &self.names
+        }
+    }
+
+    /// A white crop, dark where `ink` holds.
+    fn crop(width: u32, height: u32, ink: impl Fn(u32, u32) -> bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+            if ink(x, y) { Luma([0]) } else { Luma([255]) }
+        }))
+    }
+
+    #[test]
+    fn test_upright() {
+        let classifier = AngleClassifier::new(InkSide { names: Vec::new() });
+        let angle = |line: DynamicImage| classifier.upright(line, 24.0).unwrap().1;
+
+        // Horizontal lines starting at the left or, upside down, the right
+        assert_eq!(angle(crop(120, 24, |x, _| x < 40)), 0);
+        assert_eq!(angle(crop(120, 24, |x, _| x > 80)), 180);
+
+        // Vertical lines reading down or up
+        let (upright, down) = classifier.upright(crop(24, 120, |_, y| y < 40), 24.0).unwrap();
+        assert_eq!((down, upright.width(), upright.height()), (90, 120, 24));
+        assert_eq!(angle(crop(24, 120, |_, y| y > 80)), 270);
+
+        // A lone digit is tall but not a vertical line
+        assert_eq!(angle(crop(12, 24, |_, y| y < 8)), 0);
+    }
+
+    #[test]
+    fn test_page_orientation() {
+        let classifier = AngleClassifier::new(InkSide { names: Vec::new() });
+        let lines = |line: DynamicImage| vec![line; 5];
+
+        let orientation = |lines: Vec<DynamicImage>| classifier.page_orientation(&lines).unwrap();
+        assert_eq!(orientation(lines(crop(120, 24, |x, _| x < 40))), 0);
+        assert_eq!(orientation(lines(crop(120, 24, |x, _| x > 80))), 180);
+        assert_eq!(orientation(lines(crop(24, 120, |_, y| y < 40))), 90);
+        assert_eq!(orientation(lines(crop(24, 120, |_, y| y > 80))), 270);
+        assert_eq!(orientation(Vec::new()), 0);
+    }
+}
@@ -0,0 +1,238 @@
+//! Deskewing of pages photographed or scanned at an angle.
+//!
+//! Text lines of a skewed page are slanted, so detection boxes overlap
+//! and recognition reads parts of neighbouring lines. The skew angle is
+//! estimated with a projection profile: the page's dark pixels are
+//! projected onto the vertical axis at each candidate angle, and at the
+//! page's angle the lines fall into a few sharp peaks separated by empty
+//! rows. The page is then rotated back by that angle before detection.
+//!
+//! Pages scanned sideways are left to [`orientation`](super::orientation).
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::models::capabilities::{Capabilities, Stage};
+use crate::models::config::OcrConfig;
+
+/// Largest skew corrected, in degrees; pages rotated further are turned
+/// sideways or upside down, which the angle classifier handles.
+pub const MAX_SKEW_ANGLE: f32 = 15.0;
+
+/// Smaller skews do not affect detection and are left alone.
+const MIN_SKEW_ANGLE: f32 = 0.3;
+
+/// Longer side of the downscaled copy the angle is estimated on.
+const ESTIMATION_SIZE: u32 = 1024;
+
+/// Step of the coarse search; the best step is refined tenfold.
+const COARSE_STEP: f32 = 0.5;
+
+/// Fewer dark pixels than this carry no reliable line structure.
+const MIN_INK_PIXELS: usize = 200;
+
+/// Estimate the skew of a page in degrees, positive when text lines
+/// descend to the right, or `None` for a page without enough text or
+/// with vertical lines.
+pub fn estimate_skew(image: &DynamicImage) -> Option<f32> {
+    let (width, height) = image.dimensions();
+    let scale = (ESTIMATION_SIZE as f32 / width.max(height).max(1) as f32).min(1.0);
+    let small = if scale < 1.0 {
+        let (w, h) = ((width as f32 * scale) as u32, (height as f32 * scale) as u32);
+        image.resize_exact(w.max(1), h.max(1), FilterType::Triangle)
+    } else {
+        image.clone()
+    };
+    let gray = small.to_luma8();
+
+    let threshold = otsu_threshold(gray.as_raw());
+    let ink: Vec<(f32, f32)> = gray
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p.0[0] < threshold)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    // A page that is mostly dark is a photo or an inverted scan
+    if ink.len() < MIN_INK_PIXELS || ink.len() > gray.len() / 2 {
+        return None;
+    }
+
+    let (width, height) = gray.dimensions();
+    let diagonal = (width as f32).hypot(height as f32).ceil() as usize;
+    let score = |angle: f32| profile_score(&ink, angle, diagonal);
+
+    let steps = (MAX_SKEW_ANGLE / COARSE_STEP) as i32;
+    let coarse = (-steps..=steps)
+        .map(|i| i as f32 * COARSE_STEP)
+        .max_by(|a, b| score(*a).total_cmp(&score(*b)))?;
+    let fine_step = COARSE_STEP / 10.0;
+    let angle = (-10..=10)
+        .map(|i| coarse + i as f32 * fine_step)
+        .max_by(|a, b| score(*a).total_cmp(&score(*b)))?;
+
+    // Lines of a page scanned sideways run down the page
+    (score(angle) >= score(90.0)).then_some(angle)
+}
+
+/// Rotate a page so that text lines at `angle` degrees (as returned by
+/// [`estimate_skew`]) become horizontal.
+///
+/// The canvas grows to keep the whole page; the corners it gains are
+/// filled with white.
+pub fn rotate(image: &DynamicImage, angle: f32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let (sin, cos) = (angle as f64).to_radians().sin_cos();
+    let new_width = (width as f64 * cos.abs() + height as f64 * sin.abs()).ceil() as u32;
+    let new_height = (width as f64 * sin.abs() + height as f64 * cos.abs()).ceil() as u32;
+
+    let source = image.to_rgba8();
+    let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+    let (ncx, ncy) = (new_width as f64 / 2.0, new_height as f64 / 2.0);
+
+    let rotated = RgbaImage::from_fn(new_width.max(1), new_height.max(1), |x, y| {
+        let (dx, dy) = (x as f64 + 0.5 - ncx, y as f64 + 0.5 - ncy);
+        let sx = cx + dx * cos - dy * sin;
+        let sy = cy + dx * sin + dy * cos;
+        if sx < 0.0 || sy < 0.0 || sx > width as f64 || sy > height as f64 {
+            return Rgba([255, 255, 255, 255]);
+        }
+        sample(&source, sx - 0.5, sy - 0.5)
+    });
+
+    DynamicImage::ImageRgba8(rotated)
+}
+
+/// Straighten a skewed page, returning the rotated page and the skew
+/// angle it was corrected by, or `None` if it is straight enough.
+pub fn deskew(image: &DynamicImage) -> Option<(DynamicImage, f32)> {
+    let angle = estimate_skew(image)?;
+    (angle.abs() >= MIN_SKEW_ANGLE).then(|| (rotate(image, angle), angle))
+}
+
+/// Record whether a page was deskewed by `angle` and, if not, why.
+pub fn record_deskew(capabilities: &mut Capabilities, config: &OcrConfig, angle: Option<f32>) {
+    match angle {
+        Some(_) => capabilities.ran(Stage::Deskew),
+        None if !config.auto_deskew => {
+            capabilities.skipped(Stage::Deskew, "disabled by ocr.auto_deskew")
+        }
+        None => capabilities.skipped(Stage::Deskew, "page is not skewed"),
+    }
+}
+
+/// Bilinear sample of `image` at pixel-center coordinates, clamped to
+/// the edges.
+pub(super) fn sample(image: &RgbaImage, x: f64, y: f64) -> Rgba<u8> {
+    let max_x = (image.width() - 1) as f64;
+    let max_y = (image.height() - 1) as f64;
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (x1, y1) = ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y));
+    let (fx, fy) = (x - x0, y - y0);
+
+    let pixel = |x: f64, y: f64| image.get_pixel(x as u32, y as u32).0;
+    let (p00, p10, p01, p11) = (pixel(x0, y0), pixel(x1, y0), pixel(x0, y1), pixel(x1, y1));
+
+    let mut value = [0u8; 4];
+    for c in 0..4 {
+        let top = p00[c] as f64 * (1.0 - fx) + p10[c] as f64 * fx;
+        let bottom = p01[c] as f64 * (1.0 - fx) + p11[c] as f64 * fx;
+        value[c] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+    }
+    Rgba(value)
+}
+
+/// Sharpness of the horizontal projection of `ink` rotated by `angle`:
+/// the sum of squared differences between neighbouring rows, highest
+/// when lines and the gaps between them are level.
+fn profile_score(ink: &[(f32, f32)], angle: f32, diagonal: usize) -> f64 {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let mut rows = vec![0u32; 2 * diagonal + 1];
+    for &(x, y) in ink {
+        let row = y * cos - x * sin + diagonal as f32;
+        rows[(row as usize).min(2 * diagonal)] += 1;
+    }
+    rows.windows(2)
+        .map(|pair| {
+            let difference = pair[1] as f64 - pair[0] as f64;
+            difference * difference
+        })
+        .sum()
+}
+
+/// Gray level separating dark from light pixels (Otsu's method).
+fn otsu_threshold(pixels: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &p in pixels {
+        histogram[p as usize] += 1;
+    }
+
+    let total = pixels.len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+    let (mut dark, mut dark_sum) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (128u8, 0.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        dark += count as f64;
+        dark_sum += level as f64 * count as f64;
+        let light = total - dark;
+        if dark == 0.0 || light == 0.0 {
+            continue;
+        }
+        // Variance between the two classes
+        let difference = dark_sum / dark - (sum - dark_sum) / light;
+        let variance = dark * light * difference * difference;
+        if variance > best_variance {
+            best_variance = variance;
+            best = level as u8;
+        }
+    }
+
+    // Pixels strictly below the threshold are dark
+    best.saturating_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    /// A white page with dark text-like lines, slanted by `angle` degrees.
+    fn lined_page(angle: f32) -> DynamicImage {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let page = GrayImage::from_fn(600, 400, |x, y| {
+            let (dx, dy) = (x as f32 - 300.0, y as f32 - 200.0);
+            let along = dx * cos + dy * sin;
+            let across = -dx * sin + dy * cos + 200.0;
+            let on_line = (across as i32).rem_euclid(30) < 8 && (across as i32) > 20;
+            // Words: gaps along the line, in different places on each line
+            let shift = (across as i32).div_euclid(30) * 23;
+            let in_word = (along as i32 + 1000 + shift).rem_euclid(60) < 45;
+            let inside = along.abs() < 220.0 && across < 380.0;
+            if on_line && in_word && inside { Luma([20]) } else { Luma([245]) }
+        });
+        DynamicImage::ImageLuma8(page)
+    }
+
+    #[test]
+    fn test_estimate_skew() {
+        for angle in [-7.0f32, -2.0, 0.0, 3.5, 10.0] {
+            let estimated = estimate_skew(&lined_page(angle)).unwrap();
+            assert!((estimated - angle).abs() < 0.3, "{} estimated as {}", angle, estimated);
+        }
+
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(200, 100, Luma([255])));
+        assert_eq!(estimate_skew(&blank), None);
+
+        // Sideways pages are not deskewed
+        assert_eq!(estimate_skew(&lined_page(3.0).rotate90()), None);
+    }
+
+    #[test]
+    fn test_deskew() {
+        let (straightened, angle) = deskew(&lined_page(5.0)).unwrap();
+        assert!((angle - 5.0).abs() < 0.3);
+        assert!(straightened.width() > 600 && straightened.height() > 400);
+        assert!(estimate_skew(&straightened).unwrap().abs() < MIN_SKEW_ANGLE);
+
+        assert!(deskew(&lined_page(0.0)).is_none());
+    }
+}
@@ -41,6 +41,16 @@ impl<B: InferenceBackend> TextDetector<B> {
         }
     }
 
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
     /// Set detection threshold.
     pub fn with_threshold(mut self, threshold: f32) -> Self {
         self.threshold = threshold;
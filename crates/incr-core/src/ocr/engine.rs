@@ -1,30 +1,51 @@
 //! Complete OCR engine orchestrating detection, classification, and recognition.
 
-use std::path::Path;
 use std::time::Instant;
 
 use image::{DynamicImage, GenericImageView};
 use tracing::{debug, info};
 
 use crate::error::OcrError;
+use crate::models::capabilities::{Capabilities, Stage};
 use crate::models::config::OcrConfig;
-use incr_inference::InferenceBackend;
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink, ProgressStage};
+use incr_inference::{InferenceBackend, InferenceOptions};
 
 use super::{
     classifier::AngleClassifier,
-    detector::TextDetector,
+    deskew::record_deskew,
+    detector::{DetectionResult, TextDetector},
+    barcode::{decode_page, engine_decoder, no_decoder_reason, BarcodeDecoder},
     layout::{LayoutDetector, LayoutResult},
+    orientation::{is_sideways, record_orientation, turn_upright},
     preprocessing::ImagePreprocessor,
-    recognizer::TextRecognizer,
+    recognizer::{RecognitionResult, TextRecognizer},
+    style::{StyleClassifier, TextStyle},
+    table::TableRecognizer,
+    upscale::{median_text_height, record_upscaling, scale_bbox, upscale_bicubic, upscale_factor},
     OcrResult, TextBox,
 };
+#[cfg(feature = "super-resolution")]
+use super::{
+    upscale::{text_upscale_factor, TARGET_TEXT_HEIGHT},
+    SuperResolution,
+};
+
+/// Most text lines read to tell the orientation of a page.
+const ORIENTATION_SAMPLE: usize = 16;
 
 /// Complete OCR engine combining detection, classification, and recognition.
 pub struct OcrEngine<B: InferenceBackend> {
     detector: Option<TextDetector<B>>,
     classifier: Option<AngleClassifier<B>>,
     recognizer: Option<TextRecognizer<B>>,
+    style_classifier: Option<StyleClassifier<B>>,
+    handwriting_recognizer: Option<TextRecognizer<B>>,
     layout_detector: Option<LayoutDetector<B>>,
+    table_recognizer: Option<TableRecognizer<B>>,
+    #[cfg(feature = "super-resolution")]
+    super_resolution: Option<SuperResolution<B>>,
+    barcode_decoder: Option<Box<dyn BarcodeDecoder>>,
     preprocessor: ImagePreprocessor,
     config: OcrConfig,
 }
@@ -34,7 +55,14 @@ pub struct OcrEngineBuilder<B: InferenceBackend> {
     detector: Option<TextDetector<B>>,
     classifier: Option<AngleClassifier<B>>,
     recognizer: Option<TextRecognizer<B>>,
+    style_classifier: Option<StyleClassifier<B>>,
+    handwriting_recognizer: Option<TextRecognizer<B>>,
     layout_detector: Option<LayoutDetector<B>>,
+    table_recognizer: Option<TableRecognizer<B>>,
+    #[cfg(feature = "super-resolution")]
+    super_resolution: Option<SuperResolution<B>>,
+    barcode_decoder: Option<Box<dyn BarcodeDecoder>>,
+    inference_options: Option<InferenceOptions>,
     config: OcrConfig,
 }
 
@@ -45,7 +73,14 @@ impl<B: InferenceBackend> OcrEngineBuilder<B> {
             detector: None,
             classifier: None,
             recognizer: None,
+            style_classifier: None,
+            handwriting_recognizer: None,
             layout_detector: None,
+            table_recognizer: None,
+            #[cfg(feature = "super-resolution")]
+            super_resolution: None,
+            barcode_decoder: None,
+            inference_options: None,
             config: OcrConfig::default(),
         }
     }
@@ -68,31 +103,105 @@ impl<B: InferenceBackend> OcrEngineBuilder<B> {
         self
     }
 
+    /// Set the printed/handwritten style classifier.
+    pub fn with_style_classifier(mut self, style_classifier: StyleClassifier<B>) -> Self {
+        self.style_classifier = Some(style_classifier);
+        self
+    }
+
+    /// Set the recognizer for regions the style classifier marks as
+    /// handwritten.
+    pub fn with_handwriting_recognizer(mut self, recognizer: TextRecognizer<B>) -> Self {
+        self.handwriting_recognizer = Some(recognizer);
+        self
+    }
+
     /// Set the layout detector.
     pub fn with_layout_detector(mut self, layout_detector: LayoutDetector<B>) -> Self {
         self.layout_detector = Some(layout_detector);
         self
     }
 
+    /// Set the recognizer for the structure of the tables the layout
+    /// detector finds.
+    pub fn with_table_recognizer(mut self, table_recognizer: TableRecognizer<B>) -> Self {
+        self.table_recognizer = Some(table_recognizer);
+        self
+    }
+
+    /// Upscale low-resolution images with a super-resolution model instead
+    /// of bicubic interpolation.
+    #[cfg(feature = "super-resolution")]
+    pub fn with_super_resolution(mut self, super_resolution: SuperResolution<B>) -> Self {
+        self.super_resolution = Some(super_resolution);
+        self
+    }
+
+    /// Decode QR codes and barcodes with `decoder` instead of the built-in
+    /// decoder of the `barcode` feature (unless `ocr.decode_barcodes` is
+    /// off).
+    pub fn with_barcode_decoder(mut self, decoder: impl BarcodeDecoder + 'static) -> Self {
+        self.barcode_decoder = Some(Box::new(decoder));
+        self
+    }
+
     /// Set configuration.
     pub fn with_config(mut self, config: OcrConfig) -> Self {
         self.config = config;
         self
     }
 
+    /// Apply session options (threads, memory arena, profiling) to the
+    /// backends of all models when the engine is built.
+    pub fn with_inference_options(mut self, options: InferenceOptions) -> Self {
+        self.inference_options = Some(options);
+        self
+    }
+
     /// Build the OCR engine.
-    pub fn build(self) -> OcrEngine<B> {
+    pub fn build(mut self) -> OcrEngine<B> {
+        if let Some(options) = self.inference_options.take() {
+            self.configure(&options);
+        }
+
         OcrEngine {
             detector: self.detector,
             classifier: self.classifier,
             recognizer: self.recognizer,
+            style_classifier: self.style_classifier,
+            handwriting_recognizer: self.handwriting_recognizer,
             layout_detector: self.layout_detector,
+            table_recognizer: self.table_recognizer,
+            #[cfg(feature = "super-resolution")]
+            super_resolution: self.super_resolution,
+            barcode_decoder: engine_decoder(&self.config, self.barcode_decoder),
             preprocessor: ImagePreprocessor::new().with_max_size(self.config.max_image_size),
             config: self.config,
         }
     }
 }
 
+impl<B: InferenceBackend> OcrEngineBuilder<B> {
+    fn configure(&mut self, options: &InferenceOptions) {
+        let backends = [
+            self.detector.as_mut().map(TextDetector::backend_mut),
+            self.classifier.as_mut().map(AngleClassifier::backend_mut),
+            self.recognizer.as_mut().map(TextRecognizer::backend_mut),
+            self.style_classifier.as_mut().map(StyleClassifier::backend_mut),
+            self.handwriting_recognizer.as_mut().map(TextRecognizer::backend_mut),
+            self.layout_detector.as_mut().map(LayoutDetector::backend_mut),
+            #[cfg(feature = "super-resolution")]
+            self.super_resolution.as_mut().map(SuperResolution::backend_mut),
+        ];
+        for backend in backends.into_iter().flatten() {
+            backend.configure(options);
+        }
+        if let Some(table_recognizer) = &mut self.table_recognizer {
+            table_recognizer.configure(options);
+        }
+    }
+}
+
 impl<B: InferenceBackend> Default for OcrEngineBuilder<B> {
     fn default() -> Self {
         Self::new()
@@ -107,75 +216,164 @@ impl<B: InferenceBackend> OcrEngine<B> {
 
     /// Process an image and extract text.
     pub fn process(&self, image: &DynamicImage) -> Result<OcrResult, OcrError> {
+        self.process_with_progress(image, &NoProgress)
+    }
+
+    /// Process an image and extract text, reporting progress to `progress`.
+    pub fn process_with_progress(
+        &self,
+        image: &DynamicImage,
+        progress: &dyn ProgressSink,
+    ) -> Result<OcrResult, OcrError> {
         let start = Instant::now();
         let (width, height) = image.dimensions();
 
         info!("Processing image: {}x{}", width, height);
 
+        let mut capabilities = self.capabilities();
+
+        // Skewed pages are straightened first; boxes refer to the result
+        let deskewed = self.straighten(image);
+        let image = match &deskewed {
+            Some((page, _)) => page,
+            None => image,
+        };
+        let mut skew = deskewed.as_ref().map(|(_, angle)| *angle);
+
         // Step 1: Detect text regions
-        let detection_result = if let Some(ref detector) = self.detector {
-            if self.config.enable_detection {
-                detector.detect(image)?
-            } else {
-                // If detection disabled, treat whole image as one region
-                super::detector::DetectionResult {
-                    boxes: vec![[
-                        0.0,
-                        0.0,
-                        width as f32,
-                        0.0,
-                        width as f32,
-                        height as f32,
-                        0.0,
-                        height as f32,
-                    ]],
-                    scores: vec![1.0],
-                    image_size: (width, height),
+        progress.report(ProgressEvent::new(ProgressStage::Detection, 0, 1, "Detecting text regions"));
+        let mut detection_result = self.detect(image)?;
+
+        // Pages scanned sideways or upside down are turned, straightened
+        // and detected again
+        let orientation = if self.config.auto_orient {
+            self.page_orientation(image, &detection_result.boxes)?
+        } else {
+            0
+        };
+        record_orientation(&mut capabilities, &self.config, orientation);
+        let turned = (orientation != 0).then(|| {
+            debug!("Page text rotated {} degrees, turning upright", orientation);
+            let page = turn_upright(image, orientation);
+            match self.straighten(&page) {
+                Some((straightened, angle)) => {
+                    skew = Some(angle);
+                    straightened
                 }
+                None => page,
             }
-        } else {
-            return Err(OcrError::Detection("No detector configured".to_string()));
+        });
+        let image = match &turned {
+            Some(page) => {
+                detection_result = self.detect(page)?;
+                page
+            }
+            None => image,
         };
+        record_deskew(&mut capabilities, &self.config, skew);
+        let (width, height) = image.dimensions();
 
         if detection_result.boxes.is_empty() {
             debug!("No text regions detected");
-            return Ok(OcrResult::empty(width, height));
+            let mut result = OcrResult::empty(width, height);
+            record_upscaling(&mut capabilities, &self.config, None, None);
+            result.capabilities = capabilities;
+            return Ok(result);
         }
 
         debug!("Detected {} text regions", detection_result.boxes.len());
 
+        let region_count = detection_result.boxes.len() as u64;
+        progress.report(ProgressEvent::new(
+            ProgressStage::Detection,
+            1,
+            1,
+            format!("Detected {} text regions", region_count),
+        ));
+
+        // Small text is cropped from an upscaled copy for recognition
+        let heights = detection_result.boxes.iter().map(|b| (b[6] - b[0]).hypot(b[7] - b[1]));
+        let text_height = median_text_height(heights);
+        let factor = text_height.and_then(|h| upscale_factor(&self.config, h, (width, height)));
+        record_upscaling(&mut capabilities, &self.config, text_height, factor);
+        let upscaled = match factor {
+            Some(factor) => {
+                debug!("Small text detected, upscaling {:.2}x before recognition", factor);
+                Some((self.upscale(image, factor)?, factor))
+            }
+            None => None,
+        };
+
+        // Height of ordinary lines in the crops, to tell vertical lines
+        // from single characters
+        let line_height = text_height.unwrap_or(0.0) * factor.unwrap_or(1.0);
+
         // Step 2: Process each detected region
         let mut text_boxes = Vec::with_capacity(detection_result.boxes.len());
 
-        for (bbox, det_score) in detection_result
+        for (i, (bbox, det_score)) in detection_result
             .boxes
             .iter()
             .zip(detection_result.scores.iter())
+            .enumerate()
         {
+            progress.report(ProgressEvent::new(
+                ProgressStage::Recognition,
+                i as u64,
+                region_count,
+                format!("Recognizing region {}/{}", i + 1, region_count),
+            ));
+
             // Crop the region
-            let cropped = self.preprocessor.crop_text_region(image, bbox)?;
+            let cropped = match &upscaled {
+                Some((upscaled, factor)) => {
+                    self.preprocessor.crop_text_region(upscaled, &scale_bbox(bbox, *factor))?
+                }
+                None => self.preprocessor.crop_text_region(image, bbox)?,
+            };
 
             // Step 2a: Classify angle (optional)
-            let (rotated, angle) = if let Some(ref classifier) = self.classifier {
-                if self.config.enable_classification {
-                    let (angle, _conf) = classifier.classify(&cropped)?;
-                    let rotated = if angle == 180 {
-                        cropped.rotate180()
-                    } else {
-                        cropped
-                    };
-                    (rotated, angle)
-                } else {
-                    (cropped, 0)
+            let (rotated, angle) = match self.classifier {
+                Some(ref classifier) if self.config.enable_classification => {
+                    classifier.upright(cropped, line_height)?
                 }
-            } else {
-                (cropped, 0)
+                _ => (cropped, 0),
             };
 
-            // Step 2b: Recognize text
-            let (text, rec_score) = if let Some(ref recognizer) = self.recognizer {
+            // Step 2b: Route handwritten regions to the handwriting model
+            let (recognizer, handwritten) = match self.handwriting_recognizer {
+                Some(ref handwriting)
+                    if self.config.enable_recognition && self.is_handwritten(&rotated)? =>
+                {
+                    (Some(handwriting), true)
+                }
+                _ => (self.recognizer.as_ref(), false),
+            };
+
+            // Step 2c: Recognize text
+            let (text, rec_score) = if let Some(recognizer) = recognizer {
                 if self.config.enable_recognition {
                     let result = recognizer.recognize(&rotated)?;
+
+                    // Tiny print is enhanced and recognized again
+                    #[cfg(feature = "super-resolution")]
+                    let result = match &self.super_resolution {
+                        Some(sr) if upscaled.is_none() => {
+                            let (result, enhanced) =
+                                self.enhance_small_text(sr, recognizer, &rotated, result)?;
+                            if enhanced {
+                                capabilities.ran(Stage::SuperResolution);
+                            }
+                            result
+                        }
+                        _ => result,
+                    };
+
+                    let (result, retried) = self.second_pass(recognizer, &rotated, result)?;
+                    if retried {
+                        capabilities.ran(Stage::SecondPass);
+                    }
+
                     (result.text, result.confidence)
                 } else {
                     (String::new(), 0.0)
@@ -192,62 +390,39 @@ impl<B: InferenceBackend> OcrEngine<B> {
                     detection_score: *det_score,
                     recognition_score: rec_score,
                     angle,
+                    handwritten,
                 });
             }
         }
 
+        // Optional passes that were available but had nothing to do
+        #[cfg(feature = "super-resolution")]
+        if self.super_resolution.is_some() {
+            if upscaled.is_some() {
+                capabilities.ran(Stage::SuperResolution);
+            }
+            capabilities.skipped(Stage::SuperResolution, "no small low-confidence text");
+        }
+        if self.config.second_pass_threshold > 0.0 {
+            capabilities.skipped(Stage::SecondPass, "no low-confidence lines");
+        }
+
+        progress.report(ProgressEvent::new(
+            ProgressStage::Recognition,
+            region_count,
+            region_count,
+            format!("Recognized {} text boxes", text_boxes.len()),
+        ));
+
         // Detect layout if available
-        let layout = if let Some(ref layout_detector) = self.layout_detector {
+        let layout_detector = self.layout_detector.as_ref().filter(|_| self.config.enable_layout);
+        let layout = if let Some(layout_detector) = layout_detector {
+            progress.report(ProgressEvent::new(ProgressStage::Layout, 0, 1, "Detecting layout"));
             match layout_detector.detect(image) {
-                Ok(layout_result) => {
-                    use super::{LayoutInfo, RegionBox};
-
-                    let tables: Vec<RegionBox> = layout_result
-                        .tables()
-                        .iter()
-                        .map(|r| RegionBox {
-                            region_type: "table".to_string(),
-                            bbox: r.bbox,
-                            confidence: r.confidence,
-                        })
-                        .collect();
-
-                    let text_regions: Vec<RegionBox> = layout_result
-                        .text_regions()
-                        .iter()
-                        .map(|r| RegionBox {
-                            region_type: format!("{:?}", r.region_type).to_lowercase(),
-                            bbox: r.bbox,
-                            confidence: r.confidence,
-                        })
-                        .collect();
-
-                    let figures: Vec<RegionBox> = layout_result
-                        .regions
-                        .iter()
-                        .filter(|r| matches!(r.region_type, super::layout::LayoutType::Figure))
-                        .map(|r| RegionBox {
-                            region_type: "figure".to_string(),
-                            bbox: r.bbox,
-                            confidence: r.confidence,
-                        })
-                        .collect();
-
-                    debug!(
-                        "Layout detected: {} tables, {} text regions, {} figures",
-                        tables.len(),
-                        text_regions.len(),
-                        figures.len()
-                    );
-
-                    Some(LayoutInfo {
-                        tables,
-                        text_regions,
-                        figures,
-                    })
-                }
+                Ok(layout_result) => Some(layout_result.info()),
                 Err(e) => {
                     debug!("Layout detection failed: {}", e);
+                    capabilities.failed(Stage::Layout, format!("layout detection failed: {}", e));
                     None
                 }
             }
@@ -255,6 +430,11 @@ impl<B: InferenceBackend> OcrEngine<B> {
             None
         };
 
+        // Codes in figure regions, or anywhere without layout analysis
+        let figures = layout.as_ref().map(|layout| layout.figures.as_slice());
+        let barcodes =
+            decode_page(self.barcode_decoder.as_deref(), image, figures, &mut capabilities);
+
         // Sort by reading order
         let mut result = OcrResult {
             boxes: text_boxes,
@@ -262,10 +442,17 @@ impl<B: InferenceBackend> OcrEngine<B> {
             processing_time_ms: start.elapsed().as_millis() as u64,
             image_size: (width, height),
             layout,
+            capabilities,
+            barcodes,
         };
 
         result.sort_by_reading_order();
 
+        if let Some(table_recognizer) = &self.table_recognizer {
+            let count = table_recognizer.recognize_regions(image, &mut result);
+            debug!("Recognized the structure of {} tables", count);
+        }
+
         info!(
             "OCR complete: {} text boxes in {}ms",
             result.boxes.len(),
@@ -296,114 +483,255 @@ impl<B: InferenceBackend> OcrEngine<B> {
         }
     }
 
+    /// Stages this engine runs given its models and configuration, and
+    /// why the others are skipped.
+    ///
+    /// Stages that depend on the image (upscaling, super-resolution and
+    /// the second pass) are only listed here when they cannot run;
+    /// [`process`](Self::process) records whether they did.
+    pub fn capabilities(&self) -> Capabilities {
+        let config = &self.config;
+        let mut capabilities = Capabilities::new();
+
+        if config.enable_detection {
+            capabilities.ran(Stage::Detection);
+        } else {
+            capabilities.skipped(Stage::Detection, "disabled by ocr.enable_detection");
+        }
+        capabilities.model_stage(
+            Stage::Classification,
+            ("ocr.enable_classification", config.enable_classification),
+            ("angle classification model", self.classifier.is_some()),
+        );
+        capabilities.model_stage(
+            Stage::Recognition,
+            ("ocr.enable_recognition", config.enable_recognition),
+            ("recognition model", self.recognizer.is_some()),
+        );
+        capabilities.model_stage(
+            Stage::Handwriting,
+            ("ocr.enable_recognition", config.enable_recognition),
+            (
+                "style classification or handwriting model",
+                self.style_classifier.is_some() && self.handwriting_recognizer.is_some(),
+            ),
+        );
+        capabilities.model_stage(
+            Stage::Layout,
+            ("ocr.enable_layout", config.enable_layout),
+            ("layout model", self.layout_detector.is_some()),
+        );
+
+        #[cfg(feature = "super-resolution")]
+        if self.super_resolution.is_none() {
+            capabilities.skipped(Stage::SuperResolution, "super-resolution model not installed");
+        }
+        #[cfg(not(feature = "super-resolution"))]
+        capabilities.skipped(Stage::SuperResolution, "built without the super-resolution feature");
+
+        if config.second_pass_threshold <= 0.0 {
+            capabilities.skipped(Stage::SecondPass, "disabled by ocr.second_pass_threshold");
+        }
+        if self.barcode_decoder.is_none() {
+            capabilities.skipped(Stage::Barcodes, no_decoder_reason(config));
+        }
+
+        capabilities
+    }
+
     /// Check if layout detection is available.
     pub fn has_layout_detection(&self) -> bool {
         self.layout_detector.is_some()
     }
-}
 
-/// Convenience function to create an OCR engine with models from a directory.
-#[cfg(feature = "native")]
-pub fn create_engine_from_dir(
-    model_dir: &Path,
-    config: OcrConfig,
-) -> Result<OcrEngine<crate::OrtBackend>, OcrError> {
-    use crate::OrtBackend;
-    use super::layout::LayoutDetector;
-
-    let det_path = model_dir.join("det.onnx");
-    let cls_path = model_dir.join("cls.onnx");
-    let rec_path = model_dir.join("latin_rec.onnx");
-    let dict_path = model_dir.join("latin_dict.txt");
-    let layout_path = model_dir.join("layout.onnx");
+    /// Run every model once on zeros, so the first page is not slowed down
+    /// by session creation and graph optimization. Worth it for long-running
+    /// processes (servers, batch workers) that load the engine up front.
+    pub fn warmup(&self) -> Result<(), OcrError> {
+        let start = Instant::now();
+        let models: [(&str, Option<&B>); 7] = [
+            ("detection", self.detector.as_ref().map(TextDetector::backend)),
+            ("classification", self.classifier.as_ref().map(AngleClassifier::backend)),
+            ("recognition", self.recognizer.as_ref().map(TextRecognizer::backend)),
+            ("style classification", self.style_classifier.as_ref().map(StyleClassifier::backend)),
+            (
+                "handwriting recognition",
+                self.handwriting_recognizer.as_ref().map(TextRecognizer::backend),
+            ),
+            ("layout", self.layout_detector.as_ref().map(LayoutDetector::backend)),
+            #[cfg(feature = "super-resolution")]
+            ("super-resolution", self.super_resolution.as_ref().map(SuperResolution::backend)),
+            #[cfg(not(feature = "super-resolution"))]
+            ("super-resolution", None),
+        ];
+
+        for (name, backend) in models {
+            if let Some(backend) = backend {
+                backend
+                    .warmup()
+                    .map_err(|e| OcrError::ModelLoad(format!("{} model warm-up: {}", name, e)))?;
+            }
+        }
+        if let Some(table_recognizer) = &self.table_recognizer {
+            table_recognizer.warmup()?;
+        }
 
-    let mut builder = OcrEngine::builder().with_config(config.clone());
+        debug!("Warmed up OCR models in {}ms", start.elapsed().as_millis());
+        Ok(())
+    }
 
-    // Load detector
-    if config.enable_detection && det_path.exists() {
-        let backend = OrtBackend::from_file(&det_path)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load detector: {}", e)))?;
-        builder = builder.with_detector(TextDetector::new(backend));
+    /// Replace the text recognizer, e.g. with the model and dictionary for
+    /// another script. Pages being processed keep the previous one.
+    pub fn set_recognizer(&mut self, recognizer: TextRecognizer<B>) {
+        self.recognizer = Some(recognizer);
     }
 
-    // Load classifier
-    if config.enable_classification && cls_path.exists() {
-        let backend = OrtBackend::from_file(&cls_path)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load classifier: {}", e)))?;
-        builder = builder.with_classifier(AngleClassifier::new(backend));
+    /// Recognize the structure of the tables the layout detector finds
+    /// with `table_recognizer`, for their line items.
+    pub fn set_table_recognizer(&mut self, table_recognizer: TableRecognizer<B>) {
+        self.table_recognizer = Some(table_recognizer);
     }
 
-    // Load recognizer
-    if config.enable_recognition && rec_path.exists() {
-        let backend = OrtBackend::from_file(&rec_path)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load recognizer: {}", e)))?;
+    /// Replace the dictionary of the text recognizer, e.g. with a corrected
+    /// one for the same model.
+    pub fn set_dictionary(&mut self, dictionary: Vec<char>) {
+        if let Some(recognizer) = &mut self.recognizer {
+            recognizer.set_dictionary(dictionary);
+        }
+    }
 
-        let dictionary = if dict_path.exists() {
-            TextRecognizer::<OrtBackend>::load_dictionary(&dict_path)?
-        } else {
-            TextRecognizer::<OrtBackend>::default_latin_dictionary()
+    /// Detect text regions, or take the whole image as one region when
+    /// detection is disabled.
+    fn detect(&self, image: &DynamicImage) -> Result<DetectionResult, OcrError> {
+        let Some(detector) = &self.detector else {
+            return Err(OcrError::Detection("No detector configured".to_string()));
         };
+        if self.config.enable_detection {
+            return detector.detect(image);
+        }
 
-        builder = builder.with_recognizer(TextRecognizer::new(backend, dictionary));
+        let (width, height) = image.dimensions();
+        Ok(DetectionResult {
+            boxes: vec![[
+                0.0,
+                0.0,
+                width as f32,
+                0.0,
+                width as f32,
+                height as f32,
+                0.0,
+                height as f32,
+            ]],
+            scores: vec![1.0],
+            image_size: (width, height),
+        })
     }
 
-    // Load layout detector (PP-Structure)
-    if layout_path.exists() {
-        let backend = OrtBackend::from_file(&layout_path)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load layout detector: {}", e)))?;
-        builder = builder.with_layout_detector(LayoutDetector::new(backend));
-        debug!("Loaded layout detector from {}", layout_path.display());
+    /// Straighten a skewed page if `ocr.auto_deskew` is set.
+    fn straighten(&self, image: &DynamicImage) -> Option<(DynamicImage, f32)> {
+        let deskewed = self.config.auto_deskew.then(|| self.preprocessor.deskew(image))??;
+        debug!("Page skewed by {:.1} degrees, straightened", deskewed.1);
+        Some(deskewed)
     }
 
-    Ok(builder.build())
-}
+    /// Clockwise rotation (0, 90, 180 or 270) of the page's text, read
+    /// from a sample of the detected lines.
+    ///
+    /// The angle classifier tells all four orientations. Without it only
+    /// sideways pages are recognized, and the lines are read turned both
+    /// ways to tell 90 from 270.
+    fn page_orientation(&self, image: &DynamicImage, boxes: &[[f32; 8]]) -> Result<i32, OcrError> {
+        let classifier = self
+            .classifier
+            .as_ref()
+            .filter(|_| self.config.enable_classification);
+        if boxes.is_empty() || (classifier.is_none() && !is_sideways(boxes)) {
+            return Ok(0);
+        }
 
-/// Create an OCR engine from embedded mobile models.
-/// This provides a standalone binary with no external model files needed.
-#[cfg(feature = "native")]
-pub fn create_engine_from_embedded(
-    config: OcrConfig,
-) -> Result<OcrEngine<crate::OrtBackend>, OcrError> {
-    use crate::models::embedded::EmbeddedModels;
-    use crate::OrtBackend;
-    use super::layout::LayoutDetector;
-
-    let models = EmbeddedModels::mobile();
-    let mut builder = OcrEngine::builder().with_config(config.clone());
-
-    // Load detector from embedded bytes
-    if config.enable_detection {
-        let backend = OrtBackend::from_bytes(models.detection)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load embedded detector: {}", e)))?;
-        builder = builder.with_detector(TextDetector::new(backend));
-        debug!("Loaded embedded detector ({} bytes)", models.detection.len());
-    }
-
-    // Load recognizer from embedded bytes
-    if config.enable_recognition {
-        let backend = OrtBackend::from_bytes(models.recognition)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load embedded recognizer: {}", e)))?;
-
-        // Convert dictionary lines to chars (first char of each line)
-        let mut dictionary: Vec<char> = vec![' ']; // Blank token
-        for line in models.dictionary.lines() {
-            if let Some(c) = line.chars().next() {
-                dictionary.push(c);
-            }
+        let step = boxes.len().div_ceil(ORIENTATION_SAMPLE);
+        let lines = boxes
+            .iter()
+            .step_by(step)
+            .map(|bbox| self.preprocessor.crop_text_region(image, bbox))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(classifier) = classifier {
+            return classifier.page_orientation(&lines);
+        }
+        let Some(recognizer) = &self.recognizer else {
+            return Ok(0);
+        };
+        let (mut clockwise, mut counter_clockwise) = (0.0, 0.0);
+        for line in &lines {
+            clockwise += recognizer.recognize(&line.rotate270())?.confidence;
+            counter_clockwise += recognizer.recognize(&line.rotate90())?.confidence;
         }
+        Ok(if clockwise >= counter_clockwise { 90 } else { 270 })
+    }
 
-        builder = builder.with_recognizer(TextRecognizer::new(backend, dictionary));
-        debug!("Loaded embedded recognizer ({} bytes)", models.recognition.len());
+    /// Whether the style classifier marks a text crop as handwritten.
+    fn is_handwritten(&self, crop: &DynamicImage) -> Result<bool, OcrError> {
+        match &self.style_classifier {
+            Some(classifier) => Ok(classifier.classify(crop)?.0 == TextStyle::Handwritten),
+            None => Ok(false),
+        }
     }
 
-    // Load layout detector from embedded bytes
-    if !models.layout.is_empty() {
-        let backend = OrtBackend::from_bytes(models.layout)
-            .map_err(|e| OcrError::ModelLoad(format!("Failed to load embedded layout detector: {}", e)))?;
-        builder = builder.with_layout_detector(LayoutDetector::new(backend));
-        debug!("Loaded embedded layout detector ({} bytes)", models.layout.len());
+    /// Recognize a small, low-confidence text crop again after upscaling it
+    /// with the super-resolution model, keeping the better result. Also
+    /// returns whether the crop was enhanced.
+    #[cfg(feature = "super-resolution")]
+    fn enhance_small_text(
+        &self,
+        super_resolution: &SuperResolution<B>,
+        recognizer: &TextRecognizer<B>,
+        crop: &DynamicImage,
+        result: RecognitionResult,
+    ) -> Result<(RecognitionResult, bool), OcrError> {
+        let height = crop.height() as f32;
+        if result.confidence >= self.config.super_resolution_threshold || height >= TARGET_TEXT_HEIGHT {
+            return Ok((result, false));
+        }
+
+        let enhanced = super_resolution.upscale(crop, text_upscale_factor(height))?;
+        let retry = recognizer.recognize(&enhanced)?;
+        debug!(
+            "Super-resolution: '{}' ({:.2}) -> '{}' ({:.2})",
+            result.text, result.confidence, retry.text, retry.confidence
+        );
+
+        Ok((if retry.confidence > result.confidence { retry } else { result }, true))
     }
 
-    info!("Created OCR engine from embedded mobile models");
-    Ok(builder.build())
+    /// Recognize a low-confidence text crop again at twice its size,
+    /// keeping the better result. Also returns whether the crop was retried.
+    fn second_pass(
+        &self,
+        recognizer: &TextRecognizer<B>,
+        crop: &DynamicImage,
+        result: RecognitionResult,
+    ) -> Result<(RecognitionResult, bool), OcrError> {
+        if result.confidence >= self.config.second_pass_threshold {
+            return Ok((result, false));
+        }
+
+        let retry = recognizer.recognize(&self.upscale(crop, 2.0)?)?;
+        debug!(
+            "Second pass: '{}' ({:.2}) -> '{}' ({:.2})",
+            result.text, result.confidence, retry.text, retry.confidence
+        );
+
+        Ok((if retry.confidence > result.confidence { retry } else { result }, true))
+    }
+
+    /// Upscale an image by `factor`.
+    fn upscale(&self, image: &DynamicImage, factor: f32) -> Result<DynamicImage, OcrError> {
+        #[cfg(feature = "super-resolution")]
+        if let Some(super_resolution) = &self.super_resolution {
+            return super_resolution.upscale(image, factor);
+        }
+
+        Ok(upscale_bicubic(image, factor))
+    }
 }
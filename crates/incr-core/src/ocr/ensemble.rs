@@ -0,0 +1,184 @@
+//! Merging OCR results of several engines over the same image.
+//!
+//! Different model variants fail on different lines: the server detector
+//! finds faint print the mobile one misses, while either recognizer can
+//! misread a digit the other gets right. Boxes of every run are matched by
+//! overlap, and each matched line takes the text the runs agree on,
+//! weighted by recognition confidence. Lines only one run read are kept
+//! with reduced confidence.
+
+use super::{OcrResult, TextBox};
+
+/// Minimum overlap (intersection over union) for boxes of different runs
+/// to be treated as the same line.
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.5;
+
+/// Boxes of different runs covering the same line.
+struct Cluster<'a> {
+    rect: (f32, f32, f32, f32),
+    members: Vec<(usize, &'a TextBox)>,
+}
+
+/// Merge the results of several runs over the same image into one.
+///
+/// The merged box's recognition score is the combined confidence of the
+/// runs that read the winning text, divided by the number of runs, so
+/// disagreement and lines found by a single run lower it.
+pub fn merge_results(results: &[OcrResult], iou_threshold: f32) -> OcrResult {
+    let Some(first) = results.first() else {
+        return OcrResult::empty(0, 0);
+    };
+    if results.len() == 1 {
+        return first.clone();
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for (run, result) in results.iter().enumerate() {
+        for text_box in &result.boxes {
+            let rect = text_box.rect();
+
+            // Best overlapping line that this run hasn't contributed to yet
+            let matched = clusters
+                .iter_mut()
+                .filter(|c| c.members.iter().all(|&(r, _)| r != run))
+                .map(|c| (iou(c.rect, rect), c))
+                .filter(|(overlap, _)| *overlap >= iou_threshold)
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+
+            match matched {
+                Some((_, cluster)) => cluster.members.push((run, text_box)),
+                None => clusters.push(Cluster {
+                    rect,
+                    members: vec![(run, text_box)],
+                }),
+            }
+        }
+    }
+
+    let boxes = clusters
+        .iter()
+        .filter_map(|c| vote(c, results.len()))
+        .collect();
+
+    let mut capabilities = first.capabilities.clone();
+    for result in &results[1..] {
+        capabilities.merge(&result.capabilities);
+    }
+
+    let mut merged = OcrResult {
+        boxes,
+        text: String::new(),
+        processing_time_ms: results.iter().map(|r| r.processing_time_ms).sum(),
+        image_size: first.image_size,
+        layout: results.iter().find_map(|r| r.layout.clone()),
+        capabilities,
+        // Every run decodes the same page image
+        barcodes: first.barcodes.clone(),
+    };
+    merged.sort_by_reading_order();
+    merged
+}
+
+/// The box with the text most runs agree on, weighted by confidence.
+fn vote(cluster: &Cluster, runs: usize) -> Option<TextBox> {
+    let mut tally: Vec<(String, f32, &TextBox)> = Vec::new();
+    for &(_, text_box) in &cluster.members {
+        let key = normalize(&text_box.text);
+        match tally.iter_mut().find(|(text, _, _)| *text == key) {
+            Some((_, weight, best)) => {
+                *weight += text_box.recognition_score;
+                if text_box.recognition_score > best.recognition_score {
+                    *best = text_box;
+                }
+            }
+            None => tally.push((key, text_box.recognition_score, text_box)),
+        }
+    }
+
+    let (_, weight, best) = tally.into_iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let mut merged = best.clone();
+    merged.recognition_score = (weight / runs as f32).clamp(0.0, 1.0);
+    Some(merged)
+}
+
+/// Text with runs of whitespace collapsed, for comparing readings.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Intersection over union of two axis-aligned rectangles.
+fn iou(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+    let width = a.2.min(b.2) - a.0.max(b.0);
+    let height = a.3.min(b.3) - a.1.max(b.1);
+    if width <= 0.0 || height <= 0.0 {
+        return 0.0;
+    }
+
+    let intersection = width * height;
+    let area = |r: (f32, f32, f32, f32)| (r.2 - r.0) * (r.3 - r.1);
+    intersection / (area(a) + area(b) - intersection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_box(x: f32, y: f32, text: &str, score: f32) -> TextBox {
+        TextBox {
+            bbox: [x, y, x + 100.0, y, x + 100.0, y + 20.0, x, y + 20.0],
+            text: text.to_string(),
+            detection_score: 0.9,
+            recognition_score: score,
+            angle: 0,
+            handwritten: false,
+        }
+    }
+
+    fn result(boxes: Vec<TextBox>) -> OcrResult {
+        let mut result = OcrResult::empty(800, 600);
+        result.boxes = boxes;
+        result.sort_by_reading_order();
+        result
+    }
+
+    #[test]
+    fn test_merge_votes_on_text() {
+        let mobile = result(vec![
+            text_box(10.0, 10.0, "NIP: 5261O40828", 0.7),
+            text_box(10.0, 50.0, "Razem  1230,00", 0.9),
+        ]);
+        let server = result(vec![
+            text_box(12.0, 11.0, "NIP: 5261040828", 0.95),
+            text_box(10.0, 50.0, "Razem 1230,00", 0.95),
+            text_box(10.0, 90.0, "Termin płatności", 0.8),
+        ]);
+
+        let merged = merge_results(&[mobile, server], DEFAULT_IOU_THRESHOLD);
+
+        assert_eq!(
+            merged.text,
+            "NIP: 5261040828\nRazem 1230,00\nTermin płatności"
+        );
+        // Both runs agree on the total
+        assert!((merged.boxes[1].recognition_score - 0.925).abs() < 1e-6);
+        // Disagreement and a line only one run found lower the confidence
+        assert!((merged.boxes[0].recognition_score - 0.475).abs() < 1e-6);
+        assert!((merged.boxes[2].recognition_score - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_merge_single_result() {
+        let only = result(vec![text_box(0.0, 0.0, "Faktura VAT", 0.9)]);
+        let merged = merge_results(std::slice::from_ref(&only), DEFAULT_IOU_THRESHOLD);
+        assert_eq!(merged.text, only.text);
+        assert_eq!(merged.boxes[0].recognition_score, 0.9);
+
+        assert!(merge_results(&[], DEFAULT_IOU_THRESHOLD).boxes.is_empty());
+    }
+
+    #[test]
+    fn test_iou() {
+        assert_eq!(iou((0.0, 0.0, 10.0, 10.0), (20.0, 0.0, 30.0, 10.0)), 0.0);
+        assert!((iou((0.0, 0.0, 10.0, 10.0), (5.0, 0.0, 15.0, 10.0)) - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
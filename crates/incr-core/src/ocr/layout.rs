@@ -3,12 +3,17 @@
 //! Detects document regions: text, title, list, table, figure.
 
 use image::{DynamicImage, GenericImageView};
-use ndarray::Array3;
+use ndarray::{Array2, Array4};
 use tracing::debug;
 
 use crate::error::OcrError;
 use incr_inference::{InferenceBackend, InputTensor, OutputTensor};
 
+use super::{LayoutInfo, RegionBox};
+
+/// Layout model in a model directory.
+pub const LAYOUT_MODEL: &str = "layout.onnx";
+
 /// Layout region types detected by the model.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LayoutType {
@@ -155,6 +160,48 @@ impl LayoutResult {
             .collect()
     }
 
+    /// The tables, text regions and figures, as reported in an OCR result.
+    pub fn info(&self) -> LayoutInfo {
+        let region_box = |region: &LayoutRegion, region_type: String| RegionBox {
+            region_type,
+            bbox: region.bbox,
+            confidence: region.confidence,
+            structure: None,
+        };
+
+        let tables: Vec<RegionBox> = self
+            .tables()
+            .into_iter()
+            .map(|r| region_box(r, "table".to_string()))
+            .collect();
+
+        let text_regions: Vec<RegionBox> = self
+            .text_regions()
+            .into_iter()
+            .map(|r| region_box(r, format!("{:?}", r.region_type).to_lowercase()))
+            .collect();
+
+        let figures: Vec<RegionBox> = self
+            .regions
+            .iter()
+            .filter(|r| matches!(r.region_type, LayoutType::Figure))
+            .map(|r| region_box(r, "figure".to_string()))
+            .collect();
+
+        debug!(
+            "Layout detected: {} tables, {} text regions, {} figures",
+            tables.len(),
+            text_regions.len(),
+            figures.len()
+        );
+
+        LayoutInfo {
+            tables,
+            text_regions,
+            figures,
+        }
+    }
+
     /// Get regions sorted by reading order.
     pub fn sorted_by_reading_order(&self) -> Vec<&LayoutRegion> {
         let mut regions: Vec<&LayoutRegion> = self.regions.iter().collect();
@@ -205,6 +252,17 @@ impl<B: InferenceBackend> LayoutDetector<B> {
         }
     }
 
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
     /// Set the model type for class mapping.
     pub fn with_model_type(mut self, model_type: LayoutModelType) -> Self {
         self.model_type = model_type;
@@ -241,9 +299,9 @@ impl<B: InferenceBackend> LayoutDetector<B> {
             self.input_size.0, self.input_size.1, scale_x, scale_y
         );
 
-        // Create scale factor tensor for PP-PicoDet
-        let scale_factor = Array3::from_shape_vec(
-            (1, 2, 1),
+        // Create scale factor tensor for PP-PicoDet: [[scale_y, scale_x]]
+        let scale_factor = Array2::from_shape_vec(
+            (1, 2),
             vec![scale_y, scale_x],
         )
         .map_err(|e| OcrError::Detection(format!("Failed to create scale tensor: {}", e)))?;
@@ -270,7 +328,7 @@ impl<B: InferenceBackend> LayoutDetector<B> {
         })
     }
 
-    fn preprocess(&self, image: &DynamicImage) -> Result<(Array3<f32>, f32, f32), OcrError> {
+    fn preprocess(&self, image: &DynamicImage) -> Result<(Array4<f32>, f32, f32), OcrError> {
         let (orig_w, orig_h) = image.dimensions();
         let (target_w, target_h) = self.input_size;
 
@@ -290,18 +348,17 @@ impl<B: InferenceBackend> LayoutDetector<B> {
         let std = [0.229, 0.224, 0.225];
 
         let rgb = resized.to_rgb8();
-        let mut tensor = Array3::<f32>::zeros((3, target_h as usize, target_w as usize));
+        let mut tensor = Array4::<f32>::zeros((1, 3, target_h as usize, target_w as usize));
 
         for y in 0..target_h as usize {
             for x in 0..target_w as usize {
                 let pixel = rgb.get_pixel(x as u32, y as u32);
-                tensor[[0, y, x]] = (pixel[0] as f32 / 255.0 - mean[0]) / std[0];
-                tensor[[1, y, x]] = (pixel[1] as f32 / 255.0 - mean[1]) / std[1];
-                tensor[[2, y, x]] = (pixel[2] as f32 / 255.0 - mean[2]) / std[2];
+                tensor[[0, 0, y, x]] = (pixel[0] as f32 / 255.0 - mean[0]) / std[0];
+                tensor[[0, 1, y, x]] = (pixel[1] as f32 / 255.0 - mean[1]) / std[1];
+                tensor[[0, 2, y, x]] = (pixel[2] as f32 / 255.0 - mean[2]) / std[2];
             }
         }
 
-        // Add batch dimension by reshaping to 4D when used
         Ok((tensor, scale_x, scale_y))
     }
 
@@ -6,14 +6,25 @@ mod classifier;
 mod detector;
 #[cfg(feature = "wasm")]
 mod engine;
-#[cfg(feature = "wasm")]
+#[cfg(any(feature = "wasm", feature = "layout"))]
 mod layout;
-#[cfg(feature = "wasm")]
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
 mod preprocessing;
-#[cfg(feature = "wasm")]
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
 mod recognizer;
-#[cfg(feature = "wasm")]
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
+mod style;
+mod barcode;
+mod checkpoint;
+pub mod deskew;
+pub mod ensemble;
+pub mod orientation;
+mod regions;
+#[cfg(feature = "super-resolution")]
+mod super_resolution;
 mod table;
+pub mod upscale;
+mod wired_table;
 
 #[cfg(feature = "wasm")]
 pub use classifier::AngleClassifier;
@@ -21,14 +32,32 @@ pub use classifier::AngleClassifier;
 pub use detector::TextDetector;
 #[cfg(feature = "wasm")]
 pub use engine::{OcrEngine, OcrEngineBuilder};
-#[cfg(feature = "wasm")]
-pub use layout::{LayoutDetector, LayoutModelType, LayoutRegion, LayoutResult, LayoutType};
-#[cfg(feature = "wasm")]
-pub use preprocessing::ImagePreprocessor;
-#[cfg(feature = "wasm")]
+#[cfg(any(feature = "wasm", feature = "layout"))]
+pub use layout::{
+    LayoutDetector, LayoutModelType, LayoutRegion, LayoutResult, LayoutType, LAYOUT_MODEL,
+};
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
+pub use preprocessing::{ImagePreprocessor, PreprocessingTargets};
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
 pub use recognizer::TextRecognizer;
-#[cfg(feature = "wasm")]
-pub use table::{TableCell, TableClassifier, TableRecognizer, TableStructure, TableType};
+#[cfg(any(feature = "wasm", feature = "handwriting"))]
+pub use style::{
+    HandwritingReader, StyleClassifier, TextStyle, HANDWRITING_DICTIONARY, HANDWRITING_MODEL,
+    STYLE_MODEL,
+};
+#[cfg(any(feature = "wasm", feature = "tables"))]
+pub use table::{TableClassifier, TableRecognizer};
+pub use barcode::{scan_barcodes, BarcodeDecoder, DecodedSymbol};
+#[cfg(feature = "barcode")]
+pub use barcode::RxingDecoder;
+pub use checkpoint::OcrCheckpoint;
+pub use regions::{crop_regions, RegionCrop, RegionManifest, RegionManifestEntry};
+#[cfg(feature = "super-resolution")]
+pub use super_resolution::{SuperResolution, SR_MODEL};
+pub use table::{
+    TableCell, TableGrid, TableStructure, TableType, TABLE_CLASSIFIER_MODEL, TABLE_MODEL,
+};
+pub use wired_table::WiredTableReconstructor;
 
 #[cfg(feature = "native")]
 mod pure_engine;
@@ -51,8 +80,23 @@ pub fn create_engine_from_embedded(
     PureOcrEngine::from_embedded(config)
 }
 
+/// Create an engine from embedded models, e.g. `EmbeddedModels::server()`
+/// with the `embedded-server-models` feature.
+#[cfg(feature = "native")]
+pub fn create_engine_from_embedded_models(
+    models: crate::models::embedded::EmbeddedModels,
+    config: crate::models::config::OcrConfig,
+) -> Result<PureOcrEngine, crate::error::OcrError> {
+    PureOcrEngine::from_embedded_models(models, config)
+}
+
 use serde::{Deserialize, Serialize};
 
+use crate::models::capabilities::Capabilities;
+
+/// Height to width ratio from which a text line is read as vertical.
+pub(crate) const VERTICAL_ASPECT: f32 = 1.5;
+
 /// A detected text box with its coordinates and content.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextBox {
@@ -68,8 +112,13 @@ pub struct TextBox {
     /// Recognition confidence score (0.0 - 1.0).
     pub recognition_score: f32,
 
-    /// Detected angle (0, 90, 180, 270).
+    /// Clockwise rotation the text was found in (0, 90, 180, 270); it
+    /// was turned upright for recognition.
     pub angle: i32,
+
+    /// Text was classified as handwritten and read by the handwriting model.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub handwritten: bool,
 }
 
 impl TextBox {
@@ -126,6 +175,14 @@ pub struct OcrResult {
     /// Layout regions detected (if layout detection was enabled).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub layout: Option<LayoutInfo>,
+
+    /// Pipeline stages that ran on this image, and why others did not.
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    pub capabilities: Capabilities,
+
+    /// QR codes and barcodes decoded from the image.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub barcodes: Vec<Barcode>,
 }
 
 /// Layout information from PP-Structure.
@@ -148,6 +205,22 @@ pub struct RegionBox {
     pub bbox: [f32; 4],
     /// Confidence score.
     pub confidence: f32,
+    /// Cells of a table region recognized by a table structure model,
+    /// filled from the OCR boxes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure: Option<TableStructure>,
+}
+
+/// A QR code or barcode decoded from an image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Barcode {
+    /// Symbology, e.g. `qr_code`.
+    pub format: String,
+    /// Decoded text.
+    pub payload: String,
+    /// Region the code was found in (x1, y1, x2, y2): the figure region
+    /// with a margin, or the whole image.
+    pub bbox: [f32; 4],
 }
 
 impl OcrResult {
@@ -159,6 +232,8 @@ impl OcrResult {
             processing_time_ms: 0,
             image_size: (width, height),
             layout: None,
+            capabilities: Capabilities::new(),
+            barcodes: Vec::new(),
         }
     }
 
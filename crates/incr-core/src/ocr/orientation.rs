@@ -0,0 +1,91 @@
+//! Orientation of pages scanned sideways or upside down.
+//!
+//! Landscape scans of portrait invoices put every text line on its side.
+//! Most detected boxes are then taller than wide, which tells a sideways
+//! page from an upright one; which way it is turned is read from the
+//! text itself, by the angle classifier when it is loaded or by
+//! recognizing the page both ways up. The page is turned upright before
+//! detection runs on it again.
+
+use image::DynamicImage;
+
+use crate::models::capabilities::{Capabilities, Stage};
+use crate::models::config::OcrConfig;
+
+use super::VERTICAL_ASPECT;
+
+/// Turn a page whose text appears rotated clockwise by `angle` degrees
+/// (0, 90, 180 or 270) upright.
+pub fn turn_upright(image: &DynamicImage, angle: i32) -> DynamicImage {
+    match angle.rem_euclid(360) {
+        90 => image.rotate270(),
+        180 => image.rotate180(),
+        270 => image.rotate90(),
+        _ => image.clone(),
+    }
+}
+
+/// Whether a text box is at least [`VERTICAL_ASPECT`] times taller than
+/// wide.
+pub fn is_tall(bbox: &[f32; 8]) -> bool {
+    let xs = [bbox[0], bbox[2], bbox[4], bbox[6]];
+    let ys = [bbox[1], bbox[3], bbox[5], bbox[7]];
+    let extent = |values: [f32; 4]| {
+        let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+        max - min
+    };
+    extent(ys) >= extent(xs) * VERTICAL_ASPECT
+}
+
+/// Whether most text boxes of a page are tall, as on a page scanned
+/// sideways.
+pub fn is_sideways<'a>(boxes: impl IntoIterator<Item = &'a [f32; 8]>) -> bool {
+    let (mut tall, mut total) = (0, 0);
+    for bbox in boxes {
+        tall += is_tall(bbox) as usize;
+        total += 1;
+    }
+    tall * 2 > total
+}
+
+/// Record whether a page was turned upright from `orientation` and, if
+/// not, why.
+pub fn record_orientation(capabilities: &mut Capabilities, config: &OcrConfig, orientation: i32) {
+    if orientation != 0 {
+        capabilities.ran(Stage::Orientation);
+    } else if !config.auto_orient {
+        capabilities.skipped(Stage::Orientation, "disabled by ocr.auto_orient");
+    } else {
+        capabilities.skipped(Stage::Orientation, "page is upright");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, GrayImage, Luma};
+
+    #[test]
+    fn test_turn_upright() {
+        // A mark in the top-left corner of text turned 90 degrees
+        // clockwise sits in the top-right corner
+        let mut page = GrayImage::from_pixel(40, 20, Luma([255]));
+        page.put_pixel(39, 0, Luma([0]));
+        let turned = turn_upright(&DynamicImage::ImageLuma8(page), 90);
+        assert_eq!(turned.dimensions(), (20, 40));
+        assert_eq!(turned.to_luma8().get_pixel(0, 0).0, [0]);
+    }
+
+    #[test]
+    fn test_is_sideways() {
+        let line = [0.0, 0.0, 100.0, 0.0, 100.0, 20.0, 0.0, 20.0];
+        let vertical = [0.0, 0.0, 20.0, 0.0, 20.0, 100.0, 0.0, 100.0];
+        let digit = [0.0, 0.0, 10.0, 0.0, 10.0, 20.0, 0.0, 20.0];
+
+        assert!(is_tall(&vertical) && is_tall(&digit) && !is_tall(&line));
+        assert!(is_sideways(&[vertical, vertical, line]));
+        assert!(!is_sideways(&[line, line, digit]));
+        assert!(!is_sideways(&[]));
+    }
+}
@@ -1,11 +1,46 @@
 //! Image preprocessing for OCR.
 
-use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, RgbaImage};
 use ndarray::Array4;
+use serde::Serialize;
 use tracing::debug;
 
 use crate::error::OcrError;
 
+use super::deskew::{self, sample};
+
+/// Detection input is padded to a multiple of this (required by PaddleOCR).
+const DETECTION_ALIGN: u32 = 32;
+
+/// Angle and style classifier input size (width, height).
+const CLASSIFICATION_SIZE: (u32, u32) = (192, 48);
+
+/// PaddleOCR detection normalization: (x / 255 - mean) / std.
+const DETECTION_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const DETECTION_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// Recognition and classification normalization to [-1, 1].
+const TEXT_MEAN: [f32; 3] = [0.5, 0.5, 0.5];
+const TEXT_STD: [f32; 3] = [0.5, 0.5, 0.5];
+
+/// Input sizes of the OCR models, for resizing images outside the
+/// preprocessor (e.g. on a browser canvas).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PreprocessingTargets {
+    /// Longer side of detection input; smaller images are not enlarged.
+    pub detection_max_side: u32,
+    /// Detection input is padded to a multiple of this.
+    pub detection_align: u32,
+    /// Height of recognition input.
+    pub recognition_height: u32,
+    /// Largest width of recognition input.
+    pub recognition_max_width: u32,
+    /// Width of classification input.
+    pub classification_width: u32,
+    /// Height of classification input.
+    pub classification_height: u32,
+}
+
 /// Image preprocessor for OCR pipeline.
 pub struct ImagePreprocessor {
     /// Maximum image dimension.
@@ -58,29 +93,14 @@ impl ImagePreprocessor {
             image::imageops::FilterType::Lanczos3,
         );
 
-        // Pad to be divisible by 32 (required by PaddleOCR)
-        let pad_width = ((new_width + 31) / 32) * 32;
-        let pad_height = ((new_height + 31) / 32) * 32;
-
         let rgb = resized.to_rgb8();
-
-        // Normalize to [-0.5, 0.5] range and create NCHW tensor
-        let mut tensor = Array4::<f32>::zeros((1, 3, pad_height as usize, pad_width as usize));
-
-        // PaddleOCR normalization: (x / 255 - mean) / std
-        // mean = [0.485, 0.456, 0.406], std = [0.229, 0.224, 0.225]
-        let mean = [0.485f32, 0.456, 0.406];
-        let std = [0.229f32, 0.224, 0.225];
-
-        for y in 0..new_height {
-            for x in 0..new_width {
-                let pixel = rgb.get_pixel(x, y);
-                for c in 0..3 {
-                    let value = pixel[c] as f32 / 255.0;
-                    tensor[[0, c, y as usize, x as usize]] = (value - mean[c]) / std[c];
-                }
-            }
-        }
+        let tensor = pack(
+            (align(new_width), align(new_height)),
+            (new_width, new_height),
+            DETECTION_MEAN,
+            DETECTION_STD,
+            |x, y| rgb.get_pixel(x, y).0,
+        );
 
         let scale_x = new_width as f32 / orig_width as f32;
         let scale_y = new_height as f32 / orig_height as f32;
@@ -94,42 +114,23 @@ impl ImagePreprocessor {
         image: &DynamicImage,
     ) -> Result<Array4<f32>, OcrError> {
         let (width, height) = image.dimensions();
-
-        // Calculate target width maintaining aspect ratio
-        let aspect_ratio = width as f32 / height as f32;
-        let target_width = (self.rec_target_height as f32 * aspect_ratio) as u32;
-        let target_width = target_width.min(self.rec_target_width).max(1);
+        let (target_width, target_height) = self.recognition_size(width, height);
 
         let resized = image.resize_exact(
             target_width,
-            self.rec_target_height,
+            target_height,
             image::imageops::FilterType::Lanczos3,
         );
 
+        // Pad to the full recognition width
         let rgb = resized.to_rgb8();
-
-        // Create tensor with padding
-        let mut tensor = Array4::<f32>::zeros((
-            1,
-            3,
-            self.rec_target_height as usize,
-            self.rec_target_width as usize,
-        ));
-
-        let mean = [0.5f32, 0.5, 0.5];
-        let std = [0.5f32, 0.5, 0.5];
-
-        for y in 0..self.rec_target_height {
-            for x in 0..target_width {
-                let pixel = rgb.get_pixel(x, y);
-                for c in 0..3 {
-                    let value = pixel[c] as f32 / 255.0;
-                    tensor[[0, c, y as usize, x as usize]] = (value - mean[c]) / std[c];
-                }
-            }
-        }
-
-        Ok(tensor)
+        Ok(pack(
+            (self.rec_target_width, target_height),
+            (target_width, target_height),
+            TEXT_MEAN,
+            TEXT_STD,
+            |x, y| rgb.get_pixel(x, y).0,
+        ))
     }
 
     /// Preprocess for angle classification.
@@ -138,8 +139,7 @@ impl ImagePreprocessor {
         image: &DynamicImage,
     ) -> Result<Array4<f32>, OcrError> {
         // Angle classifier expects 192x48 input
-        let target_width = 192u32;
-        let target_height = 48u32;
+        let (target_width, target_height) = CLASSIFICATION_SIZE;
 
         let resized = image.resize_exact(
             target_width,
@@ -148,53 +148,161 @@ impl ImagePreprocessor {
         );
 
         let rgb = resized.to_rgb8();
+        Ok(pack(CLASSIFICATION_SIZE, CLASSIFICATION_SIZE, TEXT_MEAN, TEXT_STD, |x, y| {
+            rgb.get_pixel(x, y).0
+        }))
+    }
 
-        let mut tensor = Array4::<f32>::zeros((1, 3, target_height as usize, target_width as usize));
+    /// Model input sizes used by this preprocessor.
+    pub fn targets(&self) -> PreprocessingTargets {
+        PreprocessingTargets {
+            detection_max_side: self.det_target_size,
+            detection_align: DETECTION_ALIGN,
+            recognition_height: self.rec_target_height,
+            recognition_max_width: self.rec_target_width,
+            classification_width: CLASSIFICATION_SIZE.0,
+            classification_height: CLASSIFICATION_SIZE.1,
+        }
+    }
 
-        let mean = [0.5f32, 0.5, 0.5];
-        let std = [0.5f32, 0.5, 0.5];
+    /// Size an image is resized to before detection.
+    pub fn detection_size(&self, width: u32, height: u32) -> (u32, u32) {
+        self.calculate_resize_dimensions(width, height, self.det_target_size)
+    }
 
-        for y in 0..target_height {
-            for x in 0..target_width {
-                let pixel = rgb.get_pixel(x, y);
-                for c in 0..3 {
-                    let value = pixel[c] as f32 / 255.0;
-                    tensor[[0, c, y as usize, x as usize]] = (value - mean[c]) / std[c];
-                }
-            }
+    /// Size a text crop is resized to before recognition.
+    pub fn recognition_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let aspect_ratio = width as f32 / height.max(1) as f32;
+        let target_width = (self.rec_target_height as f32 * aspect_ratio) as u32;
+        (target_width.min(self.rec_target_width).max(1), self.rec_target_height)
+    }
+
+    /// Pack RGBA pixels already resized to [`detection_size`](Self::detection_size)
+    /// into a detection tensor.
+    ///
+    /// Lets callers resize with faster means, such as a GPU-accelerated
+    /// browser canvas, so only normalization and padding happen here.
+    pub fn pack_for_detection(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Array4<f32>, OcrError> {
+        check_rgba(rgba, width, height)?;
+        if width.max(height) > self.det_target_size {
+            return Err(OcrError::Preprocessing(format!(
+                "{}x{} image is larger than the detection input, resize it to {} pixels on the longer side",
+                width, height, self.det_target_size
+            )));
+        }
+
+        Ok(pack(
+            (align(width), align(height)),
+            (width, height),
+            DETECTION_MEAN,
+            DETECTION_STD,
+            |x, y| rgba_pixel(rgba, width, x, y),
+        ))
+    }
+
+    /// Pack RGBA pixels of a text crop already resized to
+    /// [`recognition_size`](Self::recognition_size) into a recognition tensor.
+    pub fn pack_for_recognition(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Array4<f32>, OcrError> {
+        check_rgba(rgba, width, height)?;
+        if height != self.rec_target_height || width > self.rec_target_width {
+            return Err(OcrError::Preprocessing(format!(
+                "{}x{} text crop does not fit the recognition input, resize it to {} pixels high and at most {} wide",
+                width, height, self.rec_target_height, self.rec_target_width
+            )));
+        }
+
+        Ok(pack(
+            (self.rec_target_width, height),
+            (width, height),
+            TEXT_MEAN,
+            TEXT_STD,
+            |x, y| rgba_pixel(rgba, width, x, y),
+        ))
+    }
+
+    /// Pack RGBA pixels of a text crop already resized to the classification
+    /// input size into a classification tensor.
+    pub fn pack_for_classification(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Array4<f32>, OcrError> {
+        check_rgba(rgba, width, height)?;
+        if (width, height) != CLASSIFICATION_SIZE {
+            return Err(OcrError::Preprocessing(format!(
+                "{}x{} text crop does not match the classification input, resize it to {}x{}",
+                width, height, CLASSIFICATION_SIZE.0, CLASSIFICATION_SIZE.1
+            )));
         }
 
-        Ok(tensor)
+        Ok(pack(CLASSIFICATION_SIZE, CLASSIFICATION_SIZE, TEXT_MEAN, TEXT_STD, |x, y| {
+            rgba_pixel(rgba, width, x, y)
+        }))
     }
 
-    /// Crop text region from image using quadrilateral coordinates.
+    /// Crop a text region given as a quadrilateral (top-left, top-right,
+    /// bottom-right, bottom-left corners).
+    ///
+    /// Rotated or skewed lines, common in phone photos, are warped to an
+    /// upright rectangle with a perspective transform, so the recognizer
+    /// gets a straight line instead of its tilted bounding box with
+    /// background and parts of neighbouring lines. Axis-aligned regions
+    /// are cropped directly.
     pub fn crop_text_region(
         &self,
         image: &DynamicImage,
         bbox: &[f32; 8],
     ) -> Result<DynamicImage, OcrError> {
-        // Get axis-aligned bounding box
-        let xs = [bbox[0], bbox[2], bbox[4], bbox[6]];
-        let ys = [bbox[1], bbox[3], bbox[5], bbox[7]];
-
-        let min_x = xs.iter().cloned().fold(f32::INFINITY, f32::min).max(0.0) as u32;
-        let max_x = xs
-            .iter()
-            .cloned()
-            .fold(f32::NEG_INFINITY, f32::max)
-            .min(image.width() as f32) as u32;
-        let min_y = ys.iter().cloned().fold(f32::INFINITY, f32::min).max(0.0) as u32;
-        let max_y = ys
-            .iter()
-            .cloned()
-            .fold(f32::NEG_INFINITY, f32::max)
-            .min(image.height() as f32) as u32;
-
-        let width = max_x.saturating_sub(min_x).max(1);
-        let height = max_y.saturating_sub(min_y).max(1);
-
-        let cropped = image.crop_imm(min_x, min_y, width, height);
-        Ok(cropped)
+        let corners = [
+            (bbox[0], bbox[1]),
+            (bbox[2], bbox[3]),
+            (bbox[4], bbox[5]),
+            (bbox[6], bbox[7]),
+        ];
+        if is_axis_aligned(&corners) {
+            return Ok(crop_bounding_box(image, &corners));
+        }
+
+        // Output size from the longer of each pair of opposite edges
+        let distance = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).hypot(a.1 - b.1);
+        let width = distance(corners[0], corners[1]).max(distance(corners[3], corners[2]));
+        let height = distance(corners[0], corners[3]).max(distance(corners[1], corners[2]));
+        let (width, height) = (width.round().max(1.0) as u32, height.round().max(1.0) as u32);
+
+        let Some(transform) = Perspective::to_quad(&corners) else {
+            return Ok(crop_bounding_box(image, &corners));
+        };
+
+        // Only the pixels under the region are converted and sampled
+        let (left, top, right, bottom) = bounds(image, &corners);
+        let source = image.crop_imm(left, top, right - left, bottom - top).to_rgba8();
+
+        let warped = RgbaImage::from_fn(width, height, |x, y| {
+            let u = (x as f64 + 0.5) / width as f64;
+            let v = (y as f64 + 0.5) / height as f64;
+            let (sx, sy) = transform.map(u, v);
+            sample(&source, sx - left as f64 - 0.5, sy - top as f64 - 0.5)
+        });
+
+        Ok(DynamicImage::ImageRgba8(warped))
+    }
+
+    /// Straighten a page photographed or scanned at an angle, returning
+    /// the rotated page and the corrected skew in degrees, or `None` if
+    /// it is straight enough. See [`deskew`](super::deskew).
+    pub fn deskew(&self, image: &DynamicImage) -> Option<(DynamicImage, f32)> {
+        deskew::deskew(image)
     }
 
     /// Apply basic image enhancement for better OCR.
@@ -264,6 +372,154 @@ impl ImagePreprocessor {
     }
 }
 
+/// Whether a quadrilateral is an upright rectangle, to within a pixel.
+fn is_axis_aligned(corners: &[(f32, f32); 4]) -> bool {
+    let [top_left, top_right, bottom_right, bottom_left] = *corners;
+    let close = |a: f32, b: f32| (a - b).abs() < 1.0;
+    close(top_left.1, top_right.1)
+        && close(bottom_left.1, bottom_right.1)
+        && close(top_left.0, bottom_left.0)
+        && close(top_right.0, bottom_right.0)
+}
+
+/// Pixel bounds (left, top, right, bottom) of the corners within the
+/// image, at least one pixel wide and high.
+fn bounds(image: &DynamicImage, corners: &[(f32, f32); 4]) -> (u32, u32, u32, u32) {
+    let (width, height) = image.dimensions();
+    let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min);
+    let max_x = corners.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max);
+    let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min);
+    let max_y = corners.iter().map(|c| c.1).fold(f32::NEG_INFINITY, f32::max);
+
+    let left = (min_x.max(0.0) as u32).min(width.saturating_sub(1));
+    let top = (min_y.max(0.0) as u32).min(height.saturating_sub(1));
+    let right = (max_x.ceil().min(width as f32) as u32).max(left + 1);
+    let bottom = (max_y.ceil().min(height as f32) as u32).max(top + 1);
+    (left, top, right, bottom)
+}
+
+/// Crop the axis-aligned bounding box of the corners.
+fn crop_bounding_box(image: &DynamicImage, corners: &[(f32, f32); 4]) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min).max(0.0) as u32;
+    let max_x = corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f32::NEG_INFINITY, f32::max)
+        .min(width as f32) as u32;
+    let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min).max(0.0) as u32;
+    let max_y = corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f32::NEG_INFINITY, f32::max)
+        .min(height as f32) as u32;
+
+    let width = max_x.saturating_sub(min_x).max(1);
+    let height = max_y.saturating_sub(min_y).max(1);
+    image.crop_imm(min_x, min_y, width, height)
+}
+
+/// Projective map of the unit square onto a quadrilateral: (0, 0), (1, 0),
+/// (1, 1) and (0, 1) go to its four corners in order (Heckbert, 1989).
+struct Perspective {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+    g: f64,
+    h: f64,
+}
+
+impl Perspective {
+    /// The map onto `corners`, `None` if they are degenerate.
+    fn to_quad(corners: &[(f32, f32); 4]) -> Option<Self> {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = corners.map(|(x, y)| (x as f64, y as f64));
+
+        let sx = x0 - x1 + x2 - x3;
+        let sy = y0 - y1 + y2 - y3;
+        let (g, h) = if sx.abs() < 1e-9 && sy.abs() < 1e-9 {
+            // A parallelogram: the map is affine
+            (0.0, 0.0)
+        } else {
+            let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+            let det = dx1 * dy2 - dx2 * dy1;
+            if det.abs() < 1e-9 {
+                return None;
+            }
+            ((sx * dy2 - dx2 * sy) / det, (dx1 * sy - sx * dy1) / det)
+        };
+
+        let transform = Self {
+            a: x1 - x0 + g * x1,
+            b: x3 - x0 + h * x3,
+            c: x0,
+            d: y1 - y0 + g * y1,
+            e: y3 - y0 + h * y3,
+            f: y0,
+            g,
+            h,
+        };
+        let area = transform.a * transform.e - transform.b * transform.d;
+        (area.abs() > 1e-9).then_some(transform)
+    }
+
+    /// Image coordinates of the point (u, v) of the unit square.
+    fn map(&self, u: f64, v: f64) -> (f64, f64) {
+        let w = self.g * u + self.h * v + 1.0;
+        ((self.a * u + self.b * v + self.c) / w, (self.d * u + self.e * v + self.f) / w)
+    }
+}
+
+/// Round a detection input side up to [`DETECTION_ALIGN`].
+fn align(side: u32) -> u32 {
+    side.div_ceil(DETECTION_ALIGN) * DETECTION_ALIGN
+}
+
+/// Normalize the pixels of an image of `image_size` into an NCHW tensor of
+/// `tensor_size` (both width, height), zero-padded right and bottom.
+fn pack(
+    tensor_size: (u32, u32),
+    image_size: (u32, u32),
+    mean: [f32; 3],
+    std: [f32; 3],
+    pixel: impl Fn(u32, u32) -> [u8; 3],
+) -> Array4<f32> {
+    let mut tensor = Array4::<f32>::zeros((1, 3, tensor_size.1 as usize, tensor_size.0 as usize));
+
+    for y in 0..image_size.1 {
+        for x in 0..image_size.0 {
+            let pixel = pixel(x, y);
+            for c in 0..3 {
+                let value = pixel[c] as f32 / 255.0;
+                tensor[[0, c, y as usize, x as usize]] = (value - mean[c]) / std[c];
+            }
+        }
+    }
+
+    tensor
+}
+
+/// Check that `rgba` holds `width` x `height` RGBA pixels, as in `ImageData`.
+fn check_rgba(rgba: &[u8], width: u32, height: u32) -> Result<(), OcrError> {
+    if width == 0 || height == 0 || rgba.len() != width as usize * height as usize * 4 {
+        return Err(OcrError::Preprocessing(format!(
+            "expected {}x{} RGBA pixels ({} bytes), got {} bytes",
+            width,
+            height,
+            width as usize * height as usize * 4,
+            rgba.len()
+        )));
+    }
+    Ok(())
+}
+
+fn rgba_pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+    let i = (y as usize * width as usize + x as usize) * 4;
+    [rgba[i], rgba[i + 1], rgba[i + 2]]
+}
+
 impl Default for ImagePreprocessor {
     fn default() -> Self {
         Self::new()
@@ -287,4 +543,81 @@ mod tests {
         assert_eq!(w, 960);
         assert!(h < 960);
     }
+
+    #[test]
+    fn test_pack_matches_preprocessing() {
+        let preprocessor = ImagePreprocessor::new();
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(100, 40, |x, y| {
+            image::Rgba([(x * 2) as u8, (y * 5) as u8, 128, 255])
+        }));
+        let rgba = image.to_rgba8().into_raw();
+
+        // Images within the detection target are not resized
+        let (expected, ..) = preprocessor.preprocess_for_detection(&image).unwrap();
+        let packed = preprocessor.pack_for_detection(&rgba, 100, 40).unwrap();
+        assert_eq!(packed.shape(), &[1, 3, 64, 128]);
+        assert_eq!(packed, expected);
+
+        assert_eq!(preprocessor.recognition_size(100, 40), (120, 48));
+        assert_eq!(preprocessor.detection_size(1920, 1080), (960, 540));
+    }
+
+    #[test]
+    fn test_crop_rotated_region() {
+        // A dark line rotated by about 10 degrees on a white page
+        let (angle, length, thickness) = (0.17f32, 120.0f32, 16.0f32);
+        let (cos, sin) = (angle.cos(), angle.sin());
+        let origin = (40.0f32, 30.0f32);
+        let inside = |x: f32, y: f32| {
+            let (dx, dy) = (x - origin.0, y - origin.1);
+            let along = dx * cos + dy * sin;
+            let across = -dx * sin + dy * cos;
+            (0.0..length).contains(&along) && (0.0..thickness).contains(&across)
+        };
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(200, 100, |x, y| {
+            let value = if inside(x as f32 + 0.5, y as f32 + 0.5) { 0 } else { 255 };
+            image::Rgba([value, value, value, 255])
+        }));
+
+        let corner = |along: f32, across: f32| {
+            (origin.0 + along * cos - across * sin, origin.1 + along * sin + across * cos)
+        };
+        let corners = [
+            corner(0.0, 0.0),
+            corner(length, 0.0),
+            corner(length, thickness),
+            corner(0.0, thickness),
+        ];
+        let bbox = corners.map(|(x, y)| [x, y]).concat().try_into().unwrap();
+
+        let preprocessor = ImagePreprocessor::new();
+        let crop = preprocessor.crop_text_region(&image, &bbox).unwrap();
+        assert_eq!(crop.dimensions(), (120, 16));
+
+        // The warped line is dark throughout; its bounding box is mostly page
+        let mean = |image: &DynamicImage| {
+            let gray = image.to_luma8();
+            gray.pixels().map(|p| p.0[0] as f32).sum::<f32>() / gray.len() as f32
+        };
+        assert!(mean(&crop) < 40.0, "{}", mean(&crop));
+        assert!(mean(&crop_bounding_box(&image, &corners)) > 100.0);
+
+        // Upright boxes are cropped as they are
+        let upright = preprocessor
+            .crop_text_region(&image, &[10.0, 5.0, 50.0, 5.0, 50.0, 25.0, 10.0, 25.0])
+            .unwrap();
+        assert_eq!(upright.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn test_pack_rejects_wrong_size() {
+        let preprocessor = ImagePreprocessor::new();
+
+        assert!(preprocessor.pack_for_detection(&[0; 16], 2, 3).is_err());
+        assert!(preprocessor.pack_for_detection(&vec![0; 1000 * 4], 1000, 1).is_err());
+        assert!(preprocessor.pack_for_recognition(&vec![0; 100 * 40 * 4], 100, 40).is_err());
+        assert!(preprocessor.pack_for_classification(&vec![0; 192 * 48 * 4], 192, 48).is_ok());
+        let packed = preprocessor.pack_for_recognition(&vec![0; 120 * 48 * 4], 120, 48).unwrap();
+        assert_eq!(packed.shape(), &[1, 3, 48, 320]);
+    }
 }
@@ -1,124 +1,396 @@
 //! Pure Rust OCR engine wrapper using `pure-onnx-ocr`.
 
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 use std::time::Instant;
 
 use image::{DynamicImage, GenericImageView};
 use tracing::{debug, info};
 
 use crate::error::OcrError;
+use crate::models::capabilities::{Capabilities, Stage};
 use crate::models::config::OcrConfig;
+use crate::models::embedded::EmbeddedModels;
+use crate::progress::{NoProgress, ProgressEvent, ProgressSink, ProgressStage};
 
-use super::{OcrResult, TextBox};
+use super::barcode::{decode_page, engine_decoder, no_decoder_reason, BarcodeDecoder};
+use super::deskew::{deskew, record_deskew};
+use super::orientation::{is_sideways, record_orientation, turn_upright};
+use super::upscale::{
+    median_text_height, record_upscaling, scale_bbox, upscale_bicubic, upscale_factor,
+};
+use super::{LayoutInfo, OcrResult, TextBox};
+#[cfg(feature = "super-resolution")]
+use super::upscale::{text_upscale_factor, TARGET_TEXT_HEIGHT};
+#[cfg(feature = "super-resolution")]
+use super::{SuperResolution, SR_MODEL};
+#[cfg(feature = "layout")]
+use super::{LayoutDetector, LAYOUT_MODEL};
+#[cfg(feature = "tables")]
+use super::{TableClassifier, TableRecognizer, TABLE_CLASSIFIER_MODEL, TABLE_MODEL};
+#[cfg(feature = "handwriting")]
+use super::{
+    HandwritingReader, StyleClassifier, TextRecognizer, HANDWRITING_DICTIONARY,
+    HANDWRITING_MODEL, STYLE_MODEL,
+};
+
+/// Reason for the stages `pure-onnx-ocr` has no model for.
+const UNSUPPORTED: &str = "not supported by the pure-onnx-ocr engine";
+
+/// Models loaded by live engines, shared with engines loading them again.
+type ModelCache<K, T> = OnceLock<Mutex<HashMap<K, Weak<T>>>>;
+
+/// Detection and recognition engines by detection model, recognition model
+/// and dictionary file, so engines differing only in their settings run
+/// on one copy of the models.
+static OCR_MODELS: ModelCache<
+    (std::path::PathBuf, std::path::PathBuf, std::path::PathBuf),
+    pure_onnx_ocr::engine::OcrEngine,
+> = OnceLock::new();
+
+/// Embedded models written out for `pure-onnx-ocr`, by the address of their
+/// detection model, so engines of one variant share one copy on disk.
+static EXTRACTED_MODELS: ModelCache<usize, tempfile::TempDir> = OnceLock::new();
+
+/// Super-resolution models by file.
+#[cfg(feature = "super-resolution")]
+static SR_MODELS: ModelCache<std::path::PathBuf, SuperResolution<incr_inference::TractBackend>> =
+    OnceLock::new();
+
+/// Layout models by file.
+#[cfg(feature = "layout")]
+static LAYOUT_MODELS: ModelCache<std::path::PathBuf, LayoutDetector<incr_inference::TractBackend>> =
+    OnceLock::new();
+
+/// Table models by structure and classification model file.
+#[cfg(feature = "tables")]
+static TABLE_MODELS: ModelCache<
+    (std::path::PathBuf, Option<std::path::PathBuf>),
+    TableRecognizer<incr_inference::TractBackend>,
+> = OnceLock::new();
+
+/// Handwriting models by recognition model and dictionary file.
+#[cfg(feature = "handwriting")]
+static HANDWRITING_MODELS: ModelCache<
+    (std::path::PathBuf, std::path::PathBuf),
+    HandwritingReader<incr_inference::TractBackend>,
+> = OnceLock::new();
 
 /// OCR engine backed by `pure-onnx-ocr` (pure Rust, no external ONNX Runtime).
 pub struct PureOcrEngine {
-    engine: pure_onnx_ocr::engine::OcrEngine,
+    /// Shared by the engines loading the same model files.
+    engine: Arc<pure_onnx_ocr::engine::OcrEngine>,
     #[allow(dead_code)]
     config: OcrConfig,
-    /// Keep temp dir alive so the temp files aren't deleted.
-    _temp_dir: Option<tempfile::TempDir>,
+    /// Keep temp dir alive so the temp files aren't deleted. Shared by the
+    /// engines built from the same embedded models.
+    _temp_dir: Option<Arc<tempfile::TempDir>>,
+    /// Used instead of bicubic interpolation to upscale low-resolution
+    /// images. Shared by the engines loading the same model file.
+    #[cfg(feature = "super-resolution")]
+    super_resolution: Option<Arc<SuperResolution<incr_inference::TractBackend>>>,
+    /// Finds tables, text regions and figures. Shared by the engines
+    /// loading the same model file.
+    #[cfg(feature = "layout")]
+    layout_detector: Option<Arc<LayoutDetector<incr_inference::TractBackend>>>,
+    /// Recognizes the structure of the tables the layout model finds.
+    /// Shared by the engines loading the same model files.
+    #[cfg(feature = "tables")]
+    table_recognizer: Option<Arc<TableRecognizer<incr_inference::TractBackend>>>,
+    /// Reads the lines classified as handwritten again. Shared by the
+    /// engines loading the same model files.
+    #[cfg(feature = "handwriting")]
+    handwriting: Option<Arc<HandwritingReader<incr_inference::TractBackend>>>,
+    barcode_decoder: Option<Box<dyn BarcodeDecoder>>,
 }
 
 impl PureOcrEngine {
     /// Create an engine from model files in a directory.
     pub fn from_dir(model_dir: &Path, config: OcrConfig) -> Result<Self, OcrError> {
-        let det_path = model_dir.join("det.onnx");
-        let rec_path = model_dir.join("latin_rec.onnx");
-        let dict_path = model_dir.join("latin_dict.txt");
+        Self::from_dir_with_files(model_dir, "latin_rec.onnx", "latin_dict.txt", config)
+    }
 
-        let engine = pure_onnx_ocr::engine::OcrEngineBuilder::new()
-            .det_model_path(&det_path)
-            .rec_model_path(&rec_path)
-            .dictionary_path(&dict_path)
-            .build()
-            .map_err(|e| OcrError::ModelLoad(format!("pure-onnx-ocr: {}", e)))?;
+    /// Create an engine from the model files in a directory, with the
+    /// recognition model and dictionary named `recognition_model` and
+    /// `dictionary` (e.g. those of [`ModelConfig::recognition_files`]).
+    ///
+    /// [`ModelConfig::recognition_files`]: crate::models::config::ModelConfig::recognition_files
+    pub fn from_dir_with_files(
+        model_dir: &Path,
+        recognition_model: &str,
+        dictionary: &str,
+        config: OcrConfig,
+    ) -> Result<Self, OcrError> {
+        let det_path = model_dir.join("det.onnx");
+        let rec_path = model_dir.join(recognition_model);
+        let dict_path = model_dir.join(dictionary);
 
+        let engine = load_models(&det_path, &rec_path, &dict_path)?;
         info!("Loaded pure-onnx-ocr engine from {}", model_dir.display());
 
-        Ok(Self {
+        let engine = Self {
             engine,
+            barcode_decoder: engine_decoder(&config, None),
             config,
             _temp_dir: None,
-        })
+            #[cfg(feature = "super-resolution")]
+            super_resolution: None,
+            #[cfg(feature = "layout")]
+            layout_detector: load_layout(model_dir)?,
+            #[cfg(feature = "tables")]
+            table_recognizer: load_tables(model_dir)?,
+            #[cfg(feature = "handwriting")]
+            handwriting: load_handwriting(model_dir, &dict_path)?,
+        };
+
+        // Pick up the optional super-resolution model
+        #[cfg(feature = "super-resolution")]
+        let engine = {
+            let sr_path = model_dir.join(SR_MODEL);
+            if sr_path.exists() {
+                let key = sr_path.canonicalize().unwrap_or_else(|_| sr_path.clone());
+                let super_resolution = shared_model(&SR_MODELS, key, || {
+                    let tile = super::super_resolution::TILE_SIZE as usize;
+                    let backend = incr_inference::TractBackend::from_file_with_shape(
+                        &sr_path,
+                        &[1, 3, tile, tile],
+                    )
+                    .map_err(|e| OcrError::ModelLoad(format!("super-resolution: {}", e)))?;
+                    info!("Loaded super-resolution model from {}", sr_path.display());
+                    Ok(SuperResolution::new(backend))
+                })?;
+                Self {
+                    super_resolution: Some(super_resolution),
+                    ..engine
+                }
+            } else {
+                engine
+            }
+        };
+
+        Ok(engine)
+    }
+
+    /// Create an engine from the embedded mobile models.
+    pub fn from_embedded(config: OcrConfig) -> Result<Self, OcrError> {
+        Self::from_embedded_models(EmbeddedModels::mobile(), config)
     }
 
     /// Create an engine from embedded model bytes.
     ///
     /// Writes model bytes to temporary files (required by `pure-onnx-ocr`'s
     /// file-path-based API), then loads from those paths. The temp directory
-    /// is kept alive for the lifetime of the engine.
-    pub fn from_embedded(config: OcrConfig) -> Result<Self, OcrError> {
-        use crate::models::embedded::EmbeddedModels;
-
-        let models = EmbeddedModels::mobile();
-        let temp_dir = tempfile::tempdir()
-            .map_err(|e| OcrError::ModelLoad(format!("failed to create temp dir: {}", e)))?;
+    /// is kept alive for the lifetime of the engine, and engines built from
+    /// the same models while it is alive reuse it instead of writing the
+    /// models again.
+    pub fn from_embedded_models(
+        models: EmbeddedModels,
+        config: OcrConfig,
+    ) -> Result<Self, OcrError> {
+        let temp_dir = shared_model(&EXTRACTED_MODELS, models.detection.as_ptr() as usize, || {
+            extract_models(&models)
+        })?;
 
         let det_path = temp_dir.path().join("det.onnx");
         let rec_path = temp_dir.path().join("latin_rec.onnx");
         let dict_path = temp_dir.path().join("latin_dict.txt");
 
-        std::fs::write(&det_path, models.detection)
-            .map_err(|e| OcrError::ModelLoad(format!("failed to write det model: {}", e)))?;
-        std::fs::write(&rec_path, models.recognition)
-            .map_err(|e| OcrError::ModelLoad(format!("failed to write rec model: {}", e)))?;
-        std::fs::write(&dict_path, models.dictionary)
-            .map_err(|e| OcrError::ModelLoad(format!("failed to write dictionary: {}", e)))?;
-
-        debug!(
-            "Wrote embedded models to temp dir: {}",
-            temp_dir.path().display()
-        );
-
-        let engine = pure_onnx_ocr::engine::OcrEngineBuilder::new()
-            .det_model_path(&det_path)
-            .rec_model_path(&rec_path)
-            .dictionary_path(&dict_path)
-            .build()
-            .map_err(|e| OcrError::ModelLoad(format!("pure-onnx-ocr: {}", e)))?;
-
+        let engine = load_models(&det_path, &rec_path, &dict_path)?;
         info!("Created pure-onnx-ocr engine from embedded models");
 
         Ok(Self {
             engine,
+            barcode_decoder: engine_decoder(&config, None),
             config,
             _temp_dir: Some(temp_dir),
+            #[cfg(feature = "super-resolution")]
+            super_resolution: None,
+            #[cfg(feature = "layout")]
+            layout_detector: None,
+            #[cfg(feature = "tables")]
+            table_recognizer: None,
+            #[cfg(feature = "handwriting")]
+            handwriting: None,
         })
     }
 
+    /// Upscale low-resolution images with a super-resolution model instead
+    /// of bicubic interpolation.
+    #[cfg(feature = "super-resolution")]
+    pub fn with_super_resolution(
+        mut self,
+        super_resolution: SuperResolution<incr_inference::TractBackend>,
+    ) -> Self {
+        self.super_resolution = Some(Arc::new(super_resolution));
+        self
+    }
+
+    /// Detect tables, text regions and figures with `layout_detector`
+    /// (unless `ocr.enable_layout` is off).
+    #[cfg(feature = "layout")]
+    pub fn with_layout_detector(
+        mut self,
+        layout_detector: LayoutDetector<incr_inference::TractBackend>,
+    ) -> Self {
+        self.layout_detector = Some(Arc::new(layout_detector));
+        self
+    }
+
+    /// Recognize the structure of the tables the layout detector finds with
+    /// `table_recognizer`, for their line items.
+    #[cfg(feature = "tables")]
+    pub fn with_table_recognizer(
+        mut self,
+        table_recognizer: TableRecognizer<incr_inference::TractBackend>,
+    ) -> Self {
+        self.table_recognizer = Some(Arc::new(table_recognizer));
+        self
+    }
+
+    /// Read the lines `reader` classifies as handwritten with its
+    /// handwriting recognizer.
+    #[cfg(feature = "handwriting")]
+    pub fn with_handwriting(
+        mut self,
+        reader: HandwritingReader<incr_inference::TractBackend>,
+    ) -> Self {
+        self.handwriting = Some(Arc::new(reader));
+        self
+    }
+
+    /// Decode QR codes and barcodes with `decoder` instead of the built-in
+    /// decoder of the `barcode` feature (unless `ocr.decode_barcodes` is
+    /// off).
+    pub fn with_barcode_decoder(mut self, decoder: impl BarcodeDecoder + 'static) -> Self {
+        self.barcode_decoder = engine_decoder(&self.config, Some(Box::new(decoder)));
+        self
+    }
+
     /// Process an image and extract text with bounding boxes.
     pub fn process(&self, image: &DynamicImage) -> Result<OcrResult, OcrError> {
+        self.process_with_progress(image, &NoProgress)
+    }
+
+    /// Process an image, reporting progress to `progress`.
+    ///
+    /// `pure-onnx-ocr` runs detection and recognition in one call, reported
+    /// as detection; the readings of a sideways page turned upright, the
+    /// upscaled pass and the lines recognized again by super-resolution and
+    /// the second pass are reported as recognition.
+    pub fn process_with_progress(
+        &self,
+        image: &DynamicImage,
+        progress: &dyn ProgressSink,
+    ) -> Result<OcrResult, OcrError> {
         let start = Instant::now();
         let (width, height) = image.dimensions();
 
         info!("Processing image: {}x{}", width, height);
 
-        let results = self
-            .engine
-            .run_from_image(image)
-            .map_err(|e| OcrError::Detection(format!("pure-onnx-ocr: {}", e)))?;
+        // Skewed pages are straightened first; boxes refer to the result
+        let deskewed = self.straighten(image);
+        let image = match &deskewed {
+            Some((page, _)) => page,
+            None => image,
+        };
+        let mut skew = deskewed.as_ref().map(|(_, angle)| *angle);
 
-        debug!("pure-onnx-ocr returned {} text regions", results.len());
+        progress.report(ProgressEvent::new(ProgressStage::Detection, 0, 1, "Detecting text regions"));
 
-        let mut text_boxes: Vec<TextBox> = results
-            .iter()
-            .map(|r| {
-                let bbox = polygon_to_bbox(&r.bounding_box);
-                let text = if self.config.keep_unk {
-                    r.text.clone()
-                } else {
-                    r.text.replace("[UNK]", " ")
-                };
-                TextBox {
-                    bbox,
-                    text,
-                    detection_score: r.confidence,
-                    recognition_score: r.confidence,
-                    angle: 0,
-                }
-            })
-            .collect();
+        let mut text_boxes = self.recognize(image)?;
+        progress.report(ProgressEvent::new(
+            ProgressStage::Detection,
+            1,
+            1,
+            format!("Detected {} text regions", text_boxes.len()),
+        ));
+        let mut capabilities = self.capabilities();
+
+        // Pages scanned sideways are read turned both ways
+        let turned = if self.config.auto_orient && is_sideways(text_boxes.iter().map(|b| &b.bbox)) {
+            self.turn_sideways(image, &mut text_boxes, progress)?
+        } else {
+            None
+        };
+        let orientation = turned.as_ref().map_or(0, |turned| turned.orientation);
+        record_orientation(&mut capabilities, &self.config, orientation);
+        let image = match &turned {
+            Some(turned) => {
+                debug!("Page text rotated {} degrees, turned upright", orientation);
+                skew = turned.skew.or(skew);
+                &turned.page
+            }
+            None => image,
+        };
+        record_deskew(&mut capabilities, &self.config, skew);
+        let (width, height) = image.dimensions();
+
+        // Small text is recognized again on an upscaled copy
+        let text_height = median_text_height(text_boxes.iter().map(TextBox::height));
+        let factor = text_height.and_then(|h| upscale_factor(&self.config, h, (width, height)));
+        record_upscaling(&mut capabilities, &self.config, text_height, factor);
+        if let Some(factor) = factor {
+            debug!(
+                "Median text height {:.1}px, upscaling {:.2}x before recognition",
+                text_height.unwrap_or_default(),
+                factor
+            );
+
+            progress.report(ProgressEvent::new(
+                ProgressStage::Recognition,
+                0,
+                1,
+                format!("Recognizing {:.1}x upscaled image", factor),
+            ));
+            let upscaled = self.upscale(image, factor)?;
+            let mut upscaled_boxes = self.recognize(&upscaled)?;
+            for text_box in &mut upscaled_boxes {
+                text_box.bbox = scale_bbox(&text_box.bbox, 1.0 / factor);
+            }
+
+            // Keep the original result if upscaling didn't help
+            if mean_score(&upscaled_boxes) >= mean_score(&text_boxes) {
+                text_boxes = upscaled_boxes;
+            } else {
+                debug!("Upscaled image recognized with lower confidence, keeping original");
+            }
+        }
+
+        // Otherwise only tiny print is enhanced and recognized again
+        #[cfg(feature = "super-resolution")]
+        if let Some(sr) = &self.super_resolution {
+            // With an upscaled image the whole page went through the model
+            if factor.is_some() || self.enhance_small_text(sr, image, &mut text_boxes, progress)? {
+                capabilities.ran(Stage::SuperResolution);
+            } else {
+                capabilities.skipped(Stage::SuperResolution, "no small low-confidence text");
+            }
+        }
+
+        // Low-confidence regions get a second look at twice the size
+        if self.config.second_pass_threshold > 0.0 {
+            if self.second_pass(image, &mut text_boxes, progress)? {
+                capabilities.ran(Stage::SecondPass);
+            } else {
+                capabilities.skipped(Stage::SecondPass, "no low-confidence lines");
+            }
+        }
+
+        // Lines classified as handwritten are read by the handwriting model
+        #[cfg(feature = "handwriting")]
+        if let Some(handwriting) = &self.handwriting {
+            let count = handwriting.read(image, &mut text_boxes)?;
+            debug!("Read {} handwritten text boxes", count);
+        }
+
+        let layout = self.detect_layout(image, &mut capabilities, progress);
+
+        // Codes in figure regions, or anywhere without layout analysis
+        let figures = layout.as_ref().map(|layout| layout.figures.as_slice());
+        let barcodes =
+            decode_page(self.barcode_decoder.as_deref(), image, figures, &mut capabilities);
 
         // Sort by reading order
         text_boxes.sort_by(|a, b| {
@@ -143,25 +415,549 @@ impl PureOcrEngine {
 
         let processing_time_ms = start.elapsed().as_millis() as u64;
 
+        progress.report(ProgressEvent::new(
+            ProgressStage::Recognition,
+            1,
+            1,
+            format!("Recognized {} text boxes", text_boxes.len()),
+        ));
+
         info!(
             "OCR complete: {} text boxes in {}ms",
             text_boxes.len(),
             processing_time_ms
         );
 
-        Ok(OcrResult {
+        let mut result = OcrResult {
             boxes: text_boxes,
             text,
             processing_time_ms,
             image_size: (width, height),
-            layout: None,
-        })
+            layout,
+            capabilities,
+            barcodes,
+        };
+        self.recognize_tables(image, &mut result);
+        Ok(result)
+    }
+
+    /// Stages this engine runs on every image, and why the others are
+    /// skipped.
+    ///
+    /// Stages that depend on the image (upscaling, super-resolution and
+    /// the second pass) are only listed here when they cannot run;
+    /// [`process`](Self::process) records whether they did.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::new();
+        capabilities.ran(Stage::Detection);
+        capabilities.ran(Stage::Recognition);
+
+        if self.config.enable_classification {
+            capabilities.skipped(Stage::Classification, UNSUPPORTED);
+        } else {
+            capabilities.skipped(Stage::Classification, "disabled by ocr.enable_classification");
+        }
+
+        #[cfg(feature = "layout")]
+        capabilities.model_stage(
+            Stage::Layout,
+            ("ocr.enable_layout", self.config.enable_layout),
+            (LAYOUT_MODEL, self.layout_detector.is_some()),
+        );
+        #[cfg(not(feature = "layout"))]
+        if self.config.enable_layout {
+            capabilities.skipped(Stage::Layout, "built without the layout feature");
+        } else {
+            capabilities.skipped(Stage::Layout, "disabled by ocr.enable_layout");
+        }
+
+        #[cfg(feature = "handwriting")]
+        if self.handwriting.is_some() {
+            capabilities.ran(Stage::Handwriting);
+        } else {
+            capabilities.skipped(
+                Stage::Handwriting,
+                format!("{} and {} not installed", STYLE_MODEL, HANDWRITING_MODEL),
+            );
+        }
+        #[cfg(not(feature = "handwriting"))]
+        capabilities.skipped(Stage::Handwriting, "built without the handwriting feature");
+
+        #[cfg(feature = "super-resolution")]
+        if self.super_resolution.is_none() {
+            capabilities.skipped(Stage::SuperResolution, format!("{} not installed", SR_MODEL));
+        }
+        #[cfg(not(feature = "super-resolution"))]
+        capabilities.skipped(Stage::SuperResolution, "built without the super-resolution feature");
+
+        if self.config.second_pass_threshold <= 0.0 {
+            capabilities.skipped(Stage::SecondPass, "disabled by ocr.second_pass_threshold");
+        }
+        if self.barcode_decoder.is_none() {
+            capabilities.skipped(Stage::Barcodes, no_decoder_reason(&self.config));
+        }
+
+        capabilities
+    }
+
+    /// Tables, text regions and figures of a page, with the layout model
+    /// if there is one and `ocr.enable_layout` is set.
+    #[cfg(feature = "layout")]
+    fn detect_layout(
+        &self,
+        image: &DynamicImage,
+        capabilities: &mut Capabilities,
+        progress: &dyn ProgressSink,
+    ) -> Option<LayoutInfo> {
+        let layout_detector = self.layout_detector.as_ref().filter(|_| self.config.enable_layout)?;
+        progress.report(ProgressEvent::new(ProgressStage::Layout, 0, 1, "Detecting layout"));
+        match layout_detector.detect(image) {
+            Ok(layout) => Some(layout.info()),
+            Err(e) => {
+                debug!("Layout detection failed: {}", e);
+                capabilities.failed(Stage::Layout, format!("layout detection failed: {}", e));
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "layout"))]
+    fn detect_layout(
+        &self,
+        _image: &DynamicImage,
+        _capabilities: &mut Capabilities,
+        _progress: &dyn ProgressSink,
+    ) -> Option<LayoutInfo> {
+        None
+    }
+
+    /// Cells of the table regions of `result`, with the table structure
+    /// model if there is one.
+    #[cfg(feature = "tables")]
+    fn recognize_tables(&self, image: &DynamicImage, result: &mut OcrResult) {
+        if let Some(table_recognizer) = &self.table_recognizer {
+            let count = table_recognizer.recognize_regions(image, result);
+            debug!("Recognized the structure of {} tables", count);
+        }
+    }
+
+    #[cfg(not(feature = "tables"))]
+    fn recognize_tables(&self, _image: &DynamicImage, _result: &mut OcrResult) {}
+
+    /// Straighten a skewed page if `ocr.auto_deskew` is set.
+    fn straighten(&self, image: &DynamicImage) -> Option<(DynamicImage, f32)> {
+        let deskewed = self.config.auto_deskew.then(|| deskew(image))??;
+        debug!("Page skewed by {:.1} degrees, straightened", deskewed.1);
+        Some(deskewed)
+    }
+
+    /// Read a page scanned sideways turned a quarter both ways (and
+    /// straightened), replacing `text_boxes` with the more confident
+    /// reading if it beats the original.
+    fn turn_sideways(
+        &self,
+        image: &DynamicImage,
+        text_boxes: &mut Vec<TextBox>,
+        progress: &dyn ProgressSink,
+    ) -> Result<Option<TurnedPage>, OcrError> {
+        let mut best = None;
+        let mut best_score = mean_score(text_boxes);
+        for (i, orientation) in [90, 270].into_iter().enumerate() {
+            progress.report(ProgressEvent::new(
+                ProgressStage::Recognition,
+                i as u64,
+                2,
+                format!("Recognizing page turned {} degrees", orientation),
+            ));
+            let page = turn_upright(image, orientation);
+            let (page, skew) = match self.straighten(&page) {
+                Some((straightened, angle)) => (straightened, Some(angle)),
+                None => (page, None),
+            };
+            let mut boxes = self.recognize(&page)?;
+            let score = mean_score(&boxes);
+            if score > best_score {
+                for text_box in &mut boxes {
+                    text_box.angle = orientation;
+                }
+                best_score = score;
+                best = Some((TurnedPage { page, orientation, skew }, boxes));
+            }
+        }
+
+        Ok(best.map(|(turned, boxes)| {
+            *text_boxes = boxes;
+            turned
+        }))
+    }
+
+    /// Run detection and recognition on an image.
+    fn recognize(&self, image: &DynamicImage) -> Result<Vec<TextBox>, OcrError> {
+        let results = self
+            .engine
+            .run_from_image(image)
+            .map_err(|e| OcrError::Detection(format!("pure-onnx-ocr: {}", e)))?;
+
+        debug!("pure-onnx-ocr returned {} text regions", results.len());
+
+        Ok(results
+            .iter()
+            .map(|r| {
+                let bbox = polygon_to_bbox(&r.bounding_box);
+                let text = if self.config.keep_unk {
+                    r.text.clone()
+                } else {
+                    r.text.replace("[UNK]", " ")
+                };
+                TextBox {
+                    bbox,
+                    text,
+                    detection_score: r.confidence,
+                    recognition_score: r.confidence,
+                    angle: 0,
+                    handwritten: false,
+                }
+            })
+            .collect())
+    }
+
+    /// Recognize small, low-confidence text boxes again after upscaling
+    /// their crops with the super-resolution model, keeping the better text.
+    /// Returns whether any box was enhanced.
+    #[cfg(feature = "super-resolution")]
+    fn enhance_small_text(
+        &self,
+        super_resolution: &SuperResolution<incr_inference::TractBackend>,
+        image: &DynamicImage,
+        text_boxes: &mut [TextBox],
+        progress: &dyn ProgressSink,
+    ) -> Result<bool, OcrError> {
+        let threshold = self.config.super_resolution_threshold;
+        let mut retries: Vec<&mut TextBox> = text_boxes
+            .iter_mut()
+            .filter(|b| b.recognition_score < threshold && b.height() < TARGET_TEXT_HEIGHT)
+            .collect();
+
+        let total = retries.len();
+        for (i, text_box) in retries.iter_mut().enumerate() {
+            report_retry(progress, "Enhancing small text", i, total);
+            let factor = text_upscale_factor(text_box.height());
+            self.retry_box(image, text_box, "Super-resolution", |crop| {
+                super_resolution.upscale(crop, factor)
+            })?;
+        }
+
+        Ok(total > 0)
+    }
+
+    /// Recognize text boxes below `second_pass_threshold` again from a 2x
+    /// upscaled crop, keeping the better text. Returns whether any box was
+    /// below the threshold.
+    fn second_pass(
+        &self,
+        image: &DynamicImage,
+        text_boxes: &mut [TextBox],
+        progress: &dyn ProgressSink,
+    ) -> Result<bool, OcrError> {
+        let threshold = self.config.second_pass_threshold;
+        let mut retries: Vec<&mut TextBox> = text_boxes
+            .iter_mut()
+            .filter(|b| b.recognition_score < threshold)
+            .collect();
+
+        let total = retries.len();
+        for (i, text_box) in retries.iter_mut().enumerate() {
+            report_retry(progress, "Second pass on line", i, total);
+            self.retry_box(image, text_box, "Second pass", |crop| self.upscale(crop, 2.0))?;
+        }
+
+        Ok(total > 0)
+    }
+
+    /// Recognize a text box again from an enhanced crop of `image`,
+    /// replacing its text if the retry is more confident.
+    fn retry_box(
+        &self,
+        image: &DynamicImage,
+        text_box: &mut TextBox,
+        label: &str,
+        enhance: impl Fn(&DynamicImage) -> Result<DynamicImage, OcrError>,
+    ) -> Result<(), OcrError> {
+        // Crop with a margin so the detector finds the line again
+        let (min_x, min_y, max_x, max_y) = text_box.rect();
+        let margin = text_box.height() / 2.0;
+        let x = (min_x - margin).max(0.0) as u32;
+        let y = (min_y - margin).max(0.0) as u32;
+        let right = (max_x + margin).max(0.0) as u32;
+        let bottom = (max_y + margin).max(0.0) as u32;
+        let crop = image.crop_imm(x, y, right.saturating_sub(x), bottom.saturating_sub(y));
+        if crop.width() == 0 || crop.height() == 0 {
+            return Ok(());
+        }
+
+        let mut boxes = self.recognize(&enhance(&crop)?)?;
+        if boxes.is_empty() || mean_score(&boxes) <= text_box.recognition_score {
+            return Ok(());
+        }
+
+        boxes.sort_by(|a, b| a.rect().0.total_cmp(&b.rect().0));
+        let text = boxes.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join(" ");
+        debug!(
+            "{}: '{}' ({:.2}) -> '{}' ({:.2})",
+            label,
+            text_box.text,
+            text_box.recognition_score,
+            text,
+            mean_score(&boxes)
+        );
+        text_box.text = text;
+        text_box.recognition_score = mean_score(&boxes);
+
+        Ok(())
+    }
+
+    /// Upscale an image by `factor`.
+    fn upscale(&self, image: &DynamicImage, factor: f32) -> Result<DynamicImage, OcrError> {
+        #[cfg(feature = "super-resolution")]
+        if let Some(super_resolution) = &self.super_resolution {
+            return super_resolution.upscale(image, factor);
+        }
+
+        Ok(upscale_bicubic(image, factor))
     }
 
     /// Convenience: extract text only.
     pub fn extract_text(&self, image: &DynamicImage) -> Result<String, OcrError> {
         Ok(self.process(image)?.text)
     }
+
+    /// Run the models once on a synthetic text line, so the first page is
+    /// not slowed down by one-time setup. Worth it for long-running
+    /// processes (servers) that load the engine up front.
+    pub fn warmup(&self) -> Result<(), OcrError> {
+        let start = Instant::now();
+        self.recognize(&warmup_line())?;
+
+        #[cfg(feature = "super-resolution")]
+        if let Some(super_resolution) = &self.super_resolution {
+            use incr_inference::InferenceBackend;
+
+            super_resolution.backend().warmup().map_err(|e| {
+                OcrError::ModelLoad(format!("super-resolution model warm-up: {}", e))
+            })?;
+        }
+        #[cfg(feature = "layout")]
+        if let Some(layout_detector) = &self.layout_detector {
+            use incr_inference::InferenceBackend;
+
+            layout_detector
+                .backend()
+                .warmup()
+                .map_err(|e| OcrError::ModelLoad(format!("layout model warm-up: {}", e)))?;
+        }
+        #[cfg(feature = "tables")]
+        if let Some(table_recognizer) = &self.table_recognizer {
+            table_recognizer.warmup()?;
+        }
+        #[cfg(feature = "handwriting")]
+        if let Some(handwriting) = &self.handwriting {
+            handwriting.warmup()?;
+        }
+
+        debug!("Warmed up OCR models in {}ms", start.elapsed().as_millis());
+        Ok(())
+    }
+}
+
+/// The model cached under `key` while an engine still holds it, otherwise
+/// one newly loaded with `load`.
+///
+/// Loads are serialized, so engines created at the same time wait for a
+/// single copy instead of loading their own.
+fn shared_model<K: Eq + Hash, T>(
+    cache: &ModelCache<K, T>,
+    key: K,
+    load: impl FnOnce() -> Result<T, OcrError>,
+) -> Result<Arc<T>, OcrError> {
+    let mut models = cache
+        .get_or_init(Default::default)
+        .lock()
+        .map_err(|e| OcrError::ModelLoad(format!("model cache lock poisoned: {}", e)))?;
+    if let Some(model) = models.get(&key).and_then(Weak::upgrade) {
+        return Ok(model);
+    }
+
+    let model = Arc::new(load()?);
+    models.retain(|_, cached| cached.strong_count() > 0);
+    models.insert(key, Arc::downgrade(&model));
+    Ok(model)
+}
+
+/// The layout model in `model_dir`, if installed.
+#[cfg(feature = "layout")]
+fn load_layout(
+    model_dir: &Path,
+) -> Result<Option<Arc<LayoutDetector<incr_inference::TractBackend>>>, OcrError> {
+    let path = model_dir.join(LAYOUT_MODEL);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+    let layout_detector = shared_model(&LAYOUT_MODELS, key, || {
+        // The image at the layout detector's default 800x608, and the
+        // scale factor of the resize
+        let shapes: [(&str, &[usize]); 2] =
+            [("image", &[1, 3, 608, 800]), ("scale_factor", &[1, 2])];
+        let backend = incr_inference::TractBackend::from_file_with_shapes(&path, &shapes)
+            .map_err(|e| OcrError::ModelLoad(format!("layout: {}", e)))?;
+        info!("Loaded layout model from {}", path.display());
+        Ok(LayoutDetector::new(backend))
+    })?;
+    Ok(Some(layout_detector))
+}
+
+/// The table structure model in `model_dir`, if installed, with the table
+/// classifier if that is installed too.
+#[cfg(feature = "tables")]
+fn load_tables(
+    model_dir: &Path,
+) -> Result<Option<Arc<TableRecognizer<incr_inference::TractBackend>>>, OcrError> {
+    let path = model_dir.join(TABLE_MODEL);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let classifier_path = Some(model_dir.join(TABLE_CLASSIFIER_MODEL)).filter(|p| p.exists());
+
+    let key = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let key = (key(&path), classifier_path.as_deref().map(key));
+    let table_recognizer = shared_model(&TABLE_MODELS, key, || {
+        // SLANet at its default 488x488, the classifier at 224x224
+        let load = |path: &Path, side: usize| {
+            incr_inference::TractBackend::from_file_with_shape(path, &[1, 3, side, side])
+                .map_err(|e| OcrError::ModelLoad(format!("{}: {}", path.display(), e)))
+        };
+        let mut table_recognizer = TableRecognizer::new(load(&path, 488)?);
+        info!("Loaded table structure model from {}", path.display());
+        if let Some(classifier_path) = &classifier_path {
+            let classifier = TableClassifier::new(load(classifier_path, 224)?);
+            table_recognizer = table_recognizer.with_classifier(classifier);
+            info!("Loaded table classifier from {}", classifier_path.display());
+        }
+        Ok(table_recognizer)
+    })?;
+    Ok(Some(table_recognizer))
+}
+
+/// The handwriting models in `model_dir`, if both are installed. Without a
+/// dictionary of its own the handwriting model uses `dictionary`, that of
+/// the recognition model.
+#[cfg(feature = "handwriting")]
+fn load_handwriting(
+    model_dir: &Path,
+    dictionary: &Path,
+) -> Result<Option<Arc<HandwritingReader<incr_inference::TractBackend>>>, OcrError> {
+    let style_path = model_dir.join(STYLE_MODEL);
+    let rec_path = model_dir.join(HANDWRITING_MODEL);
+    if !style_path.exists() || !rec_path.exists() {
+        return Ok(None);
+    }
+    let own_dictionary = model_dir.join(HANDWRITING_DICTIONARY);
+    let dict_path = if own_dictionary.exists() { own_dictionary } else { dictionary.to_path_buf() };
+
+    let key = rec_path.canonicalize().unwrap_or_else(|_| rec_path.clone());
+    let reader = shared_model(&HANDWRITING_MODELS, (key, dict_path.clone()), || {
+        let load = |path: &Path, model: &str| {
+            incr_inference::TractBackend::from_file(path)
+                .map_err(|e| OcrError::ModelLoad(format!("{}: {}", model, e)))
+        };
+        let classifier = StyleClassifier::new(load(&style_path, "style classification")?);
+        let recognizer = TextRecognizer::new(
+            load(&rec_path, "handwriting recognition")?,
+            TextRecognizer::<incr_inference::TractBackend>::load_dictionary(&dict_path)?,
+        );
+        info!("Loaded handwriting models from {}", model_dir.display());
+        Ok(HandwritingReader::new(classifier, recognizer))
+    })?;
+    Ok(Some(reader))
+}
+
+/// The `pure-onnx-ocr` engine for the detection model, recognition model and
+/// dictionary at these paths, shared with live engines using the same files.
+fn load_models(
+    det_path: &Path,
+    rec_path: &Path,
+    dict_path: &Path,
+) -> Result<Arc<pure_onnx_ocr::engine::OcrEngine>, OcrError> {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let key = (canonical(det_path), canonical(rec_path), canonical(dict_path));
+    shared_model(&OCR_MODELS, key, || {
+        pure_onnx_ocr::engine::OcrEngineBuilder::new()
+            .det_model_path(det_path)
+            .rec_model_path(rec_path)
+            .dictionary_path(dict_path)
+            .build()
+            .map_err(|e| OcrError::ModelLoad(format!("pure-onnx-ocr: {}", e)))
+    })
+}
+
+/// Write embedded models to a temporary directory.
+fn extract_models(models: &EmbeddedModels) -> Result<tempfile::TempDir, OcrError> {
+    let temp_dir = tempfile::tempdir()
+        .map_err(|e| OcrError::ModelLoad(format!("failed to create temp dir: {}", e)))?;
+
+    std::fs::write(temp_dir.path().join("det.onnx"), models.detection)
+        .map_err(|e| OcrError::ModelLoad(format!("failed to write det model: {}", e)))?;
+    std::fs::write(temp_dir.path().join("latin_rec.onnx"), models.recognition)
+        .map_err(|e| OcrError::ModelLoad(format!("failed to write rec model: {}", e)))?;
+    std::fs::write(temp_dir.path().join("latin_dict.txt"), models.dictionary)
+        .map_err(|e| OcrError::ModelLoad(format!("failed to write dictionary: {}", e)))?;
+
+    debug!(
+        "Wrote embedded models to temp dir: {}",
+        temp_dir.path().display()
+    );
+    Ok(temp_dir)
+}
+
+/// Dark strokes on white, shaped like a line of text, so detection finds a
+/// region and recognition runs on it.
+fn warmup_line() -> DynamicImage {
+    let mut line = image::RgbImage::from_pixel(320, 64, image::Rgb([255, 255, 255]));
+    for (x, y, pixel) in line.enumerate_pixels_mut() {
+        let stroke = x % 12;
+        if (16..304).contains(&x) && (20..44).contains(&y) && stroke < 3 {
+            *pixel = image::Rgb([0, 0, 0]);
+        }
+    }
+    DynamicImage::ImageRgb8(line)
+}
+
+/// Report recognizing line `index` of `total` again.
+fn report_retry(progress: &dyn ProgressSink, label: &str, index: usize, total: usize) {
+    progress.report(ProgressEvent::new(
+        ProgressStage::Recognition,
+        index as u64,
+        total as u64,
+        format!("{} {}/{}", label, index + 1, total),
+    ));
+}
+
+/// A sideways page turned upright.
+struct TurnedPage {
+    page: DynamicImage,
+    /// Clockwise rotation the text was in.
+    orientation: i32,
+    /// Skew the turned page was straightened by.
+    skew: Option<f32>,
+}
+
+/// Mean recognition score of text boxes (0 without boxes).
+fn mean_score(boxes: &[TextBox]) -> f32 {
+    if boxes.is_empty() {
+        return 0.0;
+    }
+    boxes.iter().map(|b| b.recognition_score).sum::<f32>() / boxes.len() as f32
 }
 
 /// Convert a `Polygon<f64>` to our `[f32; 8]` bbox format.
@@ -176,3 +972,30 @@ fn polygon_to_bbox(polygon: &pure_onnx_ocr::Polygon<f64>) -> [f32; 8] {
     }
     bbox
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engines_share_models() {
+        let first = PureOcrEngine::from_embedded(OcrConfig::default()).unwrap();
+        let config = OcrConfig {
+            second_pass_threshold: 0.9,
+            ..OcrConfig::default()
+        };
+        let second = PureOcrEngine::from_embedded(config).unwrap();
+
+        // One copy of the models on disk and in memory
+        assert!(Arc::ptr_eq(&first.engine, &second.engine));
+        let temp_dir = |engine: &PureOcrEngine| engine._temp_dir.clone().unwrap();
+        assert!(Arc::ptr_eq(&temp_dir(&first), &temp_dir(&second)));
+
+        // Loaded again once no engine holds them
+        let path = temp_dir(&first).path().to_path_buf();
+        drop((first, second));
+        assert!(!path.exists());
+        let third = PureOcrEngine::from_embedded(OcrConfig::default()).unwrap();
+        assert!(third._temp_dir.as_ref().unwrap().path().exists());
+    }
+}
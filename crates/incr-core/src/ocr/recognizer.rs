@@ -1,5 +1,6 @@
 //! Text recognition using PaddleOCR recognition model.
 
+use std::collections::HashMap;
 use std::path::Path;
 
 use image::DynamicImage;
@@ -17,6 +18,7 @@ pub struct TextRecognizer<B: InferenceBackend> {
     preprocessor: ImagePreprocessor,
     dictionary: Vec<char>,
     threshold: f32,
+    beam_width: usize,
 }
 
 /// Recognition result for a single text region.
@@ -38,20 +40,70 @@ impl<B: InferenceBackend> TextRecognizer<B> {
             preprocessor: ImagePreprocessor::new(),
             dictionary,
             threshold: 0.5,
+            beam_width: 1,
         }
     }
 
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
     /// Set recognition confidence threshold.
     pub fn with_threshold(mut self, threshold: f32) -> Self {
         self.threshold = threshold;
         self
     }
 
+    /// Set the CTC beam search width; 1 (the default) decodes greedily.
+    pub fn with_beam_width(mut self, beam_width: usize) -> Self {
+        self.beam_width = beam_width.max(1);
+        self
+    }
+
+    /// Use `dictionary` to map the model's classes to characters.
+    pub fn with_dictionary(mut self, dictionary: Vec<char>) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    /// Use the dictionary in the file at `path`, e.g. one for a model
+    /// trained on other characters.
+    pub fn with_dictionary_from_file(self, path: &Path) -> Result<Self, OcrError> {
+        let dictionary = Self::load_dictionary(path)?;
+        Ok(self.with_dictionary(dictionary))
+    }
+
+    /// Replace the dictionary of a recognizer in use, e.g. together with a
+    /// recognition model for another script.
+    pub fn set_dictionary(&mut self, dictionary: Vec<char>) {
+        self.dictionary = dictionary;
+    }
+
+    /// The dictionary mapping the model's classes to characters, starting
+    /// with the CTC blank.
+    pub fn dictionary(&self) -> &[char] {
+        &self.dictionary
+    }
+
     /// Load dictionary from a file.
     pub fn load_dictionary(path: &Path) -> Result<Vec<char>, OcrError> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| OcrError::ModelLoad(format!("Failed to load dictionary: {}", e)))?;
 
+        let chars = Self::parse_dictionary(&content);
+        debug!("Loaded dictionary with {} characters", chars.len());
+        Ok(chars)
+    }
+
+    /// Parse the contents of a dictionary file.
+    pub fn parse_dictionary(content: &str) -> Vec<char> {
         // Dictionary file has one character per line
         // First entry is usually blank (for CTC blank token)
         let mut chars: Vec<char> = vec![' ']; // Blank token
@@ -62,8 +114,7 @@ impl<B: InferenceBackend> TextRecognizer<B> {
             }
         }
 
-        debug!("Loaded dictionary with {} characters", chars.len());
-        Ok(chars)
+        chars
     }
 
     /// Create a default Latin dictionary for Polish text.
@@ -150,6 +201,18 @@ impl<B: InferenceBackend> TextRecognizer<B> {
         let seq_len = shape[1];
         let num_classes = shape[2];
 
+        if self.beam_width > 1 {
+            let probs: Vec<Vec<f32>> = (0..seq_len)
+                .map(|t| softmax((0..num_classes).map(|c| output[[0, t, c]])))
+                .collect();
+            let (labels, char_scores) = ctc_beam_search(&probs, self.beam_width);
+            let text = labels
+                .iter()
+                .filter_map(|&idx| self.dictionary.get(idx))
+                .collect::<String>();
+            return Ok(Self::result(text, char_scores));
+        }
+
         let mut text = String::new();
         let mut char_scores = Vec::new();
         let mut prev_idx = 0usize;
@@ -185,6 +248,10 @@ impl<B: InferenceBackend> TextRecognizer<B> {
             prev_idx = max_idx;
         }
 
+        Ok(Self::result(text, char_scores))
+    }
+
+    fn result(text: String, char_scores: Vec<f32>) -> RecognitionResult {
         // Calculate overall confidence
         let avg_confidence = if char_scores.is_empty() {
             0.0
@@ -194,12 +261,108 @@ impl<B: InferenceBackend> TextRecognizer<B> {
 
         trace!("Recognized: '{}' (confidence: {:.3})", text, avg_confidence);
 
-        Ok(RecognitionResult {
+        RecognitionResult {
             text,
             confidence: avg_confidence,
             char_scores,
-        })
+        }
+    }
+}
+
+fn softmax(logits: impl Iterator<Item = f32> + Clone) -> Vec<f32> {
+    let max = logits.clone().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = logits.map(|v| (v - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.into_iter().map(|v| v / sum).collect()
+}
+
+/// A CTC beam: probabilities of the prefix ending in blank and in its last
+/// label, and the probability each label was emitted with.
+#[derive(Clone, Default)]
+struct Beam {
+    blank: f64,
+    label: f64,
+    scores: Vec<f32>,
+}
+
+/// CTC prefix beam search over per-timestep class probabilities
+/// (class 0 is the blank). Returns the best label sequence and the
+/// probability of each label.
+///
+/// Unlike greedy decoding this sums over all alignments of a prefix, so
+/// text spread over several uncertain frames is not lost to blanks.
+fn ctc_beam_search(probs: &[Vec<f32>], beam_width: usize) -> (Vec<usize>, Vec<f32>) {
+    let mut beams: Vec<(Vec<usize>, Beam)> = vec![(
+        Vec::new(),
+        Beam {
+            blank: 1.0,
+            ..Beam::default()
+        },
+    )];
+
+    for frame in probs {
+        // Only extend with the most likely labels of this frame
+        let mut candidates: Vec<usize> = (1..frame.len()).collect();
+        candidates.sort_by(|&a, &b| frame[b].total_cmp(&frame[a]));
+        candidates.truncate(beam_width);
+
+        let mut next: HashMap<Vec<usize>, Beam> = HashMap::new();
+        for (prefix, beam) in &beams {
+            let total = beam.blank + beam.label;
+            let entry = next.entry(prefix.clone()).or_insert_with(|| Beam {
+                scores: beam.scores.clone(),
+                ..Beam::default()
+            });
+            entry.blank += total * f64::from(frame[0]);
+
+            for &c in &candidates {
+                let p = f64::from(frame[c]);
+                let mut extended = prefix.clone();
+                extended.push(c);
+
+                // A repeated label only starts a new character after a blank
+                let from = if prefix.last() == Some(&c) {
+                    let same = next.get_mut(prefix).expect("inserted above");
+                    same.label += beam.label * p;
+                    if let Some(last) = same.scores.last_mut() {
+                        *last = last.max(frame[c]);
+                    }
+                    beam.blank
+                } else {
+                    total
+                };
+
+                let entry = next.entry(extended).or_insert_with(|| {
+                    let mut scores = beam.scores.clone();
+                    scores.push(frame[c]);
+                    Beam {
+                        scores,
+                        ..Beam::default()
+                    }
+                });
+                entry.label += from * p;
+            }
+        }
+
+        beams = next.into_iter().collect();
+        beams.sort_by(|a, b| (b.1.blank + b.1.label).total_cmp(&(a.1.blank + a.1.label)));
+        beams.truncate(beam_width);
+
+        // Renormalize so long sequences do not underflow
+        let sum: f64 = beams.iter().map(|(_, b)| b.blank + b.label).sum();
+        if sum > 0.0 {
+            for (_, beam) in &mut beams {
+                beam.blank /= sum;
+                beam.label /= sum;
+            }
+        }
     }
+
+    beams
+        .into_iter()
+        .next()
+        .map(|(prefix, beam)| (prefix, beam.scores))
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -208,7 +371,7 @@ mod tests {
 
     #[test]
     fn test_default_dictionary() {
-        let dict = TextRecognizer::<incr_inference::OrtBackend>::default_latin_dictionary();
+        let dict = TextRecognizer::<incr_inference::TractBackend>::default_latin_dictionary();
 
         // Should contain Polish characters
         assert!(dict.contains(&'ą'));
@@ -224,4 +387,19 @@ mod tests {
         assert!(dict.contains(&'.'));
         assert!(dict.contains(&','));
     }
+
+    #[test]
+    fn test_beam_search_sums_alignments() {
+        // Greedy picks blank in both frames, but "a" is more likely overall
+        let probs = vec![vec![0.6, 0.4], vec![0.6, 0.4]];
+        assert_eq!(ctc_beam_search(&probs, 1).0, Vec::<usize>::new());
+
+        let (labels, scores) = ctc_beam_search(&probs, 3);
+        assert_eq!(labels, vec![1]);
+        assert_eq!(scores, vec![0.4]);
+
+        // Repeated labels need a blank in between
+        let probs = vec![vec![0.0, 1.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert_eq!(ctc_beam_search(&probs, 2).0, vec![1, 1]);
+    }
 }
@@ -0,0 +1,116 @@
+//! Cropping of detected layout regions for dataset export.
+//!
+//! Turns the layout regions of an [`OcrResult`](super::OcrResult) into
+//! separate images plus a manifest describing where each crop came from,
+//! so production documents can be fed into labeling tools.
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use super::{LayoutInfo, RegionBox};
+
+/// A cropped layout region.
+#[derive(Debug, Clone)]
+pub struct RegionCrop {
+    /// Source region (bbox clamped to the image).
+    pub region: RegionBox,
+    /// Cropped region image.
+    pub image: DynamicImage,
+}
+
+/// Manifest entry describing one exported region image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionManifestEntry {
+    /// Image file name, relative to the manifest.
+    pub file: String,
+    /// Region type name (table, text, figure, ...).
+    pub region_type: String,
+    /// Bounding box (x1, y1, x2, y2) in page image coordinates.
+    pub bbox: [f32; 4],
+    /// Page number (1-based).
+    pub page: u32,
+    /// Detection confidence score.
+    pub confidence: f32,
+}
+
+/// Manifest for a set of exported region images.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegionManifest {
+    /// Source document path or name.
+    pub source: String,
+    /// Exported regions.
+    pub regions: Vec<RegionManifestEntry>,
+}
+
+impl LayoutInfo {
+    /// Iterate over all detected regions (tables, text regions, figures).
+    pub fn regions(&self) -> impl Iterator<Item = &RegionBox> {
+        self.tables
+            .iter()
+            .chain(self.text_regions.iter())
+            .chain(self.figures.iter())
+    }
+}
+
+/// Crop every layout region out of the page image.
+///
+/// Bounding boxes are clamped to the image; regions that end up empty
+/// are skipped.
+pub fn crop_regions(image: &DynamicImage, layout: &LayoutInfo) -> Vec<RegionCrop> {
+    layout.regions().filter_map(|region| crop_region(image, region)).collect()
+}
+
+/// Crop a region out of the page image, its bounding box clamped to the
+/// image, or `None` if that leaves it empty.
+pub(super) fn crop_region(image: &DynamicImage, region: &RegionBox) -> Option<RegionCrop> {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let x1 = region.bbox[0].clamp(0.0, width).floor();
+    let y1 = region.bbox[1].clamp(0.0, height).floor();
+    let x2 = region.bbox[2].clamp(0.0, width).ceil();
+    let y2 = region.bbox[3].clamp(0.0, height).ceil();
+
+    if x2 - x1 < 1.0 || y2 - y1 < 1.0 {
+        return None;
+    }
+
+    let crop = image.crop_imm(x1 as u32, y1 as u32, (x2 - x1) as u32, (y2 - y1) as u32);
+
+    Some(RegionCrop {
+        region: RegionBox {
+            bbox: [x1, y1, x2, y2],
+            ..region.clone()
+        },
+        image: crop,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(region_type: &str, bbox: [f32; 4]) -> RegionBox {
+        RegionBox {
+            region_type: region_type.to_string(),
+            bbox,
+            confidence: 0.9,
+            structure: None,
+        }
+    }
+
+    #[test]
+    fn test_crop_regions() {
+        let image = DynamicImage::new_rgb8(100, 80);
+        let layout = LayoutInfo {
+            tables: vec![region("table", [10.0, 20.0, 60.0, 50.0])],
+            text_regions: vec![region("text", [90.0, 70.0, 150.0, 120.0])],
+            figures: vec![region("figure", [120.0, 0.0, 130.0, 10.0])],
+        };
+
+        let crops = crop_regions(&image, &layout);
+
+        assert_eq!(crops.len(), 2);
+        assert_eq!(crops[0].region.region_type, "table");
+        assert_eq!((crops[0].image.width(), crops[0].image.height()), (50, 30));
+        assert_eq!(crops[1].region.bbox, [90.0, 70.0, 100.0, 80.0]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Printed/handwritten style classification for text regions.
+
+use image::DynamicImage;
+use tracing::debug;
+
+use crate::error::OcrError;
+use incr_inference::{InferenceBackend, InputTensor, OutputTensor};
+
+use super::preprocessing::ImagePreprocessor;
+use super::recognizer::TextRecognizer;
+use super::TextBox;
+
+/// Style classification model in a model directory.
+pub const STYLE_MODEL: &str = "style_cls.onnx";
+
+/// Handwriting recognition model in a model directory.
+pub const HANDWRITING_MODEL: &str = "handwriting_rec.onnx";
+
+/// Dictionary of the handwriting model; the recognition dictionary is used
+/// without it.
+pub const HANDWRITING_DICTIONARY: &str = "handwriting_dict.txt";
+
+/// Writing style of a text region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Machine-printed text.
+    Printed,
+    /// Handwritten text, e.g. an amount or date filled in by hand.
+    Handwritten,
+}
+
+/// Style classifier telling handwritten text regions from printed ones.
+///
+/// Takes the same input as the angle classifier and outputs `[1, 2]`
+/// probabilities for [printed, handwritten].
+pub struct StyleClassifier<B: InferenceBackend> {
+    backend: B,
+    preprocessor: ImagePreprocessor,
+    threshold: f32,
+}
+
+impl<B: InferenceBackend> StyleClassifier<B> {
+    /// Create a new style classifier.
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            preprocessor: ImagePreprocessor::new(),
+            threshold: 0.5,
+        }
+    }
+
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Set the handwriting probability above which a region is handwritten.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Classify the style of a text region.
+    ///
+    /// Returns the style and the probability of handwriting.
+    pub fn classify(&self, image: &DynamicImage) -> Result<(TextStyle, f32), OcrError> {
+        let tensor = self
+            .preprocessor
+            .preprocess_for_classification(image)
+            .map_err(|e| OcrError::Preprocessing(e.to_string()))?;
+
+        let outputs = self
+            .backend
+            .run(&[("x", InputTensor::Float32(tensor.into_dyn()))])
+            .map_err(|e| OcrError::Recognition(e.to_string()))?;
+
+        let output = outputs
+            .into_iter()
+            .next()
+            .ok_or_else(|| OcrError::Recognition("No output from style classifier".to_string()))?
+            .1;
+
+        let OutputTensor::Float32(probs) = output else {
+            return Err(OcrError::Recognition("Unexpected output type".to_string()));
+        };
+
+        let handwriting = match probs.as_slice() {
+            Some([_, handwritten, ..]) => *handwritten,
+            _ => 0.0,
+        };
+
+        let style = if handwriting > self.threshold {
+            TextStyle::Handwritten
+        } else {
+            TextStyle::Printed
+        };
+        debug!("Classified style: {:?} (handwriting: {:.3})", style, handwriting);
+
+        Ok((style, handwriting))
+    }
+}
+
+/// Reads text boxes the style classifier marks as handwritten again with
+/// a dedicated handwriting recognizer.
+///
+/// For engines that recognize a whole page in one call, such as
+/// [`PureOcrEngine`](super::PureOcrEngine); [`OcrEngine`](super::OcrEngine)
+/// routes each text crop itself.
+pub struct HandwritingReader<B: InferenceBackend> {
+    classifier: StyleClassifier<B>,
+    recognizer: TextRecognizer<B>,
+    preprocessor: ImagePreprocessor,
+}
+
+impl<B: InferenceBackend> HandwritingReader<B> {
+    /// Create a reader from a style classifier and a handwriting recognizer.
+    pub fn new(classifier: StyleClassifier<B>, recognizer: TextRecognizer<B>) -> Self {
+        Self {
+            classifier,
+            recognizer,
+            preprocessor: ImagePreprocessor::new(),
+        }
+    }
+
+    /// Replace the text of the boxes of `image` classified as handwritten
+    /// with the handwriting recognizer's reading, and mark them
+    /// `handwritten`. Returns how many were.
+    pub fn read(&self, image: &DynamicImage, text_boxes: &mut [TextBox]) -> Result<usize, OcrError> {
+        let mut count = 0;
+        for text_box in text_boxes {
+            let crop = self.preprocessor.crop_text_region(image, &text_box.bbox)?;
+            if self.classifier.classify(&crop)?.0 != TextStyle::Handwritten {
+                continue;
+            }
+
+            let result = self.recognizer.recognize(&crop)?;
+            debug!(
+                "Handwriting: '{}' ({:.2}) -> '{}' ({:.2})",
+                text_box.text, text_box.recognition_score, result.text, result.confidence
+            );
+            text_box.text = result.text;
+            text_box.recognition_score = result.confidence;
+            text_box.handwritten = true;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Run both models once on zeros.
+    pub fn warmup(&self) -> Result<(), OcrError> {
+        for (name, backend) in [
+            ("style classification", self.classifier.backend()),
+            ("handwriting recognition", self.recognizer.backend()),
+        ] {
+            backend
+                .warmup()
+                .map_err(|e| OcrError::ModelLoad(format!("{} model warm-up: {}", name, e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::ArrayD;
+
+    /// Returns fixed [printed, handwritten] probabilities.
+    struct Fixed {
+        probs: [f32; 2],
+        names: Vec<String>,
+    }
+
+    impl InferenceBackend for Fixed {
+        fn run(
+            &self,
+            _inputs: &[(&str, InputTensor)],
+        ) -> incr_inference::Result<Vec<(String, OutputTensor)>> {
+            let output = ArrayD::from_shape_vec(vec![1, 2], self.probs.to_vec()).unwrap();
+            Ok(vec![("output".to_string(), OutputTensor::Float32(output))])
+        }
+
+        fn input_names(&self) -> &[String] {
+            &self.names
+        }
+
+        fn output_names(&self) -> &[String] {
+            &self.names
+        }
+    }
+
+    #[test]
+    fn test_classify_style() {
+        let image = DynamicImage::new_rgb8(120, 24);
+        let classifier = |probs| StyleClassifier::new(Fixed { probs, names: Vec::new() });
+
+        let (style, handwriting) = classifier([0.2, 0.8]).classify(&image).unwrap();
+        assert_eq!(style, TextStyle::Handwritten);
+        assert_eq!(handwriting, 0.8);
+
+        let (style, _) = classifier([0.7, 0.3]).classify(&image).unwrap();
+        assert_eq!(style, TextStyle::Printed);
+
+        let strict = classifier([0.2, 0.8]).with_threshold(0.9);
+        assert_eq!(strict.classify(&image).unwrap().0, TextStyle::Printed);
+    }
+
+    /// Returns a fixed output of any shape.
+    struct Canned {
+        shape: Vec<usize>,
+        values: Vec<f32>,
+        names: Vec<String>,
+    }
+
+    impl InferenceBackend for Canned {
+        fn run(
+            &self,
+            _inputs: &[(&str, InputTensor)],
+        ) -> incr_inference::Result<Vec<(String, OutputTensor)>> {
+            let output = ArrayD::from_shape_vec(self.shape.clone(), self.values.clone()).unwrap();
+            Ok(vec![("output".to_string(), OutputTensor::Float32(output))])
+        }
+
+        fn input_names(&self) -> &[String] {
+            &self.names
+        }
+
+        fn output_names(&self) -> &[String] {
+            &self.names
+        }
+    }
+
+    #[test]
+    fn test_handwriting_reader() {
+        let image = DynamicImage::new_rgb8(200, 100);
+        let text_box = TextBox {
+            bbox: [10.0, 10.0, 130.0, 10.0, 130.0, 34.0, 10.0, 34.0],
+            text: "1".to_string(),
+            detection_score: 0.9,
+            recognition_score: 0.4,
+            angle: 0,
+            handwritten: false,
+        };
+        let reader = |probs: [f32; 2]| {
+            let canned = |shape: &[usize], values: &[f32]| Canned {
+                shape: shape.to_vec(),
+                values: values.to_vec(),
+                names: Vec::new(),
+            };
+            // The handwriting model reads "7": CTC logits over [blank, '7']
+            HandwritingReader::new(
+                StyleClassifier::new(canned(&[1, 2], &probs)),
+                TextRecognizer::new(canned(&[1, 2, 2], &[0.0, 0.9, 1.0, 0.0]), vec![' ', '7']),
+            )
+        };
+
+        // Handwritten boxes go to the handwriting recognizer
+        let mut boxes = vec![text_box.clone()];
+        assert_eq!(reader([0.1, 0.9]).read(&image, &mut boxes).unwrap(), 1);
+        assert_eq!(boxes[0].text, "7");
+        assert!(boxes[0].recognition_score > 0.5);
+        assert!(boxes[0].handwritten);
+
+        // Printed ones keep their reading
+        let mut boxes = vec![text_box];
+        assert_eq!(reader([0.9, 0.1]).read(&image, &mut boxes).unwrap(), 0);
+        assert_eq!(boxes[0].text, "1");
+        assert!(!boxes[0].handwritten);
+    }
+}
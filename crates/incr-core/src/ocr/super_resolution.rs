@@ -0,0 +1,196 @@
+//! Super-resolution upscaling with a lightweight ONNX model.
+//!
+//! The model (ESPCN/FSRCNN class) takes RGB tiles of [`TILE_SIZE`] pixels
+//! with values in 0..1, shape `[1, 3, T, T]`, and returns them upscaled by
+//! a fixed factor, `[1, 3, sT, sT]`. Images are processed tile by tile so
+//! the model can be loaded with a fixed input shape; the result is resized
+//! to the requested factor with bicubic interpolation.
+
+use image::{DynamicImage, Rgb, RgbImage};
+use ndarray::Array4;
+use tracing::debug;
+
+use crate::error::OcrError;
+use incr_inference::{InferenceBackend, InputTensor};
+
+use super::upscale::{scaled_size, upscale_bicubic};
+
+/// File name of the super-resolution model in a model directory.
+pub const SR_MODEL: &str = "sr.onnx";
+
+/// Tile size (pixels) the model is run on.
+pub const TILE_SIZE: u32 = 128;
+
+/// Super-resolution model.
+pub struct SuperResolution<B: InferenceBackend> {
+    backend: B,
+}
+
+impl<B: InferenceBackend> SuperResolution<B> {
+    /// Create a super-resolution model from a backend loaded with input
+    /// shape `[1, 3, TILE_SIZE, TILE_SIZE]`.
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// The inference backend.
+    pub(crate) fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The inference backend, e.g. to apply session options.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Upscale `image` by `factor`.
+    pub fn upscale(&self, image: &DynamicImage, factor: f32) -> Result<DynamicImage, OcrError> {
+        let rgb = image.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        let mut output: Option<(RgbImage, u32)> = None;
+
+        for tile_y in (0..height).step_by(TILE_SIZE as usize) {
+            for tile_x in (0..width).step_by(TILE_SIZE as usize) {
+                let tile = self.run_tile(&rgb, tile_x, tile_y)?;
+                let scale = tile.shape()[2] / TILE_SIZE as usize;
+
+                let (upscaled, scale) = output.get_or_insert_with(|| {
+                    (RgbImage::new(width * scale as u32, height * scale as u32), scale as u32)
+                });
+
+                // Copy the part of the tile that lies inside the image
+                let tile_width = (width - tile_x).min(TILE_SIZE) * *scale;
+                let tile_height = (height - tile_y).min(TILE_SIZE) * *scale;
+                for y in 0..tile_height {
+                    for x in 0..tile_width {
+                        let channel = |c| {
+                            (tile[[0, c, y as usize, x as usize]] * 255.0).clamp(0.0, 255.0) as u8
+                        };
+                        upscaled.put_pixel(
+                            tile_x * *scale + x,
+                            tile_y * *scale + y,
+                            Rgb([channel(0), channel(1), channel(2)]),
+                        );
+                    }
+                }
+            }
+        }
+
+        let Some((upscaled, scale)) = output else {
+            return Ok(image.clone());
+        };
+        debug!("Super-resolution upscaled {}x{} by {}x", width, height, scale);
+
+        // Match the requested factor
+        let upscaled = DynamicImage::ImageRgb8(upscaled);
+        let (target_width, target_height) = scaled_size((width, height), factor);
+        if (upscaled.width(), upscaled.height()) == (target_width, target_height) {
+            Ok(upscaled)
+        } else {
+            Ok(upscale_bicubic(&upscaled, target_width as f32 / upscaled.width() as f32))
+        }
+    }
+
+    /// Run the model on the tile at (`x`, `y`), padding it with the edge
+    /// pixels of the image.
+    fn run_tile(&self, image: &RgbImage, x: u32, y: u32) -> Result<Array4<f32>, OcrError> {
+        let size = TILE_SIZE as usize;
+        let (width, height) = image.dimensions();
+
+        let mut input = Array4::<f32>::zeros((1, 3, size, size));
+        for ty in 0..size {
+            for tx in 0..size {
+                let pixel = image.get_pixel(
+                    (x + tx as u32).min(width - 1),
+                    (y + ty as u32).min(height - 1),
+                );
+                for c in 0..3 {
+                    input[[0, c, ty, tx]] = pixel[c] as f32 / 255.0;
+                }
+            }
+        }
+
+        let outputs = self
+            .backend
+            .run(&[("input", InputTensor::Float32(input.into_dyn()))])
+            .map_err(|e| OcrError::Preprocessing(format!("super-resolution: {}", e)))?;
+
+        let output = outputs
+            .into_iter()
+            .next()
+            .and_then(|(_, tensor)| tensor.as_f32().cloned())
+            .ok_or_else(|| OcrError::Preprocessing("super-resolution: no output".to_string()))?;
+
+        let shape = output.shape().to_vec();
+        let square = shape.len() == 4 && shape[1] == 3 && shape[2] == shape[3];
+        if !square || shape[2] < size || shape[2] % size != 0 {
+            return Err(OcrError::Preprocessing(format!(
+                "super-resolution: unexpected output shape {:?}",
+                shape
+            )));
+        }
+
+        output
+            .into_dimensionality()
+            .map_err(|e| OcrError::Preprocessing(format!("super-resolution: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use incr_inference::OutputTensor;
+    use ndarray::Axis;
+
+    /// Doubles tiles with nearest-neighbour sampling.
+    struct Nearest2x {
+        names: Vec<String>,
+    }
+
+    impl InferenceBackend for Nearest2x {
+        fn run(
+            &self,
+            inputs: &[(&str, InputTensor)],
+        ) -> incr_inference::Result<Vec<(String, OutputTensor)>> {
+            let InputTensor::Float32(input) = &inputs[0].1 else {
+                unreachable!()
+            };
+            let doubled = input
+                .view()
+                .insert_axis(Axis(3))
+                .insert_axis(Axis(5))
+                .broadcast((1, 3, 128, 2, 128, 2))
+                .unwrap()
+                .to_shape((1, 3, 256, 256))
+                .unwrap()
+                .to_owned()
+                .into_dyn();
+            Ok(vec![("output".to_string(), OutputTensor::Float32(doubled))])
+        }
+
+        fn input_names(&self) -> &[String] {
+            &self.names
+        }
+
+        fn output_names(&self) -> &[String] {
+            &self.names
+        }
+    }
+
+    #[test]
+    fn test_tiled_upscale() {
+        let mut image = RgbImage::new(150, 20);
+        image.put_pixel(140, 10, Rgb([255, 0, 0]));
+        let sr = SuperResolution::new(Nearest2x { names: Vec::new() });
+
+        // The second tile is padded with edge pixels
+        let upscaled = sr.upscale(&DynamicImage::ImageRgb8(image.clone()), 2.0).unwrap();
+        assert_eq!((upscaled.width(), upscaled.height()), (300, 40));
+        assert_eq!(upscaled.to_rgb8().get_pixel(281, 21), &Rgb([255, 0, 0]));
+
+        // The model output is resized to the requested factor
+        let upscaled = sr.upscale(&DynamicImage::ImageRgb8(image), 3.0).unwrap();
+        assert_eq!((upscaled.width(), upscaled.height()), (450, 60));
+    }
+}
@@ -1,16 +1,40 @@
 //! Table structure recognition using SLANet model.
 //!
 //! Extracts table structure (rows, columns, cells) from table images.
+//! The table data types are always available; the SLANet recognizer and
+//! table classifier require the `wasm` inference backend or the `tables`
+//! feature.
 
+#[cfg(any(feature = "wasm", feature = "tables"))]
 use image::{DynamicImage, GenericImageView};
+#[cfg(any(feature = "wasm", feature = "tables"))]
 use ndarray::Array3;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
+#[cfg(any(feature = "wasm", feature = "tables"))]
+use tracing::warn;
 
+#[cfg(any(feature = "wasm", feature = "tables"))]
 use crate::error::OcrError;
+#[cfg(feature = "wasm")]
+use incr_inference::InferenceOptions;
+#[cfg(any(feature = "wasm", feature = "tables"))]
 use incr_inference::{InferenceBackend, InputTensor, OutputTensor};
 
+use super::{OcrResult, RegionBox, TextBox};
+#[cfg(any(feature = "wasm", feature = "tables"))]
+use super::regions::crop_region;
+#[cfg(any(feature = "wasm", feature = "tables"))]
+use super::wired_table::WiredTableReconstructor;
+
+/// File name of the SLANet table structure model in a model directory.
+pub const TABLE_MODEL: &str = "table_structure.onnx";
+
+/// File name of the wired/lineless table classifier in a model directory.
+pub const TABLE_CLASSIFIER_MODEL: &str = "table_cls.onnx";
+
 /// A cell in a table.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableCell {
     /// Row index (0-based).
     pub row: usize,
@@ -46,7 +70,7 @@ impl TableCell {
 }
 
 /// A recognized table structure.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableStructure {
     /// Number of rows.
     pub num_rows: usize,
@@ -62,7 +86,167 @@ pub struct TableStructure {
     pub confidence: f32,
 }
 
+/// Plain cell grid of a table, suitable for JSON export.
+///
+/// Spanning cells place their content at the top-left position; the
+/// positions they cover are left empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableGrid {
+    /// Number of rows.
+    pub num_rows: usize,
+    /// Number of columns.
+    pub num_cols: usize,
+    /// Cell text, row by row.
+    pub rows: Vec<Vec<String>>,
+}
+
 impl TableStructure {
+    /// Build a table from text boxes by grouping them into rows and columns.
+    ///
+    /// Rows are formed from boxes whose vertical centers fall within the same
+    /// band; columns are formed from horizontally overlapping box extents.
+    /// Intended for lineless tables or when no structure model is available.
+    pub fn from_text_boxes(boxes: &[TextBox]) -> Self {
+        let mut boxes: Vec<&TextBox> = boxes
+            .iter()
+            .filter(|b| !b.text.trim().is_empty())
+            .collect();
+
+        if boxes.is_empty() {
+            return Self {
+                num_rows: 0,
+                num_cols: 0,
+                cells: Vec::new(),
+                html: String::from("<table>\n</table>"),
+                bbox: [0.0; 4],
+                confidence: 0.0,
+            };
+        }
+
+        // Group into rows by vertical center
+        boxes.sort_by(|a, b| {
+            a.center()
+                .1
+                .partial_cmp(&b.center().1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut rows: Vec<Vec<&TextBox>> = Vec::new();
+        let mut band = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        for text_box in boxes {
+            let (_, y1, _, y2) = text_box.rect();
+            let (_, cy) = text_box.center();
+
+            match rows.last_mut() {
+                Some(row) if cy >= band.0 && cy <= band.1 => {
+                    row.push(text_box);
+                    band = (band.0.min(y1), band.1.max(y2));
+                }
+                _ => {
+                    rows.push(vec![text_box]);
+                    band = (y1, y2);
+                }
+            }
+        }
+
+        for row in &mut rows {
+            row.sort_by(|a, b| {
+                a.rect()
+                    .0
+                    .partial_cmp(&b.rect().0)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+
+        // Columns from overlapping horizontal extents across all rows
+        let mut columns: Vec<(f32, f32)> = rows
+            .iter()
+            .flatten()
+            .map(|b| {
+                let (x1, _, x2, _) = b.rect();
+                (x1, x2)
+            })
+            .collect();
+        columns.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        columns.dedup_by(|next, prev| {
+            if next.0 <= prev.1 {
+                prev.1 = prev.1.max(next.1);
+                true
+            } else {
+                false
+            }
+        });
+
+        let mut cells: Vec<TableCell> = Vec::new();
+        let mut score_sum = 0.0f32;
+        let mut score_count = 0usize;
+
+        for (row_idx, row) in rows.iter().enumerate() {
+            let mut row_cells: Vec<TableCell> = Vec::new();
+
+            for text_box in row {
+                let (x1, y1, x2, y2) = text_box.rect();
+                let col = nearest_column(&columns, x1, x2);
+                score_sum += text_box.recognition_score;
+                score_count += 1;
+
+                if let Some(cell) = row_cells.iter_mut().find(|c| c.col == col) {
+                    cell.content.push(' ');
+                    cell.content.push_str(text_box.text.trim());
+                    cell.bbox = [
+                        cell.bbox[0].min(x1),
+                        cell.bbox[1].min(y1),
+                        cell.bbox[2].max(x2),
+                        cell.bbox[3].max(y2),
+                    ];
+                    cell.confidence = cell.confidence.min(text_box.recognition_score);
+                } else {
+                    row_cells.push(TableCell {
+                        row: row_idx,
+                        col,
+                        row_span: 1,
+                        col_span: 1,
+                        bbox: [x1, y1, x2, y2],
+                        content: text_box.text.trim().to_string(),
+                        confidence: text_box.recognition_score,
+                    });
+                }
+            }
+
+            cells.extend(row_cells);
+        }
+
+        let bbox = cells.iter().fold(
+            [f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY],
+            |acc, c| {
+                [
+                    acc[0].min(c.bbox[0]),
+                    acc[1].min(c.bbox[1]),
+                    acc[2].max(c.bbox[2]),
+                    acc[3].max(c.bbox[3]),
+                ]
+            },
+        );
+
+        let mut table = Self {
+            num_rows: rows.len(),
+            num_cols: columns.len().max(1),
+            cells,
+            html: String::new(),
+            bbox,
+            confidence: score_sum / score_count.max(1) as f32,
+        };
+        table.html = table.to_html();
+
+        debug!(
+            "Reconstructed table from text boxes: {}x{}",
+            table.num_rows, table.num_cols
+        );
+
+        table
+    }
+
     /// Get cells in a specific row.
     pub fn row(&self, row: usize) -> Vec<&TableCell> {
         self.cells.iter().filter(|c| c.row == row).collect()
@@ -108,6 +292,134 @@ impl TableStructure {
         grid
     }
 
+    /// Convert to a plain grid of cell text.
+    pub fn to_grid(&self) -> TableGrid {
+        let mut rows = vec![vec![String::new(); self.num_cols]; self.num_rows];
+
+        for cell in &self.cells {
+            if cell.row < self.num_rows && cell.col < self.num_cols {
+                rows[cell.row][cell.col] = cell.content.clone();
+            }
+        }
+
+        TableGrid {
+            num_rows: self.num_rows,
+            num_cols: self.num_cols,
+            rows,
+        }
+    }
+
+    /// Export the table as CSV (one record per row, header row first).
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+
+        for row in self.to_grid().rows {
+            let record: Vec<String> = row.iter().map(|field| escape_csv(field)).collect();
+            csv.push_str(&record.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Shift all coordinates by the given offset.
+    ///
+    /// Useful when the table was recognized from a crop and the OCR boxes
+    /// are in page coordinates.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        let cell_boxes = self.cells.iter_mut().map(|c| &mut c.bbox);
+        for bbox in std::iter::once(&mut self.bbox).chain(cell_boxes) {
+            bbox[0] += dx;
+            bbox[1] += dy;
+            bbox[2] += dx;
+            bbox[3] += dy;
+        }
+    }
+
+    /// Fill cell contents from OCR text boxes.
+    ///
+    /// Each box is assigned to the cell containing its center, falling back to
+    /// the cell with the highest IoU. Boxes sharing a cell are joined in reading
+    /// order. The `html` field is regenerated afterwards.
+    pub fn fill_from_ocr(&mut self, ocr: &OcrResult) {
+        let mut assigned: Vec<Vec<&TextBox>> = vec![Vec::new(); self.cells.len()];
+
+        for text_box in &ocr.boxes {
+            if text_box.text.trim().is_empty() {
+                continue;
+            }
+            if let Some(idx) = self.find_cell_for_box(text_box) {
+                assigned[idx].push(text_box);
+            }
+        }
+
+        for (cell, mut boxes) in self.cells.iter_mut().zip(assigned) {
+            if boxes.is_empty() {
+                continue;
+            }
+
+            // Reading order: boxes whose vertical centers are within half a line
+            // height of each other are on the same line
+            boxes.sort_by(|a, b| {
+                let (ax, ay) = a.center();
+                let (bx, by) = b.center();
+                let tolerance = a.height().min(b.height()) / 2.0;
+                if (ay - by).abs() <= tolerance {
+                    ax.partial_cmp(&bx).unwrap_or(std::cmp::Ordering::Equal)
+                } else {
+                    ay.partial_cmp(&by).unwrap_or(std::cmp::Ordering::Equal)
+                }
+            });
+
+            cell.content = boxes
+                .iter()
+                .map(|b| b.text.trim())
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+
+        self.html = self.to_html();
+
+        debug!(
+            "Filled {} of {} table cells from {} text boxes",
+            self.cells.iter().filter(|c| !c.content.is_empty()).count(),
+            self.cells.len(),
+            ocr.boxes.len()
+        );
+    }
+
+    fn find_cell_for_box(&self, text_box: &TextBox) -> Option<usize> {
+        let (cx, cy) = text_box.center();
+
+        // Prefer the smallest cell containing the box center
+        let containing = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                cx >= c.bbox[0] && cx <= c.bbox[2] && cy >= c.bbox[1] && cy <= c.bbox[3]
+            })
+            .min_by(|(_, a), (_, b)| {
+                a.area()
+                    .partial_cmp(&b.area())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(i, _)| i);
+
+        if containing.is_some() {
+            return containing;
+        }
+
+        let (x1, y1, x2, y2) = text_box.rect();
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, bbox_iou(&c.bbox, &[x1, y1, x2, y2])))
+            .filter(|(_, iou)| *iou > 0.0)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+    }
+
     /// Generate HTML from table structure.
     pub fn to_html(&self) -> String {
         let mut html = String::from("<table>\n");
@@ -131,7 +443,10 @@ impl TableStructure {
 
                     html.push_str(&format!(
                         "    <{}{}>{}</{}>\n",
-                        tag, attrs, cell.content, tag
+                        tag,
+                        attrs,
+                        escape_html(&cell.content),
+                        tag
                     ));
 
                     col_idx += cell.col_span;
@@ -149,8 +464,87 @@ impl TableStructure {
     }
 }
 
+/// Intersection over union of two axis-aligned boxes (x1, y1, x2, y2).
+fn bbox_iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let x1 = a[0].max(b[0]);
+    let y1 = a[1].max(b[1]);
+    let x2 = a[2].min(b[2]);
+    let y2 = a[3].min(b[3]);
+
+    if x2 <= x1 || y2 <= y1 {
+        return 0.0;
+    }
+
+    let intersection = (x2 - x1) * (y2 - y1);
+    let union = (a[2] - a[0]) * (a[3] - a[1]) + (b[2] - b[0]) * (b[3] - b[1]) - intersection;
+
+    if union > 0.0 {
+        intersection / union
+    } else {
+        0.0
+    }
+}
+
+/// Index of the column interval best matching the horizontal span x1..x2.
+fn nearest_column(columns: &[(f32, f32)], x1: f32, x2: f32) -> usize {
+    let center = (x1 + x2) / 2.0;
+
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, &(c1, c2))| {
+            let overlap = (x2.min(c2) - x1.max(c1)).max(0.0);
+            let distance = (center - (c1 + c2) / 2.0).abs();
+            (i, overlap, distance)
+        })
+        .max_by(|a, b| {
+            a.1.partial_cmp(&b.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal))
+        })
+        .map(|(i, _, _)| i)
+        .unwrap_or(0)
+}
+
+/// Quote a CSV field if it contains separators, quotes or line breaks.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escape text for inclusion in HTML cell content.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl RegionBox {
+    /// Cells of a table region: its recognized structure, or else a grid of
+    /// the `boxes` whose centers lie in the region.
+    pub fn table(&self, boxes: &[TextBox]) -> TableStructure {
+        if let Some(structure) = &self.structure {
+            return structure.clone();
+        }
+        let [x1, y1, x2, y2] = self.bbox;
+        let boxes: Vec<_> = boxes
+            .iter()
+            .filter(|b| {
+                let (cx, cy) = b.center();
+                cx >= x1 && cx <= x2 && cy >= y1 && cy <= y2
+            })
+            .cloned()
+            .collect();
+        TableStructure::from_text_boxes(&boxes)
+    }
+}
+
 /// Table type classification.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TableType {
     /// Table with visible grid lines.
     Wired,
@@ -161,12 +555,20 @@ pub enum TableType {
 }
 
 /// Table structure recognizer using SLANet model.
+///
+/// When a [`TableClassifier`] is attached, tables classified as wired are
+/// reconstructed from their ruling lines instead, falling back to SLANet
+/// if no grid can be found.
+#[cfg(any(feature = "wasm", feature = "tables"))]
 pub struct TableRecognizer<B: InferenceBackend> {
     backend: B,
     input_size: (u32, u32),
     max_length: usize,
+    classifier: Option<TableClassifier<B>>,
+    wired: WiredTableReconstructor,
 }
 
+#[cfg(any(feature = "wasm", feature = "tables"))]
 impl<B: InferenceBackend> TableRecognizer<B> {
     /// Create a new table recognizer.
     pub fn new(backend: B) -> Self {
@@ -174,9 +576,23 @@ impl<B: InferenceBackend> TableRecognizer<B> {
             backend,
             input_size: (488, 488), // SLANet default
             max_length: 500,
+            classifier: None,
+            wired: WiredTableReconstructor::default(),
         }
     }
 
+    /// Set the table classifier used to route wired tables.
+    pub fn with_classifier(mut self, classifier: TableClassifier<B>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Set the line-based reconstructor used for wired tables.
+    pub fn with_wired_reconstructor(mut self, wired: WiredTableReconstructor) -> Self {
+        self.wired = wired;
+        self
+    }
+
     /// Set input size.
     pub fn with_input_size(mut self, width: u32, height: u32) -> Self {
         self.input_size = (width, height);
@@ -191,6 +607,93 @@ impl<B: InferenceBackend> TableRecognizer<B> {
 
     /// Recognize table structure from an image.
     pub fn recognize(&self, image: &DynamicImage) -> Result<TableStructure, OcrError> {
+        if let Some(ref classifier) = self.classifier {
+            match classifier.classify(image) {
+                Ok((TableType::Wired, prob)) => {
+                    if let Some(mut structure) = self.wired.reconstruct(image) {
+                        debug!("Wired table ({:.2}), using line reconstruction", prob);
+                        structure.confidence = prob;
+                        return Ok(structure);
+                    }
+                    debug!("Wired table without detectable grid, falling back to SLANet");
+                }
+                Ok((table_type, prob)) => {
+                    debug!("{:?} table ({:.2}), using SLANet", table_type, prob);
+                }
+                Err(e) => {
+                    warn!("Table classification failed, using SLANet: {}", e);
+                }
+            }
+        }
+
+        self.recognize_slanet(image)
+    }
+
+    /// Recognize the structure of every table region of `result`, cropped
+    /// from `image`, and fill its cells from the OCR boxes. Returns how
+    /// many tables were recognized; tables that fail are logged and keep
+    /// no structure, so their line items come from the text boxes.
+    pub fn recognize_regions(&self, image: &DynamicImage, result: &mut OcrResult) -> usize {
+        let Some(layout) = &result.layout else {
+            return 0;
+        };
+
+        let structures: Vec<Option<TableStructure>> = layout
+            .tables
+            .iter()
+            .map(|region| {
+                let crop = crop_region(image, region)?;
+                let [x, y, _, _] = crop.region.bbox;
+                match self.recognize(&crop.image) {
+                    Ok(mut structure) => {
+                        structure.translate(x, y);
+                        structure.fill_from_ocr(result);
+                        Some(structure)
+                    }
+                    Err(e) => {
+                        warn!("Table recognition failed: {}", e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let count = structures.iter().flatten().count();
+        if let Some(layout) = &mut result.layout {
+            for (region, structure) in layout.tables.iter_mut().zip(structures) {
+                region.structure = structure;
+            }
+        }
+        count
+    }
+
+    /// Run the models once on zeros.
+    pub fn warmup(&self) -> Result<(), OcrError> {
+        let models = [
+            ("table structure", Some(&self.backend)),
+            ("table classification", self.classifier.as_ref().map(|c| &c.backend)),
+        ];
+        for (name, backend) in models {
+            if let Some(backend) = backend {
+                backend
+                    .warmup()
+                    .map_err(|e| OcrError::ModelLoad(format!("{} model warm-up: {}", name, e)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply session options to the models.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn configure(&mut self, options: &InferenceOptions) {
+        self.backend.configure(options);
+        if let Some(classifier) = &mut self.classifier {
+            classifier.backend.configure(options);
+        }
+    }
+
+    /// Recognize table structure with the SLANet model only.
+    pub fn recognize_slanet(&self, image: &DynamicImage) -> Result<TableStructure, OcrError> {
         let (orig_width, orig_height) = image.dimensions();
 
         // Preprocess
@@ -503,7 +1006,10 @@ impl<B: InferenceBackend> TableRecognizer<B> {
 
                     html.push_str(&format!(
                         "    <{}{}>{}</{}>\n",
-                        tag, attrs, cell.content, tag
+                        tag,
+                        attrs,
+                        escape_html(&cell.content),
+                        tag
                     ));
 
                     col_idx += cell.col_span;
@@ -521,11 +1027,13 @@ impl<B: InferenceBackend> TableRecognizer<B> {
 }
 
 /// Table type classifier.
+#[cfg(any(feature = "wasm", feature = "tables"))]
 pub struct TableClassifier<B: InferenceBackend> {
     backend: B,
     input_size: (u32, u32),
 }
 
+#[cfg(any(feature = "wasm", feature = "tables"))]
 impl<B: InferenceBackend> TableClassifier<B> {
     /// Create a new table classifier.
     pub fn new(backend: B) -> Self {
@@ -600,3 +1108,217 @@ impl<B: InferenceBackend> TableClassifier<B> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(row: usize, col: usize, bbox: [f32; 4]) -> TableCell {
+        TableCell {
+            row,
+            col,
+            row_span: 1,
+            col_span: 1,
+            bbox,
+            content: String::new(),
+            confidence: 1.0,
+        }
+    }
+
+    fn text_box(text: &str, x1: f32, y1: f32, x2: f32, y2: f32) -> TextBox {
+        TextBox {
+            bbox: [x1, y1, x2, y1, x2, y2, x1, y2],
+            text: text.to_string(),
+            detection_score: 0.9,
+            recognition_score: 0.9,
+            angle: 0,
+            handwritten: false,
+        }
+    }
+
+    fn two_by_two() -> TableStructure {
+        TableStructure {
+            num_rows: 2,
+            num_cols: 2,
+            cells: vec![
+                cell(0, 0, [0.0, 0.0, 100.0, 50.0]),
+                cell(0, 1, [100.0, 0.0, 200.0, 50.0]),
+                cell(1, 0, [0.0, 50.0, 100.0, 100.0]),
+                cell(1, 1, [100.0, 50.0, 200.0, 100.0]),
+            ],
+            html: String::new(),
+            bbox: [0.0, 0.0, 200.0, 100.0],
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_fill_from_ocr() {
+        let mut table = two_by_two();
+        let mut ocr = OcrResult::empty(200, 100);
+        ocr.boxes = vec![
+            text_box("Nazwa", 10.0, 10.0, 60.0, 30.0),
+            text_box("Cena", 110.0, 10.0, 160.0, 30.0),
+            text_box("usługa", 60.0, 60.0, 95.0, 80.0),
+            text_box("Montaż", 5.0, 60.0, 55.0, 80.0),
+            text_box("100,00", 120.0, 60.0, 180.0, 80.0),
+        ];
+
+        table.fill_from_ocr(&ocr);
+
+        assert_eq!(table.cell_at(0, 0).unwrap().content, "Nazwa");
+        assert_eq!(table.cell_at(0, 1).unwrap().content, "Cena");
+        assert_eq!(table.cell_at(1, 0).unwrap().content, "Montaż usługa");
+        assert_eq!(table.cell_at(1, 1).unwrap().content, "100,00");
+        assert!(table.html.contains("<th>Nazwa</th>"));
+        assert!(table.html.contains("<td>100,00</td>"));
+    }
+
+    #[test]
+    fn test_fill_from_ocr_iou_fallback() {
+        let mut table = two_by_two();
+        let mut ocr = OcrResult::empty(200, 100);
+        // Center lies outside the table, but the box overlaps the last cell
+        ocr.boxes = vec![text_box("23%", 150.0, 90.0, 190.0, 130.0)];
+
+        table.fill_from_ocr(&ocr);
+
+        assert_eq!(table.cell_at(1, 1).unwrap().content, "23%");
+    }
+
+    #[test]
+    fn test_to_grid_and_csv() {
+        let mut table = two_by_two();
+        table.cells[0].content = "Nazwa".to_string();
+        table.cells[1].content = "Cena, netto".to_string();
+        table.cells[2].content = "Klej \"Super\"".to_string();
+        table.cells[3].content = "10,00".to_string();
+
+        let grid = table.to_grid();
+        assert_eq!(grid.rows[1][0], "Klej \"Super\"");
+
+        assert_eq!(
+            table.to_csv(),
+            "Nazwa,\"Cena, netto\"\n\"Klej \"\"Super\"\"\",\"10,00\"\n"
+        );
+    }
+
+    #[test]
+    fn test_from_text_boxes() {
+        let boxes = vec![
+            text_box("Lp", 10.0, 10.0, 30.0, 30.0),
+            text_box("Nazwa", 50.0, 10.0, 150.0, 30.0),
+            text_box("Kwota", 200.0, 10.0, 260.0, 30.0),
+            text_box("1", 12.0, 50.0, 22.0, 70.0),
+            text_box("Usługa", 50.0, 50.0, 100.0, 70.0),
+            text_box("IT", 105.0, 50.0, 120.0, 70.0),
+            text_box("100,00", 205.0, 50.0, 255.0, 70.0),
+        ];
+
+        let table = TableStructure::from_text_boxes(&boxes);
+
+        assert_eq!(table.num_rows, 2);
+        assert_eq!(table.num_cols, 3);
+        assert_eq!(table.cell_at(1, 1).unwrap().content, "Usługa IT");
+        assert_eq!(table.cell_at(1, 2).unwrap().content, "100,00");
+    }
+
+    #[test]
+    fn test_translate() {
+        let mut table = two_by_two();
+        table.translate(10.0, 20.0);
+
+        assert_eq!(table.bbox, [10.0, 20.0, 210.0, 120.0]);
+        assert_eq!(table.cells[3].bbox, [110.0, 70.0, 210.0, 120.0]);
+    }
+
+    fn table_region(bbox: [f32; 4]) -> RegionBox {
+        RegionBox {
+            region_type: "table".to_string(),
+            bbox,
+            confidence: 0.9,
+            structure: None,
+        }
+    }
+
+    #[test]
+    fn test_region_table_from_boxes() {
+        let boxes = vec![
+            text_box("Razem", 10.0, 170.0, 80.0, 190.0),
+            text_box("100,00", 210.0, 170.0, 280.0, 190.0),
+            text_box("Uwagi", 10.0, 10.0, 80.0, 30.0),
+        ];
+
+        // Without a recognized structure, the boxes inside make the grid
+        let table = table_region([0.0, 160.0, 300.0, 200.0]).table(&boxes);
+        assert_eq!((table.num_rows, table.num_cols), (1, 2));
+        assert_eq!(table.cell_at(0, 0).unwrap().content, "Razem");
+    }
+
+    /// Scores every table as wired, so SLANet is never run.
+    #[cfg(any(feature = "wasm", feature = "tables"))]
+    struct Wired {
+        names: Vec<String>,
+    }
+
+    #[cfg(any(feature = "wasm", feature = "tables"))]
+    impl InferenceBackend for Wired {
+        fn run(
+            &self,
+            _inputs: &[(&str, InputTensor)],
+        ) -> incr_inference::Result<Vec<(String, OutputTensor)>> {
+            let output = ndarray::ArrayD::from_shape_vec(vec![1, 2], vec![5.0, -5.0]).unwrap();
+            Ok(vec![("output".to_string(), OutputTensor::Float32(output))])
+        }
+
+        fn input_names(&self) -> &[String] {
+            &self.names
+        }
+
+        fn output_names(&self) -> &[String] {
+            &self.names
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "tables"))]
+    fn test_recognize_regions() {
+        use image::{GrayImage, Luma};
+
+        // A ruled 2x2 table from (100, 50) to (300, 150) on the page
+        let mut page = GrayImage::from_pixel(400, 200, Luma([255]));
+        for y in [50, 100, 150] {
+            (100..=300).for_each(|x| page.put_pixel(x, y, Luma([0])));
+        }
+        for x in [100, 200, 300] {
+            (50..=150).for_each(|y| page.put_pixel(x, y, Luma([0])));
+        }
+        let mut ocr = OcrResult::empty(400, 200);
+        ocr.boxes = vec![
+            text_box("Nazwa", 110.0, 60.0, 180.0, 90.0),
+            text_box("Cena", 210.0, 60.0, 280.0, 90.0),
+            text_box("Montaż", 110.0, 110.0, 180.0, 140.0),
+            text_box("100,00", 210.0, 110.0, 280.0, 140.0),
+            text_box("Razem", 10.0, 170.0, 80.0, 190.0),
+        ];
+        ocr.layout = Some(crate::ocr::LayoutInfo {
+            tables: vec![table_region([95.0, 45.0, 305.0, 155.0])],
+            text_regions: Vec::new(),
+            figures: Vec::new(),
+        });
+
+        let backend = || Wired { names: vec!["x".to_string()] };
+        let recognizer =
+            TableRecognizer::new(backend()).with_classifier(TableClassifier::new(backend()));
+        let page = DynamicImage::ImageLuma8(page);
+        assert_eq!(recognizer.recognize_regions(&page, &mut ocr), 1);
+
+        // Cells in page coordinates, filled from the boxes inside them
+        let table = ocr.layout.as_ref().unwrap().tables[0].table(&ocr.boxes);
+        assert_eq!((table.num_rows, table.num_cols), (2, 2));
+        assert_eq!(table.cell_at(0, 0).unwrap().content, "Nazwa");
+        assert_eq!(table.cell_at(1, 1).unwrap().content, "100,00");
+        assert!((table.cell_at(1, 1).unwrap().bbox[0] - 200.0).abs() <= 1.0);
+        assert!(table.cells.iter().all(|c| c.content != "Razem"));
+    }
+}
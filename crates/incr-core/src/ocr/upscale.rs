@@ -0,0 +1,142 @@
+//! Upscaling of low-resolution images before recognition.
+//!
+//! Invoices forwarded as email images are often 72-96 DPI, where small
+//! digits are only a few pixels tall and recognition confuses them. The
+//! effective resolution is estimated from the height of the detected text
+//! lines; when it is below [`OcrConfig::min_text_height`] the image is
+//! upscaled so lines reach [`TARGET_TEXT_HEIGHT`] before recognition.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use crate::models::capabilities::{Capabilities, Stage};
+use crate::models::config::OcrConfig;
+
+/// Text line height (pixels) the recognition models read best.
+pub const TARGET_TEXT_HEIGHT: f32 = 32.0;
+
+/// Largest upscaling factor applied.
+const MAX_FACTOR: f32 = 4.0;
+
+/// Smaller factors are not worth another recognition pass.
+const MIN_FACTOR: f32 = 1.25;
+
+/// Median height of the given text line heights, ignoring degenerate boxes.
+pub fn median_text_height(heights: impl IntoIterator<Item = f32>) -> Option<f32> {
+    let mut heights: Vec<f32> = heights.into_iter().filter(|h| *h >= 1.0).collect();
+    if heights.is_empty() {
+        return None;
+    }
+
+    heights.sort_by(f32::total_cmp);
+    Some(heights[heights.len() / 2])
+}
+
+/// Factor to upscale an image of `size` by, given the median height of its
+/// text lines, or `None` when no upscaling is needed.
+///
+/// The upscaled image never exceeds [`OcrConfig::max_image_size`].
+pub fn upscale_factor(config: &OcrConfig, text_height: f32, size: (u32, u32)) -> Option<f32> {
+    if !config.auto_upscale || text_height >= config.min_text_height {
+        return None;
+    }
+
+    let longer_side = size.0.max(size.1).max(1) as f32;
+    let factor = text_upscale_factor(text_height).min(config.max_image_size as f32 / longer_side);
+
+    (factor >= MIN_FACTOR).then_some(factor)
+}
+
+/// Record whether an image was upscaled by `factor` and, if not, why.
+pub fn record_upscaling(
+    capabilities: &mut Capabilities,
+    config: &OcrConfig,
+    text_height: Option<f32>,
+    factor: Option<f32>,
+) {
+    match (factor, text_height) {
+        (Some(_), _) => capabilities.ran(Stage::Upscaling),
+        _ if !config.auto_upscale => {
+            capabilities.skipped(Stage::Upscaling, "disabled by ocr.auto_upscale")
+        }
+        (None, Some(height)) if height < config.min_text_height => {
+            capabilities.skipped(Stage::Upscaling, "image already at ocr.max_image_size")
+        }
+        (None, Some(height)) => capabilities.skipped(
+            Stage::Upscaling,
+            format!("median text height {:.0}px is large enough", height),
+        ),
+        (None, None) => capabilities.skipped(Stage::Upscaling, "no text detected"),
+    }
+}
+
+/// Factor that brings text of `text_height` pixels to [`TARGET_TEXT_HEIGHT`],
+/// between 1 and the largest factor applied.
+pub fn text_upscale_factor(text_height: f32) -> f32 {
+    (TARGET_TEXT_HEIGHT / text_height.max(1.0)).clamp(1.0, MAX_FACTOR)
+}
+
+/// Size of an image of `size` upscaled by `factor`.
+pub fn scaled_size(size: (u32, u32), factor: f32) -> (u32, u32) {
+    (
+        (size.0 as f32 * factor).round() as u32,
+        (size.1 as f32 * factor).round() as u32,
+    )
+}
+
+/// Upscale `image` by `factor` with bicubic interpolation.
+pub fn upscale_bicubic(image: &DynamicImage, factor: f32) -> DynamicImage {
+    let (width, height) = scaled_size((image.width(), image.height()), factor);
+    image.resize_exact(width, height, FilterType::CatmullRom)
+}
+
+/// Scale the corners of a text box by `factor`.
+pub fn scale_bbox(bbox: &[f32; 8], factor: f32) -> [f32; 8] {
+    bbox.map(|c| c * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_text_height() {
+        assert_eq!(median_text_height([9.0, 30.0, 10.0, 0.0]), Some(10.0));
+        assert_eq!(median_text_height([]), None);
+    }
+
+    #[test]
+    fn test_text_upscale_factor() {
+        assert_eq!(text_upscale_factor(16.0), 2.0);
+        assert_eq!(text_upscale_factor(4.0), 4.0);
+        assert_eq!(text_upscale_factor(48.0), 1.0);
+    }
+
+    #[test]
+    fn test_upscale_factor() {
+        let config = OcrConfig::default();
+
+        // 96 DPI email image: 10px lines are upscaled 3.2x
+        assert_eq!(upscale_factor(&config, 10.0, (600, 400)), Some(3.2));
+        // Limited by max_image_size
+        assert_eq!(upscale_factor(&config, 10.0, (1024, 800)), Some(2.0));
+        // Readable text, too large an image or upscaling disabled
+        assert_eq!(upscale_factor(&config, 24.0, (600, 400)), None);
+        assert_eq!(upscale_factor(&config, 10.0, (1900, 800)), None);
+
+        let disabled = OcrConfig {
+            auto_upscale: false,
+            ..OcrConfig::default()
+        };
+        assert_eq!(upscale_factor(&disabled, 10.0, (600, 400)), None);
+    }
+
+    #[test]
+    fn test_upscale_bicubic() {
+        let image = DynamicImage::new_rgb8(100, 40);
+        let upscaled = upscale_bicubic(&image, 2.5);
+
+        assert_eq!((upscaled.width(), upscaled.height()), (250, 100));
+        assert_eq!(scale_bbox(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], 0.5)[7], 4.0);
+    }
+}
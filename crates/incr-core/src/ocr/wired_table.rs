@@ -0,0 +1,303 @@
+//! Geometric reconstruction of wired (ruled) tables.
+//!
+//! Detects horizontal and vertical ruling lines in a table image and
+//! intersects them to build the cell grid. Missing line segments between
+//! neighbouring grid positions are treated as merged cells.
+
+use image::{DynamicImage, GrayImage};
+use tracing::debug;
+
+use super::table::{TableCell, TableStructure};
+
+/// Line-based table structure reconstructor for wired tables.
+#[derive(Debug, Clone)]
+pub struct WiredTableReconstructor {
+    /// Pixels darker than this are treated as ink.
+    dark_threshold: u8,
+    /// Minimum line length as a fraction of the image dimension.
+    min_line_ratio: f32,
+    /// Maximum gap (pixels) between line pixels belonging to the same line.
+    merge_distance: u32,
+    /// Minimum fraction of a cell edge that must be inked to count as a border.
+    min_border_coverage: f32,
+}
+
+impl Default for WiredTableReconstructor {
+    fn default() -> Self {
+        Self {
+            dark_threshold: 128,
+            min_line_ratio: 0.5,
+            merge_distance: 3,
+            min_border_coverage: 0.6,
+        }
+    }
+}
+
+impl WiredTableReconstructor {
+    /// Create a reconstructor with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the binarization threshold.
+    pub fn with_dark_threshold(mut self, threshold: u8) -> Self {
+        self.dark_threshold = threshold;
+        self
+    }
+
+    /// Set the minimum ruling line length relative to the image size.
+    pub fn with_min_line_ratio(mut self, ratio: f32) -> Self {
+        self.min_line_ratio = ratio;
+        self
+    }
+
+    /// Set the minimum inked fraction of a cell edge.
+    pub fn with_min_border_coverage(mut self, coverage: f32) -> Self {
+        self.min_border_coverage = coverage;
+        self
+    }
+
+    /// Reconstruct the table grid from ruling lines.
+    ///
+    /// Returns `None` if fewer than two horizontal or vertical lines are
+    /// found. Cells have empty content; use
+    /// [`TableStructure::fill_from_ocr`] to populate them.
+    pub fn reconstruct(&self, image: &DynamicImage) -> Option<TableStructure> {
+        let gray = image.to_luma8();
+        let (width, height) = gray.dimensions();
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let min_h_len = (width as f32 * self.min_line_ratio) as u32;
+        let min_v_len = (height as f32 * self.min_line_ratio) as u32;
+
+        let h_lines = self.group_lines(
+            (0..height).filter(|&y| self.longest_run(&gray, width, |i| (i, y)) >= min_h_len),
+        );
+        let v_lines = self.group_lines(
+            (0..width).filter(|&x| self.longest_run(&gray, height, |i| (x, i)) >= min_v_len),
+        );
+
+        debug!(
+            "Wired table lines: {} horizontal, {} vertical",
+            h_lines.len(),
+            v_lines.len()
+        );
+
+        if h_lines.len() < 2 || v_lines.len() < 2 {
+            return None;
+        }
+
+        let num_rows = h_lines.len() - 1;
+        let num_cols = v_lines.len() - 1;
+
+        // Assign each grid position to the anchor of the cell covering it
+        let mut owner = vec![vec![(0usize, 0usize); num_cols]; num_rows];
+        for r in 0..num_rows {
+            for c in 0..num_cols {
+                owner[r][c] = if c > 0
+                    && !self.has_vertical_border(&gray, v_lines[c], h_lines[r], h_lines[r + 1])
+                {
+                    owner[r][c - 1]
+                } else if r > 0
+                    && !self.has_horizontal_border(&gray, h_lines[r], v_lines[c], v_lines[c + 1])
+                {
+                    owner[r - 1][c]
+                } else {
+                    (r, c)
+                };
+            }
+        }
+
+        let mut cells = Vec::new();
+        for r in 0..num_rows {
+            for c in 0..num_cols {
+                if owner[r][c] != (r, c) {
+                    continue;
+                }
+
+                let (mut last_row, mut last_col) = (r, c);
+                for (rr, row) in owner.iter().enumerate() {
+                    for (cc, &o) in row.iter().enumerate() {
+                        if o == (r, c) {
+                            last_row = last_row.max(rr);
+                            last_col = last_col.max(cc);
+                        }
+                    }
+                }
+
+                cells.push(TableCell {
+                    row: r,
+                    col: c,
+                    row_span: last_row - r + 1,
+                    col_span: last_col - c + 1,
+                    bbox: [
+                        v_lines[c] as f32,
+                        h_lines[r] as f32,
+                        v_lines[last_col + 1] as f32,
+                        h_lines[last_row + 1] as f32,
+                    ],
+                    content: String::new(),
+                    confidence: 1.0,
+                });
+            }
+        }
+
+        let mut table = TableStructure {
+            num_rows,
+            num_cols,
+            cells,
+            html: String::new(),
+            bbox: [
+                v_lines[0] as f32,
+                h_lines[0] as f32,
+                v_lines[num_cols] as f32,
+                h_lines[num_rows] as f32,
+            ],
+            confidence: 1.0,
+        };
+        table.html = table.to_html();
+
+        debug!(
+            "Reconstructed wired table: {}x{} with {} cells",
+            table.num_rows,
+            table.num_cols,
+            table.cells.len()
+        );
+
+        Some(table)
+    }
+
+    fn is_dark(&self, gray: &GrayImage, x: u32, y: u32) -> bool {
+        gray.get_pixel(x, y)[0] < self.dark_threshold
+    }
+
+    /// Longest run of dark pixels along a scan line of `len` pixels.
+    fn longest_run(&self, gray: &GrayImage, len: u32, at: impl Fn(u32) -> (u32, u32)) -> u32 {
+        let mut longest = 0;
+        let mut current = 0;
+
+        for i in 0..len {
+            let (x, y) = at(i);
+            if self.is_dark(gray, x, y) {
+                current += 1;
+                longest = longest.max(current);
+            } else {
+                current = 0;
+            }
+        }
+
+        longest
+    }
+
+    /// Merge adjacent line positions into single line centers.
+    fn group_lines(&self, positions: impl Iterator<Item = u32>) -> Vec<u32> {
+        let mut groups: Vec<(u32, u32)> = Vec::new();
+
+        for pos in positions {
+            match groups.last_mut() {
+                Some((_, end)) if pos - *end <= self.merge_distance => *end = pos,
+                _ => groups.push((pos, pos)),
+            }
+        }
+
+        groups.into_iter().map(|(start, end)| (start + end) / 2).collect()
+    }
+
+    /// Check for a vertical border at `x` between `y1` and `y2`.
+    fn has_vertical_border(&self, gray: &GrayImage, x: u32, y1: u32, y2: u32) -> bool {
+        let tolerance = self.merge_distance;
+        let x_range = x.saturating_sub(tolerance)..=(x + tolerance).min(gray.width() - 1);
+        let inner = (y1 + tolerance)..y2.saturating_sub(tolerance);
+
+        self.coverage(inner, |y| x_range.clone().any(|x| self.is_dark(gray, x, y)))
+    }
+
+    /// Check for a horizontal border at `y` between `x1` and `x2`.
+    fn has_horizontal_border(&self, gray: &GrayImage, y: u32, x1: u32, x2: u32) -> bool {
+        let tolerance = self.merge_distance;
+        let y_range = y.saturating_sub(tolerance)..=(y + tolerance).min(gray.height() - 1);
+        let inner = (x1 + tolerance)..x2.saturating_sub(tolerance);
+
+        self.coverage(inner, |x| y_range.clone().any(|y| self.is_dark(gray, x, y)))
+    }
+
+    fn coverage(&self, range: std::ops::Range<u32>, inked: impl Fn(u32) -> bool) -> bool {
+        let total = range.len();
+        if total == 0 {
+            return true;
+        }
+
+        let dark = range.filter(|&i| inked(i)).count();
+        dark as f32 / total as f32 >= self.min_border_coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn hline(img: &mut GrayImage, y: u32, x1: u32, x2: u32) {
+        for x in x1..=x2 {
+            img.put_pixel(x, y, Luma([0]));
+        }
+    }
+
+    fn vline(img: &mut GrayImage, x: u32, y1: u32, y2: u32) {
+        for y in y1..=y2 {
+            img.put_pixel(x, y, Luma([0]));
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_grid() {
+        let mut img = GrayImage::from_pixel(200, 100, Luma([255]));
+        for y in [10, 50, 90] {
+            hline(&mut img, y, 10, 190);
+        }
+        for x in [10, 100, 190] {
+            vline(&mut img, x, 10, 90);
+        }
+
+        let table = WiredTableReconstructor::new()
+            .reconstruct(&DynamicImage::ImageLuma8(img))
+            .unwrap();
+
+        assert_eq!(table.num_rows, 2);
+        assert_eq!(table.num_cols, 2);
+        assert_eq!(table.cells.len(), 4);
+        assert_eq!(table.cell_at(1, 1).unwrap().bbox, [100.0, 50.0, 190.0, 90.0]);
+    }
+
+    #[test]
+    fn test_reconstruct_merged_header() {
+        let mut img = GrayImage::from_pixel(200, 100, Luma([255]));
+        for y in [10, 50, 90] {
+            hline(&mut img, y, 10, 190);
+        }
+        vline(&mut img, 10, 10, 90);
+        vline(&mut img, 190, 10, 90);
+        // Middle divider only in the second row
+        vline(&mut img, 100, 50, 90);
+
+        let table = WiredTableReconstructor::new()
+            .with_min_line_ratio(0.3)
+            .reconstruct(&DynamicImage::ImageLuma8(img))
+            .unwrap();
+
+        assert_eq!(table.num_cols, 2);
+        assert_eq!(table.cells.len(), 3);
+        assert_eq!(table.cell_at(0, 0).unwrap().col_span, 2);
+    }
+
+    #[test]
+    fn test_reconstruct_no_lines() {
+        let img = GrayImage::from_pixel(100, 100, Luma([255]));
+        assert!(WiredTableReconstructor::new()
+            .reconstruct(&DynamicImage::ImageLuma8(img))
+            .is_none());
+    }
+}
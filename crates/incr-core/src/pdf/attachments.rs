@@ -0,0 +1,255 @@
+//! Files embedded in PDFs.
+//!
+//! Hybrid e-invoices (ZUGFeRD/Factur-X, KSeF visualizations) carry the
+//! structured invoice as an XML attachment of the PDF. Attachments are read
+//! from the document's `EmbeddedFiles` name tree, the associated files
+//! (`AF`) of the catalog that PDF/A-3 requires, and file attachment
+//! annotations on the pages.
+
+use std::collections::HashSet;
+
+use lopdf::{decode_text_string, Dictionary, Document, Object, ObjectId};
+use tracing::trace;
+
+/// Deepest nesting of the name tree followed.
+const MAX_DEPTH: usize = 16;
+
+/// A file embedded in a PDF.
+#[derive(Debug, Clone)]
+pub struct PdfAttachment {
+    /// File name.
+    pub name: String,
+    /// MIME type, if declared (e.g. `text/xml`).
+    pub mime_type: Option<String>,
+    /// Description of the file.
+    pub description: Option<String>,
+    /// Relationship to the document (PDF/A-3 `AFRelationship`, e.g.
+    /// `Alternative` or `Data`).
+    pub relationship: Option<String>,
+    /// Contents of the file.
+    pub data: Vec<u8>,
+}
+
+impl PdfAttachment {
+    /// Whether the file is an XML document, by its MIME type, name or
+    /// contents.
+    pub fn is_xml(&self) -> bool {
+        self.mime_type.as_deref().is_some_and(|mime| mime.ends_with("xml"))
+            || self.name.to_ascii_lowercase().ends_with(".xml")
+            || self
+                .data
+                .strip_prefix(b"\xEF\xBB\xBF")
+                .unwrap_or(&self.data)
+                .trim_ascii_start()
+                .starts_with(b"<?xml")
+    }
+}
+
+/// Attachments of a document, each file once.
+pub(super) fn attachments(doc: &Document) -> Vec<PdfAttachment> {
+    let mut specs: Vec<&Object> = Vec::new();
+
+    if let Ok(catalog) = doc.catalog() {
+        let tree = get(doc, catalog, b"Names").and_then(|names| {
+            get(doc, names.as_dict().ok()?, b"EmbeddedFiles")?.as_dict().ok()
+        });
+        if let Some(tree) = tree {
+            name_tree(doc, tree, &mut specs, 0);
+        }
+        if let Some(files) = get(doc, catalog, b"AF").and_then(|o| o.as_array().ok()) {
+            specs.extend(files);
+        }
+    }
+
+    for page_id in doc.get_pages().into_values() {
+        let Ok(page) = doc.get_dictionary(page_id) else {
+            continue;
+        };
+        let annotations = get(doc, page, b"Annots").and_then(|o| o.as_array().ok());
+        for annotation in annotations.map(Vec::as_slice).unwrap_or_default() {
+            let Some(annotation) = dereference(doc, annotation).and_then(|o| o.as_dict().ok())
+            else {
+                continue;
+            };
+            let subtype = annotation.get(b"Subtype").and_then(Object::as_name).ok();
+            if subtype == Some(b"FileAttachment") {
+                specs.extend(annotation.get(b"FS").ok());
+            }
+        }
+    }
+
+    // The same file spec is often both named and associated
+    let mut seen: HashSet<ObjectId> = HashSet::new();
+    specs
+        .into_iter()
+        .filter(|spec| spec.as_reference().map_or(true, |id| seen.insert(id)))
+        .filter_map(|spec| file_spec(doc, dereference(doc, spec)?.as_dict().ok()?))
+        .collect()
+}
+
+/// Collect the file specs of a name tree node and its kids.
+fn name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    specs: &mut Vec<&'a Object>,
+    depth: usize,
+) {
+    if let Some(names) = get(doc, node, b"Names").and_then(|o| o.as_array().ok()) {
+        // [key1 value1 key2 value2 ...]
+        specs.extend(names.iter().skip(1).step_by(2));
+    }
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    let kids = get(doc, node, b"Kids").and_then(|o| o.as_array().ok());
+    for kid in kids.map(Vec::as_slice).unwrap_or_default() {
+        if let Some(kid) = dereference(doc, kid).and_then(|o| o.as_dict().ok()) {
+            name_tree(doc, kid, specs, depth + 1);
+        }
+    }
+}
+
+/// The file of a file specification dictionary.
+fn file_spec(doc: &Document, spec: &Dictionary) -> Option<PdfAttachment> {
+    let text = |key: &[u8]| get(doc, spec, key).and_then(|o| decode_text_string(o).ok());
+    let name = text(b"UF").or_else(|| text(b"F")).unwrap_or_default();
+
+    let files = get(doc, spec, b"EF")?.as_dict().ok()?;
+    let stream = get(doc, files, b"UF")
+        .or_else(|| get(doc, files, b"F"))?
+        .as_stream()
+        .ok()?;
+    let data = match stream.decompressed_content() {
+        Ok(data) => data,
+        Err(_) if stream.dict.get(b"Filter").is_err() => stream.content.clone(),
+        Err(e) => {
+            trace!("Skipping attachment {} that could not be decompressed: {}", name, e);
+            return None;
+        }
+    };
+
+    let mime_type = stream
+        .dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .ok()
+        .map(|mime| String::from_utf8_lossy(mime).into_owned());
+    let relationship = get(doc, spec, b"AFRelationship")
+        .and_then(|o| o.as_name().ok())
+        .map(|relationship| String::from_utf8_lossy(relationship).into_owned());
+
+    Some(PdfAttachment {
+        name,
+        mime_type,
+        description: text(b"Desc"),
+        relationship,
+        data,
+    })
+}
+
+fn dereference<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    doc.dereference(object).ok().map(|(_, object)| object)
+}
+
+fn get<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    dereference(doc, dict.get(key).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream, StringFormat};
+
+    const XML: &[u8] = b"<?xml version=\"1.0\"?><Faktura/>";
+
+    /// A one-page PDF with `factur-x.xml` in the name tree and the
+    /// catalog's associated files, and a note attached to the page.
+    fn pdf_with_attachments() -> Document {
+        let mut doc = Document::with_version("1.7");
+        let xml = Stream::new(dictionary! { "Subtype" => "text/xml" }, XML.to_vec());
+        let xml = doc.add_object(xml);
+        let spec = doc.add_object(dictionary! {
+            "Type" => "Filespec",
+            "F" => Object::string_literal("factur-x.xml"),
+            "UF" => Object::String(
+                lopdf::encode_utf16_be("factur-x.xml"),
+                StringFormat::Hexadecimal,
+            ),
+            "AFRelationship" => "Alternative",
+            "EF" => dictionary! { "F" => xml },
+        });
+        let note = doc.add_object(Stream::new(dictionary! {}, b"notatka".to_vec()));
+        let annotation = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "FileAttachment",
+            "FS" => dictionary! {
+                "Type" => "Filespec",
+                "F" => Object::string_literal("notatka.txt"),
+                "Desc" => Object::string_literal("Uwagi"),
+                "EF" => dictionary! { "F" => note },
+            },
+        });
+
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Annots" => vec![annotation.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Names" => dictionary! {
+                "EmbeddedFiles" => dictionary! {
+                    "Kids" => vec![Object::Dictionary(dictionary! {
+                        "Names" => vec![Object::string_literal("factur-x.xml"), spec.into()],
+                    })],
+                },
+            },
+            "AF" => vec![spec.into()],
+        });
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn test_attachments() {
+        let found = attachments(&pdf_with_attachments());
+        assert_eq!(found.len(), 2);
+
+        let xml = &found[0];
+        assert_eq!(xml.name, "factur-x.xml");
+        assert_eq!(xml.mime_type.as_deref(), Some("text/xml"));
+        assert_eq!(xml.relationship.as_deref(), Some("Alternative"));
+        assert_eq!(xml.data, XML);
+        assert!(xml.is_xml());
+
+        let note = &found[1];
+        assert_eq!(note.name, "notatka.txt");
+        assert_eq!(note.description.as_deref(), Some("Uwagi"));
+        assert_eq!(note.data, b"notatka");
+        assert!(!note.is_xml());
+    }
+
+    #[test]
+    fn test_is_xml() {
+        let attachment = |name: &str, data: &[u8]| PdfAttachment {
+            name: name.to_string(),
+            mime_type: None,
+            description: None,
+            relationship: None,
+            data: data.to_vec(),
+        };
+        assert!(attachment("FA_1.XML", b"").is_xml());
+        assert!(attachment("faktura", b"\xEF\xBB\xBF\n<?xml version=\"1.0\"?>").is_xml());
+        assert!(!attachment("faktura.pdf", b"%PDF-1.7").is_xml());
+    }
+}
@@ -0,0 +1,635 @@
+//! CCITT Group 3 and Group 4 fax decoding (`CCITTFaxDecode`).
+//!
+//! Office copiers and scanners store black and white pages as CCITT
+//! images. Lines are coded as runs of white and black pixels (Modified
+//! Huffman, one-dimensional) or relative to the changes on the line above
+//! (two-dimensional), as described in ITU-T T.4 and T.6:
+//!
+//! - `K < 0`: Group 4, every line two-dimensional
+//! - `K = 0`: Group 3, every line one-dimensional
+//! - `K > 0`: Group 3, each line tagged as one- or two-dimensional
+
+use std::sync::OnceLock;
+
+use image::GrayImage;
+use lopdf::Dictionary;
+use tracing::trace;
+
+/// Longest code word, in bits (the extended make-up codes).
+const MAX_CODE_LENGTH: usize = 13;
+
+/// End of line code word, `000000000001`.
+const EOL: u32 = 1;
+const EOL_LENGTH: usize = 12;
+
+/// Parameters of a `CCITTFaxDecode` filter, from its `DecodeParms`.
+#[derive(Debug, Clone)]
+pub(super) struct CcittParams {
+    /// Coding scheme: negative for Group 4, zero for one-dimensional
+    /// Group 3, positive for mixed Group 3.
+    pub k: i64,
+    /// Width of a line in pixels.
+    pub columns: u32,
+    /// Number of lines, 0 if not given.
+    pub rows: u32,
+    /// Lines start on a byte boundary.
+    pub byte_align: bool,
+    /// 1 bits are black pixels rather than white ones.
+    pub black_is_1: bool,
+}
+
+impl CcittParams {
+    pub(super) fn from_dict(params: Option<&Dictionary>) -> Self {
+        let int = |key: &[u8], default: i64| {
+            params
+                .and_then(|p| p.get(key).ok())
+                .and_then(|o| o.as_i64().ok())
+                .unwrap_or(default)
+        };
+        let flag = |key: &[u8]| {
+            params
+                .and_then(|p| p.get(key).ok())
+                .and_then(|o| o.as_bool().ok())
+                .unwrap_or(false)
+        };
+        Self {
+            k: int(b"K", 0),
+            columns: int(b"Columns", 1728).clamp(1, u16::MAX as i64) as u32,
+            rows: int(b"Rows", 0).max(0) as u32,
+            byte_align: flag(b"EncodedByteAlign"),
+            black_is_1: flag(b"BlackIs1"),
+        }
+    }
+}
+
+/// Decode CCITT data into an image of `params.columns` by `height` pixels,
+/// black 0 and white 255 (before `BlackIs1`).
+///
+/// Decoding stops at the end of block, after `height` lines or at the end
+/// of the data; lines that are missing or could not be decoded are left
+/// white. `None` if not even the first line could be decoded.
+pub(super) fn decode(data: &[u8], params: &CcittParams, height: u32) -> Option<GrayImage> {
+    let width = params.columns;
+    let mut image = GrayImage::from_pixel(width, height, image::Luma([255]));
+    let mut reader = BitReader { data, position: 0 };
+    // Changes of the line above, starting with a change to black; the
+    // line above the first one is white
+    let mut reference: Vec<u32> = Vec::new();
+    let mut line: Vec<u32> = Vec::new();
+
+    for y in 0..height {
+        if params.byte_align && (params.k < 0 || !reader.at_eol()) {
+            reader.align();
+        }
+        if params.k >= 0 && reader.skip_eol() {
+            // Return to control: more EOLs follow (each after a 1D tag
+            // bit in mixed coding)
+            let tag = (params.k > 0) as usize;
+            if reader.peek(tag + EOL_LENGTH - 3) == Some((tag as u32) << (EOL_LENGTH - 3)) {
+                trace!("CCITT end of data after {} lines", y);
+                break;
+            }
+        }
+
+        let two_dimensional = match params.k {
+            k if k < 0 => true,
+            0 => false,
+            _ => reader.bit()? == 0,
+        };
+        line.clear();
+        let decoded = if two_dimensional {
+            decode_2d(&mut reader, &reference, &mut line, width)
+        } else {
+            decode_1d(&mut reader, &mut line, width)
+        };
+        match decoded {
+            Ok(()) => {}
+            Err(Stop::EndOfBlock) => {
+                trace!("CCITT end of block after {} lines", y);
+                break;
+            }
+            Err(Stop::Invalid) if y == 0 => {
+                trace!("Invalid CCITT data in the first line");
+                return None;
+            }
+            Err(Stop::Invalid) => {
+                trace!("Invalid CCITT data in line {}, the rest is left white", y + 1);
+                break;
+            }
+        }
+
+        // Paint the black runs
+        for run in line.chunks(2) {
+            let end = run.get(1).copied().unwrap_or(width);
+            for x in run[0]..end.min(width) {
+                image.put_pixel(x, y, image::Luma([0]));
+            }
+        }
+        std::mem::swap(&mut reference, &mut line);
+    }
+    Some(image)
+}
+
+/// Why decoding a line stopped short.
+enum Stop {
+    /// The end of block (`EOFB`) or of the data.
+    EndOfBlock,
+    /// A code word that does not fit.
+    Invalid,
+}
+
+/// Record a change of color at `position`; two changes at the same
+/// position cancel out.
+fn change(line: &mut Vec<u32>, position: u32, width: u32) {
+    if position >= width {
+        return;
+    }
+    if line.last() == Some(&position) {
+        line.pop();
+    } else {
+        line.push(position);
+    }
+}
+
+/// A one-dimensional (Modified Huffman) line: alternating white and
+/// black runs.
+fn decode_1d(reader: &mut BitReader, line: &mut Vec<u32>, width: u32) -> Result<(), Stop> {
+    let mut position = 0;
+    let mut black = false;
+    while position < width {
+        let run = reader.run(black).ok_or_else(|| reader.stop())?;
+        position += run;
+        change(line, position, width);
+        black = !black;
+    }
+    if position > width {
+        return Err(Stop::Invalid);
+    }
+    Ok(())
+}
+
+/// A two-dimensional line, coded relative to the changes on the line
+/// above.
+fn decode_2d(
+    reader: &mut BitReader,
+    reference: &[u32],
+    line: &mut Vec<u32>,
+    width: u32,
+) -> Result<(), Stop> {
+    // a0 is the position coding continues from, -1 before the first pixel
+    let mut a0: i64 = -1;
+    let mut black = false;
+    while a0 < width as i64 {
+        // b1: the first change on the line above right of a0 to the
+        // opposite color of a0; b2: the change after it
+        let mut b = reference.partition_point(|&p| p as i64 <= a0);
+        if b % 2 != black as usize {
+            b += 1;
+        }
+        let b1 = reference.get(b).copied().unwrap_or(width);
+        let b2 = reference.get(b + 1).copied().unwrap_or(width);
+
+        match reader.mode().ok_or_else(|| reader.stop())? {
+            Mode::Pass => a0 = b2 as i64,
+            Mode::Vertical(delta) => {
+                let a1 = b1 as i64 + delta as i64;
+                if a1 < a0.max(0) || a1 > width as i64 {
+                    return Err(Stop::Invalid);
+                }
+                change(line, a1 as u32, width);
+                a0 = a1;
+                black = !black;
+            }
+            Mode::Horizontal => {
+                let start = a0.max(0) as u32;
+                let first = reader.run(black).ok_or_else(|| reader.stop())?;
+                let second = reader.run(!black).ok_or_else(|| reader.stop())?;
+                let a1 = start + first;
+                let a2 = a1 + second;
+                if a2 > width {
+                    return Err(Stop::Invalid);
+                }
+                change(line, a1, width);
+                change(line, a2, width);
+                a0 = a2 as i64;
+            }
+            Mode::EndOfLine if a0 < 0 => return Err(Stop::EndOfBlock),
+            Mode::EndOfLine | Mode::Extension => return Err(Stop::Invalid),
+        }
+    }
+    Ok(())
+}
+
+/// Coding mode of a two-dimensional line.
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Pass,
+    Horizontal,
+    /// The change is this far from the one on the line above.
+    Vertical(i8),
+    /// Uncompressed mode and other extensions, not supported.
+    Extension,
+    /// An EOL, part of an end of block when at the start of a line.
+    EndOfLine,
+}
+
+const MODE_CODES: &[(&str, Mode)] = &[
+    ("1", Mode::Vertical(0)),
+    ("011", Mode::Vertical(1)),
+    ("010", Mode::Vertical(-1)),
+    ("001", Mode::Horizontal),
+    ("0001", Mode::Pass),
+    ("000011", Mode::Vertical(2)),
+    ("000010", Mode::Vertical(-2)),
+    ("0000011", Mode::Vertical(3)),
+    ("0000010", Mode::Vertical(-3)),
+    ("0000001", Mode::Extension),
+    ("000000000001", Mode::EndOfLine),
+];
+
+/// Make-up codes for runs of 1792 to 2560 pixels, shared by both colors.
+const EXTENDED_MAKEUP_CODES: &[(&str, u16)] = &[
+    ("00000001000", 1792),
+    ("00000001100", 1856),
+    ("00000001101", 1920),
+    ("000000010010", 1984),
+    ("000000010011", 2048),
+    ("000000010100", 2112),
+    ("000000010101", 2176),
+    ("000000010110", 2240),
+    ("000000010111", 2304),
+    ("000000011100", 2368),
+    ("000000011101", 2432),
+    ("000000011110", 2496),
+    ("000000011111", 2560),
+];
+
+/// White run lengths: terminating codes 0-63, then make-up codes.
+const WHITE_CODES: &[(&str, u16)] = &[
+    ("00110101", 0),
+    ("000111", 1),
+    ("0111", 2),
+    ("1000", 3),
+    ("1011", 4),
+    ("1100", 5),
+    ("1110", 6),
+    ("1111", 7),
+    ("10011", 8),
+    ("10100", 9),
+    ("00111", 10),
+    ("01000", 11),
+    ("001000", 12),
+    ("000011", 13),
+    ("110100", 14),
+    ("110101", 15),
+    ("101010", 16),
+    ("101011", 17),
+    ("0100111", 18),
+    ("0001100", 19),
+    ("0001000", 20),
+    ("0010111", 21),
+    ("0000011", 22),
+    ("0000100", 23),
+    ("0101000", 24),
+    ("0101011", 25),
+    ("0010011", 26),
+    ("0100100", 27),
+    ("0011000", 28),
+    ("00000010", 29),
+    ("00000011", 30),
+    ("00011010", 31),
+    ("00011011", 32),
+    ("00010010", 33),
+    ("00010011", 34),
+    ("00010100", 35),
+    ("00010101", 36),
+    ("00010110", 37),
+    ("00010111", 38),
+    ("00101000", 39),
+    ("00101001", 40),
+    ("00101010", 41),
+    ("00101011", 42),
+    ("00101100", 43),
+    ("00101101", 44),
+    ("00000100", 45),
+    ("00000101", 46),
+    ("00001010", 47),
+    ("00001011", 48),
+    ("01010010", 49),
+    ("01010011", 50),
+    ("01010100", 51),
+    ("01010101", 52),
+    ("00100100", 53),
+    ("00100101", 54),
+    ("01011000", 55),
+    ("01011001", 56),
+    ("01011010", 57),
+    ("01011011", 58),
+    ("01001010", 59),
+    ("01001011", 60),
+    ("00110010", 61),
+    ("00110011", 62),
+    ("00110100", 63),
+    ("11011", 64),
+    ("10010", 128),
+    ("010111", 192),
+    ("0110111", 256),
+    ("00110110", 320),
+    ("00110111", 384),
+    ("01100100", 448),
+    ("01100101", 512),
+    ("01101000", 576),
+    ("01100111", 640),
+    ("011001100", 704),
+    ("011001101", 768),
+    ("011010010", 832),
+    ("011010011", 896),
+    ("011010100", 960),
+    ("011010101", 1024),
+    ("011010110", 1088),
+    ("011010111", 1152),
+    ("011011000", 1216),
+    ("011011001", 1280),
+    ("011011010", 1344),
+    ("011011011", 1408),
+    ("010011000", 1472),
+    ("010011001", 1536),
+    ("010011010", 1600),
+    ("011000", 1664),
+    ("010011011", 1728),
+];
+
+/// Black run lengths: terminating codes 0-63, then make-up codes.
+const BLACK_CODES: &[(&str, u16)] = &[
+    ("0000110111", 0),
+    ("010", 1),
+    ("11", 2),
+    ("10", 3),
+    ("011", 4),
+    ("0011", 5),
+    ("0010", 6),
+    ("00011", 7),
+    ("000101", 8),
+    ("000100", 9),
+    ("0000100", 10),
+    ("0000101", 11),
+    ("0000111", 12),
+    ("00000100", 13),
+    ("00000111", 14),
+    ("000011000", 15),
+    ("0000010111", 16),
+    ("0000011000", 17),
+    ("0000001000", 18),
+    ("00001100111", 19),
+    ("00001101000", 20),
+    ("00001101100", 21),
+    ("00000110111", 22),
+    ("00000101000", 23),
+    ("00000010111", 24),
+    ("00000011000", 25),
+    ("000011001010", 26),
+    ("000011001011", 27),
+    ("000011001100", 28),
+    ("000011001101", 29),
+    ("000001101000", 30),
+    ("000001101001", 31),
+    ("000001101010", 32),
+    ("000001101011", 33),
+    ("000011010010", 34),
+    ("000011010011", 35),
+    ("000011010100", 36),
+    ("000011010101", 37),
+    ("000011010110", 38),
+    ("000011010111", 39),
+    ("000001101100", 40),
+    ("000001101101", 41),
+    ("000011011010", 42),
+    ("000011011011", 43),
+    ("000001010100", 44),
+    ("000001010101", 45),
+    ("000001010110", 46),
+    ("000001010111", 47),
+    ("000001100100", 48),
+    ("000001100101", 49),
+    ("000001010010", 50),
+    ("000001010011", 51),
+    ("000000100100", 52),
+    ("000000110111", 53),
+    ("000000111000", 54),
+    ("000000100111", 55),
+    ("000000101000", 56),
+    ("000001011000", 57),
+    ("000001011001", 58),
+    ("000000101011", 59),
+    ("000000101100", 60),
+    ("000001011010", 61),
+    ("000001100110", 62),
+    ("000001100111", 63),
+    ("0000001111", 64),
+    ("000011001000", 128),
+    ("000011001001", 192),
+    ("000001011011", 256),
+    ("000000110011", 320),
+    ("000000110100", 384),
+    ("000000110101", 448),
+    ("0000001101100", 512),
+    ("0000001101101", 576),
+    ("0000001001010", 640),
+    ("0000001001011", 704),
+    ("0000001001100", 768),
+    ("0000001001101", 832),
+    ("0000001110010", 896),
+    ("0000001110011", 960),
+    ("0000001110100", 1024),
+    ("0000001110101", 1088),
+    ("0000001110110", 1152),
+    ("0000001110111", 1216),
+    ("0000001010010", 1280),
+    ("0000001010011", 1344),
+    ("0000001010100", 1408),
+    ("0000001010101", 1472),
+    ("0000001011010", 1536),
+    ("0000001011011", 1600),
+    ("0000001100100", 1664),
+    ("0000001100101", 1728),
+];
+
+/// Code words by their bits, indexed by the bits behind a leading 1
+/// (so `01` and `1` differ).
+struct Codes<T> {
+    values: Vec<Option<T>>,
+}
+
+impl<T: Copy> Codes<T> {
+    fn new<'a>(codes: impl IntoIterator<Item = &'a (&'a str, T)>) -> Self
+    where
+        T: 'a,
+    {
+        let mut values = vec![None; 1 << (MAX_CODE_LENGTH + 1)];
+        for (bits, value) in codes {
+            let key = bits.bytes().fold(1, |key, bit| key << 1 | (bit == b'1') as usize);
+            values[key] = Some(*value);
+        }
+        Self { values }
+    }
+}
+
+fn mode_codes() -> &'static Codes<Mode> {
+    static CODES: OnceLock<Codes<Mode>> = OnceLock::new();
+    CODES.get_or_init(|| Codes::new(MODE_CODES))
+}
+
+fn run_codes(black: bool) -> &'static Codes<u16> {
+    static WHITE: OnceLock<Codes<u16>> = OnceLock::new();
+    static BLACK: OnceLock<Codes<u16>> = OnceLock::new();
+    let (cell, codes) = match black {
+        false => (&WHITE, WHITE_CODES),
+        true => (&BLACK, BLACK_CODES),
+    };
+    cell.get_or_init(|| Codes::new(codes.iter().chain(EXTENDED_MAKEUP_CODES)))
+}
+
+/// Reads the data bit by bit, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        let byte = self.data.get(self.position / 8)?;
+        let bit = byte >> (7 - self.position % 8) & 1;
+        self.position += 1;
+        Some(bit as u32)
+    }
+
+    /// The next `count` bits without consuming them.
+    fn peek(&self, count: usize) -> Option<u32> {
+        let mut reader = BitReader { data: self.data, position: self.position };
+        (0..count).try_fold(0, |bits, _| Some(bits << 1 | reader.bit()?))
+    }
+
+    /// Why a code word could not be read: the data ended or the bits
+    /// match no code.
+    fn stop(&self) -> Stop {
+        if self.position >= self.data.len() * 8 {
+            Stop::EndOfBlock
+        } else {
+            Stop::Invalid
+        }
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+
+    /// Whether an EOL, possibly after fill bits, comes next.
+    fn at_eol(&self) -> bool {
+        // No code word starts with more than 8 zeros
+        self.peek(EOL_LENGTH - 3) == Some(0)
+    }
+
+    /// Skip fill bits and an EOL, if there is one.
+    fn skip_eol(&mut self) -> bool {
+        if !self.at_eol() {
+            return false;
+        }
+        while self.peek(EOL_LENGTH) == Some(0) {
+            self.position += 1;
+        }
+        if self.peek(EOL_LENGTH) == Some(EOL) {
+            self.position += EOL_LENGTH;
+            return true;
+        }
+        false
+    }
+
+    fn code<T: Copy>(&mut self, codes: &Codes<T>) -> Option<T> {
+        let mut key = 1;
+        for _ in 0..MAX_CODE_LENGTH {
+            key = key << 1 | self.bit()? as usize;
+            if let Some(value) = codes.values[key] {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn mode(&mut self) -> Option<Mode> {
+        self.code(mode_codes())
+    }
+
+    /// A run of one color: make-up codes followed by a terminating code.
+    fn run(&mut self, black: bool) -> Option<u32> {
+        let codes = run_codes(black);
+        let mut length = 0;
+        loop {
+            let run = self.code(codes)? as u32;
+            length += run;
+            if run < 64 {
+                return Some(length);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(k: i64) -> CcittParams {
+        CcittParams {
+            k,
+            columns: 8,
+            rows: 0,
+            byte_align: false,
+            black_is_1: false,
+        }
+    }
+
+    fn rows(image: &GrayImage) -> Vec<Vec<u8>> {
+        image.as_raw().chunks(image.width() as usize).map(<[u8]>::to_vec).collect()
+    }
+
+    const WHITE: [u8; 8] = [255; 8];
+    const STRIPE: [u8; 8] = [255, 255, 0, 0, 0, 255, 255, 255];
+
+    #[test]
+    fn test_group_4() {
+        // V0 (white line), then H with white 2 and black 3, V0
+        let data = [0b1001_0111, 0b1010_0000];
+        let image = decode(&data, &params(-1), 2).unwrap();
+        assert_eq!(rows(&image), [WHITE, STRIPE]);
+
+        // Vertical modes against the line above: V0, VL1, then V0 to the end
+        let data = [0b1001_0111, 0b1011_0101];
+        let image = decode(&data, &params(-1), 3).unwrap();
+        assert_eq!(rows(&image)[2], [255, 255, 0, 0, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_group_3() {
+        // White 8; white 2, black 3, white 3
+        let data = [0b1001_1011, 0b1101_0000];
+        let image = decode(&data, &params(0), 2).unwrap();
+        assert_eq!(rows(&image), [WHITE, STRIPE]);
+
+        // The same lines after EOLs, then the end of data (RTC)
+        let mut bits = String::new();
+        for line in ["10011", "0111101000"] {
+            bits.push_str("000000000001");
+            bits.push_str(line);
+        }
+        bits.push_str(&"000000000001".repeat(6));
+        bits.push_str(&"0".repeat((8 - bits.len() % 8) % 8));
+        let data: Vec<u8> = bits
+            .as_bytes()
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |b, bit| b << 1 | (bit - b'0')))
+            .collect();
+        let image = decode(&data, &params(0), 4).unwrap();
+        assert_eq!(rows(&image), [WHITE, STRIPE, WHITE, WHITE]);
+
+        assert!(decode(&[0, 0], &params(-1), 1).is_none());
+    }
+}
@@ -6,8 +6,10 @@ use std::collections::HashSet;
 use std::io::Cursor;
 use tracing::{debug, trace};
 
-use super::{PdfProcessor, PdfType, Result};
+use super::{ccitt, PdfAttachment, PdfProcessor, PdfType, Result};
 use crate::error::PdfError;
+use crate::models::selection::Region;
+use crate::ocr::OcrResult;
 
 /// PDF content extractor using lopdf.
 pub struct PdfExtractor {
@@ -59,6 +61,103 @@ impl PdfExtractor {
         }
     }
 
+    /// Images to OCR for a page: its embedded images or, with the `pdfium`
+    /// feature, a rendering at `dpi` when it has none (vector-drawn scans,
+    /// pages printed to PDF).
+    pub fn page_images(&self, page: u32, dpi: u32) -> Result<Vec<DynamicImage>> {
+        #[cfg(feature = "pdfium")]
+        {
+            let images = self.page_xobject_images(page)?;
+            if !images.is_empty() {
+                return Ok(images);
+            }
+            super::render::render_page(&self.raw_data, page, dpi).map(|image| vec![image])
+        }
+
+        #[cfg(not(feature = "pdfium"))]
+        {
+            let _ = dpi;
+            self.extract_images(page)
+        }
+    }
+
+    /// The parts of a page's images inside `region`, given in pixels of a
+    /// rendering of the page at `dpi`. Each image is cropped in its own
+    /// pixels where the region falls on it, however it is scaled and placed
+    /// on the page. With the `pdfium` feature a page without images is
+    /// rendered and cropped instead.
+    pub fn page_region_images(
+        &self,
+        page: u32,
+        dpi: u32,
+        region: Region,
+    ) -> Result<Vec<DynamicImage>> {
+        let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
+        let page_id = doc.get_pages().get(&page).copied().ok_or(PdfError::InvalidPage(page))?;
+        let drawn = super::layout::page_region_images(doc, page_id, dpi, region)?;
+
+        #[cfg(feature = "pdfium")]
+        if drawn.is_empty() {
+            let image = super::render::render_page(&self.raw_data, page, dpi)?;
+            return Ok(crop(&image, region).into_iter().collect());
+        }
+
+        let mut images = Vec::new();
+        for (stream, region) in drawn {
+            let Some(region) = region else { continue };
+            let object = Object::Stream(stream.clone());
+            if let Some(image) = self.try_extract_image_from_object(doc, &object) {
+                images.extend(crop(&image, region));
+            }
+        }
+        Ok(images)
+    }
+
+    /// Files embedded in the document, such as the XML of a hybrid
+    /// e-invoice.
+    pub fn extract_attachments(&self) -> Result<Vec<PdfAttachment>> {
+        let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
+        Ok(super::attachments::attachments(doc))
+    }
+
+    /// Text lines of a text PDF page with their positions, as OCR boxes in
+    /// pixels of a rendering of the page at `dpi`.
+    pub fn extract_page_layout(&self, page: u32, dpi: u32) -> Result<OcrResult> {
+        let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
+        let page_id = doc.get_pages().get(&page).copied().ok_or(PdfError::InvalidPage(page))?;
+        super::layout::page_layout(doc, page_id, dpi)
+    }
+
+    /// Images referenced from the page's XObject resources.
+    fn page_xobject_images(&self, page: u32) -> Result<Vec<DynamicImage>> {
+        let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
+
+        let pages = doc.get_pages();
+        let page_id = pages
+            .get(&page)
+            .ok_or(PdfError::InvalidPage(page))?;
+
+        let mut images = Vec::new();
+
+        // Get resources (with inheritance support)
+        if let Some(resources) = self.get_page_resources(doc, *page_id) {
+            // Look for XObjects
+            if let Ok(xobjects) = resources.get(b"XObject") {
+                if let Ok((_, Object::Dictionary(xobj_dict))) = doc.dereference(xobjects) {
+                    for (_name, obj_ref) in xobj_dict.iter() {
+                        if let Ok((_, obj)) = doc.dereference(obj_ref) {
+                            if let Some(img) = self.try_extract_image_from_object(doc, obj) {
+                                images.push(img);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(images)
+    }
+
     /// Load and extract all content from a PDF.
     pub fn extract_all(&self) -> Result<PdfContent> {
         let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
@@ -142,6 +241,14 @@ impl PdfExtractor {
         let mut images = Vec::new();
         let mut seen_objects: HashSet<ObjectId> = HashSet::new();
 
+        // Soft masks are applied to their images, not extracted on their own
+        for object in doc.objects.values() {
+            let mask = object.as_stream().and_then(|stream| stream.dict.get(b"SMask"));
+            if let Ok(Object::Reference(mask)) = mask {
+                seen_objects.insert(*mask);
+            }
+        }
+
         // Iterate through all objects in the document
         for (id, object) in doc.objects.iter() {
             if seen_objects.contains(id) {
@@ -159,6 +266,44 @@ impl PdfExtractor {
     }
 
     fn try_extract_image_from_object(&self, doc: &Document, obj: &Object) -> Option<DynamicImage> {
+        let image = self.decode_image_object(doc, obj)?;
+        let mask = obj.as_stream().ok()?.dict.get(b"SMask").ok();
+        match mask.and_then(|mask| doc.dereference(mask).ok()) {
+            Some((_, mask @ Object::Stream(_))) => Some(self.apply_soft_mask(doc, image, mask)),
+            _ => Some(image),
+        }
+    }
+
+    /// Composite an image with its soft mask (`SMask`) onto a white page,
+    /// so transparent areas are background rather than the color stored
+    /// under them.
+    fn apply_soft_mask(&self, doc: &Document, image: DynamicImage, mask: &Object) -> DynamicImage {
+        let Some(mask) = self.decode_image_object(doc, mask) else {
+            trace!("Could not decode soft mask, using the image without it");
+            return image;
+        };
+        let mut image = image.to_rgba8();
+        let mut mask = mask.to_luma8();
+        if mask.dimensions() != image.dimensions() {
+            mask = image::imageops::resize(
+                &mask,
+                image.width(),
+                image.height(),
+                image::imageops::FilterType::Triangle,
+            );
+        }
+
+        for (pixel, alpha) in image.pixels_mut().zip(mask.pixels()) {
+            let alpha = alpha.0[0] as u32;
+            for channel in &mut pixel.0[..3] {
+                *channel = ((*channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+            }
+            pixel.0[3] = 255;
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn decode_image_object(&self, doc: &Document, obj: &Object) -> Option<DynamicImage> {
         if let Object::Stream(stream) = obj {
             let dict = &stream.dict;
 
@@ -200,9 +345,12 @@ impl PdfExtractor {
                         trace!("Found JPEG2000 image (not supported)");
                         return None;
                     }
-                    Some(b"CCITTFaxDecode") | Some(b"JBIG2Decode") => {
-                        // Fax/JBIG2 - complex to decode
-                        trace!("Found fax/JBIG2 image (not supported)");
+                    Some(b"CCITTFaxDecode") => {
+                        trace!("Decoding CCITT fax image");
+                        return self.decode_ccitt(doc, stream, height);
+                    }
+                    Some(b"JBIG2Decode") => {
+                        trace!("Found JBIG2 image (not supported)");
                         return None;
                     }
                     _ => {}
@@ -213,12 +361,7 @@ impl PdfExtractor {
             let color_space = dict
                 .get(b"ColorSpace")
                 .ok()
-                .and_then(|o| match o {
-                    Object::Name(name) => Some(name.as_slice()),
-                    Object::Array(arr) => arr.first().and_then(|o| o.as_name().ok()),
-                    Object::Reference(r) => doc.get_object(*r).ok().and_then(|o| o.as_name().ok()),
-                    _ => None,
-                })
+                .and_then(|o| self.base_color_space(doc, o, 0))
                 .unwrap_or(b"DeviceRGB");
 
             let bits = dict
@@ -232,6 +375,80 @@ impl PdfExtractor {
         None
     }
 
+    /// Decode a CCITT fax image (black and white scans from copiers).
+    fn decode_ccitt(
+        &self,
+        doc: &Document,
+        stream: &lopdf::Stream,
+        height: u32,
+    ) -> Option<DynamicImage> {
+        let dict = &stream.dict;
+        // One dictionary, or one per filter
+        let params = dict.get(b"DecodeParms").ok().and_then(|o| match doc.dereference(o) {
+            Ok((_, Object::Dictionary(params))) => Some(params),
+            Ok((_, Object::Array(params))) => params
+                .iter()
+                .find_map(|o| doc.dereference(o).ok()?.1.as_dict().ok()),
+            _ => None,
+        });
+        let params = ccitt::CcittParams::from_dict(params);
+        let rows = if params.rows > 0 { params.rows } else { height };
+        let mut image = ccitt::decode(&stream.content, &params, rows)?;
+
+        // Decode [1 0] swaps black and white, as does BlackIs1
+        let inverted = dict
+            .get(b"Decode")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|decode| decode.first())
+            .and_then(|first| first.as_float().ok())
+            .is_some_and(|first| first == 1.0);
+        if inverted != params.black_is_1 {
+            image::imageops::invert(&mut image);
+        }
+        Some(DynamicImage::ImageLuma8(image))
+    }
+
+    /// The device color space an image color space stands for: ICC-based
+    /// spaces resolve to their alternate space or, by their number of
+    /// components, to gray, RGB or CMYK.
+    fn base_color_space<'a>(
+        &self,
+        doc: &'a Document,
+        obj: &'a Object,
+        depth: usize,
+    ) -> Option<&'a [u8]> {
+        let (_, obj) = doc.dereference(obj).ok()?;
+        let arr = match obj {
+            Object::Name(name) => return Some(name.as_slice()),
+            Object::Array(arr) => arr,
+            _ => return None,
+        };
+
+        let family = arr.first()?.as_name().ok()?;
+        match family {
+            b"ICCBased" => {
+                let (_, profile) = doc.dereference(arr.get(1)?).ok()?;
+                let profile = &profile.as_stream().ok()?.dict;
+                let alternate = profile.get(b"Alternate").ok().filter(|_| depth < 4);
+                let base = alternate.and_then(|o| self.base_color_space(doc, o, depth + 1));
+                if let Some(base) = base {
+                    return Some(base);
+                }
+                match profile.get(b"N").and_then(Object::as_i64) {
+                    Ok(1) => Some(b"DeviceGray"),
+                    Ok(3) => Some(b"DeviceRGB"),
+                    Ok(4) => Some(b"DeviceCMYK"),
+                    _ => None,
+                }
+            }
+            // Calibrated spaces have the components of the device ones
+            b"CalGray" => Some(b"DeviceGray"),
+            b"CalRGB" => Some(b"DeviceRGB"),
+            _ => Some(family),
+        }
+    }
+
     fn create_image_from_raw(
         &self,
         data: &[u8],
@@ -279,6 +496,22 @@ impl PdfExtractor {
                 return ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, rgba_data)
                     .map(DynamicImage::ImageRgba8);
             }
+        } else if color_space == b"DeviceCMYK" || color_space == b"CMYK" {
+            let expected_cmyk = expected_gray * 4;
+            if data.len() >= expected_cmyk {
+                // Naive conversion without a color profile; good enough
+                // for OCR
+                let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
+                for chunk in data[..expected_cmyk].chunks(4) {
+                    let white = 255 - chunk[3] as u32;
+                    for &ink in &chunk[..3] {
+                        rgba_data.push(((255 - ink as u32) * white / 255) as u8);
+                    }
+                    rgba_data.push(255);
+                }
+                return ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, rgba_data)
+                    .map(DynamicImage::ImageRgba8);
+            }
         }
 
         trace!("Could not decode image: data_len={}, expected_rgb={}, expected_gray={}",
@@ -384,6 +617,8 @@ impl PdfProcessor for PdfExtractor {
             (true, false) => PdfType::Text,
             (false, true) => PdfType::Image,
             (true, true) => PdfType::Hybrid,
+            // Vector-drawn pages are only readable once rendered
+            (false, false) if cfg!(feature = "pdfium") && self.page_count() > 0 => PdfType::Image,
             (false, false) => PdfType::Empty,
         };
 
@@ -414,7 +649,17 @@ impl PdfProcessor for PdfExtractor {
         Ok(lines[start.min(lines.len())..end.min(lines.len())].join("\n"))
     }
 
-    fn render_page(&self, page: u32, _dpi: u32) -> Result<DynamicImage> {
+    /// With the `pdfium` feature the page is rasterized at `dpi`; without it,
+    /// or when PDFium is not installed, the first embedded image is returned.
+    fn render_page(&self, page: u32, dpi: u32) -> Result<DynamicImage> {
+        #[cfg(feature = "pdfium")]
+        match super::render::render_page(&self.raw_data, page, dpi) {
+            Ok(image) => return Ok(image),
+            Err(e) => debug!("{}, using embedded images", e),
+        }
+        #[cfg(not(feature = "pdfium"))]
+        let _ = dpi;
+
         // Try to extract images from the page
         let images = self.extract_images(page)?;
 
@@ -440,30 +685,7 @@ impl PdfProcessor for PdfExtractor {
     }
 
     fn extract_images(&self, page: u32) -> Result<Vec<DynamicImage>> {
-        let doc = self.document.as_ref().ok_or(PdfError::Parse("No document loaded".to_string()))?;
-
-        let pages = doc.get_pages();
-        let page_id = pages
-            .get(&page)
-            .ok_or(PdfError::InvalidPage(page))?;
-
-        let mut images = Vec::new();
-
-        // Get resources (with inheritance support)
-        if let Some(resources) = self.get_page_resources(doc, *page_id) {
-            // Look for XObjects
-            if let Ok(xobjects) = resources.get(b"XObject") {
-                if let Ok((_, Object::Dictionary(xobj_dict))) = doc.dereference(xobjects) {
-                    for (_name, obj_ref) in xobj_dict.iter() {
-                        if let Ok((_, obj)) = doc.dereference(obj_ref) {
-                            if let Some(img) = self.try_extract_image_from_object(doc, obj) {
-                                images.push(img);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let mut images = self.page_xobject_images(page)?;
 
         // If no images found via XObject, try scanning all objects
         if images.is_empty() {
@@ -476,6 +698,14 @@ impl PdfProcessor for PdfExtractor {
     }
 }
 
+/// The part of `image` inside `region`, or `None` if they don't overlap.
+fn crop(image: &DynamicImage, region: Region) -> Option<DynamicImage> {
+    if region.x >= image.width() || region.y >= image.height() {
+        return None;
+    }
+    Some(image.crop_imm(region.x, region.y, region.width, region.height))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -486,4 +716,115 @@ mod tests {
         assert!(extractor.document.is_none());
         assert_eq!(extractor.page_count(), 0);
     }
+
+    #[test]
+    fn test_ccitt_image() {
+        use lopdf::{dictionary, Stream};
+
+        // Group 4: a white line, then black pixels 2-4
+        let stream = |decode: Vec<Object>| {
+            Object::Stream(Stream::new(
+                dictionary! {
+                    "Subtype" => "Image",
+                    "Width" => 8,
+                    "Height" => 2,
+                    "ImageMask" => true,
+                    "Decode" => decode,
+                    "Filter" => "CCITTFaxDecode",
+                    "DecodeParms" => dictionary! { "K" => -1, "Columns" => 8 },
+                },
+                vec![0b1001_0111, 0b1010_0000],
+            ))
+        };
+        let extractor = PdfExtractor::new();
+        let doc = Document::with_version("1.5");
+
+        let image = extractor.try_extract_image_from_object(&doc, &stream(vec![])).unwrap();
+        let image = image.to_luma8();
+        assert_eq!((image.width(), image.height()), (8, 2));
+        assert_eq!(image.get_pixel(0, 0).0, [255]);
+        assert_eq!(image.get_pixel(2, 1).0, [0]);
+        assert_eq!(image.get_pixel(5, 1).0, [255]);
+
+        let inverted = stream(vec![1.into(), 0.into()]);
+        let image = extractor.try_extract_image_from_object(&doc, &inverted).unwrap();
+        assert_eq!(image.to_luma8().get_pixel(0, 0).0, [0]);
+    }
+
+    #[test]
+    fn test_cmyk_and_icc_images() {
+        use lopdf::{dictionary, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let cmyk_profile = doc.add_object(Stream::new(dictionary! { "N" => 4 }, vec![]));
+        let gray_profile = doc.add_object(Stream::new(
+            dictionary! { "N" => 1, "Alternate" => "DeviceGray" },
+            vec![],
+        ));
+        let image = |color_space: Object, data: Vec<u8>| {
+            Object::Stream(Stream::new(
+                dictionary! {
+                    "Subtype" => "Image",
+                    "Width" => 2,
+                    "Height" => 1,
+                    "BitsPerComponent" => 8,
+                    "ColorSpace" => color_space,
+                },
+                data,
+            ))
+        };
+        let extractor = PdfExtractor::new();
+        let rgb = |obj: &Object| {
+            let image = extractor.try_extract_image_from_object(&doc, obj).unwrap().to_rgb8();
+            (image.get_pixel(0, 0).0, image.get_pixel(1, 0).0)
+        };
+
+        // Pure cyan and 50% black
+        let cmyk = vec![255, 0, 0, 0, 0, 0, 0, 128];
+        let expected = ([0, 255, 255], [127, 127, 127]);
+        assert_eq!(rgb(&image("DeviceCMYK".into(), cmyk.clone())), expected);
+        let icc = vec!["ICCBased".into(), cmyk_profile.into()];
+        assert_eq!(rgb(&image(icc.into(), cmyk)), expected);
+
+        let icc = vec!["ICCBased".into(), gray_profile.into()];
+        assert_eq!(rgb(&image(icc.into(), vec![0, 200])), ([0, 0, 0], [200, 200, 200]));
+    }
+
+    #[test]
+    fn test_soft_mask() {
+        use lopdf::{dictionary, Stream};
+
+        // Black pixels, the second one fully transparent, the third half
+        let mut doc = Document::with_version("1.5");
+        let mask = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 3,
+                "Height" => 1,
+                "BitsPerComponent" => 8,
+                "ColorSpace" => "DeviceGray",
+            },
+            vec![255, 0, 128],
+        ));
+        doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 3,
+                "Height" => 1,
+                "BitsPerComponent" => 8,
+                "ColorSpace" => "DeviceGray",
+                "SMask" => mask,
+            },
+            vec![0, 0, 0],
+        ));
+
+        let mut extractor = PdfExtractor::new();
+        extractor.document = Some(doc);
+        let images = extractor.extract_all_images();
+        assert_eq!(images.len(), 1);
+        let image = images[0].to_luma8();
+        assert_eq!(image.as_raw(), &[0, 255, 127]);
+    }
 }
@@ -0,0 +1,761 @@
+//! Positions of the text in text PDFs.
+//!
+//! `pdf-extract` gives the text of a PDF without its layout. Here the page
+//! content streams are interpreted instead: the text and graphics state are
+//! followed to place every string shown on the page, each string is
+//! measured with the glyph widths of its font, and strings on the same
+//! baseline are merged into lines, split where the gap between them is
+//! wider than a few spaces (table cells, columns). The result is an
+//! [`OcrResult`] like one recognized from a rendering of the page, so
+//! extraction that uses the positions of lines works on digital PDFs too.
+//!
+//! Text in Form XObjects is included. Glyph positions are approximate for
+//! the standard 14 fonts, which PDFs may use without glyph widths.
+//!
+//! The same interpreter records where images are drawn, so a region of the
+//! page can be mapped into the pixels of each image on it.
+
+use std::collections::HashMap;
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId, Stream};
+use tracing::trace;
+
+use super::Result;
+use crate::error::PdfError;
+use crate::models::capabilities::Capabilities;
+use crate::models::selection::Region;
+use crate::ocr::{OcrResult, TextBox};
+
+/// Affine transformation `[a b c d e f]`, as in PDF.
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Page size used when a page has no media box (A4, in points).
+const DEFAULT_PAGE: [f32; 4] = [0.0, 0.0, 595.0, 842.0];
+
+/// Glyph width, in thousandths of the font size, for fonts without widths.
+const DEFAULT_WIDTH: f32 = 500.0;
+
+/// Height above and below the baseline of a line, in font sizes.
+const ASCENT: f32 = 0.8;
+const DESCENT: f32 = 0.2;
+
+/// Gap between strings, in font sizes, from which a space is inserted.
+const SPACE_GAP: f32 = 0.15;
+
+/// Gap between strings, in font sizes, from which they are separate boxes.
+const BOX_GAP: f32 = 1.0;
+
+/// Baseline difference, in font sizes, within which strings share a line.
+const BASELINE_TOLERANCE: f32 = 0.3;
+
+/// Deepest nesting of Form XObjects followed.
+const MAX_DEPTH: usize = 8;
+
+/// `m` followed by `n`.
+fn multiply(m: Matrix, n: Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+fn translate(x: f32, y: f32) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, x, y]
+}
+
+/// Numbers among `operands`.
+fn numbers(operands: &[Object]) -> Vec<f32> {
+    operands.iter().filter_map(|o| o.as_float().ok()).collect()
+}
+
+/// A matrix operand (`cm`, `Tm`, a form's `/Matrix`).
+fn matrix(operands: &[Object]) -> Option<Matrix> {
+    let values = numbers(operands);
+    values.get(..6).map(|v| [v[0], v[1], v[2], v[3], v[4], v[5]])
+}
+
+/// A font: how to decode and measure its strings.
+struct Font<'a> {
+    encoding: Option<Encoding<'a>>,
+    /// Character codes are two bytes (composite fonts).
+    two_byte: bool,
+    /// Glyph widths by character code, in thousandths of the font size.
+    widths: HashMap<u32, f32>,
+    default_width: f32,
+}
+
+impl<'a> Font<'a> {
+    fn load(doc: &'a Document, dict: &'a Dictionary) -> Self {
+        let get = |dict: &'a Dictionary, key: &[u8]| -> Option<&'a Object> {
+            dict.get(key).ok().and_then(|o| doc.dereference(o).ok()).map(|(_, o)| o)
+        };
+        let number = |o: &Object| doc.dereference(o).ok().and_then(|(_, o)| o.as_float().ok());
+
+        let two_byte = get(dict, b"Subtype").and_then(|o| o.as_name().ok()) == Some(b"Type0");
+        let mut widths = HashMap::new();
+        let mut default_width = DEFAULT_WIDTH;
+
+        if two_byte {
+            let descendant = get(dict, b"DescendantFonts")
+                .and_then(|o| o.as_array().ok())
+                .and_then(|fonts| fonts.first())
+                .and_then(|o| doc.dereference(o).ok())
+                .and_then(|(_, o)| o.as_dict().ok());
+            if let Some(descendant) = descendant {
+                default_width = get(descendant, b"DW").and_then(&number).unwrap_or(1000.0);
+                let w = get(descendant, b"W").and_then(|o| o.as_array().ok());
+                // [first [w1 w2 ...]] or [first last w]
+                let mut items = w.map(|w| w.as_slice()).unwrap_or_default().iter();
+                while let Some(first) = items.next().and_then(number) {
+                    let first = first as u32;
+                    match items.next().map(|o| doc.dereference(o).map(|(_, o)| o)) {
+                        Some(Ok(Object::Array(list))) => {
+                            for (i, width) in list.iter().filter_map(number).enumerate() {
+                                widths.insert(first + i as u32, width);
+                            }
+                        }
+                        Some(Ok(last)) => {
+                            let (Some(last), Some(width)) =
+                                (last.as_float().ok(), items.next().and_then(number))
+                            else {
+                                break;
+                            };
+                            for code in first..=(last as u32).min(first + 0xFFFF) {
+                                widths.insert(code, width);
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        } else {
+            let first = get(dict, b"FirstChar").and_then(&number).unwrap_or(0.0) as u32;
+            if let Some(list) = get(dict, b"Widths").and_then(|o| o.as_array().ok()) {
+                for (i, width) in list.iter().filter_map(number).enumerate() {
+                    widths.insert(first + i as u32, width);
+                }
+            }
+            if let Some(missing) = get(dict, b"FontDescriptor")
+                .and_then(|o| o.as_dict().ok())
+                .and_then(|descriptor| get(descriptor, b"MissingWidth"))
+                .and_then(&number)
+                .filter(|&width| width > 0.0)
+            {
+                default_width = missing;
+            }
+        }
+
+        Self {
+            encoding: dict.get_font_encoding(doc).ok(),
+            two_byte,
+            widths,
+            default_width,
+        }
+    }
+
+    /// Character codes of a string.
+    fn codes<'b>(&self, bytes: &'b [u8]) -> impl Iterator<Item = u32> + 'b {
+        let size = if self.two_byte { 2 } else { 1 };
+        bytes
+            .chunks(size)
+            .map(|code| code.iter().fold(0, |value, &byte| value << 8 | u32::from(byte)))
+    }
+
+    fn width(&self, code: u32) -> f32 {
+        self.widths.get(&code).copied().unwrap_or(self.default_width)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        self.encoding
+            .as_ref()
+            .and_then(|encoding| Document::decode_text(encoding, bytes).ok())
+            .unwrap_or_else(|| bytes.iter().map(|&b| char::from(b)).collect())
+    }
+}
+
+/// Graphics state parameters that `q` and `Q` save and restore.
+#[derive(Clone)]
+struct State {
+    ctm: Matrix,
+    char_spacing: f32,
+    word_spacing: f32,
+    /// Horizontal scaling, as a factor.
+    scale: f32,
+    leading: f32,
+    font: Option<Vec<u8>>,
+    font_size: f32,
+}
+
+impl State {
+    fn new(ctm: Matrix) -> Self {
+        Self {
+            ctm,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            scale: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+        }
+    }
+}
+
+/// A string shown on the page, in default user space (points, y up).
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    /// Start and end of its baseline.
+    start: (f32, f32),
+    end: (f32, f32),
+    /// Font size on the page.
+    size: f32,
+}
+
+impl Run {
+    fn is_horizontal(&self) -> bool {
+        (self.end.1 - self.start.1).abs() <= self.size * BASELINE_TOLERANCE
+            && self.end.0 >= self.start.0
+    }
+}
+
+/// Content stream interpreter collecting the strings shown and the images
+/// drawn, with the CTM mapping the unit square onto the image.
+struct Interpreter<'a> {
+    doc: &'a Document,
+    runs: Vec<Run>,
+    images: Vec<(&'a Stream, Matrix)>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn run(
+        &mut self,
+        content: &[u8],
+        resources: Option<&'a Dictionary>,
+        ctm: Matrix,
+        depth: usize,
+    ) {
+        let content = match Content::decode(content) {
+            Ok(content) => content,
+            Err(e) => {
+                trace!("Skipping undecodable content stream: {}", e);
+                return;
+            }
+        };
+
+        let doc = self.doc;
+        let resource = |key: &[u8]| -> Option<&'a Dictionary> {
+            let object = resources?.get(key).ok()?;
+            doc.dereference(object).ok()?.1.as_dict().ok()
+        };
+        let fonts: HashMap<Vec<u8>, Font<'a>> = resource(b"Font")
+            .map(|fonts| {
+                fonts
+                    .iter()
+                    .filter_map(|(name, font)| {
+                        let font = doc.dereference(font).ok()?.1.as_dict().ok()?;
+                        Some((name.clone(), Font::load(doc, font)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut state = State::new(ctm);
+        let mut saved = Vec::new();
+        let mut text_matrix = IDENTITY;
+        let mut line_matrix = IDENTITY;
+
+        for operation in &content.operations {
+            let operands = &operation.operands;
+            let values = numbers(operands);
+            match operation.operator.as_str() {
+                "q" => saved.push(state.clone()),
+                "Q" => state = saved.pop().unwrap_or(state),
+                "cm" => {
+                    if let Some(m) = matrix(operands) {
+                        state.ctm = multiply(m, state.ctm);
+                    }
+                }
+                "BT" => {
+                    text_matrix = IDENTITY;
+                    line_matrix = IDENTITY;
+                }
+                "Tf" => {
+                    let name = operands.first().and_then(|o| o.as_name().ok());
+                    state.font = name.map(<[u8]>::to_vec);
+                    state.font_size = values.first().copied().unwrap_or(state.font_size);
+                }
+                "Tc" => state.char_spacing = values.first().copied().unwrap_or(0.0),
+                "Tw" => state.word_spacing = values.first().copied().unwrap_or(0.0),
+                "Tz" => state.scale = values.first().copied().unwrap_or(100.0) / 100.0,
+                "TL" => state.leading = values.first().copied().unwrap_or(0.0),
+                "Td" | "TD" => {
+                    if let [x, y, ..] = values[..] {
+                        if operation.operator == "TD" {
+                            state.leading = -y;
+                        }
+                        line_matrix = multiply(translate(x, y), line_matrix);
+                        text_matrix = line_matrix;
+                    }
+                }
+                "Tm" => {
+                    if let Some(m) = matrix(operands) {
+                        line_matrix = m;
+                        text_matrix = m;
+                    }
+                }
+                "T*" | "'" | "\"" => {
+                    if let ("\"", [word, char, ..]) = (operation.operator.as_str(), &values[..]) {
+                        state.word_spacing = *word;
+                        state.char_spacing = *char;
+                    }
+                    line_matrix = multiply(translate(0.0, -state.leading), line_matrix);
+                    text_matrix = line_matrix;
+                    let shown = operands.last().filter(|_| operation.operator != "T*");
+                    if let Some(Ok(bytes)) = shown.map(Object::as_str) {
+                        self.show(&state, &fonts, &mut text_matrix, bytes);
+                    }
+                }
+                "Tj" => {
+                    if let Some(Ok(bytes)) = operands.first().map(Object::as_str) {
+                        self.show(&state, &fonts, &mut text_matrix, bytes);
+                    }
+                }
+                "TJ" => {
+                    let items = operands.first().and_then(|o| o.as_array().ok());
+                    for item in items.map(Vec::as_slice).unwrap_or_default() {
+                        match item {
+                            Object::String(bytes, _) => {
+                                self.show(&state, &fonts, &mut text_matrix, bytes);
+                            }
+                            item => {
+                                let adjust = item.as_float().unwrap_or(0.0);
+                                let x = -adjust / 1000.0 * state.font_size * state.scale;
+                                text_matrix = multiply(translate(x, 0.0), text_matrix);
+                            }
+                        }
+                    }
+                }
+                "Do" => {
+                    let xobject = operands
+                        .first()
+                        .and_then(|o| o.as_name().ok())
+                        .and_then(|name| resource(b"XObject")?.get(name).ok())
+                        .and_then(|o| doc.dereference(o).ok())
+                        .and_then(|(_, o)| o.as_stream().ok());
+                    let is = |s: &Stream, kind: &[u8]| {
+                        s.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(kind)
+                    };
+                    if let Some(image) = xobject.filter(|s| is(s, b"Image")) {
+                        self.images.push((image, state.ctm));
+                    }
+                    let form = xobject.filter(|s| is(s, b"Form") && depth < MAX_DEPTH);
+                    if let Some(form) = form {
+                        let content =
+                            form.decompressed_content().unwrap_or_else(|_| form.content.clone());
+                        let form_matrix = form
+                            .dict
+                            .get(b"Matrix")
+                            .ok()
+                            .and_then(|o| o.as_array().ok())
+                            .and_then(|m| matrix(m))
+                            .unwrap_or(IDENTITY);
+                        let form_resources = form
+                            .dict
+                            .get(b"Resources")
+                            .ok()
+                            .and_then(|o| doc.dereference(o).ok())
+                            .and_then(|(_, o)| o.as_dict().ok())
+                            .or(resources);
+                        let ctm = multiply(form_matrix, state.ctm);
+                        self.run(&content, form_resources, ctm, depth + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Place a string at the text matrix and advance it past the string.
+    fn show(
+        &mut self,
+        state: &State,
+        fonts: &HashMap<Vec<u8>, Font<'a>>,
+        text_matrix: &mut Matrix,
+        bytes: &[u8],
+    ) {
+        let Some(font) = state.font.as_ref().and_then(|name| fonts.get(name)) else {
+            return;
+        };
+
+        let advance: f32 = font
+            .codes(bytes)
+            .map(|code| {
+                let word = if !font.two_byte && code == 32 { state.word_spacing } else { 0.0 };
+                (font.width(code) / 1000.0 * state.font_size + state.char_spacing + word)
+                    * state.scale
+            })
+            .sum();
+
+        let start = multiply(*text_matrix, state.ctm);
+        *text_matrix = multiply(translate(advance, 0.0), *text_matrix);
+        let end = multiply(*text_matrix, state.ctm);
+
+        self.runs.push(Run {
+            text: font.decode(bytes),
+            start: (start[4], start[5]),
+            end: (end[4], end[5]),
+            size: state.font_size * start[2].hypot(start[3]),
+        });
+    }
+}
+
+/// An inheritable attribute of a page (`Resources`, `MediaBox`).
+fn inherited<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return doc.dereference(value).ok().map(|(_, o)| o);
+        }
+        node = doc.get_dictionary(node.get(b"Parent").ok()?.as_reference().ok()?).ok()?;
+    }
+    None
+}
+
+/// The visible area of a page `[x0, y0, x1, y1]`, in points.
+fn page_box(doc: &Document, page_id: ObjectId) -> [f32; 4] {
+    ["CropBox", "MediaBox"]
+        .iter()
+        .filter_map(|key| inherited(doc, page_id, key.as_bytes())?.as_array().ok())
+        .find_map(|values| matrix(&[values.as_slice(), &[0.into(), 0.into()]].concat()))
+        .map_or(DEFAULT_PAGE, |b| [b[0].min(b[2]), b[1].min(b[3]), b[0].max(b[2]), b[1].max(b[3])])
+}
+
+/// Run the content streams of a page.
+fn interpret(doc: &Document, page_id: ObjectId) -> Result<Interpreter<'_>> {
+    let content = doc
+        .get_page_content(page_id)
+        .map_err(|e| PdfError::TextExtraction(e.to_string()))?;
+    let resources = inherited(doc, page_id, b"Resources").and_then(|o| o.as_dict().ok());
+    let mut interpreter = Interpreter { doc, runs: Vec::new(), images: Vec::new() };
+    interpreter.run(&content, resources, IDENTITY, 0);
+    Ok(interpreter)
+}
+
+/// Images drawn on a page, each with the part of it inside `region`, given
+/// in pixels at `dpi` from the top left of the page (`None` for images
+/// outside the region).
+pub(super) fn page_region_images(
+    doc: &Document,
+    page_id: ObjectId,
+    dpi: u32,
+    region: Region,
+) -> Result<Vec<(&Stream, Option<Region>)>> {
+    let page = page_box(doc, page_id);
+    interpret(doc, page_id)?
+        .images
+        .into_iter()
+        .map(|(image, ctm)| {
+            let size = |key: &[u8]| image.dict.get(key).and_then(Object::as_i64).unwrap_or(0);
+            let size = (size(b"Width").max(0) as u32, size(b"Height").max(0) as u32);
+            Ok((image, image_region(ctm, size, page, region, dpi)?))
+        })
+        .collect()
+}
+
+/// The part of an image of `size` pixels, drawn with `ctm` on a page with
+/// the box `page`, that lies inside `region` (pixels at `dpi` from the top
+/// left of the page), in the image's pixels. Rotated and skewed images
+/// can't be cropped to a rectangle and are an error.
+fn image_region(
+    ctm: Matrix,
+    size: (u32, u32),
+    page: [f32; 4],
+    region: Region,
+    dpi: u32,
+) -> Result<Option<Region>> {
+    let [a, b, c, d, e, f] = ctm;
+    if b.abs() > 1e-3 * a.abs() || c.abs() > 1e-3 * d.abs() {
+        return Err(PdfError::ImageExtraction(
+            "--region can't be applied to a rotated or skewed image".to_string(),
+        ));
+    }
+    if a == 0.0 || d == 0.0 || size.0 == 0 || size.1 == 0 {
+        return Ok(None);
+    }
+
+    // The region in points, then in the unit square the image is drawn on
+    let scale = dpi as f32 / 72.0;
+    let left = page[0] + region.x as f32 / scale;
+    let right = page[0] + (region.x + region.width) as f32 / scale;
+    let top = page[3] - region.y as f32 / scale;
+    let bottom = page[3] - (region.y + region.height) as f32 / scale;
+    let span = |from: f32, to: f32| {
+        let (from, to) = (from.clamp(0.0, 1.0), to.clamp(0.0, 1.0));
+        (from.min(to), from.max(to))
+    };
+    let (u0, u1) = span((left - e) / a, (right - e) / a);
+    let (v0, v1) = span((bottom - f) / d, (top - f) / d);
+
+    // Image rows run from the top of the unit square down
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    let (x0, x1) = ((u0 * width).floor() as u32, (u1 * width).ceil() as u32);
+    let (y0, y1) = (((1.0 - v1) * height).floor() as u32, ((1.0 - v0) * height).ceil() as u32);
+    if x1 <= x0 || y1 <= y0 || u1 <= u0 || v1 <= v0 {
+        return Ok(None);
+    }
+    Ok(Some(Region { x: x0, y: y0, width: x1 - x0, height: y1 - y0 }))
+}
+
+/// Text lines of a page as OCR boxes, in pixels at `dpi` from the top left
+/// of the page.
+pub(super) fn page_layout(doc: &Document, page_id: ObjectId, dpi: u32) -> Result<OcrResult> {
+    let [x0, y0, x1, y1] = page_box(doc, page_id);
+    let interpreter = interpret(doc, page_id)?;
+
+    let scale = dpi as f32 / 72.0;
+    let boxes: Vec<TextBox> = lines(interpreter.runs)
+        .into_iter()
+        .map(|line| {
+            let left = (line.start.0 - x0) * scale;
+            let right = (line.end.0 - x0) * scale;
+            let top = (y1 - (line.start.1 + line.size * ASCENT)) * scale;
+            let bottom = (y1 - (line.start.1 - line.size * DESCENT)) * scale;
+            TextBox {
+                bbox: [left, top, right, top, right, bottom, left, bottom],
+                text: line.text,
+                detection_score: 1.0,
+                recognition_score: 1.0,
+                angle: 0,
+                handwritten: false,
+            }
+        })
+        .collect();
+
+    Ok(OcrResult {
+        text: boxes.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join("\n"),
+        boxes,
+        processing_time_ms: 0,
+        image_size: (((x1 - x0) * scale).round() as u32, ((y1 - y0) * scale).round() as u32),
+        layout: None,
+        capabilities: Capabilities::text_layer(),
+        barcodes: Vec::new(),
+    })
+}
+
+/// Merge strings into lines, top to bottom and left to right. Strings on
+/// the same baseline are joined unless far apart.
+fn lines(runs: Vec<Run>) -> Vec<Run> {
+    let (mut horizontal, other): (Vec<Run>, Vec<Run>) = runs
+        .into_iter()
+        .filter(|run| !run.text.trim().is_empty() && run.size > 0.0)
+        .partition(Run::is_horizontal);
+    horizontal.sort_by(|a, b| b.start.1.total_cmp(&a.start.1));
+
+    // Rows of strings sharing a baseline
+    let mut rows: Vec<Vec<Run>> = Vec::new();
+    for run in horizontal {
+        match rows.last_mut() {
+            Some(row)
+                if (row[0].start.1 - run.start.1).abs() <= row[0].size * BASELINE_TOLERANCE =>
+            {
+                row.push(run);
+            }
+            _ => rows.push(vec![run]),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for mut row in rows {
+        row.sort_by(|a, b| a.start.0.total_cmp(&b.start.0));
+        let mut row = row.into_iter();
+        let Some(mut line) = row.next() else {
+            continue;
+        };
+        for run in row {
+            let size = line.size.max(run.size);
+            let gap = run.start.0 - line.end.0;
+            if gap > size * BOX_GAP {
+                lines.push(std::mem::replace(&mut line, run));
+                continue;
+            }
+            let joined = line.text.ends_with(char::is_whitespace)
+                || run.text.starts_with(char::is_whitespace);
+            if gap > size * SPACE_GAP && !joined {
+                line.text.push(' ');
+            }
+            line.text.push_str(&run.text);
+            line.end.0 = line.end.0.max(run.end.0);
+            line.size = size;
+        }
+        lines.push(line);
+    }
+
+    // Rotated text, one box per string
+    lines.extend(other);
+    for line in &mut lines {
+        line.text = line.text.trim().to_string();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    /// A one-page PDF showing `content` with Helvetica as `/F1`, every glyph
+    /// 500 units wide.
+    fn text_pdf(content: &str) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let font = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+            "FirstChar" => 32,
+            "LastChar" => 255,
+            "Widths" => vec![Object::Integer(500); 224],
+        });
+        let content = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font } },
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    fn layout(content: &str) -> OcrResult {
+        let doc = Document::load_mem(&text_pdf(content)).unwrap();
+        let page = *doc.get_pages().get(&1).unwrap();
+        page_layout(&doc, page, 72).unwrap()
+    }
+
+    #[test]
+    fn test_page_layout() {
+        // Two columns, a line shown in pieces and a table row
+        let result = layout(
+            "BT /F1 10 Tf 50 742 Td (Sprzedawca:) Tj 300 0 Td (Nabywca:) Tj ET \
+             BT /F1 10 Tf 50 728 Td [(ABC) -300 (Sp. z o.o.)] TJ ET \
+             BT /F1 10 Tf 1 0 0 1 350 728 Tm (XYZ) Tj ( S.A.) Tj ET \
+             q 1 0 0 1 0 -100 cm BT /F1 10 Tf 50 600 Td (1) Tj 20 0 Td (Usluga) Tj ET Q",
+        );
+
+        let texts: Vec<&str> = result.boxes.iter().map(|b| b.text.as_str()).collect();
+        assert_eq!(texts, ["Sprzedawca:", "Nabywca:", "ABC Sp. z o.o.", "XYZ S.A.", "1", "Usluga"]);
+        assert_eq!(result.text.lines().count(), 6);
+        assert_eq!(result.image_size, (595, 842));
+
+        // 11 glyphs 5pt wide from x = 50, baseline 100pt from the top
+        let (x1, y1, x2, y2) = result.boxes[0].rect();
+        assert_eq!((x1, x2), (50.0, 105.0));
+        assert_eq!((y1, y2), (92.0, 102.0));
+        // The table row moved by the CTM
+        assert_eq!(result.boxes[4].rect().3, 344.0);
+    }
+
+    #[test]
+    fn test_page_layout_scaled() {
+        let data = text_pdf("BT /F1 12 Tf 2 0 0 2 10 800 Tm (A) Tj ET");
+        let doc = Document::load_mem(&data).unwrap();
+        let page = *doc.get_pages().get(&1).unwrap();
+        let result = page_layout(&doc, page, 144).unwrap();
+
+        // 12pt text scaled twice, rendered at twice 72 DPI
+        let (x1, _, x2, _) = result.boxes[0].rect();
+        assert_eq!((x1, x2), (20.0, 44.0));
+        assert_eq!(result.boxes[0].height(), 48.0);
+        assert_eq!(result.image_size, (1190, 1684));
+    }
+
+    #[test]
+    fn test_page_region_images() {
+        let mut doc = Document::with_version("1.5");
+        let image = |width: i64, height: i64| {
+            Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => width,
+                    "Height" => height,
+                    "BitsPerComponent" => 8,
+                    "ColorSpace" => "DeviceGray",
+                },
+                vec![0; (width * height) as usize],
+            )
+        };
+        let scan = doc.add_object(image(150, 100));
+        let logo = doc.add_object(image(10, 10));
+        // The scan 2x4 points per pixel at the top left, the logo at the bottom
+        let content = "q 300 0 0 400 0 442 cm /Im1 Do Q q 100 0 0 100 400 100 cm /Im2 Do Q";
+        let content = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Resources" => dictionary! {
+                "XObject" => dictionary! { "Im1" => scan, "Im2" => logo },
+            },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+            }),
+        );
+
+        // Points 50-150 from the left and 100-300 from the top, at 144 DPI
+        let region = Region { x: 100, y: 200, width: 200, height: 400 };
+        let images = page_region_images(&doc, page, 144, region).unwrap();
+        let crops: Vec<_> = images.iter().map(|(_, crop)| *crop).collect();
+        assert_eq!(crops, [Some(Region { x: 25, y: 25, width: 50, height: 50 }), None]);
+
+        // The whole page covers both images entirely
+        let page_region = Region { x: 0, y: 0, width: 595, height: 842 };
+        let images = page_region_images(&doc, page, 72, page_region).unwrap();
+        let crops: Vec<_> = images.iter().map(|(_, crop)| *crop).collect();
+        let whole = |w, h| Some(Region { x: 0, y: 0, width: w, height: h });
+        assert_eq!(crops, [whole(150, 100), whole(10, 10)]);
+    }
+
+    #[test]
+    fn test_image_region_rotated() {
+        let page = [0.0, 0.0, 595.0, 842.0];
+        let region = Region { x: 0, y: 0, width: 100, height: 100 };
+        let rotated = [0.0, 300.0, -400.0, 0.0, 400.0, 0.0];
+        assert!(image_region(rotated, (150, 100), page, region, 72).is_err());
+
+        // Drawn upside down: the first rows are at the bottom of the page
+        let flipped = [595.0, 0.0, 0.0, -842.0, 0.0, 842.0];
+        let region = Region { x: 0, y: 0, width: 595, height: 421 };
+        let crop = image_region(flipped, (100, 100), page, region, 72).unwrap();
+        assert_eq!(crop, Some(Region { x: 0, y: 50, width: 100, height: 50 }));
+    }
+}
@@ -1,7 +1,13 @@
 //! PDF processing module.
 
+mod attachments;
+mod ccitt;
 mod extractor;
+mod layout;
+#[cfg(feature = "pdfium")]
+mod render;
 
+pub use attachments::PdfAttachment;
 pub use extractor::{PdfExtractor, PdfContent, PdfPage, ExtractedImage};
 
 use crate::error::PdfError;
@@ -41,6 +47,9 @@ pub trait PdfProcessor {
     fn extract_page_text(&self, page: u32) -> Result<String>;
 
     /// Render a page as an image at the specified DPI.
+    ///
+    /// Implementations without a rasterizer may return an embedded image
+    /// of the page instead, at its own resolution.
     fn render_page(&self, page: u32, dpi: u32) -> Result<DynamicImage>;
 
     /// Extract embedded images from a page.
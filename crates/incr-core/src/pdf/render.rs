@@ -0,0 +1,63 @@
+//! Page rasterization with PDFium.
+//!
+//! PDFium is loaded at runtime, so the library has to be installed separately:
+//! `libpdfium` (`pdfium.dll` on Windows) is looked up next to the executable
+//! first and then in the system library path.
+
+use image::DynamicImage;
+use pdfium_render::prelude::{Pdfium, PdfRenderConfig, PdfiumError, PdfiumLibraryBindings};
+use tracing::debug;
+
+use super::Result;
+use crate::error::PdfError;
+
+/// PDF page size unit: points per inch.
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// Render page `page` (1-indexed) of the PDF in `data` at `dpi`.
+pub(crate) fn render_page(data: &[u8], page: u32, dpi: u32) -> Result<DynamicImage> {
+    let pdfium = Pdfium::new(bind().map_err(|e| render_error("cannot load PDFium", e))?);
+    let document = pdfium
+        .load_pdf_from_byte_slice(data, None)
+        .map_err(|e| PdfError::Parse(e.to_string()))?;
+
+    let index = page
+        .checked_sub(1)
+        .and_then(|i| u16::try_from(i).ok())
+        .ok_or(PdfError::InvalidPage(page))?;
+    let pdf_page = document
+        .pages()
+        .get(index)
+        .map_err(|_| PdfError::InvalidPage(page))?;
+
+    let config = PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / POINTS_PER_INCH);
+    let image = pdf_page
+        .render_with_config(&config)
+        .map_err(|e| render_error("cannot render page", e))?
+        .as_image();
+
+    debug!(
+        "Rendered page {} at {} DPI ({}x{})",
+        page,
+        dpi,
+        image.width(),
+        image.height()
+    );
+    Ok(image)
+}
+
+/// Bind to PDFium next to the executable, or else the system library.
+fn bind() -> std::result::Result<Box<dyn PdfiumLibraryBindings>, PdfiumError> {
+    let local = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Pdfium::pdfium_platform_library_name_at_path));
+
+    match local.filter(|path| path.exists()) {
+        Some(path) => Pdfium::bind_to_library(path),
+        None => Pdfium::bind_to_system_library(),
+    }
+}
+
+fn render_error(context: &str, error: PdfiumError) -> PdfError {
+    PdfError::Render(format!("{}: {}", context, error))
+}
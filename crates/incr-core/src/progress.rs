@@ -0,0 +1,183 @@
+//! Structured progress reporting.
+//!
+//! Long-running operations (OCR, parsing) report [`ProgressEvent`]s to a
+//! [`ProgressSink`]. Frontends implement the sink to drive their own
+//! display: progress bars in the CLI, a JavaScript callback in WASM.
+
+use serde::{Deserialize, Serialize};
+
+/// Pipeline stage an event belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStage {
+    /// Reading the input document.
+    Load,
+    /// Inspecting a PDF for embedded text and images.
+    Analyze,
+    /// Extracting embedded PDF text.
+    TextExtraction,
+    /// Detecting text regions.
+    Detection,
+    /// Recognizing text in detected regions.
+    Recognition,
+    /// Detecting layout regions (tables, figures).
+    Layout,
+    /// Extracting invoice fields from text.
+    Parsing,
+    /// Processing finished.
+    Done,
+}
+
+impl std::fmt::Display for ProgressStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ProgressStage::Load => "load",
+            ProgressStage::Analyze => "analyze",
+            ProgressStage::TextExtraction => "text_extraction",
+            ProgressStage::Detection => "detection",
+            ProgressStage::Recognition => "recognition",
+            ProgressStage::Layout => "layout",
+            ProgressStage::Parsing => "parsing",
+            ProgressStage::Done => "done",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single progress update.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    /// Current stage.
+    pub stage: ProgressStage,
+    /// Completed steps within the stage.
+    pub current: u64,
+    /// Total steps within the stage (0 if unknown).
+    pub total: u64,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl ProgressEvent {
+    /// Create a new event.
+    pub fn new(stage: ProgressStage, current: u64, total: u64, message: impl Into<String>) -> Self {
+        Self {
+            stage,
+            current,
+            total,
+            message: message.into(),
+        }
+    }
+
+    /// Completed fraction of the stage in `0.0..=1.0` (0 if the total is unknown).
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.current.min(self.total) as f32) / (self.total as f32)
+    }
+}
+
+/// Receiver of progress events.
+///
+/// Implemented for closures, so `&|event| println!("{:?}", event)` can be
+/// passed wherever a sink is expected.
+pub trait ProgressSink {
+    /// Handle a progress event.
+    fn report(&self, event: ProgressEvent);
+}
+
+/// Sink that discards all events.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _event: ProgressEvent) {}
+}
+
+impl<F: Fn(ProgressEvent)> ProgressSink for F {
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Reports the events of one page as progress through a whole document.
+///
+/// Step `current` of `total` on page `page` (0-based) of `pages` becomes
+/// step `page * total + current` of `pages * total`, so per-page stages
+/// advance steadily across the document instead of restarting on every
+/// page. Messages are prefixed with `Page n/m:`.
+pub struct PageProgress<'a> {
+    sink: &'a dyn ProgressSink,
+    page: u64,
+    pages: u64,
+}
+
+impl<'a> PageProgress<'a> {
+    /// Report events of page `page` (0-based) of `pages` to `sink`.
+    pub fn new(sink: &'a dyn ProgressSink, page: u64, pages: u64) -> Self {
+        Self { sink, page, pages }
+    }
+}
+
+impl ProgressSink for PageProgress<'_> {
+    fn report(&self, event: ProgressEvent) {
+        if self.pages <= 1 {
+            return self.sink.report(event);
+        }
+
+        let total = event.total.max(1);
+        let current = if event.total == 0 { 0 } else { event.current.min(total) };
+        self.sink.report(ProgressEvent::new(
+            event.stage,
+            self.page * total + current,
+            self.pages * total,
+            format!("Page {}/{}: {}", self.page + 1, self.pages, event.message),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_closure_sink() {
+        let events = RefCell::new(Vec::new());
+        let sink = |event: ProgressEvent| events.borrow_mut().push(event);
+
+        sink.report(ProgressEvent::new(ProgressStage::Parsing, 1, 4, "dates"));
+        NoProgress.report(ProgressEvent::new(ProgressStage::Done, 1, 1, "ignored"));
+
+        let events = events.into_inner();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].stage, ProgressStage::Parsing);
+        assert_eq!(events[0].fraction(), 0.25);
+    }
+
+    #[test]
+    fn test_page_progress() {
+        let events = RefCell::new(Vec::new());
+        let sink = |event: ProgressEvent| events.borrow_mut().push(event);
+
+        let second = PageProgress::new(&sink, 1, 4);
+        second.report(ProgressEvent::new(ProgressStage::Recognition, 5, 10, "Recognizing"));
+        second.report(ProgressEvent::new(ProgressStage::Recognition, 0, 0, "Running OCR"));
+        PageProgress::new(&sink, 0, 1).report(ProgressEvent::new(ProgressStage::Detection, 1, 1, "Done"));
+
+        let events = events.into_inner();
+        assert_eq!((events[0].current, events[0].total), (15, 40));
+        assert_eq!(events[0].message, "Page 2/4: Recognizing");
+        assert_eq!((events[1].current, events[1].total), (1, 4));
+        assert_eq!(events[2].message, "Done");
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = ProgressEvent::new(ProgressStage::TextExtraction, 0, 0, "PDF text");
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert!(json.contains("\"stage\":\"text_extraction\""));
+        assert_eq!(event.fraction(), 0.0);
+    }
+}
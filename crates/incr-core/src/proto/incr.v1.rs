@@ -0,0 +1,365 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Invoice {
+    #[prost(message, optional, tag = "1")]
+    pub header: ::core::option::Option<InvoiceHeader>,
+    #[prost(message, optional, tag = "2")]
+    pub issuer: ::core::option::Option<Party>,
+    #[prost(message, optional, tag = "3")]
+    pub receiver: ::core::option::Option<Party>,
+    #[prost(message, repeated, tag = "4")]
+    pub line_items: ::prost::alloc::vec::Vec<LineItem>,
+    #[prost(message, optional, tag = "5")]
+    pub summary: ::core::option::Option<InvoiceSummary>,
+    #[prost(message, optional, tag = "6")]
+    pub metadata: ::core::option::Option<ExtractionMetadata>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvoiceHeader {
+    #[prost(string, tag = "1")]
+    pub invoice_number: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub issue_date: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub sale_date: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "4")]
+    pub due_date: ::core::option::Option<::prost::alloc::string::String>,
+    /// standard, correction, advance, final, proforma or margin
+    #[prost(string, tag = "5")]
+    pub invoice_type: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub currency: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "7")]
+    pub correction_of: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(bool, tag = "8")]
+    pub self_invoice: bool,
+    /// Reason and change of the totals, for correction invoices.
+    #[prost(message, optional, tag = "9")]
+    pub correction: ::core::option::Option<CorrectionDetails>,
+    /// Number assigned by KSeF (NIP-YYYYMMDD-XXXXXXXXXXXX-CC).
+    #[prost(string, optional, tag = "10")]
+    pub ksef_number: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CorrectionDetails {
+    #[prost(string, optional, tag = "1")]
+    pub corrected_issue_date: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "2")]
+    pub reason: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "3")]
+    pub before: ::core::option::Option<CorrectionAmounts>,
+    #[prost(message, optional, tag = "4")]
+    pub after: ::core::option::Option<CorrectionAmounts>,
+    #[prost(message, optional, tag = "5")]
+    pub difference: ::core::option::Option<CorrectionAmounts>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CorrectionAmounts {
+    #[prost(string, optional, tag = "1")]
+    pub net: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "2")]
+    pub vat: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "3")]
+    pub gross: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Party {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub nip: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub regon: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "4")]
+    pub pesel: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "5")]
+    pub krs: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "6")]
+    pub address: ::core::option::Option<Address>,
+    #[prost(string, optional, tag = "7")]
+    pub bank_account: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "8")]
+    pub bank_name: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "9")]
+    pub email: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "10")]
+    pub additional_emails: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "11")]
+    pub phone: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "12")]
+    pub additional_phones: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "13")]
+    pub website: ::core::option::Option<::prost::alloc::string::String>,    #[prost(string, optional, tag = "14")]
+    pub vat_id: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Address {
+    #[prost(string, optional, tag = "1")]
+    pub street: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "2")]
+    pub postal_code: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub city: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "4")]
+    pub country: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "5")]
+    pub raw: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LineItem {
+    #[prost(uint32, optional, tag = "1")]
+    pub ordinal: ::core::option::Option<u32>,
+    #[prost(string, tag = "2")]
+    pub description: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "3")]
+    pub code: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "4")]
+    pub quantity: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "5")]
+    pub unit: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "6")]
+    pub unit_price_net: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "7")]
+    pub unit_price_gross: ::core::option::Option<::prost::alloc::string::String>,
+    /// "23", "8", "5", "0", "zw", "np", "oo" or another whole percentage
+    #[prost(string, tag = "8")]
+    pub vat_rate: ::prost::alloc::string::String,
+    #[prost(string, tag = "9")]
+    pub total_net: ::prost::alloc::string::String,
+    #[prost(string, tag = "10")]
+    pub vat_amount: ::prost::alloc::string::String,
+    #[prost(string, tag = "11")]
+    pub total_gross: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "12")]
+    pub discount_percent: ::core::option::Option<::prost::alloc::string::String>,
+    /// GTU_01 to GTU_13
+    #[prost(string, repeated, tag = "13")]
+    pub gtu_codes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvoiceSummary {
+    #[prost(string, tag = "1")]
+    pub total_net: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub total_vat: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub total_gross: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "4")]
+    pub vat_breakdown: ::prost::alloc::vec::Vec<VatBreakdown>,
+    /// transfer, cash, card, compensation or a free-form description
+    #[prost(string, optional, tag = "5")]
+    pub payment_method: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "6")]
+    pub amount_paid: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "7")]
+    pub amount_due: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "8")]
+    pub amount_in_words: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "9")]
+    pub currency_info: ::core::option::Option<CurrencyInfo>,
+    #[prost(bool, tag = "10")]
+    pub split_payment: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VatBreakdown {
+    #[prost(string, tag = "1")]
+    pub rate: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub net: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub vat: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub gross: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CurrencyInfo {
+    #[prost(string, tag = "1")]
+    pub exchange_rate: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub rate_date: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub rate_table: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "4")]
+    pub total_net_pln: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub total_vat_pln: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub total_gross_pln: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExtractionMetadata {
+    #[prost(float, tag = "1")]
+    pub confidence: f32,
+    /// text_pdf, image_pdf, hybrid_pdf, image, scanned_with_layout, ksef_xml,
+    /// facturx_xml or unknown
+    #[prost(string, tag = "2")]
+    pub source_type: ::prost::alloc::string::String,
+    #[prost(uint64, optional, tag = "3")]
+    pub processing_time_ms: ::core::option::Option<u64>,
+    #[prost(string, optional, tag = "4")]
+    pub ocr_engine: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "5")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "6")]
+    pub missing_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "7")]
+    pub corrections: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(map = "string, float", tag = "8")]
+    pub field_confidence: ::std::collections::HashMap<::prost::alloc::string::String, f32>,
+    /// Some pages could not be processed.
+    #[prost(bool, tag = "9")]
+    pub incomplete: bool,
+    /// Pages and region the extraction was limited to.
+    #[prost(message, optional, tag = "10")]
+    pub selection: ::core::option::Option<Selection>,
+    /// Fields whose value was read from handwritten text.
+    #[prost(string, repeated, tag = "11")]
+    pub handwritten_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Pipeline stages that ran, and why others were skipped.
+    #[prost(message, repeated, tag = "12")]
+    pub capabilities: ::prost::alloc::vec::Vec<StageStatus>,
+    /// White list of VAT taxpayers lookups of the parties.
+    #[prost(message, repeated, tag = "13")]
+    pub whitelist: ::prost::alloc::vec::Vec<WhitelistCheck>,
+    /// Warnings with their codes; `warnings` repeats their messages.
+    #[prost(message, repeated, tag = "14")]
+    pub issues: ::prost::alloc::vec::Vec<Issue>,
+    /// Page (from 1) each field was read from, for invoices spanning pages.
+    #[prost(map = "string, uint32", tag = "15")]
+    pub field_pages: ::std::collections::HashMap<::prost::alloc::string::String, u32>,
+    /// QR codes and barcodes decoded from the page images.
+    #[prost(message, repeated, tag = "16")]
+    pub barcodes: ::prost::alloc::vec::Vec<Barcode>,    /// VIES lookups of the parties' EU VAT IDs.
+    #[prost(message, repeated, tag = "17")]
+    pub vies: ::prost::alloc::vec::Vec<ViesCheck>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Issue {
+    /// missing_issue_date, invalid_nip, vat_total_mismatch, ... or other
+    #[prost(string, tag = "1")]
+    pub code: ::prost::alloc::string::String,
+    /// warning or error
+    #[prost(string, tag = "2")]
+    pub severity: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub field: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WhitelistCheck {
+    /// issuer or receiver
+    #[prost(string, tag = "1")]
+    pub party: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub nip: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub date: ::prost::alloc::string::String,
+    /// Not set if the NIP is not registered.
+    #[prost(string, optional, tag = "4")]
+    pub vat_status: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "5")]
+    pub name: ::core::option::Option<::prost::alloc::string::String>,
+    /// Not set if no bank account was checked.
+    #[prost(bool, optional, tag = "6")]
+    pub account_assigned: ::core::option::Option<bool>,
+    #[prost(string, tag = "7")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "8")]
+    pub account_request_id: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ViesCheck {
+    /// issuer or receiver
+    #[prost(string, tag = "1")]
+    pub party: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub vat_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub date: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub valid: bool,
+    #[prost(string, optional, tag = "5")]
+    pub name: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "6")]
+    pub address: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Barcode {
+    /// qr_code, data_matrix, code_128, ...
+    #[prost(string, tag = "1")]
+    pub format: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub payload: ::prost::alloc::string::String,
+    /// transfer or ksef; not set for other content.
+    #[prost(string, optional, tag = "3")]
+    pub kind: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag = "4")]
+    pub page: ::core::option::Option<u32>,
+    /// Fields the code confirms or supplied.
+    #[prost(string, repeated, tag = "5")]
+    pub confirmed_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StageStatus {
+    /// text_layer, deskew, orientation, detection, classification,
+    /// recognition, upscaling, super_resolution, second_pass, handwriting,
+    /// layout, barcodes or tables
+    #[prost(string, tag = "1")]
+    pub stage: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub ran: bool,
+    #[prost(string, optional, tag = "3")]
+    pub reason: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Selection {
+    /// Processed pages; all pages when empty.
+    #[prost(uint32, repeated, tag = "1")]
+    pub pages: ::prost::alloc::vec::Vec<u32>,
+    /// Area of each page image that was recognized, in pixels.
+    #[prost(message, optional, tag = "2")]
+    pub region: ::core::option::Option<Region>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Region {
+    #[prost(uint32, tag = "1")]
+    pub x: u32,
+    #[prost(uint32, tag = "2")]
+    pub y: u32,
+    #[prost(uint32, tag = "3")]
+    pub width: u32,
+    #[prost(uint32, tag = "4")]
+    pub height: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TextBox {
+    /// Quadrilateral corners (x1, y1, x2, y2, x3, y3, x4, y4).
+    #[prost(float, repeated, tag = "1")]
+    pub bbox: ::prost::alloc::vec::Vec<f32>,
+    #[prost(string, tag = "2")]
+    pub text: ::prost::alloc::string::String,
+    #[prost(float, tag = "3")]
+    pub detection_score: f32,
+    #[prost(float, tag = "4")]
+    pub recognition_score: f32,
+    #[prost(int32, tag = "5")]
+    pub angle: i32,
+    /// Read by the handwriting recognition model.
+    #[prost(bool, tag = "6")]
+    pub handwritten: bool,
+}
+/// Layout regions are not included.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OcrResult {
+    #[prost(message, repeated, tag = "1")]
+    pub boxes: ::prost::alloc::vec::Vec<TextBox>,
+    #[prost(string, tag = "2")]
+    pub text: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub processing_time_ms: u64,
+    #[prost(uint32, tag = "4")]
+    pub image_width: u32,
+    #[prost(uint32, tag = "5")]
+    pub image_height: u32,
+}
@@ -0,0 +1,861 @@
+//! Protobuf encoding of invoices and OCR results.
+//!
+//! The message types are generated by prost from `proto/incr.proto`
+//! (package `incr.v1`) and convert to and from the models with
+//! [`From`]/[`TryFrom`]. Amounts and dates are carried as strings, so an
+//! invoice survives an encode/decode roundtrip unchanged.
+
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use prost::Message;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ProtoError;
+use crate::models::capabilities::Capabilities;
+use crate::models::invoice::{self as model, PaymentMethod, VatRate};
+use crate::models::validation::{IssueCode, ValidationIssue};
+use crate::models::selection;
+
+include!("incr.v1.rs");
+
+/// Encode an invoice as a protobuf `incr.v1.Invoice` message.
+pub fn encode_invoice(invoice: &model::Invoice) -> Vec<u8> {
+    Invoice::from(invoice).encode_to_vec()
+}
+
+/// Decode an invoice from a protobuf `incr.v1.Invoice` message.
+pub fn decode_invoice(bytes: &[u8]) -> Result<model::Invoice, ProtoError> {
+    Invoice::decode(bytes)?.try_into()
+}
+
+impl From<&model::Invoice> for Invoice {
+    fn from(invoice: &model::Invoice) -> Self {
+        Self {
+            header: Some((&invoice.header).into()),
+            issuer: Some((&invoice.issuer).into()),
+            receiver: Some((&invoice.receiver).into()),
+            line_items: invoice.line_items.iter().map(Into::into).collect(),
+            summary: Some((&invoice.summary).into()),
+            metadata: Some((&invoice.metadata).into()),
+        }
+    }
+}
+
+impl TryFrom<Invoice> for model::Invoice {
+    type Error = ProtoError;
+
+    fn try_from(invoice: Invoice) -> Result<Self, Self::Error> {
+        Ok(Self {
+            header: invoice.header.unwrap_or_default().try_into()?,
+            issuer: invoice.issuer.unwrap_or_default().into(),
+            receiver: invoice.receiver.unwrap_or_default().into(),
+            line_items: invoice
+                .line_items
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            summary: invoice.summary.unwrap_or_default().try_into()?,
+            metadata: invoice.metadata.unwrap_or_default().try_into()?,
+        })
+    }
+}
+
+impl From<&model::InvoiceHeader> for InvoiceHeader {
+    fn from(header: &model::InvoiceHeader) -> Self {
+        Self {
+            invoice_number: header.invoice_number.clone(),
+            issue_date: header.issue_date.map(|d| d.to_string()),
+            sale_date: header.sale_date.map(|d| d.to_string()),
+            due_date: header.due_date.map(|d| d.to_string()),
+            invoice_type: enum_name(&header.invoice_type),
+            currency: header.currency.clone(),
+            correction_of: header.correction_of.clone(),
+            self_invoice: header.self_invoice,
+            correction: header.correction.as_ref().map(Into::into),
+            ksef_number: header.ksef_number.clone(),
+        }
+    }
+}
+
+impl TryFrom<InvoiceHeader> for model::InvoiceHeader {
+    type Error = ProtoError;
+
+    fn try_from(header: InvoiceHeader) -> Result<Self, Self::Error> {
+        Ok(Self {
+            invoice_number: header.invoice_number,
+            issue_date: header.issue_date.as_deref().map(|d| parse_date("issue_date", d)).transpose()?,
+            sale_date: header.sale_date.as_deref().map(|d| parse_date("sale_date", d)).transpose()?,
+            due_date: header.due_date.as_deref().map(|d| parse_date("due_date", d)).transpose()?,
+            invoice_type: parse_enum("invoice_type", &header.invoice_type)?,
+            currency: header.currency,
+            correction_of: header.correction_of,
+            correction: header.correction.map(TryInto::try_into).transpose()?,
+            self_invoice: header.self_invoice,
+            ksef_number: header.ksef_number,
+        })
+    }
+}
+
+impl From<&model::CorrectionDetails> for CorrectionDetails {
+    fn from(correction: &model::CorrectionDetails) -> Self {
+        Self {
+            corrected_issue_date: correction.corrected_issue_date.map(|d| d.to_string()),
+            reason: correction.reason.clone(),
+            before: correction.before.as_ref().map(Into::into),
+            after: correction.after.as_ref().map(Into::into),
+            difference: correction.difference.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<CorrectionDetails> for model::CorrectionDetails {
+    type Error = ProtoError;
+
+    fn try_from(correction: CorrectionDetails) -> Result<Self, Self::Error> {
+        Ok(Self {
+            corrected_issue_date: correction
+                .corrected_issue_date
+                .as_deref()
+                .map(|d| parse_date("correction.corrected_issue_date", d))
+                .transpose()?,
+            reason: correction.reason,
+            before: correction.before.map(TryInto::try_into).transpose()?,
+            after: correction.after.map(TryInto::try_into).transpose()?,
+            difference: correction.difference.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<&model::CorrectionAmounts> for CorrectionAmounts {
+    fn from(amounts: &model::CorrectionAmounts) -> Self {
+        Self {
+            net: amounts.net.map(|a| a.to_string()),
+            vat: amounts.vat.map(|a| a.to_string()),
+            gross: amounts.gross.to_string(),
+        }
+    }
+}
+
+impl TryFrom<CorrectionAmounts> for model::CorrectionAmounts {
+    type Error = ProtoError;
+
+    fn try_from(amounts: CorrectionAmounts) -> Result<Self, Self::Error> {
+        let amount = |field: &str, value: Option<String>| {
+            value.as_deref().map(|v| parse_decimal(field, v)).transpose()
+        };
+        Ok(Self {
+            net: amount("correction.net", amounts.net)?,
+            vat: amount("correction.vat", amounts.vat)?,
+            gross: parse_decimal("correction.gross", &amounts.gross)?,
+        })
+    }
+}
+
+impl From<&model::Party> for Party {
+    fn from(party: &model::Party) -> Self {
+        Self {
+            name: party.name.clone(),
+            nip: party.nip.clone(),
+            vat_id: party.vat_id.clone(),
+            regon: party.regon.clone(),
+            pesel: party.pesel.clone(),
+            krs: party.krs.clone(),
+            address: Some((&party.address).into()),
+            bank_account: party.bank_account.clone(),
+            bank_name: party.bank_name.clone(),
+            email: party.email.clone(),
+            additional_emails: party.additional_emails.clone(),
+            phone: party.phone.clone(),
+            additional_phones: party.additional_phones.clone(),
+            website: party.website.clone(),
+        }
+    }
+}
+
+impl From<Party> for model::Party {
+    fn from(party: Party) -> Self {
+        Self {
+            name: party.name,
+            nip: party.nip,
+            vat_id: party.vat_id,
+            regon: party.regon,
+            pesel: party.pesel,
+            krs: party.krs,
+            address: party.address.unwrap_or_default().into(),
+            bank_account: party.bank_account,
+            bank_name: party.bank_name,
+            email: party.email,
+            additional_emails: party.additional_emails,
+            phone: party.phone,
+            additional_phones: party.additional_phones,
+            website: party.website,
+        }
+    }
+}
+
+impl From<&model::Address> for Address {
+    fn from(address: &model::Address) -> Self {
+        Self {
+            street: address.street.clone(),
+            postal_code: address.postal_code.clone(),
+            city: address.city.clone(),
+            country: address.country.clone(),
+            raw: address.raw.clone(),
+        }
+    }
+}
+
+impl From<Address> for model::Address {
+    fn from(address: Address) -> Self {
+        Self {
+            street: address.street,
+            postal_code: address.postal_code,
+            city: address.city,
+            country: address.country,
+            raw: address.raw,
+        }
+    }
+}
+
+impl From<&model::LineItem> for LineItem {
+    fn from(item: &model::LineItem) -> Self {
+        Self {
+            ordinal: item.ordinal,
+            description: item.description.clone(),
+            code: item.code.clone(),
+            quantity: item.quantity.to_string(),
+            unit: item.unit.clone(),
+            unit_price_net: item.unit_price_net.to_string(),
+            unit_price_gross: item.unit_price_gross.map(|p| p.to_string()),
+            vat_rate: vat_rate_name(item.vat_rate),
+            total_net: item.total_net.to_string(),
+            vat_amount: item.vat_amount.to_string(),
+            total_gross: item.total_gross.to_string(),
+            discount_percent: item.discount_percent.map(|d| d.to_string()),
+            gtu_codes: item.gtu_codes.clone(),
+        }
+    }
+}
+
+impl TryFrom<LineItem> for model::LineItem {
+    type Error = ProtoError;
+
+    fn try_from(item: LineItem) -> Result<Self, Self::Error> {
+        Ok(Self {
+            ordinal: item.ordinal,
+            description: item.description,
+            code: item.code,
+            quantity: parse_decimal("quantity", &item.quantity)?,
+            unit: item.unit,
+            unit_price_net: parse_decimal("unit_price_net", &item.unit_price_net)?,
+            unit_price_gross: item
+                .unit_price_gross
+                .as_deref()
+                .map(|p| parse_decimal("unit_price_gross", p))
+                .transpose()?,
+            vat_rate: parse_vat_rate("vat_rate", &item.vat_rate)?,
+            total_net: parse_decimal("total_net", &item.total_net)?,
+            vat_amount: parse_decimal("vat_amount", &item.vat_amount)?,
+            total_gross: parse_decimal("total_gross", &item.total_gross)?,
+            discount_percent: item
+                .discount_percent
+                .as_deref()
+                .map(|d| parse_decimal("discount_percent", d))
+                .transpose()?,
+            gtu_codes: item.gtu_codes,
+        })
+    }
+}
+
+impl From<&model::InvoiceSummary> for InvoiceSummary {
+    fn from(summary: &model::InvoiceSummary) -> Self {
+        Self {
+            total_net: summary.total_net.to_string(),
+            total_vat: summary.total_vat.to_string(),
+            total_gross: summary.total_gross.to_string(),
+            vat_breakdown: summary.vat_breakdown.iter().map(Into::into).collect(),
+            payment_method: summary.payment_method.as_ref().map(payment_method_name),
+            amount_paid: summary.amount_paid.map(|a| a.to_string()),
+            amount_due: summary.amount_due.map(|a| a.to_string()),
+            amount_in_words: summary.amount_in_words.clone(),
+            currency_info: summary.currency_info.as_ref().map(Into::into),
+            split_payment: summary.split_payment,
+        }
+    }
+}
+
+impl TryFrom<InvoiceSummary> for model::InvoiceSummary {
+    type Error = ProtoError;
+
+    fn try_from(summary: InvoiceSummary) -> Result<Self, Self::Error> {
+        Ok(Self {
+            total_net: parse_decimal("total_net", &summary.total_net)?,
+            total_vat: parse_decimal("total_vat", &summary.total_vat)?,
+            total_gross: parse_decimal("total_gross", &summary.total_gross)?,
+            vat_breakdown: summary
+                .vat_breakdown
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            payment_method: summary.payment_method.map(parse_payment_method),
+            amount_paid: summary
+                .amount_paid
+                .as_deref()
+                .map(|a| parse_decimal("amount_paid", a))
+                .transpose()?,
+            amount_due: summary
+                .amount_due
+                .as_deref()
+                .map(|a| parse_decimal("amount_due", a))
+                .transpose()?,
+            amount_in_words: summary.amount_in_words,
+            currency_info: summary.currency_info.map(TryInto::try_into).transpose()?,
+            split_payment: summary.split_payment,
+        })
+    }
+}
+
+impl From<&model::CurrencyInfo> for CurrencyInfo {
+    fn from(info: &model::CurrencyInfo) -> Self {
+        Self {
+            exchange_rate: info.exchange_rate.to_string(),
+            rate_date: info.rate_date.map(|d| d.to_string()),
+            rate_table: info.rate_table.clone(),
+            total_net_pln: info.total_net_pln.to_string(),
+            total_vat_pln: info.total_vat_pln.to_string(),
+            total_gross_pln: info.total_gross_pln.to_string(),
+        }
+    }
+}
+
+impl TryFrom<CurrencyInfo> for model::CurrencyInfo {
+    type Error = ProtoError;
+
+    fn try_from(info: CurrencyInfo) -> Result<Self, Self::Error> {
+        Ok(Self {
+            exchange_rate: parse_decimal("currency_info.exchange_rate", &info.exchange_rate)?,
+            rate_date: info
+                .rate_date
+                .as_deref()
+                .map(|d| parse_date("currency_info.rate_date", d))
+                .transpose()?,
+            rate_table: info.rate_table,
+            total_net_pln: parse_decimal("currency_info.total_net_pln", &info.total_net_pln)?,
+            total_vat_pln: parse_decimal("currency_info.total_vat_pln", &info.total_vat_pln)?,
+            total_gross_pln: parse_decimal("currency_info.total_gross_pln", &info.total_gross_pln)?,
+        })
+    }
+}
+
+impl From<&model::VatBreakdown> for VatBreakdown {
+    fn from(breakdown: &model::VatBreakdown) -> Self {
+        Self {
+            rate: vat_rate_name(breakdown.rate),
+            net: breakdown.net.to_string(),
+            vat: breakdown.vat.to_string(),
+            gross: breakdown.gross.to_string(),
+        }
+    }
+}
+
+impl TryFrom<VatBreakdown> for model::VatBreakdown {
+    type Error = ProtoError;
+
+    fn try_from(breakdown: VatBreakdown) -> Result<Self, Self::Error> {
+        Ok(Self {
+            rate: parse_vat_rate("vat_breakdown.rate", &breakdown.rate)?,
+            net: parse_decimal("vat_breakdown.net", &breakdown.net)?,
+            vat: parse_decimal("vat_breakdown.vat", &breakdown.vat)?,
+            gross: parse_decimal("vat_breakdown.gross", &breakdown.gross)?,
+        })
+    }
+}
+
+impl From<&model::ExtractionMetadata> for ExtractionMetadata {
+    fn from(metadata: &model::ExtractionMetadata) -> Self {
+        Self {
+            confidence: metadata.confidence,
+            source_type: enum_name(&metadata.source_type),
+            processing_time_ms: metadata.processing_time_ms,
+            ocr_engine: metadata.ocr_engine.clone(),
+            warnings: metadata.warnings.iter().map(|w| w.message.clone()).collect(),
+            missing_fields: metadata.missing_fields.clone(),
+            corrections: metadata.corrections.clone(),
+            field_confidence: metadata.field_confidence.clone(),
+            incomplete: metadata.incomplete,
+            selection: metadata.selection.as_ref().map(Into::into),
+            handwritten_fields: metadata.handwritten_fields.clone(),
+            capabilities: metadata
+                .capabilities
+                .iter()
+                .map(|(stage, status)| StageStatus {
+                    stage: stage.name().to_string(),
+                    ran: status.ran,
+                    reason: status.reason.clone(),
+                })
+                .collect(),
+            whitelist: metadata.whitelist.iter().map(Into::into).collect(),
+            issues: metadata.warnings.iter().map(Into::into).collect(),
+            field_pages: metadata.field_pages.clone(),
+            barcodes: metadata.barcodes.iter().map(Into::into).collect(),
+            vies: metadata.vies.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<ExtractionMetadata> for model::ExtractionMetadata {
+    type Error = ProtoError;
+
+    fn try_from(metadata: ExtractionMetadata) -> Result<Self, Self::Error> {
+        Ok(Self {
+            confidence: metadata.confidence,
+            source_type: parse_enum("source_type", &metadata.source_type)?,
+            processing_time_ms: metadata.processing_time_ms,
+            ocr_engine: metadata.ocr_engine,
+            warnings: warnings(metadata.warnings, metadata.issues)?,
+            missing_fields: metadata.missing_fields,
+            corrections: metadata.corrections,
+            field_confidence: metadata.field_confidence,
+            incomplete: metadata.incomplete,
+            selection: metadata.selection.map(Into::into),
+            handwritten_fields: metadata.handwritten_fields,
+            capabilities: capabilities(metadata.capabilities)?,
+            whitelist: metadata
+                .whitelist
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            field_pages: metadata.field_pages,
+            barcodes: metadata
+                .barcodes
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            vies: metadata
+                .vies
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<&ValidationIssue> for Issue {
+    fn from(issue: &ValidationIssue) -> Self {
+        Self {
+            code: issue.code.to_string(),
+            severity: enum_name(&issue.severity),
+            field: issue.field.clone(),
+            message: issue.message.clone(),
+        }
+    }
+}
+
+impl TryFrom<Issue> for ValidationIssue {
+    type Error = ProtoError;
+
+    fn try_from(issue: Issue) -> Result<Self, Self::Error> {
+        Ok(Self {
+            code: parse_enum("issues.code", &issue.code)?,
+            severity: parse_enum("issues.severity", &issue.severity)?,
+            field: issue.field,
+            message: issue.message,
+        })
+    }
+}
+
+/// Warnings from their coded `issues`, or from the plain `warnings`
+/// messages written before issues had codes.
+fn warnings(messages: Vec<String>, issues: Vec<Issue>) -> Result<Vec<ValidationIssue>, ProtoError> {
+    if issues.is_empty() {
+        return Ok(messages
+            .into_iter()
+            .map(|message| ValidationIssue::warning(IssueCode::Other, "", message))
+            .collect());
+    }
+    issues.into_iter().map(TryInto::try_into).collect()
+}
+
+impl From<&model::WhitelistCheck> for WhitelistCheck {
+    fn from(check: &model::WhitelistCheck) -> Self {
+        Self {
+            party: check.party.clone(),
+            nip: check.nip.clone(),
+            date: check.date.to_string(),
+            vat_status: check.vat_status.clone(),
+            name: check.name.clone(),
+            account_assigned: check.account_assigned,
+            request_id: check.request_id.clone(),
+            account_request_id: check.account_request_id.clone(),
+        }
+    }
+}
+
+impl TryFrom<WhitelistCheck> for model::WhitelistCheck {
+    type Error = ProtoError;
+
+    fn try_from(check: WhitelistCheck) -> Result<Self, Self::Error> {
+        Ok(Self {
+            party: check.party,
+            nip: check.nip,
+            date: parse_date("whitelist.date", &check.date)?,
+            vat_status: check.vat_status,
+            name: check.name,
+            account_assigned: check.account_assigned,
+            request_id: check.request_id,
+            account_request_id: check.account_request_id,
+        })
+    }
+}
+
+impl From<&model::ViesCheck> for ViesCheck {
+    fn from(check: &model::ViesCheck) -> Self {
+        Self {
+            party: check.party.clone(),
+            vat_id: check.vat_id.clone(),
+            date: check.date.to_string(),
+            valid: check.valid,
+            name: check.name.clone(),
+            address: check.address.clone(),
+        }
+    }
+}
+
+impl TryFrom<ViesCheck> for model::ViesCheck {
+    type Error = ProtoError;
+
+    fn try_from(check: ViesCheck) -> Result<Self, Self::Error> {
+        Ok(Self {
+            party: check.party,
+            vat_id: check.vat_id,
+            date: parse_date("vies.date", &check.date)?,
+            valid: check.valid,
+            name: check.name,
+            address: check.address,
+        })
+    }
+}
+
+impl From<&model::DecodedBarcode> for Barcode {
+    fn from(barcode: &model::DecodedBarcode) -> Self {
+        Self {
+            format: barcode.format.clone(),
+            payload: barcode.payload.clone(),
+            kind: barcode.kind.as_ref().map(enum_name),
+            page: barcode.page,
+            confirmed_fields: barcode.confirmed_fields.clone(),
+        }
+    }
+}
+
+impl TryFrom<Barcode> for model::DecodedBarcode {
+    type Error = ProtoError;
+
+    fn try_from(barcode: Barcode) -> Result<Self, Self::Error> {
+        Ok(Self {
+            format: barcode.format,
+            payload: barcode.payload,
+            kind: barcode
+                .kind
+                .map(|kind| parse_enum("barcodes.kind", &kind))
+                .transpose()?,
+            page: barcode.page,
+            confirmed_fields: barcode.confirmed_fields,
+        })
+    }
+}
+
+impl From<&selection::Selection> for Selection {
+    fn from(selection: &selection::Selection) -> Self {
+        Self {
+            pages: selection
+                .pages
+                .as_ref()
+                .map(|pages| pages.pages().to_vec())
+                .unwrap_or_default(),
+            region: selection.region.map(|region| Region {
+                x: region.x,
+                y: region.y,
+                width: region.width,
+                height: region.height,
+            }),
+        }
+    }
+}
+
+impl From<Selection> for selection::Selection {
+    fn from(selection: Selection) -> Self {
+        Self {
+            pages: (!selection.pages.is_empty()).then(|| selection.pages.into()),
+            region: selection.region.map(|region| selection::Region {
+                x: region.x,
+                y: region.y,
+                width: region.width,
+                height: region.height,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "pipeline")]
+impl From<&crate::ocr::OcrResult> for OcrResult {
+    fn from(result: &crate::ocr::OcrResult) -> Self {
+        Self {
+            boxes: result
+                .boxes
+                .iter()
+                .map(|b| TextBox {
+                    bbox: b.bbox.to_vec(),
+                    text: b.text.clone(),
+                    detection_score: b.detection_score,
+                    recognition_score: b.recognition_score,
+                    angle: b.angle,
+                    handwritten: b.handwritten,
+                })
+                .collect(),
+            text: result.text.clone(),
+            processing_time_ms: result.processing_time_ms,
+            image_width: result.image_size.0,
+            image_height: result.image_size.1,
+        }
+    }
+}
+
+#[cfg(feature = "pipeline")]
+impl TryFrom<OcrResult> for crate::ocr::OcrResult {
+    type Error = ProtoError;
+
+    fn try_from(result: OcrResult) -> Result<Self, Self::Error> {
+        let boxes = result
+            .boxes
+            .into_iter()
+            .map(|b| {
+                let bbox = <[f32; 8]>::try_from(b.bbox.as_slice())
+                    .map_err(|_| invalid("bbox", format!("{} coordinates", b.bbox.len())))?;
+                Ok(crate::ocr::TextBox {
+                    bbox,
+                    text: b.text,
+                    detection_score: b.detection_score,
+                    recognition_score: b.recognition_score,
+                    angle: b.angle,
+                    handwritten: b.handwritten,
+                })
+            })
+            .collect::<Result<_, ProtoError>>()?;
+
+        Ok(Self {
+            boxes,
+            text: result.text,
+            processing_time_ms: result.processing_time_ms,
+            image_size: (result.image_width, result.image_height),
+            layout: None,
+            capabilities: Default::default(),
+            barcodes: Vec::new(),
+        })
+    }
+}
+
+/// JSON name of a unit enum variant.
+fn enum_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+fn capabilities(stages: Vec<StageStatus>) -> Result<Capabilities, ProtoError> {
+    let mut capabilities = Capabilities::new();
+    for status in stages {
+        let stage = parse_enum("capabilities.stage", &status.stage)?;
+        if status.ran {
+            capabilities.ran(stage);
+        } else {
+            capabilities.skipped(stage, status.reason.unwrap_or_default());
+        }
+    }
+    Ok(capabilities)
+}
+
+fn parse_enum<T: DeserializeOwned>(field: &str, name: &str) -> Result<T, ProtoError> {
+    serde_json::from_value(Value::String(name.to_string())).map_err(|_| invalid(field, name))
+}
+
+/// JSON name of a VAT rate ("23", "zw", ...), also for custom rates.
+fn vat_rate_name(rate: VatRate) -> String {
+    match rate {
+        VatRate::Other(rate) => rate.to_string(),
+        rate => enum_name(&rate),
+    }
+}
+
+fn parse_vat_rate(field: &str, name: &str) -> Result<VatRate, ProtoError> {
+    VatRate::from_str(name).ok_or_else(|| invalid(field, name))
+}
+
+fn payment_method_name(method: &PaymentMethod) -> String {
+    match method {
+        PaymentMethod::Other(description) => description.clone(),
+        method => enum_name(method),
+    }
+}
+
+fn parse_payment_method(name: String) -> PaymentMethod {
+    parse_enum("payment_method", &name).unwrap_or(PaymentMethod::Other(name))
+}
+
+fn parse_decimal(field: &str, value: &str) -> Result<Decimal, ProtoError> {
+    Decimal::from_str(value).map_err(|_| invalid(field, value))
+}
+
+fn parse_date(field: &str, value: &str) -> Result<NaiveDate, ProtoError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| invalid(field, value))
+}
+
+fn invalid(field: &str, value: impl Into<String>) -> ProtoError {
+    ProtoError::InvalidField {
+        field: field.to_string(),
+        value: value.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::capabilities::Stage;
+    use crate::models::invoice::{SourceType, VatBreakdown as ModelBreakdown};
+
+    fn sample_invoice() -> model::Invoice {
+        let mut invoice = model::Invoice::new();
+        invoice.header.invoice_number = "FV/2024/01/001".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.header.ksef_number = Some("5265877635-20250826-0100001AF629-AF".to_string());
+        invoice.issuer.name = "ABC Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("5260250274".to_string());
+        invoice.issuer.address.city = Some("Warszawa".to_string());
+        invoice.receiver.vat_id = Some("DE136695976".to_string());
+        invoice.line_items.push(model::LineItem {
+            ordinal: Some(1),
+            description: "Usługa".to_string(),
+            code: None,
+            quantity: Decimal::new(15, 1),
+            unit: Some("h".to_string()),
+            unit_price_net: Decimal::new(10000, 2),
+            unit_price_gross: None,
+            vat_rate: VatRate::Other(12),
+            total_net: Decimal::new(15000, 2),
+            vat_amount: Decimal::new(1800, 2),
+            total_gross: Decimal::new(16800, 2),
+            discount_percent: None,
+            gtu_codes: vec!["GTU_12".to_string()],
+        });
+        invoice.summary.total_gross = Decimal::new(16800, 2);
+        invoice.summary.vat_breakdown.push(ModelBreakdown {
+            rate: VatRate::Exempt,
+            net: Decimal::new(100, 0),
+            vat: Decimal::ZERO,
+            gross: Decimal::new(100, 0),
+        });
+        invoice.summary.payment_method = Some(PaymentMethod::Other("czek".to_string()));
+        invoice.summary.split_payment = true;
+        invoice.summary.currency_info = Some(model::CurrencyInfo {
+            exchange_rate: Decimal::new(43123, 4),
+            rate_date: NaiveDate::from_ymd_opt(2024, 1, 12),
+            rate_table: Some("009/A/NBP/2024".to_string()),
+            total_net_pln: Decimal::new(64685, 2),
+            total_vat_pln: Decimal::new(7762, 2),
+            total_gross_pln: Decimal::new(72447, 2),
+        });
+        invoice.metadata.source_type = SourceType::ImagePdf;
+        invoice.metadata.field_confidence.insert("header.invoice_number".to_string(), 0.9);
+        invoice.metadata.selection = Some(selection::Selection {
+            pages: Some("2-3".parse().unwrap()),
+            region: Some("0,100,800,600".parse().unwrap()),
+        });
+        invoice.metadata.handwritten_fields.push("summary.total_gross".to_string());
+        invoice.metadata.capabilities.ran(Stage::Recognition);
+        invoice.metadata.whitelist.push(model::WhitelistCheck {
+            party: "issuer".to_string(),
+            nip: "5260250274".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            vat_status: Some("Czynny".to_string()),
+            name: None,
+            account_assigned: Some(true),
+            request_id: "d6r4m-88hd0o1".to_string(),
+            account_request_id: None,
+        });
+        invoice.metadata.capabilities.skipped(Stage::Layout, "layout.onnx not installed");
+        invoice.metadata.vies.push(model::ViesCheck {
+            party: "receiver".to_string(),
+            vat_id: "DE136695976".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            valid: true,
+            name: None,
+            address: None,
+        });
+        invoice.metadata.barcodes.push(model::DecodedBarcode {
+            format: "qr_code".to_string(),
+            payload: "5260250274|PL|61109010140000071219812874|072447|ABC|FV/1/2024|||".to_string(),
+            kind: Some(model::BarcodeKind::Transfer),
+            page: Some(2),
+            confirmed_fields: vec!["issuer.nip".to_string()],
+        });
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::DueDateBeforeIssueDate,
+            "header.due_date",
+            "Due date 2024-01-10 is before issue date 2024-01-15",
+        ));
+        invoice
+    }
+
+    #[test]
+    fn test_invoice_roundtrip() {
+        let invoice = sample_invoice();
+        let decoded = decode_invoice(&encode_invoice(&invoice)).unwrap();
+
+        // The JSON form covers every field
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&invoice).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_enum_names() {
+        let message = Invoice::from(&sample_invoice());
+
+        assert_eq!(message.header.unwrap().invoice_type, "standard");
+        assert_eq!(message.line_items[0].vat_rate, "12");
+        assert_eq!(message.line_items[0].quantity, "1.5");
+        assert_eq!(message.summary.unwrap().vat_breakdown[0].rate, "zw");
+        assert_eq!(message.metadata.unwrap().source_type, "image_pdf");
+    }
+
+    #[test]
+    fn test_plain_warnings() {
+        let mut message = Invoice::from(&sample_invoice());
+        let metadata = message.metadata.as_mut().unwrap();
+        assert_eq!(metadata.issues[0].code, "due_date_before_issue_date");
+        metadata.issues.clear();
+
+        let decoded = decode_invoice(&message.encode_to_vec()).unwrap();
+        assert_eq!(decoded.metadata.warnings[0].code, IssueCode::Other);
+        assert_eq!(
+            decoded.metadata.warnings[0].message,
+            "Due date 2024-01-10 is before issue date 2024-01-15"
+        );
+    }
+
+    #[test]
+    fn test_invalid_amount() {
+        let mut message = Invoice::from(&sample_invoice());
+        message.line_items[0].total_net = "12,5".to_string();
+
+        let error = decode_invoice(&message.encode_to_vec()).unwrap_err();
+        assert!(matches!(error, ProtoError::InvalidField { ref field, .. } if field == "total_net"));
+        assert!(decode_invoice(&[0xff]).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+//! Matching extracted invoices to ERP open items.
+//!
+//! The "match" step of accounts payable: each extracted invoice is paired
+//! with the open item booked for it in the ERP, by seller NIP, invoice
+//! number, gross amount and issue date. Invoices with no good candidate
+//! are unmatched; invoices with several equally good candidates are
+//! ambiguous and need a person to decide.
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::invoice::Invoice;
+
+/// Score weights of the matching criteria; they add up to 1.
+const NIP_WEIGHT: f32 = 0.35;
+const NUMBER_WEIGHT: f32 = 0.35;
+const AMOUNT_WEIGHT: f32 = 0.2;
+const DATE_WEIGHT: f32 = 0.1;
+
+/// Share of [`NUMBER_WEIGHT`] given to a similar invoice number.
+const SIMILAR_NUMBER: f32 = 0.8;
+
+/// An open item from an ERP export, e.g. a booked but unpaid invoice.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OpenItem {
+    /// Identifier of the item in the ERP.
+    pub id: String,
+
+    /// Seller NIP.
+    pub nip: Option<String>,
+
+    /// Seller's invoice number.
+    pub invoice_number: Option<String>,
+
+    /// Gross amount.
+    pub amount: Decimal,
+
+    /// Currency code, `None` if the export has a single currency.
+    pub currency: Option<String>,
+
+    /// Invoice or booking date.
+    pub date: Option<NaiveDate>,
+}
+
+/// A matching criterion that agreed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchReason {
+    /// Same seller NIP.
+    Nip,
+    /// Same invoice number.
+    Number,
+    /// Same invoice number up to OCR confusions (`O`/`0`, `I`/`1`, ...)
+    /// or a missing prefix or suffix.
+    SimilarNumber,
+    /// Gross amount within the tolerance.
+    Amount,
+    /// Issue date within the date window.
+    Date,
+}
+
+/// An invoice paired with an open item.
+#[derive(Debug, Clone, Serialize)]
+pub struct Match {
+    /// Index of the invoice in the matched slice.
+    pub invoice: usize,
+    /// Open item ID.
+    pub item_id: String,
+    /// Match score (0.0 - 1.0).
+    pub score: f32,
+    /// Criteria that agreed.
+    pub reasons: Vec<MatchReason>,
+}
+
+/// An invoice with several equally good candidates.
+#[derive(Debug, Clone, Serialize)]
+pub struct Ambiguous {
+    /// Index of the invoice in the matched slice.
+    pub invoice: usize,
+    /// Candidates, best first.
+    pub candidates: Vec<Match>,
+}
+
+/// Result of matching invoices to open items.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MatchReport {
+    /// Invoices paired with exactly one open item.
+    pub matched: Vec<Match>,
+    /// Invoices that need a person to pick the open item.
+    pub ambiguous: Vec<Ambiguous>,
+    /// Indices of invoices with no candidate.
+    pub unmatched_invoices: Vec<usize>,
+    /// IDs of open items no invoice was matched to.
+    pub unmatched_items: Vec<String>,
+}
+
+/// Matches invoices to open items.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    amount_tolerance: Decimal,
+    date_window_days: i64,
+    min_score: f32,
+    ambiguity_margin: f32,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Matcher {
+    /// Create a matcher with default settings: amounts within 0.01, dates
+    /// within 7 days, a minimum score of 0.6 and an ambiguity margin of 0.1.
+    pub fn new() -> Self {
+        Self {
+            amount_tolerance: Decimal::new(1, 2),
+            date_window_days: 7,
+            min_score: 0.6,
+            ambiguity_margin: 0.1,
+        }
+    }
+
+    /// Set the largest gross amount difference that still matches.
+    pub fn with_amount_tolerance(mut self, tolerance: Decimal) -> Self {
+        self.amount_tolerance = tolerance;
+        self
+    }
+
+    /// Set how many days apart the issue date and the item date may be.
+    pub fn with_date_window(mut self, days: i64) -> Self {
+        self.date_window_days = days;
+        self
+    }
+
+    /// Set the score below which a candidate is ignored.
+    pub fn with_min_score(mut self, score: f32) -> Self {
+        self.min_score = score;
+        self
+    }
+
+    /// Set how close the two best candidates' scores must be for the
+    /// invoice to be ambiguous.
+    pub fn with_ambiguity_margin(mut self, margin: f32) -> Self {
+        self.ambiguity_margin = margin;
+        self
+    }
+
+    /// Score an invoice against an open item.
+    ///
+    /// Returns `None` if they can't belong together: different NIPs or
+    /// different currencies.
+    pub fn score(&self, invoice: &Invoice, item: &OpenItem) -> Option<(f32, Vec<MatchReason>)> {
+        let mut score = 0.0;
+        let mut reasons = Vec::new();
+
+        if let (Some(nip), Some(item_nip)) = (&invoice.issuer.nip, &item.nip) {
+            if digits(nip) != digits(item_nip) {
+                return None;
+            }
+            score += NIP_WEIGHT;
+            reasons.push(MatchReason::Nip);
+        }
+
+        let currency = &invoice.header.currency;
+        if item.currency.as_ref().is_some_and(|c| !c.eq_ignore_ascii_case(currency)) {
+            return None;
+        }
+
+        let number = item.invoice_number.as_deref();
+        match number.and_then(|n| compare_numbers(&invoice.header.invoice_number, n)) {
+            Some(MatchReason::Number) => {
+                score += NUMBER_WEIGHT;
+                reasons.push(MatchReason::Number);
+            }
+            Some(reason) => {
+                score += NUMBER_WEIGHT * SIMILAR_NUMBER;
+                reasons.push(reason);
+            }
+            None => {}
+        }
+
+        if (invoice.summary.total_gross - item.amount).abs() <= self.amount_tolerance {
+            score += AMOUNT_WEIGHT;
+            reasons.push(MatchReason::Amount);
+        }
+
+        let days_apart = match (invoice.header.issue_date, item.date) {
+            (Some(issued), Some(date)) => Some((issued - date).num_days().abs()),
+            _ => None,
+        };
+        if days_apart.is_some_and(|days| days <= self.date_window_days) {
+            score += DATE_WEIGHT;
+            reasons.push(MatchReason::Date);
+        }
+
+        Some((score, reasons))
+    }
+
+    /// Match invoices to open items.
+    ///
+    /// Each open item is given to at most one invoice. When several
+    /// invoices want the same item, the clearly best one gets it and the
+    /// others become ambiguous.
+    pub fn match_invoices(&self, invoices: &[Invoice], items: &[OpenItem]) -> MatchReport {
+        let mut report = MatchReport::default();
+        let mut best: Vec<Match> = Vec::new();
+
+        for (index, invoice) in invoices.iter().enumerate() {
+            let mut candidates: Vec<Match> = items
+                .iter()
+                .filter_map(|item| {
+                    let (score, reasons) = self.score(invoice, item)?;
+                    (score >= self.min_score).then(|| Match {
+                        invoice: index,
+                        item_id: item.id.clone(),
+                        score,
+                        reasons,
+                    })
+                })
+                .collect();
+            candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+            match candidates.as_slice() {
+                [] => report.unmatched_invoices.push(index),
+                [first, second, ..] if first.score - second.score < self.ambiguity_margin => {
+                    report.ambiguous.push(Ambiguous {
+                        invoice: index,
+                        candidates,
+                    });
+                }
+                [first, ..] => best.push(first.clone()),
+            }
+        }
+
+        // Resolve invoices competing for the same item
+        best.sort_by(|a, b| b.score.total_cmp(&a.score));
+        for (i, candidate) in best.iter().enumerate() {
+            let rivals: Vec<&Match> = best
+                .iter()
+                .filter(|other| other.item_id == candidate.item_id)
+                .collect();
+
+            let top = rivals[0];
+            let clear = rivals
+                .get(1)
+                .is_none_or(|second| top.score - second.score >= self.ambiguity_margin);
+
+            if clear && std::ptr::eq(top, &best[i]) {
+                report.matched.push(candidate.clone());
+            } else {
+                report.ambiguous.push(Ambiguous {
+                    invoice: candidate.invoice,
+                    candidates: vec![candidate.clone()],
+                });
+            }
+        }
+
+        report.matched.sort_by_key(|m| m.invoice);
+        report.ambiguous.sort_by_key(|a| a.invoice);
+        report.unmatched_items = items
+            .iter()
+            .filter(|item| !report.matched.iter().any(|m| m.item_id == item.id))
+            .map(|item| item.id.clone())
+            .collect();
+
+        report
+    }
+}
+
+fn digits(value: &str) -> String {
+    value.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Compare two invoice numbers, ignoring case and punctuation.
+///
+/// Returns [`MatchReason::Number`] for equal numbers and
+/// [`MatchReason::SimilarNumber`] for numbers equal up to OCR confusions,
+/// or where one is the other without a prefix or suffix part
+/// (`12/2024` and `FV/12/2024`).
+fn compare_numbers(a: &str, b: &str) -> Option<MatchReason> {
+    let (a, b) = (number_parts(a), number_parts(b));
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    if a.concat() == b.concat() {
+        return Some(MatchReason::Number);
+    }
+
+    let confusable = |parts: &[String]| -> String {
+        parts
+            .concat()
+            .chars()
+            .map(|c| match c {
+                'O' | 'D' | 'Q' => '0',
+                'I' | 'L' => '1',
+                'Z' => '2',
+                'S' => '5',
+                'B' => '8',
+                c => c,
+            })
+            .collect()
+    };
+    let (shorter, longer) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    let affix = shorter.len() >= 2 && (longer.starts_with(shorter) || longer.ends_with(shorter));
+
+    (affix || confusable(&a) == confusable(&b)).then_some(MatchReason::SimilarNumber)
+}
+
+/// Uppercase alphanumeric parts of an invoice number.
+fn number_parts(number: &str) -> Vec<String> {
+    number
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_uppercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(number: &str, nip: &str, gross: i64, day: u32) -> Invoice {
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = number.to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 3, day);
+        invoice.issuer.nip = Some(nip.to_string());
+        invoice.summary.total_gross = Decimal::new(gross, 2);
+        invoice
+    }
+
+    fn item(id: &str, number: Option<&str>, nip: &str, amount: i64, day: u32) -> OpenItem {
+        OpenItem {
+            id: id.to_string(),
+            nip: Some(nip.to_string()),
+            invoice_number: number.map(String::from),
+            amount: Decimal::new(amount, 2),
+            currency: None,
+            date: NaiveDate::from_ymd_opt(2024, 3, day),
+        }
+    }
+
+    #[test]
+    fn test_score() {
+        let matcher = Matcher::new();
+        let inv = invoice("FV/12/2024", "526-025-02-74", 123000, 1);
+
+        let (score, reasons) = matcher.score(&inv, &item("A", Some("fv 12/2024"), "5260250274", 123000, 3)).unwrap();
+        assert!((score - 1.0).abs() < 1e-6);
+        assert_eq!(
+            reasons,
+            vec![MatchReason::Nip, MatchReason::Number, MatchReason::Amount, MatchReason::Date]
+        );
+
+        let (_, reasons) = matcher.score(&inv, &item("B", Some("FV/12/2O24"), "5260250274", 99, 30)).unwrap();
+        assert_eq!(reasons, vec![MatchReason::Nip, MatchReason::SimilarNumber]);
+
+        assert!(matcher.score(&inv, &item("C", Some("FV/12/2024"), "1234563218", 123000, 1)).is_none());
+
+        let mut euro = item("D", None, "5260250274", 123000, 1);
+        euro.currency = Some("EUR".to_string());
+        assert!(matcher.score(&inv, &euro).is_none());
+    }
+
+    #[test]
+    fn test_match_invoices() {
+        let invoices = vec![
+            invoice("FV/1/2024", "5260250274", 10000, 1),
+            invoice("FV/2/2024", "5260250274", 20000, 2),
+            invoice("FV/3/2024", "7740001454", 30000, 3),
+            invoice("X-9", "9999999999", 500, 4),
+        ];
+        let items = vec![
+            item("1", Some("FV/1/2024"), "5260250274", 10000, 1),
+            // Two identical bookings for the third invoice
+            item("3a", None, "7740001454", 30000, 3),
+            item("3b", None, "7740001454", 30000, 3),
+            item("7", Some("FV/7/2024"), "5260250274", 70000, 20),
+        ];
+
+        let report = Matcher::new().match_invoices(&invoices, &items);
+
+        assert_eq!(report.matched.len(), 1);
+        assert_eq!((report.matched[0].invoice, report.matched[0].item_id.as_str()), (0, "1"));
+        assert_eq!(report.ambiguous.len(), 1);
+        assert_eq!(report.ambiguous[0].invoice, 2);
+        assert_eq!(report.ambiguous[0].candidates.len(), 2);
+        assert_eq!(report.unmatched_invoices, vec![1, 3]);
+        assert_eq!(report.unmatched_items, vec!["3a", "3b", "7"]);
+    }
+
+    #[test]
+    fn test_competing_invoices() {
+        // A duplicate scan of the same invoice competes for one item
+        let invoices = vec![
+            invoice("FV/5/2024", "5260250274", 50000, 5),
+            invoice("FV/5/2024", "5260250274", 50000, 5),
+        ];
+        let items = vec![item("5", Some("FV/5/2024"), "5260250274", 50000, 5)];
+
+        let report = Matcher::new().match_invoices(&invoices, &items);
+        assert!(report.matched.is_empty());
+        assert_eq!(report.ambiguous.len(), 2);
+        assert_eq!(report.unmatched_items, vec!["5"]);
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        assert_eq!(compare_numbers("FV/1/2024", "fv-1-2024"), Some(MatchReason::Number));
+        assert_eq!(compare_numbers("FV 1/2024", "FV1/2024"), Some(MatchReason::Number));
+        assert_eq!(compare_numbers("FV/1O/2O24", "FV/10/2024"), Some(MatchReason::SimilarNumber));
+        assert_eq!(compare_numbers("12/2024", "FV/12/2024"), Some(MatchReason::SimilarNumber));
+        assert_eq!(compare_numbers("FV/12/2024", "FV/12/2024/K"), Some(MatchReason::SimilarNumber));
+        assert_eq!(compare_numbers("1/2024", "FV/11/2024"), None);
+        assert_eq!(compare_numbers("FV/1/2024", "FV/2/2024"), None);
+        assert_eq!(compare_numbers("", "FV/1"), None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Rendering invoices as HTML and PDF documents.
+//!
+//! Corrected or normalized invoices can be re-issued or archived in one
+//! uniform layout. HTML is produced from a [`Template`], either the
+//! built-in one or a user template; PDF uses the built-in layout.
+//!
+//! ```
+//! use incr_core::render::{render_html, Template};
+//! use incr_core::Invoice;
+//! use rust_decimal::Decimal;
+//!
+//! let mut invoice = Invoice::new();
+//! invoice.header.invoice_number = "FV/1/2024".to_string();
+//! invoice.summary.total_gross = Decimal::new(123, 0);
+//!
+//! let html = render_html(&invoice);
+//! assert!(html.contains("FV/1/2024"));
+//!
+//! let template = Template::parse("{{header.number}}: {{summary.total_gross}} {{header.currency}}")?;
+//! assert_eq!(template.render(&invoice), "FV/1/2024: 123,00 PLN");
+//! # Ok::<(), incr_core::error::RenderError>(())
+//! ```
+
+mod pdf;
+mod template;
+
+pub use pdf::render_pdf;
+pub use template::Template;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::{json, Value};
+
+use crate::models::invoice::{Invoice, InvoiceType, LineItem, Party, PaymentMethod};
+use crate::words::format_amount_in_words;
+
+/// Render an invoice as HTML with the built-in template.
+pub fn render_html(invoice: &Invoice) -> String {
+    Template::builtin().render(invoice)
+}
+
+/// Values available to templates.
+///
+/// Amounts are formatted the Polish way (`1 234,56`), dates as
+/// `YYYY-MM-DD`; missing values are `null`.
+pub(crate) fn context(invoice: &Invoice) -> Value {
+    let header = &invoice.header;
+    let summary = &invoice.summary;
+
+    json!({
+        "header": {
+            "title": title(header.invoice_type),
+            "number": header.invoice_number,
+            "issue_date": header.issue_date.map(|d| d.to_string()),
+            "sale_date": header.sale_date.map(|d| d.to_string()),
+            "due_date": header.due_date.map(|d| d.to_string()),
+            "currency": header.currency,
+            "correction_of": header.correction_of,
+            "ksef_number": header.ksef_number,
+        },
+        "issuer": party(&invoice.issuer),
+        "receiver": party(&invoice.receiver),
+        "line_items": invoice
+            .line_items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| line_item(i, item))
+            .collect::<Vec<_>>(),
+        "vat_breakdown": summary
+            .vat_breakdown
+            .iter()
+            .map(|b| json!({
+                "rate": b.rate.display(),
+                "net": format_amount(b.net),
+                "vat": format_amount(b.vat),
+                "gross": format_amount(b.gross),
+            }))
+            .collect::<Vec<_>>(),
+        "summary": {
+            "total_net": format_amount(summary.total_net),
+            "total_vat": format_amount(summary.total_vat),
+            "total_gross": format_amount(summary.total_gross),
+            "amount_in_words": amount_in_words(invoice),
+            "payment_method": summary.payment_method.as_ref().map(payment_method),
+            "amount_paid": summary.amount_paid.map(format_amount),
+            "amount_due": summary.amount_due.map(format_amount),
+        },
+    })
+}
+
+fn party(party: &Party) -> Value {
+    let address = party.address.format();
+
+    json!({
+        "name": party.name,
+        "address": (!address.is_empty()).then_some(address),
+        "nip": party.nip,
+        "vat_id": party.vat_id,
+        "regon": party.regon,
+        "bank_account": party.bank_account,
+        "bank_name": party.bank_name,
+        "email": party.email,
+        "phone": party.phone,
+    })
+}
+
+fn line_item(index: usize, item: &LineItem) -> Value {
+    json!({
+        "ordinal": item.ordinal.unwrap_or(index as u32 + 1),
+        "description": item.description,
+        "code": item.code,
+        "quantity": format_quantity(item.quantity),
+        "unit": item.unit,
+        "unit_price_net": format_amount(item.unit_price_net),
+        "vat_rate": item.vat_rate.display(),
+        "total_net": format_amount(item.total_net),
+        "vat_amount": format_amount(item.vat_amount),
+        "total_gross": format_amount(item.total_gross),
+    })
+}
+
+/// Document title for an invoice type.
+pub(crate) fn title(invoice_type: InvoiceType) -> &'static str {
+    match invoice_type {
+        InvoiceType::Standard => "Faktura VAT",
+        InvoiceType::Correction => "Faktura korygująca",
+        InvoiceType::Advance => "Faktura zaliczkowa",
+        InvoiceType::Final => "Faktura końcowa",
+        InvoiceType::Proforma => "Faktura proforma",
+        InvoiceType::Margin => "Faktura VAT marża",
+    }
+}
+
+pub(crate) fn payment_method(method: &PaymentMethod) -> String {
+    match method {
+        PaymentMethod::Transfer => "przelew".to_string(),
+        PaymentMethod::Cash => "gotówka".to_string(),
+        PaymentMethod::Card => "karta".to_string(),
+        PaymentMethod::Compensation => "kompensata".to_string(),
+        PaymentMethod::Other(other) => other.clone(),
+    }
+}
+
+/// The stated amount in words, or the gross total written out.
+pub(crate) fn amount_in_words(invoice: &Invoice) -> String {
+    invoice.summary.amount_in_words.clone().unwrap_or_else(|| {
+        format_amount_in_words(invoice.summary.total_gross, &invoice.header.currency)
+    })
+}
+
+/// Format an amount with two decimals, e.g. `1 234,56`.
+pub(crate) fn format_amount(amount: Decimal) -> String {
+    let mut amount = amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+    amount.rescale(2);
+
+    let text = amount.abs().to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, "00"));
+    let sign = if amount.is_sign_negative() && !amount.is_zero() { "-" } else { "" };
+
+    format!("{}{},{}", sign, group_thousands(whole), fraction)
+}
+
+/// Format a quantity without trailing zeros, e.g. `2,5`.
+pub(crate) fn format_quantity(quantity: Decimal) -> String {
+    quantity.normalize().to_string().replace('.', ",")
+}
+
+fn group_thousands(digits: &str) -> String {
+    let head = match digits.len() % 3 {
+        0 => 3.min(digits.len()),
+        n => n,
+    };
+    let (head, rest) = digits.split_at(head);
+
+    let mut groups = vec![head];
+    groups.extend(rest.as_bytes().chunks(3).filter_map(|g| std::str::from_utf8(g).ok()));
+    groups.join(" ")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::models::invoice::{InvoiceSummary, VatRate};
+    use chrono::NaiveDate;
+
+    pub(crate) fn sample_invoice() -> Invoice {
+        let mut invoice = Invoice::new();
+        invoice.header.invoice_number = "FV/12/2024".to_string();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 3, 1);
+        invoice.header.due_date = NaiveDate::from_ymd_opt(2024, 3, 15);
+        invoice.issuer.name = "Zakład Usług <Łódź> Sp. z o.o.".to_string();
+        invoice.issuer.nip = Some("5260250274".to_string());
+        invoice.issuer.bank_account = Some("PL61109010140000071219812874".to_string());
+        invoice.receiver.name = "Nabywca S.A.".to_string();
+        invoice.line_items.push(LineItem {
+            ordinal: None,
+            description: "Usługa serwisowa".to_string(),
+            code: None,
+            quantity: Decimal::new(25, 1),
+            unit: Some("h".to_string()),
+            unit_price_net: Decimal::new(40000, 2),
+            unit_price_gross: None,
+            vat_rate: VatRate::Standard23,
+            total_net: Decimal::new(100000, 2),
+            vat_amount: Decimal::new(23000, 2),
+            total_gross: Decimal::new(123000, 2),
+            discount_percent: None,
+            gtu_codes: Vec::new(),
+        });
+        invoice.summary = InvoiceSummary {
+            total_net: Decimal::new(100000, 2),
+            total_vat: Decimal::new(23000, 2),
+            total_gross: Decimal::new(123000, 2),
+            payment_method: Some(PaymentMethod::Transfer),
+            ..Default::default()
+        };
+        invoice
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(Decimal::new(123456, 2)), "1 234,56");
+        assert_eq!(format_amount(Decimal::new(1234567891, 1)), "123 456 789,10");
+        assert_eq!(format_amount(Decimal::new(5, 3)), "0,01");
+        assert_eq!(format_amount(Decimal::new(-100050, 2)), "-1 000,50");
+        assert_eq!(format_amount(Decimal::ZERO), "0,00");
+        assert_eq!(format_quantity(Decimal::new(2500, 3)), "2,5");
+    }
+
+    #[test]
+    fn test_context() {
+        let context = context(&sample_invoice());
+
+        assert_eq!(context["header"]["title"], "Faktura VAT");
+        assert_eq!(context["header"]["due_date"], "2024-03-15");
+        assert_eq!(context["header"]["sale_date"], Value::Null);
+        assert_eq!(context["line_items"][0]["ordinal"], 1);
+        assert_eq!(context["line_items"][0]["quantity"], "2,5");
+        assert_eq!(context["summary"]["total_gross"], "1 230,00");
+        assert_eq!(
+            context["summary"]["amount_in_words"],
+            "jeden tysiąc dwieście trzydzieści złotych 00/100"
+        );
+        assert_eq!(context["summary"]["payment_method"], "przelew");
+    }
+}
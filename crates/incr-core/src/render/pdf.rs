@@ -0,0 +1,545 @@
+//! PDF rendering with the built-in layout.
+//!
+//! Uses the standard Helvetica fonts, so no font files are embedded.
+//! Polish letters missing from WinAnsiEncoding are mapped to unused codes
+//! through the font encoding's `Differences` array.
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::error::RenderError;
+use crate::models::invoice::Invoice;
+
+use super::{amount_in_words, format_amount, format_quantity, payment_method, title};
+
+/// A4 in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// Codes given to Polish letters, as (letter, code, glyph name).
+const POLISH: [(char, u8, &str); 16] = [
+    ('Ą', 0x81, "Aogonek"),
+    ('ą', 0x83, "aogonek"),
+    ('Ć', 0x86, "Cacute"),
+    ('ć', 0x87, "cacute"),
+    ('Ę', 0x88, "Eogonek"),
+    ('ę', 0x89, "eogonek"),
+    ('Ł', 0x8B, "Lslash"),
+    ('ł', 0x8C, "lslash"),
+    ('Ń', 0x8D, "Nacute"),
+    ('ń', 0x8F, "nacute"),
+    ('Ś', 0x90, "Sacute"),
+    ('ś', 0x98, "sacute"),
+    ('Ź', 0x99, "Zacute"),
+    ('ź', 0x9B, "zacute"),
+    ('Ż', 0x9D, "Zdotaccent"),
+    ('ż', 0x9F, "zdotaccent"),
+];
+
+/// Line item table columns, as (title, width, right-aligned).
+const COLUMNS: [(&str, f32, bool); 9] = [
+    ("Lp.", 22.0, false),
+    ("Nazwa", 155.0, false),
+    ("Ilość", 38.0, true),
+    ("J.m.", 30.0, false),
+    ("Cena netto", 60.0, true),
+    ("VAT", 32.0, true),
+    ("Wartość netto", 60.0, true),
+    ("Kwota VAT", 55.0, true),
+    ("Wartość brutto", 63.0, true),
+];
+
+/// Padding inside table cells.
+const CELL_PADDING: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// Render an invoice as a PDF document with the built-in layout.
+pub fn render_pdf(invoice: &Invoice) -> Result<Vec<u8>, RenderError> {
+    let mut canvas = Canvas::new();
+    let header = &invoice.header;
+    let currency = &header.currency;
+
+    canvas.advance(16.0);
+    canvas.text(
+        MARGIN,
+        16.0,
+        Font::Bold,
+        &format!("{} {}", title(header.invoice_type), header.invoice_number),
+    );
+    if let Some(corrected) = &header.correction_of {
+        canvas.advance(14.0);
+        canvas.text(MARGIN, 10.0, Font::Regular, &format!("Korekta faktury {}", corrected));
+    }
+    if let Some(number) = &header.ksef_number {
+        canvas.advance(14.0);
+        canvas.text(MARGIN, 10.0, Font::Regular, &format!("Numer KSeF: {}", number));
+    }
+
+    canvas.advance(8.0);
+    let dates = [("Data wystawienia", header.issue_date), ("Data sprzedaży", header.sale_date)];
+    for (label, date) in dates {
+        if let Some(date) = date {
+            canvas.advance(13.0);
+            canvas.text(MARGIN, 10.0, Font::Regular, &format!("{}: {}", label, date));
+        }
+    }
+
+    canvas.advance(12.0);
+    draw_parties(&mut canvas, invoice);
+
+    canvas.advance(14.0);
+    draw_line_items(&mut canvas, invoice);
+
+    canvas.advance(10.0);
+    draw_vat_summary(&mut canvas, invoice);
+
+    let summary = &invoice.summary;
+    let to_pay = summary.amount_due.unwrap_or(summary.total_gross);
+    canvas.advance(24.0);
+    canvas.text_right(
+        PAGE_WIDTH - MARGIN,
+        12.0,
+        Font::Bold,
+        &format!("Razem do zapłaty: {} {}", format_amount(to_pay), currency),
+    );
+
+    let words = format!("Słownie: {}", amount_in_words(invoice));
+    for line in wrap(&words, CONTENT_WIDTH, 10.0, Font::Regular) {
+        canvas.advance(14.0);
+        canvas.text(MARGIN, 10.0, Font::Regular, &line);
+    }
+
+    let mut payment = Vec::new();
+    if let Some(paid) = summary.amount_paid {
+        payment.push(format!("Zapłacono: {} {}", format_amount(paid), currency));
+    }
+    if let Some(method) = &summary.payment_method {
+        payment.push(format!("Forma płatności: {}", payment_method(method)));
+    }
+    if let Some(due) = header.due_date {
+        payment.push(format!("Termin płatności: {}", due));
+    }
+    if let Some(account) = &invoice.issuer.bank_account {
+        match &invoice.issuer.bank_name {
+            Some(bank) => payment.push(format!("Numer konta: {} ({})", account, bank)),
+            None => payment.push(format!("Numer konta: {}", account)),
+        }
+    }
+
+    canvas.advance(6.0);
+    for line in payment {
+        canvas.advance(14.0);
+        canvas.text(MARGIN, 10.0, Font::Regular, &line);
+    }
+
+    write_document(canvas.pages)
+}
+
+/// Issuer and receiver side by side.
+fn draw_parties(canvas: &mut Canvas, invoice: &Invoice) {
+    let column_width = (CONTENT_WIDTH - 20.0) / 2.0;
+    let lines = |heading: &str, party: &crate::models::invoice::Party| {
+        let mut lines = vec![(heading.to_string(), Font::Bold, 9.0)];
+        for line in wrap(&party.name, column_width, 10.0, Font::Bold) {
+            lines.push((line, Font::Bold, 10.0));
+        }
+        for line in wrap(&party.address.format(), column_width, 10.0, Font::Regular) {
+            lines.push((line, Font::Regular, 10.0));
+        }
+        if let Some(nip) = &party.nip {
+            lines.push((format!("NIP: {}", nip), Font::Regular, 10.0));
+        }
+        if let Some(vat_id) = &party.vat_id {
+            lines.push((format!("VAT UE: {}", vat_id), Font::Regular, 10.0));
+        }
+        if let Some(regon) = &party.regon {
+            lines.push((format!("REGON: {}", regon), Font::Regular, 10.0));
+        }
+        lines
+    };
+
+    let issuer = lines("SPRZEDAWCA", &invoice.issuer);
+    let receiver = lines("NABYWCA", &invoice.receiver);
+    for row in 0..issuer.len().max(receiver.len()) {
+        canvas.advance(13.0);
+        let columns = [(MARGIN, &issuer), (MARGIN + column_width + 20.0, &receiver)];
+        for (x, lines) in columns {
+            if let Some((text, font, size)) = lines.get(row) {
+                canvas.text(x, *size, *font, text);
+            }
+        }
+    }
+}
+
+fn draw_line_items(canvas: &mut Canvas, invoice: &Invoice) {
+    draw_table_header(canvas);
+
+    for (i, item) in invoice.line_items.iter().enumerate() {
+        let ordinal = item.ordinal.unwrap_or(i as u32 + 1);
+        let cells = [
+            ordinal.to_string(),
+            String::new(),
+            format_quantity(item.quantity),
+            item.unit.clone().unwrap_or_default(),
+            format_amount(item.unit_price_net),
+            item.vat_rate.display(),
+            format_amount(item.total_net),
+            format_amount(item.vat_amount),
+            format_amount(item.total_gross),
+        ];
+
+        let mut description = wrap(&item.description, COLUMNS[1].1 - 2.0 * CELL_PADDING, 9.0, Font::Regular);
+        if let Some(code) = &item.code {
+            description.push(code.clone());
+        }
+        let height = description.len().max(1) as f32 * 11.0 + 4.0;
+
+        if canvas.needs_page(height) {
+            canvas.new_page();
+            draw_table_header(canvas);
+        }
+
+        canvas.advance(11.0);
+        let mut x = MARGIN;
+        for ((_, width, right), cell) in COLUMNS.iter().zip(&cells) {
+            draw_cell(canvas, x, *width, *right, 9.0, Font::Regular, cell);
+            x += width;
+        }
+        for (n, line) in description.iter().enumerate() {
+            if n > 0 {
+                canvas.advance(11.0);
+            }
+            canvas.text(MARGIN + COLUMNS[0].1 + CELL_PADDING, 9.0, Font::Regular, line);
+        }
+
+        canvas.advance(4.0);
+        canvas.rule(MARGIN, PAGE_WIDTH - MARGIN);
+    }
+}
+
+fn draw_table_header(canvas: &mut Canvas) {
+    canvas.advance(10.0);
+    let mut x = MARGIN;
+    for (title, width, right) in COLUMNS {
+        draw_cell(canvas, x, width, right, 7.5, Font::Bold, title);
+        x += width;
+    }
+    canvas.advance(4.0);
+    canvas.rule(MARGIN, PAGE_WIDTH - MARGIN);
+}
+
+/// VAT breakdown and totals, right-aligned under the line items.
+fn draw_vat_summary(canvas: &mut Canvas, invoice: &Invoice) {
+    const WIDTH: f32 = 70.0;
+    let left = PAGE_WIDTH - MARGIN - 4.0 * WIDTH;
+    let summary = &invoice.summary;
+
+    let mut rows = vec![(
+        Font::Bold,
+        ["Stawka", "Netto", "VAT", "Brutto"].map(String::from),
+    )];
+    for breakdown in &summary.vat_breakdown {
+        rows.push((
+            Font::Regular,
+            [
+                breakdown.rate.display(),
+                format_amount(breakdown.net),
+                format_amount(breakdown.vat),
+                format_amount(breakdown.gross),
+            ],
+        ));
+    }
+    rows.push((
+        Font::Bold,
+        [
+            "Razem".to_string(),
+            format_amount(summary.total_net),
+            format_amount(summary.total_vat),
+            format_amount(summary.total_gross),
+        ],
+    ));
+
+    for (font, cells) in rows {
+        canvas.advance(12.0);
+        for (i, cell) in cells.iter().enumerate() {
+            let x = left + i as f32 * WIDTH;
+            draw_cell(canvas, x, WIDTH, i > 0, 9.0, font, cell);
+        }
+        canvas.advance(3.0);
+        canvas.rule(left, PAGE_WIDTH - MARGIN);
+    }
+}
+
+fn draw_cell(canvas: &mut Canvas, x: f32, width: f32, right: bool, size: f32, font: Font, text: &str) {
+    if right {
+        canvas.text_right(x + width - CELL_PADDING, size, font, text);
+    } else {
+        canvas.text(x + CELL_PADDING, size, font, text);
+    }
+}
+
+/// Page content streams, drawn top to bottom.
+struct Canvas {
+    pages: Vec<Vec<u8>>,
+    /// Baseline of the current line.
+    y: f32,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            pages: vec![Vec::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn needs_page(&self, height: f32) -> bool {
+        self.y - height < MARGIN
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Move down by `height`, starting a new page if it doesn't fit.
+    fn advance(&mut self, height: f32) {
+        if self.needs_page(height) {
+            self.new_page();
+        }
+        self.y -= height;
+    }
+
+    fn text(&mut self, x: f32, size: f32, font: Font, text: &str) {
+        let y = self.y;
+        let content = self.content();
+        content.extend_from_slice(
+            format!("BT /{} {} Tf {:.2} {:.2} Td (", font.resource(), size, x, y).as_bytes(),
+        );
+        for byte in encode(text) {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                content.push(b'\\');
+            }
+            content.push(byte);
+        }
+        content.extend_from_slice(b") Tj ET\n");
+    }
+
+    fn text_right(&mut self, right: f32, size: f32, font: Font, text: &str) {
+        self.text(right - text_width(text, size, font), size, font, text);
+    }
+
+    /// A thin horizontal line at the current baseline.
+    fn rule(&mut self, from: f32, to: f32) {
+        let y = self.y;
+        self.content().extend_from_slice(
+            format!("0.5 w 0.7 G {:.2} {:.2} m {:.2} {:.2} l S\n", from, y, to, y).as_bytes(),
+        );
+    }
+
+    fn content(&mut self) -> &mut Vec<u8> {
+        self.pages.last_mut().expect("canvas has a page")
+    }
+}
+
+fn write_document(pages: Vec<Vec<u8>>) -> Result<Vec<u8>, RenderError> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+
+    let mut differences = Vec::new();
+    for (_, code, glyph) in POLISH {
+        differences.push(Object::Integer(code as i64));
+        differences.push(name(glyph));
+    }
+    let encoding = doc.add_object(dictionary! {
+        "Type" => name("Encoding"),
+        "BaseEncoding" => name("WinAnsiEncoding"),
+        "Differences" => differences,
+    });
+
+    let font = |doc: &mut Document, base: &str| -> ObjectId {
+        doc.add_object(dictionary! {
+            "Type" => name("Font"),
+            "Subtype" => name("Type1"),
+            "BaseFont" => name(base),
+            "Encoding" => encoding,
+        })
+    };
+    let regular = font(&mut doc, "Helvetica");
+    let bold = font(&mut doc, "Helvetica-Bold");
+    let resources = doc.add_object(dictionary! {
+        "Font" => dictionary! {
+            Font::Regular.resource() => regular,
+            Font::Bold.resource() => bold,
+        },
+    });
+
+    let mut kids = Vec::new();
+    for content in pages {
+        let content = doc.add_object(Stream::new(Dictionary::new(), content));
+        let page = doc.add_object(dictionary! {
+            "Type" => name("Page"),
+            "Parent" => pages_id,
+            "Contents" => content,
+            "Resources" => resources,
+            "MediaBox" => vec![Object::Integer(0), Object::Integer(0), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+        });
+        kids.push(Object::Reference(page));
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => name("Pages"),
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog = doc.add_object(dictionary! {
+        "Type" => name("Catalog"),
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog);
+    doc.compress();
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)
+        .map_err(|e| RenderError::Pdf(e.to_string()))?;
+    Ok(bytes)
+}
+
+fn name(name: &str) -> Object {
+    Object::Name(name.as_bytes().to_vec())
+}
+
+/// Encode text for the fonts' encoding; unsupported characters become `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            '\t' | '\n' | '\r' => b' ',
+            '€' => 0x80,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '\u{A0}'..='\u{FF}' => c as u8,
+            _ => POLISH
+                .iter()
+                .find(|(letter, _, _)| *letter == c)
+                .map_or(b'?', |(_, code, _)| *code),
+        })
+        .collect()
+}
+
+/// Approximate Helvetica text width in points.
+fn text_width(text: &str, size: f32, font: Font) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => 556,
+            ' ' | ',' | '.' | ':' | ';' | '/' | 'I' | 'f' | 't' => 278,
+            'i' | 'j' | 'l' => 222,
+            '-' | 'r' | '(' | ')' => 333,
+            '%' => 889,
+            'm' | 'M' => 833,
+            'w' => 722,
+            'W' => 944,
+            'A'..='Z' => 667,
+            _ => 556,
+        })
+        .sum();
+    let scale = match font {
+        Font::Regular => 1.0,
+        Font::Bold => 1.05,
+    };
+    units as f32 * size / 1000.0 * scale
+}
+
+/// Split text into lines that fit `width`, breaking at spaces.
+fn wrap(text: &str, width: f32, size: f32, font: Font) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if line.is_empty() || text_width(&candidate, size, font) <= width {
+            line = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::sample_invoice;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("Łódź"), vec![0x8B, 0xF3, 0x64, 0x9B]);
+        assert_eq!(encode("„x” €"), vec![0x84, b'x', 0x94, b' ', 0x80]);
+        assert_eq!(encode("日"), b"?");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let lines = wrap("Usługa serwisowa urządzeń chłodniczych", 80.0, 10.0, Font::Regular);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.join(" "), "Usługa serwisowa urządzeń chłodniczych");
+        assert!(wrap("", 80.0, 10.0, Font::Regular).is_empty());
+    }
+
+    #[test]
+    fn test_render_pdf() {
+        let bytes = render_pdf(&sample_invoice()).unwrap();
+        assert!(bytes.starts_with(b"%PDF-1.5"));
+
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 1);
+
+        let text = pdf_extract::extract_text_from_mem(&bytes).unwrap();
+        assert!(text.contains("Zakład Usług <Łódź> Sp. z o.o."));
+        assert!(text.contains("1 230,00"));
+    }
+
+    #[test]
+    fn test_render_pdf_breaks_pages() {
+        let mut invoice = sample_invoice();
+        let item = invoice.line_items[0].clone();
+        invoice.line_items = vec![item; 80];
+
+        let doc = Document::load_mem(&render_pdf(&invoice).unwrap()).unwrap();
+        assert!(doc.get_pages().len() > 1);
+    }
+}
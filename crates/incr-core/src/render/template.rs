@@ -0,0 +1,258 @@
+//! Mustache-style HTML templates.
+//!
+//! Supported tags:
+//! - `{{header.number}}`: a value, HTML-escaped
+//! - `{{#line_items}}...{{/line_items}}`: repeated for each list entry,
+//!   or rendered once if the value is present and not empty
+//! - `{{^header.due_date}}...{{/header.due_date}}`: rendered if the value
+//!   is missing or empty
+//! - `{{! comment }}`
+//!
+//! Inside a list, names are looked up in the entry first, then in the
+//! enclosing values. See [`context`](super::context) for the names.
+
+use serde_json::Value;
+
+use crate::error::RenderError;
+use crate::models::invoice::Invoice;
+
+const BUILTIN: &str = include_str!("invoice.html");
+
+/// An HTML invoice template.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Value(String),
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+}
+
+impl Template {
+    /// The built-in template: a one-page A4 invoice.
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("built-in template is valid")
+    }
+
+    /// Parse a template.
+    pub fn parse(source: &str) -> Result<Self, RenderError> {
+        // Sections being parsed, as (name, inverted, line, nodes before it)
+        let mut open: Vec<(String, bool, usize, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            let line = line_of(source, rest, start);
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+
+            let Some(end) = rest[start..].find("}}") else {
+                return Err(error(line, "unclosed tag"));
+            };
+            let tag = rest[start + 2..start + end].trim();
+            rest = &rest[start + end + 2..];
+
+            let (kind, name) = match tag.chars().next() {
+                Some(c @ ('#' | '^' | '/' | '!')) => (Some(c), tag[1..].trim()),
+                _ => (None, tag),
+            };
+            if kind != Some('!') && name.is_empty() {
+                return Err(error(line, "empty tag"));
+            }
+
+            match kind {
+                Some('!') => {}
+                Some('#' | '^') => {
+                    let outer = std::mem::take(&mut nodes);
+                    open.push((name.to_string(), kind == Some('^'), line, outer));
+                }
+                Some(_) => {
+                    let Some((section, inverted, _, outer)) = open.pop() else {
+                        return Err(error(line, &format!("unexpected {{{{/{}}}}}", name)));
+                    };
+                    if section != name {
+                        return Err(error(
+                            line,
+                            &format!("{{{{/{}}}}} closes section '{}'", name, section),
+                        ));
+                    }
+                    let children = std::mem::replace(&mut nodes, outer);
+                    nodes.push(Node::Section {
+                        name: section,
+                        inverted,
+                        children,
+                    });
+                }
+                None => nodes.push(Node::Value(name.to_string())),
+            }
+        }
+
+        if let Some((name, _, line, _)) = open.pop() {
+            return Err(error(line, &format!("section '{}' is not closed", name)));
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Render an invoice with this template.
+    pub fn render(&self, invoice: &Invoice) -> String {
+        let context = super::context(invoice);
+        let mut output = String::new();
+        render_nodes(&self.nodes, &mut vec![&context], &mut output);
+        output
+    }
+}
+
+fn render_nodes<'a>(nodes: &'a [Node], scopes: &mut Vec<&'a Value>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value(name) => match lookup(scopes, name) {
+                Some(Value::String(s)) => escape_into(s, output),
+                Some(Value::Number(n)) => output.push_str(&n.to_string()),
+                Some(Value::Bool(b)) => output.push_str(&b.to_string()),
+                _ => {}
+            },
+            Node::Section {
+                name,
+                inverted,
+                children,
+            } => {
+                let value = lookup(scopes, name);
+                if *inverted {
+                    if value.is_none_or(is_empty) {
+                        render_nodes(children, scopes, output);
+                    }
+                    continue;
+                }
+
+                match value {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            scopes.push(item);
+                            render_nodes(children, scopes, output);
+                            scopes.pop();
+                        }
+                    }
+                    Some(value) if !is_empty(value) => {
+                        scopes.push(value);
+                        render_nodes(children, scopes, output);
+                        scopes.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Find a dotted name in the innermost scope that has its first part.
+fn lookup<'a>(scopes: &[&'a Value], name: &str) -> Option<&'a Value> {
+    if name == "." {
+        return scopes.last().copied();
+    }
+
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+    let mut value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+fn escape_into(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+}
+
+/// 1-based line of `rest[offset]`, where `rest` is a suffix of `source`.
+fn line_of(source: &str, rest: &str, offset: usize) -> usize {
+    let position = source.len() - rest.len() + offset;
+    source[..position].matches('\n').count() + 1
+}
+
+fn error(line: usize, message: &str) -> RenderError {
+    RenderError::Template {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::sample_invoice;
+
+    fn render(source: &str) -> String {
+        Template::parse(source).unwrap().render(&sample_invoice())
+    }
+
+    #[test]
+    fn test_render_values_and_sections() {
+        assert_eq!(render("{{ header.title }} {{header.number}}"), "Faktura VAT FV/12/2024");
+        assert_eq!(
+            render("{{#line_items}}{{ordinal}}. {{description}} ({{header.currency}}){{/line_items}}"),
+            "1. Usługa serwisowa (PLN)"
+        );
+        assert_eq!(render("{{#issuer.nip}}NIP {{.}}{{/issuer.nip}}"), "NIP 5260250274");
+        assert_eq!(render("{{#header.sale_date}}x{{/header.sale_date}}"), "");
+        assert_eq!(render("{{^header.sale_date}}brak{{/header.sale_date}}"), "brak");
+        assert_eq!(render("{{#issuer}}{{name}}{{/issuer}}"), "Zakład Usług &lt;Łódź&gt; Sp. z o.o.");
+        assert_eq!(render("a{{! note }}b{{missing.field}}"), "ab");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let line = |source| match Template::parse(source) {
+            Err(RenderError::Template { line, .. }) => line,
+            other => panic!("expected template error, got {:?}", other),
+        };
+
+        assert_eq!(line("{{#line_items}}\n{{/issuer}}"), 2);
+        assert_eq!(line("\n\n{{#line_items}}"), 3);
+        assert_eq!(line("{{/line_items}}"), 1);
+        assert_eq!(line("ok\n{{header.number"), 2);
+        assert_eq!(line("{{}}"), 1);
+    }
+
+    #[test]
+    fn test_builtin_template() {
+        let html = Template::builtin().render(&sample_invoice());
+
+        assert!(html.contains("Faktura VAT FV/12/2024"));
+        assert!(html.contains("Zakład Usług &lt;Łódź&gt; Sp. z o.o."));
+        assert!(html.contains("1 230,00"));
+        assert!(html.contains("jeden tysiąc dwieście trzydzieści złotych 00/100"));
+        assert!(!html.contains("{{"));
+    }
+}
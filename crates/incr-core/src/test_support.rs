@@ -0,0 +1,122 @@
+//! Helpers shared by the tests of the HTTP clients.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// A request received by [`serve`].
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Path and query string
+    pub path: String,
+    /// Header names (lowercase) and values
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// The value of the header `name` (lowercase), if sent.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The answer to a request.
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: Option<&'static str>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// A `200 OK` response with `content_type`.
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self::new("200 OK", Some(content_type), body)
+    }
+
+    pub fn new(
+        status: &'static str,
+        content_type: Option<&'static str>,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+}
+
+/// The requests a server received, in order.
+pub type Requests = Arc<Mutex<Vec<Request>>>;
+
+/// Answer `requests` connections on a local port with `respond`, one
+/// request per connection. Returns the base URL (`http://127.0.0.1:port`)
+/// and the requests received.
+pub fn serve(
+    requests: usize,
+    mut respond: impl FnMut(&Request) -> Response + Send + 'static,
+) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let seen = Arc::clone(&received);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let request = read_request(&mut BufReader::new(stream.try_clone().unwrap()));
+            let response = respond(&request);
+
+            let content_type = response
+                .content_type
+                .map_or(String::new(), |content_type| {
+                    format!("Content-Type: {}\r\n", content_type)
+                });
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response.status,
+                content_type,
+                response.body.len()
+            )
+            .unwrap();
+            stream.write_all(&response.body).unwrap();
+            seen.lock().unwrap().push(request);
+        }
+    });
+
+    (url, received)
+}
+
+fn read_request(reader: &mut impl BufRead) -> Request {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let path = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        path,
+        headers,
+        body: String::new(),
+    };
+    let length = request.header("content-length").map_or(0, |l| l.parse().unwrap());
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    request.body = String::from_utf8(body).unwrap();
+    request
+}
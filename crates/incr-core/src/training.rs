@@ -0,0 +1,310 @@
+//! Training data export in PaddleOCR label format.
+//!
+//! Converts OCR text boxes into detection labels (polygons with
+//! transcripts) and recognition samples (line crops with transcripts).
+//! Transcripts can be corrected using a manually verified invoice: values
+//! that differ from the original extraction are substituted in the box
+//! they were read from before export.
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::models::invoice::{Invoice, Party};
+use crate::ocr::TextBox;
+
+/// A single text region in a PaddleOCR detection label.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetLabel {
+    /// Text content of the region.
+    pub transcription: String,
+    /// Polygon corners, clockwise from top-left.
+    pub points: [[f32; 2]; 4],
+    /// Whether the region should be ignored during training.
+    pub difficult: bool,
+}
+
+impl From<&TextBox> for DetLabel {
+    fn from(text_box: &TextBox) -> Self {
+        let b = &text_box.bbox;
+        Self {
+            transcription: text_box.text.clone(),
+            points: [[b[0], b[1]], [b[2], b[3]], [b[4], b[5]], [b[6], b[7]]],
+            difficult: false,
+        }
+    }
+}
+
+/// A text substitution derived from a corrected invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Correction {
+    /// Field path (e.g. `issuer.nip`).
+    pub field: String,
+    /// Value as originally extracted.
+    pub extracted: String,
+    /// Verified value.
+    pub corrected: String,
+}
+
+/// Format a detection label line (`image_path\t[json labels]`).
+pub fn det_label_line(image_path: &str, labels: &[DetLabel]) -> serde_json::Result<String> {
+    Ok(format!("{}\t{}", image_path, serde_json::to_string(labels)?))
+}
+
+/// Format a recognition label line (`crop_path\ttranscript`).
+pub fn rec_label_line(crop_path: &str, text: &str) -> String {
+    // Tabs and newlines would break the line-based label format
+    let text: String = text
+        .chars()
+        .map(|c| if c == '\t' || c == '\n' || c == '\r' { ' ' } else { c })
+        .collect();
+    format!("{}\t{}", crop_path, text)
+}
+
+/// Collect textual fields that differ between the extracted and corrected invoice.
+///
+/// Only fields that appear verbatim in OCR text are compared (numbers,
+/// identifiers and names); dates and amounts are normalized during
+/// extraction and cannot be mapped back to their printed form.
+pub fn diff_invoices(extracted: &Invoice, corrected: &Invoice) -> Vec<Correction> {
+    let mut corrections = Vec::new();
+
+    let mut push = |field: &str, extracted: Option<&str>, corrected: Option<&str>| {
+        match (extracted, corrected) {
+            (Some(extracted), Some(corrected))
+                if !extracted.is_empty() && !corrected.is_empty() && extracted != corrected =>
+            {
+                corrections.push(Correction {
+                    field: field.to_string(),
+                    extracted: extracted.to_string(),
+                    corrected: corrected.to_string(),
+                });
+            }
+            _ => {}
+        }
+    };
+
+    push(
+        "header.invoice_number",
+        Some(&extracted.header.invoice_number),
+        Some(&corrected.header.invoice_number),
+    );
+
+    let parties: [(&str, &Party, &Party); 2] = [
+        ("issuer", &extracted.issuer, &corrected.issuer),
+        ("receiver", &extracted.receiver, &corrected.receiver),
+    ];
+
+    for (prefix, ext, cor) in parties {
+        push(&format!("{}.name", prefix), Some(&ext.name), Some(&cor.name));
+        push(&format!("{}.nip", prefix), ext.nip.as_deref(), cor.nip.as_deref());
+        push(&format!("{}.regon", prefix), ext.regon.as_deref(), cor.regon.as_deref());
+        push(&format!("{}.pesel", prefix), ext.pesel.as_deref(), cor.pesel.as_deref());
+        push(&format!("{}.krs", prefix), ext.krs.as_deref(), cor.krs.as_deref());
+        push(
+            &format!("{}.bank_account", prefix),
+            ext.bank_account.as_deref(),
+            cor.bank_account.as_deref(),
+        );
+    }
+
+    corrections
+}
+
+/// Apply corrections to box transcripts, returning the number of boxes changed.
+///
+/// Each value is looked up in the boxes the way its extractor reads it:
+/// identifiers (NIP, REGON, PESEL, KRS, bank account) ignoring the spaces
+/// and dashes between digit groups, other fields as printed. Only the first
+/// box printing it, where the parser takes the value from, is corrected,
+/// and only where the value stands on its own, not inside a longer number
+/// or word. Separators printed within the value are kept when the corrected
+/// value has as many characters as the extracted one.
+pub fn apply_corrections(boxes: &mut [TextBox], corrections: &[Correction]) -> usize {
+    let mut changed = std::collections::BTreeSet::new();
+
+    for correction in corrections {
+        let identifier = is_identifier(&correction.field);
+        let kept = |c: &char| !identifier || !matches!(c, ' ' | '-');
+        let extracted: Vec<char> = correction.extracted.chars().filter(kept).collect();
+        if extracted.is_empty() {
+            continue;
+        }
+        // Identifiers may follow their label directly (`NIP5260250874`)
+        let joined = |c: char| if identifier { c.is_ascii_digit() } else { c.is_alphanumeric() };
+
+        let found = boxes.iter().enumerate().find_map(|(i, text_box)| {
+            let chars: Vec<(usize, char)> =
+                text_box.text.char_indices().filter(|(_, c)| kept(c)).collect();
+            let start = (0..(chars.len() + 1).saturating_sub(extracted.len())).find(|&start| {
+                let end = start + extracted.len();
+                chars[start..end].iter().map(|(_, c)| *c).eq(extracted.iter().copied())
+                    && !(start > 0 && joined(chars[start - 1].1))
+                    && !chars.get(end).is_some_and(|(_, c)| joined(*c))
+            })?;
+            Some((i, chars[start..start + extracted.len()].to_vec()))
+        });
+        let Some((i, matched)) = found else {
+            continue;
+        };
+
+        let text = &boxes[i].text;
+        let corrected: Vec<char> = correction.corrected.chars().filter(kept).collect();
+        let (first, last) = (matched[0].0, matched[matched.len() - 1]);
+        let end = last.0 + last.1.len_utf8();
+        let value = if corrected.len() == matched.len() {
+            // Character by character, keeping the separators in between
+            let mut value = String::new();
+            let mut corrected = corrected.into_iter();
+            for (offset, c) in text[first..end].char_indices() {
+                let position = first + offset;
+                if matched.iter().any(|(p, _)| *p == position) {
+                    value.extend(corrected.next());
+                } else {
+                    value.push(c);
+                }
+            }
+            value
+        } else {
+            correction.corrected.clone()
+        };
+        boxes[i].text = format!("{}{}{}", &text[..first], value, &text[end..]);
+        changed.insert(i);
+    }
+
+    changed.len()
+}
+
+/// Whether a field holds an identifier printed in digit groups.
+fn is_identifier(field: &str) -> bool {
+    matches!(
+        field.rsplit('.').next(),
+        Some("nip" | "regon" | "pesel" | "krs" | "bank_account")
+    )
+}
+
+/// Crop the axis-aligned bounding rectangle of a text box.
+///
+/// Returns `None` if the box lies outside the image or is empty.
+pub fn crop_text_box(image: &DynamicImage, text_box: &TextBox) -> Option<DynamicImage> {
+    let (x1, y1, x2, y2) = text_box.rect();
+    let (width, height) = (image.width() as f32, image.height() as f32);
+
+    let x1 = x1.clamp(0.0, width).floor();
+    let y1 = y1.clamp(0.0, height).floor();
+    let x2 = x2.clamp(0.0, width).ceil();
+    let y2 = y2.clamp(0.0, height).ceil();
+
+    if x2 - x1 < 1.0 || y2 - y1 < 1.0 {
+        return None;
+    }
+
+    Some(image.crop_imm(x1 as u32, y1 as u32, (x2 - x1) as u32, (y2 - y1) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_box(text: &str) -> TextBox {
+        TextBox {
+            bbox: [10.0, 10.0, 60.0, 10.0, 60.0, 30.0, 10.0, 30.0],
+            text: text.to_string(),
+            detection_score: 0.9,
+            recognition_score: 0.9,
+            angle: 0,
+            handwritten: false,
+        }
+    }
+
+    #[test]
+    fn test_det_label_line() {
+        let labels = vec![DetLabel::from(&text_box("NIP"))];
+        let line = det_label_line("images/a.png", &labels).unwrap();
+
+        assert_eq!(
+            line,
+            "images/a.png\t[{\"transcription\":\"NIP\",\"points\":[[10.0,10.0],[60.0,10.0],[60.0,30.0],[10.0,30.0]],\"difficult\":false}]"
+        );
+    }
+
+    #[test]
+    fn test_rec_label_line() {
+        assert_eq!(rec_label_line("crops/a_0.png", "FV\t1/2024"), "crops/a_0.png\tFV 1/2024");
+    }
+
+    #[test]
+    fn test_corrections() {
+        let mut extracted = Invoice::default();
+        extracted.issuer.nip = Some("5260250B74".to_string());
+        let mut corrected = extracted.clone();
+        corrected.issuer.nip = Some("5260250874".to_string());
+
+        let corrections = diff_invoices(&extracted, &corrected);
+        assert_eq!(corrections.len(), 1);
+        assert_eq!(corrections[0].field, "issuer.nip");
+
+        let mut boxes = vec![text_box("NIP: 5260250B74"), text_box("Sprzedawca")];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "NIP: 5260250874");
+    }
+
+    fn nip_correction(extracted: &str, corrected: &str) -> Vec<Correction> {
+        vec![Correction {
+            field: "issuer.nip".to_string(),
+            extracted: extracted.to_string(),
+            corrected: corrected.to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_corrections_printed_with_separators() {
+        let corrections = nip_correction("1234563218", "1234563280");
+
+        let mut boxes = vec![text_box("NIP: 123-456-32-18")];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "NIP: 123-456-32-80");
+
+        let mut boxes = vec![text_box("NIP 123 456 32 18 ")];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "NIP 123 456 32 80 ");
+
+        let mut boxes = vec![text_box("NIP1234563218")];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "NIP1234563280");
+    }
+
+    #[test]
+    fn test_corrections_only_the_field() {
+        let corrections = nip_correction("1234563218", "1234563280");
+
+        // The digits inside an account number, and the NIP printed again in
+        // the footer, are left alone
+        let mut boxes = vec![
+            text_box("Konto: 61 1090 1234563218 0000 0001"),
+            text_box("NIP: 123-456-32-18"),
+            text_box("Sprzedawca NIP 1234563218"),
+        ];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "Konto: 61 1090 1234563218 0000 0001");
+        assert_eq!(boxes[1].text, "NIP: 123-456-32-80");
+        assert_eq!(boxes[2].text, "Sprzedawca NIP 1234563218");
+
+        // Names and numbers are matched as printed, as whole words
+        let corrections = vec![Correction {
+            field: "header.invoice_number".to_string(),
+            extracted: "FV 1/2024".to_string(),
+            corrected: "FV 7/2024".to_string(),
+        }];
+        let mut boxes = vec![text_box("FV 1/20245"), text_box("Faktura FV 1/2024")];
+        assert_eq!(apply_corrections(&mut boxes, &corrections), 1);
+        assert_eq!(boxes[0].text, "FV 1/20245");
+        assert_eq!(boxes[1].text, "Faktura FV 7/2024");
+    }
+
+    #[test]
+    fn test_crop_text_box() {
+        let image = DynamicImage::new_rgb8(40, 40);
+        let crop = crop_text_box(&image, &text_box("x")).unwrap();
+        assert_eq!((crop.width(), crop.height()), (30, 20));
+    }
+}
@@ -0,0 +1,473 @@
+//! Checksum validation and formatting of Polish identifiers, and of the EU
+//! VAT identification numbers seen on cross-border invoices.
+//!
+//! This module has no OCR or PDF dependencies; build incr-core with
+//! `default-features = false` to use it on its own.
+//!
+//! The validators of numeric identifiers ignore separators (spaces, dashes)
+//! and accept any formatting that keeps the digits in order.
+
+use chrono::NaiveDate;
+
+/// Validate a Polish NIP using the checksum algorithm.
+///
+/// NIP format: 10 digits where the last digit is a checksum.
+/// Weights: 6, 5, 7, 2, 3, 4, 5, 6, 7
+pub fn validate_nip(nip: &str) -> bool {
+    let digits = digits(nip);
+
+    if digits.len() != 10 {
+        return false;
+    }
+
+    let checksum = weighted_sum(&digits, &[6, 5, 7, 2, 3, 4, 5, 6, 7]) % 11;
+
+    // If checksum is 10, the NIP is invalid
+    if checksum == 10 {
+        return false;
+    }
+
+    checksum == digits[9]
+}
+
+/// Format NIP with dashes (XXX-XXX-XX-XX).
+pub fn format_nip(nip: &str) -> String {
+    let digits: String = nip.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    if digits.len() != 10 {
+        return nip.to_string();
+    }
+
+    format!(
+        "{}-{}-{}-{}",
+        &digits[0..3],
+        &digits[3..6],
+        &digits[6..8],
+        &digits[8..10]
+    )
+}
+
+/// Validate a Polish REGON using the checksum algorithm.
+///
+/// REGON can be 9 or 14 digits.
+/// - 9 digits: weights [8, 9, 2, 3, 4, 5, 6, 7]
+/// - 14 digits: first 9 validated as above, then [2, 4, 8, 5, 0, 9, 7, 3, 6, 1, 2, 4, 8]
+pub fn validate_regon(regon: &str) -> bool {
+    let digits = digits(regon);
+
+    match digits.len() {
+        9 => validate_regon_9(&digits),
+        14 => validate_regon_14(&digits),
+        _ => false,
+    }
+}
+
+fn validate_regon_9(digits: &[u32]) -> bool {
+    let checksum = weighted_sum(digits, &[8, 9, 2, 3, 4, 5, 6, 7]) % 11;
+    let expected = if checksum == 10 { 0 } else { checksum };
+
+    expected == digits[8]
+}
+
+fn validate_regon_14(digits: &[u32]) -> bool {
+    // First validate the 9-digit base
+    if !validate_regon_9(&digits[..9]) {
+        return false;
+    }
+
+    // Then validate the full 14-digit number
+    let checksum = weighted_sum(digits, &[2, 4, 8, 5, 0, 9, 7, 3, 6, 1, 2, 4, 8]) % 11;
+    let expected = if checksum == 10 { 0 } else { checksum };
+
+    expected == digits[13]
+}
+
+/// Validate an IBAN using the checksum algorithm.
+///
+/// Algorithm:
+/// 1. Move first 4 characters to the end
+/// 2. Replace letters with numbers (A=10, B=11, ..., Z=35)
+/// 3. The resulting number mod 97 should equal 1
+pub fn validate_iban(iban: &str) -> bool {
+    // Remove spaces and convert to uppercase
+    let iban: String = iban
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+
+    // Check minimum length (country code + check digits + BBAN)
+    if iban.len() < 5 || !iban.is_ascii() {
+        return false;
+    }
+
+    // Check country code is letters and check digits are numbers
+    let country_code = &iban[..2];
+    let check_digits = &iban[2..4];
+
+    if !country_code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return false;
+    }
+    if !check_digits.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    // Move first 4 characters to the end
+    let rearranged = format!("{}{}", &iban[4..], &iban[..4]);
+
+    // Convert to number string (letters become 10-35)
+    let mut number_str = String::new();
+    for c in rearranged.chars() {
+        if c.is_ascii_digit() {
+            number_str.push(c);
+        } else if c.is_ascii_alphabetic() {
+            let value = (c as u32) - ('A' as u32) + 10;
+            number_str.push_str(&value.to_string());
+        } else {
+            return false;
+        }
+    }
+
+    // Calculate mod 97 using string arithmetic (number is too large for u64)
+    mod97(&number_str) == 1
+}
+
+fn mod97(number_str: &str) -> u32 {
+    let mut remainder: u32 = 0;
+
+    for c in number_str.chars() {
+        let digit = c.to_digit(10).unwrap_or(0);
+        remainder = (remainder * 10 + digit) % 97;
+    }
+
+    remainder
+}
+
+/// Format IBAN in groups of 4 characters.
+pub fn format_iban(iban: &str) -> String {
+    let cleaned: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
+
+    cleaned
+        .chars()
+        .collect::<Vec<char>>()
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Validate a Polish PESEL (personal identification number).
+///
+/// PESEL format: 11 digits, YYMMDD birth date, serial and checksum.
+/// Weights: 1, 3, 7, 9, 1, 3, 7, 9, 1, 3; the check digit is
+/// `(10 - sum % 10) % 10`. The encoded birth date must also exist.
+pub fn validate_pesel(pesel: &str) -> bool {
+    let digits = digits(pesel);
+
+    if digits.len() != 11 {
+        return false;
+    }
+
+    let sum = weighted_sum(&digits, &[1, 3, 7, 9, 1, 3, 7, 9, 1, 3]);
+    if (10 - sum % 10) % 10 != digits[10] {
+        return false;
+    }
+
+    pesel_birth_date(pesel).is_some()
+}
+
+/// Birth date encoded in a PESEL.
+///
+/// The century is encoded in the month: +80 for 1800-1899, +0 for
+/// 1900-1999, +20 for 2000-2099, +40 for 2100-2199 and +60 for 2200-2299.
+/// The checksum is not verified here; use [`validate_pesel`] for that.
+pub fn pesel_birth_date(pesel: &str) -> Option<NaiveDate> {
+    let digits = digits(pesel);
+
+    if digits.len() != 11 {
+        return None;
+    }
+
+    let year = digits[0] * 10 + digits[1];
+    let month = digits[2] * 10 + digits[3];
+    let day = digits[4] * 10 + digits[5];
+
+    let (century, month) = match month {
+        81..=92 => (1800, month - 80),
+        1..=12 => (1900, month),
+        21..=32 => (2000, month - 20),
+        41..=52 => (2100, month - 40),
+        61..=72 => (2200, month - 60),
+        _ => return None,
+    };
+
+    NaiveDate::from_ymd_opt(century + year as i32, month, day)
+}
+
+/// Validate a Polish KRS (National Court Register) number.
+///
+/// KRS numbers have no check digit; this only checks for 10 digits
+/// that are not all zero (KRS numbers are written with leading zeros).
+pub fn validate_krs(krs: &str) -> bool {
+    let digits = digits(krs);
+
+    digits.len() == 10 && digits.iter().any(|&d| d != 0)
+}
+
+/// Validate a KSeF number, the 35-character reference the National e-Invoice
+/// System (KSeF) assigns to an invoice.
+///
+/// Format: `NNNNNNNNNN-YYYYMMDD-XXXXXXXXXXXX-CC`, the issuer's NIP, the date
+/// the invoice was accepted, 12 hex digits and a CRC-8 checksum (polynomial
+/// 0x07, initial value 0) of the first 32 characters as 2 hex digits. Unlike
+/// the other validators this one needs the dashes, as they take part in the
+/// checksum; surrounding whitespace and lowercase hex digits are accepted.
+pub fn validate_ksef_number(number: &str) -> bool {
+    let number = number.trim().to_ascii_uppercase();
+    let parts: Vec<&str> = number.split('-').collect();
+    let [nip, date, id, checksum] = parts.as_slice() else {
+        return false;
+    };
+
+    let hex = |part: &str, len: usize| {
+        part.len() == len && part.chars().all(|c| c.is_ascii_hexdigit())
+    };
+    if nip.len() != 10
+        || !nip.chars().all(|c| c.is_ascii_digit())
+        || !validate_nip(nip)
+        || date.len() != 8
+        || !date.chars().all(|c| c.is_ascii_digit())
+        || NaiveDate::parse_from_str(date, "%Y%m%d").is_err()
+        || !hex(id, 12)
+        || !hex(checksum, 2)
+    {
+        return false;
+    }
+
+    format!("{:02X}", crc8(&number.as_bytes()[..32])) == *checksum
+}
+
+/// Countries whose VAT identification numbers [`validate_vat_id`] checks.
+pub const VAT_ID_COUNTRIES: &[&str] = &["PL", "DE", "CZ", "SK", "FR"];
+
+/// Validate an EU VAT identification number with its country prefix
+/// (`DE136695976`, `PL5261040828`).
+///
+/// Spaces, dashes and dots are ignored. The number is checked with the
+/// algorithm of its country:
+/// - PL: the NIP checksum
+/// - DE: 9 digits, ISO 7064 MOD 11,10
+/// - CZ: 8 digits (companies, weighted checksum), 9 digits (persons born
+///   before 1954, no checksum) or 10 digits (birth number divisible by 11)
+/// - SK: 10 digits divisible by 11
+/// - FR: a 2-character key and the 9-digit SIREN; numeric keys are derived
+///   from the SIREN, which otherwise has to pass the Luhn check
+///
+/// Numbers of other countries are not recognized and fail.
+pub fn validate_vat_id(vat_id: &str) -> bool {
+    let Some((country, number)) = split_vat_id(vat_id) else {
+        return false;
+    };
+    let all_digits = !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit());
+
+    match country.as_str() {
+        "PL" => all_digits && validate_nip(&number),
+        "DE" => all_digits && number.len() == 9 && validate_de(&number),
+        "CZ" => all_digits && validate_cz(&number),
+        "SK" => all_digits && validate_sk(&number),
+        "FR" => validate_fr(&number),
+        _ => false,
+    }
+}
+
+/// Country code and number of a VAT identification number, uppercased and
+/// without separators.
+pub fn split_vat_id(vat_id: &str) -> Option<(String, String)> {
+    let compact: String = vat_id
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '.'))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if compact.len() < 3 || !compact.is_char_boundary(2) {
+        return None;
+    }
+
+    let (country, number) = compact.split_at(2);
+    if !country.bytes().all(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    Some((country.to_string(), number.to_string()))
+}
+
+/// German USt-IdNr.: ISO 7064 MOD 11,10 over the first 8 digits.
+fn validate_de(number: &str) -> bool {
+    let digits = digits(number);
+    if digits[0] == 0 {
+        return false;
+    }
+
+    let mut product = 10;
+    for &digit in &digits[..8] {
+        let sum = match (digit + product) % 10 {
+            0 => 10,
+            sum => sum,
+        };
+        product = (2 * sum) % 11;
+    }
+    (11 - product) % 10 == digits[8]
+}
+
+/// Czech DIČ: the IČO of a company or the birth number of a person.
+fn validate_cz(number: &str) -> bool {
+    let digits = digits(number);
+    match digits.len() {
+        8 => {
+            let sum = weighted_sum(&digits, &[8, 7, 6, 5, 4, 3, 2]);
+            digits[0] != 9 && (11 - sum % 11) % 10 == digits[7]
+        }
+        9 => true,
+        10 => {
+            let value: u64 = number.parse().unwrap_or(0);
+            let remainder = value % 11;
+            // Birth numbers issued before 1985 may end in 0 with remainder 10
+            remainder == 0 || (value / 10 % 11 == 10 && digits[9] == 0)
+        }
+        _ => false,
+    }
+}
+
+/// Slovak IČ DPH: 10 digits divisible by 11.
+fn validate_sk(number: &str) -> bool {
+    let digits = digits(number);
+    let remainder = number.parse::<u64>().unwrap_or(1) % 11;
+    digits.len() == 10 && digits[0] != 0 && remainder == 0
+}
+
+/// French numéro de TVA: a key and the SIREN.
+fn validate_fr(number: &str) -> bool {
+    if number.len() != 11 || !number.is_ascii() {
+        return false;
+    }
+    let (key, siren) = number.split_at(2);
+    if !siren.bytes().all(|b| b.is_ascii_digit())
+        || !key.bytes().all(|b| b.is_ascii_digit() || b.is_ascii_uppercase())
+        || key.contains(['I', 'O'])
+    {
+        return false;
+    }
+
+    match key.parse::<u64>() {
+        Ok(key) => {
+            let siren: u64 = siren.parse().unwrap_or(0);
+            key == (12 + 3 * (siren % 97)) % 97
+        }
+        Err(_) => luhn(&digits(siren)),
+    }
+}
+
+/// Luhn checksum, as used by the SIREN.
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    let remainder = sum % 10;
+    remainder == 0
+}
+
+/// CRC-8 with polynomial 0x07 and initial value 0.
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn digits(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn weighted_sum(digits: &[u32], weights: &[u32]) -> u32 {
+    digits.iter().zip(weights).map(|(d, w)| d * w).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_pesel() {
+        assert!(validate_pesel("44051401359"));
+        assert!(validate_pesel("440514 01359")); // With a space
+        assert!(validate_pesel("02270803624")); // Born in 2002
+
+        assert!(!validate_pesel("44051401358")); // Invalid checksum
+        assert!(!validate_pesel("44131401350")); // Month 13
+        assert!(!validate_pesel("4405140135")); // Too short
+    }
+
+    #[test]
+    fn test_pesel_birth_date() {
+        assert_eq!(pesel_birth_date("44051401359"), NaiveDate::from_ymd_opt(1944, 5, 14));
+        assert_eq!(pesel_birth_date("02270803624"), NaiveDate::from_ymd_opt(2002, 7, 8));
+        assert_eq!(pesel_birth_date("02870803624"), NaiveDate::from_ymd_opt(1802, 7, 8));
+        assert_eq!(pesel_birth_date("01023012345"), None); // 30 February
+    }
+
+    #[test]
+    fn test_validate_krs() {
+        assert!(validate_krs("0000123456"));
+        assert!(validate_krs("KRS 0000123456"));
+
+        assert!(!validate_krs("0000000000"));
+        assert!(!validate_krs("123456")); // Leading zeros dropped
+    }
+
+    #[test]
+    fn test_validate_ksef_number() {
+        assert!(validate_ksef_number("5265877635-20250826-0100001AF629-AF"));
+        assert!(validate_ksef_number(" 5261040828-20240115-8a3f21c0d9e4-7e "));
+
+        assert!(!validate_ksef_number("5265877635-20250826-0100001AF629-AE")); // Checksum
+        assert!(!validate_ksef_number("5265877636-20250826-0100001AF629-AF")); // NIP
+        assert!(!validate_ksef_number("5265877635-20250231-0100001AF629-AF")); // 31 February
+        assert!(!validate_ksef_number("5265877635202508260100001AF629AF")); // No dashes
+    }
+
+    #[test]
+    fn test_validate_vat_id() {
+        assert!(validate_vat_id("PL5261040828"));
+        assert!(validate_vat_id("DE136695976"));
+        assert!(validate_vat_id("DE 811 128 135"));
+        assert!(validate_vat_id("CZ25123891"));
+        assert!(validate_vat_id("CZ7103192745")); // Birth number
+        assert!(validate_vat_id("SK2020273893"));
+        assert!(validate_vat_id("FR40303265045"));
+        assert!(validate_vat_id("fr 83 404 833 048"));
+
+        assert!(!validate_vat_id("DE136695977")); // Checksum
+        assert!(!validate_vat_id("DE036695976")); // Leading zero
+        assert!(!validate_vat_id("CZ25123892"));
+        assert!(!validate_vat_id("SK2020273894"));
+        assert!(!validate_vat_id("FR41303265045"));
+        assert!(!validate_vat_id("AT U12345678")); // Not supported
+        assert!(!validate_vat_id("5261040828")); // No country code
+    }
+
+    #[test]
+    fn test_unicode_digits_rejected() {
+        assert!(!validate_nip("５２６１０４０８２８"));
+        assert!(!validate_iban("PL61１09010140000071219812874"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Checking EU VAT IDs in VIES.
+//!
+//! VIES, the VAT Information Exchange System of the European Commission,
+//! answers whether a VAT ID is registered for intra-EU transactions in its
+//! member state. Zero-rated intra-EU supplies need a buyer with a valid
+//! VAT ID, so the buyers of cross-border invoices are checked.
+//!
+//! [`ViesClient::verify`] looks up the EU VAT IDs (`vat_id`) of both
+//! parties and records the results in `metadata.vies`. IDs that are not
+//! registered are also reported in `metadata.warnings`. VIES only answers
+//! for the current day, unlike the [white list](crate::whitelist).
+//!
+//! ```no_run
+//! # async fn example(mut invoice: incr_core::Invoice) -> Result<(), incr_core::error::ViesError> {
+//! use incr_core::vies::ViesClient;
+//!
+//! ViesClient::new().verify(&mut invoice).await?;
+//! for check in &invoice.metadata.vies {
+//!     println!("{} {}: {}", check.party, check.vat_id, check.valid);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::ViesError;
+use crate::models::invoice::{Invoice, ViesCheck};
+use crate::models::validation::{IssueCode, ValidationIssue};
+use crate::validate::{split_vat_id, validate_vat_id};
+
+/// Production REST API of VIES.
+pub const DEFAULT_API_URL: &str = "https://ec.europa.eu/taxation_customs/vies/rest-api";
+
+/// Client of the VIES REST API.
+#[derive(Debug, Clone)]
+pub struct ViesClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl ViesClient {
+    /// Create a client of the production API.
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("incr/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            base_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+
+    /// Use another API, e.g. a proxy.
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Check the EU VAT IDs of the parties of `invoice` and record the
+    /// results in its metadata.
+    ///
+    /// Parties without a VAT ID, or with one that fails its checksum, are
+    /// skipped.
+    pub async fn verify(&self, invoice: &mut Invoice) -> Result<(), ViesError> {
+        let mut checks = Vec::new();
+        for (party, details) in [("issuer", &invoice.issuer), ("receiver", &invoice.receiver)] {
+            let Some(vat_id) = details.vat_id.as_deref().filter(|id| validate_vat_id(id)) else {
+                continue;
+            };
+            let Some((country, number)) = split_vat_id(vat_id) else {
+                continue;
+            };
+            let mut check = self.lookup(&country, &number).await?;
+            check.party = party.to_string();
+            checks.push(check);
+        }
+
+        record(invoice, checks);
+        Ok(())
+    }
+
+    /// Look up `number` (without the country code) in the register of
+    /// `country`.
+    ///
+    /// The returned check has no party.
+    pub async fn lookup(&self, country: &str, number: &str) -> Result<ViesCheck, ViesError> {
+        let url = format!("{}/ms/{}/vat/{}", self.base_url, country, number);
+        debug!("VIES request: {}", url);
+        let response = self.client.get(&url).send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        if !status.is_success() {
+            return Err(ViesError::Status {
+                url,
+                status: status.as_u16(),
+            });
+        }
+        let response: CheckResponse =
+            serde_json::from_slice(&body).map_err(|e| ViesError::Response(e.to_string()))?;
+
+        // VALID and INVALID are answers, anything else (MS_UNAVAILABLE,
+        // TIMEOUT, ...) means the register could not be asked
+        match response.user_error.as_deref() {
+            None | Some("VALID") | Some("INVALID") => {}
+            Some(error) => return Err(ViesError::Unavailable(error.to_string())),
+        }
+
+        let date = response
+            .request_date
+            .as_deref()
+            .and_then(|date| date.get(..10))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .unwrap_or_else(|| Utc::now().date_naive());
+
+        Ok(ViesCheck {
+            party: String::new(),
+            vat_id: format!("{}{}", country, number),
+            date,
+            valid: response.is_valid,
+            name: disclosed(response.name),
+            address: disclosed(response.address),
+        })
+    }
+}
+
+impl Default for ViesClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckResponse {
+    is_valid: bool,
+    request_date: Option<String>,
+    user_error: Option<String>,
+    name: Option<String>,
+    address: Option<String>,
+}
+
+/// A name or address; member states that don't disclose them send `---`.
+fn disclosed(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty() && value != "---")
+}
+
+/// Store `checks` in the metadata and warn about IDs that are not
+/// registered.
+fn record(invoice: &mut Invoice, checks: Vec<ViesCheck>) {
+    for check in checks.iter().filter(|check| !check.valid) {
+        invoice.metadata.warnings.push(ValidationIssue::warning(
+            IssueCode::VatIdNotRegistered,
+            format!("{}.vat_id", check.party),
+            format!(
+                "{} VAT ID {} is not registered in VIES",
+                if check.party == "issuer" { "Issuer" } else { "Receiver" },
+                check.vat_id
+            ),
+        ));
+    }
+    invoice.metadata.vies = checks;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Requests, Response};
+
+    /// Answer `requests` connections with the body for their path, or a
+    /// 404. Returns the base URL and the requests.
+    fn serve(
+        routes: &'static [(&'static str, &'static str)],
+        requests: usize,
+    ) -> (String, Requests) {
+        test_support::serve(requests, move |request| {
+            match routes.iter().find(|(route, _)| request.path == *route) {
+                Some((_, body)) => Response::ok("application/json", *body),
+                None => Response::new("404 Not Found", Some("application/json"), "{}"),
+            }
+        })
+    }
+
+    const VALID: &str = r#"{"isValid":true,"requestDate":"2024-01-15T09:12:44.512Z","userError":"VALID","name":"---","address":"---","requestIdentifier":"","originalVatNumber":"136695976","vatNumber":"136695976"}"#;
+    const INVALID: &str = r#"{"isValid":false,"requestDate":"2024-01-15T09:12:45.101Z","userError":"INVALID","name":"---","address":"---","requestIdentifier":"","originalVatNumber":"25123891","vatNumber":"25123891"}"#;
+    const UNAVAILABLE: &str = r#"{"isValid":false,"requestDate":"2024-01-15T09:12:46.003Z","userError":"MS_UNAVAILABLE","name":"---","address":"---","requestIdentifier":"","vatNumber":"40303265045"}"#;
+
+    #[tokio::test]
+    async fn test_verify_records_checks() {
+        let (url, requests) = serve(
+            &[("/ms/DE/vat/136695976", VALID), ("/ms/CZ/vat/25123891", INVALID)],
+            2,
+        );
+        let mut invoice = Invoice::new();
+        invoice.issuer.nip = Some("526-025-02-74".to_string());
+        invoice.issuer.vat_id = Some("DE136695976".to_string());
+        invoice.receiver.vat_id = Some("CZ 25123891".to_string());
+
+        ViesClient::new().with_base_url(url).verify(&mut invoice).await.unwrap();
+
+        let paths: Vec<_> = requests.lock().unwrap().iter().map(|r| r.path.clone()).collect();
+        assert_eq!(paths, ["/ms/DE/vat/136695976", "/ms/CZ/vat/25123891"]);
+        let [issuer, receiver] = invoice.metadata.vies.as_slice() else {
+            panic!("expected two checks: {:?}", invoice.metadata.vies);
+        };
+        assert_eq!(issuer.party, "issuer");
+        assert!(issuer.valid);
+        assert_eq!(issuer.name, None);
+        assert_eq!(issuer.date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(receiver.vat_id, "CZ25123891");
+        assert!(!receiver.valid);
+
+        let [warning] = invoice.metadata.warnings.as_slice() else {
+            panic!("expected one warning: {:?}", invoice.metadata.warnings);
+        };
+        assert_eq!(warning.code, IssueCode::VatIdNotRegistered);
+        assert_eq!(warning.field, "receiver.vat_id");
+        assert_eq!(warning.message, "Receiver VAT ID CZ25123891 is not registered in VIES");
+    }
+
+    #[tokio::test]
+    async fn test_unavailable() {
+        let (url, _) = serve(&[("/ms/FR/vat/40303265045", UNAVAILABLE)], 1);
+        let client = ViesClient::new().with_base_url(url);
+
+        let error = client.lookup("FR", "40303265045").await.unwrap_err();
+        assert!(matches!(error, ViesError::Unavailable(ref code) if code == "MS_UNAVAILABLE"));
+    }
+
+    #[test]
+    fn test_disclosed() {
+        assert_eq!(disclosed(Some("---".to_string())), None);
+        assert_eq!(
+            disclosed(Some(" Dupont SARL ".to_string())).as_deref(),
+            Some("Dupont SARL")
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! Checking taxpayers against the white list of VAT taxpayers.
+//!
+//! The Ministry of Finance publishes the register of VAT taxpayers and
+//! their bank accounts (*Biała Lista*, `wl-api.mf.gov.pl`). Paying an
+//! invoice over 15 000 PLN to an account that is not on the list makes the
+//! buyer jointly liable for the seller's VAT, so accounts are checked before
+//! payment.
+//!
+//! [`WhitelistClient::verify`] looks up the NIPs of both parties on the day
+//! the invoice was issued, checks that the issuer's bank account is assigned
+//! to the issuer and records the results in `metadata.whitelist`. Parties
+//! that are not active VAT payers and accounts that are not on the list are
+//! also reported in `metadata.warnings`.
+//!
+//! ```no_run
+//! # async fn example(mut invoice: incr_core::Invoice) -> Result<(), incr_core::error::WhitelistError> {
+//! use incr_core::whitelist::WhitelistClient;
+//!
+//! WhitelistClient::new().verify(&mut invoice).await?;
+//! for check in &invoice.metadata.whitelist {
+//!     println!("{} {}: {:?}", check.party, check.nip, check.vat_status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+use crate::error::WhitelistError;
+use crate::models::invoice::{Invoice, Party, WhitelistCheck};
+use crate::models::validation::{IssueCode, ValidationIssue};
+use crate::validate::validate_nip;
+
+/// Production API of the white list.
+pub const DEFAULT_API_URL: &str = "https://wl-api.mf.gov.pl";
+
+/// Client of the white list API.
+#[derive(Debug, Clone)]
+pub struct WhitelistClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl WhitelistClient {
+    /// Create a client of the production API.
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("incr/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            base_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+
+    /// Use another API, e.g. the test environment
+    /// `https://wl-test.mf.gov.pl`.
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Check the parties of `invoice` and record the results in its
+    /// metadata.
+    ///
+    /// Parties without a valid NIP are skipped. The register is checked for
+    /// the issue date, or today if the invoice has none.
+    pub async fn verify(&self, invoice: &mut Invoice) -> Result<(), WhitelistError> {
+        let today = Utc::now().date_naive();
+        let date = invoice
+            .header
+            .issue_date
+            .filter(|date| *date <= today)
+            .unwrap_or(today);
+
+        let mut checks = Vec::new();
+        for (party, details) in [("issuer", &invoice.issuer), ("receiver", &invoice.receiver)] {
+            let Some(nip) = party_nip(details) else {
+                continue;
+            };
+            let mut check = self.lookup(&nip, date).await?;
+            check.party = party.to_string();
+
+            // Only the issuer is paid, so only its account matters
+            let account = details.bank_account.as_deref().and_then(domestic_account);
+            if let (true, Some(account)) = (party == "issuer", account) {
+                let (assigned, request_id) = self.account_assigned(&nip, &account, date).await?;
+                check.account_assigned = Some(assigned);
+                check.account_request_id = Some(request_id);
+            }
+            checks.push(check);
+        }
+
+        record(invoice, checks);
+        Ok(())
+    }
+
+    /// Look up `nip` (10 digits) in the register on `date`.
+    ///
+    /// The returned check has no party and no account result.
+    pub async fn lookup(&self, nip: &str, date: NaiveDate) -> Result<WhitelistCheck, WhitelistError> {
+        let url = format!("{}/api/search/nip/{}?date={}", self.base_url, nip, date);
+        let response: SearchResponse = self.get(&url).await?;
+        let subject = response.result.subject;
+
+        Ok(WhitelistCheck {
+            party: String::new(),
+            nip: nip.to_string(),
+            date,
+            vat_status: subject
+                .as_ref()
+                .and_then(|s| s.status_vat.clone())
+                .filter(|status| status != "Niezarejestrowany"),
+            name: subject.and_then(|s| s.name),
+            account_assigned: None,
+            request_id: response.result.request_id,
+            account_request_id: None,
+        })
+    }
+
+    /// Check whether the 26-digit `account` is assigned to `nip` on `date`.
+    /// Returns the answer and the request ID.
+    pub async fn account_assigned(
+        &self,
+        nip: &str,
+        account: &str,
+        date: NaiveDate,
+    ) -> Result<(bool, String), WhitelistError> {
+        let url = format!(
+            "{}/api/check/nip/{}/bank-account/{}?date={}",
+            self.base_url, nip, account, date
+        );
+        let response: CheckResponse = self.get(&url).await?;
+
+        let assigned = match response.result.account_assigned.as_str() {
+            "TAK" => true,
+            "NIE" => false,
+            other => return Err(WhitelistError::Response(format!("accountAssigned: {}", other))),
+        };
+        Ok((assigned, response.result.request_id))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, WhitelistError> {
+        debug!("White list request: {}", url);
+        let response = self.client.get(url).send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        if !status.is_success() {
+            return Err(match serde_json::from_slice::<ApiError>(&body) {
+                Ok(error) => WhitelistError::Api {
+                    code: error.code,
+                    message: error.message,
+                },
+                Err(_) => WhitelistError::Status {
+                    url: url.to_string(),
+                    status: status.as_u16(),
+                },
+            });
+        }
+
+        serde_json::from_slice(&body).map_err(|e| WhitelistError::Response(e.to_string()))
+    }
+}
+
+impl Default for WhitelistClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    result: SearchResult,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    subject: Option<Subject>,
+    request_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Subject {
+    name: Option<String>,
+    status_vat: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CheckResponse {
+    result: CheckResult,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckResult {
+    account_assigned: String,
+    request_id: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: String,
+    message: String,
+}
+
+/// The party's NIP as 10 digits, if it is valid.
+fn party_nip(party: &Party) -> Option<String> {
+    let nip = party.nip.as_deref()?;
+    let digits: String = nip.chars().filter(char::is_ascii_digit).collect();
+    validate_nip(&digits).then_some(digits)
+}
+
+/// A Polish account number (NRB) as the 26 digits the API expects.
+/// Foreign IBANs can't be on the list.
+fn domestic_account(account: &str) -> Option<String> {
+    let account: String = account
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_uppercase();
+    let account = account.strip_prefix("PL").unwrap_or(&account);
+    (account.len() == 26 && account.bytes().all(|b| b.is_ascii_digit())).then(|| account.to_string())
+}
+
+/// Store `checks` in the metadata and warn about failed ones.
+fn record(invoice: &mut Invoice, checks: Vec<WhitelistCheck>) {
+    for check in &checks {
+        if !check.is_active() {
+            let status = check.vat_status.as_deref().unwrap_or("not registered");
+            invoice.metadata.warnings.push(ValidationIssue::warning(
+                IssueCode::NotActiveVatPayer,
+                format!("{}.nip", check.party),
+                format!(
+                    "{} NIP {} is not an active VAT payer on the white list ({})",
+                    capitalize(&check.party),
+                    check.nip,
+                    status
+                ),
+            ));
+        }
+        if check.account_assigned == Some(false) {
+            invoice.metadata.warnings.push(ValidationIssue::warning(
+                IssueCode::AccountNotWhitelisted,
+                format!("{}.bank_account", check.party),
+                format!(
+                    "Bank account is not on the white list for {} NIP {}",
+                    check.party, check.nip
+                ),
+            ));
+        }
+    }
+    invoice.metadata.whitelist = checks;
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Requests, Response};
+
+    /// Answer `requests` connections with the body for the first route
+    /// their path starts with, or a `WL-113` error. Returns the base URL
+    /// and the requests.
+    fn serve(
+        routes: &'static [(&'static str, &'static str)],
+        requests: usize,
+    ) -> (String, Requests) {
+        test_support::serve(requests, move |request| {
+            match routes.iter().find(|(prefix, _)| request.path.starts_with(prefix)) {
+                Some((_, body)) => Response::ok("application/json", *body),
+                None => Response::new(
+                    "400 Bad Request",
+                    Some("application/json"),
+                    r#"{"code":"WL-113","message":"Pole 'nip' ma nieprawidłową długość."}"#,
+                ),
+            }
+        })
+    }
+
+    const ISSUER: &str = r#"{"result":{"subject":{"name":"ABC SPÓŁKA Z OGRANICZONĄ ODPOWIEDZIALNOŚCIĄ","nip":"5260250274","statusVat":"Czynny","accountNumbers":["61109010140000071219812874"]},"requestDateTime":"15-01-2024 10:00:00","requestId":"d6r4m-88hd0o1"}}"#;
+    const RECEIVER: &str = r#"{"result":{"subject":null,"requestDateTime":"15-01-2024 10:00:00","requestId":"d6r4m-88hd0o2"}}"#;
+    const ACCOUNT: &str = r#"{"result":{"accountAssigned":"NIE","requestDateTime":"15-01-2024 10:00:00","requestId":"d6r4m-88hd0o3"}}"#;
+
+    #[tokio::test]
+    async fn test_verify_records_checks() {
+        let (url, requests) = serve(
+            &[
+                ("/api/search/nip/5260250274", ISSUER),
+                ("/api/search/nip/7740001454", RECEIVER),
+                ("/api/check/nip/5260250274/bank-account/", ACCOUNT),
+            ],
+            3,
+        );
+        let mut invoice = Invoice::new();
+        invoice.header.issue_date = NaiveDate::from_ymd_opt(2024, 1, 15);
+        invoice.issuer.nip = Some("526-025-02-74".to_string());
+        invoice.issuer.bank_account = Some("PL 61 1090 1014 0000 0712 1981 2874".to_string());
+        invoice.receiver.nip = Some("PL7740001454".to_string());
+        invoice.receiver.bank_account = Some("PL 61 1090 1014 0000 0712 1981 2874".to_string());
+
+        WhitelistClient::new().with_base_url(url).verify(&mut invoice).await.unwrap();
+
+        let paths: Vec<_> = requests.lock().unwrap().iter().map(|r| r.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/api/search/nip/5260250274?date=2024-01-15",
+                "/api/check/nip/5260250274/bank-account/61109010140000071219812874?date=2024-01-15",
+                "/api/search/nip/7740001454?date=2024-01-15",
+            ]
+        );
+
+        let [issuer, receiver] = invoice.metadata.whitelist.as_slice() else {
+            panic!("expected two checks: {:?}", invoice.metadata.whitelist);
+        };
+        assert_eq!(issuer.party, "issuer");
+        assert!(issuer.is_active());
+        assert_eq!(issuer.account_assigned, Some(false));
+        assert_eq!(issuer.account_request_id.as_deref(), Some("d6r4m-88hd0o3"));
+        assert_eq!(receiver.vat_status, None);
+        assert_eq!(receiver.account_assigned, None);
+        assert_eq!(receiver.request_id, "d6r4m-88hd0o2");
+
+        let warnings: Vec<_> = invoice
+            .metadata
+            .warnings
+            .iter()
+            .map(|w| (w.code, w.field.as_str(), w.message.as_str()))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    IssueCode::AccountNotWhitelisted,
+                    "issuer.bank_account",
+                    "Bank account is not on the white list for issuer NIP 5260250274",
+                ),
+                (
+                    IssueCode::NotActiveVatPayer,
+                    "receiver.nip",
+                    "Receiver NIP 7740001454 is not an active VAT payer on the white list (not registered)",
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let (url, _) = serve(&[], 1);
+        let client = WhitelistClient::new().with_base_url(url);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+
+        let error = client.lookup("123", date).await.unwrap_err();
+        assert!(matches!(error, WhitelistError::Api { ref code, .. } if code == "WL-113"));
+    }
+
+    #[test]
+    fn test_domestic_account() {
+        assert_eq!(
+            domestic_account("PL61 1090 1014 0000 0712 1981 2874").as_deref(),
+            Some("61109010140000071219812874")
+        );
+        assert_eq!(domestic_account("DE89 3704 0044 0532 0130 00"), None);
+    }
+}
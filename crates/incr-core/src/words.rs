@@ -0,0 +1,207 @@
+//! Amounts written out in Polish words ("słownie").
+//!
+//! Invoices and correction documents state the total in words, e.g.
+//! `jeden tysiąc dwieście trzydzieści złotych 00/100`. Whole units are
+//! spelled out with the currency name in the grammatically correct form;
+//! the fraction is written as hundredths.
+//!
+//! The rendered invoice template fills in `amount_in_words` with
+//! [`format_amount_in_words`]; currencies without Polish names are
+//! written as their code (`1 000 SEK` becomes `jeden tysiąc SEK 00/100`).
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+const UNITS: [&str; 10] = [
+    "", "jeden", "dwa", "trzy", "cztery", "pięć", "sześć", "siedem", "osiem", "dziewięć",
+];
+
+const TEENS: [&str; 10] = [
+    "dziesięć",
+    "jedenaście",
+    "dwanaście",
+    "trzynaście",
+    "czternaście",
+    "piętnaście",
+    "szesnaście",
+    "siedemnaście",
+    "osiemnaście",
+    "dziewiętnaście",
+];
+
+const TENS: [&str; 10] = [
+    "",
+    "",
+    "dwadzieścia",
+    "trzydzieści",
+    "czterdzieści",
+    "pięćdziesiąt",
+    "sześćdziesiąt",
+    "siedemdziesiąt",
+    "osiemdziesiąt",
+    "dziewięćdziesiąt",
+];
+
+const HUNDREDS: [&str; 10] = [
+    "", "sto", "dwieście", "trzysta", "czterysta", "pięćset", "sześćset", "siedemset", "osiemset",
+    "dziewięćset",
+];
+
+/// Forms of the powers of a thousand, see [`Forms`].
+const SCALES: [Forms; 9] = [
+    ["tysiąc", "tysiące", "tysięcy"],
+    ["milion", "miliony", "milionów"],
+    ["miliard", "miliardy", "miliardów"],
+    ["bilion", "biliony", "bilionów"],
+    ["biliard", "biliardy", "biliardów"],
+    ["trylion", "tryliony", "trylionów"],
+    ["tryliard", "tryliardy", "tryliardów"],
+    ["kwadrylion", "kwadryliony", "kwadrylionów"],
+    ["kwadryliard", "kwadryliardy", "kwadryliardów"],
+];
+
+/// Noun forms used after 1, after 2-4 (22-24, ...) and after other numbers.
+type Forms = [&'static str; 3];
+
+/// Name of a currency after a number, by ISO 4217 code.
+fn currency_forms(currency: &str) -> Option<Forms> {
+    match currency.to_ascii_uppercase().as_str() {
+        "PLN" => Some(["złoty", "złote", "złotych"]),
+        "EUR" => Some(["euro", "euro", "euro"]),
+        "USD" => Some(["dolar", "dolary", "dolarów"]),
+        "GBP" => Some(["funt", "funty", "funtów"]),
+        "CHF" => Some(["frank", "franki", "franków"]),
+        _ => None,
+    }
+}
+
+/// Write `amount` in Polish words, with the fraction as hundredths.
+///
+/// `currency` is an ISO 4217 code; PLN, EUR, USD, GBP and CHF are named in
+/// words, other codes are written as given. The amount is rounded to whole
+/// hundredths.
+///
+/// ```
+/// use rust_decimal::Decimal;
+/// use incr_core::words::format_amount_in_words;
+///
+/// assert_eq!(
+///     format_amount_in_words(Decimal::new(123000, 2), "PLN"),
+///     "jeden tysiąc dwieście trzydzieści złotych 00/100"
+/// );
+/// ```
+pub fn format_amount_in_words(amount: Decimal, currency: &str) -> String {
+    let amount = amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+    let whole = amount.abs().trunc();
+    let hundredths: u32 = ((amount.abs() - whole) * Decimal::ONE_HUNDRED)
+        .try_into()
+        .unwrap_or_default();
+
+    // Decimal's mantissa is 96 bits, so whole units always fit in a u128
+    let whole: u128 = whole.try_into().unwrap_or_default();
+
+    let mut words = Vec::new();
+    if amount.is_sign_negative() && !amount.is_zero() {
+        words.push("minus".to_string());
+    }
+    words.push(number_in_words(whole));
+    words.push(match currency_forms(currency) {
+        Some(forms) => form(whole, &forms).to_string(),
+        None => currency.to_string(),
+    });
+    words.push(format!("{:02}/100", hundredths));
+
+    words.join(" ")
+}
+
+/// Write a whole number in Polish words.
+///
+/// Thousands and higher powers are preceded by "jeden" when there is
+/// exactly one of them ("jeden tysiąc"), as is customary on invoices.
+pub fn number_in_words(number: u128) -> String {
+    if number == 0 {
+        return "zero".to_string();
+    }
+
+    let mut groups = Vec::new();
+    let mut rest = number;
+    while rest > 0 {
+        groups.push((rest % 1000) as u16);
+        rest /= 1000;
+    }
+
+    let mut words = Vec::new();
+    for (scale, &group) in groups.iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        words.extend(group_words(group));
+        if let Some(forms) = scale.checked_sub(1).and_then(|s| SCALES.get(s)) {
+            words.push(form(group as u128, forms));
+        }
+    }
+
+    words.join(" ")
+}
+
+/// Words of a number below a thousand.
+fn group_words(number: u16) -> Vec<&'static str> {
+    let hundreds = (number / 100) as usize;
+    let tens = (number / 10 % 10) as usize;
+    let units = (number % 10) as usize;
+
+    let words = match tens {
+        1 => vec![HUNDREDS[hundreds], TEENS[units]],
+        _ => vec![HUNDREDS[hundreds], TENS[tens], UNITS[units]],
+    };
+    words.into_iter().filter(|w| !w.is_empty()).collect()
+}
+
+/// Form of a noun after `number`.
+fn form(number: u128, forms: &Forms) -> &'static str {
+    let last_two = number % 100;
+    let last = number % 10;
+
+    if number == 1 {
+        forms[0]
+    } else if (2..=4).contains(&last) && !(12..=14).contains(&last_two) {
+        forms[1]
+    } else {
+        forms[2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn words(amount: &str, currency: &str) -> String {
+        format_amount_in_words(Decimal::from_str(amount).unwrap(), currency)
+    }
+
+    #[test]
+    fn test_number_in_words() {
+        assert_eq!(number_in_words(0), "zero");
+        assert_eq!(number_in_words(15), "piętnaście");
+        assert_eq!(number_in_words(101), "sto jeden");
+        assert_eq!(number_in_words(212), "dwieście dwanaście");
+        assert_eq!(number_in_words(2024), "dwa tysiące dwadzieścia cztery");
+        assert_eq!(number_in_words(12_000), "dwanaście tysięcy");
+        assert_eq!(number_in_words(22_500), "dwadzieścia dwa tysiące pięćset");
+        assert_eq!(number_in_words(1_000_001), "jeden milion jeden");
+        assert_eq!(number_in_words(5_000_000_000), "pięć miliardów");
+    }
+
+    #[test]
+    fn test_amount_in_words() {
+        assert_eq!(words("1230.00", "PLN"), "jeden tysiąc dwieście trzydzieści złotych 00/100");
+        assert_eq!(words("1", "PLN"), "jeden złoty 00/100");
+        assert_eq!(words("2.5", "PLN"), "dwa złote 50/100");
+        assert_eq!(words("13.99", "pln"), "trzynaście złotych 99/100");
+        assert_eq!(words("0.07", "PLN"), "zero złotych 07/100");
+        assert_eq!(words("24.005", "PLN"), "dwadzieścia cztery złote 01/100");
+        assert_eq!(words("-3", "EUR"), "minus trzy euro 00/100");
+        assert_eq!(words("21", "USD"), "dwadzieścia jeden dolarów 00/100");
+        assert_eq!(words("100", "CZK"), "sto CZK 00/100");
+    }
+}
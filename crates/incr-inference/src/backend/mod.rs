@@ -6,7 +6,7 @@ pub mod ort;
 #[cfg(feature = "wasm")]
 pub mod tract;
 
-use crate::{InputTensor, OutputTensor, Result};
+use crate::{InferenceOptions, InputTensor, OutputTensor, Result};
 
 /// Trait for ONNX inference backends.
 ///
@@ -28,4 +28,22 @@ pub trait InferenceBackend: Send + Sync {
 
     /// Get the output names produced by the model.
     fn output_names(&self) -> &[String];
+
+    /// Prepare the model for inference, e.g. create and optimize the session
+    /// and run it once on zeros, so the first real inference is not slowed
+    /// down by one-time setup.
+    fn warmup(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Apply session options. Backends that don't support an option ignore
+    /// it; a session already created is recreated on next use.
+    fn configure(&mut self, _options: &InferenceOptions) {}
+}
+
+/// Size used for the dynamic dimensions of a warm-up input: one item for
+/// the batch axis, a small size for the others.
+#[cfg(any(feature = "native", feature = "wasm"))]
+pub(crate) fn warmup_dim(axis: usize) -> usize {
+    if axis == 0 { 1 } else { 32 }
 }
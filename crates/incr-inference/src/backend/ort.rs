@@ -1,24 +1,42 @@
 //! ONNX Runtime (ort) backend for native platforms with XNNPACK.
 
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 
 use ndarray::ArrayD;
-use ort::ep::XNNPACK;
+use ort::ep::{CPU, XNNPACK};
 use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
+use ort::tensor::TensorElementType;
 use ort::value::Tensor;
-use tracing::debug;
+use tracing::{debug, warn};
 
+use crate::backend::warmup_dim;
 use crate::error::InferenceError;
-use crate::tensor::{InputTensor, OutputTensor};
-use crate::{InferenceBackend, Result};
+use crate::model::ModelBytes;
+use crate::tensor::{InputTensor, OutputTensor, TensorType};
+use crate::{InferenceBackend, InferenceOptions, Result};
 
 /// Backend using ONNX Runtime for native inference.
+///
+/// The session is created up front, and again on first use after
+/// [`configure`](InferenceBackend::configure) changes its options. Model
+/// bytes are shared, so several backends built from the same [`ModelBytes`]
+/// keep a single copy.
 pub struct OrtBackend {
+    model: ModelBytes,
+    options: InferenceOptions,
+    loaded: OnceLock<LoadedSession>,
+    init_lock: Mutex<()>,
+}
+
+/// An initialized ONNX Runtime session and its tensor names.
+struct LoadedSession {
     session: Mutex<Session>,
     input_names: Vec<String>,
     output_names: Vec<String>,
+    /// Element type and shape (-1 for dynamic dimensions) of each input.
+    input_types: Vec<(TensorElementType, Vec<i64>)>,
 }
 
 impl OrtBackend {
@@ -27,28 +45,93 @@ impl OrtBackend {
         let path = path.as_ref();
         debug!("Loading ONNX model from: {}", path.display());
 
-        let bytes = std::fs::read(path)
-            .map_err(|e| InferenceError::Io(e))?;
-
-        Self::from_bytes_internal(&bytes)
+        Self::from_model(ModelBytes::from(std::fs::read(path)?))
     }
 
     /// Load a model from bytes.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        Self::from_bytes_internal(bytes)
+        Self::from_model(ModelBytes::from(bytes.to_vec()))
+    }
+
+    /// Load a model from shared model bytes, creating the session immediately.
+    pub fn from_model(model: ModelBytes) -> Result<Self> {
+        Self::from_model_with_options(model, InferenceOptions::default())
+    }
+
+    /// Load a model from shared model bytes with session `options`,
+    /// creating the session immediately.
+    pub fn from_model_with_options(model: ModelBytes, options: InferenceOptions) -> Result<Self> {
+        if model.is_empty() {
+            return Err(InferenceError::ModelLoad("model data is empty".to_string()));
+        }
+
+        let backend = Self {
+            model,
+            options,
+            loaded: OnceLock::new(),
+            init_lock: Mutex::new(()),
+        };
+        backend.loaded()?;
+        Ok(backend)
     }
 
-    fn from_bytes_internal(bytes: &[u8]) -> Result<Self> {
-        debug!("Loading ONNX model from {} bytes", bytes.len());
+    /// The model bytes backing this backend.
+    pub fn model(&self) -> &ModelBytes {
+        &self.model
+    }
+
+    /// The session options.
+    pub fn options(&self) -> &InferenceOptions {
+        &self.options
+    }
 
-        let session = Session::builder()
-            .map_err(|e| InferenceError::SessionCreate(e.to_string()))?
-            .with_execution_providers([XNNPACK::default().build()])
-            .map_err(|e| InferenceError::SessionCreate(e.to_string()))?
+    fn loaded(&self) -> Result<&LoadedSession> {
+        if let Some(loaded) = self.loaded.get() {
+            return Ok(loaded);
+        }
+
+        // Serialize initialization so concurrent first calls build one session.
+        let _guard = self.init_lock.lock()
+            .map_err(|e| InferenceError::SessionCreate(format!("Failed to lock session init: {}", e)))?;
+
+        if let Some(loaded) = self.loaded.get() {
+            return Ok(loaded);
+        }
+
+        let loaded = Self::create_session(&self.model, &self.options)?;
+        Ok(self.loaded.get_or_init(|| loaded))
+    }
+
+    fn create_session(bytes: &[u8], options: &InferenceOptions) -> Result<LoadedSession> {
+        debug!("Loading ONNX model from {} bytes with {:?}", bytes.len(), options);
+        let session_error = |e: ort::Error| InferenceError::SessionCreate(e.to_string());
+
+        let providers = [
+            XNNPACK::default().build(),
+            CPU::default().with_arena_allocator(options.memory_arena).build(),
+        ];
+        let mut builder = Session::builder()
+            .map_err(session_error)?
+            .with_execution_providers(providers)
+            .map_err(session_error)?
             .with_optimization_level(GraphOptimizationLevel::Level3)
-            .map_err(|e| InferenceError::SessionCreate(e.to_string()))?
-            .with_intra_threads(4)
-            .map_err(|e| InferenceError::SessionCreate(e.to_string()))?
+            .map_err(session_error)?
+            .with_memory_pattern(options.memory_arena)
+            .map_err(session_error)?;
+        if options.intra_threads > 0 {
+            builder = builder.with_intra_threads(options.intra_threads).map_err(session_error)?;
+        }
+        if options.inter_threads > 0 {
+            builder = builder
+                .with_parallel_execution(true)
+                .map_err(session_error)?
+                .with_inter_threads(options.inter_threads)
+                .map_err(session_error)?;
+        }
+        if let Some(prefix) = &options.profiling {
+            builder = builder.with_profiling(prefix).map_err(session_error)?;
+        }
+        let session = builder
             .commit_from_memory(bytes)
             .map_err(|e| InferenceError::ModelLoad(e.to_string()))?;
 
@@ -64,16 +147,34 @@ impl OrtBackend {
             .map(|o| o.name().to_string())
             .collect();
 
+        let input_types = session
+            .inputs()
+            .iter()
+            .filter_map(|i| Some((i.dtype().tensor_type()?, i.dtype().tensor_shape()?.to_vec())))
+            .collect();
+
         debug!("Model inputs: {:?}", input_names);
         debug!("Model outputs: {:?}", output_names);
 
-        Ok(Self {
+        Ok(LoadedSession {
             session: Mutex::new(session),
             input_names,
             output_names,
+            input_types,
         })
     }
 
+    /// Tensor names of the loaded session, initializing it if needed.
+    fn names(&self, pick: fn(&LoadedSession) -> &[String]) -> &[String] {
+        match self.loaded() {
+            Ok(loaded) => pick(loaded),
+            Err(e) => {
+                warn!("Failed to initialize ONNX session: {}", e);
+                &[]
+            }
+        }
+    }
+
     fn convert_input(&self, tensor: &InputTensor) -> Result<ort::session::SessionInputValue<'static>> {
         match tensor {
             InputTensor::Float32(arr) => {
@@ -125,7 +226,7 @@ impl InferenceBackend for OrtBackend {
             })
             .collect::<Result<Vec<_>>>()?;
 
-        let mut session = self.session.lock()
+        let mut session = self.loaded()?.session.lock()
             .map_err(|e| InferenceError::InferenceFailed(format!("Failed to lock session: {}", e)))?;
 
         let outputs = session
@@ -163,6 +264,13 @@ impl InferenceBackend for OrtBackend {
                 let arr = ArrayD::from_shape_vec(ndarray::IxDyn(&shape), data_vec)
                     .map_err(|e| InferenceError::OutputExtraction(e.to_string()))?;
                 OutputTensor::Float64(arr)
+            } else if let Ok(tensor_ref) = value.try_extract_tensor::<u8>() {
+                let (shape_ref, data) = tensor_ref;
+                let shape: Vec<usize> = shape_ref.iter().map(|&s| s as usize).collect();
+                let data_vec: Vec<u8> = data.to_vec();
+                let arr = ArrayD::from_shape_vec(ndarray::IxDyn(&shape), data_vec)
+                    .map_err(|e| InferenceError::OutputExtraction(e.to_string()))?;
+                OutputTensor::Uint8(arr)
             } else {
                 return Err(InferenceError::OutputExtraction(
                     format!("unsupported output type for '{}'", name),
@@ -176,10 +284,49 @@ impl InferenceBackend for OrtBackend {
     }
 
     fn input_names(&self) -> &[String] {
-        &self.input_names
+        self.names(|loaded| &loaded.input_names)
     }
 
     fn output_names(&self) -> &[String] {
-        &self.output_names
+        self.names(|loaded| &loaded.output_names)
+    }
+
+    fn warmup(&self) -> Result<()> {
+        let loaded = self.loaded()?;
+        if loaded.input_types.len() != loaded.input_names.len() {
+            debug!("Skipping warm-up of a model with non-tensor inputs");
+            return Ok(());
+        }
+
+        let mut inputs = Vec::with_capacity(loaded.input_names.len());
+        for (name, (element_type, shape)) in loaded.input_names.iter().zip(&loaded.input_types) {
+            let dtype = match element_type {
+                TensorElementType::Float32 => TensorType::Float32,
+                TensorElementType::Float64 => TensorType::Float64,
+                TensorElementType::Int32 => TensorType::Int32,
+                TensorElementType::Int64 => TensorType::Int64,
+                TensorElementType::Uint8 => TensorType::Uint8,
+                other => {
+                    debug!("Skipping warm-up of a model with {:?} input '{}'", other, name);
+                    return Ok(());
+                }
+            };
+            let shape: Vec<usize> = shape
+                .iter()
+                .enumerate()
+                .map(|(axis, &size)| if size > 0 { size as usize } else { warmup_dim(axis) })
+                .collect();
+            inputs.push((name.as_str(), InputTensor::zeros(dtype, &shape)));
+        }
+
+        self.run(&inputs)?;
+        Ok(())
+    }
+
+    fn configure(&mut self, options: &InferenceOptions) {
+        if self.options != *options {
+            self.options = options.clone();
+            self.loaded = OnceLock::new();
+        }
     }
 }
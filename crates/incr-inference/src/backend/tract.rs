@@ -1,102 +1,224 @@
 //! Tract backend for cross-platform ONNX inference.
+//!
+//! Input and output names and input shapes are read from the ONNX graph.
+//! Dynamic dimensions (a `dim_param`, or no size at all) stay symbolic, so
+//! one plan runs inputs of any size, e.g. text lines of varying width; tract
+//! resolves the symbols from the tensors passed to each run. Shapes can be
+//! fixed at load time instead, which lets tract plan the model for exactly
+//! that size.
 
+use std::collections::HashSet;
 use std::path::Path;
 
 use ndarray::ArrayD;
+use tract_onnx::pb::tensor_proto::DataType;
+use tract_onnx::pb::tensor_shape_proto::dimension::Value as DimValue;
+use tract_onnx::pb::type_proto::Value as TypeValue;
+use tract_onnx::pb::{self, ModelProto};
 use tract_onnx::prelude::*;
 use tracing::debug;
 
+use crate::backend::warmup_dim;
 use crate::error::InferenceError;
-use crate::tensor::{InputTensor, OutputTensor};
+use crate::tensor::{InputTensor, OutputTensor, TensorType};
 use crate::{InferenceBackend, Result};
 
+/// A dimension of a model input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputDim {
+    /// Size fixed by the model or at load time.
+    Fixed(usize),
+    /// Size taken from the tensor of each run. The name is the graph's
+    /// `dim_param`, or `<input>_<axis>` for dimensions without one.
+    Symbolic(String),
+}
+
+/// Name and shape of a model input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputInfo {
+    /// Name in the ONNX graph.
+    pub name: String,
+    /// Element type (float for types the backend can't feed).
+    pub dtype: TensorType,
+    /// Shape, one entry per axis.
+    pub shape: Vec<InputDim>,
+}
+
 /// Backend using Tract for cross-platform ONNX inference.
 pub struct TractBackend {
-    model: SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>,
+    model: TypedRunnableModel<TypedModel>,
+    inputs: Vec<InputInfo>,
     input_names: Vec<String>,
     output_names: Vec<String>,
 }
 
 impl TractBackend {
-    /// Load a model from a file path with default input shape (batch=1, channels=3, height=640, width=640).
+    /// Load a model from a file path with the input shapes of its graph.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        // Default to common OCR input dimensions
-        Self::from_file_with_shape(path, &[1, 3, 640, 640])
+        Self::from_file_with_shapes(path, &[])
     }
 
-    /// Load a model from a file path with specified input shape.
+    /// Load a model from a file path, fixing the shape of its first input.
     pub fn from_file_with_shape<P: AsRef<Path>>(path: P, input_shape: &[usize]) -> Result<Self> {
-        let path = path.as_ref();
-        debug!("Loading ONNX model with Tract from: {}", path.display());
-
-        // Load as inference model first
-        let mut model = tract_onnx::onnx()
-            .model_for_path(path)
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to load model: {}", e)))?;
+        let proto = read_file(path.as_ref())?;
+        let first = first_input(&proto)?;
+        Self::from_proto(proto, &[(&first, input_shape)])
+    }
 
-        // Set input fact with concrete shape to replace dynamic dimensions
-        model
-            .set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), input_shape))
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to set input shape: {}", e)))?;
+    /// Load a model from a file path, fixing the shapes of the named inputs.
+    /// Other inputs keep the shapes of the graph.
+    pub fn from_file_with_shapes<P: AsRef<Path>>(
+        path: P,
+        shapes: &[(&str, &[usize])],
+    ) -> Result<Self> {
+        Self::from_proto(read_file(path.as_ref())?, shapes)
+    }
 
-        // Now convert to typed model and optimize
-        let model = model
-            .into_typed()
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to type model: {}", e)))?
-            .into_optimized()
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to optimize: {}", e)))?
-            .into_runnable()
-            .map_err(|e| InferenceError::SessionCreate(e.to_string()))?;
+    /// Load a model from bytes with the input shapes of its graph.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes_with_shapes(bytes, &[])
+    }
 
-        // Tract doesn't expose input/output names as easily, use indices
-        let input_names = vec!["input".to_string()];
-        let output_names = vec!["output".to_string()];
+    /// Load a model from bytes, fixing the shape of its first input.
+    pub fn from_bytes_with_shape(bytes: &[u8], input_shape: &[usize]) -> Result<Self> {
+        let proto = read_bytes(bytes)?;
+        let first = first_input(&proto)?;
+        Self::from_proto(proto, &[(&first, input_shape)])
+    }
 
-        Ok(Self {
-            model,
-            input_names,
-            output_names,
-        })
+    /// Load a model from bytes, fixing the shapes of the named inputs.
+    /// Other inputs keep the shapes of the graph.
+    pub fn from_bytes_with_shapes(bytes: &[u8], shapes: &[(&str, &[usize])]) -> Result<Self> {
+        Self::from_proto(read_bytes(bytes)?, shapes)
     }
 
-    /// Load a model from bytes with default input shape.
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        Self::from_bytes_with_shape(bytes, &[1, 3, 640, 640])
+    /// The inputs of the model, in the order of the graph.
+    pub fn inputs(&self) -> &[InputInfo] {
+        &self.inputs
     }
 
-    /// Load a model from bytes with specified input shape.
-    pub fn from_bytes_with_shape(bytes: &[u8], input_shape: &[usize]) -> Result<Self> {
-        debug!("Loading ONNX model with Tract from {} bytes", bytes.len());
+    fn from_proto(mut proto: ModelProto, shapes: &[(&str, &[usize])]) -> Result<Self> {
+        let graph = proto
+            .graph
+            .as_mut()
+            .ok_or_else(|| InferenceError::ModelLoad("model has no graph".to_string()))?;
+        let output_names: Vec<String> = graph.output.iter().map(|o| o.name.clone()).collect();
+        let inputs = name_dynamic_dims(graph);
+
+        for (name, _) in shapes {
+            if !inputs.iter().any(|input| input.name == *name) {
+                return Err(InferenceError::ModelLoad(format!(
+                    "model has no input '{}' (inputs: {})",
+                    name,
+                    names(&inputs)
+                )));
+            }
+        }
 
-        // Load as inference model first
         let mut model = tract_onnx::onnx()
-            .model_for_read(&mut std::io::Cursor::new(bytes))
+            .model_for_proto_model(&proto)
             .map_err(|e| InferenceError::ModelLoad(format!("Failed to load model: {}", e)))?;
+        if model.inputs.len() != inputs.len() {
+            return Err(InferenceError::ModelLoad(format!(
+                "graph declares {} inputs, tract found {}",
+                inputs.len(),
+                model.inputs.len()
+            )));
+        }
+
+        // Output shapes are inferred; declared symbolic ones would not unify
+        // with the sizes tract derives from the inputs
+        for index in 0..model.outputs.len() {
+            model
+                .set_output_fact(index, InferenceFact::default())
+                .map_err(|e| InferenceError::ModelLoad(e.to_string()))?;
+        }
 
-        // Set input fact with concrete shape to replace dynamic dimensions
-        model
-            .set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), input_shape))
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to set input shape: {}", e)))?;
+        // Replace the declared shapes of fixed inputs
+        let mut inputs = inputs;
+        for (index, input) in inputs.iter_mut().enumerate() {
+            let Some((_, shape)) = shapes.iter().find(|(name, _)| *name == input.name) else {
+                continue;
+            };
+            if !input.shape.is_empty() && input.shape.len() != shape.len() {
+                return Err(InferenceError::ModelLoad(format!(
+                    "input '{}' has {} dimensions, got shape {:?}",
+                    input.name,
+                    input.shape.len(),
+                    shape
+                )));
+            }
+            model
+                .set_input_fact(index, InferenceFact::dt_shape(datum_type(input.dtype), *shape))
+                .map_err(|e| {
+                    InferenceError::ModelLoad(format!("Failed to set input shape: {}", e))
+                })?;
+            input.shape = shape.iter().map(|&size| InputDim::Fixed(size)).collect();
+        }
+        debug!("Tract model inputs: {:?}, outputs: {:?}", inputs, output_names);
 
-        // Now convert to typed model and optimize
+        // Some operators can't be planned for symbolic sizes; those models
+        // have to be loaded with fixed shapes
+        let symbolic = inputs
+            .iter()
+            .any(|input| input.shape.iter().any(|dim| matches!(dim, InputDim::Symbolic(_))));
         let model = model
             .into_typed()
-            .map_err(|e| InferenceError::ModelLoad(format!("Failed to type model: {}", e)))?
+            .map_err(|e| {
+                let hint = if symbolic { " (try fixing the input shapes)" } else { "" };
+                InferenceError::ModelLoad(format!("Failed to type model: {}{}", e, hint))
+            })?
             .into_optimized()
             .map_err(|e| InferenceError::ModelLoad(format!("Failed to optimize: {}", e)))?
             .into_runnable()
             .map_err(|e| InferenceError::SessionCreate(e.to_string()))?;
 
-        let input_names = vec!["input".to_string()];
-        let output_names = vec!["output".to_string()];
-
         Ok(Self {
             model,
-            input_names,
+            input_names: inputs.iter().map(|input| input.name.clone()).collect(),
+            inputs,
             output_names,
         })
     }
 
+    /// The tensors of `inputs` in the order of the graph: by name when every
+    /// input of the model is named, otherwise in the order given.
+    fn order_inputs<'a>(&self, inputs: &'a [(&str, InputTensor)]) -> Result<Vec<&'a InputTensor>> {
+        if inputs.len() != self.inputs.len() {
+            return Err(InferenceError::InvalidInput(format!(
+                "model takes {} inputs ({}), got {}",
+                self.inputs.len(),
+                names(&self.inputs),
+                inputs.len()
+            )));
+        }
+
+        let by_name: Option<Vec<&InputTensor>> = self
+            .input_names
+            .iter()
+            .map(|name| inputs.iter().find(|(n, _)| n == name).map(|(_, tensor)| tensor))
+            .collect();
+        let ordered = by_name.unwrap_or_else(|| inputs.iter().map(|(_, tensor)| tensor).collect());
+
+        for (input, tensor) in self.inputs.iter().zip(&ordered) {
+            let fits = input.shape.len() == tensor.shape().len()
+                && input.shape.iter().zip(tensor.shape()).all(|(dim, &size)| match dim {
+                    InputDim::Fixed(fixed) => *fixed == size,
+                    InputDim::Symbolic(_) => true,
+                });
+            if !input.shape.is_empty() && !fits {
+                return Err(InferenceError::InvalidInput(format!(
+                    "input '{}' has shape {:?}, the model takes {}",
+                    input.name,
+                    tensor.shape(),
+                    describe_shape(&input.shape)
+                )));
+            }
+        }
+
+        Ok(ordered)
+    }
+
     fn convert_input(&self, tensor: &InputTensor) -> Result<TValue> {
         match tensor {
             InputTensor::Float32(arr) => {
@@ -155,9 +277,10 @@ impl TractBackend {
 
 impl InferenceBackend for TractBackend {
     fn run(&self, inputs: &[(&str, InputTensor)]) -> Result<Vec<(String, OutputTensor)>> {
-        let tract_inputs: TVec<TValue> = inputs
-            .iter()
-            .map(|(_, tensor)| self.convert_input(tensor))
+        let tract_inputs: TVec<TValue> = self
+            .order_inputs(inputs)?
+            .into_iter()
+            .map(|tensor| self.convert_input(tensor))
             .collect::<Result<TVec<_>>>()?;
 
         let outputs = self
@@ -190,6 +313,12 @@ impl InferenceBackend for TractBackend {
                 let arr = ArrayD::from_shape_vec(ndarray::IxDyn(&shape), data)
                     .map_err(|e| InferenceError::OutputExtraction(e.to_string()))?;
                 OutputTensor::Int32(arr)
+            } else if let Ok(arr) = output.to_array_view::<u8>() {
+                let shape: Vec<usize> = arr.shape().to_vec();
+                let data: Vec<u8> = arr.iter().cloned().collect();
+                let arr = ArrayD::from_shape_vec(ndarray::IxDyn(&shape), data)
+                    .map_err(|e| InferenceError::OutputExtraction(e.to_string()))?;
+                OutputTensor::Uint8(arr)
             } else {
                 return Err(InferenceError::OutputExtraction(
                     format!("unsupported output type for '{}'", name),
@@ -209,4 +338,146 @@ impl InferenceBackend for TractBackend {
     fn output_names(&self) -> &[String] {
         &self.output_names
     }
+
+    fn warmup(&self) -> Result<()> {
+        if self.inputs.iter().any(|input| input.shape.is_empty()) {
+            debug!("Skipping warm-up of a model with inputs of unknown shape");
+            return Ok(());
+        }
+
+        let inputs: Vec<(&str, InputTensor)> = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let shape: Vec<usize> = input
+                    .shape
+                    .iter()
+                    .enumerate()
+                    .map(|(axis, dim)| match dim {
+                        InputDim::Fixed(size) => *size,
+                        InputDim::Symbolic(_) => warmup_dim(axis),
+                    })
+                    .collect();
+                (input.name.as_str(), InputTensor::zeros(input.dtype, &shape))
+            })
+            .collect();
+
+        self.run(&inputs)?;
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> Result<ModelProto> {
+    debug!("Loading ONNX model with Tract from: {}", path.display());
+    tract_onnx::onnx()
+        .proto_model_for_path(path)
+        .map_err(|e| InferenceError::ModelLoad(format!("Failed to load model: {}", e)))
+}
+
+fn read_bytes(bytes: &[u8]) -> Result<ModelProto> {
+    debug!("Loading ONNX model with Tract from {} bytes", bytes.len());
+    tract_onnx::onnx()
+        .proto_model_for_read(&mut std::io::Cursor::new(bytes))
+        .map_err(|e| InferenceError::ModelLoad(format!("Failed to load model: {}", e)))
+}
+
+/// Name of the first input of the graph.
+fn first_input(proto: &ModelProto) -> Result<String> {
+    let graph = proto
+        .graph
+        .as_ref()
+        .ok_or_else(|| InferenceError::ModelLoad("model has no graph".to_string()))?;
+    let initializers: HashSet<&str> = graph.initializer.iter().map(|t| t.name.as_str()).collect();
+    graph
+        .input
+        .iter()
+        .find(|input| !initializers.contains(input.name.as_str()))
+        .map(|input| input.name.clone())
+        .ok_or_else(|| InferenceError::ModelLoad("model has no inputs".to_string()))
+}
+
+/// Give the dynamic dimensions of the graph inputs that have no
+/// `dim_param` a name, so tract keeps them symbolic instead of failing to
+/// type the model. Returns the inputs, without the initializers older
+/// exporters list as inputs.
+fn name_dynamic_dims(graph: &mut pb::GraphProto) -> Vec<InputInfo> {
+    let initializers: HashSet<String> = graph.initializer.iter().map(|t| t.name.clone()).collect();
+    let mut inputs = Vec::new();
+
+    for input in graph.input.iter_mut().filter(|i| !initializers.contains(&i.name)) {
+        let mut info = InputInfo {
+            name: input.name.clone(),
+            dtype: TensorType::Float32,
+            shape: Vec::new(),
+        };
+
+        if let Some(TypeValue::TensorType(tensor)) =
+            input.r#type.as_mut().and_then(|t| t.value.as_mut())
+        {
+            info.dtype = element_type(tensor.elem_type);
+            for (axis, dim) in tensor.shape.iter_mut().flat_map(|s| s.dim.iter_mut()).enumerate() {
+                let size = match &dim.value {
+                    Some(DimValue::DimValue(size)) if *size > 0 => Some(*size as usize),
+                    _ => None,
+                };
+                info.shape.push(match (size, &dim.value) {
+                    (Some(size), _) => InputDim::Fixed(size),
+                    (None, Some(DimValue::DimParam(param))) if !param.is_empty() => {
+                        InputDim::Symbolic(param.clone())
+                    }
+                    (None, _) => {
+                        let param = format!("{}_{}", info.name, axis);
+                        dim.value = Some(DimValue::DimParam(param.clone()));
+                        InputDim::Symbolic(param)
+                    }
+                });
+            }
+        }
+        inputs.push(info);
+    }
+
+    inputs
+}
+
+/// Tensor type of an ONNX element type; float for types the backend can't
+/// feed anyway.
+fn element_type(elem_type: i32) -> TensorType {
+    match elem_type {
+        t if t == DataType::Double as i32 => TensorType::Float64,
+        t if t == DataType::Int32 as i32 => TensorType::Int32,
+        t if t == DataType::Int64 as i32 => TensorType::Int64,
+        t if t == DataType::Uint8 as i32 => TensorType::Uint8,
+        _ => TensorType::Float32,
+    }
+}
+
+fn datum_type(dtype: TensorType) -> DatumType {
+    match dtype {
+        TensorType::Float32 => f32::datum_type(),
+        TensorType::Float64 => f64::datum_type(),
+        TensorType::Int32 => i32::datum_type(),
+        TensorType::Int64 => i64::datum_type(),
+        TensorType::Uint8 => u8::datum_type(),
+    }
+}
+
+/// Comma-separated names of `inputs`.
+fn names<'a>(inputs: impl IntoIterator<Item = &'a InputInfo>) -> String {
+    inputs
+        .into_iter()
+        .map(|input| input.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A shape like `[1, 3, 48, x_3]`.
+fn describe_shape(shape: &[InputDim]) -> String {
+    let dims: Vec<String> = shape
+        .iter()
+        .map(|dim| match dim {
+            InputDim::Fixed(size) => size.to_string(),
+            InputDim::Symbolic(name) => name.clone(),
+        })
+        .collect();
+    format!("[{}]", dims.join(", "))
 }
@@ -7,17 +7,21 @@
 
 mod backend;
 mod error;
+mod model;
+mod options;
 mod tensor;
 
 pub use backend::InferenceBackend;
 pub use error::InferenceError;
+pub use model::ModelBytes;
+pub use options::InferenceOptions;
 pub use tensor::{InputTensor, OutputTensor, TensorType};
 
 #[cfg(feature = "native")]
 pub use backend::ort::OrtBackend;
 
 #[cfg(feature = "wasm")]
-pub use backend::tract::TractBackend;
+pub use backend::tract::{InputDim, InputInfo, TractBackend};
 
 /// Result type for inference operations.
 pub type Result<T> = std::result::Result<T, InferenceError>;
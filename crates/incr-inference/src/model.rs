@@ -0,0 +1,63 @@
+//! Shared model data.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Cheaply clonable ONNX model bytes.
+///
+/// Cloning never copies the model, so one `ModelBytes` can back any number
+/// of sessions (e.g. a pool of per-worker backends).
+#[derive(Clone)]
+pub struct ModelBytes {
+    data: Data,
+}
+
+#[derive(Clone)]
+enum Data {
+    Static(&'static [u8]),
+    Shared(Arc<[u8]>),
+}
+
+impl ModelBytes {
+    /// Wrap model bytes embedded in the binary.
+    pub fn from_static(bytes: &'static [u8]) -> Self {
+        Self {
+            data: Data::Static(bytes),
+        }
+    }
+}
+
+impl Deref for ModelBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.data {
+            Data::Static(bytes) => bytes,
+            Data::Shared(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for ModelBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for ModelBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self {
+            data: Data::Shared(bytes.into()),
+        }
+    }
+}
+
+impl fmt::Debug for ModelBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelBytes")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
@@ -0,0 +1,62 @@
+//! Session options for inference backends.
+
+use std::path::PathBuf;
+
+/// Tuning options for inference sessions.
+///
+/// Backends apply the options they support: ONNX Runtime all of them,
+/// tract none (it runs single-threaded).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferenceOptions {
+    /// Threads used within an operator (0 = runtime default, one per core).
+    pub intra_threads: usize,
+
+    /// Threads used to run independent operators in parallel
+    /// (0 = sequential execution).
+    pub inter_threads: usize,
+
+    /// Keep freed tensor memory in an arena for reuse. Faster for repeated
+    /// runs, but memory is not returned to the system.
+    pub memory_arena: bool,
+
+    /// Write a profile of each session to `<prefix>_<timestamp>.json`,
+    /// viewable in `chrome://tracing`.
+    pub profiling: Option<PathBuf>,
+}
+
+impl Default for InferenceOptions {
+    fn default() -> Self {
+        Self {
+            intra_threads: 4,
+            inter_threads: 0,
+            memory_arena: true,
+            profiling: None,
+        }
+    }
+}
+
+impl InferenceOptions {
+    /// Set the number of threads used within an operator.
+    pub fn with_intra_threads(mut self, threads: usize) -> Self {
+        self.intra_threads = threads;
+        self
+    }
+
+    /// Set the number of threads running independent operators in parallel.
+    pub fn with_inter_threads(mut self, threads: usize) -> Self {
+        self.inter_threads = threads;
+        self
+    }
+
+    /// Enable or disable the memory arena.
+    pub fn with_memory_arena(mut self, enable: bool) -> Self {
+        self.memory_arena = enable;
+        self
+    }
+
+    /// Profile sessions, writing the profiles to files starting with `prefix`.
+    pub fn with_profiling(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.profiling = Some(prefix.into());
+        self
+    }
+}
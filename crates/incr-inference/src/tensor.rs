@@ -52,6 +52,18 @@ impl InputTensor {
         InputTensor::Float32(arr)
     }
 
+    /// Create a tensor of `dtype` filled with zeros, e.g. to warm up a model.
+    pub fn zeros(dtype: TensorType, shape: &[usize]) -> Self {
+        let shape = IxDyn(shape);
+        match dtype {
+            TensorType::Float32 => InputTensor::Float32(ArrayD::zeros(shape)),
+            TensorType::Float64 => InputTensor::Float64(ArrayD::zeros(shape)),
+            TensorType::Int32 => InputTensor::Int32(ArrayD::zeros(shape)),
+            TensorType::Int64 => InputTensor::Int64(ArrayD::zeros(shape)),
+            TensorType::Uint8 => InputTensor::Uint8(ArrayD::zeros(shape)),
+        }
+    }
+
     /// Create a Uint8 tensor from raw data and shape.
     pub fn from_u8(data: Vec<u8>, shape: Vec<usize>) -> Self {
         let arr = ArrayD::from_shape_vec(IxDyn(&shape), data)
@@ -2,11 +2,28 @@
 //!
 //! This crate provides WebAssembly bindings for use in browsers and Node.js.
 
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 use serde_wasm_bindgen;
+use web_sys::{ImageBitmap, ImageData, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
 
+use incr_core::archive::{ZipArchive, ZipEntry, ZipSource};
+use incr_core::audit::sha256_hex;
+use incr_core::error::ArchiveError;
 use incr_core::models::invoice::{Invoice, InvoiceType, VatRate};
-use incr_core::invoice::{HybridInvoiceParser, InvoiceParser};
+use incr_core::models::naming::FieldNaming;
+use incr_core::models::validation::ValidationIssue;
+use incr_core::invoice::{FieldKind, HybridInvoiceParser, InvoiceParser};
+use incr_core::models::config::OcrConfig;
+use incr_core::ocr::{
+    AngleClassifier, ImagePreprocessor, LayoutDetector, LayoutInfo, OcrResult, StyleClassifier,
+    TableClassifier, TableRecognizer, TextDetector, TextRecognizer,
+};
+use incr_core::{OcrEngine, TractBackend};
+use incr_core::pdf::{PdfExtractor, PdfProcessor};
+use incr_core::progress::{ProgressEvent, ProgressSink};
 
 /// Initialize panic hook for better error messages in console.
 #[wasm_bindgen(start)]
@@ -64,10 +81,24 @@ pub fn parse_polish_amount(amount: &str) -> Option<f64> {
         .map(|d| d.to_string().parse().unwrap_or(0.0))
 }
 
+/// Write an amount (e.g., "1230.00" or "1 230,00") in Polish words.
+#[wasm_bindgen]
+pub fn format_amount_in_words(amount: &str, currency: &str) -> Result<String, JsValue> {
+    let trimmed = amount.trim();
+    let value = incr_core::invoice::rules::parse_polish_amount(trimmed)
+        .filter(|_| trimmed.chars().any(|c| c.is_ascii_digit()))
+        .ok_or_else(|| JsValue::from_str(&format!("Invalid amount '{}'", amount)))?;
+    let value = if trimmed.starts_with('-') { -value } else { value };
+
+    Ok(incr_core::words::format_amount_in_words(value, currency))
+}
+
 /// Invoice extractor class for browser use.
 #[wasm_bindgen]
 pub struct InvoiceExtractor {
     parser: HybridInvoiceParser,
+    naming: FieldNaming,
+    cache: Option<JsCache>,
 }
 
 #[wasm_bindgen]
@@ -77,9 +108,28 @@ impl InvoiceExtractor {
     pub fn new() -> Self {
         Self {
             parser: HybridInvoiceParser::new(),
+            naming: FieldNaming::default(),
+            cache: None,
         }
     }
 
+    /// Cache extracted invoices of files, keyed by a hash of their bytes.
+    ///
+    /// `get(key)` returns the stored JSON string (or `undefined`) and
+    /// `set(key, json)` stores it; both may return promises, e.g. to use
+    /// IndexedDB. Keys include the library version, so results of older
+    /// versions are not reused. Used by `extract_file` and `process_zip`.
+    #[wasm_bindgen]
+    pub fn set_cache(&mut self, get: js_sys::Function, set: js_sys::Function) {
+        self.cache = Some(JsCache { get, set });
+    }
+
+    /// Stop using the extraction cache.
+    #[wasm_bindgen]
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
     /// Configure NIP validation.
     #[wasm_bindgen]
     pub fn set_validate_nip(&mut self, validate: bool) {
@@ -87,6 +137,14 @@ impl InvoiceExtractor {
             .with_nip_validation(validate);
     }
 
+    /// Set the field names of returned objects: `snake_case` (default),
+    /// `camel_case` or `legacy`.
+    #[wasm_bindgen]
+    pub fn set_field_naming(&mut self, naming: &str) -> Result<(), JsValue> {
+        self.naming = naming.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
     /// Extract invoice from text.
     #[wasm_bindgen]
     pub fn extract(&self, text: &str) -> Result<JsValue, JsValue> {
@@ -94,8 +152,33 @@ impl InvoiceExtractor {
             .parse(text)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        serde_wasm_bindgen::to_value(&result.invoice)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+        to_js(&result.invoice, self.naming)
+    }
+
+    /// Extract a single field from text, running only the extractors it
+    /// needs. `field` is a name such as `issuer_nip` or `total_gross`, or
+    /// a path such as `issuer.nip`. Returns the value as a string (dates as
+    /// `YYYY-MM-DD`), or `undefined` if the field was not found.
+    #[wasm_bindgen]
+    pub fn extract_field(&self, text: &str, field: &str) -> Result<Option<String>, JsValue> {
+        let field: FieldKind = field.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        Ok(self.parser.extract_field(text, field).map(|value| value.to_string()))
+    }
+
+    /// Extract invoice from text, calling `callback` with each progress event.
+    ///
+    /// The callback receives `{ stage, current, total, message }`.
+    #[wasm_bindgen]
+    pub fn extract_with_progress(
+        &self,
+        text: &str,
+        callback: &js_sys::Function,
+    ) -> Result<JsValue, JsValue> {
+        let result = self.parser
+            .parse_with_progress(text, &JsProgress(callback))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        to_js(&result.invoice, self.naming)
     }
 
     /// Get extraction result with metadata.
@@ -109,7 +192,7 @@ impl InvoiceExtractor {
         struct ExtractResult {
             invoice: Invoice,
             raw_text: String,
-            warnings: Vec<String>,
+            warnings: Vec<ValidationIssue>,
             processing_time_ms: u64,
         }
 
@@ -120,22 +203,321 @@ impl InvoiceExtractor {
             processing_time_ms: result.processing_time_ms,
         };
 
-        serde_wasm_bindgen::to_value(&output)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+        to_js(&output, self.naming)
+    }
+
+    /// Extract an invoice from a file (text, PDF or image).
+    ///
+    /// `bytes` is an `ArrayBuffer` or `Uint8Array`; the type is taken from
+    /// the extension of `name`. Scans are passed to the `ocr` callback as in
+    /// `process_zip`. Resolves to the invoice.
+    #[wasm_bindgen]
+    pub fn extract_file(
+        &self,
+        name: String,
+        bytes: &JsValue,
+        ocr: Option<js_sys::Function>,
+    ) -> js_sys::Promise {
+        let bytes = js_bytes(bytes).to_vec();
+        let processor = self.processor(ocr);
+        let naming = self.naming;
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let kind = DocumentKind::of(&name)
+                .ok_or_else(|| JsValue::from_str(&format!("unsupported file type: {}", name)))?;
+            let invoice = processor
+                .process(&name, kind, bytes)
+                .await
+                .map_err(|e| JsValue::from_str(&e))?;
+            to_js(&invoice, naming)
+        })
+    }
+
+    /// Extract invoices from every document in a ZIP archive.
+    ///
+    /// `archive` is an `ArrayBuffer` or `Uint8Array`. Entries are read and
+    /// processed one at a time, so memory use does not grow with the
+    /// archive. `on_document` is called for each document with
+    /// `{ name, invoice }` or `{ name, error }`.
+    ///
+    /// Text files and text PDFs are parsed directly. Scans (images and PDFs
+    /// without a text layer) are passed to the optional `ocr(name, bytes)`
+    /// callback, which returns their text or a promise of it; without it
+    /// they are reported as errors.
+    ///
+    /// Resolves to `{ processed, failed, skipped }`.
+    #[wasm_bindgen]
+    pub fn process_zip(
+        &self,
+        archive: &JsValue,
+        on_document: js_sys::Function,
+        ocr: Option<js_sys::Function>,
+    ) -> js_sys::Promise {
+        let archive = js_bytes(archive);
+        let processor = self.processor(ocr);
+        let naming = self.naming;
+
+        wasm_bindgen_futures::future_to_promise(async move {
+            let archive = ZipArchive::new(JsBytes(archive))
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+            let mut summary = ZipSummary::default();
+
+            for entry in archive.entries() {
+                if entry.is_dir() || is_hidden(entry) {
+                    continue;
+                }
+                let Some(kind) = DocumentKind::of(entry.file_name()) else {
+                    summary.skipped += 1;
+                    continue;
+                };
+
+                let result = match archive.read(entry) {
+                    Ok(bytes) => processor.process(&entry.name, kind, bytes).await,
+                    Err(e) => Err(e.to_string()),
+                };
+
+                let document = match result {
+                    Ok(invoice) => {
+                        summary.processed += 1;
+                        ZipDocument { name: &entry.name, invoice: Some(invoice), error: None }
+                    }
+                    Err(error) => {
+                        summary.failed += 1;
+                        ZipDocument { name: &entry.name, invoice: None, error: Some(error) }
+                    }
+                };
+
+                // Errors thrown by the callback must not abort the archive
+                let _ = on_document.call1(&JsValue::NULL, &to_js(&document, naming)?);
+            }
+
+            serde_wasm_bindgen::to_value(&summary).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    fn processor(&self, ocr: Option<js_sys::Function>) -> DocumentProcessor {
+        DocumentProcessor {
+            parser: self.parser.clone(),
+            cache: self.cache.clone(),
+            ocr,
+        }
+    }
+}
+
+/// Bytes of an `ArrayBuffer` or `Uint8Array`. A `Uint8Array` is used as is
+/// and wrapping an `ArrayBuffer` does not copy it.
+fn js_bytes(value: &JsValue) -> js_sys::Uint8Array {
+    match value.dyn_ref::<js_sys::Uint8Array>() {
+        Some(bytes) => bytes.clone(),
+        None => js_sys::Uint8Array::new(value),
+    }
+}
+
+/// Extracts invoices from files, through the cache if one is set.
+struct DocumentProcessor {
+    parser: HybridInvoiceParser,
+    cache: Option<JsCache>,
+    ocr: Option<js_sys::Function>,
+}
+
+impl DocumentProcessor {
+    async fn process(&self, name: &str, kind: DocumentKind, bytes: Vec<u8>) -> Result<Invoice, String> {
+        let cache = self.cache.as_ref().map(|cache| (cache, cache_key(&bytes)));
+        let cached = match &cache {
+            Some((cache, key)) => cache.get(key).await,
+            None => None,
+        };
+        if let Some(invoice) = cached {
+            return Ok(invoice);
+        }
+
+        let text = document_text(name, kind, bytes, self.ocr.as_ref()).await?;
+        let invoice = self.parser.parse(&text).map_err(|e| e.to_string())?.invoice;
+
+        if let Some((cache, key)) = &cache {
+            cache.set(key, &invoice).await;
+        }
+        Ok(invoice)
+    }
+}
+
+/// Cache key of a file: library version and content hash.
+fn cache_key(bytes: &[u8]) -> String {
+    format!("incr-{}:{}", env!("CARGO_PKG_VERSION"), sha256_hex(bytes))
+}
+
+/// Extraction cache stored through JavaScript callbacks.
+///
+/// A failing or malformed cache never fails extraction: lookups fall back
+/// to processing the file and failed writes are ignored.
+#[derive(Clone)]
+struct JsCache {
+    get: js_sys::Function,
+    set: js_sys::Function,
+}
+
+impl JsCache {
+    async fn get(&self, key: &str) -> Option<Invoice> {
+        let value = self.get.call1(&JsValue::NULL, &JsValue::from_str(key)).ok()?;
+        let json = resolve(value).await.ok()?.as_string()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    async fn set(&self, key: &str, invoice: &Invoice) {
+        let Ok(json) = serde_json::to_string(invoice) else {
+            return;
+        };
+        if let Ok(value) = self.set.call2(&JsValue::NULL, &JsValue::from_str(key), &JsValue::from_str(&json)) {
+            let _ = resolve(value).await;
+        }
     }
 }
 
+/// Await `value` if it is a promise.
+async fn resolve(value: JsValue) -> Result<JsValue, JsValue> {
+    match value.dyn_into::<js_sys::Promise>() {
+        Ok(promise) => wasm_bindgen_futures::JsFuture::from(promise).await,
+        Err(value) => Ok(value),
+    }
+}
+
+/// ZIP archive held in JavaScript memory; entries are copied into WASM
+/// memory only when read.
+struct JsBytes(js_sys::Uint8Array);
+
+impl ZipSource for JsBytes {
+    fn size(&self) -> u64 {
+        self.0.length() as u64
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, ArchiveError> {
+        let end = offset + len as u64;
+        if end > self.size() {
+            return Err(ArchiveError::Read(format!("{} bytes at {} past the end", len, offset)));
+        }
+        Ok(self.0.subarray(offset as u32, end as u32).to_vec())
+    }
+}
+
+/// Files `extract_file` and `process_zip` can handle.
+#[derive(Clone, Copy)]
+enum DocumentKind {
+    Text,
+    Pdf,
+    Image,
+}
+
+impl DocumentKind {
+    /// Kind of a file, from the extension of its name.
+    fn of(file_name: &str) -> Option<Self> {
+        let name = file_name.to_lowercase();
+        let extension = name.rsplit_once('.').map(|(_, ext)| ext)?;
+        match extension {
+            "txt" => Some(Self::Text),
+            "pdf" => Some(Self::Pdf),
+            "png" | "jpg" | "jpeg" | "tif" | "tiff" | "bmp" | "webp" => Some(Self::Image),
+            _ => None,
+        }
+    }
+}
+
+/// macOS resource forks and hidden files.
+fn is_hidden(entry: &ZipEntry) -> bool {
+    entry.name.starts_with("__MACOSX/") || entry.file_name().starts_with('.')
+}
+
+/// Text of a document, from its text layer or the `ocr` callback.
+async fn document_text(
+    name: &str,
+    kind: DocumentKind,
+    bytes: Vec<u8>,
+    ocr: Option<&js_sys::Function>,
+) -> Result<String, String> {
+    match kind {
+        DocumentKind::Text => return Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        DocumentKind::Pdf => {
+            let mut extractor = PdfExtractor::new();
+            extractor.load(&bytes).map_err(|e| e.to_string())?;
+            let text = extractor.extract_text().unwrap_or_default();
+            // Same threshold as PDF analysis: shorter text means a scan
+            if text.trim().len() > 50 {
+                return Ok(text);
+            }
+        }
+        DocumentKind::Image => {}
+    }
+
+    let ocr = ocr.ok_or("scanned document needs OCR, pass an ocr callback")?;
+    let text = ocr
+        .call2(&JsValue::NULL, &JsValue::from_str(name), &js_sys::Uint8Array::from(bytes.as_slice()))
+        .map_err(js_error)?;
+    let text = resolve(text).await.map_err(js_error)?;
+
+    text.as_string().ok_or_else(|| "ocr callback did not return text".to_string())
+}
+
+fn js_error(error: JsValue) -> String {
+    error
+        .as_string()
+        .or_else(|| error.dyn_ref::<js_sys::Error>().map(|e| String::from(e.message())))
+        .unwrap_or_else(|| "ocr callback failed".to_string())
+}
+
+/// Result for one document of a ZIP archive.
+#[derive(Serialize)]
+struct ZipDocument<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invoice: Option<Invoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Document counts of a processed ZIP archive.
+#[derive(Default, Serialize)]
+struct ZipSummary {
+    processed: u32,
+    failed: u32,
+    skipped: u32,
+}
+
+/// Convert a value to a JavaScript value with the given field names.
+fn to_js<T: Serialize>(value: &T, naming: FieldNaming) -> Result<JsValue, JsValue> {
+    let result = match naming {
+        FieldNaming::SnakeCase => serde_wasm_bindgen::to_value(value),
+        // Renamed values go through JSON maps, which must become plain objects
+        _ => naming
+            .apply(value)
+            .serialize(&serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true)),
+    };
+
+    result.map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 impl Default for InvoiceExtractor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Forwards progress events to a JavaScript callback.
+struct JsProgress<'a>(&'a js_sys::Function);
+
+impl ProgressSink for JsProgress<'_> {
+    fn report(&self, event: ProgressEvent) {
+        if let Ok(value) = serde_wasm_bindgen::to_value(&event) {
+            // Errors thrown by the callback must not abort extraction
+            let _ = self.0.call1(&JsValue::NULL, &value);
+        }
+    }
+}
+
 /// OCR result from browser-side processing.
 #[wasm_bindgen]
 pub struct OcrResultJs {
     boxes: Vec<TextBoxJs>,
     text: String,
+    layout: Option<LayoutInfo>,
 }
 
 #[wasm_bindgen]
@@ -145,6 +527,7 @@ impl OcrResultJs {
         Self {
             boxes: Vec::new(),
             text: String::new(),
+            layout: None,
         }
     }
 
@@ -182,6 +565,24 @@ impl OcrResultJs {
         }
     }
 
+    /// Text boxes as `[{ text, bbox: [x1, y1, ..., x4, y4], confidence }]`.
+    #[wasm_bindgen(getter)]
+    pub fn boxes(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.boxes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Layout regions as `{ tables, text_regions, figures }`, each a list of
+    /// `{ region_type, bbox: [x1, y1, x2, y2], confidence }`; `undefined`
+    /// without a layout model.
+    #[wasm_bindgen(getter)]
+    pub fn layout(&self) -> Result<JsValue, JsValue> {
+        match &self.layout {
+            Some(layout) => serde_wasm_bindgen::to_value(layout)
+                .map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+
     /// Extract invoice from this OCR result.
     #[wasm_bindgen]
     pub fn extract_invoice(&self) -> Result<JsValue, JsValue> {
@@ -196,12 +597,376 @@ impl Default for OcrResultJs {
     }
 }
 
+impl From<OcrResult> for OcrResultJs {
+    fn from(result: OcrResult) -> Self {
+        let boxes = result
+            .boxes
+            .into_iter()
+            .map(|b| TextBoxJs {
+                text: b.text,
+                bbox: b.bbox,
+                confidence: b.recognition_score,
+            })
+            .collect();
+        Self { boxes, text: result.text, layout: result.layout }
+    }
+}
+
+#[derive(Serialize)]
 struct TextBoxJs {
     text: String,
     bbox: [f32; 8],
     confidence: f32,
 }
 
+/// OCR engine running the PaddleOCR models in WASM.
+///
+/// Models are passed as bytes (e.g. fetched `.onnx` files), so browsers need
+/// no separate OCR library. Scans are padded to a square before detection:
+/// the models are planned for fixed input shapes, and this keeps the
+/// detection input the same size for every scan.
+#[wasm_bindgen]
+pub struct WasmOcrEngine {
+    engine: OcrEngine<TractBackend>,
+    detection_side: u32,
+}
+
+#[wasm_bindgen]
+impl WasmOcrEngine {
+    /// Load the detection and recognition models.
+    ///
+    /// `dictionary` is the recognition model's character list, one per line
+    /// (default: the built-in Latin dictionary). `classifier` is the optional
+    /// angle classification model, which turns upside-down text around.
+    /// Skewed and sideways pages are turned upright either way. `layout` is
+    /// the optional PP-Structure layout model; its regions are reported in
+    /// the result's `layout`. `style` (printed/handwritten classification)
+    /// and `handwriting` (a recognition model using the same dictionary)
+    /// are optional but go together: text lines classified as handwritten
+    /// are read by the handwriting model and marked `handwritten`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        detection: &[u8],
+        recognition: &[u8],
+        dictionary: Option<String>,
+        classifier: Option<Vec<u8>>,
+        layout: Option<Vec<u8>>,
+        style: Option<Vec<u8>>,
+        handwriting: Option<Vec<u8>>,
+    ) -> Result<WasmOcrEngine, JsValue> {
+        let targets = ImagePreprocessor::new().targets();
+        let side = targets.detection_max_side as usize;
+        let load = |bytes: &[u8], shape: &[usize], model: &str| {
+            TractBackend::from_bytes_with_shape(bytes, shape)
+                .map_err(|e| JsValue::from_str(&format!("{} model: {}", model, e)))
+        };
+
+        let detector = TextDetector::new(load(detection, &[1, 3, side, side], "detection")?);
+        let dictionary = match dictionary {
+            Some(dictionary) => TextRecognizer::<TractBackend>::parse_dictionary(&dictionary),
+            None => TextRecognizer::<TractBackend>::default_latin_dictionary(),
+        };
+        let recognition_shape = [
+            1,
+            3,
+            targets.recognition_height as usize,
+            targets.recognition_max_width as usize,
+        ];
+        let recognizer = TextRecognizer::new(
+            load(recognition, &recognition_shape, "recognition")?,
+            dictionary.clone(),
+        );
+
+        let mut builder = OcrEngine::builder()
+            .with_detector(detector)
+            .with_recognizer(recognizer)
+            .with_config(OcrConfig::default());
+        if let Some(classifier) = classifier {
+            let shape = [
+                1,
+                3,
+                targets.classification_height as usize,
+                targets.classification_width as usize,
+            ];
+            builder = builder.with_classifier(AngleClassifier::new(load(
+                &classifier,
+                &shape,
+                "classification",
+            )?));
+        }
+
+        match (style, handwriting) {
+            (Some(style), Some(handwriting)) => {
+                // The style classifier takes the angle classifier's input
+                let style_shape = [
+                    1,
+                    3,
+                    targets.classification_height as usize,
+                    targets.classification_width as usize,
+                ];
+                let style = load(&style, &style_shape, "style classification")?;
+                let handwriting = load(&handwriting, &recognition_shape, "handwriting")?;
+                builder = builder
+                    .with_style_classifier(StyleClassifier::new(style))
+                    .with_handwriting_recognizer(TextRecognizer::new(handwriting, dictionary));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(JsValue::from_str(
+                    "the style and handwriting models must be given together",
+                ))
+            }
+        }
+
+        if let Some(layout) = layout {
+            // The image at the layout detector's default 800x608, and the
+            // scale factor of the resize
+            let shapes: [(&str, &[usize]); 2] =
+                [("image", &[1, 3, 608, 800]), ("scale_factor", &[1, 2])];
+            let backend = TractBackend::from_bytes_with_shapes(&layout, &shapes)
+                .map_err(|e| JsValue::from_str(&format!("layout model: {}", e)))?;
+            builder = builder.with_layout_detector(LayoutDetector::new(backend));
+        }
+
+        Ok(Self {
+            engine: builder.build(),
+            detection_side: targets.detection_max_side,
+        })
+    }
+
+    /// Run every model once, so the first scan is not slowed down by
+    /// one-time setup; e.g. call it while the user picks a file.
+    #[wasm_bindgen]
+    pub fn warmup(&self) -> Result<(), JsValue> {
+        self.engine.warmup().map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Switch to another recognition model and its dictionary (one character
+    /// per line), e.g. the Cyrillic model for Ukrainian invoices. Without a
+    /// model only the dictionary is replaced.
+    #[wasm_bindgen]
+    pub fn set_recognition_model(
+        &mut self,
+        recognition: Option<Vec<u8>>,
+        dictionary: String,
+    ) -> Result<(), JsValue> {
+        let dictionary = TextRecognizer::<TractBackend>::parse_dictionary(&dictionary);
+        match recognition {
+            Some(recognition) => {
+                let targets = ImagePreprocessor::new().targets();
+                let shape = [
+                    1,
+                    3,
+                    targets.recognition_height as usize,
+                    targets.recognition_max_width as usize,
+                ];
+                let backend = TractBackend::from_bytes_with_shape(&recognition, &shape)
+                    .map_err(|e| JsValue::from_str(&format!("recognition model: {}", e)))?;
+                self.engine.set_recognizer(TextRecognizer::new(backend, dictionary));
+            }
+            None => self.engine.set_dictionary(dictionary),
+        }
+        Ok(())
+    }
+
+    /// Load the table structure model (SLANet) and, optionally, the table
+    /// classifier, to read the line items of the tables the layout model
+    /// finds cell by cell. Tables classified as wired are reconstructed from
+    /// their ruling lines instead where a grid can be found.
+    #[wasm_bindgen]
+    pub fn set_table_models(
+        &mut self,
+        structure: &[u8],
+        classifier: Option<Vec<u8>>,
+    ) -> Result<(), JsValue> {
+        // SLANet at its default 488x488, the classifier at 224x224
+        let load = |bytes: &[u8], side: usize, model: &str| {
+            TractBackend::from_bytes_with_shape(bytes, &[1, 3, side, side])
+                .map_err(|e| JsValue::from_str(&format!("{} model: {}", model, e)))
+        };
+        let mut table_recognizer = TableRecognizer::new(load(structure, 488, "table structure")?);
+        if let Some(classifier) = classifier {
+            let classifier = load(&classifier, 224, "table classification")?;
+            table_recognizer = table_recognizer.with_classifier(TableClassifier::new(classifier));
+        }
+        self.engine.set_table_recognizer(table_recognizer);
+        Ok(())
+    }
+
+    /// Recognize the text in an `ImageData`, `OffscreenCanvas` or `ImageBitmap`.
+    #[wasm_bindgen]
+    pub fn recognize(&self, source: &JsValue) -> Result<OcrResultJs, JsValue> {
+        let (rgba, width, height) = read_pixels(source)?;
+        self.recognize_rgba(rgba, width, height)
+    }
+
+    /// Recognize the text in a raw RGBA buffer of `width * height * 4` bytes.
+    #[wasm_bindgen]
+    pub fn recognize_rgba(
+        &self,
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> Result<OcrResultJs, JsValue> {
+        let image = RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| JsValue::from_str("RGBA buffer does not match width * height * 4"))?;
+        let result = self
+            .engine
+            .process(&self.pad_square(image))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(result.into())
+    }
+
+    /// Recognize the text in an image, calling `callback` with each progress
+    /// event as text regions are detected and recognized.
+    ///
+    /// The callback receives `{ stage, current, total, message }`.
+    #[wasm_bindgen]
+    pub fn recognize_with_progress(
+        &self,
+        source: &JsValue,
+        callback: &js_sys::Function,
+    ) -> Result<OcrResultJs, JsValue> {
+        let (rgba, width, height) = read_pixels(source)?;
+        let image = RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| JsValue::from_str("image pixels do not match its size"))?;
+        let result = self
+            .engine
+            .process_with_progress(&self.pad_square(image), &JsProgress(callback))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(result.into())
+    }
+}
+
+impl WasmOcrEngine {
+    /// Pad an image with white to a square of at least the detection size.
+    /// Padding is added right and bottom, so box coordinates stay the same.
+    fn pad_square(&self, image: RgbaImage) -> DynamicImage {
+        let side = image.width().max(image.height()).max(self.detection_side);
+        if image.dimensions() == (side, side) {
+            return DynamicImage::ImageRgba8(image);
+        }
+
+        let mut square = RgbaImage::from_pixel(side, side, Rgba([255, 255, 255, 255]));
+        imageops::replace(&mut square, &image, 0, 0);
+        DynamicImage::ImageRgba8(square)
+    }
+}
+
+/// Model input sizes for resizing images on a canvas before packing:
+/// `{ detection_max_side, detection_align, recognition_height,
+/// recognition_max_width, classification_width, classification_height }`.
+#[wasm_bindgen]
+pub fn preprocessing_targets() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&ImagePreprocessor::new().targets())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Size `[width, height]` to draw an image at for text detection.
+#[wasm_bindgen]
+pub fn detection_size(width: u32, height: u32) -> Vec<u32> {
+    let (width, height) = ImagePreprocessor::new().detection_size(width, height);
+    vec![width, height]
+}
+
+/// Size `[width, height]` to draw a text crop at for recognition.
+#[wasm_bindgen]
+pub fn recognition_size(width: u32, height: u32) -> Vec<u32> {
+    let (width, height) = ImagePreprocessor::new().recognition_size(width, height);
+    vec![width, height]
+}
+
+/// Pack an image already resized to `detection_size` into a detection
+/// tensor.
+///
+/// `source` is an `ImageData`, `OffscreenCanvas` or `ImageBitmap`. Resizing
+/// on the (GPU-accelerated) canvas is much faster than in WASM, which then
+/// only normalizes and pads the pixels.
+#[wasm_bindgen]
+pub fn pack_detection_input(source: &JsValue) -> Result<Tensor, JsValue> {
+    let (rgba, width, height) = read_pixels(source)?;
+    let tensor = ImagePreprocessor::new()
+        .pack_for_detection(&rgba, width, height)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let shape = tensor.shape().iter().map(|&d| d as u32).collect();
+    Ok(Tensor { data: tensor.into_raw_vec_and_offset().0, shape })
+}
+
+/// Pack a text crop already resized to `recognition_size` into a
+/// recognition tensor.
+#[wasm_bindgen]
+pub fn pack_recognition_input(source: &JsValue) -> Result<Tensor, JsValue> {
+    let (rgba, width, height) = read_pixels(source)?;
+    let tensor = ImagePreprocessor::new()
+        .pack_for_recognition(&rgba, width, height)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let shape = tensor.shape().iter().map(|&d| d as u32).collect();
+    Ok(Tensor { data: tensor.into_raw_vec_and_offset().0, shape })
+}
+
+/// Pack a text crop already resized to the classification input size into
+/// an angle classification tensor.
+#[wasm_bindgen]
+pub fn pack_classification_input(source: &JsValue) -> Result<Tensor, JsValue> {
+    let (rgba, width, height) = read_pixels(source)?;
+    let tensor = ImagePreprocessor::new()
+        .pack_for_classification(&rgba, width, height)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let shape = tensor.shape().iter().map(|&d| d as u32).collect();
+    Ok(Tensor { data: tensor.into_raw_vec_and_offset().0, shape })
+}
+
+/// Read the RGBA pixels of an `ImageData`, `OffscreenCanvas` or `ImageBitmap`.
+fn read_pixels(source: &JsValue) -> Result<(Vec<u8>, u32, u32), JsValue> {
+    let image_data = if let Some(image_data) = source.dyn_ref::<ImageData>() {
+        image_data.clone()
+    } else if let Some(canvas) = source.dyn_ref::<OffscreenCanvas>() {
+        canvas_pixels(canvas)?
+    } else if let Some(bitmap) = source.dyn_ref::<ImageBitmap>() {
+        let canvas = OffscreenCanvas::new(bitmap.width(), bitmap.height())?;
+        context_2d(&canvas)?.draw_image_with_image_bitmap(bitmap, 0.0, 0.0)?;
+        canvas_pixels(&canvas)?
+    } else {
+        return Err(JsValue::from_str("expected ImageData, OffscreenCanvas or ImageBitmap"));
+    };
+
+    Ok((image_data.data().0, image_data.width(), image_data.height()))
+}
+
+fn canvas_pixels(canvas: &OffscreenCanvas) -> Result<ImageData, JsValue> {
+    context_2d(canvas)?.get_image_data(0.0, 0.0, canvas.width() as f64, canvas.height() as f64)
+}
+
+fn context_2d(canvas: &OffscreenCanvas) -> Result<OffscreenCanvasRenderingContext2d, JsValue> {
+    canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("canvas has no 2d context"))?
+        .dyn_into()
+        .map_err(JsValue::from)
+}
+
+/// Model input tensor in NCHW layout.
+#[wasm_bindgen]
+pub struct Tensor {
+    data: Vec<f32>,
+    shape: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Tensor {
+    /// Tensor values as a `Float32Array`.
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<f32> {
+        self.data.clone()
+    }
+
+    /// Tensor shape, `[1, 3, height, width]`.
+    #[wasm_bindgen(getter)]
+    pub fn shape(&self) -> Vec<u32> {
+        self.shape.clone()
+    }
+}
+
 /// Utilities for working with Polish invoice data.
 #[wasm_bindgen]
 pub struct PolishInvoiceUtils;